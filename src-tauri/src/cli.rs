@@ -0,0 +1,369 @@
+//! Command-line arguments for scripted launches, e.g.
+//! `bloatsql --connection "prod-replica" --file query.sql`.
+//!
+//! The app connects to the stored connection and reads the file, then leaves a
+//! `LaunchRequest` for the frontend to pick up with `take_launch_request`, which loads the
+//! SQL into the editor and runs it.
+//!
+//! `bloatsql export` runs an export without opening a window, for cron jobs and CI. A store
+//! protected by a master password is unlocked with `BLOATSQL_MASTER_PASSWORD`.
+
+use crate::commands::{self, Connection, ExportOptions};
+use crate::db::{create_connection, PoolSize, RdsIamAuth};
+use crate::storage::{data_dir, ConnectionSort, ConnectionsStore, ExportProfileStore};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+/// Emitted to the main window, which launches connect, once a launch request is ready to be
+/// taken.
+const LAUNCH_REQUEST_EVENT: &str = "launch-request";
+
+/// Identifier from `tauri.conf.json`, which names the OS app-data dir.
+const APP_IDENTIFIER: &str = "com.bloatsql.app";
+
+/// Master password that unlocks the connection store for `bloatsql export`.
+const MASTER_PASSWORD_ENV: &str = "BLOATSQL_MASTER_PASSWORD";
+
+const EXPORT_USAGE: &str =
+    "Usage: bloatsql export --connection <name or id> --profile <name> [--output <file>]";
+
+/// Collects the values of `--flag value` and `--flag=value` arguments, keyed by the first
+/// spelling of each flag in `known`. Other arguments are skipped.
+fn parse_flags(
+    args: impl IntoIterator<Item = String>,
+    known: &[&[&'static str]],
+) -> Result<HashMap<&'static str, String>, String> {
+    let mut values = HashMap::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg, None),
+        };
+        let Some(spellings) = known
+            .iter()
+            .find(|spellings| spellings.contains(&flag.as_str()))
+        else {
+            continue;
+        };
+        let value = inline_value
+            .or_else(|| args.next())
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| format!("Missing value for {}", flag))?;
+        values.insert(spellings[0], value);
+    }
+    Ok(values)
+}
+
+/// Options given on the command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchArgs {
+    /// Name or id of a stored connection to connect to.
+    pub connection: Option<String>,
+    /// SQL file to open.
+    pub file: Option<PathBuf>,
+}
+
+impl LaunchArgs {
+    /// Parses the arguments after the program name. Unknown arguments are ignored, since
+    /// the OS passes deep links as positional arguments.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut values = parse_flags(args, &[&["--connection", "-c"], &["--file", "-f"]])?;
+        Ok(LaunchArgs {
+            connection: values.remove("--connection"),
+            file: values.remove("--file").map(PathBuf::from),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connection.is_none() && self.file.is_none()
+    }
+}
+
+/// What a launch from the command line asks the frontend to do.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LaunchRequest {
+    /// Stored connection that was connected to.
+    pub connection_id: Option<String>,
+    pub file_path: Option<String>,
+    /// Contents of the file, to load into the editor and execute.
+    pub sql: Option<String>,
+    /// Problems with the arguments, the connection or the file.
+    pub errors: Vec<String>,
+}
+
+/// Launch request waiting for the frontend.
+pub type PendingLaunch = Arc<Mutex<Option<LaunchRequest>>>;
+
+/// Acts on the command line of this launch, or of a second launch forwarded to the running
+/// app. Relative file paths are resolved against `cwd`.
+pub fn handle(app: &AppHandle, args: Vec<String>, cwd: PathBuf) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let request = match LaunchArgs::parse(args) {
+            Ok(args) if args.is_empty() => return,
+            Ok(args) => launch(&app, args, &cwd).await,
+            Err(e) => LaunchRequest {
+                errors: vec![e],
+                ..Default::default()
+            },
+        };
+        for error in &request.errors {
+            warn!("Launch: {}", error);
+        }
+
+        *app.state::<PendingLaunch>().lock().await = Some(request);
+        let _ = app.emit_to("main", LAUNCH_REQUEST_EVENT, ());
+    });
+}
+
+async fn launch(app: &AppHandle, args: LaunchArgs, cwd: &Path) -> LaunchRequest {
+    let mut request = LaunchRequest::default();
+
+    if let Some(path) = args.file {
+        let path = cwd.join(path);
+        match tokio::fs::read_to_string(&path).await {
+            Ok(sql) => {
+                request.file_path = Some(path.display().to_string());
+                request.sql = Some(sql);
+            }
+            Err(e) => request
+                .errors
+                .push(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    if let Some(name) = args.connection {
+        match connect(app, &name).await {
+            Ok(id) => request.connection_id = Some(id),
+            Err(e) => request.errors.push(e),
+        }
+    }
+
+    request
+}
+
+/// Returns the stored connection named `name`, or with id `name`.
+fn find_connection(store: &ConnectionsStore, name: &str) -> Result<Connection, String> {
+    let connections = store
+        .get_all_connections(ConnectionSort::default())
+        .map_err(|e| e.to_string())?;
+    connections
+        .iter()
+        .find(|conn| conn.name == name)
+        .or_else(|| connections.iter().find(|conn| conn.id == name))
+        .cloned()
+        .map(Connection::from)
+        .ok_or_else(|| format!("No stored connection named '{}'", name))
+}
+
+/// Connects to the stored connection named `name`, or with id `name`, and returns its id.
+async fn connect(app: &AppHandle, name: &str) -> Result<String, String> {
+    let store = app.state::<Arc<ConnectionsStore>>();
+    let conn = find_connection(&store, name)?;
+
+    let window = app
+        .get_webview_window("main")
+        .ok_or("The main window is closed")?;
+
+    let id = conn.id.clone();
+    commands::connect_to_database(
+        conn,
+        app.clone(),
+        store,
+        window,
+        app.state(),
+        app.state(),
+        app.state(),
+    )
+    .await?;
+    debug!("Connected to {} from the command line", name);
+    Ok(id)
+}
+
+/// Options of `bloatsql export`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ExportArgs {
+    connection: String,
+    profile: String,
+    /// Overrides the file named in the profile.
+    output: Option<PathBuf>,
+}
+
+impl ExportArgs {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut values = parse_flags(
+            args,
+            &[
+                &["--connection", "-c"],
+                &["--profile", "-p"],
+                &["--output", "-o"],
+            ],
+        )?;
+        Ok(ExportArgs {
+            connection: values
+                .remove("--connection")
+                .ok_or("Missing --connection")?,
+            profile: values.remove("--profile").ok_or("Missing --profile")?,
+            output: values.remove("--output").map(PathBuf::from),
+        })
+    }
+}
+
+/// Runs a subcommand given on the command line without opening a window, and returns its
+/// exit code; returns `None` when the app should start normally.
+///
+/// Release builds on Windows have no console, so output is only seen when redirected.
+pub fn run_subcommand() -> Option<i32> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("export") => Some(run_export(args.collect())),
+        _ => None,
+    }
+}
+
+/// Runs `bloatsql export`: 0 on success, 1 if the export failed, 2 for invalid arguments.
+fn run_export(args: Vec<String>) -> i32 {
+    let args = match ExportArgs::parse(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, EXPORT_USAGE);
+            return 2;
+        }
+    };
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start: {}", e);
+            return 1;
+        }
+    };
+
+    match runtime.block_on(export(args)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Export failed: {}", e);
+            1
+        }
+    }
+}
+
+async fn export(args: ExportArgs) -> Result<(), String> {
+    let app_data_dir = dirs::data_dir()
+        .ok_or("Could not find the app data directory")?
+        .join(APP_IDENTIFIER);
+    let data_dir = data_dir::resolve(app_data_dir);
+    let db_path = data_dir.path.join("connections.db");
+    let store =
+        ConnectionsStore::new(db_path.clone(), data_dir.source).map_err(|e| e.to_string())?;
+    if store.is_locked() {
+        let password = std::env::var(MASTER_PASSWORD_ENV).map_err(|_| {
+            format!(
+                "The connection store is locked; set {} to unlock it",
+                MASTER_PASSWORD_ENV
+            )
+        })?;
+        if !store.unlock(&password).map_err(|e| e.to_string())? {
+            return Err(format!("Wrong master password in {}", MASTER_PASSWORD_ENV));
+        }
+    }
+    let profiles = ExportProfileStore::new(db_path).map_err(|e| e.to_string())?;
+
+    let mut options: ExportOptions = profiles
+        .get(&args.profile)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No export profile named '{}'", args.profile))?;
+    if let Some(output) = args.output {
+        let file_name = output
+            .file_name()
+            .ok_or_else(|| format!("Invalid output file: {}", output.display()))?;
+        options.file_name = file_name.to_string_lossy().into_owned();
+        options.output_path = output
+            .parent()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_default();
+    }
+    let conn = find_connection(&store, &args.connection)?;
+
+    println!("Connecting to {}...", conn.name);
+    let db_conn = create_connection(
+        &conn.db_type,
+        &conn.host,
+        conn.port as u16,
+        &conn.username,
+        &conn.password,
+        &conn.database,
+        &conn.ssl_mode,
+        conn.ssl_ca_cert.as_deref(),
+        PoolSize::new(conn.pool_min_size, conn.pool_max_size),
+        conn.preferences.query_timeout_secs.map(Duration::from_secs),
+        conn.rds_iam
+            .clone()
+            .map(|iam| RdsIamAuth::new(iam.profile, iam.region)),
+    )
+    .await
+    .map_err(|e| e.message)?;
+    if let Some(schema) = conn.preferences.default_schema.as_deref() {
+        db_conn
+            .set_default_schema(schema)
+            .await
+            .map_err(|e| e.message)?;
+    }
+
+    println!("Exporting with profile '{}'...", args.profile);
+    let result = commands::write_export(db_conn.as_ref(), &options).await;
+    if let Err(e) = db_conn.disconnect().await {
+        warn!("Failed to disconnect: {}", e.message);
+    }
+    println!("Wrote {}", result?.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_launch_args() {
+        let parsed =
+            LaunchArgs::parse(args(&["--connection", "prod-replica", "--file=query.sql"])).unwrap();
+        assert_eq!(parsed.connection.as_deref(), Some("prod-replica"));
+        assert_eq!(parsed.file, Some(PathBuf::from("query.sql")));
+
+        let parsed = LaunchArgs::parse(args(&["bloatsql://query?id=1", "-c", "local"])).unwrap();
+        assert_eq!(parsed.connection.as_deref(), Some("local"));
+        assert!(parsed.file.is_none());
+
+        assert!(LaunchArgs::parse(args(&[])).unwrap().is_empty());
+        assert!(LaunchArgs::parse(args(&["--file"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_export_args() {
+        let parsed = ExportArgs::parse(args(&[
+            "-c",
+            "prod",
+            "--profile=nightly",
+            "-o",
+            "/tmp/prod.sql",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.connection, "prod");
+        assert_eq!(parsed.profile, "nightly");
+        assert_eq!(parsed.output, Some(PathBuf::from("/tmp/prod.sql")));
+
+        assert!(ExportArgs::parse(args(&["--connection", "prod"])).is_err());
+    }
+}
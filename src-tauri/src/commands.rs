@@ -1,10 +1,46 @@
-use crate::db::{create_connection, DatabaseConnection, TableColumn, TableRelationship};
-use crate::storage::{ConnectionsStore, StoredConnection};
-use serde::{Deserialize, Serialize};
+use crate::cli::{LaunchRequest, PendingLaunch};
+use crate::db::{
+    aggregation::{Aggregate, Filter, TimeBucket},
+    bulk_insert,
+    connection::{LIVENESS_TIMEOUT, SCRATCH_SCHEMA},
+    create_connection, diagnostics, driver_info,
+    sql_dialect::SqlDialect,
+    statement, time_travel, ActiveTransaction, AggregateRequest, ChartData, ColumnMetadata,
+    ColumnStats, ConnectionDiagnosis, DatabaseConnection, DbResult, DbUser, DeadlockReport,
+    DriverInfo, HealthSnapshot, IndexSuggestion, KeyPage, KeyValue, PasteError, PoolSize,
+    PrivilegeGrant, QueryStatistic, QueryTiming, RdsIamAuth, ResultCursor, RlsStatus,
+    RoutineResult, RowChange, RowChunk, SampleMethod, SessionRoles, SessionVariable, TableBloat,
+    TableColumn, TableOperation, TableOptions, TableRef, TableRelationship, TextSearchRequest,
+    TriggerSpec,
+};
+use crate::debug_bundle::DebugBundle;
+use crate::deep_link;
+use crate::export_manifest::{self, ManifestVerification};
+use crate::ipc::{self, ResultFormat};
+use crate::logging;
+use crate::notify::{self, Notifier};
+use crate::redact;
+use crate::results::{CursorStore, QueryCache, ResultStore, DEFAULT_MEMORY_BUDGET};
+use crate::storage::{
+    audit_log, cache_kinds, data_dir, query_documents, AppSettings, AuditEntry, AuditExportFormat,
+    AuditLog, AuditLogFilter, ConnectionEnvironment, ConnectionGroup, ConnectionPreferences,
+    ConnectionSort, ConnectionSync, ConnectionsStore, DataDir, DocumentRevision,
+    ExportProfileStore, QueryDocument, QueryDocumentStore, QueryLibrary, RdsIamConfig,
+    RecoveryStore, SavedQuery, SchemaCache, SettingsStore, SlowQuery, SlowQueryLog, SshAuthMethod,
+    SshConfig, StoredConnection,
+};
+use chrono::NaiveDateTime;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tauri::{Manager, WebviewWindow};
+use std::time::{Duration, Instant};
+use tauri::ipc::{InvokeResponseBody, Response};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
 use tokio::sync::Mutex;
-use tracing::debug;
+use tracing::{debug, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Connection {
@@ -17,6 +53,28 @@ pub struct Connection {
     pub password: String,
     pub database: String,
     pub ssl_mode: String,
+    #[serde(default)]
+    pub ssl_ca_cert: Option<String>,
+    #[serde(default)]
+    pub pool_min_size: Option<u32>,
+    #[serde(default)]
+    pub pool_max_size: Option<u32>,
+    #[serde(default)]
+    pub group_id: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+    #[serde(default)]
+    pub use_count: i64,
+    #[serde(default)]
+    pub preferences: ConnectionPreferences,
+    #[serde(default)]
+    pub ssh: Option<SshConfig>,
+    #[serde(default)]
+    pub rds_iam: Option<RdsIamConfig>,
+    #[serde(default)]
+    pub deleted_at: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +84,14 @@ pub struct QueryResult {
     pub row_count: usize,
     pub execution_time: u128,
     pub truncated: bool,
+    pub affected_rows: Option<u64>,
+    #[serde(default)]
+    pub timing: QueryTiming,
+    #[serde(default)]
+    pub column_metadata: Vec<ColumnMetadata>,
+    /// Whether the result was served from the `QueryCache`.
+    #[serde(default)]
+    pub cached: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,14 +99,21 @@ pub struct ExportOptions {
     pub include_drop: bool,
     pub include_create: bool,
     pub data_mode: String,
-    pub selected_tables: Vec<String>,
+    pub selected_tables: Vec<TableRef>,
     pub output_path: String,
     pub file_name: String,
     pub max_insert_size: usize,
+    /// Also writes a manifest with table row counts, the server version and a checksum of
+    /// the dump, see `export_manifest`.
+    #[serde(default)]
+    pub write_manifest: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateCellRequest {
+    /// Schema of the table; the session's default schema when omitted.
+    #[serde(default)]
+    pub schema: Option<String>,
     pub table_name: String,
     pub column_name: String,
     pub new_value: Option<String>,
@@ -48,6 +121,31 @@ pub struct UpdateCellRequest {
     pub primary_key_value: String,
 }
 
+/// Rows pasted from a spreadsheet or CSV file, to insert into a table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasteRowsRequest {
+    /// Schema of the table; the session's default schema when omitted.
+    #[serde(default)]
+    pub schema: Option<String>,
+    pub table_name: String,
+    /// Tab- or comma-separated rows.
+    pub text: String,
+    /// Whether the first row names the columns.
+    #[serde(default)]
+    pub has_header: bool,
+    /// Target column for each pasted column, `None` to skip it; overrides the header.
+    /// Without either, pasted columns follow the table's column order.
+    #[serde(default)]
+    pub columns: Option<Vec<Option<String>>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PasteRowsResult {
+    pub inserted: u64,
+    /// Values that don't fit their columns; nothing is inserted when there are any.
+    pub errors: Vec<PasteError>,
+}
+
 impl From<crate::db::QueryResult> for QueryResult {
     fn from(result: crate::db::QueryResult) -> Self {
         QueryResult {
@@ -56,58 +154,44 @@ impl From<crate::db::QueryResult> for QueryResult {
             row_count: result.row_count,
             execution_time: result.execution_time,
             truncated: result.truncated,
+            affected_rows: result.affected_rows,
+            timing: result.timing,
+            column_metadata: result.column_metadata,
+            cached: false,
         }
     }
 }
 
-pub type ActiveConnection = Arc<Mutex<Option<Arc<dyn DatabaseConnection>>>>;
-
-#[tauri::command]
-pub async fn close_splashscreen(window: WebviewWindow) {
-    if let Some(splashscreen) = window.get_webview_window("splashscreen") {
-        let _ = splashscreen.close();
-    }
-    if let Some(main) = window.get_webview_window("main") {
-        let _ = main.show();
+impl From<Connection> for StoredConnection {
+    fn from(conn: Connection) -> Self {
+        StoredConnection {
+            id: conn.id,
+            name: conn.name,
+            db_type: conn.db_type,
+            host: conn.host,
+            port: conn.port,
+            username: conn.username,
+            password_encrypted: conn.password,
+            database: conn.database,
+            ssl_mode: conn.ssl_mode,
+            ssl_ca_cert: conn.ssl_ca_cert,
+            pool_min_size: conn.pool_min_size,
+            pool_max_size: conn.pool_max_size,
+            group_id: conn.group_id,
+            tags: conn.tags,
+            last_used_at: conn.last_used_at,
+            use_count: conn.use_count,
+            preferences: conn.preferences,
+            ssh: conn.ssh,
+            rds_iam: conn.rds_iam,
+            deleted_at: conn.deleted_at,
+        }
     }
 }
 
-#[tauri::command]
-pub async fn save_connection(
-    store: tauri::State<'_, Arc<ConnectionsStore>>,
-    conn: Connection,
-) -> Result<Connection, String> {
-    let stored = StoredConnection {
-        id: conn.id.clone(),
-        name: conn.name.clone(),
-        db_type: conn.db_type.clone(),
-        host: conn.host.clone(),
-        port: conn.port,
-        username: conn.username.clone(),
-        password_encrypted: conn.password.clone(),
-        database: conn.database.clone(),
-        ssl_mode: conn.ssl_mode.clone(),
-    };
-
-    store
-        .save_connection(stored)
-        .map_err(|e| e.to_string())?;
-
-    debug!("Saved connection: {}", conn.name);
-    Ok(conn)
-}
-
-#[tauri::command]
-pub async fn get_connections(
-    store: tauri::State<'_, Arc<ConnectionsStore>>,
-) -> Result<Vec<Connection>, String> {
-    let stored_connections = store
-        .get_all_connections()
-        .map_err(|e| e.to_string())?;
-
-    Ok(stored_connections
-        .into_iter()
-        .map(|sc| Connection {
+impl From<StoredConnection> for Connection {
+    fn from(sc: StoredConnection) -> Self {
+        Connection {
             id: sc.id,
             name: sc.name,
             db_type: sc.db_type,
@@ -117,232 +201,2911 @@ pub async fn get_connections(
             password: sc.password_encrypted,
             database: sc.database,
             ssl_mode: sc.ssl_mode,
-        })
-        .collect())
+            ssl_ca_cert: sc.ssl_ca_cert,
+            pool_min_size: sc.pool_min_size,
+            pool_max_size: sc.pool_max_size,
+            group_id: sc.group_id,
+            tags: sc.tags,
+            last_used_at: sc.last_used_at,
+            use_count: sc.use_count,
+            preferences: sc.preferences,
+            ssh: sc.ssh,
+            rds_iam: sc.rds_iam,
+            deleted_at: sc.deleted_at,
+        }
+    }
 }
 
-#[tauri::command]
-pub async fn delete_connection(
-    store: tauri::State<'_, Arc<ConnectionsStore>>,
-    id: String,
-) -> Result<bool, String> {
-    let result = store
-        .delete_connection(&id)
-        .map_err(|e| e.to_string())?;
+/// A database connection of a window (see `WindowSessions`).
+///
+/// Commands clone the inner `Arc` and release the lock before running, so operations on the
+/// connection (e.g. a sidebar refresh during a long query) can run concurrently; drivers lease
+/// their own underlying connections per operation.
+pub type ActiveConnection = Arc<Mutex<Option<Arc<dyn DatabaseConnection>>>>;
 
-    debug!("Deleted connection: {}", id);
-    Ok(result)
+/// Saved-connection details of the active connection that commands need alongside the driver.
+#[derive(Debug, Clone, Default)]
+pub struct SessionInfo {
+    /// Id of the stored connection, if the active connection was saved.
+    pub connection_id: Option<String>,
+    pub connection_name: String,
+    pub preferences: ConnectionPreferences,
+    /// Result of the last keepalive ping, once there has been one.
+    pub health: Option<ConnectionHealth>,
 }
 
-#[tauri::command]
-pub async fn test_connection(conn: Connection) -> Result<(), String> {
-    let db_conn = create_connection(
-        &conn.db_type,
-        &conn.host,
-        conn.port as u16,
-        &conn.username,
-        &conn.password,
-        &conn.database,
-        &conn.ssl_mode,
-    )
-    .await
-    .map_err(|e| e.message)?;
+pub type ActiveSession = Arc<Mutex<SessionInfo>>;
 
-    db_conn.test_connection().await.map_err(|e| e.message)?;
-    debug!("Connection test successful: {}", conn.name);
-    Ok(())
+/// State of one connection of an app window.
+#[derive(Clone, Default)]
+pub struct WindowSession {
+    pub connection: ActiveConnection,
+    pub session: ActiveSession,
 }
 
-#[tauri::command]
-pub async fn connect_to_database(
-    conn: Connection,
-    active_conn: tauri::State<'_, ActiveConnection>,
-) -> Result<(), String> {
-    let db_conn = create_connection(
-        &conn.db_type,
-        &conn.host,
-        conn.port as u16,
-        &conn.username,
-        &conn.password,
-        &conn.database,
-        &conn.ssl_mode,
-    )
-    .await
-    .map_err(|e| e.message)?;
+/// Connections of one app window, keyed by stored connection id ("" for an unsaved
+/// connection).
+#[derive(Default)]
+struct WindowConnections {
+    /// Connection used by commands that don't name one: the one connected last.
+    current: String,
+    connections: HashMap<String, WindowSession>,
+}
 
-    let mut active = active_conn.lock().await;
-    *active = Some(db_conn);
+/// Connection registry: the connections of each window, keyed by window label and then by
+/// connection id, so tabs and windows can be connected to different servers side by side.
+///
+/// Commands take an optional `connection_id`; without it they use the connection the window
+/// connected last.
+#[derive(Default)]
+pub struct WindowSessions(std::sync::Mutex<HashMap<String, WindowConnections>>);
 
-    debug!("Connected to database: {}", conn.name);
-    Ok(())
+impl WindowSessions {
+    /// Returns the state of a connection of the window labelled `label`, or of its current
+    /// connection when `connection_id` is None, creating it on first use.
+    pub fn get(&self, label: &str, connection_id: Option<&str>) -> WindowSession {
+        let mut windows = self.0.lock().unwrap();
+        let window = windows.entry(label.to_string()).or_default();
+        let id = connection_id.unwrap_or(&window.current).to_string();
+        window.connections.entry(id).or_default().clone()
+    }
+
+    /// Returns the id of the connection commands of a window use when they don't name one.
+    pub fn current(&self, label: &str) -> String {
+        let windows = self.0.lock().unwrap();
+        windows
+            .get(label)
+            .map(|window| window.current.clone())
+            .unwrap_or_default()
+    }
+
+    /// Makes a connection the one commands of a window use when they don't name one.
+    pub fn select(&self, label: &str, connection_id: &str) {
+        let mut windows = self.0.lock().unwrap();
+        windows.entry(label.to_string()).or_default().current = connection_id.to_string();
+    }
+
+    /// Removes the state of a closed window, returning its connections so they can be closed.
+    pub fn remove(&self, label: &str) -> Vec<WindowSession> {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(label)
+            .map(|window| window.connections.into_values().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the state of every connection of every window.
+    pub fn all(&self) -> Vec<WindowSession> {
+        self.entries()
+            .into_iter()
+            .map(|(_, session)| session)
+            .collect()
+    }
+
+    /// Returns the state of every connection with the label of its window.
+    pub fn entries(&self) -> Vec<(String, WindowSession)> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|(label, window)| {
+                window
+                    .connections
+                    .values()
+                    .map(|session| (label.clone(), session.clone()))
+            })
+            .collect()
+    }
 }
 
-#[tauri::command]
-pub async fn execute_query(
-    query: String,
-    active_conn: tauri::State<'_, ActiveConnection>,
-) -> Result<QueryResult, String> {
-    let active = active_conn.lock().await;
-    match &*active {
-        Some(conn) => {
-            let result = conn.execute_query(&query).await.map_err(|e| e.message)?;
-            Ok(result.into())
-        }
-        None => Err("No active connection".to_string()),
+/// Emitted once a connection has been established.
+const CONNECTED_EVENT: &str = "db:connected";
+
+/// Emitted when the active connection has been closed.
+const DISCONNECTED_EVENT: &str = "db:disconnected";
+
+/// Emitted when the active connection switched to another database.
+const DATABASE_CHANGED_EVENT: &str = "db:database_changed";
+
+/// Emitted before the active connection is established again.
+const RECONNECTING_EVENT: &str = "db:reconnecting";
+
+/// Emitted when the active connection broke and couldn't be re-established.
+const CONNECTION_LOST_EVENT: &str = "db:connection_lost";
+
+/// Payload of the `db:*` connection lifecycle events.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionEvent {
+    pub connection_id: Option<String>,
+    pub connection_name: String,
+    /// Current database, if known.
+    pub database: Option<String>,
+}
+
+/// Notifies a window of a change to its connection, so UI surfaces stay in sync without
+/// each command returning the connection state.
+fn emit_connection_event(
+    window: &WebviewWindow,
+    event: &str,
+    session: &SessionInfo,
+    database: Option<String>,
+) {
+    let payload = ConnectionEvent {
+        connection_id: session.connection_id.clone(),
+        connection_name: session.connection_name.clone(),
+        database,
+    };
+    if let Err(e) = window.emit_to(window.label(), event, payload) {
+        warn!("Failed to emit {}: {}", event, e);
     }
 }
 
-#[tauri::command]
-pub async fn list_tables(
-    active_conn: tauri::State<'_, ActiveConnection>,
-) -> Result<Vec<String>, String> {
-    let active = active_conn.lock().await;
-    match &*active {
-        Some(conn) => {
-            let tables = conn.list_tables().await.map_err(|e| e.message)?;
-            Ok(tables)
-        }
-        None => Err("No active connection".to_string()),
+/// Records a destructive statement in the audit log.
+///
+/// Failing to write the log never fails the command itself; the error is only logged.
+async fn record_audit(
+    audit_log: &AuditLog,
+    session: &SessionInfo,
+    conn: &Arc<dyn DatabaseConnection>,
+    command: &str,
+    statement: &str,
+    outcome: Result<Option<u64>, &str>,
+) {
+    let database = conn.get_current_database().await.unwrap_or_default();
+    let (affected_rows, error) = match outcome {
+        Ok(affected_rows) => (affected_rows, None),
+        Err(message) => (None, Some(message.to_string())),
+    };
+
+    let entry = AuditEntry {
+        id: 0,
+        executed_at: String::new(),
+        connection_id: session.connection_id.clone(),
+        connection_name: session.connection_name.clone(),
+        database,
+        statement: statement.to_string(),
+        affected_rows,
+        command: command.to_string(),
+        error,
+    };
+
+    if let Err(e) = audit_log.record(&entry) {
+        warn!("Failed to write audit log entry: {}", e);
     }
 }
 
-#[tauri::command]
-pub async fn list_databases(
-    active_conn: tauri::State<'_, ActiveConnection>,
-) -> Result<Vec<String>, String> {
-    let active = active_conn.lock().await;
-    match &*active {
-        Some(conn) => {
-            let databases = conn.list_databases().await.map_err(|e| e.message)?;
-            Ok(databases)
+/// Records a slow query with its plan, when the statement can be explained.
+///
+/// Failing to write the log never fails the command itself; the error is only logged.
+async fn record_slow_query(
+    slow_log: Arc<SlowQueryLog>,
+    session: SessionInfo,
+    conn: Arc<dyn DatabaseConnection>,
+    statement: String,
+    duration_ms: u128,
+) {
+    let plan = if statement::is_explainable(&statement) {
+        match conn
+            .execute_query(&format!("EXPLAIN {}", statement.trim()))
+            .await
+        {
+            Ok(plan) => Some(plan_text(&plan)),
+            Err(e) => {
+                debug!("Could not explain slow query: {}", e.message);
+                None
+            }
         }
-        None => Err("No active connection".to_string()),
+    } else {
+        None
+    };
+    let database = conn.get_current_database().await.unwrap_or_default();
+
+    let entry = SlowQuery {
+        id: 0,
+        executed_at: String::new(),
+        connection_id: session.connection_id,
+        connection_name: session.connection_name,
+        database,
+        statement,
+        duration_ms: duration_ms.min(u64::MAX as u128) as u64,
+        plan,
+    };
+
+    if let Err(e) = slow_log.record(&entry) {
+        warn!("Failed to write slow query log entry: {}", e);
     }
 }
 
-#[tauri::command]
-pub async fn change_database(
-    database_name: String,
-    active_conn: tauri::State<'_, ActiveConnection>,
-) -> Result<(), String> {
-    let active = active_conn.lock().await;
-    match &*active {
-        Some(conn) => {
-            conn.change_database(&database_name)
-                .await
-                .map_err(|e| e.message)?;
-            debug!("Changed database to: {}", database_name);
-            Ok(())
-        }
-        None => Err("No active connection".to_string()),
+/// Renders EXPLAIN output as text: PostgreSQL's one-column plan as its lines, tabular
+/// plans (MariaDB/MySQL) as JSON rows.
+fn plan_text(plan: &crate::db::QueryResult) -> String {
+    match plan.columns.as_slice() {
+        [column] => plan
+            .rows
+            .iter()
+            .filter_map(|row| row.get(column).and_then(|value| value.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => serde_json::to_string(&plan.rows).unwrap_or_default(),
     }
 }
 
-#[tauri::command]
-pub async fn get_current_database(
-    active_conn: tauri::State<'_, ActiveConnection>,
-) -> Result<String, String> {
-    let active = active_conn.lock().await;
-    match &*active {
-        Some(conn) => {
-            let db_name = conn.get_current_database().await.map_err(|e| e.message)?;
-            Ok(db_name)
+/// Cached schema metadata younger than this is served without refreshing it.
+const SCHEMA_CACHE_FRESH_FOR: Duration = Duration::from_secs(5 * 60);
+
+/// Emitted when a background refresh has replaced cached schema metadata.
+const SCHEMA_CACHE_UPDATED_EVENT: &str = "schema-cache-updated";
+
+/// Payload of `SCHEMA_CACHE_UPDATED_EVENT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaCacheUpdate {
+    pub connection_id: String,
+    pub database: String,
+    /// One of `cache_kinds`.
+    pub kind: String,
+    /// Table name for column metadata, empty otherwise.
+    pub object_name: String,
+}
+
+/// Returns schema metadata from the persistent cache when possible.
+///
+/// Cached data is returned immediately; if it is older than `SCHEMA_CACHE_FRESH_FOR` it is
+/// refreshed in the background and `SCHEMA_CACHE_UPDATED_EVENT` is emitted once the new data
+/// is stored. Unsaved connections, cache misses and `refresh` bypass the cache.
+#[allow(clippy::too_many_arguments)]
+async fn cached_metadata<T, F, Fut>(
+    app: &AppHandle,
+    cache: &Arc<SchemaCache>,
+    session: &SessionInfo,
+    conn: &Arc<dyn DatabaseConnection>,
+    kind: &'static str,
+    object_name: &str,
+    refresh: bool,
+    fetch: F,
+) -> Result<T, String>
+where
+    T: Serialize + DeserializeOwned + Send + 'static,
+    F: FnOnce(Arc<dyn DatabaseConnection>) -> Fut + Send + 'static,
+    Fut: Future<Output = DbResult<T>> + Send,
+{
+    let connection_id = match &session.connection_id {
+        Some(id) => id.clone(),
+        None => return fetch(conn.clone()).await.map_err(|e| e.message),
+    };
+    let database = conn.get_current_database().await.map_err(|e| e.message)?;
+
+    if !refresh {
+        match cache.get::<T>(&connection_id, &database, kind, object_name) {
+            Ok(Some(cached)) => {
+                if cached.age >= SCHEMA_CACHE_FRESH_FOR {
+                    let update = SchemaCacheUpdate {
+                        connection_id,
+                        database,
+                        kind: kind.to_string(),
+                        object_name: object_name.to_string(),
+                    };
+                    let (app, cache, conn) = (app.clone(), cache.clone(), conn.clone());
+                    tokio::spawn(async move {
+                        match fetch(conn).await {
+                            Ok(value) => {
+                                if let Err(e) = cache.put(
+                                    &update.connection_id,
+                                    &update.database,
+                                    &update.kind,
+                                    &update.object_name,
+                                    &value,
+                                ) {
+                                    warn!("Failed to update schema cache: {}", e);
+                                    return;
+                                }
+                                debug!("Refreshed cached {} for {}", update.kind, update.database);
+                                let _ = app.emit(SCHEMA_CACHE_UPDATED_EVENT, update);
+                            }
+                            Err(e) => warn!("Background schema refresh failed: {}", e.message),
+                        }
+                    });
+                }
+                return Ok(cached.value);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read schema cache: {}", e),
         }
-        None => Err("No active connection".to_string()),
     }
+
+    let value = fetch(conn.clone()).await.map_err(|e| e.message)?;
+    if let Err(e) = cache.put(&connection_id, &database, kind, object_name, &value) {
+        warn!("Failed to update schema cache: {}", e);
+    }
+    Ok(value)
 }
 
-#[tauri::command]
-pub async fn get_table_columns(
-    table_name: String,
-    active_conn: tauri::State<'_, ActiveConnection>,
-) -> Result<Vec<TableColumn>, String> {
-    let active = active_conn.lock().await;
-    match &*active {
-        Some(conn) => {
-            let columns = conn
-                .get_table_columns(&table_name)
-                .await
-                .map_err(|e| e.message)?;
-            Ok(columns)
+/// Emitted as the metadata prefetch started after connecting makes progress.
+const METADATA_PREFETCH_EVENT: &str = "metadata-prefetch-progress";
+
+/// Column metadata is prefetched for at most this many tables.
+const MAX_PREFETCH_TABLES: usize = 200;
+
+/// Payload of `METADATA_PREFETCH_EVENT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MetadataPrefetchProgress {
+    pub connection_id: String,
+    pub database: String,
+    /// Tables whose column metadata has been prefetched so far.
+    pub loaded: usize,
+    /// Tables whose column metadata will be prefetched.
+    pub total: usize,
+    pub done: bool,
+}
+
+/// Returns fresh metadata from the schema cache, or fetches and caches it.
+async fn prefetch_metadata<T>(
+    cache: &SchemaCache,
+    connection_id: &str,
+    database: &str,
+    kind: &str,
+    object_name: &str,
+    fetch: impl Future<Output = DbResult<T>>,
+) -> DbResult<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    if let Ok(Some(cached)) = cache.get::<T>(connection_id, database, kind, object_name) {
+        if cached.age < SCHEMA_CACHE_FRESH_FOR {
+            return Ok(cached.value);
         }
-        None => Err("No active connection".to_string()),
     }
+
+    let value = fetch.await?;
+    if let Err(e) = cache.put(connection_id, database, kind, object_name, &value) {
+        warn!("Failed to update schema cache: {}", e);
+    }
+    Ok(value)
 }
 
-#[tauri::command]
-pub async fn get_table_relationships(
-    active_conn: tauri::State<'_, ActiveConnection>,
-) -> Result<Vec<TableRelationship>, String> {
-    let active = active_conn.lock().await;
-    match &*active {
-        Some(conn) => {
-            let relationships = conn
-                .get_table_relationships()
-                .await
-                .map_err(|e| e.message)?;
-            Ok(relationships)
-        }
-        None => Err("No active connection".to_string()),
+async fn is_active_connection(
+    active_conn: &ActiveConnection,
+    conn: &Arc<dyn DatabaseConnection>,
+) -> bool {
+    match active_conn.lock().await.as_ref() {
+        Some(active) => Arc::ptr_eq(active, conn),
+        None => false,
     }
 }
 
-#[tauri::command]
-pub async fn disconnect_from_database(
-    active_conn: tauri::State<'_, ActiveConnection>,
+/// Loads the table list, relationships and column metadata of a newly connected database
+/// into the schema cache in the background, emitting `METADATA_PREFETCH_EVENT` as it goes.
+///
+/// Tables are loaded one at a time so the prefetch never competes much with the user's own
+/// queries, and it stops once the connection is no longer active.
+fn spawn_metadata_prefetch(
+    app: AppHandle,
+    cache: Arc<SchemaCache>,
+    active_conn: ActiveConnection,
+    connection_id: String,
+    conn: Arc<dyn DatabaseConnection>,
+) {
+    tokio::spawn(async move {
+        let database = match conn.get_current_database().await {
+            Ok(database) => database,
+            Err(e) => {
+                warn!("Metadata prefetch failed: {}", e.message);
+                return;
+            }
+        };
+        let emit_progress = |loaded: usize, total: usize, done: bool| {
+            let progress = MetadataPrefetchProgress {
+                connection_id: connection_id.clone(),
+                database: database.clone(),
+                loaded,
+                total,
+                done,
+            };
+            let _ = app.emit(METADATA_PREFETCH_EVENT, progress);
+        };
+
+        let tables = prefetch_metadata(
+            &cache,
+            &connection_id,
+            &database,
+            cache_kinds::TABLES,
+            "",
+            conn.list_tables(),
+        )
+        .await;
+        let tables = match tables {
+            Ok(tables) => tables,
+            Err(e) => {
+                warn!("Metadata prefetch failed: {}", e.message);
+                emit_progress(0, 0, true);
+                return;
+            }
+        };
+        if let Err(e) = prefetch_metadata(
+            &cache,
+            &connection_id,
+            &database,
+            cache_kinds::RELATIONSHIPS,
+            "",
+            conn.get_table_relationships(),
+        )
+        .await
+        {
+            warn!("Failed to prefetch relationships: {}", e.message);
+        }
+
+        let total = tables.len().min(MAX_PREFETCH_TABLES);
+        emit_progress(0, total, false);
+        for (loaded, table) in tables.iter().take(total).enumerate() {
+            if !is_active_connection(&active_conn, &conn).await {
+                debug!("Stopped metadata prefetch for {}", database);
+                return;
+            }
+            if let Err(e) = prefetch_metadata(
+                &cache,
+                &connection_id,
+                &database,
+                cache_kinds::COLUMNS,
+                table,
+                conn.get_table_columns(&TableRef::unqualified(table.as_str())),
+            )
+            .await
+            {
+                warn!("Failed to prefetch columns of {}: {}", table, e.message);
+            }
+            emit_progress(loaded + 1, total, false);
+        }
+
+        emit_progress(total, total, true);
+        debug!("Prefetched metadata of {} tables in {}", total, database);
+    });
+}
+
+const READ_ONLY_ERROR: &str =
+    "This connection is read-only; only statements that read data are allowed";
+
+/// Keys returned by `scan_keys` when the frontend doesn't ask for a page size.
+const KEY_PAGE_SIZE: usize = 200;
+
+/// Prefix of the error returned for statements that need `force`, so the frontend can ask
+/// the user to confirm and run them again.
+const CONFIRMATION_REQUIRED: &str = "CONFIRMATION_REQUIRED";
+
+/// Applies the connection's read-only and confirmation preferences to a user query.
+fn check_statement_allowed(
+    prefs: &ConnectionPreferences,
+    dialect: SqlDialect,
+    query: &str,
+    force: bool,
 ) -> Result<(), String> {
-    let mut active = active_conn.lock().await;
-    if let Some(conn) = active.take() {
-        conn.disconnect().await.map_err(|e| e.message)?;
-        debug!("Disconnected from database");
+    if prefs.read_only && !statement::is_read_only(query, dialect) {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+    if prefs.confirm_dangerous_statements && !force {
+        if let Some(reason) = statement::confirmation_reason(query, dialect) {
+            return Err(format!(
+                "{}: {}; run it again with force to confirm",
+                CONFIRMATION_REQUIRED, reason
+            ));
+        }
     }
     Ok(())
 }
 
-#[tauri::command]
-pub async fn export_database(
-    options: ExportOptions,
-    active_conn: tauri::State<'_, ActiveConnection>,
-) -> Result<(), String> {
-    let active = active_conn.lock().await;
-    match &*active {
-        Some(conn) => {
-            let sql_content = conn
-                .export_database_with_options(
-                    options.include_drop,
-                    options.include_create,
-                    &options.data_mode,
-                    &options.selected_tables,
-                    options.max_insert_size,
-                )
-                .await
-                .map_err(|e| e.message)?;
+/// Emitted when connections were changed outside the app through the sync file.
+const CONNECTIONS_CHANGED_EVENT: &str = "connections-changed";
 
-            let file_path = std::path::Path::new(&options.output_path).join(&options.file_name);
+/// How often the connection sync file is checked for changes.
+const CONNECTION_SYNC_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
-            tokio::fs::write(&file_path, sql_content)
-                .await
-                .map_err(|e| format!("Failed to write file: {}", e))?;
+/// Mirrors stored connections to the sync file after a change. Failures are only logged.
+fn sync_connections(sync: &ConnectionSync) {
+    if let Err(e) = sync.export() {
+        warn!("Failed to write connection sync file: {}", e);
+    }
+}
 
-            debug!("Exported database to: {:?}", file_path);
-            Ok(())
+/// Polls the connection sync file and imports changes made outside the app,
+/// emitting `CONNECTIONS_CHANGED_EVENT` so the frontend reloads its list.
+pub fn spawn_connection_sync_watcher(app: AppHandle, sync: Arc<ConnectionSync>) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(CONNECTION_SYNC_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            match sync.poll() {
+                Ok(true) => {
+                    debug!("Imported connections from sync file");
+                    let _ = app.emit(CONNECTIONS_CHANGED_EVENT, ());
+                }
+                Ok(false) => {}
+                Err(e) => warn!("Failed to import connection sync file: {}", e),
+            }
+        }
+    });
+}
+
+/// How often the wall clock is checked for a jump caused by sleep.
+const RESUME_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A check arriving this much later than scheduled means the machine was asleep.
+const RESUME_MIN_GAP: Duration = Duration::from_secs(30);
+
+/// Watches for the machine resuming from sleep and then checks every window's connection,
+/// re-establishing broken ones before the next query runs into them.
+///
+/// Resumes are noticed by the wall clock jumping ahead between checks, which works the same
+/// on every platform without OS power notifications.
+pub fn spawn_resume_watcher(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(RESUME_CHECK_INTERVAL);
+        let mut last_check = std::time::SystemTime::now();
+        loop {
+            interval.tick().await;
+            let now = std::time::SystemTime::now();
+            let gap = now.duration_since(last_check).unwrap_or_default();
+            last_check = now;
+            if gap >= RESUME_CHECK_INTERVAL + RESUME_MIN_GAP {
+                debug!("Resumed after about {} seconds asleep", gap.as_secs());
+                revalidate_connections(&app).await;
+            }
+        }
+    });
+}
+
+/// Re-establishes the broken connections of all windows, telling each window with
+/// `CONNECTED_EVENT` or `CONNECTION_LOST_EVENT`.
+async fn revalidate_connections(app: &AppHandle) {
+    for (label, window_session) in app.state::<WindowSessions>().entries() {
+        let Some(conn) = window_session.connection.lock().await.clone() else {
+            continue;
+        };
+        let Some(window) = app.get_webview_window(&label) else {
+            continue;
+        };
+        let session = window_session.session.lock().await.clone();
+        match conn.reconnect_if_broken().await {
+            Ok(true) => {
+                debug!("Reconnected {} after resume", session.connection_name);
+                let database = conn.get_current_database().await.ok();
+                emit_connection_event(&window, CONNECTED_EVENT, &session, database);
+            }
+            Ok(false) => {}
+            Err(e) => {
+                warn!(
+                    "Failed to reconnect {} after resume: {}",
+                    session.connection_name, e.message
+                );
+                emit_connection_event(&window, CONNECTION_LOST_EVENT, &session, None);
+            }
         }
-        None => Err("No active connection".to_string()),
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UpdateCellResult {
-    pub success: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<UpdateCellError>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub executed_query: Option<String>,
+/// Seconds between keepalive pings when the connection's preferences don't set an interval.
+const DEFAULT_KEEPALIVE_INTERVAL_SECS: u64 = 30;
+
+/// Emitted after each keepalive ping with the connection's `ConnectionHealth`.
+const CONNECTION_HEALTH_EVENT: &str = "connection_health";
+
+/// Outcome of a keepalive ping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    Unreachable,
 }
 
-/// Detailed error information for cell update failures.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UpdateCellError {
+/// Liveness of a connection as of its last keepalive ping.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionHealth {
+    pub connection_id: Option<String>,
+    pub connection_name: String,
+    pub status: HealthStatus,
+    /// Round trip of the ping, when it succeeded.
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+    /// Pings that failed in a row.
+    pub consecutive_failures: u32,
+    pub checked_at: String,
+}
+
+/// Pings a connection of a window until it is closed or replaced, keeping
+/// `SessionInfo::health` current and emitting `CONNECTION_HEALTH_EVENT` after each ping.
+///
+/// The interval is read from the session's preferences before every ping, so changing it
+/// takes effect without reconnecting.
+fn spawn_keepalive(
+    window: WebviewWindow,
+    window_session: WindowSession,
+    conn: Arc<dyn DatabaseConnection>,
+) {
+    tokio::spawn(async move {
+        let mut consecutive_failures = 0;
+        loop {
+            let interval_secs = window_session
+                .session
+                .lock()
+                .await
+                .preferences
+                .keepalive_interval_secs
+                .unwrap_or(DEFAULT_KEEPALIVE_INTERVAL_SECS);
+            // Turned off: look again later in case it is turned back on
+            tokio::time::sleep(Duration::from_secs(if interval_secs == 0 {
+                DEFAULT_KEEPALIVE_INTERVAL_SECS
+            } else {
+                interval_secs
+            }))
+            .await;
+            if !is_active_connection(&window_session.connection, &conn).await {
+                return;
+            }
+            if interval_secs == 0 {
+                continue;
+            }
+
+            let started = Instant::now();
+            let error = match tokio::time::timeout(LIVENESS_TIMEOUT, conn.test_connection()).await
+            {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(e.message),
+                Err(_) => Some(format!(
+                    "No response within {} seconds",
+                    LIVENESS_TIMEOUT.as_secs()
+                )),
+            };
+            let latency_ms = started.elapsed().as_millis() as u64;
+            consecutive_failures = if error.is_some() {
+                consecutive_failures + 1
+            } else {
+                0
+            };
+
+            let mut session = window_session.session.lock().await;
+            let health = ConnectionHealth {
+                connection_id: session.connection_id.clone(),
+                connection_name: session.connection_name.clone(),
+                status: if error.is_some() {
+                    HealthStatus::Unreachable
+                } else {
+                    HealthStatus::Healthy
+                },
+                latency_ms: error.is_none().then_some(latency_ms),
+                error,
+                consecutive_failures,
+                checked_at: chrono::Utc::now().to_rfc3339(),
+            };
+            session.health = Some(health.clone());
+            drop(session);
+
+            if let Some(error) = &health.error {
+                warn!(
+                    "Keepalive ping of {} failed: {}",
+                    health.connection_name, error
+                );
+            }
+            if let Err(e) = window.emit_to(window.label(), CONNECTION_HEALTH_EVENT, health) {
+                warn!("Failed to emit {}: {}", CONNECTION_HEALTH_EVENT, e);
+            }
+        }
+    });
+}
+
+/// Returns the result of the last keepalive ping of a connection, if it has been pinged.
+#[tauri::command]
+pub async fn get_connection_health(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<Option<ConnectionHealth>, String> {
+    let window_session = sessions.get(window.label(), connection_id.as_deref());
+    if window_session.connection.lock().await.is_none() {
+        return Err("No active connection".to_string());
+    }
+    let health = window_session.session.lock().await.health.clone();
+    Ok(health)
+}
+
+#[tauri::command]
+pub async fn close_splashscreen(window: WebviewWindow) {
+    if let Some(splashscreen) = window.get_webview_window("splashscreen") {
+        let _ = splashscreen.close();
+    }
+    if let Some(main) = window.get_webview_window("main") {
+        let _ = main.show();
+    }
+}
+
+/// Returns the request of a command-line launch once, so the frontend can load its file.
+#[tauri::command]
+pub async fn take_launch_request(
+    pending: tauri::State<'_, PendingLaunch>,
+) -> Result<Option<LaunchRequest>, String> {
+    Ok(pending.lock().await.take())
+}
+
+/// An editor tab with unsaved changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveredTab {
+    pub id: String,
+    pub title: String,
+    pub sql: String,
+    /// Stored connection the tab was used with.
+    #[serde(default)]
+    pub connection_id: Option<String>,
+    /// File the tab was opened from, if any.
+    #[serde(default)]
+    pub file_path: Option<String>,
+}
+
+/// Unsaved work of a window, saved periodically by the frontend so `recover_session` can
+/// restore it after a crash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoverySnapshot {
+    #[serde(default)]
+    pub tabs: Vec<RecoveredTab>,
+    /// Grid edits that were staged but not yet applied.
+    #[serde(default)]
+    pub staged_edits: Vec<UpdateCellRequest>,
+    /// RFC 3339 timestamp; set when the snapshot is saved.
+    #[serde(default)]
+    pub saved_at: String,
+}
+
+/// Replaces the recovery snapshot of the calling window. An empty snapshot removes it.
+#[tauri::command]
+pub async fn save_recovery_snapshot(
+    window: WebviewWindow,
+    recovery: tauri::State<'_, Arc<RecoveryStore>>,
+    mut snapshot: RecoverySnapshot,
+) -> Result<(), String> {
+    if snapshot.tabs.is_empty() && snapshot.staged_edits.is_empty() {
+        return recovery.remove(window.label()).map_err(|e| e.to_string());
+    }
+    snapshot.saved_at = chrono::Utc::now().to_rfc3339();
+    recovery
+        .save(window.label(), &snapshot)
+        .map_err(|e| e.to_string())
+}
+
+/// Returns unsaved work to restore in the calling window: work left behind by an unclean
+/// shutdown (offered once), and the window's own snapshot if its webview crashed and was
+/// reloaded.
+#[tauri::command]
+pub async fn recover_session(
+    window: WebviewWindow,
+    recovery: tauri::State<'_, Arc<RecoveryStore>>,
+) -> Result<Vec<RecoverySnapshot>, String> {
+    let snapshots: Vec<RecoverySnapshot> = recovery
+        .recover(window.label())
+        .map_err(|e| e.to_string())?;
+    if !snapshots.is_empty() {
+        debug!("Recovering {} unsaved snapshots", snapshots.len());
+    }
+    Ok(snapshots)
+}
+
+/// Emitted to a window with the `DroppedFile` for each file dropped onto it.
+const FILE_DROPPED_EVENT: &str = "file-dropped";
+
+/// What the frontend should do with a file dropped onto a window.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DroppedFile {
+    /// SQL script to load into the editor and run.
+    Sql { path: String, sql: String },
+    /// File that can't be opened, with the reason.
+    Unsupported { path: String, reason: String },
+}
+
+/// Decides by its extension what to do with a dropped file: the single entry point for
+/// files dropped onto a window and for drops the frontend receives itself.
+#[tauri::command]
+pub async fn handle_dropped_file(path: String) -> Result<DroppedFile, String> {
+    let extension = std::path::Path::new(&path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    let reason = match extension.as_str() {
+        "sql" => {
+            let sql = tokio::fs::read_to_string(&path)
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            return Ok(DroppedFile::Sql { path, sql });
+        }
+        "csv" | "tsv" => "CSV import is not available yet",
+        "db" | "sqlite" | "sqlite3" => "SQLite databases are not supported yet",
+        _ => "Only .sql files can be opened",
+    };
+    Ok(DroppedFile::Unsupported {
+        path,
+        reason: reason.to_string(),
+    })
+}
+
+/// Handles files dropped onto the window labelled `label`, emitting `FILE_DROPPED_EVENT`
+/// for each.
+pub async fn handle_dropped_files(app: &AppHandle, label: &str, paths: Vec<std::path::PathBuf>) {
+    for path in paths {
+        let path = path.to_string_lossy().into_owned();
+        match handle_dropped_file(path).await {
+            Ok(file) => {
+                if let Err(e) = app.emit_to(label, FILE_DROPPED_EVENT, file) {
+                    warn!("Failed to emit dropped file: {}", e);
+                }
+            }
+            Err(e) => warn!("Failed to open dropped file: {}", e),
+        }
+    }
+}
+
+/// Builds an unsaved connection from a connection URL (`postgres://…`, `mysql://…`) or a
+/// `.env` line holding one, for the connection form to fill in.
+#[tauri::command]
+pub async fn parse_connection_url(url: String) -> Result<Connection, String> {
+    deep_link::parse_dsn(&url)
+}
+
+/// Saves `conn`, or the connection built from `url` as by `parse_connection_url`.
+#[tauri::command]
+pub async fn save_connection(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    sync: tauri::State<'_, Arc<ConnectionSync>>,
+    conn: Option<Connection>,
+    url: Option<String>,
+) -> Result<Connection, String> {
+    let conn = match (conn, url) {
+        (Some(conn), None) => conn,
+        (None, Some(url)) => deep_link::parse_dsn(&url)?,
+        _ => return Err("Pass either a connection or a connection URL".to_string()),
+    };
+
+    if let Some(ssh) = &conn.ssh {
+        validate_ssh_config(ssh)?;
+    }
+
+    let conn: Connection = store
+        .save_connection(conn.into())
+        .map_err(|e| e.to_string())?
+        .into();
+
+    sync_connections(&sync);
+    debug!("Saved connection: {}", conn.name);
+    Ok(conn)
+}
+
+#[tauri::command]
+pub async fn get_connections(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    sort: Option<ConnectionSort>,
+) -> Result<Vec<Connection>, String> {
+    let stored_connections = store
+        .get_all_connections(sort.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+
+    Ok(stored_connections
+        .into_iter()
+        .map(Connection::from)
+        .collect())
+}
+
+/// Checks that an SSH config has what its authentication method needs.
+fn validate_ssh_config(ssh: &SshConfig) -> Result<(), String> {
+    if ssh.host.trim().is_empty() || ssh.username.trim().is_empty() {
+        return Err("SSH host and username are required".to_string());
+    }
+
+    if ssh.auth_method == SshAuthMethod::PrivateKey {
+        let key_path = ssh
+            .private_key_path
+            .as_deref()
+            .filter(|p| !p.trim().is_empty())
+            .ok_or_else(|| "A private key file is required for key authentication".to_string())?;
+        if !std::path::Path::new(key_path).is_file() {
+            return Err(format!("SSH private key file not found: {}", key_path));
+        }
+    }
+
+    Ok(())
+}
+
+/// Trusts an SSH host key fingerprint for a saved connection's tunnel.
+#[tauri::command]
+pub async fn trust_ssh_host_key(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    sync: tauri::State<'_, Arc<ConnectionSync>>,
+    connection_id: String,
+    fingerprint: String,
+) -> Result<bool, String> {
+    let result = store
+        .trust_ssh_host_key(&connection_id, &fingerprint)
+        .map_err(|e| e.to_string())?;
+
+    sync_connections(&sync);
+    debug!("Trusted SSH host key for connection: {}", connection_id);
+    Ok(result)
+}
+
+/// Returns saved connections whose name, host or tags match every term in `query`.
+#[tauri::command]
+pub async fn search_connections(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    query: String,
+) -> Result<Vec<Connection>, String> {
+    let stored_connections = store
+        .search_connections(&query)
+        .map_err(|e| e.to_string())?;
+
+    Ok(stored_connections
+        .into_iter()
+        .map(Connection::from)
+        .collect())
+}
+
+#[tauri::command]
+pub async fn get_connection_tags(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+) -> Result<Vec<String>, String> {
+    store.get_all_tags().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_connection(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    sync: tauri::State<'_, Arc<ConnectionSync>>,
+    id: String,
+) -> Result<bool, String> {
+    let result = store
+        .delete_connection(&id)
+        .map_err(|e| e.to_string())?;
+
+    sync_connections(&sync);
+    debug!("Moved connection to trash: {}", id);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_deleted_connections(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+) -> Result<Vec<Connection>, String> {
+    let connections = store.get_deleted_connections().map_err(|e| e.to_string())?;
+    Ok(connections.into_iter().map(Connection::from).collect())
+}
+
+#[tauri::command]
+pub async fn restore_connection(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    sync: tauri::State<'_, Arc<ConnectionSync>>,
+    id: String,
+) -> Result<bool, String> {
+    let result = store.restore_connection(&id).map_err(|e| e.to_string())?;
+
+    sync_connections(&sync);
+    debug!("Restored connection: {}", id);
+    Ok(result)
+}
+
+/// Permanently deletes a connection from the trash.
+#[tauri::command]
+pub async fn purge_connection(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+    id: String,
+) -> Result<bool, String> {
+    let result = store.purge_connection(&id).map_err(|e| e.to_string())?;
+    if result {
+        if let Err(e) = schema_cache.invalidate(&id, None) {
+            warn!("Failed to clear schema cache for {}: {}", id, e);
+        }
+    }
+
+    debug!("Purged connection: {}", id);
+    Ok(result)
+}
+
+/// Whether saved passwords are protected by a master password, and whether it still has to
+/// be entered.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreLockState {
+    pub master_password: bool,
+    pub locked: bool,
+}
+
+#[tauri::command]
+pub async fn get_store_lock_state(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+) -> Result<StoreLockState, String> {
+    Ok(StoreLockState {
+        master_password: store.has_master_password(),
+        locked: store.is_locked(),
+    })
+}
+
+/// Unlocks the connection store with its master password.
+#[tauri::command]
+pub async fn unlock_store(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    password: String,
+) -> Result<(), String> {
+    if !store.has_master_password() {
+        return Err("No master password is set".to_string());
+    }
+    if !store.unlock(&password).map_err(|e| e.to_string())? {
+        return Err("Wrong master password".to_string());
+    }
+    debug!("Unlocked connection store");
+    Ok(())
+}
+
+/// Locks the connection store until `unlock_store` is called with the master password.
+#[tauri::command]
+pub async fn lock_store(store: tauri::State<'_, Arc<ConnectionsStore>>) -> Result<(), String> {
+    if !store.lock() {
+        return Err("The connection store has no master password to lock it with".to_string());
+    }
+    debug!("Locked connection store");
+    Ok(())
+}
+
+/// Sets, changes or (with `None`) removes the master password, re-encrypting saved
+/// passwords. The store must be unlocked.
+#[tauri::command]
+pub async fn set_master_password(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    password: Option<String>,
+) -> Result<(), String> {
+    if password.as_deref().is_some_and(str::is_empty) {
+        return Err("The master password can't be empty".to_string());
+    }
+    store
+        .set_master_password(password.as_deref())
+        .map_err(|e| e.to_string())?;
+    debug!(
+        "{} master password",
+        if password.is_some() { "Set" } else { "Removed" }
+    );
+    Ok(())
+}
+
+/// Updates the saved preferences of a connection, applying them immediately if it is active.
+#[tauri::command]
+pub async fn update_connection_preferences(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    sync: tauri::State<'_, Arc<ConnectionSync>>,
+    sessions: tauri::State<'_, WindowSessions>,
+    id: String,
+    preferences: ConnectionPreferences,
+) -> Result<bool, String> {
+    let updated = store
+        .update_preferences(&id, &preferences)
+        .map_err(|e| e.to_string())?;
+
+    for window in sessions.all() {
+        let mut session = window.session.lock().await;
+        if session.connection_id.as_deref() == Some(id.as_str()) {
+            session.preferences = preferences.clone();
+        }
+    }
+
+    sync_connections(&sync);
+    debug!("Updated preferences for connection: {}", id);
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn save_group(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    sync: tauri::State<'_, Arc<ConnectionSync>>,
+    group: ConnectionGroup,
+) -> Result<ConnectionGroup, String> {
+    if let Some(parent_id) = &group.parent_id {
+        if !group.id.is_empty()
+            && store
+                .is_group_descendant(&group.id, parent_id)
+                .map_err(|e| e.to_string())?
+        {
+            return Err("A group cannot be nested inside itself".to_string());
+        }
+    }
+
+    let saved = store.save_group(group).map_err(|e| e.to_string())?;
+
+    sync_connections(&sync);
+    debug!("Saved group: {}", saved.name);
+    Ok(saved)
+}
+
+#[tauri::command]
+pub async fn get_groups(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+) -> Result<Vec<ConnectionGroup>, String> {
+    store.get_all_groups().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_group(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    sync: tauri::State<'_, Arc<ConnectionSync>>,
+    id: String,
+) -> Result<bool, String> {
+    let result = store.delete_group(&id).map_err(|e| e.to_string())?;
+
+    sync_connections(&sync);
+    debug!("Deleted group: {}", id);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn reorder_groups(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    sync: tauri::State<'_, Arc<ConnectionSync>>,
+    ordered_ids: Vec<String>,
+) -> Result<(), String> {
+    store
+        .reorder_groups(&ordered_ids)
+        .map_err(|e| e.to_string())?;
+    sync_connections(&sync);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn move_connection_to_group(
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    sync: tauri::State<'_, Arc<ConnectionSync>>,
+    connection_id: String,
+    group_id: Option<String>,
+) -> Result<bool, String> {
+    let result = store
+        .move_connection_to_group(&connection_id, group_id.as_deref())
+        .map_err(|e| e.to_string())?;
+
+    sync_connections(&sync);
+    debug!("Moved connection {} to group {:?}", connection_id, group_id);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn test_connection(conn: Connection) -> Result<(), String> {
+    let db_conn = create_connection(
+        &conn.db_type,
+        &conn.host,
+        conn.port as u16,
+        &conn.username,
+        &conn.password,
+        &conn.database,
+        &conn.ssl_mode,
+        conn.ssl_ca_cert.as_deref(),
+        PoolSize::new(conn.pool_min_size, conn.pool_max_size),
+        conn.preferences.query_timeout_secs.map(Duration::from_secs),
+        conn.rds_iam
+            .clone()
+            .map(|iam| RdsIamAuth::new(iam.profile, iam.region)),
+    )
+    .await
+    .map_err(|e| e.message)?;
+
+    db_conn.test_connection().await.map_err(|e| e.message)?;
+    debug!("Connection test successful: {}", conn.name);
+    Ok(())
+}
+
+/// Checks each stage of connecting (DNS, TCP, TLS, login, database) and reports which
+/// one fails, for when `test_connection` only says that connecting failed.
+#[tauri::command]
+pub async fn diagnose_connection(conn: Connection) -> Result<ConnectionDiagnosis, String> {
+    if conn.ssh.is_some() {
+        return Err("Diagnostics don't support connections through an SSH tunnel".to_string());
+    }
+    if driver_info(&conn.db_type).is_ok_and(|info| info.file_based) {
+        return Err("Diagnostics only apply to connections over the network".to_string());
+    }
+    let diagnosis = diagnostics::diagnose(
+        &conn.db_type,
+        &conn.host,
+        conn.port as u16,
+        &conn.username,
+        &conn.password,
+        &conn.database,
+        &conn.ssl_mode,
+        conn.ssl_ca_cert.as_deref(),
+        conn.rds_iam
+            .clone()
+            .map(|iam| RdsIamAuth::new(iam.profile, iam.region)),
+    )
+    .await;
+    debug!(
+        "Diagnosed connection {}: failed at {:?}",
+        conn.name, diagnosis.failed_stage
+    );
+    Ok(diagnosis)
+}
+
+/// Returns the default port, SSL modes and features of a database type, for the connection
+/// form and UI to adapt to it.
+#[tauri::command]
+pub async fn get_driver_info(db_type: String) -> Result<DriverInfo, String> {
+    driver_info(&db_type).map_err(|e| e.message)
+}
+
+/// Connects a window to `conn` alongside its other connections. Commands that don't name a
+/// connection use this one from then on.
+#[tauri::command]
+pub async fn connect_to_database(
+    conn: Connection,
+    app: AppHandle,
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+    watches: tauri::State<'_, SqlFileWatches>,
+) -> Result<(), String> {
+    // Each saved connection has its own slot, so connecting doesn't close the others
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), Some(&conn.id));
+    let new_session = SessionInfo {
+        connection_id: Some(conn.id.clone()).filter(|id| !id.is_empty()),
+        connection_name: conn.name.clone(),
+        preferences: conn.preferences.clone(),
+        health: None,
+    };
+    let reconnecting = active_conn.lock().await.is_some() && new_session.connection_id.is_some();
+    if reconnecting {
+        emit_connection_event(&window, RECONNECTING_EVENT, &new_session, None);
+    }
+
+    let db_conn = create_connection(
+        &conn.db_type,
+        &conn.host,
+        conn.port as u16,
+        &conn.username,
+        &conn.password,
+        &conn.database,
+        &conn.ssl_mode,
+        conn.ssl_ca_cert.as_deref(),
+        PoolSize::new(conn.pool_min_size, conn.pool_max_size),
+        conn.preferences.query_timeout_secs.map(Duration::from_secs),
+        conn.rds_iam
+            .clone()
+            .map(|iam| RdsIamAuth::new(iam.profile, iam.region)),
+    )
+    .await
+    .map_err(|e| e.message)?;
+
+    if let Some(schema) = conn.preferences.default_schema.as_deref() {
+        db_conn
+            .set_default_schema(schema)
+            .await
+            .map_err(|e| e.message)?;
+    }
+
+    let mut active = active_conn.lock().await;
+    *active = Some(db_conn.clone());
+    *session.lock().await = new_session.clone();
+    drop(active);
+    if conn.id.is_empty() {
+        // Unsaved connections share a slot, so files watched on the previous one stop
+        watches.stop_connection(window.label(), "");
+    }
+    sessions.select(window.label(), &conn.id);
+    spawn_keepalive(
+        window.clone(),
+        WindowSession {
+            connection: active_conn.clone(),
+            session: session.clone(),
+        },
+        db_conn.clone(),
+    );
+
+    let database = db_conn.get_current_database().await.ok();
+    emit_connection_event(&window, CONNECTED_EVENT, &new_session, database);
+
+    if !conn.id.is_empty() {
+        if let Err(e) = store.record_connection_use(&conn.id) {
+            warn!("Failed to record connection use for {}: {}", conn.name, e);
+        }
+        spawn_metadata_prefetch(
+            app,
+            schema_cache.inner().clone(),
+            active_conn.clone(),
+            conn.id.clone(),
+            db_conn,
+        );
+    }
+
+    debug!("Connected to database: {}", conn.name);
+    Ok(())
+}
+
+/// Applies the connection's query timeout preference to a driver call. Errors are prefixed
+/// with the connection's environment tag, e.g. `[production]`.
+async fn with_query_timeout<T>(
+    prefs: &ConnectionPreferences,
+    execution: impl Future<Output = DbResult<T>>,
+) -> Result<T, String> {
+    let result = match prefs.query_timeout_secs {
+        Some(secs) => match tokio::time::timeout(Duration::from_secs(secs), execution).await {
+            Ok(result) => result.map_err(|e| e.message),
+            Err(_) => Err(format!("Query timed out after {} seconds", secs)),
+        },
+        None => execution.await.map_err(|e| e.message),
+    };
+    result.map_err(|message| match prefs.environment {
+        Some(environment) => format!("[{}] {}", environment.as_str(), message),
+        None => message,
+    })
+}
+
+/// Audits destructive statements, records slow queries, drops cached query results after
+/// writes and cached schema metadata after schema changes.
+#[allow(clippy::too_many_arguments)]
+async fn after_statement(
+    audit_log: &AuditLog,
+    slow_log: &Arc<SlowQueryLog>,
+    schema_cache: &SchemaCache,
+    query_cache: &QueryCache,
+    session: &SessionInfo,
+    conn: &Arc<dyn DatabaseConnection>,
+    command: &str,
+    query: &str,
+    outcome: &Result<crate::db::QueryResult, String>,
+) {
+    if statement::is_destructive(query) {
+        let audit_outcome = match outcome {
+            Ok(result) => Ok(result.affected_rows),
+            Err(message) => Err(message.as_str()),
+        };
+        record_audit(audit_log, session, conn, command, query, audit_outcome).await;
+    }
+
+    if let Ok(result) = outcome {
+        if slow_log.is_slow(result.execution_time) {
+            // Explaining takes another round trip, so it doesn't hold up the result
+            tokio::spawn(record_slow_query(
+                slow_log.clone(),
+                session.clone(),
+                conn.clone(),
+                query.to_string(),
+                result.execution_time,
+            ));
+        }
+    }
+
+    // Failed scripts may still have applied some statements, so the outcome is not checked
+    if !statement::is_read_only(query, conn.dialect()) {
+        if let Some(connection_id) = &session.connection_id {
+            query_cache.invalidate(connection_id);
+        }
+    }
+
+    if statement::changes_schema(query) {
+        if let Some(connection_id) = &session.connection_id {
+            let database = conn.get_current_database().await.unwrap_or_default();
+            if let Err(e) = schema_cache.invalidate(connection_id, Some(&database)) {
+                warn!("Failed to clear schema cache: {}", e);
+            }
+        }
+    }
+}
+
+/// Drops cached query results and the current database's cached schema metadata after
+/// DDL run by a dedicated command rather than a user query.
+async fn clear_caches_after_ddl(
+    schema_cache: &SchemaCache,
+    query_cache: &QueryCache,
+    session: &SessionInfo,
+    conn: &Arc<dyn DatabaseConnection>,
+) {
+    let Some(connection_id) = &session.connection_id else {
+        return;
+    };
+    query_cache.invalidate(connection_id);
+    let database = conn.get_current_database().await.unwrap_or_default();
+    if let Err(e) = schema_cache.invalidate(connection_id, Some(&database)) {
+        warn!("Failed to clear schema cache: {}", e);
+    }
+}
+
+/// Notifies that a query run from `window` finished, if it ran long in the background.
+fn notify_query_finished(
+    window: &WebviewWindow,
+    started: Instant,
+    outcome: &Result<QueryResult, String>,
+) {
+    let outcome = match outcome {
+        Ok(result) => Ok(Some(
+            result.affected_rows.unwrap_or(result.row_count as u64),
+        )),
+        Err(e) => Err(e.as_str()),
+    };
+    notify::finished(window, notify::Operation::Query, started.elapsed(), outcome);
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_query(
+    query: String,
+    force: Option<bool>,
+    as_of: Option<String>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    slow_log: tauri::State<'_, Arc<SlowQueryLog>>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<QueryResult, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let as_of = as_of
+        .map(|as_of| time_travel::parse_as_of(&as_of))
+        .transpose()
+        .map_err(|e| e.message)?;
+    let started = Instant::now();
+    let result = run_query(
+        &query,
+        force.unwrap_or(false),
+        as_of,
+        &active_conn,
+        &session,
+        &audit_log,
+        &slow_log,
+        &schema_cache,
+        &query_cache,
+    )
+    .await;
+    notify_query_finished(&window, started, &result);
+    result
+}
+
+/// Like `execute_query`, but returns the result in the requested `format`.
+///
+/// Frontends that can decode MessagePack ask for `ResultFormat::Msgpack` to get a compact,
+/// columnar binary payload; JSON is the fallback.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_query_encoded(
+    query: String,
+    force: Option<bool>,
+    format: Option<ResultFormat>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    slow_log: tauri::State<'_, Arc<SlowQueryLog>>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<Response, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let started = Instant::now();
+    let result = run_query(
+        &query,
+        force.unwrap_or(false),
+        None,
+        &active_conn,
+        &session,
+        &audit_log,
+        &slow_log,
+        &schema_cache,
+        &query_cache,
+    )
+    .await;
+    notify_query_finished(&window, started, &result);
+    let result = result?;
+
+    match format.unwrap_or_default() {
+        ResultFormat::Json => {
+            let json = serde_json::to_string(&result).map_err(|e| e.to_string())?;
+            Ok(Response::new(InvokeResponseBody::Json(json)))
+        }
+        ResultFormat::Msgpack => {
+            let msgpack = ipc::encode_result_msgpack(result).map_err(|e| e.to_string())?;
+            Ok(Response::new(msgpack))
+        }
+    }
+}
+
+/// Summary of a query whose rows are kept in the backend `ResultStore`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredQueryResult {
+    /// Id to fetch rows with `get_result_rows`.
+    pub result_id: String,
+    /// The result without rows.
+    #[serde(flatten)]
+    pub summary: QueryResult,
+}
+
+/// Like `execute_query`, but keeps the rows in the backend and returns only a summary.
+///
+/// Rows are not limited to `MAX_QUERY_ROWS`: once they exceed the memory budget they are
+/// spilled to disk. The frontend fetches the rows it displays with `get_result_rows` and
+/// frees them with `release_result`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_query_deferred(
+    query: String,
+    force: Option<bool>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    slow_log: tauri::State<'_, Arc<SlowQueryLog>>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+    query_cache: tauri::State<'_, QueryCache>,
+    results: tauri::State<'_, ResultStore>,
+) -> Result<StoredQueryResult, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let session = session.lock().await.clone();
+    let prefs = &session.preferences;
+    let conn = match active_conn.lock().await.clone() {
+        Some(conn) => conn,
+        None => return Err("No active connection".to_string()),
+    };
+    check_statement_allowed(prefs, conn.dialect(), &query, force.unwrap_or(false))?;
+
+    let started = Instant::now();
+    let mut rows = results.builder();
+    let mut buffer_error = None;
+    let mut on_chunk = |chunk: RowChunk| {
+        for row in chunk.rows {
+            if prefs.row_limit.is_some_and(|limit| rows.len() >= limit) {
+                return false;
+            }
+            if let Err(e) = rows.push(row) {
+                buffer_error = Some(e);
+                return false;
+            }
+        }
+        true
+    };
+
+    let outcome = with_query_timeout(
+        prefs,
+        conn.execute_query_streaming(&query, DEFAULT_STREAM_CHUNK_SIZE, &mut on_chunk),
+    )
+    .await;
+    after_statement(
+        &audit_log,
+        &slow_log,
+        &schema_cache,
+        &query_cache,
+        &session,
+        &conn,
+        "execute_query_deferred",
+        &query,
+        &outcome,
+    )
+    .await;
+
+    let summary = match buffer_error {
+        Some(e) => Err(format!("Failed to store query result: {}", e)),
+        None => outcome.map(|result| QueryResult {
+            row_count: rows.len(),
+            ..result.into()
+        }),
+    };
+    notify_query_finished(&window, started, &summary);
+    let summary = summary?;
+    let result_id = results.insert(rows).map_err(|e| e.to_string())?;
+
+    debug!("Stored {} rows as result {}", summary.row_count, result_id);
+    Ok(StoredQueryResult { result_id, summary })
+}
+
+/// Returns up to `count` rows of a stored result, starting at `offset`.
+#[tauri::command]
+pub async fn get_result_rows(
+    results: tauri::State<'_, ResultStore>,
+    result_id: String,
+    offset: usize,
+    count: usize,
+) -> Result<Vec<serde_json::Value>, String> {
+    results
+        .get_rows(&result_id, offset, count)
+        .map_err(|e| format!("Failed to read stored result: {}", e))?
+        .ok_or_else(|| "Result is no longer available; run the query again".to_string())
+}
+
+#[tauri::command]
+pub async fn release_result(
+    results: tauri::State<'_, ResultStore>,
+    result_id: String,
+) -> Result<bool, String> {
+    Ok(results.remove(&result_id))
+}
+
+/// A server-side cursor opened with `open_cursor`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CursorInfo {
+    pub cursor_id: String,
+    pub columns: Vec<String>,
+}
+
+/// How often watched SQL files are checked for changes.
+const SQL_FILE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Emitted to the watching window each time a watched SQL file was run or validated.
+const SQL_FILE_CHANGED_EVENT: &str = "sql-file-changed";
+
+/// What to do when a watched SQL file changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchAction {
+    /// Run the file like `execute_query`.
+    #[default]
+    Execute,
+    /// Only check that the server accepts each statement, using EXPLAIN.
+    Validate,
+}
+
+/// Payload of `SQL_FILE_CHANGED_EVENT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SqlFileChange {
+    pub watch_id: String,
+    pub path: String,
+    pub sql: String,
+    /// Result of running the file with `WatchAction::Execute`.
+    pub result: Option<QueryResult>,
+    /// Why the file couldn't be read or run, or failed validation.
+    pub error: Option<String>,
+}
+
+/// A file watched with `watch_sql_file`.
+struct SqlFileWatch {
+    /// Label of the watching window.
+    window: String,
+    /// Connection the file runs on (its `WindowSessions` key), fixed when the watch starts.
+    connection_id: String,
+    task: tokio::task::JoinHandle<()>,
+}
+
+/// Files watched with `watch_sql_file`, by watch id.
+#[derive(Default)]
+pub struct SqlFileWatches(std::sync::Mutex<HashMap<String, SqlFileWatch>>);
+
+impl SqlFileWatches {
+    pub fn stop(&self, watch_id: &str) -> bool {
+        match self.0.lock().unwrap().remove(watch_id) {
+            Some(watch) => {
+                watch.task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops the watches of a closed window.
+    pub fn stop_window(&self, label: &str) {
+        self.stop_where(|watch| watch.window == label);
+    }
+
+    /// Stops the watches running on a connection of a window that was closed.
+    pub fn stop_connection(&self, label: &str, connection_id: &str) {
+        self.stop_where(|watch| watch.window == label && watch.connection_id == connection_id);
+    }
+
+    fn stop_where(&self, stopped: impl Fn(&SqlFileWatch) -> bool) {
+        self.0.lock().unwrap().retain(|_, watch| {
+            if stopped(watch) {
+                watch.task.abort();
+            }
+            !stopped(watch)
+        });
+    }
+}
+
+async fn file_modified(path: &str) -> std::io::Result<std::time::SystemTime> {
+    tokio::fs::metadata(path).await?.modified()
+}
+
+/// Re-runs (or re-validates) a SQL file with a connection of the window whenever it changes
+/// on disk, sending each outcome as `SQL_FILE_CHANGED_EVENT`. Returns an id for
+/// `unwatch_sql_file`.
+///
+/// The file keeps running on the connection that was current when the watch started (or
+/// `connection_id`), even if the window switches to another one, and the watch stops when
+/// that connection is closed.
+///
+/// For editing SQL in an external editor. The file is polled, like the connection sync
+/// file, since editors often save by replacing the file.
+#[tauri::command]
+pub async fn watch_sql_file(
+    path: String,
+    action: Option<WatchAction>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    watches: tauri::State<'_, SqlFileWatches>,
+) -> Result<String, String> {
+    let connection_id = connection_id.unwrap_or_else(|| sessions.current(window.label()));
+    let window_session = sessions.get(window.label(), Some(&connection_id));
+    if window_session.connection.lock().await.is_none() {
+        return Err("No active connection".to_string());
+    }
+    let mut modified = file_modified(&path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let watch_id = uuid::Uuid::new_v4().to_string();
+    let action = action.unwrap_or_default();
+
+    let app = window.app_handle().clone();
+    let label = window.label().to_string();
+    let id = watch_id.clone();
+    let task = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SQL_FILE_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            // The file may be missing for a moment while an editor replaces it
+            let Ok(current) = file_modified(&path).await else {
+                continue;
+            };
+            if current == modified {
+                continue;
+            }
+            modified = current;
+
+            let change = run_sql_file(&app, &window_session, &id, &path, action).await;
+            if let Err(e) = app.emit_to(label.as_str(), SQL_FILE_CHANGED_EVENT, change) {
+                warn!("Failed to emit SQL file change: {}", e);
+            }
+        }
+    });
+    watches.0.lock().unwrap().insert(
+        watch_id.clone(),
+        SqlFileWatch {
+            window: window.label().to_string(),
+            connection_id,
+            task,
+        },
+    );
+
+    debug!("Watching SQL file {} ({:?})", watch_id, action);
+    Ok(watch_id)
+}
+
+#[tauri::command]
+pub async fn unwatch_sql_file(
+    watches: tauri::State<'_, SqlFileWatches>,
+    watch_id: String,
+) -> Result<bool, String> {
+    Ok(watches.stop(&watch_id))
+}
+
+/// Reads and runs (or validates) a watched file on the connection the watch resolved when it
+/// started.
+async fn run_sql_file(
+    app: &AppHandle,
+    window_session: &WindowSession,
+    watch_id: &str,
+    path: &str,
+    action: WatchAction,
+) -> SqlFileChange {
+    let mut change = SqlFileChange {
+        watch_id: watch_id.to_string(),
+        path: path.to_string(),
+        sql: String::new(),
+        result: None,
+        error: None,
+    };
+    match tokio::fs::read_to_string(path).await {
+        Ok(sql) => change.sql = sql,
+        Err(e) => {
+            change.error = Some(format!("Failed to read {}: {}", path, e));
+            return change;
+        }
+    }
+
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = window_session;
+    let outcome = match action {
+        WatchAction::Execute => run_query(
+            &change.sql,
+            false,
+            None,
+            active_conn,
+            session,
+            &app.state::<Arc<AuditLog>>(),
+            &app.state::<Arc<SlowQueryLog>>(),
+            &app.state::<Arc<SchemaCache>>(),
+            &app.state::<QueryCache>(),
+        )
+        .await
+        .map(Some),
+        WatchAction::Validate => validate_sql(active_conn, &change.sql).await.map(|()| None),
+    };
+    match outcome {
+        Ok(result) => change.result = result,
+        Err(e) => change.error = Some(e),
+    }
+    change
+}
+
+/// Checks each statement that can be explained by asking the server for its plan, which
+/// parses and resolves names without running it.
+async fn validate_sql(active_conn: &ActiveConnection, sql: &str) -> Result<(), String> {
+    let conn = active_conn
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "No active connection".to_string())?;
+    for (i, statement) in statement::split_statements(sql).iter().enumerate() {
+        if !statement.is_explainable() {
+            continue;
+        }
+        if let Err(e) = conn
+            .execute_query(&format!("EXPLAIN {}", statement.sql))
+            .await
+        {
+            return Err(format!("Statement {}: {}", i + 1, e.message));
+        }
+    }
+    Ok(())
+}
+
+/// Opens a server-side cursor over a single SELECT, so tables of any size can be browsed
+/// with `fetch_cursor_rows` without truncation. Close it with `close_cursor`.
+///
+/// The query is checked against the connection's read-only and confirmation settings like
+/// `run_query`.
+#[tauri::command]
+pub async fn open_cursor(
+    query: String,
+    force: Option<bool>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    cursors: tauri::State<'_, CursorStore>,
+) -> Result<CursorInfo, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let statements = statement::split_statements(&query);
+    let sql = match statements.as_slice() {
+        [statement] if statement.is_query() => statement.sql.clone(),
+        _ => return Err("Only a single SELECT query can be browsed with a cursor".to_string()),
+    };
+
+    let prefs = session.lock().await.preferences.clone();
+    let conn = match active_conn.lock().await.clone() {
+        Some(conn) => conn,
+        None => return Err("No active connection".to_string()),
+    };
+    check_statement_allowed(&prefs, conn.dialect(), &sql, force.unwrap_or(false))?;
+
+    let cursor: Arc<dyn ResultCursor> = with_query_timeout(&prefs, conn.open_cursor(&sql))
+        .await?
+        .into();
+    let columns = cursor.columns().to_vec();
+    let (cursor_id, evicted) = cursors.insert(cursor);
+    if let Some(evicted) = evicted {
+        if let Err(e) = evicted.close().await {
+            warn!("Failed to close cursor: {}", e.message);
+        }
+    }
+
+    debug!("Opened cursor {}", cursor_id);
+    Ok(CursorInfo { cursor_id, columns })
+}
+
+/// Returns up to `count` rows of an open cursor, starting at `offset`.
+///
+/// MariaDB/MySQL cursors are forward-only: `offset` can't go back before rows already read.
+#[tauri::command]
+pub async fn fetch_cursor_rows(
+    cursors: tauri::State<'_, CursorStore>,
+    cursor_id: String,
+    offset: usize,
+    count: usize,
+) -> Result<Vec<serde_json::Value>, String> {
+    let cursor = cursors
+        .get(&cursor_id)
+        .ok_or_else(|| "Cursor is no longer open; run the query again".to_string())?;
+    cursor.fetch(offset, count).await.map_err(|e| e.message)
+}
+
+#[tauri::command]
+pub async fn close_cursor(
+    cursors: tauri::State<'_, CursorStore>,
+    cursor_id: String,
+) -> Result<bool, String> {
+    match cursors.remove(&cursor_id) {
+        Some(cursor) => {
+            cursor.close().await.map_err(|e| e.message)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Drops all cached query results, so the next run of each query reads fresh data.
+#[tauri::command]
+pub async fn clear_query_cache(query_cache: tauri::State<'_, QueryCache>) -> Result<(), String> {
+    query_cache.clear();
+    Ok(())
+}
+
+/// Runs a user query with the active connection's preferences applied.
+///
+/// When the connection has a result cache TTL, read-only queries are answered from the
+/// `QueryCache` if they ran recently. With `as_of`, the query reads the tables as they were
+/// at that point in time.
+#[allow(clippy::too_many_arguments)]
+async fn run_query(
+    query: &str,
+    force: bool,
+    as_of: Option<NaiveDateTime>,
+    active_conn: &ActiveConnection,
+    session: &ActiveSession,
+    audit_log: &AuditLog,
+    slow_log: &Arc<SlowQueryLog>,
+    schema_cache: &SchemaCache,
+    query_cache: &QueryCache,
+) -> Result<QueryResult, String> {
+    let session = session.lock().await.clone();
+    let prefs = &session.preferences;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => {
+            check_statement_allowed(prefs, conn.dialect(), query, force)?;
+
+            // Past data is read through a rewritten statement, cached under its own text
+            let as_of_query = as_of
+                .map(|as_of| conn.as_of_query(query, as_of))
+                .transpose()
+                .map_err(|e| e.message)?;
+            let run = as_of_query.as_deref().unwrap_or(query);
+
+            // Only saved connections have a stable id to key cached results by
+            let cache_key = match (&session.connection_id, prefs.result_cache_ttl_secs) {
+                (Some(connection_id), Some(ttl)) if statement::is_query(query) => {
+                    let database = conn.get_current_database().await.unwrap_or_default();
+                    Some((connection_id, database, Duration::from_secs(ttl)))
+                }
+                _ => None,
+            };
+            let cached = cache_key
+                .as_ref()
+                .and_then(|(connection_id, database, ttl)| {
+                    query_cache.get(connection_id, database, run, *ttl)
+                });
+
+            let (mut result, cached) = match cached {
+                Some(result) => (result, true),
+                None => {
+                    let outcome = with_query_timeout(prefs, conn.execute_query(run)).await;
+                    after_statement(
+                        audit_log,
+                        slow_log,
+                        schema_cache,
+                        query_cache,
+                        &session,
+                        conn,
+                        "execute_query",
+                        run,
+                        &outcome,
+                    )
+                    .await;
+
+                    let result = outcome?;
+                    if let Some((connection_id, database, _)) = &cache_key {
+                        query_cache.insert(connection_id, database, run, &result);
+                    }
+                    (result, false)
+                }
+            };
+
+            if let Some(limit) = prefs.row_limit {
+                if result.rows.len() > limit {
+                    result.rows.truncate(limit);
+                    result.truncated = true;
+                }
+            }
+
+            Ok(QueryResult {
+                cached,
+                ..result.into()
+            })
+        }
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Rows per `QUERY_CHUNK_EVENT` when the frontend doesn't specify a chunk size.
+const DEFAULT_STREAM_CHUNK_SIZE: usize = 500;
+
+/// Emitted to the window that started a streamed query for each chunk of its rows.
+const QUERY_CHUNK_EVENT: &str = "query-result-chunk";
+
+/// Emitted to the window that started a streamed query once it has finished, successfully
+/// or not.
+const QUERY_COMPLETE_EVENT: &str = "query-result-complete";
+
+/// Payload of `QUERY_CHUNK_EVENT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryChunkEvent {
+    pub result_id: String,
+    /// Position of the chunk in the result, starting at 0.
+    pub index: usize,
+    /// Column names; only set on the first chunk.
+    pub columns: Option<Vec<String>>,
+    pub rows: Vec<serde_json::Value>,
+}
+
+/// Payload of `QUERY_COMPLETE_EVENT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryCompleteEvent {
+    pub result_id: String,
+    /// Summary of the result (without rows) on success.
+    pub summary: Option<QueryResult>,
+    pub error: Option<String>,
+}
+
+/// Executes a query and streams its rows to the calling window as `QUERY_CHUNK_EVENT`s
+/// instead of returning them, keeping large results out of a single IPC payload.
+///
+/// `result_id` is chosen by the frontend so it can listen for the events before invoking.
+/// Other windows don't receive them, so ids only need to be unique within a window.
+/// Returns the same summary that is sent with `QUERY_COMPLETE_EVENT`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_query_streamed(
+    query: String,
+    force: Option<bool>,
+    result_id: String,
+    chunk_size: Option<usize>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    slow_log: tauri::State<'_, Arc<SlowQueryLog>>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<QueryResult, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let session = session.lock().await.clone();
+    let prefs = &session.preferences;
+    let conn = match active_conn.lock().await.clone() {
+        Some(conn) => conn,
+        None => return Err("No active connection".to_string()),
+    };
+    check_statement_allowed(prefs, conn.dialect(), &query, force.unwrap_or(false))?;
+
+    let started = Instant::now();
+    let mut index = 0;
+    let mut delivered = 0;
+    let mut on_chunk = |mut chunk: RowChunk| {
+        let mut keep_reading = true;
+        if let Some(limit) = prefs.row_limit {
+            let remaining = limit - delivered;
+            if chunk.rows.len() > remaining {
+                chunk.rows.truncate(remaining);
+                keep_reading = false;
+            }
+        }
+        delivered += chunk.rows.len();
+
+        if !chunk.rows.is_empty() || chunk.columns.is_some() {
+            let event = QueryChunkEvent {
+                result_id: result_id.clone(),
+                index,
+                columns: chunk.columns,
+                rows: chunk.rows,
+            };
+            if let Err(e) = window.emit_to(window.label(), QUERY_CHUNK_EVENT, event) {
+                warn!("Failed to emit result chunk: {}", e);
+                return false;
+            }
+            index += 1;
+        }
+        keep_reading
+    };
+
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_STREAM_CHUNK_SIZE);
+    let outcome = with_query_timeout(
+        prefs,
+        conn.execute_query_streaming(&query, chunk_size, &mut on_chunk),
+    )
+    .await;
+    after_statement(
+        &audit_log,
+        &slow_log,
+        &schema_cache,
+        &query_cache,
+        &session,
+        &conn,
+        "execute_query_streamed",
+        &query,
+        &outcome,
+    )
+    .await;
+
+    let outcome = outcome.map(|result| QueryResult {
+        row_count: delivered,
+        truncated: result.truncated || delivered < result.row_count,
+        ..result.into()
+    });
+    notify_query_finished(&window, started, &outcome);
+    let _ = window.emit_to(
+        window.label(),
+        QUERY_COMPLETE_EVENT,
+        QueryCompleteEvent {
+            result_id,
+            summary: outcome.as_ref().ok().cloned(),
+            error: outcome.as_ref().err().cloned(),
+        },
+    );
+
+    outcome
+}
+
+#[tauri::command]
+pub async fn list_tables(
+    refresh: Option<bool>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+) -> Result<Vec<String>, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let session = session.lock().await.clone();
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => {
+            cached_metadata(
+                &app,
+                &schema_cache,
+                &session,
+                conn,
+                cache_kinds::TABLES,
+                "",
+                refresh.unwrap_or(false),
+                |conn| async move { conn.list_tables().await },
+            )
+            .await
+        }
+        None => Err("No active connection".to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn list_databases(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<Vec<String>, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => {
+            let databases = conn.list_databases().await.map_err(|e| e.message)?;
+            Ok(databases)
+        }
+        None => Err("No active connection".to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn change_database(
+    database_name: String,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<(), String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => {
+            conn.change_database(&database_name)
+                .await
+                .map_err(|e| e.message)?;
+            let session = session.lock().await.clone();
+            emit_connection_event(
+                &window,
+                DATABASE_CHANGED_EVENT,
+                &session,
+                Some(database_name.clone()),
+            );
+            debug!("Changed database to: {}", database_name);
+            Ok(())
+        }
+        None => Err("No active connection".to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_current_database(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<String, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => {
+            let db_name = conn.get_current_database().await.map_err(|e| e.message)?;
+            Ok(db_name)
+        }
+        None => Err("No active connection".to_string()),
+    }
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_table_columns(
+    table_name: String,
+    schema: Option<String>,
+    refresh: Option<bool>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+) -> Result<Vec<TableColumn>, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let session = session.lock().await.clone();
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => {
+            let table = TableRef::new(schema, table_name);
+            let object_name = table.to_string();
+            cached_metadata(
+                &app,
+                &schema_cache,
+                &session,
+                conn,
+                cache_kinds::COLUMNS,
+                &object_name,
+                refresh.unwrap_or(false),
+                |conn| async move { conn.get_table_columns(&table).await },
+            )
+            .await
+        }
+        None => Err("No active connection".to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_table_relationships(
+    refresh: Option<bool>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+) -> Result<Vec<TableRelationship>, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let session = session.lock().await.clone();
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => {
+            cached_metadata(
+                &app,
+                &schema_cache,
+                &session,
+                conn,
+                cache_kinds::RELATIONSHIPS,
+                "",
+                refresh.unwrap_or(false),
+                |conn| async move { conn.get_table_relationships().await },
+            )
+            .await
+        }
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Whether an edit is permitted, with the reason when it isn't.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivilegeCheck {
+    pub allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Checks up front whether `operation` on a table is allowed, taking both the connection's
+/// read-only preference and the user's privileges into account, so the UI can disable
+/// editing instead of failing on execute.
+#[tauri::command]
+pub async fn check_table_privileges(
+    table_name: String,
+    schema: Option<String>,
+    operation: TableOperation,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<PrivilegeCheck, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let session = session.lock().await.clone();
+    if session.preferences.read_only && operation != TableOperation::Select {
+        return Ok(PrivilegeCheck {
+            allowed: false,
+            message: Some(READ_ONLY_ERROR.to_string()),
+        });
+    }
+
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => {
+            let table = TableRef::new(schema, table_name);
+            let allowed = conn
+                .check_privileges(&table, operation)
+                .await
+                .map_err(|e| e.message)?;
+            Ok(PrivilegeCheck {
+                allowed,
+                message: (!allowed).then(|| {
+                    format!(
+                        "You don't have the {} privilege on {}",
+                        operation.privilege(),
+                        table
+                    )
+                }),
+            })
+        }
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Statements listed by `get_query_statistics` when the frontend doesn't specify a limit.
+const DEFAULT_QUERY_STATISTICS_LIMIT: usize = 50;
+
+/// Returns the statements with the highest total execution time, in the same shape for
+/// PostgreSQL and MariaDB/MySQL.
+#[tauri::command]
+pub async fn get_query_statistics(
+    limit: Option<usize>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<Vec<QueryStatistic>, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => conn
+            .get_query_statistics(limit.unwrap_or(DEFAULT_QUERY_STATISTICS_LIMIT))
+            .await
+            // The hint says how to enable statistics on the server
+            .map_err(|e| match e.hint {
+                Some(hint) => format!("{}. {}", e.message, hint),
+                None => e.message,
+            }),
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Explains `query` without running it and suggests indexes for large tables it reads in
+/// full.
+#[tauri::command]
+pub async fn analyze_query_indexes(
+    query: String,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<Vec<IndexSuggestion>, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => conn.suggest_indexes(&query).await.map_err(|e| e.message),
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Reports wasted space per table in the current database, most wasted first, with the
+/// maintenance statement to run where it is worth it.
+#[tauri::command]
+pub async fn get_bloat_report(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<Vec<TableBloat>, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => conn.get_bloat_report().await.map_err(|e| e.message),
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Returns the most recent deadlocks the server reports, with the statements involved.
+#[tauri::command]
+pub async fn get_deadlocks(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<DeadlockReport, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => conn.get_deadlocks().await.map_err(|e| e.message),
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Lists transactions open in other sessions, oldest first, so long-running and idle
+/// transactions can be spotted.
+#[tauri::command]
+pub async fn get_active_transactions(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<Vec<ActiveTransaction>, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => conn.get_active_transactions().await.map_err(|e| e.message),
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Returns the figures for the server health dashboard in one call.
+#[tauri::command]
+pub async fn get_health_snapshot(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<HealthSnapshot, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => conn.get_health_snapshot().await.map_err(|e| e.message),
+        None => Err("No active connection".to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn disconnect_from_database(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    results: tauri::State<'_, ResultStore>,
+    cursors: tauri::State<'_, CursorStore>,
+    watches: tauri::State<'_, SqlFileWatches>,
+) -> Result<(), String> {
+    let label = window.label();
+    let window_session = sessions.get(label, connection_id.as_deref());
+    if let Some(closed_session) = close_window_connection(
+        label,
+        &window_session,
+        &sessions,
+        &results,
+        &cursors,
+        &watches,
+    )
+    .await?
+    {
+        emit_connection_event(&window, DISCONNECTED_EVENT, &closed_session, None);
+    }
+    Ok(())
+}
+
+/// Disconnects a connection of a window, returning its session if it was connected. SQL
+/// files watched on it stop being watched.
+///
+/// Stored results and cursors are shared by all windows, so they are only dropped once no
+/// window is connected anymore.
+pub async fn close_window_connection(
+    label: &str,
+    window_session: &WindowSession,
+    sessions: &WindowSessions,
+    results: &ResultStore,
+    cursors: &CursorStore,
+    watches: &SqlFileWatches,
+) -> Result<Option<SessionInfo>, String> {
+    let closed_session = std::mem::take(&mut *window_session.session.lock().await);
+    let conn = window_session.connection.lock().await.take();
+    if conn.is_some() {
+        let connection_id = closed_session.connection_id.as_deref().unwrap_or_default();
+        watches.stop_connection(label, connection_id);
+    }
+
+    let mut still_connected = false;
+    for other in sessions.all() {
+        still_connected |= other.connection.lock().await.is_some();
+    }
+    if !still_connected {
+        results.clear();
+        for cursor in cursors.take_all() {
+            if let Err(e) = cursor.close().await {
+                warn!("Failed to close cursor: {}", e.message);
+            }
+        }
+    }
+
+    match conn {
+        Some(conn) => {
+            conn.disconnect().await.map_err(|e| e.message)?;
+            debug!("Disconnected from database");
+            Ok(Some(closed_session))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Disconnects every connection of a window that was closed and forgets them.
+pub async fn close_window(app: &AppHandle, label: &str) {
+    app.state::<SqlFileWatches>().stop_window(label);
+    app.state::<ChangeTails>().stop_window(label);
+    // Closing a window discards its unsaved work; only crashes leave snapshots behind
+    if let Err(e) = app.state::<Arc<RecoveryStore>>().remove(label) {
+        warn!("Failed to remove recovery snapshot of {}: {}", label, e);
+    }
+    // Autosaves still waiting for their delay would be lost if this was the last window
+    if let Err(e) = app.state::<Arc<QueryDocumentStore>>().flush_all() {
+        warn!("Failed to save query documents: {}", e);
+    }
+    let sessions = app.state::<WindowSessions>();
+    let results = app.state::<ResultStore>();
+    let cursors = app.state::<CursorStore>();
+    let watches = app.state::<SqlFileWatches>();
+    for window_session in sessions.remove(label) {
+        if let Err(e) = close_window_connection(
+            label,
+            &window_session,
+            &sessions,
+            &results,
+            &cursors,
+            &watches,
+        )
+        .await
+        {
+            warn!("Failed to disconnect closed window {}: {}", label, e);
+        }
+    }
+}
+
+/// Label prefix of windows opened with `open_window`.
+const EXTRA_WINDOW_PREFIX: &str = "window-";
+
+/// Opens another app window with its own connection, e.g. to view production and staging
+/// side by side. Returns the new window's label.
+#[tauri::command]
+pub async fn open_window(app: AppHandle) -> Result<String, String> {
+    let label = format!("{}{}", EXTRA_WINDOW_PREFIX, uuid::Uuid::new_v4().simple());
+    tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::default())
+        .title("BloatSQL")
+        .inner_size(1400.0, 900.0)
+        .decorations(false)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    debug!("Opened window {}", label);
+    Ok(label)
+}
+
+#[tauri::command]
+pub async fn export_database(
+    options: ExportOptions,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<(), String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => {
+            let started = Instant::now();
+            let outcome = write_export(conn.as_ref(), &options).await;
+            notify::finished(
+                &window,
+                notify::Operation::Export,
+                started.elapsed(),
+                outcome.as_ref().map(|_| None).map_err(String::as_str),
+            );
+            debug!("Exported database to: {:?}", outcome?);
+            Ok(())
+        }
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Exports with `options` and writes the SQL to the file they name, returning its path.
+///
+/// Shared by the export dialog and headless exports from the command line.
+pub async fn write_export(
+    conn: &dyn DatabaseConnection,
+    options: &ExportOptions,
+) -> Result<std::path::PathBuf, String> {
+    let sql_content = conn
+        .export_database_with_options(
+            options.include_drop,
+            options.include_create,
+            &options.data_mode,
+            &options.selected_tables,
+            options.max_insert_size,
+        )
+        .await
+        .map_err(|e| e.message)?;
+
+    let file_path = std::path::Path::new(&options.output_path).join(&options.file_name);
+    tokio::fs::write(&file_path, sql_content)
+        .await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+    if options.write_manifest {
+        let manifest = export_manifest::write_manifest(conn, options, &file_path).await?;
+        debug!("Wrote export manifest to {:?}", manifest);
+    }
+    Ok(file_path)
+}
+
+/// Checks that the files of an export match the sizes and checksums in its manifest.
+#[tauri::command]
+pub async fn verify_export_manifest(path: String) -> Result<ManifestVerification, String> {
+    tokio::task::spawn_blocking(move || export_manifest::verify_manifest(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Export options saved under a name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportProfile {
+    pub name: String,
+    pub options: ExportOptions,
+}
+
+/// Saves export options as a profile, which can be run headless with `bloatsql export`.
+#[tauri::command]
+pub async fn save_export_profile(
+    profiles: tauri::State<'_, Arc<ExportProfileStore>>,
+    profile: ExportProfile,
+) -> Result<(), String> {
+    if profile.name.trim().is_empty() {
+        return Err("Export profile name cannot be empty".to_string());
+    }
+    profiles
+        .save(&profile.name, &profile.options)
+        .map_err(|e| e.to_string())?;
+
+    debug!("Saved export profile: {}", profile.name);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_export_profiles(
+    profiles: tauri::State<'_, Arc<ExportProfileStore>>,
+) -> Result<Vec<ExportProfile>, String> {
+    let profiles = profiles.get_all().map_err(|e| e.to_string())?;
+    Ok(profiles
+        .into_iter()
+        .map(|(name, options)| ExportProfile { name, options })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn delete_export_profile(
+    profiles: tauri::State<'_, Arc<ExportProfileStore>>,
+    name: String,
+) -> Result<bool, String> {
+    profiles.delete(&name).map_err(|e| e.to_string())
+}
+
+/// Saves a query or template to the query library, replacing the one with the same id.
+#[tauri::command]
+pub async fn save_query(
+    library: tauri::State<'_, Arc<QueryLibrary>>,
+    query: SavedQuery,
+) -> Result<(), String> {
+    if query.name.trim().is_empty() {
+        return Err("Query name cannot be empty".to_string());
+    }
+    library.save(&query).map_err(|e| e.to_string())?;
+
+    debug!("Saved query: {}", query.name);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_saved_queries(
+    library: tauri::State<'_, Arc<QueryLibrary>>,
+) -> Result<Vec<SavedQuery>, String> {
+    library.get_all().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_saved_query(
+    library: tauri::State<'_, Arc<QueryLibrary>>,
+    id: String,
+) -> Result<bool, String> {
+    library.delete(&id).map_err(|e| e.to_string())
+}
+
+/// Writes saved queries (the given ones, or all) to a `.bsqlq` file for sharing. Returns
+/// how many were written.
+#[tauri::command]
+pub async fn export_query_library(
+    library: tauri::State<'_, Arc<QueryLibrary>>,
+    path: String,
+    ids: Option<Vec<String>>,
+) -> Result<usize, String> {
+    let count = library
+        .export(Path::new(&path), ids.as_deref())
+        .map_err(|e| format!("Failed to export queries to {}: {}", path, e))?;
+
+    debug!("Exported {} saved queries to {}", count, path);
+    Ok(count)
+}
+
+/// Adds the queries of a `.bsqlq` file to the library and returns them.
+#[tauri::command]
+pub async fn import_query_library(
+    library: tauri::State<'_, Arc<QueryLibrary>>,
+    path: String,
+) -> Result<Vec<SavedQuery>, String> {
+    let queries = library
+        .import(Path::new(&path))
+        .map_err(|e| format!("Failed to import queries from {}: {}", path, e))?;
+
+    debug!("Imported {} saved queries from {}", queries.len(), path);
+    Ok(queries)
+}
+
+/// Stages an autosave of an editor tab, written once it hasn't changed for
+/// `AUTOSAVE_DELAY`. Call on every edit; only the last one in a burst is written.
+#[tauri::command]
+pub async fn autosave_query_document(
+    documents: tauri::State<'_, Arc<QueryDocumentStore>>,
+    document: QueryDocument,
+) -> Result<(), String> {
+    let documents = documents.inner().clone();
+    let id = document.id.clone();
+    let generation = documents.stage(document);
+    tokio::spawn(async move {
+        tokio::time::sleep(query_documents::AUTOSAVE_DELAY).await;
+        if let Err(e) = documents.flush_staged(&id, generation) {
+            warn!("Failed to autosave query document {}: {}", id, e);
+        }
+    });
+    Ok(())
+}
+
+/// Saves an editor tab right away, e.g. when it is renamed or reordered.
+#[tauri::command]
+pub async fn save_query_document(
+    documents: tauri::State<'_, Arc<QueryDocumentStore>>,
+    document: QueryDocument,
+) -> Result<(), String> {
+    documents.save(&document).map_err(|e| e.to_string())
+}
+
+/// Returns the open editor tabs to restore, in tab order.
+#[tauri::command]
+pub async fn get_query_documents(
+    documents: tauri::State<'_, Arc<QueryDocumentStore>>,
+) -> Result<Vec<QueryDocument>, String> {
+    documents.flush_all().map_err(|e| e.to_string())?;
+    documents.get_open().map_err(|e| e.to_string())
+}
+
+/// Returns recently closed editor tabs, most recent first, for reopening.
+#[tauri::command]
+pub async fn get_closed_query_documents(
+    documents: tauri::State<'_, Arc<QueryDocumentStore>>,
+) -> Result<Vec<QueryDocument>, String> {
+    documents.get_closed().map_err(|e| e.to_string())
+}
+
+/// Closes an editor tab. It can be reopened by saving it again.
+#[tauri::command]
+pub async fn close_query_document(
+    documents: tauri::State<'_, Arc<QueryDocumentStore>>,
+    id: String,
+) -> Result<bool, String> {
+    documents.close(&id).map_err(|e| e.to_string())
+}
+
+/// Deletes an editor tab and its revision history.
+#[tauri::command]
+pub async fn delete_query_document(
+    documents: tauri::State<'_, Arc<QueryDocumentStore>>,
+    id: String,
+) -> Result<bool, String> {
+    documents.delete(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_query_document_revisions(
+    documents: tauri::State<'_, Arc<QueryDocumentStore>>,
+    id: String,
+) -> Result<Vec<DocumentRevision>, String> {
+    documents.revisions(&id).map_err(|e| e.to_string())
+}
+
+/// Puts an earlier revision's SQL back into an editor tab and returns the tab.
+#[tauri::command]
+pub async fn restore_query_document_revision(
+    documents: tauri::State<'_, Arc<QueryDocumentStore>>,
+    id: String,
+    revision: i64,
+) -> Result<QueryDocument, String> {
+    documents
+        .restore_revision(&id, revision)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Revision {} of document {} not found", revision, id))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateCellResult {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<UpdateCellError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub executed_query: Option<String>,
+    /// Environment tag of the connection the cell was written through.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<ConnectionEnvironment>,
+}
+
+/// Detailed error information for cell update failures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateCellError {
     /// Human-readable error message.
     pub message: String,
     /// Database error code (e.g., PostgreSQL SQLSTATE).
@@ -360,102 +3123,2003 @@ pub struct UpdateCellError {
     pub column: String,
 }
 
-/// Updates a single cell value in a table.
-///
-/// Returns a structured result with detailed error information on failure.
+/// Updates a single cell value in a table.
+///
+/// Returns a structured result with detailed error information on failure.
+#[tauri::command]
+pub async fn update_cell(
+    request: UpdateCellRequest,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<UpdateCellResult, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    debug!(
+        "update_cell called for {}.{}",
+        request.table_name, request.column_name
+    );
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Ok(UpdateCellResult {
+            success: false,
+            error: Some(UpdateCellError {
+                message: READ_ONLY_ERROR.to_string(),
+                code: Some("READ_ONLY".to_string()),
+                detail: None,
+                hint: Some("Disable read-only mode in the connection preferences".to_string()),
+                table: request.table_name,
+                column: request.column_name,
+            }),
+            executed_query: None,
+            environment: session.preferences.environment,
+        });
+    }
+
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => {
+            let table = TableRef::new(request.schema.clone(), request.table_name.clone());
+            debug!(
+                "Executing update: table={}, column={}, pk_column={}, pk_value={}, new_value={:?}",
+                table,
+                request.column_name,
+                request.primary_key_column,
+                redact::data(&request.primary_key_value),
+                redact::data(&request.new_value)
+            );
+
+            let columns = conn.get_table_columns(&table).await.unwrap_or_default();
+            let column = columns.iter().find(|c| c.name == request.column_name);
+            // Writing a generated column fails with an error that doesn't say why
+            if let Some(column) = column.filter(|c| c.is_generated) {
+                return Ok(UpdateCellResult {
+                    success: false,
+                    error: Some(UpdateCellError {
+                        message: format!(
+                            "Column \"{}\" is generated and can't be edited",
+                            column.name
+                        ),
+                        code: Some("GENERATED_COLUMN".to_string()),
+                        detail: None,
+                        hint: column
+                            .generation_expression
+                            .as_ref()
+                            .map(|expression| format!("Its value is computed as {}", expression)),
+                        table: request.table_name,
+                        column: request.column_name,
+                    }),
+                    executed_query: None,
+                    environment: session.preferences.environment,
+                });
+            }
+            // ENUM and SET columns otherwise store a mismatch as '' outside strict mode
+            if let (Some(column), Some(value)) = (column, request.new_value.as_deref()) {
+                if let Err(message) = column.check_allowed_value(value) {
+                    return Ok(UpdateCellResult {
+                        success: false,
+                        error: Some(UpdateCellError {
+                            message,
+                            code: Some("INVALID_VALUE".to_string()),
+                            detail: None,
+                            hint: None,
+                            table: request.table_name,
+                            column: request.column_name,
+                        }),
+                        executed_query: None,
+                        environment: session.preferences.environment,
+                    });
+                }
+            }
+
+            match conn
+                .update_cell(
+                    &table,
+                    &request.column_name,
+                    request.new_value.as_deref(),
+                    &request.primary_key_column,
+                    &request.primary_key_value,
+                )
+                .await
+            {
+                Ok(executed_query) => {
+                    record_audit(
+                        &audit_log,
+                        &session,
+                        conn,
+                        "update_cell",
+                        &executed_query,
+                        Ok(None),
+                    )
+                    .await;
+                    if let Some(connection_id) = &session.connection_id {
+                        query_cache.invalidate(connection_id);
+                    }
+                    debug!(
+                        "Successfully updated cell in {}.{} where {} = {} to {:?}",
+                        request.table_name,
+                        request.column_name,
+                        request.primary_key_column,
+                        redact::data(&request.primary_key_value),
+                        redact::data(&request.new_value)
+                    );
+                    Ok(UpdateCellResult {
+                        success: true,
+                        error: None,
+                        executed_query: Some(executed_query),
+                        environment: session.preferences.environment,
+                    })
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to update {}.{}: {} (code: {:?}, detail: {:?}, hint: {:?})",
+                        request.table_name,
+                        request.column_name,
+                        e.message,
+                        e.code,
+                        // Details such as "Key (id)=(5) already exists" quote row data
+                        redact::data(&e.detail),
+                        e.hint
+                    );
+                    Ok(UpdateCellResult {
+                        success: false,
+                        error: Some(UpdateCellError {
+                            message: e.message,
+                            code: e.code,
+                            detail: e.detail,
+                            hint: e.hint,
+                            table: request.table_name,
+                            column: request.column_name,
+                        }),
+                        executed_query: None,
+                        environment: session.preferences.environment,
+                    })
+                }
+            }
+        }
+        None => {
+            tracing::error!("No active database connection");
+            Ok(UpdateCellResult {
+                success: false,
+                error: Some(UpdateCellError {
+                    message: "No active database connection".to_string(),
+                    code: Some("NO_CONNECTION".to_string()),
+                    detail: None,
+                    hint: Some("Please connect to a database first".to_string()),
+                    table: request.table_name,
+                    column: request.column_name,
+                }),
+                executed_query: None,
+                environment: session.preferences.environment,
+            })
+        }
+    }
+}
+
+/// Inserts rows pasted as tab- or comma-separated text into a table.
+///
+/// Values are checked against the table's column types first; if any don't fit, nothing is
+/// inserted and the errors are returned. Otherwise all rows are inserted in one transaction.
+#[tauri::command]
+pub async fn paste_rows(
+    request: PasteRowsRequest,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<PasteRowsResult, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+    let table = TableRef::new(request.schema, request.table_name);
+
+    let mut rows = bulk_insert::parse_delimited(&request.text);
+    let header = if request.has_header && !rows.is_empty() {
+        Some(rows.remove(0))
+    } else {
+        None
+    };
+    if rows.is_empty() {
+        return Err("No rows to insert".to_string());
+    }
+
+    let table_columns = conn
+        .get_table_columns(&table)
+        .await
+        .map_err(|e| e.message)?;
+    let pasted_columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let mapping = bulk_insert::map_columns(
+        &table_columns,
+        header.as_deref(),
+        request.columns.as_deref(),
+        pasted_columns,
+    )?;
+    let values = match bulk_insert::prepare_rows(&rows, &mapping) {
+        Ok(values) => values,
+        Err(errors) => {
+            return Ok(PasteRowsResult {
+                inserted: 0,
+                errors,
+            })
+        }
+    };
+    let columns: Vec<String> = mapping
+        .iter()
+        .flatten()
+        .map(|column| column.name.clone())
+        .collect();
+
+    debug!("Pasting {} rows into {}", values.len(), table);
+    let statement = format!(
+        "INSERT INTO {} ({}) -- {} pasted rows",
+        table,
+        columns.join(", "),
+        values.len()
+    );
+    let started = Instant::now();
+    let outcome = conn.insert_rows(&table, &columns, &values).await;
+    notify::finished(
+        &window,
+        notify::Operation::Import,
+        started.elapsed(),
+        outcome
+            .as_ref()
+            .map(|&n| Some(n))
+            .map_err(|e| e.message.as_str()),
+    );
+    match outcome {
+        Ok(inserted) => {
+            record_audit(
+                &audit_log,
+                &session,
+                conn,
+                "paste_rows",
+                &statement,
+                Ok(Some(inserted)),
+            )
+            .await;
+            if let Some(connection_id) = &session.connection_id {
+                query_cache.invalidate(connection_id);
+            }
+            Ok(PasteRowsResult {
+                inserted,
+                errors: Vec::new(),
+            })
+        }
+        Err(e) => {
+            record_audit(
+                &audit_log,
+                &session,
+                conn,
+                "paste_rows",
+                &statement,
+                Err(&e.message),
+            )
+            .await;
+            Err(e.message)
+        }
+    }
+}
+
+/// Calls a stored procedure or function with `params` (text values, or null, by parameter
+/// name), returning its result sets and OUT parameters.
+#[tauri::command]
+pub async fn call_routine(
+    routine: TableRef,
+    params: HashMap<String, Option<String>>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<RoutineResult, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    // Routines can modify data, so they're treated like any other write
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let mut names: Vec<&str> = params.keys().map(String::as_str).collect();
+    names.sort_unstable();
+    let statement = format!("CALL {}({})", routine, names.join(", "));
+    match conn.call_routine(&routine, &params).await {
+        Ok(result) => {
+            record_audit(
+                &audit_log,
+                &session,
+                conn,
+                "call_routine",
+                &statement,
+                Ok(None),
+            )
+            .await;
+            if let Some(connection_id) = &session.connection_id {
+                query_cache.invalidate(connection_id);
+            }
+            Ok(result)
+        }
+        Err(e) => {
+            record_audit(
+                &audit_log,
+                &session,
+                conn,
+                "call_routine",
+                &statement,
+                Err(&e.message),
+            )
+            .await;
+            Err(e.message)
+        }
+    }
+}
+
+/// Returns the statement that creates a stored procedure or function, for editing.
+#[tauri::command]
+pub async fn get_routine_definition(
+    routine: TableRef,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<String, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => conn
+            .get_routine_definition(&routine)
+            .await
+            .map_err(|e| e.message),
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Creates or replaces stored procedures and functions from edited source. `replaces` is
+/// the routine the source was loaded from, if any.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn save_routine_definition(
+    source: String,
+    replaces: Option<TableRef>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+) -> Result<(), String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let outcome = conn
+        .save_routine_definition(replaces.as_ref(), &source)
+        .await
+        .map_err(|e| e.message);
+    let audit_outcome = outcome.as_ref().map(|_| None).map_err(String::as_str);
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "save_routine_definition",
+        &source,
+        audit_outcome,
+    )
+    .await;
+
+    // A failed save may still have dropped or created routines
+    clear_caches_after_ddl(&schema_cache, &query_cache, &session, conn).await;
+    outcome
+}
+
+/// Returns the query a view is defined by, for editing.
+#[tauri::command]
+pub async fn get_view_definition(
+    name: TableRef,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<String, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => conn.get_view_definition(&name).await.map_err(|e| e.message),
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Redefines the view `name` as `new_sql`, which must be a single query.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn alter_view(
+    name: TableRef,
+    new_sql: String,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+) -> Result<(), String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let query = match statement::split_statements(&new_sql).as_slice() {
+        [query] if query.is_query() => query.sql.clone(),
+        _ => return Err("A view must be defined by a single SELECT statement".to_string()),
+    };
+    let audited = format!("CREATE OR REPLACE VIEW {} AS {}", name, query);
+    match conn.alter_view(&name, &query).await {
+        Ok(()) => {
+            record_audit(&audit_log, &session, conn, "alter_view", &audited, Ok(None)).await;
+            clear_caches_after_ddl(&schema_cache, &query_cache, &session, conn).await;
+            Ok(())
+        }
+        Err(e) => {
+            record_audit(
+                &audit_log,
+                &session,
+                conn,
+                "alter_view",
+                &audited,
+                Err(&e.message),
+            )
+            .await;
+            Err(e.message)
+        }
+    }
+}
+
+/// Creates a trigger from a spec, rendered as DDL for the connection's dialect.
+#[tauri::command]
+pub async fn create_trigger(
+    spec: TriggerSpec,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+) -> Result<(), String> {
+    let spec = &spec;
+    run_trigger_ddl(
+        "create_trigger",
+        |conn| conn.trigger_ddl(spec),
+        sessions.get(window.label(), connection_id.as_deref()),
+        &audit_log,
+        &query_cache,
+        &schema_cache,
+        |conn, ddl| async move { conn.create_trigger(spec).await.map(|()| ddl) },
+    )
+    .await
+}
+
+/// Drops the trigger `name` on `table`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn drop_trigger(
+    name: String,
+    table: TableRef,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+) -> Result<(), String> {
+    let (name, table) = (&name, &table);
+    run_trigger_ddl(
+        "drop_trigger",
+        |conn| conn.drop_trigger_ddl(name, table),
+        sessions.get(window.label(), connection_id.as_deref()),
+        &audit_log,
+        &query_cache,
+        &schema_cache,
+        |conn, _| async move { conn.drop_trigger(name, table).await },
+    )
+    .await
+}
+
+/// Runs trigger DDL on a window's connection, refusing in read-only sessions. `run` gets
+/// the statement `ddl` renders for the connection and returns the DDL it ran, which is
+/// audited; a failure is audited with the rendered statement.
+async fn run_trigger_ddl<D, F, Fut>(
+    command: &str,
+    ddl: D,
+    window_session: WindowSession,
+    audit_log: &AuditLog,
+    query_cache: &QueryCache,
+    schema_cache: &SchemaCache,
+    run: F,
+) -> Result<(), String>
+where
+    D: FnOnce(&Arc<dyn DatabaseConnection>) -> DbResult<String>,
+    F: FnOnce(Arc<dyn DatabaseConnection>, String) -> Fut,
+    Fut: Future<Output = DbResult<String>>,
+{
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = window_session;
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let statement = ddl(conn).map_err(|e| e.message)?;
+    match run(conn.clone(), statement.clone()).await {
+        Ok(executed) => {
+            record_audit(audit_log, &session, conn, command, &executed, Ok(None)).await;
+            clear_caches_after_ddl(schema_cache, query_cache, &session, conn).await;
+            Ok(())
+        }
+        Err(e) => {
+            record_audit(
+                audit_log,
+                &session,
+                conn,
+                command,
+                &statement,
+                Err(&e.message),
+            )
+            .await;
+            Err(e.message)
+        }
+    }
+}
+
+/// Sets the next value of a table's identity or AUTO_INCREMENT column; without `value`,
+/// to one past the largest value in use. Returns the next value.
+#[tauri::command]
+pub async fn reset_identity(
+    table: TableRef,
+    value: Option<i64>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+) -> Result<i64, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let outcome = conn.reset_identity(&table, value).await;
+    let statement = match outcome.as_ref().ok().copied().or(value) {
+        Some(next) => format!("-- reset identity of {} to {}", table, next),
+        None => format!("-- reset identity of {}", table),
+    };
+    let audit_outcome = outcome
+        .as_ref()
+        .map(|_| None)
+        .map_err(|e| e.message.as_str());
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "reset_identity",
+        &statement,
+        audit_outcome,
+    )
+    .await;
+    outcome.map_err(|e| e.message)
+}
+
+/// Returns the login user, the current role and the roles the session may switch to.
+#[tauri::command]
+pub async fn get_roles(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<SessionRoles, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => conn.get_roles().await.map_err(|e| e.message),
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Runs the window's statements as `role` (`SET ROLE`), or as the login user for `None`,
+/// to see what an application role can access.
+#[tauri::command]
+pub async fn set_role(
+    role: Option<String>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<(), String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let session = session.lock().await.clone();
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let statement = match &role {
+        Some(role) => format!("SET ROLE {}", role),
+        None => "RESET ROLE".to_string(),
+    };
+    let outcome = conn.set_role(role.as_deref()).await.map_err(|e| e.message);
+    let audit_outcome = outcome.as_ref().map(|_| None).map_err(String::as_str);
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "set_role",
+        &statement,
+        audit_outcome,
+    )
+    .await;
+    // Cached results were read with the previous role's privileges
+    if let Some(connection_id) = &session.connection_id {
+        query_cache.invalidate(connection_id);
+    }
+    outcome
+}
+
+/// Returns the session variables whose name contains `filter`, or all of them.
+#[tauri::command]
+pub async fn get_session_variables(
+    filter: Option<String>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<Vec<SessionVariable>, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
+        Some(conn) => conn
+            .get_session_variables(filter.as_deref().filter(|f| !f.is_empty()))
+            .await
+            .map_err(|e| e.message),
+        None => Err("No active connection".to_string()),
+    }
+}
+
+/// Sets a session variable such as `work_mem` or `sql_mode` for the window's connection.
+#[tauri::command]
+pub async fn set_session_variable(
+    name: String,
+    value: String,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<(), String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let session = session.lock().await.clone();
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let statement = format!("SET {} = {}", name, value);
+    let outcome = conn
+        .set_session_variable(&name, &value)
+        .await
+        .map_err(|e| e.message);
+    let audit_outcome = outcome.as_ref().map(|_| None).map_err(String::as_str);
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "set_session_variable",
+        &statement,
+        audit_outcome,
+    )
+    .await;
+    // Cached results were read with the previous settings (search_path, time zone, sql_mode)
+    if outcome.is_ok() {
+        if let Some(connection_id) = &session.connection_id {
+            query_cache.invalidate(connection_id);
+        }
+    }
+    outcome
+}
+
+/// Aggregates a table by the given columns and time bucket, returning chart series.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn aggregate_table(
+    table: TableRef,
+    group_by: Vec<String>,
+    aggregates: Vec<Aggregate>,
+    filters: Vec<Filter>,
+    bucket: Option<TimeBucket>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<ChartData, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let request = AggregateRequest {
+        table,
+        group_by,
+        aggregates,
+        filters,
+        bucket,
+    };
+    conn.aggregate_table(&request).await.map_err(|e| e.message)
+}
+
+/// Lists the text search configurations available for full-text searches (PostgreSQL).
+#[tauri::command]
+pub async fn get_text_search_configs(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<Vec<String>, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    conn.get_text_search_configs().await.map_err(|e| e.message)
+}
+
+/// Generates the SQL for a full-text search without running it.
+#[tauri::command]
+pub async fn build_text_search(
+    request: TextSearchRequest,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<String, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    conn.build_text_search(&request)
+        .await
+        .map_err(|e| e.message)
+}
+
+/// Runs a full-text search like a query typed by the user, best matches first.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn text_search(
+    request: TextSearchRequest,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    slow_log: tauri::State<'_, Arc<SlowQueryLog>>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<QueryResult, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+    let query = conn
+        .build_text_search(&request)
+        .await
+        .map_err(|e| e.message)?;
+
+    let started = Instant::now();
+    let result = run_query(
+        &query,
+        false,
+        None,
+        &active_conn,
+        &session,
+        &audit_log,
+        &slow_log,
+        &schema_cache,
+        &query_cache,
+    )
+    .await;
+    notify_query_finished(&window, started, &result);
+    result
+}
+
+/// Runs a random sample of a table's rows, for a quick look at huge tables.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn sample_rows(
+    table: TableRef,
+    n: usize,
+    method: Option<SampleMethod>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    slow_log: tauri::State<'_, Arc<SlowQueryLog>>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<QueryResult, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+    let query = conn
+        .build_sample_query(&table, n, method.unwrap_or_default())
+        .await
+        .map_err(|e| e.message)?;
+
+    let started = Instant::now();
+    let result = run_query(
+        &query,
+        false,
+        None,
+        &active_conn,
+        &session,
+        &audit_log,
+        &slow_log,
+        &schema_cache,
+        &query_cache,
+    )
+    .await;
+    notify_query_finished(&window, started, &result);
+    result
+}
+
+/// Returns whether a table keeps the history of its rows, so it can be browsed `as_of` a
+/// point in time.
+#[tauri::command]
+pub async fn get_time_travel_support(
+    table: TableRef,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<bool, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+    conn.supports_time_travel(&table)
+        .await
+        .map_err(|e| e.message)
+}
+
+/// Runs `SELECT *` on a table, as it is now or, with `as_of`, as it was at that point in
+/// time.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn browse_table(
+    table: TableRef,
+    as_of: Option<String>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    slow_log: tauri::State<'_, Arc<SlowQueryLog>>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<QueryResult, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+    let query = format!("SELECT * FROM {}", conn.dialect().qualified_name(&table));
+    let as_of = match as_of {
+        Some(as_of) => {
+            let as_of = time_travel::parse_as_of(&as_of).map_err(|e| e.message)?;
+            // Tables without history would silently show their current rows
+            if !conn
+                .supports_time_travel(&table)
+                .await
+                .map_err(|e| e.message)?
+            {
+                return Err(format!("Table '{}' does not keep its history", table.name));
+            }
+            Some(as_of)
+        }
+        None => None,
+    };
+
+    let started = Instant::now();
+    let result = run_query(
+        &query,
+        false,
+        as_of,
+        &active_conn,
+        &session,
+        &audit_log,
+        &slow_log,
+        &schema_cache,
+        &query_cache,
+    )
+    .await;
+    notify_query_finished(&window, started, &result);
+    result
+}
+
+/// Emitted to the tailing window with each batch of changes a change tail reads.
+const CHANGE_TAIL_EVENT: &str = "change-tail-changes";
+
+/// Emitted to the tailing window when a change tail has stopped.
+const CHANGE_TAIL_STOPPED_EVENT: &str = "change-tail-stopped";
+
+/// Payload of `CHANGE_TAIL_EVENT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeTailEvent {
+    pub tail_id: String,
+    pub changes: Vec<RowChange>,
+}
+
+/// Payload of `CHANGE_TAIL_STOPPED_EVENT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeTailStopped {
+    pub tail_id: String,
+    /// Why the tail stopped, unless `untail_changes` stopped it.
+    pub error: Option<String>,
+}
+
+/// Change tails started with `tail_changes`: tail id to the tailing window's label and the
+/// flag that stops the tail.
+#[derive(Default)]
+pub struct ChangeTails(std::sync::Mutex<HashMap<String, (String, Arc<AtomicBool>)>>);
+
+impl ChangeTails {
+    pub fn stop(&self, tail_id: &str) -> bool {
+        match self.0.lock().unwrap().remove(tail_id) {
+            Some((_, stop)) => {
+                stop.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops the tails of a closed window.
+    pub fn stop_window(&self, label: &str) {
+        self.0.lock().unwrap().retain(|_, (window, stop)| {
+            if window == label {
+                stop.store(true, Ordering::Relaxed);
+            }
+            window != label
+        });
+    }
+}
+
+/// Starts tailing committed changes to a table (experimental), sending them to the window
+/// as `CHANGE_TAIL_EVENT`s until `untail_changes` is called. Returns the tail's id.
+#[tauri::command]
+pub async fn tail_changes(
+    table: TableRef,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    tails: tauri::State<'_, ChangeTails>,
+) -> Result<String, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let Some(conn) = active_conn.lock().await.clone() else {
+        return Err("No active connection".to_string());
+    };
+
+    let tail_id = uuid::Uuid::new_v4().to_string();
+    let stop = Arc::new(AtomicBool::new(false));
+    tails.0.lock().unwrap().insert(
+        tail_id.clone(),
+        (window.label().to_string(), Arc::clone(&stop)),
+    );
+
+    debug!("Tailing changes to {} ({})", table, tail_id);
+
+    let app = window.app_handle().clone();
+    let label = window.label().to_string();
+    let id = tail_id.clone();
+    tokio::spawn(async move {
+        let mut on_changes = |changes: Vec<RowChange>| {
+            if !changes.is_empty() {
+                let event = ChangeTailEvent {
+                    tail_id: id.clone(),
+                    changes,
+                };
+                if let Err(e) = app.emit_to(label.as_str(), CHANGE_TAIL_EVENT, event) {
+                    warn!("Failed to emit table changes: {}", e);
+                }
+            }
+            !stop.load(Ordering::Relaxed)
+        };
+        let outcome = conn.tail_changes(&table, &mut on_changes).await;
+
+        app.state::<ChangeTails>().0.lock().unwrap().remove(&id);
+        if let Err(e) = &outcome {
+            warn!("Change tail on {} stopped: {}", table, e.message);
+        }
+        let stopped = ChangeTailStopped {
+            tail_id: id,
+            error: outcome.err().map(|e| e.message),
+        };
+        let _ = app.emit_to(label.as_str(), CHANGE_TAIL_STOPPED_EVENT, stopped);
+    });
+
+    Ok(tail_id)
+}
+
+#[tauri::command]
+pub async fn untail_changes(
+    tails: tauri::State<'_, ChangeTails>,
+    tail_id: String,
+) -> Result<bool, String> {
+    Ok(tails.stop(&tail_id))
+}
+
+/// Returns a table's row-level security status and policies, which explain queries that
+/// unexpectedly return no rows.
+#[tauri::command]
+pub async fn get_rls_policies(
+    table: TableRef,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<RlsStatus, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    conn.get_rls_policies(&table).await.map_err(|e| e.message)
+}
+
+/// Returns a page of the keys matching a glob pattern (Redis); pass the returned cursor
+/// back for the next page.
+#[tauri::command]
+pub async fn scan_keys(
+    pattern: Option<String>,
+    cursor: Option<u64>,
+    count: Option<usize>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<KeyPage, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    conn.scan_keys(
+        pattern.as_deref().unwrap_or("*"),
+        cursor.unwrap_or(0),
+        count.unwrap_or(KEY_PAGE_SIZE),
+    )
+    .await
+    .map_err(|e| e.message)
+}
+
+/// Returns a key's value and expiry (Redis).
+#[tauri::command]
+pub async fn get_key(
+    key: String,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<KeyValue, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    conn.get_key(&key).await.map_err(|e| e.message)
+}
+
+/// Replaces a key's value and expiry, creating the key if needed (Redis).
+#[tauri::command]
+pub async fn set_key(
+    value: KeyValue,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<(), String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let commands = conn.set_key(&value).await.map_err(|e| e.message)?;
+    record_audit(&audit_log, &session, conn, "set_key", &commands, Ok(None)).await;
+    if let Some(connection_id) = &session.connection_id {
+        query_cache.invalidate(connection_id);
+    }
+    Ok(())
+}
+
+/// Deletes a key (Redis).
+#[tauri::command]
+pub async fn delete_key(
+    key: String,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+) -> Result<(), String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let command = conn.delete_key(&key).await.map_err(|e| e.message)?;
+    record_audit(&audit_log, &session, conn, "delete_key", &command, Ok(None)).await;
+    if let Some(connection_id) = &session.connection_id {
+        query_cache.invalidate(connection_id);
+    }
+    Ok(())
+}
+
+/// Creates a database on the connected server.
+#[tauri::command]
+pub async fn create_database(
+    name: String,
+    encoding: Option<String>,
+    owner: Option<String>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+) -> Result<(), String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+    if name.trim().is_empty() {
+        return Err("Database name is required".to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let outcome = conn
+        .create_database(&name, encoding.as_deref(), owner.as_deref())
+        .await
+        .map_err(|e| e.message);
+    if let Ok(statement) = &outcome {
+        record_audit(
+            &audit_log,
+            &session,
+            conn,
+            "create_database",
+            statement,
+            Ok(None),
+        )
+        .await;
+    }
+    outcome.map(|_| ())
+}
+
+/// Drops a database. Always needs `force`, whatever the confirmation preference.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn drop_database(
+    name: String,
+    force: Option<bool>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+) -> Result<(), String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+    if !force.unwrap_or(false) {
+        return Err(format!(
+            "{}: DROP DATABASE removes \"{}\" and all its data; run it again with force to confirm",
+            CONFIRMATION_REQUIRED, name
+        ));
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let statement = conn.drop_database(&name).await.map_err(|e| e.message)?;
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "drop_database",
+        &statement,
+        Ok(None),
+    )
+    .await;
+    if let Some(connection_id) = &session.connection_id {
+        query_cache.invalidate(connection_id);
+        if let Err(e) = schema_cache.invalidate(connection_id, Some(&name)) {
+            warn!("Failed to clear schema cache: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Creates a database user. Returns the statement run, with the password redacted.
+#[tauri::command]
+pub async fn create_user(
+    user: DbUser,
+    password: Option<String>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+) -> Result<String, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let statement = conn
+        .create_user(&user, password.as_deref())
+        .await
+        .map_err(|e| e.message)?;
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "create_user",
+        &statement,
+        Ok(None),
+    )
+    .await;
+    Ok(statement)
+}
+
+/// Drops a database user. Returns the statement run.
 #[tauri::command]
-pub async fn update_cell(
-    request: UpdateCellRequest,
-    active_conn: tauri::State<'_, ActiveConnection>,
-) -> Result<UpdateCellResult, String> {
-    debug!("update_cell called with request: {:?}", request);
+pub async fn drop_user(
+    user: DbUser,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+) -> Result<String, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
 
-    let active = active_conn.lock().await;
-    match &*active {
-        Some(conn) => {
-            debug!(
-                "Executing update: table={}, column={}, pk_column={}, pk_value={}, new_value={:?}",
-                request.table_name,
-                request.column_name,
-                request.primary_key_column,
-                request.primary_key_value,
-                request.new_value
-            );
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
 
-            match conn
-                .update_cell(
-                    &request.table_name,
-                    &request.column_name,
-                    request.new_value.as_deref(),
-                    &request.primary_key_column,
-                    &request.primary_key_value,
-                )
-                .await
-            {
-                Ok(executed_query) => {
-                    debug!(
-                        "Successfully updated cell in {}.{} where {} = {} to {:?}",
-                        request.table_name,
-                        request.column_name,
-                        request.primary_key_column,
-                        request.primary_key_value,
-                        request.new_value
-                    );
-                    Ok(UpdateCellResult {
-                        success: true,
-                        error: None,
-                        executed_query: Some(executed_query),
-                    })
-                }
-                Err(e) => {
-                    tracing::error!(
-                        "Failed to update {}.{}: {} (code: {:?}, detail: {:?}, hint: {:?})",
-                        request.table_name,
-                        request.column_name,
-                        e.message,
-                        e.code,
-                        e.detail,
-                        e.hint
-                    );
-                    Ok(UpdateCellResult {
-                        success: false,
-                        error: Some(UpdateCellError {
-                            message: e.message,
-                            code: e.code,
-                            detail: e.detail,
-                            hint: e.hint,
-                            table: request.table_name,
-                            column: request.column_name,
-                        }),
-                        executed_query: None,
-                    })
-                }
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let statement = conn.drop_user(&user).await.map_err(|e| e.message)?;
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "drop_user",
+        &statement,
+        Ok(None),
+    )
+    .await;
+    Ok(statement)
+}
+
+/// Changes a database user's password. Returns the statement run, with the password
+/// redacted. With `update_saved_connection`, the saved connection's password is replaced
+/// too if it logs in as `user`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn change_db_user_password(
+    user: DbUser,
+    new_password: String,
+    update_saved_connection: Option<bool>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    sync: tauri::State<'_, Arc<ConnectionSync>>,
+) -> Result<String, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let statement = conn
+        .change_password(&user, &new_password)
+        .await
+        .map_err(|e| e.message)?;
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "change_db_user_password",
+        &statement,
+        Ok(None),
+    )
+    .await;
+
+    if update_saved_connection.unwrap_or(false) {
+        if let Some(connection_id) = &session.connection_id {
+            let stored = store
+                .get_connection(connection_id)
+                .map_err(|e| e.to_string())?;
+            if stored.is_some_and(|stored| stored.username == user.name) {
+                store
+                    .update_password(connection_id, &new_password)
+                    .map_err(|e| {
+                        format!(
+                            "Password changed, but the saved connection wasn't updated: {}",
+                            e
+                        )
+                    })?;
+                sync_connections(&sync);
             }
         }
-        None => {
-            tracing::error!("No active database connection");
-            Ok(UpdateCellResult {
-                success: false,
-                error: Some(UpdateCellError {
-                    message: "No active database connection".to_string(),
-                    code: Some("NO_CONNECTION".to_string()),
-                    detail: None,
-                    hint: Some("Please connect to a database first".to_string()),
-                    table: request.table_name,
-                    column: request.column_name,
-                }),
-                executed_query: None,
-            })
-        }
     }
+    Ok(statement)
+}
+
+/// Grants privileges to a database user. Returns the statement run.
+#[tauri::command]
+pub async fn grant_privileges(
+    grant: PrivilegeGrant,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+) -> Result<String, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let statement = conn.grant_privileges(&grant).await.map_err(|e| e.message)?;
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "grant_privileges",
+        &statement,
+        Ok(None),
+    )
+    .await;
+    Ok(statement)
+}
+
+/// Revokes privileges from a database user. Returns the statement run.
+#[tauri::command]
+pub async fn revoke_privileges(
+    grant: PrivilegeGrant,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+) -> Result<String, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let statement = conn
+        .revoke_privileges(&grant)
+        .await
+        .map_err(|e| e.message)?;
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "revoke_privileges",
+        &statement,
+        Ok(None),
+    )
+    .await;
+    Ok(statement)
+}
+
+/// Returns a table's storage engine and storage parameters.
+#[tauri::command]
+pub async fn get_table_options(
+    table: TableRef,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<TableOptions, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    conn.get_table_options(&table).await.map_err(|e| e.message)
+}
+
+/// Profiles a column for the data profiling panel.
+#[tauri::command]
+pub async fn get_column_stats(
+    table: TableRef,
+    column: String,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+) -> Result<ColumnStats, String> {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    conn.get_column_stats(&table, &column)
+        .await
+        .map_err(|e| e.message)
+}
+
+/// Creates the scratch schema for materialized query results.
+#[tauri::command]
+pub async fn create_scratch_schema(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+) -> Result<(), String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let outcome = conn.create_scratch_schema().await.map_err(|e| e.message);
+    let statement = format!("CREATE SCHEMA IF NOT EXISTS {}", SCRATCH_SCHEMA);
+    let audited = outcome.as_ref().map(|_| None).map_err(String::as_str);
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "create_scratch_schema",
+        &statement,
+        audited,
+    )
+    .await;
+    if outcome.is_ok() {
+        clear_caches_after_ddl(&schema_cache, &query_cache, &session, conn).await;
+    }
+    outcome
+}
+
+/// Drops the scratch schema and every table materialized into it.
+#[tauri::command]
+pub async fn drop_scratch_schema(
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+) -> Result<(), String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let outcome = conn.drop_scratch_schema().await.map_err(|e| e.message);
+    let statement = format!("DROP SCHEMA IF EXISTS {} CASCADE", SCRATCH_SCHEMA);
+    let audited = outcome.as_ref().map(|_| None).map_err(String::as_str);
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "drop_scratch_schema",
+        &statement,
+        audited,
+    )
+    .await;
+    if outcome.is_ok() {
+        clear_caches_after_ddl(&schema_cache, &query_cache, &session, conn).await;
+    }
+    outcome
+}
+
+/// Stores a query's result in a new table, in the scratch schema or as a temporary table,
+/// so it can be queried further without running the query again.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn materialize_query(
+    query: String,
+    table_name: String,
+    temporary: Option<bool>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    query_cache: tauri::State<'_, QueryCache>,
+    schema_cache: tauri::State<'_, Arc<SchemaCache>>,
+) -> Result<TableRef, String> {
+    let WindowSession {
+        connection: active_conn,
+        session,
+    } = sessions.get(window.label(), connection_id.as_deref());
+
+    let session = session.lock().await.clone();
+    if session.preferences.read_only {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+
+    let active = active_conn.lock().await.clone();
+    let Some(conn) = &active else {
+        return Err("No active connection".to_string());
+    };
+
+    let query = match statement::split_statements(&query).as_slice() {
+        [query] if query.is_query() => query.sql.clone(),
+        _ => return Err("Only a single SELECT statement can be materialized".to_string()),
+    };
+    let temporary = temporary.unwrap_or(false);
+    let outcome = conn
+        .materialize_query(&query, &table_name, temporary)
+        .await
+        .map_err(|e| e.message);
+    let statement = match &outcome {
+        Ok(table) => format!("CREATE TABLE {} AS {}", table, query),
+        Err(_) => format!("CREATE TABLE {} AS {}", table_name, query),
+    };
+    let audited = outcome.as_ref().map(|_| None).map_err(String::as_str);
+    record_audit(
+        &audit_log,
+        &session,
+        conn,
+        "materialize_query",
+        &statement,
+        audited,
+    )
+    .await;
+    if outcome.is_ok() {
+        clear_caches_after_ddl(&schema_cache, &query_cache, &session, conn).await;
+    }
+    outcome
+}
+
+/// Returns audit log entries matching the filter, newest first.
+#[tauri::command]
+pub async fn get_audit_log(
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    filter: Option<AuditLogFilter>,
+) -> Result<Vec<AuditEntry>, String> {
+    audit_log
+        .get_entries(&filter.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// Returns recorded slow queries, newest first, optionally for one saved connection only.
+#[tauri::command]
+pub async fn get_slow_queries(
+    slow_log: tauri::State<'_, Arc<SlowQueryLog>>,
+    connection_id: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<SlowQuery>, String> {
+    slow_log
+        .get_entries(connection_id.as_deref(), limit)
+        .map_err(|e| e.to_string())
+}
+
+/// Writes audit log entries matching the filter to `path` as CSV or JSON, oldest first,
+/// and returns how many were written.
+#[tauri::command]
+pub async fn export_audit_log(
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    filter: Option<AuditLogFilter>,
+    format: AuditExportFormat,
+    path: String,
+) -> Result<usize, String> {
+    let mut entries = audit_log
+        .get_entries(&filter.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    entries.reverse();
+
+    tokio::fs::write(&path, audit_log::format_entries(&entries, format))
+        .await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+
+    debug!("Exported {} audit log entries to {}", entries.len(), path);
+    Ok(entries.len())
+}
+
+#[tauri::command]
+pub async fn get_settings(
+    settings: tauri::State<'_, Arc<SettingsStore>>,
+) -> Result<AppSettings, String> {
+    settings.get().map_err(|e| e.to_string())
+}
+
+/// Enables mirroring non-secret connection fields to `path`, or disables it with `None`.
+///
+/// Connections already in the file are merged into the store before it is rewritten.
+#[tauri::command]
+pub async fn set_connection_sync_path(
+    settings: tauri::State<'_, Arc<SettingsStore>>,
+    sync: tauri::State<'_, Arc<ConnectionSync>>,
+    path: Option<String>,
+) -> Result<(), String> {
+    sync.set_path(path.as_ref().map(std::path::PathBuf::from))
+        .map_err(|e| e.to_string())?;
+
+    let mut app_settings = settings.get().map_err(|e| e.to_string())?;
+    app_settings.connection_sync_path = path;
+    settings.save(&app_settings).map_err(|e| e.to_string())?;
+
+    debug!(
+        "Connection sync file set to: {:?}",
+        app_settings.connection_sync_path
+    );
+    Ok(())
+}
+
+/// Sets the memory in MiB that stored query results may use before spilling to disk, or
+/// restores the default with `None`.
+#[tauri::command]
+pub async fn set_result_memory_budget(
+    settings: tauri::State<'_, Arc<SettingsStore>>,
+    results: tauri::State<'_, ResultStore>,
+    megabytes: Option<u64>,
+) -> Result<(), String> {
+    let mut app_settings = settings.get().map_err(|e| e.to_string())?;
+    app_settings.result_memory_budget_mb = megabytes;
+    settings.save(&app_settings).map_err(|e| e.to_string())?;
+
+    results.set_memory_budget(match megabytes {
+        Some(mb) => (mb as usize).saturating_mul(1024 * 1024),
+        None => DEFAULT_MEMORY_BUDGET,
+    });
+    debug!("Result memory budget set to {:?} MiB", megabytes);
+    Ok(())
+}
+
+/// Sets whether data values are left out of logs (see `redact`).
+#[tauri::command]
+pub async fn set_hide_data_in_logs(
+    settings: tauri::State<'_, Arc<SettingsStore>>,
+    hide: bool,
+) -> Result<(), String> {
+    let mut app_settings = settings.get().map_err(|e| e.to_string())?;
+    app_settings.hide_data_in_logs = hide;
+    settings.save(&app_settings).map_err(|e| e.to_string())?;
+
+    redact::set_hide_data_values(hide);
+    debug!("Hiding data in logs: {}", hide);
+    Ok(())
+}
+
+/// Changes the log level ("off", "error", "warn", "info", "debug" or "trace") and keeps it
+/// for later starts.
+#[tauri::command]
+pub async fn set_log_level(
+    settings: tauri::State<'_, Arc<SettingsStore>>,
+    level: String,
+) -> Result<(), String> {
+    logging::set_level(&level)?;
+
+    let mut app_settings = settings.get().map_err(|e| e.to_string())?;
+    app_settings.log_level = Some(level);
+    settings.save(&app_settings).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Sets the duration in milliseconds from which queries are recorded as slow and keeps it
+/// for later starts. `None` or 0 stops recording.
+#[tauri::command]
+pub async fn set_slow_query_threshold(
+    settings: tauri::State<'_, Arc<SettingsStore>>,
+    slow_log: tauri::State<'_, Arc<SlowQueryLog>>,
+    threshold_ms: Option<u64>,
+) -> Result<(), String> {
+    let threshold_ms = threshold_ms.filter(|&ms| ms > 0);
+    let mut app_settings = settings.get().map_err(|e| e.to_string())?;
+    app_settings.slow_query_threshold_ms = threshold_ms;
+    settings.save(&app_settings).map_err(|e| e.to_string())?;
+
+    slow_log.set_threshold(threshold_ms);
+    Ok(())
+}
+
+/// Sets how many seconds a query, export or import must run to send a notification when
+/// it finishes in the background, and keeps it for later starts. `None` restores the
+/// default and 0 turns notifications off.
+#[tauri::command]
+pub async fn set_notification_threshold(
+    settings: tauri::State<'_, Arc<SettingsStore>>,
+    notifier: tauri::State<'_, Notifier>,
+    threshold_secs: Option<u64>,
+) -> Result<(), String> {
+    let mut app_settings = settings.get().map_err(|e| e.to_string())?;
+    app_settings.notification_threshold_secs = threshold_secs;
+    settings.save(&app_settings).map_err(|e| e.to_string())?;
+
+    notifier.set_threshold(threshold_secs);
+    Ok(())
+}
+
+/// Returns where app data is stored and why that directory was chosen.
+#[tauri::command]
+pub async fn get_data_dir(data_dir: tauri::State<'_, DataDir>) -> Result<DataDir, String> {
+    Ok(data_dir.inner().clone())
+}
+
+/// Number of audit log entries searched for recent errors when generating a debug bundle.
+const DEBUG_BUNDLE_AUDIT_ENTRIES: u32 = 500;
+
+/// Writes a zip with app logs, recent errors, settings, version info and anonymized
+/// connection metadata to `path`, for attaching to bug reports.
+#[tauri::command]
+pub async fn generate_debug_bundle(
+    data_dir: tauri::State<'_, DataDir>,
+    settings: tauri::State<'_, Arc<SettingsStore>>,
+    store: tauri::State<'_, Arc<ConnectionsStore>>,
+    audit_log: tauri::State<'_, Arc<AuditLog>>,
+    path: String,
+) -> Result<(), String> {
+    let mut connections = store
+        .get_all_connections(ConnectionSort::default())
+        .map_err(|e| e.to_string())?;
+    connections.extend(store.get_deleted_connections().map_err(|e| e.to_string())?);
+
+    let mut errors = audit_log
+        .get_entries(&AuditLogFilter {
+            limit: Some(DEBUG_BUNDLE_AUDIT_ENTRIES),
+            ..Default::default()
+        })
+        .map_err(|e| e.to_string())?;
+    errors.retain(|entry| entry.error.is_some());
+
+    let bundle = DebugBundle {
+        data_dir: data_dir.inner().clone(),
+        settings: settings.get().map_err(|e| e.to_string())?,
+        connections,
+        errors,
+    };
+    let target = std::path::PathBuf::from(&path);
+    tokio::task::spawn_blocking(move || bundle.write(&target))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to write debug bundle: {}", e))?;
+
+    debug!("Wrote debug bundle to {}", path);
+    Ok(())
+}
+
+/// Saves a data directory override, or clears it when `path` is `None`.
+///
+/// Takes effect on next launch; the environment variable and portable mode take precedence.
+#[tauri::command]
+pub async fn set_data_dir_override(app: AppHandle, path: Option<String>) -> Result<(), String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    data_dir::set_override(&app_data_dir, path.as_deref().map(std::path::Path::new))
+        .map_err(|e| e.to_string())?;
+
+    debug!("Data directory override set to: {:?}", path);
+    Ok(())
 }
 
 #[tauri::command]
 pub async fn ping_connection(
-    active_conn: tauri::State<'_, ActiveConnection>,
+    connection_id: Option<String>,
+    window: WebviewWindow,
+    sessions: tauri::State<'_, WindowSessions>,
 ) -> Result<u64, String> {
-    let active = active_conn.lock().await;
-    match &*active {
+    let active_conn = sessions
+        .get(window.label(), connection_id.as_deref())
+        .connection;
+    let active = active_conn.lock().await.clone();
+    match &active {
         Some(conn) => {
             let start = std::time::Instant::now();
             conn.test_connection().await.map_err(|e| e.message)?;
@@ -477,3 +5141,77 @@ pub async fn write_text_file(path: String, content: String) -> Result<(), String
     debug!("Wrote text file: {}", path);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watch(watches: &SqlFileWatches, id: &str, window: &str, connection_id: &str) {
+        let task = tokio::spawn(std::future::pending());
+        watches.0.lock().unwrap().insert(
+            id.to_string(),
+            SqlFileWatch {
+                window: window.to_string(),
+                connection_id: connection_id.to_string(),
+                task,
+            },
+        );
+    }
+
+    fn watch_ids(watches: &SqlFileWatches) -> Vec<String> {
+        let mut ids: Vec<String> = watches.0.lock().unwrap().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_closing_connection_stops_only_its_watches() {
+        let watches = SqlFileWatches::default();
+        watch(&watches, "a", "main", "prod");
+        watch(&watches, "b", "main", "staging");
+        watch(&watches, "c", "window-2", "prod");
+        watch(&watches, "d", "main", "");
+
+        watches.stop_connection("main", "prod");
+        assert_eq!(watch_ids(&watches), ["b", "c", "d"]);
+
+        watches.stop_connection("main", "");
+        assert_eq!(watch_ids(&watches), ["b", "c"]);
+
+        watches.stop_window("window-2");
+        assert_eq!(watch_ids(&watches), ["b"]);
+        assert!(watches.stop("b"));
+        assert!(!watches.stop("b"));
+    }
+
+    #[tokio::test]
+    async fn test_stopped_watch_task_is_aborted() {
+        let watches = SqlFileWatches::default();
+        watch(&watches, "a", "main", "prod");
+        let task = watches.0.lock().unwrap()["a"].task.abort_handle();
+
+        watches.stop_connection("main", "prod");
+        tokio::task::yield_now().await;
+        assert!(task.is_finished());
+    }
+
+    #[tokio::test]
+    async fn test_watch_keeps_connection_after_window_switches() {
+        let sessions = WindowSessions::default();
+        assert_eq!(sessions.current("main"), "");
+        sessions.select("main", "prod");
+        let connection_id = sessions.current("main");
+        let watched = sessions.get("main", Some(&connection_id));
+
+        sessions.select("main", "staging");
+        assert_eq!(sessions.current("main"), "staging");
+        assert!(Arc::ptr_eq(
+            &sessions.get("main", Some(&connection_id)).connection,
+            &watched.connection
+        ));
+        assert!(!Arc::ptr_eq(
+            &sessions.get("main", None).connection,
+            &watched.connection
+        ));
+    }
+}
@@ -0,0 +1,390 @@
+//! Grouped aggregates of a table, returned as chart series. The query is built here from
+//! a request; drivers supply the dialect (quoting, parameters, time bucketing) and run it.
+
+use super::connection::{error_codes, QueryError, QueryResult, TableRef, MAX_QUERY_ROWS};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateFunction {
+    Count,
+    CountDistinct,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+/// An aggregate to compute per group. `column` may only be omitted for `Count`, which then
+/// counts rows.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    #[serde(default)]
+    pub column: Option<String>,
+    /// Series name; defaults to e.g. `sum(total)`.
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl Aggregate {
+    fn label(&self) -> String {
+        if let Some(label) = &self.label {
+            return label.clone();
+        }
+        let function = match self.function {
+            AggregateFunction::Count => "count",
+            AggregateFunction::CountDistinct => "count distinct",
+            AggregateFunction::Sum => "sum",
+            AggregateFunction::Avg => "avg",
+            AggregateFunction::Min => "min",
+            AggregateFunction::Max => "max",
+        };
+        format!("{}({})", function, self.column.as_deref().unwrap_or("*"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOperator {
+    Eq,
+    NotEq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    Like,
+    IsNull,
+    IsNotNull,
+}
+
+/// A condition rows must meet to be aggregated. Values are text, cast by the database.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Filter {
+    pub column: String,
+    pub operator: FilterOperator,
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketUnit {
+    Minute,
+    Hour,
+    Day,
+    /// Weeks start on Monday.
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl BucketUnit {
+    /// Field name for PostgreSQL's `date_trunc`.
+    pub fn name(self) -> &'static str {
+        match self {
+            BucketUnit::Minute => "minute",
+            BucketUnit::Hour => "hour",
+            BucketUnit::Day => "day",
+            BucketUnit::Week => "week",
+            BucketUnit::Month => "month",
+            BucketUnit::Quarter => "quarter",
+            BucketUnit::Year => "year",
+        }
+    }
+}
+
+/// Groups rows by the time `column` is truncated to `unit`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimeBucket {
+    pub column: String,
+    pub unit: BucketUnit,
+}
+
+/// What `DatabaseConnection::aggregate_table` computes.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AggregateRequest {
+    pub table: TableRef,
+    /// Columns to group by. Without a bucket the first is the X axis and the others split
+    /// the series; with one they all split the series.
+    #[serde(default)]
+    pub group_by: Vec<String>,
+    pub aggregates: Vec<Aggregate>,
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+    #[serde(default)]
+    pub bucket: Option<TimeBucket>,
+}
+
+/// Chart-ready aggregates: one value per label in every series.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChartData {
+    /// X axis values in order: time buckets, the first grouping column's values, or a
+    /// single null when nothing is grouped.
+    pub labels: Vec<serde_json::Value>,
+    pub series: Vec<ChartSeries>,
+    /// Whether groups past `MAX_QUERY_ROWS` were left out.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChartSeries {
+    pub name: String,
+    /// Aligned with `ChartData::labels`; None where the group has no rows or a NULL result.
+    pub values: Vec<Option<f64>>,
+}
+
+/// The SQL a driver contributes to an aggregate query.
+pub trait Dialect {
+    /// Quotes a column name.
+    fn quote(&self, column: &str) -> String;
+    /// Placeholder for the `index`th (1-based) parameter, compared with `column`.
+    fn parameter(&self, index: usize, column: &str) -> String;
+    /// Expression truncating the (quoted) `column` to the start of its `unit`.
+    fn bucket(&self, column: &str, unit: BucketUnit) -> String;
+}
+
+const X_ALIAS: &str = "_x";
+
+fn group_alias(index: usize) -> String {
+    format!("_g{}", index)
+}
+
+fn aggregate_alias(index: usize) -> String {
+    format!("_a{}", index)
+}
+
+/// Builds the aggregate query on `table` (quoted) and its parameters. Rows beyond
+/// `MAX_QUERY_ROWS` are fetched only to detect truncation.
+pub fn build_query(
+    request: &AggregateRequest,
+    table: &str,
+    dialect: &impl Dialect,
+) -> Result<(String, Vec<String>), QueryError> {
+    let error = |message: String| QueryError::with_code(message, error_codes::QUERY_ERROR);
+    if request.aggregates.is_empty() {
+        return Err(error("At least one aggregate is required".to_string()));
+    }
+
+    let mut select = Vec::new();
+    let mut groups = Vec::new();
+    let (x_group, series_groups) = match (&request.bucket, request.group_by.split_first()) {
+        (Some(bucket), _) => (
+            Some(dialect.bucket(&dialect.quote(&bucket.column), bucket.unit)),
+            request.group_by.as_slice(),
+        ),
+        (None, Some((first, rest))) => (Some(dialect.quote(first)), rest),
+        (None, None) => (None, &[][..]),
+    };
+    if let Some(x) = x_group {
+        select.push(format!("{} AS {}", x, dialect.quote(X_ALIAS)));
+        groups.push(x);
+    }
+    for (i, column) in series_groups.iter().enumerate() {
+        let column = dialect.quote(column);
+        select.push(format!("{} AS {}", column, dialect.quote(&group_alias(i))));
+        groups.push(column);
+    }
+    for (i, aggregate) in request.aggregates.iter().enumerate() {
+        let column = aggregate.column.as_deref().map(|c| dialect.quote(c));
+        let expression = match (aggregate.function, column) {
+            (AggregateFunction::Count, None) => "COUNT(*)".to_string(),
+            (_, None) => return Err(error(format!("{} needs a column", aggregate.label()))),
+            (AggregateFunction::Count, Some(c)) => format!("COUNT({})", c),
+            (AggregateFunction::CountDistinct, Some(c)) => format!("COUNT(DISTINCT {})", c),
+            (AggregateFunction::Sum, Some(c)) => format!("SUM({})", c),
+            (AggregateFunction::Avg, Some(c)) => format!("AVG({})", c),
+            (AggregateFunction::Min, Some(c)) => format!("MIN({})", c),
+            (AggregateFunction::Max, Some(c)) => format!("MAX({})", c),
+        };
+        select.push(format!(
+            "{} AS {}",
+            expression,
+            dialect.quote(&aggregate_alias(i))
+        ));
+    }
+
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    for filter in &request.filters {
+        let column = dialect.quote(&filter.column);
+        let operator = match filter.operator {
+            FilterOperator::IsNull => {
+                conditions.push(format!("{} IS NULL", column));
+                continue;
+            }
+            FilterOperator::IsNotNull => {
+                conditions.push(format!("{} IS NOT NULL", column));
+                continue;
+            }
+            FilterOperator::Eq => "=",
+            FilterOperator::NotEq => "<>",
+            FilterOperator::Lt => "<",
+            FilterOperator::Lte => "<=",
+            FilterOperator::Gt => ">",
+            FilterOperator::Gte => ">=",
+            FilterOperator::Like => "LIKE",
+        };
+        let Some(value) = &filter.value else {
+            return Err(error(format!("Filter on {} needs a value", filter.column)));
+        };
+        params.push(value.clone());
+        let parameter = dialect.parameter(params.len(), &filter.column);
+        conditions.push(format!("{} {} {}", column, operator, parameter));
+    }
+
+    let mut query = format!("SELECT {} FROM {}", select.join(", "), table);
+    if !conditions.is_empty() {
+        query.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+    }
+    if !groups.is_empty() {
+        let positions: Vec<String> = (1..=groups.len()).map(|i| i.to_string()).collect();
+        query.push_str(&format!(
+            " GROUP BY {} ORDER BY {}",
+            groups.join(", "),
+            positions.join(", ")
+        ));
+    }
+    query.push_str(&format!(" LIMIT {}", MAX_QUERY_ROWS + 1));
+    Ok((query, params))
+}
+
+/// Turns the rows of the query from `build_query` into chart series.
+pub fn to_chart(request: &AggregateRequest, result: &QueryResult) -> ChartData {
+    let grouped_x = request.bucket.is_some() || !request.group_by.is_empty();
+    let series_groups = if request.bucket.is_some() {
+        request.group_by.len()
+    } else {
+        request.group_by.len().saturating_sub(1)
+    };
+
+    let mut chart = ChartData {
+        truncated: result.rows.len() > MAX_QUERY_ROWS,
+        ..Default::default()
+    };
+    // (series name, value by label index)
+    let mut series: Vec<(String, Vec<Option<f64>>)> = Vec::new();
+    for row in result.rows.iter().take(MAX_QUERY_ROWS) {
+        let x = if grouped_x {
+            row.get(X_ALIAS).cloned().unwrap_or_default()
+        } else {
+            serde_json::Value::Null
+        };
+        let label = match chart.labels.iter().position(|label| *label == x) {
+            Some(index) => index,
+            None => {
+                chart.labels.push(x);
+                chart.labels.len() - 1
+            }
+        };
+        let group: Vec<String> = (0..series_groups)
+            .map(|i| match row.get(group_alias(i)) {
+                Some(serde_json::Value::String(s)) => s.clone(),
+                Some(serde_json::Value::Null) | None => "NULL".to_string(),
+                Some(value) => value.to_string(),
+            })
+            .collect();
+
+        for (i, aggregate) in request.aggregates.iter().enumerate() {
+            let name = match (group.is_empty(), request.aggregates.len()) {
+                (true, _) => aggregate.label(),
+                (false, 1) => group.join(" / "),
+                (false, _) => format!("{} / {}", group.join(" / "), aggregate.label()),
+            };
+            let index = match series.iter().position(|(n, _)| *n == name) {
+                Some(index) => index,
+                None => {
+                    series.push((name, Vec::new()));
+                    series.len() - 1
+                }
+            };
+            let values = &mut series[index].1;
+            if values.len() <= label {
+                values.resize(label + 1, None);
+            }
+            values[label] = row.get(aggregate_alias(i)).and_then(number);
+        }
+    }
+
+    chart.series = series
+        .into_iter()
+        .map(|(name, mut values)| {
+            values.resize(chart.labels.len(), None);
+            ChartSeries { name, values }
+        })
+        .collect();
+    chart
+}
+
+/// Aggregates come back as numbers, or as strings for exact decimals.
+fn number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::QueryTiming;
+    use serde_json::json;
+
+    struct TestDialect;
+
+    impl Dialect for TestDialect {
+        fn quote(&self, column: &str) -> String {
+            format!("\"{}\"", column)
+        }
+
+        fn parameter(&self, index: usize, _column: &str) -> String {
+            format!("${}", index)
+        }
+
+        fn bucket(&self, column: &str, unit: BucketUnit) -> String {
+            format!("date_trunc('{}', {})", unit.name(), column)
+        }
+    }
+
+    #[test]
+    fn test_build_query_and_chart() {
+        let request: AggregateRequest = serde_json::from_value(json!({
+            "table": "orders",
+            "group_by": ["region"],
+            "aggregates": [{ "function": "sum", "column": "total" }],
+            "filters": [{ "column": "status", "operator": "eq", "value": "paid" }],
+            "bucket": { "column": "created_at", "unit": "month" }
+        }))
+        .unwrap();
+        let (query, params) = build_query(&request, "\"orders\"", &TestDialect).unwrap();
+        assert_eq!(
+            query,
+            "SELECT date_trunc('month', \"created_at\") AS \"_x\", \"region\" AS \"_g0\", \
+             SUM(\"total\") AS \"_a0\" FROM \"orders\" WHERE \"status\" = $1 \
+             GROUP BY date_trunc('month', \"created_at\"), \"region\" ORDER BY 1, 2 LIMIT 10001"
+        );
+        assert_eq!(params, ["paid"]);
+
+        let result = QueryResult {
+            columns: vec!["_x".to_string(), "_g0".to_string(), "_a0".to_string()],
+            rows: vec![
+                json!({ "_x": "2024-01", "_g0": "eu", "_a0": "10.5" }),
+                json!({ "_x": "2024-01", "_g0": "us", "_a0": 3 }),
+                json!({ "_x": "2024-02", "_g0": "us", "_a0": 4 }),
+            ],
+            row_count: 3,
+            execution_time: 0,
+            truncated: false,
+            affected_rows: None,
+            timing: QueryTiming::default(),
+            column_metadata: Vec::new(),
+        };
+        let chart = to_chart(&request, &result);
+        assert_eq!(chart.labels, [json!("2024-01"), json!("2024-02")]);
+        assert_eq!(chart.series[0].name, "eu");
+        assert_eq!(chart.series[0].values, [Some(10.5), None]);
+        assert_eq!(chart.series[1].values, [Some(3.0), Some(4.0)]);
+    }
+}
@@ -0,0 +1,1121 @@
+//! Google BigQuery over its REST API (`bigquery.googleapis.com`), authenticated with a
+//! service account key. Datasets are the connection's databases; queries are GoogleSQL and
+//! run as jobs in the connection's project, with the current dataset as the default one.
+//!
+//! The key is given as the password, either as its JSON or as the path to the key file.
+//! The username picks the project to run jobs in (and bill), the key's own by default; the
+//! host and port are ignored.
+
+use super::column_metadata::ColumnMetadata;
+use super::connection::{
+    check_droppable_database, error_codes, ChunkedRows, DatabaseConnection, DbResult, QueryError,
+    QueryResult, QueryTimer, RowChunkSink, SslMode, TableColumn, TableOperation, TableRef,
+    TableRelationship, DEFAULT_QUERY_TIMEOUT, MAX_QUERY_ROWS, SERVER_TIMEOUT_GRACE,
+};
+use super::http::{HttpEndpoint, HttpResponse};
+use super::sampling::{self, SampleMethod};
+use super::sql_dialect::SqlDialect;
+use super::statement;
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use ring::rand::SystemRandom;
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+const DIALECT: SqlDialect = SqlDialect::BigQuery;
+
+const API_HOST: &str = "bigquery.googleapis.com";
+
+const SCOPE: &str = "https://www.googleapis.com/auth/bigquery";
+
+/// Token endpoint for keys that don't name one.
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+
+/// Lifetime asked for access tokens; Google allows at most an hour.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+
+/// Tokens are renewed this long before they expire.
+const TOKEN_MARGIN: Duration = Duration::from_secs(60);
+
+/// Rows asked for per page of results.
+const PAGE_SIZE: usize = 5_000;
+
+fn connection_error(message: impl Into<String>) -> QueryError {
+    QueryError::with_code(message, error_codes::CONNECTION_ERROR)
+}
+
+fn encode(segment: &str) -> String {
+    utf8_percent_encode(segment, NON_ALPHANUMERIC).to_string()
+}
+
+fn https(host: &str, port: u16) -> DbResult<HttpEndpoint> {
+    HttpEndpoint::new(host, port, true, SslMode::VerifyFull)
+}
+
+/// Google's error message from a failed API call. Expired or revoked credentials make the
+/// connection unusable, so they are connection errors.
+fn api_error(response: &HttpResponse) -> QueryError {
+    let body = response.json().unwrap_or_default();
+    let message = body
+        .pointer("/error/message")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("BigQuery request failed with HTTP {}", response.status));
+    if response.status == 401 {
+        connection_error(message)
+    } else {
+        QueryError::with_code(message, error_codes::QUERY_ERROR)
+    }
+}
+
+/// The parts of a service account key file used to sign in.
+#[derive(Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    #[serde(default)]
+    private_key_id: Option<String>,
+    #[serde(default)]
+    project_id: Option<String>,
+    #[serde(default)]
+    token_uri: Option<String>,
+}
+
+impl ServiceAccountKey {
+    /// Reads the key from the connection's password: the key's JSON, or the path to it.
+    fn load(password: &str) -> DbResult<Self> {
+        let json = if password.trim_start().starts_with('{') {
+            password.to_string()
+        } else {
+            std::fs::read_to_string(password.trim()).map_err(|e| {
+                connection_error(format!(
+                    "Could not read the service account key file: {}",
+                    e
+                ))
+            })?
+        };
+        serde_json::from_str(&json).map_err(|_| {
+            connection_error(
+                "The password must be a service account key (JSON) or the path to its file",
+            )
+        })
+    }
+}
+
+/// Signs in as a service account with the JWT bearer grant.
+struct Credentials {
+    client_email: String,
+    key_id: Option<String>,
+    key: RsaKeyPair,
+    token_uri: String,
+    token_endpoint: HttpEndpoint,
+    token_path: String,
+}
+
+impl Credentials {
+    fn new(key: &ServiceAccountKey) -> DbResult<Self> {
+        // The PKCS#8 key is the base64 between the PEM armor lines
+        let der: String = key
+            .private_key
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(der.trim())
+            .map_err(|_| connection_error("The service account private key is not valid PEM"))?;
+        let private_key = RsaKeyPair::from_pkcs8(&der).map_err(|e| {
+            connection_error(format!(
+                "The service account private key was rejected: {}",
+                e
+            ))
+        })?;
+
+        let token_uri = key
+            .token_uri
+            .clone()
+            .unwrap_or_else(|| DEFAULT_TOKEN_URI.to_string());
+        let url = url::Url::parse(&token_uri)
+            .ok()
+            .filter(|url| url.scheme() == "https")
+            .ok_or_else(|| connection_error(format!("Invalid token URI: {}", token_uri)))?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| connection_error(format!("Invalid token URI: {}", token_uri)))?;
+
+        Ok(Credentials {
+            client_email: key.client_email.clone(),
+            key_id: key.private_key_id.clone(),
+            key: private_key,
+            token_endpoint: https(host, url.port_or_known_default().unwrap_or(443))?,
+            token_path: url.path().to_string(),
+            token_uri,
+        })
+    }
+
+    /// A signed JWT asking for a BigQuery token, issued at `now` (Unix seconds).
+    fn assertion(&self, now: u64) -> DbResult<String> {
+        let base64 = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let header = json!({ "alg": "RS256", "typ": "JWT", "kid": self.key_id });
+        let claims = json!({
+            "iss": self.client_email,
+            "scope": SCOPE,
+            "aud": self.token_uri,
+            "iat": now,
+            "exp": now + TOKEN_LIFETIME.as_secs(),
+        });
+        let signing_input = format!(
+            "{}.{}",
+            base64.encode(header.to_string()),
+            base64.encode(claims.to_string())
+        );
+        let mut signature = vec![0; self.key.public().modulus_len()];
+        self.key
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                signing_input.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| connection_error("Could not sign the token request"))?;
+        Ok(format!("{}.{}", signing_input, base64.encode(signature)))
+    }
+
+    /// Exchanges a fresh assertion for an access token and its lifetime.
+    async fn fetch_token(&self) -> DbResult<(String, Duration)> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let body = format!(
+            "grant_type={}&assertion={}",
+            encode("urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            self.assertion(now)?
+        );
+        let response = self
+            .token_endpoint
+            .post(
+                &self.token_path,
+                &[(
+                    "Content-Type",
+                    "application/x-www-form-urlencoded".to_string(),
+                )],
+                body.into_bytes(),
+                DEFAULT_QUERY_TIMEOUT,
+            )
+            .await?;
+        let body = response.json()?;
+        if !response.is_success() {
+            let reason = body
+                .get("error_description")
+                .or_else(|| body.get("error"))
+                .and_then(Value::as_str)
+                .unwrap_or("no reason given");
+            return Err(connection_error(format!(
+                "Google sign-in failed: {}",
+                reason
+            )));
+        }
+        let token = body
+            .get("access_token")
+            .and_then(Value::as_str)
+            .ok_or_else(|| connection_error("Google sign-in returned no access token"))?;
+        let lifetime = body
+            .get("expires_in")
+            .and_then(Value::as_u64)
+            .map_or(TOKEN_LIFETIME, Duration::from_secs);
+        Ok((token.to_string(), lifetime))
+    }
+}
+
+/// A column of a table schema or query result.
+#[derive(Debug, Clone, Deserialize)]
+struct Field {
+    name: String,
+    #[serde(rename = "type")]
+    field_type: String,
+    #[serde(default)]
+    mode: Option<String>,
+    #[serde(default)]
+    fields: Vec<Field>,
+    #[serde(default, rename = "maxLength")]
+    max_length: Option<String>,
+    #[serde(default)]
+    precision: Option<String>,
+    #[serde(default)]
+    scale: Option<String>,
+    #[serde(default, rename = "defaultValueExpression")]
+    default_value_expression: Option<String>,
+}
+
+impl Field {
+    fn repeated(&self) -> bool {
+        self.mode.as_deref() == Some("REPEATED")
+    }
+
+    fn required(&self) -> bool {
+        self.mode.as_deref() == Some("REQUIRED")
+    }
+
+    /// The GoogleSQL name of the field's type, without `ARRAY` for repeated fields. The API
+    /// still reports some types by their legacy SQL names.
+    fn scalar_type(&self) -> String {
+        match self.field_type.as_str() {
+            "INTEGER" => "INT64".to_string(),
+            "FLOAT" => "FLOAT64".to_string(),
+            "BOOLEAN" => "BOOL".to_string(),
+            "RECORD" | "STRUCT" => format!(
+                "STRUCT<{}>",
+                self.fields
+                    .iter()
+                    .map(|field| format!(
+                        "{} {}",
+                        DIALECT.quote_identifier(&field.name),
+                        field.type_name()
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            other => other.to_string(),
+        }
+    }
+
+    /// The field's type, e.g. `ARRAY<STRUCT<x INT64>>`.
+    fn type_name(&self) -> String {
+        if self.repeated() {
+            format!("ARRAY<{}>", self.scalar_type())
+        } else {
+            self.scalar_type()
+        }
+    }
+
+    fn metadata(&self) -> ColumnMetadata {
+        ColumnMetadata {
+            name: self.name.clone(),
+            type_name: self.type_name(),
+            numeric: !self.repeated()
+                && matches!(
+                    self.field_type.as_str(),
+                    "INTEGER" | "INT64" | "FLOAT" | "FLOAT64" | "NUMERIC" | "BIGNUMERIC"
+                ),
+            nullable: Some(!self.required()),
+            precision: self.precision.as_deref().and_then(|p| p.parse().ok()),
+            scale: self.scale.as_deref().and_then(|s| s.parse().ok()),
+            ..Default::default()
+        }
+    }
+
+    /// Converts a cell of the API's `{"f": [{"v": ...}]}` rows to JSON: numbers and booleans
+    /// as such, records as objects and repeated fields as arrays. `NUMERIC` values stay
+    /// text so they aren't rounded, and `BYTES` are base64 like the other drivers'.
+    fn to_json(&self, value: &Value) -> Value {
+        if value.is_null() {
+            return Value::Null;
+        }
+        if self.repeated() {
+            let element = self.element();
+            return Value::Array(
+                cells(value)
+                    .iter()
+                    .map(|cell| element.to_json(&cell["v"]))
+                    .collect(),
+            );
+        }
+        if matches!(self.field_type.as_str(), "RECORD" | "STRUCT") {
+            return Value::Object(
+                self.fields
+                    .iter()
+                    .zip(cells(&value["f"]))
+                    .map(|(field, cell)| (field.name.clone(), field.to_json(&cell["v"])))
+                    .collect(),
+            );
+        }
+        let Some(text) = value.as_str() else {
+            return value.clone();
+        };
+        match self.field_type.as_str() {
+            "INTEGER" | "INT64" => text
+                .parse::<i64>()
+                .map_or_else(|_| value.clone(), Value::from),
+            "FLOAT" | "FLOAT64" => text
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map_or_else(|| value.clone(), Value::Number),
+            "BOOLEAN" | "BOOL" => Value::Bool(text == "true"),
+            "TIMESTAMP" => timestamp(text)
+                .map(|t| Value::String(t.format("%Y-%m-%d %H:%M:%S%.f").to_string()))
+                .unwrap_or_else(|| value.clone()),
+            _ => value.clone(),
+        }
+    }
+
+    /// Formats a cell as a GoogleSQL literal for exports.
+    fn literal(&self, value: &Value) -> String {
+        if value.is_null() {
+            return "NULL".to_string();
+        }
+        if self.repeated() {
+            let element = self.element();
+            let items: Vec<String> = cells(value)
+                .iter()
+                .map(|cell| element.literal(&cell["v"]))
+                .collect();
+            return format!("[{}]", items.join(", "));
+        }
+        if matches!(self.field_type.as_str(), "RECORD" | "STRUCT") {
+            let items: Vec<String> = self
+                .fields
+                .iter()
+                .zip(cells(&value["f"]))
+                .map(|(field, cell)| field.literal(&cell["v"]))
+                .collect();
+            return format!("STRUCT({})", items.join(", "));
+        }
+        let text = value
+            .as_str()
+            .map_or_else(|| value.to_string(), str::to_string);
+        match self.field_type.as_str() {
+            "INTEGER" | "INT64" | "BOOLEAN" | "BOOL" => text,
+            "FLOAT" | "FLOAT64" => match text.parse::<f64>() {
+                Ok(number) if number.is_finite() => text,
+                Ok(number) if number.is_nan() => "CAST('nan' AS FLOAT64)".to_string(),
+                Ok(number) if number > 0.0 => "CAST('inf' AS FLOAT64)".to_string(),
+                _ => "CAST('-inf' AS FLOAT64)".to_string(),
+            },
+            "NUMERIC" | "BIGNUMERIC" | "DATE" | "DATETIME" | "TIME" | "JSON" => {
+                format!("{} {}", self.field_type, DIALECT.quote_string(&text))
+            }
+            "TIMESTAMP" => format!("TIMESTAMP_MICROS({})", text),
+            "BYTES" => format!("FROM_BASE64({})", DIALECT.quote_string(&text)),
+            "GEOGRAPHY" => format!("ST_GEOGFROMTEXT({})", DIALECT.quote_string(&text)),
+            "STRING" => DIALECT.quote_string(&text),
+            _ => format!(
+                "CAST({} AS {})",
+                DIALECT.quote_string(&text),
+                self.scalar_type()
+            ),
+        }
+    }
+
+    /// An element of a repeated field.
+    fn element(&self) -> Field {
+        Field {
+            mode: Some("NULLABLE".to_string()),
+            ..self.clone()
+        }
+    }
+}
+
+fn cells(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// Timestamps are read as microseconds since the epoch (`useInt64Timestamp`).
+fn timestamp(micros: &str) -> Option<NaiveDateTime> {
+    DateTime::from_timestamp_micros(micros.parse().ok()?).map(|t| t.naive_utc())
+}
+
+fn row_to_json(fields: &[Field], row: &Value) -> Value {
+    let mut row_map = serde_json::Map::with_capacity(fields.len());
+    for (field, cell) in fields.iter().zip(cells(&row["f"])) {
+        row_map.insert(field.name.clone(), field.to_json(&cell["v"]));
+    }
+    Value::Object(row_map)
+}
+
+/// A query job, for fetching its results.
+struct Job {
+    id: String,
+    location: Option<String>,
+}
+
+/// One page of a query's results, with the raw API rows.
+struct ResultPage {
+    fields: Vec<Field>,
+    rows: Vec<Value>,
+    page_token: Option<String>,
+    affected_rows: Option<u64>,
+}
+
+impl ResultPage {
+    fn parse(response: &Value) -> Self {
+        ResultPage {
+            fields: response
+                .pointer("/schema/fields")
+                .and_then(|fields| serde_json::from_value(fields.clone()).ok())
+                .unwrap_or_default(),
+            rows: cells(&response["rows"]).to_vec(),
+            page_token: response
+                .get("pageToken")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            affected_rows: response
+                .get("numDmlAffectedRows")
+                .and_then(Value::as_str)
+                .and_then(|n| n.parse().ok()),
+        }
+    }
+
+    fn columns(&self) -> Vec<String> {
+        self.fields.iter().map(|field| field.name.clone()).collect()
+    }
+}
+
+pub struct BigQueryConnection {
+    api: HttpEndpoint,
+    credentials: Credentials,
+    project: String,
+    /// The default dataset of queries; empty when none is selected.
+    dataset: Mutex<String>,
+    /// The current access token and when it expires.
+    token: Mutex<Option<(String, Instant)>>,
+    /// How long a query job may run before it is cancelled.
+    query_timeout: Duration,
+}
+
+impl BigQueryConnection {
+    /// Signs in with the service account key in `password` and checks access to the
+    /// project with a query.
+    pub async fn new(
+        project: &str,
+        password: &str,
+        dataset: &str,
+        query_timeout: Option<Duration>,
+    ) -> DbResult<Self> {
+        let key = ServiceAccountKey::load(password)?;
+        let project = if project.is_empty() {
+            key.project_id.clone().ok_or_else(|| {
+                connection_error("The key names no project; enter one as the username")
+            })?
+        } else {
+            project.to_string()
+        };
+        let conn = BigQueryConnection {
+            api: https(API_HOST, 443)?,
+            credentials: Credentials::new(&key)?,
+            project,
+            dataset: Mutex::new(dataset.to_string()),
+            token: Mutex::new(None),
+            query_timeout: query_timeout.unwrap_or(DEFAULT_QUERY_TIMEOUT),
+        };
+        conn.test_connection().await?;
+        debug!(
+            "BigQuery connection established to project {}",
+            conn.project
+        );
+        Ok(conn)
+    }
+
+    /// A valid access token, signing in again when the current one is about to expire.
+    async fn access_token(&self) -> DbResult<String> {
+        if let Some((token, expires)) = &*self.token.lock().unwrap() {
+            if Instant::now() + TOKEN_MARGIN < *expires {
+                return Ok(token.clone());
+            }
+        }
+        let (token, lifetime) = self.credentials.fetch_token().await?;
+        *self.token.lock().unwrap() = Some((token.clone(), Instant::now() + lifetime));
+        Ok(token)
+    }
+
+    async fn get(&self, path: &str) -> DbResult<Value> {
+        let token = self.access_token().await?;
+        let response = self
+            .api
+            .get(
+                &format!("/bigquery/v2/projects/{}{}", encode(&self.project), path),
+                &[("Authorization", format!("Bearer {}", token))],
+                // Long enough for the server's own timeout to pass first
+                self.query_timeout + SERVER_TIMEOUT_GRACE,
+            )
+            .await?;
+        if !response.is_success() {
+            return Err(api_error(&response));
+        }
+        response.json()
+    }
+
+    async fn post(&self, path: &str, body: &Value) -> DbResult<Value> {
+        let token = self.access_token().await?;
+        let response = self
+            .api
+            .post(
+                &format!("/bigquery/v2/projects/{}{}", encode(&self.project), path),
+                &[
+                    ("Authorization", format!("Bearer {}", token)),
+                    ("Content-Type", "application/json".to_string()),
+                ],
+                body.to_string().into_bytes(),
+                self.query_timeout + SERVER_TIMEOUT_GRACE,
+            )
+            .await?;
+        if !response.is_success() {
+            return Err(api_error(&response));
+        }
+        response.json()
+    }
+
+    /// Follows a paged list (datasets, tables) and returns its `items`.
+    async fn list(&self, path: &str, items: &str) -> DbResult<Vec<Value>> {
+        let mut listed = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut page_path = format!("{}?maxResults=1000", path);
+            if let Some(token) = &page_token {
+                page_path.push_str(&format!("&pageToken={}", encode(token)));
+            }
+            let page = self.get(&page_path).await?;
+            listed.extend_from_slice(cells(&page[items]));
+            page_token = page
+                .get("nextPageToken")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if page_token.is_none() {
+                return Ok(listed);
+            }
+        }
+    }
+
+    fn current_dataset(&self) -> Option<String> {
+        Some(self.dataset.lock().unwrap().clone()).filter(|dataset| !dataset.is_empty())
+    }
+
+    /// The dataset `table` is in: its own, or the current one.
+    fn dataset_of(&self, table: &TableRef) -> DbResult<String> {
+        table
+            .schema
+            .clone()
+            .or_else(|| self.current_dataset())
+            .ok_or_else(|| {
+                QueryError::with_code("No dataset is selected", error_codes::QUERY_ERROR)
+            })
+    }
+
+    async fn get_table(&self, table: &TableRef) -> DbResult<Value> {
+        self.get(&format!(
+            "/datasets/{}/tables/{}",
+            encode(&self.dataset_of(table)?),
+            encode(&table.name)
+        ))
+        .await
+    }
+
+    fn results_path(job: &Job, page_token: Option<&str>, timeout: Duration) -> String {
+        let mut path = format!(
+            "/queries/{}?maxResults={}&timeoutMs={}&formatOptions.useInt64Timestamp=true",
+            encode(&job.id),
+            PAGE_SIZE,
+            timeout.as_millis()
+        );
+        if let Some(location) = &job.location {
+            path.push_str(&format!("&location={}", encode(location)));
+        }
+        if let Some(token) = page_token {
+            path.push_str(&format!("&pageToken={}", encode(token)));
+        }
+        path
+    }
+
+    /// Runs a query (or script) as a job and returns its first page of results, cancelling
+    /// the job if it hasn't finished within the query timeout.
+    async fn start_query(&self, query: &str) -> DbResult<(Job, ResultPage)> {
+        let mut request = json!({
+            "query": query,
+            "useLegacySql": false,
+            "maxResults": PAGE_SIZE,
+            "timeoutMs": self.query_timeout.as_millis() as u64,
+            "formatOptions": { "useInt64Timestamp": true },
+        });
+        if let Some(dataset) = self.current_dataset() {
+            request["defaultDataset"] = json!({
+                "projectId": self.project,
+                "datasetId": dataset,
+            });
+        }
+        let deadline = Instant::now() + self.query_timeout;
+        let mut response = self.post("/queries", &request).await?;
+        let job = Job {
+            id: response
+                .pointer("/jobReference/jobId")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            location: response
+                .pointer("/jobReference/location")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        };
+
+        while response.get("jobComplete") != Some(&Value::Bool(true)) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                let mut cancel = format!("/jobs/{}/cancel", encode(&job.id));
+                if let Some(location) = &job.location {
+                    cancel.push_str(&format!("?location={}", encode(location)));
+                }
+                // The timeout is reported whether or not the job could be cancelled
+                let _ = self.post(&cancel, &json!({})).await;
+                return Err(QueryError::with_code(
+                    "Query timed out",
+                    error_codes::TIMEOUT_ERROR,
+                ));
+            }
+            response = self.get(&Self::results_path(&job, None, remaining)).await?;
+        }
+        Ok((job, ResultPage::parse(&response)))
+    }
+
+    async fn next_page(&self, job: &Job, page_token: &str) -> DbResult<ResultPage> {
+        let response = self
+            .get(&Self::results_path(
+                job,
+                Some(page_token),
+                self.query_timeout,
+            ))
+            .await?;
+        Ok(ResultPage::parse(&response))
+    }
+
+    /// Runs a query and returns every row of its result, with its columns.
+    async fn query_all(&self, query: &str) -> DbResult<(Vec<Field>, Vec<Value>)> {
+        let (job, mut page) = self.start_query(query).await?;
+        let mut rows = std::mem::take(&mut page.rows);
+        while let Some(token) = page.page_token.take() {
+            let next = self.next_page(&job, &token).await?;
+            rows.extend(next.rows);
+            page.page_token = next.page_token;
+        }
+        Ok((page.fields, rows))
+    }
+}
+
+#[async_trait]
+impl DatabaseConnection for BigQueryConnection {
+    async fn test_connection(&self) -> DbResult<()> {
+        self.start_query("SELECT 1").await.map(|_| ())
+    }
+
+    async fn reconnect_if_broken(&self) -> DbResult<bool> {
+        // Each request opens its own connection; there is nothing to re-establish
+        Ok(false)
+    }
+
+    async fn execute_query(&self, query: &str) -> DbResult<QueryResult> {
+        if statement::split_statements(query).is_empty() {
+            return Err(QueryError::with_code(
+                "Query is empty",
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let mut timer = QueryTimer::start();
+        timer.acquired();
+
+        // Scripts run as one job, which returns the result of the last statement
+        let (job, mut page) = self.start_query(query).await?;
+        timer.executed();
+        let fields = std::mem::take(&mut page.fields);
+        let mut rows: Vec<Value> = page
+            .rows
+            .iter()
+            .map(|row| row_to_json(&fields, row))
+            .collect();
+        let mut page_token = page.page_token.take();
+        while rows.len() < MAX_QUERY_ROWS {
+            let Some(token) = page_token else {
+                break;
+            };
+            let next = self.next_page(&job, &token).await?;
+            rows.extend(next.rows.iter().map(|row| row_to_json(&fields, row)));
+            page_token = next.page_token;
+        }
+        timer.fetched();
+        let truncated = rows.len() > MAX_QUERY_ROWS || page_token.is_some();
+        rows.truncate(MAX_QUERY_ROWS);
+        timer.converted();
+
+        Ok(QueryResult {
+            columns: fields.iter().map(|field| field.name.clone()).collect(),
+            row_count: rows.len(),
+            rows,
+            execution_time: timer.execution_time(),
+            truncated,
+            affected_rows: page.affected_rows,
+            timing: timer.timing(),
+            column_metadata: fields.iter().map(Field::metadata).collect(),
+        })
+    }
+
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult> {
+        if statement::split_statements(query).is_empty() {
+            return Err(QueryError::with_code(
+                "Query is empty",
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let mut timer = QueryTimer::start();
+        timer.acquired();
+        let (job, mut page) = self.start_query(query).await?;
+        timer.executed();
+        let columns = page.columns();
+        let fields = std::mem::take(&mut page.fields);
+
+        // Pages are fetched as the sink takes rows, so the whole result can be read
+        let mut chunks = ChunkedRows::new(columns.clone(), chunk_size, on_chunk);
+        'pages: loop {
+            for row in &page.rows {
+                if !chunks.push(row_to_json(&fields, row)) {
+                    break 'pages;
+                }
+            }
+            let Some(token) = page.page_token.take() else {
+                break;
+            };
+            page = self.next_page(&job, &token).await?;
+            timer.fetched();
+        }
+        timer.converted();
+        let (row_count, truncated) = chunks.finish();
+
+        Ok(QueryResult {
+            columns,
+            rows: Vec::new(),
+            row_count,
+            execution_time: timer.execution_time(),
+            truncated,
+            affected_rows: page.affected_rows,
+            timing: timer.timing(),
+            column_metadata: fields.iter().map(Field::metadata).collect(),
+        })
+    }
+
+    async fn list_tables(&self) -> DbResult<Vec<String>> {
+        let Some(dataset) = self.current_dataset() else {
+            return Ok(Vec::new());
+        };
+        let tables = self
+            .list(&format!("/datasets/{}/tables", encode(&dataset)), "tables")
+            .await?;
+        let mut names: Vec<String> = tables
+            .iter()
+            .filter(|table| table["type"] == "TABLE")
+            .filter_map(|table| table.pointer("/tableReference/tableId")?.as_str())
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn list_databases(&self) -> DbResult<Vec<String>> {
+        let datasets = self.list("/datasets", "datasets").await?;
+        let mut names: Vec<String> = datasets
+            .iter()
+            .filter_map(|dataset| dataset.pointer("/datasetReference/datasetId")?.as_str())
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn change_database(&self, database_name: &str) -> DbResult<()> {
+        // Fails with "Not found: Dataset ..." for datasets that don't exist
+        self.get(&format!("/datasets/{}", encode(database_name)))
+            .await?;
+        *self.dataset.lock().unwrap() = database_name.to_string();
+        Ok(())
+    }
+
+    async fn set_default_schema(&self, schema: &str) -> DbResult<()> {
+        self.change_database(schema).await
+    }
+
+    async fn get_current_database(&self) -> DbResult<String> {
+        Ok(self.dataset.lock().unwrap().clone())
+    }
+
+    async fn get_table_columns(&self, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        let resource = self.get_table(table).await?;
+        let fields: Vec<Field> = resource
+            .pointer("/schema/fields")
+            .and_then(|fields| serde_json::from_value(fields.clone()).ok())
+            .unwrap_or_default();
+        // Primary keys are informational; BigQuery doesn't enforce them
+        let primary_key: Vec<&str> = resource
+            .pointer("/tableConstraints/primaryKey/columns")
+            .map(cells)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(Value::as_str)
+            .collect();
+        Ok(fields
+            .iter()
+            .map(|field| TableColumn {
+                name: field.name.clone(),
+                data_type: field.type_name(),
+                is_nullable: !field.required() && !field.repeated(),
+                is_primary_key: primary_key.contains(&field.name.as_str()),
+                column_default: field.default_value_expression.clone(),
+                character_maximum_length: field.max_length.as_deref().and_then(|l| l.parse().ok()),
+                numeric_precision: field.precision.as_deref().and_then(|p| p.parse().ok()),
+                is_identity: false,
+                identity_generation: None,
+                is_generated: false,
+                generation_expression: None,
+                generation_kind: None,
+                allowed_values: None,
+            })
+            .collect())
+    }
+
+    async fn get_table_relationships(&self) -> DbResult<Vec<TableRelationship>> {
+        // Foreign keys are informational in BigQuery and rarely declared
+        Ok(Vec::new())
+    }
+
+    async fn check_privileges(
+        &self,
+        _table: &TableRef,
+        _operation: TableOperation,
+    ) -> DbResult<bool> {
+        // Rows are changed with DML written in the query editor
+        Ok(false)
+    }
+
+    async fn disconnect(&self) -> DbResult<()> {
+        debug!("Disconnecting from BigQuery");
+        Ok(())
+    }
+
+    async fn export_database_with_options(
+        &self,
+        include_drop: bool,
+        include_create: bool,
+        data_mode: &str,
+        selected_tables: &[TableRef],
+        max_insert_size: usize,
+    ) -> DbResult<String> {
+        if !matches!(data_mode, "insert" | "no_data") {
+            return Err(QueryError::with_code(
+                format!(
+                    "BigQuery exports support the insert and no_data modes, not '{}'",
+                    data_mode
+                ),
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let tables_to_export = if selected_tables.is_empty() {
+            self.list_tables()
+                .await?
+                .into_iter()
+                .map(TableRef::unqualified)
+                .collect()
+        } else {
+            selected_tables.to_vec()
+        };
+
+        let mut sql_content = String::with_capacity(1024 * 1024);
+        for table in &tables_to_export {
+            let qualified_table = DIALECT.qualified_name(table);
+            sql_content.push_str(&format!("\n-- Table: {}\n", table));
+
+            if include_drop {
+                sql_content.push_str(&format!("DROP TABLE IF EXISTS {};\n", qualified_table));
+            }
+
+            if include_create {
+                let (_, rows) = self
+                    .query_all(&format!(
+                        "SELECT ddl FROM {}.INFORMATION_SCHEMA.TABLES WHERE table_name = {}",
+                        DIALECT.quote_identifier(&self.dataset_of(table)?),
+                        DIALECT.quote_string(&table.name)
+                    ))
+                    .await?;
+                let create = rows
+                    .first()
+                    .and_then(|row| row.pointer("/f/0/v")?.as_str())
+                    .ok_or_else(|| {
+                        QueryError::with_code(
+                            format!("Table \"{}\" not found", table),
+                            error_codes::QUERY_ERROR,
+                        )
+                    })?;
+                sql_content.push_str(create.trim().trim_end_matches(';'));
+                sql_content.push_str(";\n\n");
+            }
+
+            if data_mode == "no_data" {
+                continue;
+            }
+
+            let (fields, rows) = self
+                .query_all(&format!("SELECT * FROM {}", qualified_table))
+                .await?;
+            let column_list = fields
+                .iter()
+                .map(|field| DIALECT.quote_identifier(&field.name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            for batch in rows.chunks(max_insert_size) {
+                let values: Vec<String> = batch
+                    .iter()
+                    .map(|row| {
+                        let literals: Vec<String> = fields
+                            .iter()
+                            .zip(cells(&row["f"]))
+                            .map(|(field, cell)| field.literal(&cell["v"]))
+                            .collect();
+                        format!("({})", literals.join(", "))
+                    })
+                    .collect();
+                sql_content.push_str(&format!(
+                    "INSERT INTO {} ({}) VALUES\n  {};\n",
+                    qualified_table,
+                    column_list,
+                    values.join(",\n  ")
+                ));
+            }
+            sql_content.push('\n');
+        }
+
+        Ok(sql_content)
+    }
+
+    async fn get_view_definition(&self, view: &TableRef) -> DbResult<String> {
+        let resource = self.get_table(view).await?;
+        resource
+            .pointer("/view/query")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| {
+                QueryError::with_code(
+                    format!("View \"{}\" not found", view),
+                    error_codes::QUERY_ERROR,
+                )
+            })
+    }
+
+    async fn alter_view(&self, view: &TableRef, query: &str) -> DbResult<()> {
+        self.start_query(&format!(
+            "CREATE OR REPLACE VIEW {} AS\n{}",
+            DIALECT.qualified_name(view),
+            query
+        ))
+        .await
+        .map(|_| ())
+    }
+
+    async fn create_database(
+        &self,
+        name: &str,
+        encoding: Option<&str>,
+        owner: Option<&str>,
+    ) -> DbResult<String> {
+        if encoding.is_some() || owner.is_some() {
+            return Err(QueryError::with_code(
+                "BigQuery datasets have no encoding or owner",
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let statement = format!("CREATE SCHEMA {}", DIALECT.quote_identifier(name));
+        self.start_query(&statement).await?;
+        debug!("Created dataset {}", name);
+        Ok(statement)
+    }
+
+    async fn drop_database(&self, name: &str) -> DbResult<String> {
+        let current = self.get_current_database().await?;
+        check_droppable_database(name, &current, &[])?;
+
+        // Without CASCADE, datasets that still have tables aren't dropped
+        let statement = format!("DROP SCHEMA {}", DIALECT.quote_identifier(name));
+        self.start_query(&statement).await?;
+        debug!("Dropped dataset {}", name);
+        Ok(statement)
+    }
+
+    async fn build_sample_query(
+        &self,
+        table: &TableRef,
+        n: usize,
+        _method: SampleMethod,
+    ) -> DbResult<String> {
+        Ok(sampling::random_query(
+            &DIALECT.qualified_name(table),
+            "RAND()",
+            n,
+        ))
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        DIALECT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_and_literals() {
+        let fields: Vec<Field> = serde_json::from_value(json!([
+            { "name": "id", "type": "INTEGER", "mode": "REQUIRED" },
+            { "name": "price", "type": "NUMERIC" },
+            { "name": "seen", "type": "TIMESTAMP" },
+            { "name": "tags", "type": "STRING", "mode": "REPEATED" },
+            { "name": "owner", "type": "RECORD", "fields": [
+                { "name": "name", "type": "STRING" },
+                { "name": "score", "type": "FLOAT" },
+            ] },
+        ]))
+        .unwrap();
+        let row = json!({ "f": [
+            { "v": "7" },
+            { "v": "1.50" },
+            { "v": "1714564800000000" },
+            { "v": [{ "v": "a" }, { "v": "it's" }] },
+            { "v": { "f": [{ "v": "Ann" }, { "v": "NaN" }] } },
+        ] });
+
+        assert_eq!(
+            row_to_json(&fields, &row),
+            json!({
+                "id": 7,
+                "price": "1.50",
+                "seen": "2024-05-01 12:00:00",
+                "tags": ["a", "it's"],
+                "owner": { "name": "Ann", "score": "NaN" },
+            })
+        );
+        let literals: Vec<String> = fields
+            .iter()
+            .zip(cells(&row["f"]))
+            .map(|(field, cell)| field.literal(&cell["v"]))
+            .collect();
+        assert_eq!(
+            literals,
+            [
+                "7",
+                "NUMERIC '1.50'",
+                "TIMESTAMP_MICROS(1714564800000000)",
+                "['a', 'it\\'s']",
+                "STRUCT('Ann', CAST('nan' AS FLOAT64))",
+            ]
+        );
+        assert_eq!(
+            fields[4].type_name(),
+            "STRUCT<`name` STRING, `score` FLOAT64>"
+        );
+        assert_eq!(fields[3].type_name(), "ARRAY<STRING>");
+        assert_eq!(fields[1].literal(&Value::Null), "NULL");
+    }
+}
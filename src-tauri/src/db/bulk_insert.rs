@@ -0,0 +1,305 @@
+//! Turns text pasted from a spreadsheet (tab- or comma-separated) into rows for
+//! `DatabaseConnection::insert_rows`, mapping the pasted columns to table columns and
+//! checking values against the column types first.
+
+use super::connection::TableColumn;
+use serde::Serialize;
+
+/// Rows per INSERT statement.
+pub const INSERT_BATCH_ROWS: usize = 500;
+
+/// Bound parameters per statement; both PostgreSQL and MariaDB/MySQL cap them at 65535.
+const MAX_PARAMETERS: usize = 65_535;
+
+/// Validation errors reported at most.
+const MAX_ERRORS: usize = 100;
+
+/// Rows per INSERT statement for `columns` columns, keeping under the parameter limit.
+pub fn batch_size(columns: usize) -> usize {
+    (MAX_PARAMETERS / columns.max(1)).clamp(1, INSERT_BATCH_ROWS)
+}
+
+/// A pasted value that doesn't fit its column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PasteError {
+    /// 1-based row of the pasted data, not counting the header.
+    pub row: usize,
+    pub column: String,
+    pub message: String,
+}
+
+/// Splits pasted text into rows of fields. Tab-separated when the first line has a tab,
+/// comma-separated otherwise; fields may be quoted with `"`, doubling quotes inside.
+pub fn parse_delimited(text: &str) -> Vec<Vec<String>> {
+    let delimiter = match text.lines().next() {
+        Some(line) if line.contains('\t') => '\t',
+        _ => ',',
+    };
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            c if c == delimiter && !in_quotes => row.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    // Spreadsheets often copy a trailing blank line
+    rows.retain(|row| row.iter().any(|field| !field.is_empty()));
+    rows
+}
+
+/// Picks the table column for each pasted column: `mapping` names them explicitly (`None`
+/// skips a pasted column), otherwise the header row names them, otherwise they follow the
+/// table's column order.
+pub fn map_columns<'a>(
+    table_columns: &'a [TableColumn],
+    header: Option<&[String]>,
+    mapping: Option<&[Option<String>]>,
+    pasted_columns: usize,
+) -> Result<Vec<Option<&'a TableColumn>>, String> {
+    let find = |name: &str| {
+        table_columns
+            .iter()
+            .find(|column| column.name == name)
+            .or_else(|| {
+                table_columns
+                    .iter()
+                    .find(|column| column.name.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| format!("Column \"{}\" not found", name))
+    };
+
+    let mapped: Vec<Option<&TableColumn>> = match (mapping, header) {
+        (Some(mapping), _) => mapping
+            .iter()
+            .map(|name| name.as_deref().map(find).transpose())
+            .collect::<Result<_, _>>()?,
+        (None, Some(header)) => header
+            .iter()
+            .map(|name| find(name.trim()).map(Some))
+            .collect::<Result<_, _>>()?,
+        (None, None) => {
+            let writable: Vec<&TableColumn> =
+                table_columns.iter().filter(|c| !c.is_generated).collect();
+            if pasted_columns > writable.len() {
+                return Err(format!(
+                    "Pasted data has {} columns but the table only has {} writable columns",
+                    pasted_columns,
+                    writable.len()
+                ));
+            }
+            writable
+                .into_iter()
+                .take(pasted_columns)
+                .map(Some)
+                .collect()
+        }
+    };
+    // Generated columns can't be written, so pasted values for them are dropped
+    let mapped: Vec<Option<&TableColumn>> = mapped
+        .into_iter()
+        .map(|column| column.filter(|c| !c.is_generated))
+        .collect();
+
+    let targets: Vec<&str> = mapped.iter().flatten().map(|c| c.name.as_str()).collect();
+    if targets.is_empty() {
+        return Err("No pasted column is mapped to a table column".to_string());
+    }
+    if let Some(duplicate) = targets
+        .iter()
+        .enumerate()
+        .find(|(i, name)| targets[..*i].contains(name))
+    {
+        return Err(format!("Column \"{}\" is mapped twice", duplicate.1));
+    }
+    Ok(mapped)
+}
+
+/// Converts pasted rows to values for the mapped columns. Empty fields become NULL, or an
+/// empty string for non-nullable text columns. Returns the rows, or the values that don't
+/// fit their columns.
+pub fn prepare_rows(
+    rows: &[Vec<String>],
+    mapping: &[Option<&TableColumn>],
+) -> Result<Vec<Vec<Option<String>>>, Vec<PasteError>> {
+    let mut prepared = Vec::with_capacity(rows.len());
+    let mut errors = Vec::new();
+
+    for (i, row) in rows.iter().enumerate() {
+        let mut values = Vec::new();
+        for (index, column) in mapping.iter().enumerate() {
+            let Some(column) = column else {
+                continue;
+            };
+            let field = row.get(index).map(String::as_str).unwrap_or_default();
+            match check_value(column, field) {
+                Ok(value) => values.push(value),
+                Err(message) => errors.push(PasteError {
+                    row: i + 1,
+                    column: column.name.clone(),
+                    message,
+                }),
+            }
+        }
+        prepared.push(values);
+    }
+
+    if errors.is_empty() {
+        Ok(prepared)
+    } else {
+        errors.truncate(MAX_ERRORS);
+        Err(errors)
+    }
+}
+
+fn check_value(column: &TableColumn, field: &str) -> Result<Option<String>, String> {
+    // "character varying(255)", "int unsigned", "double precision" -> first word
+    let data_type = column.data_type.to_lowercase();
+    let base = data_type.split(['(', ' ']).next().unwrap_or_default();
+    let is_text = base.contains("char") || base.contains("text") || base == "enum" || base == "set";
+
+    if field.is_empty() {
+        return if column.is_nullable {
+            Ok(None)
+        } else if is_text {
+            column
+                .check_allowed_value(field)
+                .map(|_| Some(String::new()))
+        } else if column.column_default.is_some() || column.is_identity {
+            Err("Leave the column unmapped to use its default".to_string())
+        } else {
+            Err("Value is required".to_string())
+        };
+    }
+
+    let value = field.trim();
+    if base.ends_with("int")
+        || base.ends_with("serial")
+        || matches!(base, "integer" | "int2" | "int4" | "int8")
+    {
+        value
+            .parse::<i128>()
+            .map_err(|_| format!("'{}' is not an integer", value))?;
+    } else if base.starts_with("float") || matches!(base, "numeric" | "decimal" | "double" | "real")
+    {
+        value
+            .parse::<f64>()
+            .map_err(|_| format!("'{}' is not a number", value))?;
+    } else if base.starts_with("bool") {
+        if !matches!(
+            value.to_lowercase().as_str(),
+            "true" | "false" | "t" | "f" | "1" | "0" | "yes" | "no"
+        ) {
+            return Err(format!("'{}' is not a boolean", value));
+        }
+    } else if is_text {
+        column.check_allowed_value(field)?;
+        if let Some(max) = column.character_maximum_length {
+            let length = field.chars().count();
+            if length as i64 > max {
+                return Err(format!(
+                    "Value is {} characters, at most {} fit",
+                    length, max
+                ));
+            }
+        }
+        // Text is kept as pasted, including surrounding spaces
+        return Ok(Some(field.to_string()));
+    }
+    Ok(Some(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, is_nullable: bool) -> TableColumn {
+        TableColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            is_nullable,
+            is_primary_key: false,
+            column_default: None,
+            character_maximum_length: None,
+            numeric_precision: None,
+            is_identity: false,
+            identity_generation: None,
+            is_generated: false,
+            generation_expression: None,
+            generation_kind: None,
+            allowed_values: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_delimited() {
+        assert_eq!(
+            parse_delimited("id\tname\r\n1\tAda\n2\t\n\n"),
+            vec![vec!["id", "name"], vec!["1", "Ada"], vec!["2", ""]]
+        );
+        assert_eq!(
+            parse_delimited("1,\"Lovelace, Ada\",\"say \"\"hi\"\"\nline\""),
+            vec![vec!["1", "Lovelace, Ada", "say \"hi\"\nline"]]
+        );
+    }
+
+    #[test]
+    fn test_map_and_validate_rows() {
+        let mut total = column("total", "numeric", true);
+        total.is_generated = true;
+        let columns = vec![
+            column("id", "integer", false),
+            total,
+            column("name", "varchar", true),
+            column("active", "boolean", true),
+        ];
+
+        let header = vec!["NAME".to_string(), "id".to_string()];
+        let mapping = map_columns(&columns, Some(&header), None, 2).unwrap();
+        assert_eq!(mapping[0].unwrap().name, "name");
+        let positional = map_columns(&columns, None, None, 2).unwrap();
+        assert_eq!(positional[1].unwrap().name, "name");
+
+        let rows = vec![
+            vec!["Ada".to_string(), "1".to_string()],
+            vec!["".to_string(), "2".to_string()],
+        ];
+        assert_eq!(
+            prepare_rows(&rows, &mapping).unwrap(),
+            vec![
+                vec![Some("Ada".to_string()), Some("1".to_string())],
+                vec![None, Some("2".to_string())],
+            ]
+        );
+
+        let rows = vec![vec!["Ada".to_string(), "one".to_string()]];
+        let errors = prepare_rows(&rows, &mapping).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row, 1);
+        assert_eq!(errors[0].column, "id");
+
+        assert!(map_columns(&columns, None, None, 4).is_err());
+        let duplicate = [Some("id".to_string()), Some("ID".to_string())];
+        assert!(map_columns(&columns, None, Some(&duplicate), 2).is_err());
+    }
+}
@@ -0,0 +1,1080 @@
+//! Cassandra and ScyllaDB over the CQL native protocol (port 9042), with the `scylla`
+//! driver. Keyspaces are the connection's databases; the session's keyspace is the current
+//! one, so `USE` in the editor switches it like `change_database`.
+//!
+//! Only the node connected to is used, which keeps connections through SSH tunnels and NAT
+//! working, since other nodes may advertise addresses this machine can't reach. CQL has no
+//! transactions, cursors or foreign keys.
+
+use super::column_metadata::ColumnMetadata;
+use super::connection::{
+    check_droppable_database, error_codes, unsupported, ChunkedRows, DatabaseConnection, DbResult,
+    QueryError, QueryResult, QueryTimer, RowChunkSink, SslMode, TableColumn, TableOperation,
+    TableRef, TableRelationship, DEFAULT_QUERY_TIMEOUT, MAX_QUERY_ROWS,
+};
+use super::sampling::SampleMethod;
+use super::sql_dialect::SqlDialect;
+use super::statement::{self, Statement};
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use futures_util::TryStreamExt;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore};
+use scylla::client::execution_profile::ExecutionProfile;
+use scylla::client::session::Session;
+use scylla::client::session_builder::SessionBuilder;
+use scylla::errors::{
+    DbError, ExecutionError, NextPageError, NextRowError, PagerExecutionError, RequestAttemptError,
+    RequestError,
+};
+use scylla::frame::response::result::{CollectionType, ColumnSpec, ColumnType, NativeType};
+use scylla::policies::host_filter::AllowListHostFilter;
+use scylla::value::{CqlDate, CqlTimestamp, CqlValue, Row};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+const DIALECT: SqlDialect = SqlDialect::Cql;
+
+const SYSTEM_KEYSPACES: [&str; 7] = [
+    "system",
+    "system_auth",
+    "system_distributed",
+    "system_schema",
+    "system_traces",
+    "system_views",
+    "system_virtual_schema",
+];
+
+/// Days from 0001-01-01 (chrono's day 1) to the Unix epoch.
+const UNIX_EPOCH_DAYS_FROM_CE: i64 = 719_163;
+
+fn timed_out() -> QueryError {
+    QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR)
+}
+
+/// Keeps the server's own message for errors it reports, and gives timeouts and lost
+/// connections their own error codes.
+fn attempt_error(err: &RequestAttemptError) -> QueryError {
+    match err {
+        RequestAttemptError::DbError(DbError::ReadTimeout { .. }, _)
+        | RequestAttemptError::DbError(DbError::WriteTimeout { .. }, _) => timed_out(),
+        RequestAttemptError::DbError(DbError::AuthenticationError, message) => {
+            QueryError::with_code(message.clone(), error_codes::CONNECTION_ERROR)
+        }
+        RequestAttemptError::DbError(_, message) => {
+            QueryError::with_code(message.clone(), error_codes::QUERY_ERROR)
+        }
+        RequestAttemptError::BrokenConnectionError(e) => {
+            QueryError::with_code(e.to_string(), error_codes::CONNECTION_ERROR)
+        }
+        other => QueryError::with_code(other.to_string(), error_codes::QUERY_ERROR),
+    }
+}
+
+fn execution_error(err: ExecutionError) -> QueryError {
+    match &err {
+        ExecutionError::LastAttemptError(e) => attempt_error(e),
+        ExecutionError::RequestTimeout(_) => timed_out(),
+        ExecutionError::ConnectionPoolError(_) => {
+            QueryError::with_code(err.to_string(), error_codes::CONNECTION_ERROR)
+        }
+        _ => QueryError::with_code(err.to_string(), error_codes::QUERY_ERROR),
+    }
+}
+
+fn request_error(err: &RequestError) -> QueryError {
+    match err {
+        RequestError::LastAttemptError(e) => attempt_error(e),
+        RequestError::RequestTimeout(_) => timed_out(),
+        RequestError::ConnectionPoolError(_) => {
+            QueryError::with_code(err.to_string(), error_codes::CONNECTION_ERROR)
+        }
+        _ => QueryError::with_code(err.to_string(), error_codes::QUERY_ERROR),
+    }
+}
+
+fn pager_error(err: PagerExecutionError) -> QueryError {
+    match &err {
+        PagerExecutionError::NextPageError(NextPageError::RequestFailure(e)) => request_error(e),
+        _ => QueryError::with_code(err.to_string(), error_codes::QUERY_ERROR),
+    }
+}
+
+fn next_row_error(err: NextRowError) -> QueryError {
+    match &err {
+        NextRowError::NextPageError(NextPageError::RequestFailure(e)) => request_error(e),
+        _ => QueryError::with_code(err.to_string(), error_codes::QUERY_ERROR),
+    }
+}
+
+fn query_error(err: impl std::fmt::Display) -> QueryError {
+    QueryError::with_code(err.to_string(), error_codes::QUERY_ERROR)
+}
+
+/// Checks server certificates as the SSL mode asks. The driver names nodes by IP address,
+/// so `verify-full` checks the certificate against the host name of the connection.
+#[derive(Debug)]
+struct CertificateVerifier {
+    webpki: Arc<WebPkiServerVerifier>,
+    ssl_mode: SslMode,
+    host: ServerName<'static>,
+}
+
+impl ServerCertVerifier for CertificateVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if !self.ssl_mode.verifies_certificate() {
+            return Ok(ServerCertVerified::assertion());
+        }
+        let verified = self.webpki.verify_server_cert(
+            end_entity,
+            intermediates,
+            &self.host,
+            ocsp_response,
+            now,
+        );
+        match verified {
+            // verify-ca trusts any certificate issued by a trusted authority
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. },
+            )) if self.ssl_mode == SslMode::VerifyCa => Ok(ServerCertVerified::assertion()),
+            verified => verified,
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.webpki.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.webpki.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.webpki.supported_verify_schemes()
+    }
+}
+
+/// TLS settings for `ssl_mode`; certificates are verified against the Mozilla root store.
+fn tls_config(host: &str, ssl_mode: SslMode) -> DbResult<Arc<ClientConfig>> {
+    let tls_error = |e: String| QueryError::with_code(e, error_codes::TLS_ERROR);
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let webpki = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .map_err(|e| tls_error(e.to_string()))?;
+    let host = ServerName::try_from(host.to_string())
+        .map_err(|_| tls_error(format!("Invalid host name for TLS: {}", host)))?;
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| tls_error(e.to_string()))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(CertificateVerifier {
+            webpki,
+            ssl_mode,
+            host,
+        }))
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// CQL type name, e.g. `map<text, int>`.
+fn type_name(typ: &ColumnType) -> String {
+    match typ {
+        // The native type names are the CQL ones (`bigint`, `timeuuid`, ...)
+        ColumnType::Native(native) => format!("{:?}", native).to_lowercase(),
+        ColumnType::Collection { typ, .. } => match typ {
+            CollectionType::List(item) => format!("list<{}>", type_name(item)),
+            CollectionType::Set(item) => format!("set<{}>", type_name(item)),
+            CollectionType::Map(key, value) => {
+                format!("map<{}, {}>", type_name(key), type_name(value))
+            }
+            other => format!("{:?}", other),
+        },
+        ColumnType::Vector { typ, dimensions } => {
+            format!("vector<{}, {}>", type_name(typ), dimensions)
+        }
+        ColumnType::UserDefinedType { definition, .. } => definition.name.to_string(),
+        ColumnType::Tuple(items) => format!(
+            "tuple<{}>",
+            items.iter().map(type_name).collect::<Vec<_>>().join(", ")
+        ),
+        other => format!("{:?}", other),
+    }
+}
+
+fn column_metadata(spec: &ColumnSpec) -> ColumnMetadata {
+    let table_spec = spec.table_spec();
+    ColumnMetadata {
+        name: spec.name().to_string(),
+        type_name: type_name(spec.typ()),
+        numeric: matches!(
+            spec.typ(),
+            ColumnType::Native(
+                NativeType::TinyInt
+                    | NativeType::SmallInt
+                    | NativeType::Int
+                    | NativeType::BigInt
+                    | NativeType::Varint
+                    | NativeType::Counter
+                    | NativeType::Float
+                    | NativeType::Double
+                    | NativeType::Decimal
+            )
+        ),
+        source_table: (!table_spec.table_name().is_empty()).then(|| {
+            TableRef::new(
+                Some(table_spec.ks_name().to_string()),
+                table_spec.table_name(),
+            )
+        }),
+        source_column: Some(spec.name().to_string()),
+        ..Default::default()
+    }
+}
+
+/// Formats a two's complement big-endian integer (a `varint`, or the unscaled value of a
+/// `decimal`) in base 10.
+fn varint_digits(bytes: &[u8]) -> String {
+    let negative = bytes.first().is_some_and(|b| b & 0x80 != 0);
+    let mut magnitude = bytes.to_vec();
+    if negative {
+        for b in magnitude.iter_mut() {
+            *b = !*b;
+        }
+        for b in magnitude.iter_mut().rev() {
+            let (sum, carry) = b.overflowing_add(1);
+            *b = sum;
+            if !carry {
+                break;
+            }
+        }
+    }
+
+    // Long division by 10, one digit at a time
+    let mut digits = Vec::new();
+    while magnitude.iter().any(|&b| b != 0) {
+        let mut remainder = 0u32;
+        for b in magnitude.iter_mut() {
+            let value = remainder << 8 | u32::from(*b);
+            *b = (value / 10) as u8;
+            remainder = value % 10;
+        }
+        digits.push(char::from(b'0' + remainder as u8));
+    }
+    if digits.is_empty() {
+        digits.push('0');
+    }
+    if negative {
+        digits.push('-');
+    }
+    digits.iter().rev().collect()
+}
+
+/// Formats a `decimal` from its unscaled value and scale, e.g. `-1.50`.
+fn decimal_digits(bytes: &[u8], scale: i32) -> String {
+    let unscaled = varint_digits(bytes);
+    let (sign, digits) = match unscaled.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", unscaled.as_str()),
+    };
+    // Scales beyond any realistic column are written with an exponent
+    if !(0..=1000).contains(&scale) {
+        return format!("{}{}E{}", sign, digits, -i64::from(scale));
+    }
+    let scale = scale as usize;
+    if scale == 0 {
+        return unscaled;
+    }
+    let digits = format!("{:0>width$}", digits, width = scale + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale);
+    format!("{}{}.{}", sign, whole, fraction)
+}
+
+fn date(value: CqlDate) -> Option<NaiveDate> {
+    // Days are counted from 2^31 days before the epoch
+    let days = i64::from(value.0) - (1 << 31) + UNIX_EPOCH_DAYS_FROM_CE;
+    NaiveDate::from_num_days_from_ce_opt(i32::try_from(days).ok()?)
+}
+
+fn timestamp(value: CqlTimestamp) -> Option<NaiveDateTime> {
+    DateTime::from_timestamp_millis(value.0).map(|t| t.naive_utc())
+}
+
+fn time(nanos: i64) -> Option<NaiveTime> {
+    NaiveTime::from_num_seconds_from_midnight_opt(
+        u32::try_from(nanos / 1_000_000_000).ok()?,
+        u32::try_from(nanos % 1_000_000_000).ok()?,
+    )
+}
+
+fn value_to_json(value: Option<&CqlValue>) -> serde_json::Value {
+    use serde_json::Value as Json;
+    let float = |value: f64| {
+        serde_json::Number::from_f64(value)
+            .map(Json::Number)
+            .unwrap_or_else(|| Json::String(value.to_string()))
+    };
+    let Some(value) = value else {
+        return Json::Null;
+    };
+    match value {
+        CqlValue::Ascii(v) | CqlValue::Text(v) => Json::String(v.clone()),
+        CqlValue::Boolean(v) => Json::Bool(*v),
+        CqlValue::Blob(v) => Json::String(base64::engine::general_purpose::STANDARD.encode(v)),
+        CqlValue::Counter(v) => Json::from(v.0),
+        CqlValue::TinyInt(v) => Json::from(*v),
+        CqlValue::SmallInt(v) => Json::from(*v),
+        CqlValue::Int(v) => Json::from(*v),
+        CqlValue::BigInt(v) => Json::from(*v),
+        CqlValue::Float(v) => float(f64::from(*v)),
+        CqlValue::Double(v) => float(*v),
+        // Arbitrary precision numbers stay text so they aren't rounded
+        CqlValue::Varint(v) => Json::String(varint_digits(v.as_signed_bytes_be_slice())),
+        CqlValue::Decimal(v) => {
+            let (bytes, scale) = v.as_signed_be_bytes_slice_and_exponent();
+            Json::String(decimal_digits(bytes, scale))
+        }
+        CqlValue::Date(v) => date(*v)
+            .map(|d| Json::String(d.format("%Y-%m-%d").to_string()))
+            .unwrap_or_else(|| Json::from(v.0)),
+        CqlValue::Timestamp(v) => timestamp(*v)
+            .map(|t| Json::String(t.format("%Y-%m-%d %H:%M:%S%.3f").to_string()))
+            .unwrap_or_else(|| Json::from(v.0)),
+        CqlValue::Time(v) => time(v.0)
+            .map(|t| Json::String(t.format("%H:%M:%S%.f").to_string()))
+            .unwrap_or_else(|| Json::from(v.0)),
+        CqlValue::Duration(_) | CqlValue::Inet(_) | CqlValue::Uuid(_) | CqlValue::Timeuuid(_) => {
+            Json::String(value.to_string())
+        }
+        CqlValue::Empty => Json::Null,
+        CqlValue::List(items) | CqlValue::Set(items) | CqlValue::Vector(items) => {
+            Json::Array(items.iter().map(|item| value_to_json(Some(item))).collect())
+        }
+        CqlValue::Tuple(items) => Json::Array(
+            items
+                .iter()
+                .map(|item| value_to_json(item.as_ref()))
+                .collect(),
+        ),
+        CqlValue::Map(entries) => Json::Object(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = match value_to_json(Some(key)) {
+                        Json::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    (key, value_to_json(Some(value)))
+                })
+                .collect(),
+        ),
+        CqlValue::UserDefinedType { fields, .. } => Json::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), value_to_json(value.as_ref())))
+                .collect(),
+        ),
+        other => Json::String(other.to_string()),
+    }
+}
+
+/// Formats a value as a CQL literal for exports.
+fn cql_literal(value: Option<&CqlValue>) -> String {
+    let non_finite = |value: f64| {
+        if value.is_nan() {
+            "NaN"
+        } else if value > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        }
+    };
+    match value {
+        None => "null".to_string(),
+        Some(CqlValue::Float(v)) if !v.is_finite() => non_finite(f64::from(*v)).to_string(),
+        Some(CqlValue::Double(v)) if !v.is_finite() => non_finite(*v).to_string(),
+        Some(CqlValue::Varint(v)) => varint_digits(v.as_signed_bytes_be_slice()),
+        Some(CqlValue::Decimal(v)) => {
+            let (bytes, scale) = v.as_signed_be_bytes_slice_and_exponent();
+            decimal_digits(bytes, scale)
+        }
+        // Dates and timestamps outside chrono's range are written as numbers, which CQL
+        // also reads
+        Some(CqlValue::Date(v)) if date(*v).is_none() => v.0.to_string(),
+        Some(CqlValue::Timestamp(v)) if timestamp(*v).is_none() => v.0.to_string(),
+        // The driver writes every other value as a CQL literal
+        Some(value) => value.to_string(),
+    }
+}
+
+struct Output {
+    columns: Vec<String>,
+    column_metadata: Vec<ColumnMetadata>,
+    rows: Vec<serde_json::Value>,
+    truncated: bool,
+}
+
+pub struct CassandraConnection {
+    session: Session,
+}
+
+impl CassandraConnection {
+    /// Connects to the node at `host`, using `database` as the keyspace when given. Requests
+    /// time out after `query_timeout`.
+    pub async fn new(
+        host: &str,
+        port: u16,
+        username: &str,
+        password: &str,
+        database: &str,
+        ssl_mode: &str,
+        query_timeout: Option<Duration>,
+    ) -> DbResult<Self> {
+        let ssl_mode = SslMode::parse(ssl_mode)?;
+        if ssl_mode.allows_fallback() {
+            return Err(QueryError::with_code(
+                "Cassandra connections can't fall back to plain TCP; choose disabled or required",
+                error_codes::SSL_ERROR,
+            ));
+        }
+
+        let execution_profile = ExecutionProfile::builder()
+            .request_timeout(Some(query_timeout.unwrap_or(DEFAULT_QUERY_TIMEOUT)))
+            .build();
+        // The node connected to is always accepted; an empty allow list leaves out the rest
+        let host_filter = AllowListHostFilter::new(Vec::<SocketAddr>::new())
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::CONNECTION_ERROR))?;
+        let node = if host.contains(':') {
+            format!("[{}]:{}", host, port)
+        } else {
+            format!("{}:{}", host, port)
+        };
+        let mut builder = SessionBuilder::new()
+            .known_node(node)
+            .host_filter(Arc::new(host_filter))
+            .default_execution_profile_handle(execution_profile.into_handle())
+            // Schemas are read from system_schema when they are shown
+            .fetch_schema_metadata(false);
+        if !username.is_empty() {
+            builder = builder.user(username, password);
+        }
+        if ssl_mode.uses_tls() {
+            builder = builder.tls_context(Some(tls_config(host, ssl_mode)?));
+        }
+        if !database.is_empty() {
+            builder = builder.use_keyspace(database, true);
+        }
+
+        let session = builder
+            .build()
+            .await
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::CONNECTION_ERROR))?;
+        debug!("Cassandra connection established to {}:{}", host, port);
+        Ok(CassandraConnection { session })
+    }
+
+    /// The keyspace `table` is in: its own, or the session's.
+    fn keyspace(&self, table: &TableRef) -> DbResult<String> {
+        match &table.schema {
+            Some(keyspace) => Ok(keyspace.clone()),
+            None => self
+                .session
+                .get_keyspace()
+                .map(|keyspace| keyspace.to_string())
+                .ok_or_else(|| {
+                    QueryError::with_code("No keyspace is selected", error_codes::QUERY_ERROR)
+                }),
+        }
+    }
+
+    fn row_to_json(columns: &[String], row: Row) -> serde_json::Value {
+        let mut row_map = serde_json::Map::with_capacity(columns.len());
+        for (column, value) in columns.iter().zip(row.columns) {
+            row_map.insert(column.clone(), value_to_json(value.as_ref()));
+        }
+        serde_json::Value::Object(row_map)
+    }
+
+    /// Runs one statement. Queries are read page by page and stop after `MAX_QUERY_ROWS`
+    /// rows, since counting the rest could scan the whole table.
+    async fn run(&self, statement: &Statement, timer: &mut QueryTimer) -> DbResult<Output> {
+        if statement.is_query() {
+            let pager = self
+                .session
+                .query_iter(statement.sql.as_str(), ())
+                .await
+                .map_err(pager_error)?;
+            timer.executed();
+            let mut stream = pager.rows_stream::<Row>().map_err(query_error)?;
+            let column_metadata: Vec<ColumnMetadata> =
+                stream.column_specs().iter().map(column_metadata).collect();
+            let columns: Vec<String> = column_metadata.iter().map(|c| c.name.clone()).collect();
+
+            let mut rows = Vec::new();
+            let mut truncated = false;
+            while let Some(row) = stream.try_next().await.map_err(next_row_error)? {
+                if rows.len() == MAX_QUERY_ROWS {
+                    truncated = true;
+                    break;
+                }
+                rows.push(Self::row_to_json(&columns, row));
+            }
+            timer.fetched();
+            return Ok(Output {
+                columns,
+                column_metadata,
+                rows,
+                truncated,
+            });
+        }
+
+        // Other statements return no rows, apart from lightweight transactions reporting
+        // whether they were applied
+        let result = self
+            .session
+            .query_unpaged(statement.sql.as_str(), ())
+            .await
+            .map_err(execution_error)?;
+        timer.executed();
+        if !result.is_rows() {
+            return Ok(Output {
+                columns: Vec::new(),
+                column_metadata: Vec::new(),
+                rows: Vec::new(),
+                truncated: false,
+            });
+        }
+        let result = result.into_rows_result().map_err(query_error)?;
+        let column_metadata: Vec<ColumnMetadata> =
+            result.column_specs().iter().map(column_metadata).collect();
+        let columns: Vec<String> = column_metadata.iter().map(|c| c.name.clone()).collect();
+        let rows = result
+            .rows::<Row>()
+            .map_err(query_error)?
+            .map(|row| row.map(|row| Self::row_to_json(&columns, row)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(query_error)?;
+        timer.fetched();
+        Ok(Output {
+            columns,
+            column_metadata,
+            rows,
+            truncated: false,
+        })
+    }
+
+    /// Runs a metadata query and returns its rows as JSON objects.
+    async fn query_rows(&self, query: &str) -> DbResult<Vec<serde_json::Value>> {
+        let statement = Statement {
+            sql: query.to_string(),
+            keywords: vec!["SELECT".to_string()],
+        };
+        Ok(self.run(&statement, &mut QueryTimer::start()).await?.rows)
+    }
+
+    /// Primary key columns first (partition key, then clustering columns, in key order),
+    /// then the rest by name, as `DESCRIBE TABLE` lists them.
+    async fn table_columns(&self, table: &TableRef) -> DbResult<Vec<serde_json::Value>> {
+        let mut rows = self
+            .query_rows(&format!(
+                "SELECT column_name, type, kind, position, clustering_order
+                 FROM system_schema.columns
+                 WHERE keyspace_name = {} AND table_name = {}",
+                DIALECT.quote_string(&self.keyspace(table)?),
+                DIALECT.quote_string(&table.name)
+            ))
+            .await?;
+        let rank = |row: &serde_json::Value| {
+            let kind = match text(row, "kind").as_deref() {
+                Some("partition_key") => 0,
+                Some("clustering") => 1,
+                Some("static") => 2,
+                _ => 3,
+            };
+            let position = if kind < 2 {
+                row.get("position").and_then(serde_json::Value::as_i64)
+            } else {
+                None
+            };
+            (kind, position, text(row, "column_name"))
+        };
+        rows.sort_by_key(rank);
+        Ok(rows)
+    }
+
+    /// `CREATE TABLE` for a table, from its columns in `system_schema`.
+    fn create_table_statement(table: &TableRef, columns: &[serde_json::Value]) -> String {
+        let mut partition_key = Vec::new();
+        let mut clustering = Vec::new();
+        let mut clustering_order = Vec::new();
+        let mut definitions = Vec::new();
+        for column in columns {
+            let name = DIALECT.quote_identifier(&text(column, "column_name").unwrap_or_default());
+            let kind = text(column, "kind").unwrap_or_default();
+            let mut definition = format!("{} {}", name, text(column, "type").unwrap_or_default());
+            match kind.as_str() {
+                "partition_key" => partition_key.push(name),
+                "clustering" => {
+                    let order = text(column, "clustering_order").unwrap_or_default();
+                    clustering_order.push(format!("{} {}", name, order.to_uppercase()));
+                    clustering.push(name);
+                }
+                "static" => definition.push_str(" STATIC"),
+                _ => {}
+            }
+            definitions.push(definition);
+        }
+
+        let partition_key = if partition_key.len() == 1 {
+            partition_key.remove(0)
+        } else {
+            format!("({})", partition_key.join(", "))
+        };
+        let primary_key = std::iter::once(partition_key)
+            .chain(clustering)
+            .collect::<Vec<_>>()
+            .join(", ");
+        definitions.push(format!("PRIMARY KEY ({})", primary_key));
+
+        let mut statement = format!(
+            "CREATE TABLE {} (\n  {}\n)",
+            DIALECT.qualified_name(table),
+            definitions.join(",\n  ")
+        );
+        if !clustering_order.is_empty() {
+            statement.push_str(&format!(
+                " WITH CLUSTERING ORDER BY ({})",
+                clustering_order.join(", ")
+            ));
+        }
+        statement
+    }
+}
+
+fn text(row: &serde_json::Value, column: &str) -> Option<String> {
+    match row.get(column)? {
+        serde_json::Value::String(value) => Some(value.clone()),
+        serde_json::Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+#[async_trait]
+impl DatabaseConnection for CassandraConnection {
+    async fn test_connection(&self) -> DbResult<()> {
+        self.query_rows("SELECT release_version FROM system.local")
+            .await
+            .map(|_| ())
+    }
+
+    async fn reconnect_if_broken(&self) -> DbResult<bool> {
+        // The driver reconnects to the node by itself
+        Ok(false)
+    }
+
+    async fn execute_query(&self, query: &str) -> DbResult<QueryResult> {
+        let mut timer = QueryTimer::start();
+        timer.acquired();
+
+        // Scripts run statement by statement, showing the result of the last one
+        let mut output = None;
+        for statement in statement::split_statements(query) {
+            output = Some(self.run(&statement, &mut timer).await?);
+        }
+        let Some(output) = output else {
+            return Err(QueryError::with_code(
+                "Query is empty",
+                error_codes::QUERY_ERROR,
+            ));
+        };
+        timer.converted();
+
+        Ok(QueryResult {
+            columns: output.columns,
+            row_count: output.rows.len(),
+            rows: output.rows,
+            execution_time: timer.execution_time(),
+            truncated: output.truncated,
+            affected_rows: None,
+            timing: timer.timing(),
+            column_metadata: output.column_metadata,
+        })
+    }
+
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult> {
+        let statements = statement::split_statements(query);
+        let [statement] = statements.as_slice() else {
+            // Scripts are only chunked for the consumer
+            let mut result = self.execute_query(query).await?;
+            let mut chunks = ChunkedRows::new(result.columns.clone(), chunk_size, on_chunk);
+            for row in std::mem::take(&mut result.rows) {
+                if !chunks.push(row) {
+                    break;
+                }
+            }
+            chunks.finish();
+            return Ok(result);
+        };
+        if !statement.is_query() {
+            return self.execute_query(query).await;
+        }
+
+        let mut timer = QueryTimer::start();
+        timer.acquired();
+        let pager = self
+            .session
+            .query_iter(statement.sql.as_str(), ())
+            .await
+            .map_err(pager_error)?;
+        timer.executed();
+        let mut stream = pager.rows_stream::<Row>().map_err(query_error)?;
+        let column_metadata: Vec<ColumnMetadata> =
+            stream.column_specs().iter().map(column_metadata).collect();
+        let columns: Vec<String> = column_metadata.iter().map(|c| c.name.clone()).collect();
+
+        // Pages are fetched as the sink takes rows, so the whole table can be read
+        let mut chunks = ChunkedRows::new(columns.clone(), chunk_size, on_chunk);
+        while let Some(row) = stream.try_next().await.map_err(next_row_error)? {
+            timer.fetched();
+            if !chunks.push(Self::row_to_json(&columns, row)) {
+                break;
+            }
+            timer.converted();
+        }
+        timer.fetched();
+        let (row_count, truncated) = chunks.finish();
+
+        Ok(QueryResult {
+            columns,
+            rows: Vec::new(),
+            row_count,
+            execution_time: timer.execution_time(),
+            truncated,
+            affected_rows: None,
+            timing: timer.timing(),
+            column_metadata,
+        })
+    }
+
+    async fn list_tables(&self) -> DbResult<Vec<String>> {
+        let Some(keyspace) = self.session.get_keyspace() else {
+            return Ok(Vec::new());
+        };
+        let rows = self
+            .query_rows(&format!(
+                "SELECT table_name FROM system_schema.tables WHERE keyspace_name = {}",
+                DIALECT.quote_string(&keyspace)
+            ))
+            .await?;
+        let mut tables: Vec<String> = rows
+            .iter()
+            .filter_map(|row| text(row, "table_name"))
+            .collect();
+        tables.sort();
+        Ok(tables)
+    }
+
+    async fn list_databases(&self) -> DbResult<Vec<String>> {
+        let rows = self
+            .query_rows("SELECT keyspace_name FROM system_schema.keyspaces")
+            .await?;
+        let mut keyspaces: Vec<String> = rows
+            .iter()
+            .filter_map(|row| text(row, "keyspace_name"))
+            .collect();
+        keyspaces.sort();
+        Ok(keyspaces)
+    }
+
+    async fn change_database(&self, database_name: &str) -> DbResult<()> {
+        self.session
+            .use_keyspace(database_name, true)
+            .await
+            .map_err(query_error)
+    }
+
+    async fn set_default_schema(&self, schema: &str) -> DbResult<()> {
+        self.change_database(schema).await
+    }
+
+    async fn get_current_database(&self) -> DbResult<String> {
+        Ok(self
+            .session
+            .get_keyspace()
+            .map(|keyspace| keyspace.to_string())
+            .unwrap_or_default())
+    }
+
+    async fn get_table_columns(&self, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        let rows = self.table_columns(table).await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let is_primary_key = matches!(
+                    text(row, "kind").as_deref(),
+                    Some("partition_key" | "clustering")
+                );
+                Some(TableColumn {
+                    name: text(row, "column_name")?,
+                    data_type: text(row, "type")?,
+                    // Only key columns are required
+                    is_nullable: !is_primary_key,
+                    is_primary_key,
+                    column_default: None,
+                    character_maximum_length: None,
+                    numeric_precision: None,
+                    is_identity: false,
+                    identity_generation: None,
+                    is_generated: false,
+                    generation_expression: None,
+                    generation_kind: None,
+                    allowed_values: None,
+                })
+            })
+            .collect())
+    }
+
+    async fn get_table_relationships(&self) -> DbResult<Vec<TableRelationship>> {
+        // CQL has no foreign keys
+        Ok(Vec::new())
+    }
+
+    async fn check_privileges(
+        &self,
+        _table: &TableRef,
+        _operation: TableOperation,
+    ) -> DbResult<bool> {
+        // Rows are changed with statements written in the query editor
+        Ok(false)
+    }
+
+    async fn disconnect(&self) -> DbResult<()> {
+        // The driver closes its connections when the session is dropped
+        debug!("Disconnecting from Cassandra");
+        Ok(())
+    }
+
+    async fn export_database_with_options(
+        &self,
+        include_drop: bool,
+        include_create: bool,
+        data_mode: &str,
+        selected_tables: &[TableRef],
+        _max_insert_size: usize,
+    ) -> DbResult<String> {
+        if !matches!(data_mode, "insert" | "no_data") {
+            return Err(QueryError::with_code(
+                format!(
+                    "Cassandra exports support the insert and no_data modes, not '{}'",
+                    data_mode
+                ),
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let tables_to_export = if selected_tables.is_empty() {
+            self.list_tables()
+                .await?
+                .into_iter()
+                .map(TableRef::unqualified)
+                .collect()
+        } else {
+            selected_tables.to_vec()
+        };
+
+        let mut sql_content = String::with_capacity(1024 * 1024);
+        for table in &tables_to_export {
+            let qualified_table = DIALECT.qualified_name(table);
+            let columns = self.table_columns(table).await?;
+            if columns.is_empty() {
+                return Err(QueryError::with_code(
+                    format!("Table \"{}\" not found", table),
+                    error_codes::QUERY_ERROR,
+                ));
+            }
+            sql_content.push_str(&format!("\n-- Table: {}\n", table));
+
+            if include_drop {
+                sql_content.push_str(&format!("DROP TABLE IF EXISTS {};\n", qualified_table));
+            }
+
+            if include_create {
+                sql_content.push_str(&Self::create_table_statement(table, &columns));
+                sql_content.push_str(";\n\n");
+            }
+
+            if data_mode == "no_data" {
+                continue;
+            }
+
+            let column_list = columns
+                .iter()
+                .filter_map(|c| text(c, "column_name"))
+                .map(|name| DIALECT.quote_identifier(&name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            // CQL inserts one row per statement; the table is read page by page
+            let mut stream = self
+                .session
+                .query_iter(
+                    format!("SELECT {} FROM {}", column_list, qualified_table),
+                    (),
+                )
+                .await
+                .map_err(pager_error)?
+                .rows_stream::<Row>()
+                .map_err(query_error)?;
+            while let Some(row) = stream.try_next().await.map_err(next_row_error)? {
+                let values: Vec<String> = row
+                    .columns
+                    .iter()
+                    .map(|value| cql_literal(value.as_ref()))
+                    .collect();
+                sql_content.push_str(&format!(
+                    "INSERT INTO {} ({}) VALUES ({});\n",
+                    qualified_table,
+                    column_list,
+                    values.join(", ")
+                ));
+            }
+            sql_content.push('\n');
+        }
+
+        Ok(sql_content)
+    }
+
+    async fn create_database(
+        &self,
+        name: &str,
+        encoding: Option<&str>,
+        owner: Option<&str>,
+    ) -> DbResult<String> {
+        if encoding.is_some() || owner.is_some() {
+            return Err(QueryError::with_code(
+                "Cassandra keyspaces have no encoding or owner",
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        // One replica suits the single-node clusters keyspaces are usually created on from
+        // here; production keyspaces are created with their replication spelled out
+        let statement = format!(
+            "CREATE KEYSPACE {} WITH replication = \
+             {{'class': 'SimpleStrategy', 'replication_factor': 1}}",
+            DIALECT.quote_identifier(name)
+        );
+        self.session
+            .query_unpaged(statement.as_str(), ())
+            .await
+            .map_err(execution_error)?;
+        debug!("Created keyspace {}", name);
+        Ok(statement)
+    }
+
+    async fn drop_database(&self, name: &str) -> DbResult<String> {
+        let current = self.get_current_database().await?;
+        check_droppable_database(name, &current, &SYSTEM_KEYSPACES)?;
+
+        let statement = format!("DROP KEYSPACE {}", DIALECT.quote_identifier(name));
+        self.session
+            .query_unpaged(statement.as_str(), ())
+            .await
+            .map_err(execution_error)?;
+        debug!("Dropped keyspace {}", name);
+        Ok(statement)
+    }
+
+    async fn build_sample_query(
+        &self,
+        _table: &TableRef,
+        _n: usize,
+        _method: SampleMethod,
+    ) -> DbResult<String> {
+        // CQL can't order by a random value
+        unsupported("Sampling")
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        DIALECT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scylla::value::{CqlDecimal, CqlVarint};
+    use serde_json::json;
+
+    #[test]
+    fn test_values_and_literals() {
+        assert_eq!(varint_digits(&[0x01, 0x00]), "256");
+        assert_eq!(varint_digits(&[0x80]), "-128");
+        assert_eq!(varint_digits(&[0xff, 0x38]), "-200");
+        assert_eq!(varint_digits(&[]), "0");
+        assert_eq!(decimal_digits(&[0x96], 2), "-1.06");
+        assert_eq!(decimal_digits(&[0x05], 3), "0.005");
+        assert_eq!(decimal_digits(&[0x05], -2), "5E2");
+
+        let decimal = CqlValue::Decimal(CqlDecimal::from_signed_be_bytes_and_exponent(
+            vec![0x01, 0x2c],
+            1,
+        ));
+        assert_eq!(value_to_json(Some(&decimal)), json!("30.0"));
+        assert_eq!(cql_literal(Some(&decimal)), "30.0");
+        let varint = CqlValue::Varint(CqlVarint::from_signed_bytes_be(vec![0x7f]));
+        assert_eq!(cql_literal(Some(&varint)), "127");
+
+        let date = CqlValue::Date(CqlDate((1 << 31) + 19_844));
+        assert_eq!(value_to_json(Some(&date)), json!("2024-05-01"));
+        assert_eq!(cql_literal(Some(&date)), "'2024-05-01'");
+        let tags = CqlValue::Map(vec![(CqlValue::Int(1), CqlValue::Text("it's".to_string()))]);
+        assert_eq!(value_to_json(Some(&tags)), json!({"1": "it's"}));
+        assert_eq!(cql_literal(Some(&tags)), "{1:'it''s'}");
+        assert_eq!(cql_literal(Some(&CqlValue::Double(f64::NAN))), "NaN");
+        assert_eq!(cql_literal(None), "null");
+
+        let columns = vec![
+            json!({"column_name": "body", "type": "text", "kind": "regular"}),
+            json!({"column_name": "day", "type": "date", "kind": "partition_key"}),
+            json!({"column_name": "id", "type": "timeuuid", "kind": "clustering",
+                   "clustering_order": "desc"}),
+            json!({"column_name": "user", "type": "int", "kind": "partition_key"}),
+        ];
+        assert_eq!(
+            CassandraConnection::create_table_statement(
+                &TableRef::new(Some("app".to_string()), "posts"),
+                &columns
+            ),
+            "CREATE TABLE \"app\".\"posts\" (\n  \"body\" text,\n  \"day\" date,\n  \
+             \"id\" timeuuid,\n  \"user\" int,\n  PRIMARY KEY ((\"day\", \"user\"), \"id\")\n) \
+             WITH CLUSTERING ORDER BY (\"id\" DESC)"
+        );
+    }
+}
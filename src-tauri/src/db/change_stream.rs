@@ -0,0 +1,137 @@
+//! Experimental change tailing: row changes to a table are read from PostgreSQL logical
+//! decoding (a temporary wal2json slot) or the MySQL binlog as they are committed, for
+//! watching writes live while debugging.
+
+use super::connection::TableRef;
+use serde::Serialize;
+use std::time::Duration;
+
+/// How often a PostgreSQL slot is polled, and the longest a tail goes without calling its
+/// sink, so it notices being stopped on a quiet table.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// A committed change to one row.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RowChange {
+    pub table: TableRef,
+    pub kind: ChangeKind,
+    /// The row before an update or delete, as far as the server logs it: often only the
+    /// key columns (PostgreSQL's replica identity, MySQL's `binlog_row_image`).
+    pub before: Option<serde_json::Value>,
+    /// The row after an insert or update.
+    pub after: Option<serde_json::Value>,
+}
+
+impl RowChange {
+    /// Builds a change from whichever row images a binlog event has.
+    pub fn new(
+        table: TableRef,
+        before: Option<serde_json::Value>,
+        after: Option<serde_json::Value>,
+    ) -> Option<Self> {
+        let kind = match (&before, &after) {
+            (None, Some(_)) => ChangeKind::Insert,
+            (Some(_), Some(_)) => ChangeKind::Update,
+            (Some(_), None) => ChangeKind::Delete,
+            (None, None) => return None,
+        };
+        Some(RowChange {
+            table,
+            kind,
+            before,
+            after,
+        })
+    }
+}
+
+/// Callback receiving decoded changes, possibly none at all every `POLL_INTERVAL`.
+/// Returning `false` stops the tail.
+pub type ChangeSink<'a> = &'a mut (dyn FnMut(Vec<RowChange>) -> bool + Send);
+
+/// Escapes a name for wal2json's `add-tables` option, where `.` and `,` are separators and
+/// `*` a wildcard.
+pub fn wal2json_table_filter(table: &TableRef) -> String {
+    let escape = |name: &str| {
+        let mut escaped = String::with_capacity(name.len());
+        for c in name.chars() {
+            if matches!(c, '\\' | '.' | ',' | '*' | ' ') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    match &table.schema {
+        Some(schema) => format!("{}.{}", escape(schema), escape(&table.name)),
+        None => format!("*.{}", escape(&table.name)),
+    }
+}
+
+/// Decodes one wal2json (format version 2) message; transaction boundaries and messages
+/// other than row changes give None.
+pub fn parse_wal2json(message: &str) -> Option<RowChange> {
+    let message: serde_json::Value = serde_json::from_str(message).ok()?;
+    let image = |key: &str| {
+        let columns = message.get(key)?.as_array()?;
+        let row: serde_json::Map<String, serde_json::Value> = columns
+            .iter()
+            .filter_map(|column| {
+                let name = column.get("name")?.as_str()?;
+                Some((name.to_string(), column.get("value")?.clone()))
+            })
+            .collect();
+        Some(serde_json::Value::Object(row))
+    };
+    let (kind, before, after) = match message.get("action")?.as_str()? {
+        "I" => (ChangeKind::Insert, None, image("columns")),
+        "U" => (ChangeKind::Update, image("identity"), image("columns")),
+        "D" => (ChangeKind::Delete, image("identity"), None),
+        _ => return None,
+    };
+    Some(RowChange {
+        table: TableRef::new(
+            message.get("schema")?.as_str().map(str::to_string),
+            message.get("table")?.as_str()?,
+        ),
+        kind,
+        before,
+        after,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_wal2json() {
+        let change = parse_wal2json(
+            r#"{"action":"U","schema":"public","table":"orders",
+                "columns":[{"name":"id","type":"integer","value":7},{"name":"status","type":"text","value":"paid"}],
+                "identity":[{"name":"id","type":"integer","value":7}]}"#,
+        )
+        .unwrap();
+        assert_eq!(change.kind, ChangeKind::Update);
+        assert_eq!(
+            change.table,
+            TableRef::new(Some("public".to_string()), "orders")
+        );
+        assert_eq!(change.before, Some(json!({ "id": 7 })));
+        assert_eq!(change.after, Some(json!({ "id": 7, "status": "paid" })));
+
+        assert!(parse_wal2json(r#"{"action":"B"}"#).is_none());
+        assert_eq!(
+            wal2json_table_filter(&TableRef::unqualified("odd.name")),
+            "*.odd\\.name"
+        );
+    }
+}
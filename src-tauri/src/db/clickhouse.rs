@@ -0,0 +1,778 @@
+//! ClickHouse through its HTTP interface (port 8123, or 8443 with TLS). Every statement is
+//! its own request with the database and settings in the URL, so there are no sessions,
+//! transactions or cursors.
+//!
+//! Results come back as `JSONCompact`, with 64-bit integers and decimals quoted so they
+//! keep their precision; integers that fit are turned back into JSON numbers.
+
+use super::column_metadata::ColumnMetadata;
+use super::connection::{
+    check_droppable_database, error_codes, ChunkedRows, DatabaseConnection, DbResult, QueryError,
+    QueryResult, QueryTimer, RowChunkSink, SslMode, TableColumn, TableOperation, TableRef,
+    TableRelationship, DEFAULT_QUERY_TIMEOUT, MAX_QUERY_ROWS, SERVER_TIMEOUT_GRACE,
+};
+use super::http::{HttpEndpoint, MAX_RESPONSE_BYTES};
+use super::sampling::{self, SampleMethod};
+use super::sql_dialect::SqlDialect;
+use super::statement;
+use async_trait::async_trait;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+const DIALECT: SqlDialect = SqlDialect::ClickHouse;
+
+const SYSTEM_DATABASES: [&str; 3] = ["system", "INFORMATION_SCHEMA", "information_schema"];
+
+/// Server error codes (`Code: N.` in the response) that get their own error code.
+const TIMEOUT_EXCEEDED: u32 = 159;
+const UNKNOWN_USER: u32 = 192;
+const WRONG_PASSWORD: u32 = 193;
+const AUTHENTICATION_FAILED: u32 = 516;
+
+/// Turns an error response, e.g. `Code: 60. DB::Exception: Table x does not exist.
+/// (UNKNOWN_TABLE) (version 24.3.1)`, into a `QueryError`.
+fn clickhouse_error(status: u16, body: &[u8]) -> QueryError {
+    let message = String::from_utf8_lossy(body).trim().to_string();
+    let server_code = message
+        .strip_prefix("Code: ")
+        .and_then(|rest| rest.split('.').next())
+        .and_then(|code| code.parse::<u32>().ok());
+    let code = match server_code {
+        Some(TIMEOUT_EXCEEDED) => error_codes::TIMEOUT_ERROR,
+        Some(UNKNOWN_USER | WRONG_PASSWORD | AUTHENTICATION_FAILED) => {
+            error_codes::CONNECTION_ERROR
+        }
+        _ if status == 401 || status == 403 => error_codes::CONNECTION_ERROR,
+        _ => error_codes::QUERY_ERROR,
+    };
+    if message.is_empty() {
+        return QueryError::with_code(format!("HTTP {}", status), code);
+    }
+    QueryError::with_code(message, code)
+}
+
+/// Settings that make the server stop sending a query's result once it has more rows than
+/// are shown, instead of sending all of it to be truncated here. One row past the limit
+/// tells that the result was truncated. The byte limit counts the server's blocks, which
+/// are several times smaller than their JSON.
+fn result_limits() -> String {
+    format!(
+        "&max_result_rows={}&max_result_bytes={}&result_overflow_mode=break",
+        MAX_QUERY_ROWS + 1,
+        MAX_RESPONSE_BYTES / 4
+    )
+}
+
+/// The type inside `Nullable(...)` and `LowCardinality(...)`, which don't change how
+/// values look.
+fn base_type(column_type: &str) -> &str {
+    let mut column_type = column_type.trim();
+    loop {
+        let inner = ["Nullable(", "LowCardinality("]
+            .iter()
+            .find_map(|wrapper| column_type.strip_prefix(wrapper))
+            .and_then(|rest| rest.strip_suffix(')'));
+        match inner {
+            Some(inner) => column_type = inner.trim(),
+            None => return column_type,
+        }
+    }
+}
+
+fn is_numeric_type(column_type: &str) -> bool {
+    let base = base_type(column_type);
+    ["Int", "UInt", "Float", "Decimal"]
+        .iter()
+        .any(|prefix| base.starts_with(prefix))
+}
+
+/// The arguments of a parameterized type, e.g. `10` and `2` for `Decimal(10, 2)`.
+fn type_arguments(column_type: &str) -> Vec<&str> {
+    let base = base_type(column_type);
+    let Some(arguments) = base
+        .split_once('(')
+        .and_then(|(_, rest)| rest.strip_suffix(')'))
+    else {
+        return Vec::new();
+    };
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in arguments.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(arguments[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(arguments[start..].trim());
+    parts
+}
+
+/// Converts a `JSONCompact` value of `column_type`. 64-bit and wider integers arrive
+/// quoted; those that fit are turned into numbers. Decimals stay strings, and dates and
+/// `DateTime64` keep ClickHouse's text form.
+fn clickhouse_value(column_type: &str, value: &Value) -> Value {
+    let base = base_type(column_type);
+    match value {
+        Value::Array(items) if base.starts_with("Array(") => {
+            let item_type = type_arguments(base).first().copied().unwrap_or_default();
+            Value::Array(
+                items
+                    .iter()
+                    .map(|item| clickhouse_value(item_type, item))
+                    .collect(),
+            )
+        }
+        Value::String(raw) if base.starts_with("Int") => raw
+            .parse::<i64>()
+            .map(Value::from)
+            .unwrap_or_else(|_| value.clone()),
+        Value::String(raw) if base.starts_with("UInt") => raw
+            .parse::<u64>()
+            .map(Value::from)
+            .unwrap_or_else(|_| value.clone()),
+        _ => value.clone(),
+    }
+}
+
+fn column_metadata(name: &str, column_type: &str) -> ColumnMetadata {
+    let base = base_type(column_type);
+    let (precision, scale) = if base.starts_with("Decimal(") {
+        let arguments = type_arguments(base);
+        let argument = |index: usize| arguments.get(index).and_then(|a| a.parse().ok());
+        (argument(0), argument(1))
+    } else {
+        (None, None)
+    };
+    ColumnMetadata {
+        name: name.to_string(),
+        type_name: column_type.to_string(),
+        numeric: is_numeric_type(column_type),
+        nullable: Some(column_type.trim().starts_with("Nullable(")),
+        precision,
+        scale,
+        ..Default::default()
+    }
+}
+
+/// Splits the output of `FORMAT Values`, `(1,'a'),(2,'b')`, into its rows.
+fn split_values_rows(output: &[u8]) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, &byte) in output.iter().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'\'' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'\'' => in_string = true,
+            b'(' | b'[' | b'{' => {
+                if depth == 0 {
+                    start = index;
+                }
+                depth += 1;
+            }
+            b')' | b']' | b'}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    rows.push(String::from_utf8_lossy(&output[start..=index]).into_owned());
+                }
+            }
+            _ => {}
+        }
+    }
+    rows
+}
+
+fn text(row: &Value, column: &str) -> Option<String> {
+    match row.get(column)? {
+        Value::String(value) => Some(value.clone()),
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+fn flag(row: &Value, column: &str) -> bool {
+    match row.get(column) {
+        Some(Value::Bool(value)) => *value,
+        Some(Value::Number(value)) => value.as_i64() == Some(1),
+        _ => false,
+    }
+}
+
+/// One statement's converted output.
+struct Output {
+    columns: Vec<String>,
+    rows: Vec<Value>,
+    column_metadata: Vec<ColumnMetadata>,
+}
+
+pub struct ClickHouseConnection {
+    endpoint: HttpEndpoint,
+    username: String,
+    password: String,
+    /// Sent with each request; `currentDatabase()` on the server.
+    database: Mutex<String>,
+    /// Sent with each request as `max_execution_time`.
+    query_timeout: Duration,
+}
+
+impl ClickHouseConnection {
+    /// Connects over HTTPS as `ssl_mode` asks, falling back to HTTP for `preferred`, and
+    /// checks the credentials with a query.
+    pub async fn new(
+        host: &str,
+        port: u16,
+        username: &str,
+        password: &str,
+        database: &str,
+        ssl_mode: &str,
+        query_timeout: Option<Duration>,
+    ) -> DbResult<Self> {
+        let ssl_mode = SslMode::parse(ssl_mode)?;
+        let mut conn = ClickHouseConnection {
+            endpoint: HttpEndpoint::new(host, port, ssl_mode.uses_tls(), ssl_mode)?,
+            username: username.to_string(),
+            password: password.to_string(),
+            database: Mutex::new(if database.is_empty() {
+                "default".to_string()
+            } else {
+                database.to_string()
+            }),
+            query_timeout: query_timeout.unwrap_or(DEFAULT_QUERY_TIMEOUT),
+        };
+        match conn.test_connection().await {
+            Ok(()) => {}
+            Err(e)
+                if conn.endpoint.tls
+                    && ssl_mode.allows_fallback()
+                    && e.code.as_deref() == Some(error_codes::TLS_ERROR) =>
+            {
+                warn!(
+                    "HTTPS connection failed, falling back to HTTP: {}",
+                    e.message
+                );
+                conn.endpoint.tls = false;
+                conn.test_connection().await?;
+            }
+            Err(e) => return Err(e),
+        }
+        debug!(
+            "ClickHouse {} connection established",
+            if conn.endpoint.tls { "HTTPS" } else { "HTTP" }
+        );
+        Ok(conn)
+    }
+
+    /// Sends one statement and returns the raw response body. Results without a
+    /// `FORMAT` clause come back as `JSONCompact`.
+    async fn post(&self, query: &str) -> DbResult<Vec<u8>> {
+        self.post_with_settings(query, "").await
+    }
+
+    /// Like `post`, with extra `&name=value` settings for the statement.
+    async fn post_with_settings(&self, query: &str, settings: &str) -> DbResult<Vec<u8>> {
+        let database = self.database.lock().unwrap().clone();
+        let path = format!(
+            "/?database={}&default_format=JSONCompact\
+             &output_format_json_quote_64bit_integers=1&output_format_json_quote_decimals=1\
+             &output_format_json_validate_utf8=1&max_execution_time={}{}",
+            utf8_percent_encode(&database, NON_ALPHANUMERIC),
+            // 0 would mean no limit
+            self.query_timeout.as_secs().max(1),
+            settings
+        );
+        let response = self
+            .endpoint
+            .post(
+                &path,
+                &[
+                    ("Content-Type", "text/plain; charset=utf-8".to_string()),
+                    ("X-ClickHouse-User", self.username.clone()),
+                    ("X-ClickHouse-Key", self.password.clone()),
+                ],
+                query.as_bytes().to_vec(),
+                // Long enough for the server's own timeout error to arrive first
+                self.query_timeout + SERVER_TIMEOUT_GRACE,
+            )
+            .await?;
+        if !response.is_success() {
+            return Err(clickhouse_error(response.status, &response.body));
+        }
+        Ok(response.body)
+    }
+
+    /// Runs one statement with the extra `settings` and converts its output. Statements
+    /// without a result return an empty body; output in a format picked with `FORMAT` is
+    /// shown line by line.
+    async fn run(&self, query: &str, settings: &str, timer: &mut QueryTimer) -> DbResult<Output> {
+        let body = self.post_with_settings(query, settings).await?;
+        timer.executed();
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return Ok(Output {
+                columns: Vec::new(),
+                rows: Vec::new(),
+                column_metadata: Vec::new(),
+            });
+        }
+        let Ok(response) = serde_json::from_slice::<Value>(&body) else {
+            let rows = String::from_utf8_lossy(&body)
+                .lines()
+                .map(|line| serde_json::json!({ "result": line }))
+                .collect();
+            return Ok(Output {
+                columns: vec!["result".to_string()],
+                rows,
+                column_metadata: Vec::new(),
+            });
+        };
+
+        let meta: Vec<(String, String)> = response
+            .get("meta")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|column| {
+                (
+                    text(column, "name").unwrap_or_default(),
+                    text(column, "type").unwrap_or_default(),
+                )
+            })
+            .collect();
+        let rows = response
+            .get("data")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|row| {
+                let values = row.as_array().map(Vec::as_slice).unwrap_or_default();
+                let mut row_map = serde_json::Map::with_capacity(meta.len());
+                for ((name, column_type), value) in meta.iter().zip(values) {
+                    row_map.insert(name.clone(), clickhouse_value(column_type, value));
+                }
+                Value::Object(row_map)
+            })
+            .collect();
+        timer.converted();
+
+        Ok(Output {
+            columns: meta.iter().map(|(name, _)| name.clone()).collect(),
+            column_metadata: meta
+                .iter()
+                .map(|(name, column_type)| column_metadata(name, column_type))
+                .collect(),
+            rows,
+        })
+    }
+
+    /// Runs a metadata query and returns its rows.
+    async fn query_rows(&self, query: &str) -> DbResult<Vec<Value>> {
+        let mut timer = QueryTimer::start();
+        Ok(self.run(query, "", &mut timer).await?.rows)
+    }
+
+    /// The database an unqualified table belongs to, as an SQL expression.
+    fn database_expression(table: &TableRef) -> String {
+        match &table.schema {
+            Some(database) => DIALECT.quote_string(database),
+            None => "currentDatabase()".to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl DatabaseConnection for ClickHouseConnection {
+    async fn test_connection(&self) -> DbResult<()> {
+        self.post("SELECT 1").await.map(|_| ())
+    }
+
+    async fn reconnect_if_broken(&self) -> DbResult<bool> {
+        // Each request opens its own connection; there is nothing to re-establish
+        Ok(false)
+    }
+
+    async fn execute_query(&self, query: &str) -> DbResult<QueryResult> {
+        let mut timer = QueryTimer::start();
+        timer.acquired();
+
+        // Scripts run statement by statement, showing the result of the last one
+        let mut output = None;
+        for statement in statement::split_statements(query) {
+            let settings = if statement.is_query() {
+                result_limits()
+            } else {
+                String::new()
+            };
+            output = Some(self.run(&statement.sql, &settings, &mut timer).await?);
+        }
+        let Some(mut output) = output else {
+            return Err(QueryError::with_code(
+                "Query is empty",
+                error_codes::QUERY_ERROR,
+            ));
+        };
+
+        let row_count = output.rows.len();
+        output.rows.truncate(MAX_QUERY_ROWS);
+        Ok(QueryResult {
+            columns: output.columns,
+            rows: output.rows,
+            row_count,
+            execution_time: timer.execution_time(),
+            truncated: row_count > MAX_QUERY_ROWS,
+            affected_rows: None,
+            timing: timer.timing(),
+            column_metadata: output.column_metadata,
+        })
+    }
+
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult> {
+        // The whole result comes in one response; it is only chunked for the consumer
+        let mut result = self.execute_query(query).await?;
+        let mut chunks = ChunkedRows::new(result.columns.clone(), chunk_size, on_chunk);
+        for row in std::mem::take(&mut result.rows) {
+            if !chunks.push(row) {
+                break;
+            }
+        }
+        chunks.finish();
+        Ok(result)
+    }
+
+    async fn list_tables(&self) -> DbResult<Vec<String>> {
+        let rows = self
+            .query_rows(
+                "SELECT name FROM system.tables
+                 WHERE database = currentDatabase() AND NOT is_temporary AND engine != 'View'
+                 ORDER BY name",
+            )
+            .await?;
+        Ok(rows.iter().filter_map(|row| text(row, "name")).collect())
+    }
+
+    async fn list_databases(&self) -> DbResult<Vec<String>> {
+        let rows = self
+            .query_rows("SELECT name FROM system.databases ORDER BY name")
+            .await?;
+        Ok(rows.iter().filter_map(|row| text(row, "name")).collect())
+    }
+
+    async fn change_database(&self, database_name: &str) -> DbResult<()> {
+        let rows = self
+            .query_rows(&format!(
+                "SELECT name FROM system.databases WHERE name = {}",
+                DIALECT.quote_string(database_name)
+            ))
+            .await?;
+        if rows.is_empty() {
+            return Err(QueryError::with_code(
+                format!("Database \"{}\" not found", database_name),
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        *self.database.lock().unwrap() = database_name.to_string();
+        Ok(())
+    }
+
+    async fn set_default_schema(&self, schema: &str) -> DbResult<()> {
+        self.change_database(schema).await
+    }
+
+    async fn get_current_database(&self) -> DbResult<String> {
+        Ok(self.database.lock().unwrap().clone())
+    }
+
+    async fn get_table_columns(&self, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        let query = format!(
+            "SELECT name, type, default_kind, default_expression, is_in_primary_key
+             FROM system.columns
+             WHERE database = {} AND table = {}
+             ORDER BY position",
+            Self::database_expression(table),
+            DIALECT.quote_string(&table.name)
+        );
+        let rows = self.query_rows(&query).await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let data_type = text(row, "type")?;
+                let base = base_type(&data_type);
+                let expression = text(row, "default_expression").filter(|e| !e.is_empty());
+                // MATERIALIZED columns are computed on insert, ALIAS columns on read
+                let generation_kind = match text(row, "default_kind").as_deref() {
+                    Some("MATERIALIZED") => Some("STORED".to_string()),
+                    Some("ALIAS") => Some("VIRTUAL".to_string()),
+                    _ => None,
+                };
+                let is_generated = generation_kind.is_some();
+                Some(TableColumn {
+                    name: text(row, "name")?,
+                    is_nullable: data_type.starts_with("Nullable("),
+                    is_primary_key: flag(row, "is_in_primary_key"),
+                    column_default: if is_generated {
+                        None
+                    } else {
+                        expression.clone()
+                    },
+                    character_maximum_length: base
+                        .starts_with("FixedString(")
+                        .then(|| type_arguments(base).first()?.parse().ok())
+                        .flatten(),
+                    numeric_precision: base
+                        .starts_with("Decimal(")
+                        .then(|| type_arguments(base).first()?.parse().ok())
+                        .flatten(),
+                    is_identity: false,
+                    identity_generation: None,
+                    is_generated,
+                    generation_expression: if is_generated { expression } else { None },
+                    generation_kind,
+                    allowed_values: None,
+                    data_type,
+                })
+            })
+            .collect())
+    }
+
+    async fn get_table_relationships(&self) -> DbResult<Vec<TableRelationship>> {
+        // ClickHouse has no foreign keys
+        Ok(Vec::new())
+    }
+
+    async fn check_privileges(
+        &self,
+        _table: &TableRef,
+        _operation: TableOperation,
+    ) -> DbResult<bool> {
+        // Rows are changed with mutations written in the query editor
+        Ok(false)
+    }
+
+    async fn disconnect(&self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn export_database_with_options(
+        &self,
+        include_drop: bool,
+        include_create: bool,
+        data_mode: &str,
+        selected_tables: &[TableRef],
+        max_insert_size: usize,
+    ) -> DbResult<String> {
+        if !matches!(data_mode, "insert" | "no_data") {
+            return Err(QueryError::with_code(
+                format!(
+                    "ClickHouse exports support the insert and no_data modes, not '{}'",
+                    data_mode
+                ),
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let max_insert_size = max_insert_size.max(1);
+        let tables_to_export = if selected_tables.is_empty() {
+            self.list_tables()
+                .await?
+                .into_iter()
+                .map(TableRef::unqualified)
+                .collect()
+        } else {
+            selected_tables.to_vec()
+        };
+
+        let mut sql_content = String::with_capacity(1024 * 1024);
+        for table in &tables_to_export {
+            let qualified_table = DIALECT.qualified_name(table);
+            let columns = self.get_table_columns(table).await?;
+            if columns.is_empty() {
+                return Err(QueryError::with_code(
+                    format!("Table \"{}\" not found", table),
+                    error_codes::QUERY_ERROR,
+                ));
+            }
+            sql_content.push_str(&format!("\n-- Table: {}\n", table));
+
+            if include_drop {
+                sql_content.push_str(&format!("DROP TABLE IF EXISTS {};\n", qualified_table));
+            }
+
+            if include_create {
+                let rows = self
+                    .query_rows(&format!("SHOW CREATE TABLE {}", qualified_table))
+                    .await?;
+                let create = rows
+                    .first()
+                    .and_then(|row| text(row, "statement"))
+                    .unwrap_or_default();
+                sql_content.push_str(create.trim());
+                sql_content.push_str(";\n\n");
+            }
+
+            if data_mode == "no_data" {
+                continue;
+            }
+
+            // MATERIALIZED and ALIAS columns can't be written
+            let column_list = columns
+                .iter()
+                .filter(|c| !c.is_generated)
+                .map(|c| DIALECT.quote_identifier(&c.name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            // The Values format writes rows exactly as INSERT reads them back
+            let output = self
+                .post(&format!(
+                    "SELECT {} FROM {} FORMAT Values",
+                    column_list, qualified_table
+                ))
+                .await?;
+            let rows = split_values_rows(&output);
+            for batch in rows.chunks(max_insert_size) {
+                sql_content.push_str(&format!(
+                    "INSERT INTO {} ({}) VALUES\n  {};\n",
+                    qualified_table,
+                    column_list,
+                    batch.join(",\n  ")
+                ));
+            }
+            sql_content.push('\n');
+        }
+
+        Ok(sql_content)
+    }
+
+    async fn get_view_definition(&self, view: &TableRef) -> DbResult<String> {
+        let rows = self
+            .query_rows(&format!(
+                "SELECT as_select FROM system.tables WHERE database = {} AND name = {}",
+                Self::database_expression(view),
+                DIALECT.quote_string(&view.name)
+            ))
+            .await?;
+        rows.first()
+            .and_then(|row| text(row, "as_select"))
+            .filter(|definition| !definition.is_empty())
+            .ok_or_else(|| {
+                QueryError::with_code(
+                    format!("View \"{}\" not found", view),
+                    error_codes::QUERY_ERROR,
+                )
+            })
+    }
+
+    async fn alter_view(&self, view: &TableRef, query: &str) -> DbResult<()> {
+        self.post(&format!(
+            "CREATE OR REPLACE VIEW {} AS\n{}",
+            DIALECT.qualified_name(view),
+            query
+        ))
+        .await
+        .map(|_| ())
+    }
+
+    async fn create_database(
+        &self,
+        name: &str,
+        encoding: Option<&str>,
+        owner: Option<&str>,
+    ) -> DbResult<String> {
+        if encoding.is_some() || owner.is_some() {
+            return Err(QueryError::with_code(
+                "ClickHouse databases have no encoding or owner",
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let statement = format!("CREATE DATABASE {}", DIALECT.quote_identifier(name));
+        self.post(&statement).await?;
+        debug!("Created database {}", name);
+        Ok(statement)
+    }
+
+    async fn drop_database(&self, name: &str) -> DbResult<String> {
+        let current = self.database.lock().unwrap().clone();
+        check_droppable_database(name, &current, &SYSTEM_DATABASES)?;
+
+        let statement = format!("DROP DATABASE {}", DIALECT.quote_identifier(name));
+        self.post(&statement).await?;
+        debug!("Dropped database {}", name);
+        Ok(statement)
+    }
+
+    async fn build_sample_query(
+        &self,
+        table: &TableRef,
+        n: usize,
+        _method: SampleMethod,
+    ) -> DbResult<String> {
+        Ok(sampling::random_query(
+            &DIALECT.qualified_name(table),
+            "rand()",
+            n,
+        ))
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        DIALECT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_types_and_values() {
+        assert_eq!(base_type("LowCardinality(Nullable(String))"), "String");
+        assert_eq!(type_arguments("Nullable(Decimal(18, 4))"), ["18", "4"]);
+        assert_eq!(
+            type_arguments("Map(String, Array(UInt64))"),
+            ["String", "Array(UInt64)"]
+        );
+        assert_eq!(
+            clickhouse_value("Array(Nullable(Int64))", &json!(["-5", null])),
+            json!([-5, null])
+        );
+        assert_eq!(
+            clickhouse_value("UInt256", &json!("1000000000000000000000")),
+            json!("1000000000000000000000")
+        );
+        assert_eq!(
+            clickhouse_value("DateTime64(3)", &json!("2024-05-01 12:00:00.250")),
+            json!("2024-05-01 12:00:00.250")
+        );
+        let metadata = column_metadata("price", "Nullable(Decimal(10, 2))");
+        assert!(metadata.numeric && metadata.nullable == Some(true));
+        assert_eq!((metadata.precision, metadata.scale), (Some(10), Some(2)));
+
+        assert_eq!(
+            split_values_rows(b"(1,'a\\')(',[2,3]),(2,NULL,[])"),
+            ["(1,'a\\')(',[2,3])", "(2,NULL,[])"]
+        );
+        let error = clickhouse_error(500, b"Code: 159. DB::Exception: Timeout exceeded");
+        assert_eq!(error.code.as_deref(), Some(error_codes::TIMEOUT_ERROR));
+    }
+}
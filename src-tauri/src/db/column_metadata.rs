@@ -0,0 +1,62 @@
+//! Per-column metadata of query results, from the drivers' statement metadata: the grid
+//! right-aligns numbers, picks editors by type and links cells back to the table they came
+//! from.
+
+use super::connection::TableRef;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnMetadata {
+    pub name: String,
+    /// Type as the server names it, e.g. `int4` or `VARCHAR`.
+    pub type_name: String,
+    /// Whether values are numbers.
+    pub numeric: bool,
+    /// None when the server doesn't say, as for computed columns on PostgreSQL.
+    pub nullable: Option<bool>,
+    /// Table the column was read from; None for expressions.
+    pub source_table: Option<TableRef>,
+    /// Name of the column in `source_table`, which an alias may hide.
+    pub source_column: Option<String>,
+    /// Total digits of decimal columns.
+    pub precision: Option<u32>,
+    /// Digits after the decimal point of decimal columns.
+    pub scale: Option<u32>,
+}
+
+/// Precision and scale of a PostgreSQL `numeric` column from its type modifier, which is
+/// -1 for unconstrained columns.
+pub fn numeric_precision_scale(type_modifier: i32) -> (Option<u32>, Option<u32>) {
+    // The modifier is offset by the 4 bytes of the varlena header
+    if type_modifier < 4 {
+        return (None, None);
+    }
+    let modifier = (type_modifier - 4) as u32;
+    (Some(modifier >> 16), Some(modifier & 0xffff))
+}
+
+/// Precision of a MySQL `DECIMAL` column from its display length, which counts the sign
+/// of signed columns and the decimal point.
+pub fn decimal_precision(length: u32, scale: u32, unsigned: bool) -> u32 {
+    let point = u32::from(scale > 0);
+    let sign = u32::from(!unsigned);
+    length.saturating_sub(point + sign)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precision_scale() {
+        // numeric(10,2)
+        assert_eq!(
+            numeric_precision_scale((10 << 16 | 2) + 4),
+            (Some(10), Some(2))
+        );
+        assert_eq!(numeric_precision_scale(-1), (None, None));
+        // DECIMAL(10,2) and DECIMAL(5,0) UNSIGNED
+        assert_eq!(decimal_precision(12, 2, false), 10);
+        assert_eq!(decimal_precision(5, 0, true), 5);
+    }
+}
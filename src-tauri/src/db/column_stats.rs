@@ -0,0 +1,168 @@
+//! Per-column data profiles: how many values are NULL or distinct, the range, the most
+//! common values and a histogram. PostgreSQL's planner statistics are used where they
+//! exist; everything else comes from a sample of the table's rows.
+
+use serde::{Deserialize, Serialize};
+
+/// Rows read when profiling a column from a sample.
+pub const SAMPLE_ROWS: u64 = 10_000;
+
+/// Most common values reported when they are computed from a sample.
+const MOST_COMMON_LIMIT: usize = 10;
+
+/// Statistics of one column, from `DatabaseConnection::get_column_stats`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ColumnStats {
+    /// Share of rows where the column is NULL.
+    pub null_fraction: Option<f64>,
+    /// Estimated number of distinct non-null values.
+    pub distinct_count: Option<f64>,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    /// Most common values, most frequent first.
+    pub most_common: Vec<CommonValue>,
+    /// Bounds of histogram buckets holding about as many rows each, lowest first. Empty
+    /// unless the server keeps a histogram of the column.
+    pub histogram_bounds: Vec<String>,
+    /// Number of rows sampled for the figures not taken from the server's statistics.
+    pub sampled_rows: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommonValue {
+    pub value: String,
+    /// Share of all rows with this value.
+    pub frequency: f64,
+}
+
+/// Query counting the rows, non-null values and distinct values of `column` over a sample
+/// of `table`. Both are quoted; the column may be cast to text so any type can be compared.
+pub fn summary_query(table: &str, column: &str) -> String {
+    format!(
+        "SELECT COUNT(*), COUNT(v), COUNT(DISTINCT v) \
+         FROM (SELECT {} AS v FROM {} LIMIT {}) sample",
+        column, table, SAMPLE_ROWS
+    )
+}
+
+/// Query for the smallest and largest value of `column` over a sample of `table`, each
+/// rendered as text by `as_text`. Fails for types without an ordering.
+pub fn range_query(table: &str, column: &str, as_text: impl Fn(&str) -> String) -> String {
+    format!(
+        "SELECT {}, {} FROM (SELECT {} AS v FROM {} LIMIT {}) sample",
+        as_text("MIN(v)"),
+        as_text("MAX(v)"),
+        column,
+        table,
+        SAMPLE_ROWS
+    )
+}
+
+/// Query counting the most common non-null values of `column` over a sample of `table`,
+/// quoted and possibly cast like for `summary_query`.
+pub fn most_common_query(table: &str, column: &str) -> String {
+    format!(
+        "SELECT v, COUNT(*) FROM (SELECT {} AS v FROM {} LIMIT {}) sample \
+         WHERE v IS NOT NULL GROUP BY v ORDER BY 2 DESC LIMIT {}",
+        column, table, SAMPLE_ROWS, MOST_COMMON_LIMIT
+    )
+}
+
+impl ColumnStats {
+    /// Fills in the figures still missing from what the sample queries read.
+    pub fn add_sample(
+        &mut self,
+        (rows, non_null, distinct): (u64, u64, u64),
+        (min, max): (Option<String>, Option<String>),
+        most_common: Vec<(String, u64)>,
+    ) {
+        self.sampled_rows = rows;
+        self.min = min;
+        self.max = max;
+        if rows == 0 {
+            return;
+        }
+        let rows_f = rows as f64;
+        self.null_fraction
+            .get_or_insert((rows - non_null) as f64 / rows_f);
+        self.distinct_count.get_or_insert(distinct as f64);
+        if self.most_common.is_empty() {
+            self.most_common = most_common
+                .into_iter()
+                .map(|(value, count)| CommonValue {
+                    value,
+                    frequency: count as f64 / rows_f,
+                })
+                .collect();
+        }
+    }
+}
+
+/// Reads bucket bounds from a MySQL histogram (`information_schema.COLUMN_STATISTICS`).
+/// Singleton histograms list each value; equi-height ones list bucket ranges.
+pub fn mysql_histogram_bounds(histogram: &serde_json::Value) -> Vec<String> {
+    use base64::{engine::general_purpose, Engine as _};
+
+    // Strings are stored as `base64:type<N>:<data>`
+    let text = |value: &serde_json::Value| match value {
+        serde_json::Value::String(s) => s
+            .strip_prefix("base64:type")
+            .and_then(|rest| rest.split_once(':'))
+            .and_then(|(_, data)| general_purpose::STANDARD.decode(data).ok())
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .unwrap_or_else(|| s.clone()),
+        other => other.to_string(),
+    };
+    let Some(buckets) = histogram.get("buckets").and_then(|b| b.as_array()) else {
+        return Vec::new();
+    };
+    let equi_height =
+        histogram.get("histogram-type").and_then(|t| t.as_str()) == Some("equi-height");
+
+    let mut bounds = Vec::new();
+    for bucket in buckets.iter().filter_map(|b| b.as_array()) {
+        if equi_height {
+            if bounds.is_empty() {
+                bounds.extend(bucket.first().map(text));
+            }
+            bounds.extend(bucket.get(1).map(text));
+        } else {
+            bounds.extend(bucket.first().map(text));
+        }
+    }
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sample_fills_missing_figures() {
+        let mut stats = ColumnStats {
+            distinct_count: Some(1200.0),
+            ..Default::default()
+        };
+        stats.add_sample(
+            (100, 75, 3),
+            (Some("a".to_string()), Some("c".to_string())),
+            vec![("a".to_string(), 50)],
+        );
+        assert_eq!(stats.null_fraction, Some(0.25));
+        assert_eq!(stats.distinct_count, Some(1200.0));
+        assert_eq!(stats.most_common[0].frequency, 0.5);
+
+        let histogram = json!({
+            "histogram-type": "equi-height",
+            "buckets": [[1, 10, 0.5, 10], [11, 20, 1.0, 10]]
+        });
+        assert_eq!(mysql_histogram_bounds(&histogram), ["1", "10", "20"]);
+
+        let histogram = json!({
+            "histogram-type": "singleton",
+            "buckets": [["base64:type254:YWJj", 0.4], ["base64:type254:eHl6", 1.0]]
+        });
+        assert_eq!(mysql_histogram_bounds(&histogram), ["abc", "xyz"]);
+    }
+}
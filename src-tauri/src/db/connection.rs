@@ -1,12 +1,41 @@
+use super::aggregation::{AggregateRequest, ChartData};
+use super::change_stream::ChangeSink;
+use super::column_metadata::ColumnMetadata;
+use super::column_stats::ColumnStats;
+use super::deadlocks::DeadlockReport;
+use super::index_advisor::IndexSuggestion;
+use super::keys::{KeyPage, KeyValue};
+use super::routines::RoutineResult;
+use super::sampling::SampleMethod;
+use super::session_variables::SessionVariable;
+use super::sql_dialect::SqlDialect;
+use super::text_search::TextSearchRequest;
+use super::triggers::TriggerSpec;
+use super::users::{DbUser, PrivilegeGrant};
+use crate::redact::redact_secret;
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Maximum number of rows returned from a single query to prevent memory exhaustion.
 pub const MAX_QUERY_ROWS: usize = 10_000;
 
-/// Default timeout for database operations.
+/// Schema (database on MySQL) holding tables materialized for ad-hoc analysis.
+pub const SCRATCH_SCHEMA: &str = "bloatsql_scratch";
+
+/// Default timeout for database operations, and for user statements on connections that
+/// don't set their own.
 pub const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Added to the client-side timeout of statements the server also times out, so the
+/// server's own error arrives first.
+pub const SERVER_TIMEOUT_GRACE: Duration = Duration::from_secs(5);
+
+/// How long a liveness check waits for the server before treating the connection as broken.
+pub const LIVENESS_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Result of executing a SQL query.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryResult {
@@ -20,6 +49,236 @@ pub struct QueryResult {
     pub execution_time: u128,
     /// Whether results were truncated due to MAX_QUERY_ROWS limit.
     pub truncated: bool,
+    /// Rows inserted, updated or deleted, for statements that report it.
+    pub affected_rows: Option<u64>,
+    /// Where the time went; `execution_time` is everything but `acquire`.
+    #[serde(default)]
+    pub timing: QueryTiming,
+    /// Type, nullability and source of each column, in column order. Empty where the
+    /// driver doesn't report them.
+    #[serde(default)]
+    pub column_metadata: Vec<ColumnMetadata>,
+}
+
+/// Breakdown of a query's time in milliseconds, to tell network, server and app apart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryTiming {
+    /// Waiting for a connection from the pool or for the session's connection.
+    pub acquire: u128,
+    /// Until the server returned the first row, or finished a statement without rows.
+    pub execution: u128,
+    /// Reading the remaining rows.
+    pub fetch: u128,
+    /// Converting rows to JSON.
+    pub conversion: u128,
+}
+
+/// Measures the phases of a query for `QueryTiming`.
+///
+/// Each call ends the current phase and adds the time since the previous call to it, so
+/// phases that alternate (fetching and converting row by row) are summed.
+pub struct QueryTimer {
+    start: Instant,
+    last: Instant,
+    acquire: Duration,
+    execution: Duration,
+    fetch: Duration,
+    conversion: Duration,
+}
+
+impl QueryTimer {
+    /// Starts timing; the first phase is acquiring the connection.
+    pub fn start() -> Self {
+        let now = Instant::now();
+        QueryTimer {
+            start: now,
+            last: now,
+            acquire: Duration::ZERO,
+            execution: Duration::ZERO,
+            fetch: Duration::ZERO,
+            conversion: Duration::ZERO,
+        }
+    }
+
+    fn lap(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+        elapsed
+    }
+
+    pub fn acquired(&mut self) {
+        let elapsed = self.lap();
+        self.acquire += elapsed;
+    }
+
+    pub fn executed(&mut self) {
+        let elapsed = self.lap();
+        self.execution += elapsed;
+    }
+
+    pub fn fetched(&mut self) {
+        let elapsed = self.lap();
+        self.fetch += elapsed;
+    }
+
+    pub fn converted(&mut self) {
+        let elapsed = self.lap();
+        self.conversion += elapsed;
+    }
+
+    /// Milliseconds since the connection was acquired, for `QueryResult::execution_time`.
+    pub fn execution_time(&self) -> u128 {
+        self.start
+            .elapsed()
+            .saturating_sub(self.acquire)
+            .as_millis()
+    }
+
+    pub fn timing(&self) -> QueryTiming {
+        QueryTiming {
+            acquire: self.acquire.as_millis(),
+            execution: self.execution.as_millis(),
+            fetch: self.fetch.as_millis(),
+            conversion: self.conversion.as_millis(),
+        }
+    }
+}
+
+/// Rows converted to JSON per blocking task by `convert_rows`.
+pub const CONVERSION_BATCH_SIZE: usize = 1_000;
+
+/// Converts driver rows to JSON values.
+///
+/// Results of up to `CONVERSION_BATCH_SIZE` rows are converted inline. Larger ones are split
+/// into batches converted in parallel on the blocking thread pool, so converting wide results
+/// doesn't stall other tasks on the async runtime.
+///
+/// # Errors
+/// Returns `QUERY_ERROR` if a conversion task panicked.
+pub async fn convert_rows<R, F>(rows: Vec<R>, convert: F) -> DbResult<Vec<serde_json::Value>>
+where
+    R: Send + 'static,
+    F: Fn(&R) -> serde_json::Value + Send + Sync + 'static,
+{
+    if rows.len() <= CONVERSION_BATCH_SIZE {
+        return Ok(rows.iter().map(convert).collect());
+    }
+
+    let row_count = rows.len();
+    let convert = Arc::new(convert);
+    let mut rows = rows.into_iter();
+    let mut batches = Vec::with_capacity(row_count.div_ceil(CONVERSION_BATCH_SIZE));
+    loop {
+        let batch: Vec<R> = rows.by_ref().take(CONVERSION_BATCH_SIZE).collect();
+        if batch.is_empty() {
+            break;
+        }
+        let convert = convert.clone();
+        batches.push(tokio::task::spawn_blocking(move || {
+            batch.iter().map(|row| convert(row)).collect::<Vec<_>>()
+        }));
+    }
+
+    let mut converted = Vec::with_capacity(row_count);
+    for batch in batches {
+        let values = batch.await.map_err(|e| {
+            QueryError::with_code(
+                format!("Row conversion failed: {}", e),
+                error_codes::QUERY_ERROR,
+            )
+        })?;
+        converted.extend(values);
+    }
+    Ok(converted)
+}
+
+/// A batch of rows delivered by `DatabaseConnection::execute_query_streaming`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowChunk {
+    /// Column names in order; only set on the first chunk.
+    pub columns: Option<Vec<String>>,
+    /// Row data as JSON values.
+    pub rows: Vec<serde_json::Value>,
+}
+
+/// Callback receiving streamed rows. Returning `false` stops reading further rows.
+pub type RowChunkSink<'a> = &'a mut (dyn FnMut(RowChunk) -> bool + Send);
+
+/// Groups rows into fixed-size `RowChunk`s for a `RowChunkSink`.
+pub struct ChunkedRows<'a> {
+    sink: RowChunkSink<'a>,
+    chunk_size: usize,
+    pending: RowChunk,
+    row_count: usize,
+    stopped: bool,
+}
+
+impl<'a> ChunkedRows<'a> {
+    pub fn new(columns: Vec<String>, chunk_size: usize, sink: RowChunkSink<'a>) -> Self {
+        let chunk_size = chunk_size.max(1);
+        ChunkedRows {
+            sink,
+            chunk_size,
+            pending: RowChunk {
+                columns: Some(columns),
+                rows: Vec::with_capacity(chunk_size),
+            },
+            row_count: 0,
+            stopped: false,
+        }
+    }
+
+    /// Adds a row, sending a chunk once it is full.
+    ///
+    /// Returns `false` once the sink has asked to stop.
+    pub fn push(&mut self, row: serde_json::Value) -> bool {
+        self.pending.rows.push(row);
+        self.row_count += 1;
+        if self.pending.rows.len() >= self.chunk_size {
+            let chunk = std::mem::replace(
+                &mut self.pending,
+                RowChunk {
+                    columns: None,
+                    rows: Vec::with_capacity(self.chunk_size),
+                },
+            );
+            self.stopped = !(self.sink)(chunk);
+        }
+        !self.stopped
+    }
+
+    /// Sends the remaining rows. The first chunk is always sent, even if empty, so the
+    /// consumer learns the columns.
+    ///
+    /// Returns the number of rows read and whether the sink stopped reading early.
+    pub fn finish(mut self) -> (usize, bool) {
+        if !self.stopped && (!self.pending.rows.is_empty() || self.pending.columns.is_some()) {
+            (self.sink)(self.pending);
+        }
+        (self.row_count, self.stopped)
+    }
+}
+
+/// A query result held open on the server and read a window at a time.
+///
+/// Returned by `DatabaseConnection::open_cursor`; the cursor keeps a dedicated connection
+/// until it is closed.
+#[async_trait::async_trait]
+pub trait ResultCursor: Send + Sync {
+    /// Column names in order.
+    fn columns(&self) -> &[String];
+
+    /// Returns up to `count` rows starting at row `offset` (zero-based). Fewer than `count`
+    /// rows means the end of the result was reached.
+    ///
+    /// # Errors
+    /// `QUERY_ERROR` if the cursor was closed, or if it is forward-only and `offset` is
+    /// before rows that were already read.
+    async fn fetch(&self, offset: usize, count: usize) -> DbResult<Vec<serde_json::Value>>;
+
+    /// Closes the cursor and releases its connection.
+    async fn close(&self) -> DbResult<()>;
 }
 
 /// Error returned from database operations.
@@ -71,6 +330,15 @@ impl QueryError {
         self.hint = Some(hint.into());
         self
     }
+
+    /// Removes `secret` from the message, detail and hint, in case the driver echoed a
+    /// connection parameter.
+    pub fn without_secret(mut self, secret: &str) -> Self {
+        self.message = redact_secret(&self.message, secret);
+        self.detail = self.detail.map(|detail| redact_secret(&detail, secret));
+        self.hint = self.hint.map(|hint| redact_secret(&hint, secret));
+        self
+    }
 }
 
 /// Error codes for consistent error handling across drivers.
@@ -82,6 +350,219 @@ pub mod error_codes {
     pub const SSL_ERROR: &str = "SSL_ERROR";
     pub const TLS_ERROR: &str = "TLS_ERROR";
     pub const INVALID_DB_TYPE: &str = "INVALID_DB_TYPE";
+    pub const INVALID_POOL_SIZE: &str = "INVALID_POOL_SIZE";
+    pub const AUTH_ERROR: &str = "AUTH_ERROR";
+    pub const UNSUPPORTED: &str = "UNSUPPORTED";
+}
+
+/// How a driver secures its connection, parsed from the `ssl_mode` connection setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SslMode {
+    /// Plain TCP.
+    Disabled,
+    /// TLS when the server supports it, falling back to plain TCP. The certificate is not
+    /// verified.
+    Preferred,
+    /// TLS without verifying the certificate: protects against eavesdropping, but not
+    /// against a server impersonating the real one.
+    Required,
+    /// TLS with the certificate chain verified against the system trust store and the
+    /// connection's CA certificate, if it has one.
+    VerifyCa,
+    /// Like `VerifyCa`, and the certificate must also be issued for the host name.
+    VerifyFull,
+}
+
+impl SslMode {
+    /// Parses an `ssl_mode` setting; libpq-style spellings (`require`, `verify_ca`, ...) are
+    /// accepted too.
+    ///
+    /// # Errors
+    /// Returns `SSL_ERROR` for unknown modes.
+    pub fn parse(mode: &str) -> DbResult<Self> {
+        match mode.to_lowercase().replace('_', "-").as_str() {
+            "disabled" | "disable" => Ok(SslMode::Disabled),
+            "preferred" | "prefer" => Ok(SslMode::Preferred),
+            "required" | "require" => Ok(SslMode::Required),
+            "verify-ca" => Ok(SslMode::VerifyCa),
+            "verify-full" => Ok(SslMode::VerifyFull),
+            _ => Err(QueryError::with_code(
+                format!(
+                    "Unknown SSL mode '{}'. Supported modes: disabled, preferred, required, verify-ca, verify-full",
+                    mode
+                ),
+                error_codes::SSL_ERROR,
+            )),
+        }
+    }
+
+    /// The `ssl_mode` setting for this mode, as `parse` reads it back.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SslMode::Disabled => "disabled",
+            SslMode::Preferred => "preferred",
+            SslMode::Required => "required",
+            SslMode::VerifyCa => "verify-ca",
+            SslMode::VerifyFull => "verify-full",
+        }
+    }
+
+    pub fn uses_tls(self) -> bool {
+        self != SslMode::Disabled
+    }
+
+    /// Whether a failed TLS handshake may fall back to plain TCP.
+    pub fn allows_fallback(self) -> bool {
+        self == SslMode::Preferred
+    }
+
+    pub fn verifies_certificate(self) -> bool {
+        matches!(self, SslMode::VerifyCa | SslMode::VerifyFull)
+    }
+
+    pub fn verifies_hostname(self) -> bool {
+        self == SslMode::VerifyFull
+    }
+}
+
+/// Reads the PEM file of a connection's custom CA certificate (`ssl_ca_cert`), which the
+/// `verify-ca` and `verify-full` modes then trust in addition to the system trust store.
+///
+/// # Errors
+/// Returns `TLS_ERROR` if the file can't be read or holds no PEM certificate.
+pub fn read_ca_cert(path: &str) -> DbResult<native_tls::Certificate> {
+    let pem = std::fs::read(path).map_err(|e| {
+        QueryError::with_code(
+            format!("Failed to read CA certificate {}: {}", path, e),
+            error_codes::TLS_ERROR,
+        )
+    })?;
+    native_tls::Certificate::from_pem(&pem).map_err(|e| {
+        QueryError::with_code(
+            format!("Invalid CA certificate {}: {}", path, e),
+            error_codes::TLS_ERROR,
+        )
+    })
+}
+
+/// Bounds on the server connections a driver pools, from a connection's `pool_min_size` and
+/// `pool_max_size`; unset bounds use the driver's defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolSize {
+    pub min: Option<u32>,
+    pub max: Option<u32>,
+}
+
+impl PoolSize {
+    pub fn new(min: Option<u32>, max: Option<u32>) -> Self {
+        PoolSize { min, max }
+    }
+
+    /// Whether both bounds are left to the driver.
+    pub fn is_default(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+
+    /// Returns the `(min, max)` bounds, filling unset ones from the driver's defaults. An
+    /// unset minimum never exceeds the maximum.
+    ///
+    /// # Errors
+    /// Returns `INVALID_POOL_SIZE` if the maximum is 0 or below the minimum.
+    pub fn resolve(self, default_min: usize, default_max: usize) -> DbResult<(usize, usize)> {
+        let max = self.max.map_or(default_max, |max| max as usize);
+        let min = self.min.map_or(default_min.min(max), |min| min as usize);
+        if max == 0 || min > max {
+            return Err(QueryError::with_code(
+                format!(
+                    "Invalid pool size: the maximum ({}) must be at least 1 and the minimum ({})",
+                    max, min
+                ),
+                error_codes::INVALID_POOL_SIZE,
+            ));
+        }
+        Ok((min, max))
+    }
+}
+
+/// A table, optionally qualified by its schema (the database, for MariaDB/MySQL).
+///
+/// Unqualified tables are resolved against the session's default schema. Deserializes from
+/// either a bare table name or a `{ "schema": ..., "name": ... }` object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "TableRefRepr")]
+pub struct TableRef {
+    pub schema: Option<String>,
+    pub name: String,
+}
+
+impl TableRef {
+    pub fn new(schema: Option<String>, name: impl Into<String>) -> Self {
+        TableRef {
+            schema: schema.filter(|s| !s.is_empty()),
+            name: name.into(),
+        }
+    }
+
+    /// A table in the session's default schema.
+    pub fn unqualified(name: impl Into<String>) -> Self {
+        Self::new(None, name)
+    }
+}
+
+impl std::fmt::Display for TableRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.schema {
+            Some(schema) => write!(f, "{}.{}", schema, self.name),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TableRefRepr {
+    Name(String),
+    Qualified {
+        #[serde(default)]
+        schema: Option<String>,
+        name: String,
+    },
+}
+
+impl From<TableRefRepr> for TableRef {
+    fn from(repr: TableRefRepr) -> Self {
+        match repr {
+            TableRefRepr::Name(name) => TableRef::unqualified(name),
+            TableRefRepr::Qualified { schema, name } => TableRef::new(schema, name),
+        }
+    }
+}
+
+/// A change to a table whose privileges can be checked with
+/// `DatabaseConnection::check_privileges`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TableOperation {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Alter,
+    Drop,
+}
+
+impl TableOperation {
+    /// The SQL privilege the operation needs.
+    pub fn privilege(self) -> &'static str {
+        match self {
+            TableOperation::Select => "SELECT",
+            TableOperation::Insert => "INSERT",
+            TableOperation::Update => "UPDATE",
+            TableOperation::Delete => "DELETE",
+            TableOperation::Alter => "ALTER",
+            TableOperation::Drop => "DROP",
+        }
+    }
 }
 
 /// Metadata about a table column.
@@ -101,15 +582,73 @@ pub struct TableColumn {
     pub character_maximum_length: Option<i64>,
     /// Numeric precision (for INT, DECIMAL, etc.).
     pub numeric_precision: Option<i64>,
+    /// Whether the database numbers the column itself: an identity or serial column
+    /// (PostgreSQL) or an AUTO_INCREMENT column (MySQL).
+    #[serde(default)]
+    pub is_identity: bool,
+    /// `ALWAYS` or `BY DEFAULT` for PostgreSQL identity columns. Values can't be given for
+    /// `ALWAYS` columns without overriding.
+    #[serde(default)]
+    pub identity_generation: Option<String>,
+    /// Whether the column's value is computed from other columns, so it can't be written.
+    #[serde(default)]
+    pub is_generated: bool,
+    /// Expression a generated column is computed with.
+    #[serde(default)]
+    pub generation_expression: Option<String>,
+    /// `STORED` or `VIRTUAL` for generated columns.
+    #[serde(default)]
+    pub generation_kind: Option<String>,
+    /// Values an ENUM or SET column (MySQL) accepts. A SET holds any comma-separated
+    /// combination of them.
+    #[serde(default)]
+    pub allowed_values: Option<Vec<String>>,
+}
+
+impl TableColumn {
+    /// Checks a value against `allowed_values`, which MySQL compares case-insensitively.
+    /// Outside strict mode MySQL would store a mismatch as `''` rather than fail.
+    pub fn check_allowed_value(&self, value: &str) -> Result<(), String> {
+        let Some(allowed) = &self.allowed_values else {
+            return Ok(());
+        };
+        let is_allowed = |value: &str| {
+            allowed
+                .iter()
+                .any(|allowed| allowed.to_lowercase() == value.to_lowercase())
+        };
+        let is_set = self.data_type.to_lowercase().starts_with("set(");
+        let invalid = if is_set {
+            value
+                .split(',')
+                .find(|member| !member.is_empty() && !is_allowed(member))
+        } else {
+            Some(value).filter(|value| !is_allowed(value))
+        };
+        match invalid {
+            Some(invalid) => Err(format!(
+                "'{}' is not one of the allowed values: {}",
+                invalid,
+                allowed.join(", ")
+            )),
+            None => Ok(()),
+        }
+    }
 }
 
 /// Represents a foreign key relationship between tables.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableRelationship {
+    /// Schema of the source table.
+    #[serde(default)]
+    pub from_schema: Option<String>,
     /// Source table name.
     pub from_table: String,
     /// Source column name.
     pub from_column: String,
+    /// Schema of the referenced table.
+    #[serde(default)]
+    pub to_schema: Option<String>,
     /// Referenced table name.
     pub to_table: String,
     /// Referenced column name.
@@ -118,8 +657,228 @@ pub struct TableRelationship {
     pub constraint_name: String,
 }
 
+/// Aggregated statistics for one normalized statement, read from `pg_stat_statements` or
+/// MySQL's `events_statements_summary_by_digest`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryStatistic {
+    /// Database the statement ran in, when the server records it.
+    pub database: Option<String>,
+    /// Statement text with literals replaced by placeholders.
+    pub query: String,
+    /// Number of times the statement ran.
+    pub calls: u64,
+    /// Total execution time in milliseconds.
+    pub total_time_ms: f64,
+    /// Mean execution time in milliseconds.
+    pub mean_time_ms: f64,
+    /// Rows returned or affected, summed over all calls.
+    pub rows: u64,
+}
+
+/// An open transaction on the server, from `pg_stat_activity` or InnoDB's `INNODB_TRX`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveTransaction {
+    /// Backend process id (PostgreSQL) or connection id (MariaDB/MySQL) of the session.
+    pub session_id: u64,
+    pub user: Option<String>,
+    /// Client address or host of the session.
+    pub client: Option<String>,
+    pub database: Option<String>,
+    /// What the session is doing, e.g. "active" or "idle in transaction".
+    pub state: String,
+    /// When the transaction started, as printed by the server.
+    pub started_at: Option<String>,
+    /// Seconds since the transaction started.
+    pub age_secs: u64,
+    /// Statement being run, or for idle sessions on PostgreSQL the last one run.
+    pub statement: Option<String>,
+}
+
+/// Server health figures for a dashboard, from `DatabaseConnection::get_health_snapshot`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthSnapshot {
+    /// Client sessions connected to the server.
+    pub total_connections: u64,
+    /// Client sessions currently running a statement.
+    pub active_connections: u64,
+    pub max_connections: Option<u64>,
+    /// Share of page reads served from the buffer cache, from 0 to 1.
+    pub cache_hit_ratio: Option<f64>,
+    /// Seconds this server lags behind its primary; `None` if it is not a replica or the
+    /// lag is unknown.
+    pub replication_lag_secs: Option<f64>,
+    /// The oldest open transaction in another session.
+    pub longest_transaction: Option<ActiveTransaction>,
+    /// Size of each database, largest first.
+    pub database_sizes: Vec<DatabaseSize>,
+}
+
+/// Disk space used by one database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseSize {
+    pub name: String,
+    pub bytes: u64,
+}
+
+/// Roles the session can act as, from `DatabaseConnection::get_roles`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRoles {
+    /// User the connection logged in as.
+    pub user: String,
+    /// Role set with `set_role`, if any.
+    pub role: Option<String>,
+    /// Roles `user` may switch to.
+    pub available: Vec<String>,
+}
+
+/// Row-level security of a table, from `DatabaseConnection::get_rls_policies`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RlsStatus {
+    /// Whether row-level security is enabled; without policies it then hides every row.
+    pub enabled: bool,
+    /// Whether it also applies to the table's owner (`FORCE ROW LEVEL SECURITY`).
+    pub forced: bool,
+    /// Whether the current role is exempt: a superuser, `BYPASSRLS`, or the owner of a table
+    /// without forced row-level security.
+    pub bypassed: bool,
+    pub policies: Vec<RlsPolicy>,
+}
+
+/// A row-level security policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RlsPolicy {
+    pub name: String,
+    /// Permissive policies are combined with OR, restrictive ones with AND.
+    pub permissive: bool,
+    /// Roles the policy applies to; `public` for everyone.
+    pub roles: Vec<String>,
+    /// Command the policy applies to: `ALL`, `SELECT`, `INSERT`, `UPDATE` or `DELETE`.
+    pub command: String,
+    /// Condition rows must meet to be visible (`USING`).
+    pub using: Option<String>,
+    /// Condition new rows must meet (`WITH CHECK`).
+    pub with_check: Option<String>,
+}
+
+/// Storage settings of a table, from `DatabaseConnection::get_table_options`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TableOptions {
+    /// Storage engine (MySQL, e.g. `InnoDB`) or table access method (PostgreSQL, `heap`).
+    pub engine: Option<String>,
+    /// Row format (MySQL), e.g. `Dynamic` or `Compressed`.
+    pub row_format: Option<String>,
+    /// `permanent`, `unlogged` or `temporary` (PostgreSQL).
+    pub persistence: Option<String>,
+    pub tablespace: Option<String>,
+    pub collation: Option<String>,
+    /// Percentage of each page filled by inserts (PostgreSQL), if set.
+    pub fillfactor: Option<u32>,
+    /// Every storage parameter by name: `reloptions` (PostgreSQL) or `CREATE_OPTIONS`
+    /// (MySQL). Flags without a value are `true`.
+    pub parameters: BTreeMap<String, String>,
+}
+
+impl TableOptions {
+    /// Adds `name=value` storage parameters.
+    pub fn add_parameters<'a>(&mut self, parameters: impl IntoIterator<Item = &'a str>) {
+        for parameter in parameters {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, "true"));
+            self.parameters.insert(name.to_string(), value.to_string());
+        }
+        if let Some(fillfactor) = self.parameters.get("fillfactor") {
+            self.fillfactor = fillfactor.parse().ok();
+        }
+    }
+}
+
+/// Refuses to drop one of the server's `system_databases` or `current`, the database the
+/// connection is using.
+pub fn check_droppable_database(
+    name: &str,
+    current: &str,
+    system_databases: &[&str],
+) -> DbResult<()> {
+    let refuse = |message: String| Err(QueryError::with_code(message, error_codes::QUERY_ERROR));
+    if system_databases.contains(&name) {
+        return refuse(format!(
+            "\"{}\" is a system database and can't be dropped",
+            name
+        ));
+    }
+    if name == current {
+        return refuse(format!(
+            "\"{}\" is in use by this connection; switch to another database first",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Wasted space (in bytes) below which a table is not worth maintaining.
+const BLOAT_MIN_WASTED_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Share of a table's space that must be wasted before maintenance is recommended.
+const BLOAT_MIN_WASTED_RATIO: f64 = 0.2;
+
+/// Space a table wastes on dead rows or fragmentation, with the maintenance that reclaims it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableBloat {
+    pub table: TableRef,
+    /// Estimated live rows.
+    pub live_rows: u64,
+    /// Estimated dead rows awaiting VACUUM (PostgreSQL only).
+    pub dead_rows: Option<u64>,
+    /// Size of the table including its indexes, in bytes.
+    pub table_bytes: u64,
+    /// Estimated reclaimable space, in bytes.
+    pub wasted_bytes: u64,
+    /// `wasted_bytes` as a share of `table_bytes`, from 0 to 1.
+    pub wasted_ratio: f64,
+    /// Statement to run, when enough space is wasted to be worth it.
+    pub recommendation: Option<String>,
+}
+
+impl TableBloat {
+    pub fn new(
+        table: TableRef,
+        live_rows: u64,
+        dead_rows: Option<u64>,
+        table_bytes: u64,
+        wasted_bytes: u64,
+    ) -> Self {
+        let wasted_ratio = if table_bytes > 0 {
+            (wasted_bytes as f64 / table_bytes as f64).min(1.0)
+        } else {
+            0.0
+        };
+        TableBloat {
+            table,
+            live_rows,
+            dead_rows,
+            table_bytes,
+            wasted_bytes,
+            wasted_ratio,
+            recommendation: None,
+        }
+    }
+
+    /// Returns true if the wasted space is large enough, absolutely and relatively, to be
+    /// worth reclaiming.
+    pub fn needs_maintenance(&self) -> bool {
+        self.wasted_bytes >= BLOAT_MIN_WASTED_BYTES && self.wasted_ratio >= BLOAT_MIN_WASTED_RATIO
+    }
+}
+
 pub type DbResult<T> = Result<T, QueryError>;
 
+/// Error for a feature the connected database doesn't have.
+pub fn unsupported<T>(feature: &str) -> DbResult<T> {
+    Err(QueryError::with_code(
+        format!("{} is not available for this database", feature),
+        error_codes::UNSUPPORTED,
+    ))
+}
+
 /// Trait defining the interface for database connections.
 ///
 /// All methods are async and should handle timeouts internally.
@@ -127,6 +886,7 @@ pub type DbResult<T> = Result<T, QueryError>;
 ///
 /// # Error Handling
 /// All methods return `DbResult<T>` with appropriate error codes from `error_codes` module.
+/// Methods for features not every database has return `UNSUPPORTED` unless implemented.
 ///
 /// # Timeout Behavior
 /// Long-running operations should respect `DEFAULT_QUERY_TIMEOUT`.
@@ -138,6 +898,14 @@ pub trait DatabaseConnection: Send + Sync {
     /// Returns `CONNECTION_ERROR` if the connection is not valid.
     async fn test_connection(&self) -> DbResult<()>;
 
+    /// Checks the connections to the server and replaces broken ones, e.g. after the
+    /// machine resumed from sleep, so the next query doesn't fail with a broken pipe.
+    ///
+    /// # Returns
+    /// Returns true if the session connection was re-established, which loses session
+    /// state such as `SET` variables, temporary tables and open transactions.
+    async fn reconnect_if_broken(&self) -> DbResult<bool>;
+
     /// Executes a SQL query and returns the results.
     ///
     /// Results are limited to `MAX_QUERY_ROWS` rows. Check `QueryResult::truncated`
@@ -148,6 +916,35 @@ pub trait DatabaseConnection: Send + Sync {
     /// - `TIMEOUT_ERROR` if query exceeds timeout
     async fn execute_query(&self, query: &str) -> DbResult<QueryResult>;
 
+    /// Executes a SQL query, passing rows to `on_chunk` in chunks of up to `chunk_size` rows
+    /// as they are read instead of collecting them, so memory use stays bounded.
+    ///
+    /// Unlike `execute_query`, results are not limited to `MAX_QUERY_ROWS`; reading stops
+    /// when `on_chunk` returns `false`, in which case the result is marked truncated.
+    /// The returned `QueryResult` contains no rows; `row_count` is the number of rows read.
+    ///
+    /// # Errors
+    /// Same as `execute_query`.
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult>;
+
+    /// Runs a single query inside a server-side cursor on a dedicated connection, so huge
+    /// results can be browsed without loading them or truncating at `MAX_QUERY_ROWS`.
+    ///
+    /// # Note
+    /// PostgreSQL cursors can move to any offset. MariaDB/MySQL have no cursors for ad-hoc
+    /// queries, so the result is streamed and can only be read forward.
+    ///
+    /// # Errors
+    /// Same as `execute_query`.
+    async fn open_cursor(&self, _query: &str) -> DbResult<Box<dyn ResultCursor>> {
+        unsupported("Server-side cursors")
+    }
+
     /// Returns a list of table names in the current database.
     async fn list_tables(&self) -> DbResult<Vec<String>>;
 
@@ -160,15 +957,98 @@ pub trait DatabaseConnection: Send + Sync {
     /// For PostgreSQL, this creates a new connection as USE is not supported.
     async fn change_database(&self, database_name: &str) -> DbResult<()>;
 
+    /// Makes `schema` the default for unqualified table names in this session.
+    ///
+    /// # Note
+    /// For PostgreSQL this sets `search_path`; MariaDB/MySQL treat schemas as databases,
+    /// so this switches the current database.
+    async fn set_default_schema(&self, schema: &str) -> DbResult<()>;
+
     /// Returns the name of the currently selected database.
     async fn get_current_database(&self) -> DbResult<String>;
 
     /// Returns column metadata for the specified table.
-    async fn get_table_columns(&self, table_name: &str) -> DbResult<Vec<TableColumn>>;
+    async fn get_table_columns(&self, table: &TableRef) -> DbResult<Vec<TableColumn>>;
 
     /// Returns foreign key relationships for all tables in current database.
     async fn get_table_relationships(&self) -> DbResult<Vec<TableRelationship>>;
 
+    /// Returns whether the current user may perform `operation` on `table`, so edits can
+    /// be refused up front instead of failing on execute.
+    ///
+    /// # Note
+    /// PostgreSQL has no ALTER/DROP privilege; those require owning the table (directly or
+    /// through a role). MariaDB/MySQL check global, database and table grants, but not
+    /// roles or column-level grants.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the table does not exist (PostgreSQL) or the check fails.
+    async fn check_privileges(&self, table: &TableRef, operation: TableOperation)
+        -> DbResult<bool>;
+
+    /// Returns the `limit` statements with the highest total execution time since the
+    /// server's statistics were last reset.
+    ///
+    /// # Note
+    /// PostgreSQL needs the `pg_stat_statements` extension; MariaDB/MySQL need
+    /// `performance_schema` enabled with the statement digest consumer.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if statistics are not available or not readable.
+    async fn get_query_statistics(&self, _limit: usize) -> DbResult<Vec<QueryStatistic>> {
+        unsupported("Query statistics")
+    }
+
+    /// Explains `query` without running it and reports large tables it reads in full, with
+    /// an index on the columns the query filters or joins them on.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if `query` is not a single explainable statement (see
+    /// `statement::is_explainable`) or EXPLAIN fails.
+    async fn suggest_indexes(&self, _query: &str) -> DbResult<Vec<IndexSuggestion>> {
+        unsupported("Index suggestions")
+    }
+
+    /// Reports wasted space per table in the current database, most wasted first.
+    ///
+    /// # Note
+    /// PostgreSQL estimates waste from the dead row counts in `pg_stat_user_tables`, which
+    /// are approximate until the table is analyzed. MariaDB/MySQL report `DATA_FREE`, which
+    /// for tables in a shared tablespace is the free space of the whole tablespace.
+    async fn get_bloat_report(&self) -> DbResult<Vec<TableBloat>> {
+        unsupported("The bloat report")
+    }
+
+    /// Returns the most recent deadlocks with the statements involved.
+    ///
+    /// # Note
+    /// InnoDB only keeps the latest deadlock, and reading it needs the PROCESS privilege.
+    /// PostgreSQL only writes deadlocks to the server log, which is read when the server
+    /// logs to a file and the user may read server files; otherwise only the count is
+    /// returned.
+    async fn get_deadlocks(&self) -> DbResult<DeadlockReport> {
+        unsupported("The deadlock report")
+    }
+
+    /// Lists transactions open in other sessions, oldest first.
+    ///
+    /// # Note
+    /// Users without elevated privileges may only see their own sessions. MariaDB/MySQL only
+    /// report InnoDB transactions.
+    async fn get_active_transactions(&self) -> DbResult<Vec<ActiveTransaction>> {
+        unsupported("Listing transactions")
+    }
+
+    /// Gathers connection counts, cache hit ratio, replication lag, the longest open
+    /// transaction and database sizes in one call.
+    ///
+    /// # Note
+    /// Figures the user may not read (e.g. replication status without the REPLICATION
+    /// CLIENT privilege) are left empty instead of failing the snapshot.
+    async fn get_health_snapshot(&self) -> DbResult<HealthSnapshot> {
+        unsupported("The health dashboard")
+    }
+
     /// Closes the database connection and releases resources.
     async fn disconnect(&self) -> DbResult<()>;
 
@@ -178,21 +1058,23 @@ pub trait DatabaseConnection: Send + Sync {
     /// * `include_drop` - Include DROP TABLE statements
     /// * `include_create` - Include CREATE TABLE statements
     /// * `data_mode` - "insert", "replace", "insert_ignore", or "no_data"
-    /// * `selected_tables` - Tables to export (empty = all tables)
+    /// * `selected_tables` - Tables to export (empty = all tables in the default schema)
     /// * `max_insert_size` - Maximum rows per INSERT statement
     async fn export_database_with_options(
         &self,
-        include_drop: bool,
-        include_create: bool,
-        data_mode: &str,
-        selected_tables: &[String],
-        max_insert_size: usize,
-    ) -> DbResult<String>;
+        _include_drop: bool,
+        _include_create: bool,
+        _data_mode: &str,
+        _selected_tables: &[TableRef],
+        _max_insert_size: usize,
+    ) -> DbResult<String> {
+        unsupported("Exporting the database")
+    }
 
     /// Updates a single cell value using primary key.
     ///
     /// # Arguments
-    /// * `table_name` - Name of the table
+    /// * `table` - Table to update
     /// * `column_name` - Column to update
     /// * `new_value` - New value (None for NULL, Some(value) for a string value)
     /// * `primary_key_column` - Name of the primary key column
@@ -205,10 +1087,455 @@ pub trait DatabaseConnection: Send + Sync {
     /// This method uses parameterized queries to prevent SQL injection.
     async fn update_cell(
         &self,
-        table_name: &str,
-        column_name: &str,
-        new_value: Option<&str>,
-        primary_key_column: &str,
-        primary_key_value: &str,
-    ) -> DbResult<String>;
+        _table: &TableRef,
+        _column_name: &str,
+        _new_value: Option<&str>,
+        _primary_key_column: &str,
+        _primary_key_value: &str,
+    ) -> DbResult<String> {
+        unsupported("Editing cells")
+    }
+
+    /// Inserts `rows` of text values (None for NULL) into `columns` of `table`, in one
+    /// transaction and batches of at most `bulk_insert::INSERT_BATCH_ROWS` rows.
+    ///
+    /// # Returns
+    /// Returns the number of inserted rows.
+    ///
+    /// # Security
+    /// This method uses parameterized queries to prevent SQL injection.
+    async fn insert_rows(
+        &self,
+        _table: &TableRef,
+        _columns: &[String],
+        _rows: &[Vec<Option<String>>],
+    ) -> DbResult<u64> {
+        unsupported("Inserting rows")
+    }
+
+    /// Calls a stored procedure or function on the session connection.
+    ///
+    /// # Arguments
+    /// * `routine` - Procedure or function to call
+    /// * `args` - Text values (None for NULL) by parameter name; unnamed parameters are
+    ///   given as `$1`, `$2`, ... Parameters with defaults may be omitted.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the routine doesn't exist or `args` don't fit any of its
+    /// signatures.
+    async fn call_routine(
+        &self,
+        _routine: &TableRef,
+        _args: &HashMap<String, Option<String>>,
+    ) -> DbResult<RoutineResult> {
+        unsupported("Calling routines")
+    }
+
+    /// Returns the source of a stored procedure or function as the statement that creates
+    /// it, which `save_routine_definition` accepts back. All overloads of a PostgreSQL
+    /// routine are included, one statement each.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the routine doesn't exist or its source can't be read.
+    async fn get_routine_definition(&self, _routine: &TableRef) -> DbResult<String> {
+        unsupported("Routine definitions")
+    }
+
+    /// Runs the statements in `source` that create or replace stored procedures and
+    /// functions.
+    ///
+    /// # Arguments
+    /// * `replaces` - Routine being edited, if any. MySQL has no `CREATE OR REPLACE` for
+    ///   routines, so it is dropped first and restored if `source` fails.
+    /// * `source` - Statements to run; MySQL sources may use `DELIMITER` directives
+    async fn save_routine_definition(
+        &self,
+        _replaces: Option<&TableRef>,
+        _source: &str,
+    ) -> DbResult<()> {
+        unsupported("Saving routines")
+    }
+
+    /// Returns the query a view is defined by.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the view doesn't exist.
+    async fn get_view_definition(&self, _view: &TableRef) -> DbResult<String> {
+        unsupported("View definitions")
+    }
+
+    /// Redefines a view with `CREATE OR REPLACE VIEW`, after checking with EXPLAIN that
+    /// `query` plans, so a broken query doesn't replace a working view.
+    ///
+    /// # Arguments
+    /// * `view` - View to redefine
+    /// * `query` - Single SELECT statement, without a trailing semicolon
+    async fn alter_view(&self, _view: &TableRef, _query: &str) -> DbResult<()> {
+        unsupported("Editing views")
+    }
+
+    /// Creates a trigger. On PostgreSQL this also creates the trigger function running the
+    /// spec's body, named after the trigger.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the spec is invalid or uses features the dialect lacks
+    /// (MySQL triggers fire per row, on one event, BEFORE or AFTER).
+    async fn create_trigger(&self, _spec: &TriggerSpec) -> DbResult<()> {
+        unsupported("Creating triggers")
+    }
+
+    /// Returns the DDL `create_trigger` runs for `spec`, e.g. to record it in the audit log.
+    ///
+    /// # Errors
+    /// Same as `create_trigger` for an invalid spec.
+    fn trigger_ddl(&self, _spec: &TriggerSpec) -> DbResult<String> {
+        unsupported("Creating triggers")
+    }
+
+    /// Drops the trigger `name` on `table` and returns the DDL that was run. On PostgreSQL a
+    /// trigger function created with it by `create_trigger` is dropped too, unless other
+    /// triggers use it.
+    async fn drop_trigger(&self, _name: &str, _table: &TableRef) -> DbResult<String> {
+        unsupported("Dropping triggers")
+    }
+
+    /// Returns the `DROP TRIGGER` statement `drop_trigger` runs for `name` on `table`, e.g. to
+    /// record a failed drop in the audit log.
+    fn drop_trigger_ddl(&self, _name: &str, _table: &TableRef) -> DbResult<String> {
+        unsupported("Dropping triggers")
+    }
+
+    /// Sets the next value the table's identity column (see `TableColumn::is_identity`)
+    /// will get, e.g. after a bulk import of rows with explicit ids.
+    ///
+    /// # Arguments
+    /// * `table` - Table to reset
+    /// * `value` - Next value, or None for one past the column's current maximum
+    ///
+    /// Returns the next value. PostgreSQL tables with several sequence-backed columns have
+    /// each reset, and the first one's value is returned.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the table has no identity column.
+    async fn reset_identity(&self, _table: &TableRef, _value: Option<i64>) -> DbResult<i64> {
+        unsupported("Resetting identities")
+    }
+
+    /// Returns the login user, the role set with `set_role` and the roles it may switch to.
+    async fn get_roles(&self) -> DbResult<SessionRoles> {
+        unsupported("Session roles")
+    }
+
+    /// Makes every statement of this connection, including metadata queries, run as `role`
+    /// (`SET ROLE`), or as the login user again for `None`. The role stays set across
+    /// reconnects and database changes.
+    async fn set_role(&self, _role: Option<&str>) -> DbResult<()> {
+        unsupported("Session roles")
+    }
+
+    /// Returns the session's variables whose name contains `filter` (case-insensitive),
+    /// or all of them.
+    async fn get_session_variables(&self, _filter: Option<&str>) -> DbResult<Vec<SessionVariable>> {
+        unsupported("Session variables")
+    }
+
+    /// Sets a session variable (`SET`). Like `set_role`, the value applies to every
+    /// statement of this connection and stays set across reconnects.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the name is invalid or the server rejects the value.
+    async fn set_session_variable(&self, _name: &str, _value: &str) -> DbResult<()> {
+        unsupported("Session variables")
+    }
+
+    /// Computes grouped aggregates of a table for a chart (see `aggregation::build_query`).
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the request is incomplete or names unknown columns.
+    async fn aggregate_table(&self, _request: &AggregateRequest) -> DbResult<ChartData> {
+        unsupported("Charts")
+    }
+
+    /// Lists the text search configurations (PostgreSQL); empty for MySQL, which has none.
+    async fn get_text_search_configs(&self) -> DbResult<Vec<String>> {
+        unsupported("Full-text search")
+    }
+
+    /// Generates the SQL for a full-text search, with the search text inlined so it can be
+    /// shown to the user and run as a normal query.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if no columns or search text are given.
+    async fn build_text_search(&self, _request: &TextSearchRequest) -> DbResult<String> {
+        unsupported("Full-text search")
+    }
+
+    /// Passes committed row changes to `table` to `on_changes` until it returns `false`.
+    /// Changes made before the call are not included.
+    ///
+    /// # Note
+    /// Experimental. PostgreSQL needs `wal_level = logical`, the wal2json plugin and the
+    /// REPLICATION attribute; MySQL needs row-based binary logging and the REPLICATION SLAVE
+    /// and REPLICATION CLIENT privileges.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the server can't stream changes or the stream breaks.
+    async fn tail_changes(&self, _table: &TableRef, _on_changes: ChangeSink<'_>) -> DbResult<()> {
+        unsupported("Following changes")
+    }
+
+    /// Returns whether row-level security is enabled on a table and its policies
+    /// (PostgreSQL); MySQL has no row-level security, so it is reported as disabled.
+    async fn get_rls_policies(&self, _table: &TableRef) -> DbResult<RlsStatus> {
+        unsupported("Row-level security policies")
+    }
+
+    /// Returns a page of about `count` keys matching the glob `pattern`, starting at `cursor`
+    /// (0 for the first page), for key-value stores (Redis).
+    ///
+    /// # Errors
+    /// Returns `UNSUPPORTED` on databases without keys.
+    async fn scan_keys(&self, _pattern: &str, _cursor: u64, _count: usize) -> DbResult<KeyPage> {
+        unsupported("Key browsing")
+    }
+
+    /// Reads a key's value, up to `MAX_QUERY_ROWS` items of it.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the key doesn't exist or its type can't be shown.
+    async fn get_key(&self, _key: &str) -> DbResult<KeyValue> {
+        unsupported("Key browsing")
+    }
+
+    /// Replaces a key's value and expiry in one transaction. Returns the commands run.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` for truncated values and streams, which can't be written whole.
+    async fn set_key(&self, _value: &KeyValue) -> DbResult<String> {
+        unsupported("Key browsing")
+    }
+
+    /// Deletes a key. Returns the command run.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the key doesn't exist.
+    async fn delete_key(&self, _key: &str) -> DbResult<String> {
+        unsupported("Key browsing")
+    }
+
+    /// Creates a database with an optional encoding (PostgreSQL) or character set (MySQL)
+    /// and owner (PostgreSQL only). Returns the statement run.
+    async fn create_database(
+        &self,
+        _name: &str,
+        _encoding: Option<&str>,
+        _owner: Option<&str>,
+    ) -> DbResult<String> {
+        unsupported("Creating databases")
+    }
+
+    /// Drops a database, refusing system databases and the one in use (see
+    /// `check_droppable_database`). Returns the statement run.
+    async fn drop_database(&self, _name: &str) -> DbResult<String> {
+        unsupported("Dropping databases")
+    }
+
+    /// Creates a user that can log in (a role with LOGIN on PostgreSQL). Returns the
+    /// statement run, with the password redacted; the server receives it in the statement,
+    /// so its own logs may record it.
+    async fn create_user(&self, _user: &DbUser, _password: Option<&str>) -> DbResult<String> {
+        unsupported("User management")
+    }
+
+    /// Drops a user. Returns the statement run.
+    async fn drop_user(&self, _user: &DbUser) -> DbResult<String> {
+        unsupported("User management")
+    }
+
+    /// Changes a user's password. When it is the connection's own user, the connection
+    /// logs in with the new password from then on. Returns the statement run, with the
+    /// password redacted.
+    async fn change_password(&self, _user: &DbUser, _password: &str) -> DbResult<String> {
+        unsupported("User management")
+    }
+
+    /// Grants privileges to a user. Returns the statement run.
+    async fn grant_privileges(&self, _grant: &PrivilegeGrant) -> DbResult<String> {
+        unsupported("User management")
+    }
+
+    /// Revokes privileges from a user. Returns the statement run.
+    async fn revoke_privileges(&self, _grant: &PrivilegeGrant) -> DbResult<String> {
+        unsupported("User management")
+    }
+
+    /// Returns a table's storage engine, row format, tablespace and storage parameters.
+    async fn get_table_options(&self, _table: &TableRef) -> DbResult<TableOptions> {
+        unsupported("Table options")
+    }
+
+    /// Profiles a column: NULL share, distinct values, range, most common values and (where
+    /// the server keeps one) histogram. Server statistics are preferred; the rest is computed
+    /// from a sample of `column_stats::SAMPLE_ROWS` rows.
+    async fn get_column_stats(&self, _table: &TableRef, _column: &str) -> DbResult<ColumnStats> {
+        unsupported("Column statistics")
+    }
+
+    /// Builds a query returning a random sample of about `n` rows of a table. Uses
+    /// `TABLESAMPLE` on large PostgreSQL tables, and `ORDER BY RANDOM()` over at most
+    /// `sampling::RANDOM_SCAN_LIMIT` rows otherwise (always on MySQL).
+    async fn build_sample_query(
+        &self,
+        _table: &TableRef,
+        _n: usize,
+        _method: SampleMethod,
+    ) -> DbResult<String> {
+        unsupported("Sampling")
+    }
+
+    /// Whether `table` keeps the history of its rows for `as_of_query` (MariaDB
+    /// system-versioned tables); false on servers without time travel.
+    async fn supports_time_travel(&self, _table: &TableRef) -> DbResult<bool> {
+        Ok(false)
+    }
+
+    /// Rewrites `query` to read the tables as they were at `as_of`, in the session's time
+    /// zone.
+    ///
+    /// # Errors
+    /// Returns `UNSUPPORTED` on servers without time travel.
+    fn as_of_query(&self, _query: &str, _as_of: NaiveDateTime) -> DbResult<String> {
+        unsupported("Time travel")
+    }
+
+    /// Dialect to quote identifiers in statements built for this connection. Literals whose
+    /// escaping depends on session settings are left to the driver.
+    fn dialect(&self) -> SqlDialect;
+
+    /// Creates `SCRATCH_SCHEMA` unless it exists.
+    async fn create_scratch_schema(&self) -> DbResult<()> {
+        unsupported("The scratch schema")
+    }
+
+    /// Drops `SCRATCH_SCHEMA` with every table in it.
+    async fn drop_scratch_schema(&self) -> DbResult<()> {
+        unsupported("The scratch schema")
+    }
+
+    /// Stores the result of `query` in a new table (`CREATE TABLE ... AS`), either in
+    /// `SCRATCH_SCHEMA`, which is created if needed, or as a temporary table of the session.
+    /// Returns the new table.
+    ///
+    /// # Errors
+    /// Returns `QUERY_ERROR` if the table exists or the query fails, and for temporary
+    /// tables on MySQL, whose pooled connections don't keep them between queries.
+    async fn materialize_query(
+        &self,
+        _query: &str,
+        _table: &str,
+        _temporary: bool,
+    ) -> DbResult<TableRef> {
+        unsupported("Materializing results")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_size_resolve() {
+        assert_eq!(PoolSize::default().resolve(1, 5).unwrap(), (1, 5));
+        assert_eq!(
+            PoolSize::new(None, Some(10)).resolve(1, 5).unwrap(),
+            (1, 10)
+        );
+        assert_eq!(PoolSize::new(Some(3), None).resolve(0, 4).unwrap(), (3, 4));
+        assert_eq!(PoolSize::new(None, Some(2)).resolve(4, 8).unwrap(), (2, 2));
+
+        let err = PoolSize::new(Some(6), Some(2)).resolve(1, 5).unwrap_err();
+        assert_eq!(err.code.as_deref(), Some(error_codes::INVALID_POOL_SIZE));
+        assert!(PoolSize::new(None, Some(0)).resolve(0, 4).is_err());
+    }
+
+    #[test]
+    fn test_table_options_parameters() {
+        let mut options = TableOptions::default();
+        options.add_parameters("row_format=DYNAMIC partitioned".split_whitespace());
+        options.add_parameters(["fillfactor=70"]);
+        assert_eq!(options.parameters["row_format"], "DYNAMIC");
+        assert_eq!(options.parameters["partitioned"], "true");
+        assert_eq!(options.fillfactor, Some(70));
+    }
+
+    #[test]
+    fn test_chunked_rows_sends_columns_first_and_stops_on_request() {
+        let mut chunks = Vec::new();
+        let mut sink = |chunk: RowChunk| {
+            chunks.push(chunk);
+            chunks.len() < 2
+        };
+        let mut rows = ChunkedRows::new(vec!["id".to_string()], 2, &mut sink);
+        for i in 0..5 {
+            if !rows.push(serde_json::json!(i)) {
+                break;
+            }
+        }
+        assert_eq!(rows.finish(), (4, true));
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].columns, Some(vec!["id".to_string()]));
+        assert_eq!(chunks[1].columns, None);
+        assert_eq!(chunks[1].rows.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_convert_rows_keeps_order_across_batches() {
+        let rows: Vec<usize> = (0..CONVERSION_BATCH_SIZE * 2 + 7).collect();
+        let converted = convert_rows(rows, |n| serde_json::json!(n)).await.unwrap();
+
+        assert_eq!(converted.len(), CONVERSION_BATCH_SIZE * 2 + 7);
+        assert!(converted
+            .iter()
+            .enumerate()
+            .all(|(i, value)| value == &serde_json::json!(i)));
+    }
+
+    #[test]
+    fn test_table_ref_accepts_names_and_qualified_objects() {
+        let tables: Vec<TableRef> = serde_json::from_str(
+            r#"["users", {"schema": "audit", "name": "users"}, {"schema": "", "name": "t"}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(tables[0], TableRef::unqualified("users"));
+        assert_eq!(tables[1].to_string(), "audit.users");
+        assert_eq!(tables[2].schema, None);
+    }
+
+    #[test]
+    fn test_ssl_mode_parsing() {
+        assert_eq!(SslMode::parse("required").unwrap(), SslMode::Required);
+        assert_eq!(SslMode::parse("verify_full").unwrap(), SslMode::VerifyFull);
+        assert_eq!(SslMode::parse("VERIFY-CA").unwrap(), SslMode::VerifyCa);
+        assert!(SslMode::parse("sometimes").is_err());
+
+        assert!(!SslMode::Required.verifies_certificate());
+        assert!(SslMode::VerifyCa.verifies_certificate());
+        assert!(!SslMode::VerifyCa.verifies_hostname());
+    }
+
+    #[test]
+    fn test_chunked_rows_sends_columns_for_empty_result() {
+        let mut chunks = Vec::new();
+        let mut sink = |chunk: RowChunk| {
+            chunks.push(chunk);
+            true
+        };
+        let rows = ChunkedRows::new(vec!["id".to_string()], 100, &mut sink);
+        assert_eq!(rows.finish(), (0, false));
+
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].rows.is_empty());
+    }
 }
@@ -0,0 +1,252 @@
+//! Extracts deadlock details from server diagnostics: the InnoDB status report on
+//! MariaDB/MySQL and the server log on PostgreSQL.
+
+use serde::{Deserialize, Serialize};
+
+/// Recent deadlocks, as far as the server keeps them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeadlockReport {
+    /// Deadlocks since the server's statistics were reset, when the server counts them.
+    pub total_deadlocks: Option<u64>,
+    /// Deadlocks found, oldest first.
+    pub deadlocks: Vec<Deadlock>,
+    /// Why the list may be incomplete, and how to get more.
+    pub note: Option<String>,
+}
+
+/// One deadlock between two or more transactions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deadlock {
+    /// When the deadlock was detected, as printed by the server.
+    pub detected_at: Option<String>,
+    pub transactions: Vec<DeadlockTransaction>,
+}
+
+/// A transaction taking part in a deadlock.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadlockTransaction {
+    /// InnoDB transaction id or PostgreSQL process id.
+    pub id: String,
+    /// Statement the transaction was running.
+    pub statement: Option<String>,
+    /// Lock the transaction was waiting for.
+    pub waiting_for: Option<String>,
+    /// Whether the server rolled this transaction back to resolve the deadlock.
+    pub rolled_back: bool,
+}
+
+/// Heading of the deadlock section in `SHOW ENGINE INNODB STATUS`.
+const INNODB_DEADLOCK_SECTION: &str = "LATEST DETECTED DEADLOCK";
+
+/// Parses the latest deadlock from `SHOW ENGINE INNODB STATUS` output, if there was one.
+pub fn parse_innodb_status(status: &str) -> Option<Deadlock> {
+    let mut lines = status
+        .lines()
+        .skip_while(|line| line.trim() != INNODB_DEADLOCK_SECTION)
+        .skip(1)
+        .skip_while(|line| line.starts_with('-'));
+
+    let mut deadlock = Deadlock {
+        detected_at: lines.next().map(|line| {
+            // The timestamp is followed by the id of the thread that printed the report
+            match line.rsplit_once(" 0x") {
+                Some((time, _)) => time.trim().to_string(),
+                None => line.trim().to_string(),
+            }
+        }),
+        transactions: Vec::new(),
+    };
+
+    // Each transaction block has a header, its thread line, then the statement lines
+    // until the next `***` line
+    let mut in_statement = false;
+    let mut waiting = false;
+    for line in lines {
+        // The next section starts with a dashed rule
+        if line.starts_with("------") {
+            break;
+        }
+        if let Some(marker) = line.strip_prefix("*** ") {
+            in_statement = false;
+            waiting = marker.contains("WAITING FOR THIS LOCK");
+            if marker.contains("TRANSACTION:") {
+                deadlock.transactions.push(DeadlockTransaction::default());
+            } else if let Some(number) = marker.strip_prefix("WE ROLL BACK TRANSACTION (") {
+                let index = number
+                    .trim_end_matches(')')
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| n.checked_sub(1));
+                if let Some(tx) = index.and_then(|i| deadlock.transactions.get_mut(i)) {
+                    tx.rolled_back = true;
+                }
+            }
+            continue;
+        }
+        let Some(tx) = deadlock.transactions.last_mut() else {
+            continue;
+        };
+
+        if let Some(rest) = line.strip_prefix("TRANSACTION ") {
+            tx.id = rest.split(',').next().unwrap_or_default().to_string();
+        } else if line.starts_with("MySQL thread id") {
+            in_statement = true;
+        } else if in_statement {
+            match &mut tx.statement {
+                Some(statement) => {
+                    statement.push('\n');
+                    statement.push_str(line);
+                }
+                None => tx.statement = Some(line.to_string()),
+            }
+        } else if waiting && tx.waiting_for.is_none() && !line.trim().is_empty() {
+            // The first line after the heading describes the lock; lock dumps follow
+            tx.waiting_for = Some(line.trim().to_string());
+        }
+    }
+
+    (!deadlock.transactions.is_empty()).then_some(deadlock)
+}
+
+/// Parses the deadlocks reported in a PostgreSQL server log (`stderr` format).
+///
+/// The DETAIL of a "deadlock detected" error lists which lock each process waited for and
+/// the statement it ran. The process that detected the deadlock comes first and is the one
+/// whose transaction was aborted.
+pub fn parse_postgres_log(log: &str) -> Vec<Deadlock> {
+    let lines: Vec<&str> = log.lines().collect();
+    let mut deadlocks = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let Some(error_at) = line.find("ERROR:  deadlock detected") else {
+            continue;
+        };
+        let prefix = line[..error_at].trim();
+        let mut deadlock = Deadlock {
+            detected_at: (!prefix.is_empty()).then(|| prefix.to_string()),
+            transactions: Vec::new(),
+        };
+
+        let Some(detail) = lines.get(i + 1).and_then(|l| l.split_once("DETAIL:  ")) else {
+            continue;
+        };
+        let continuation = lines[i + 2..]
+            .iter()
+            .take_while(|l| l.starts_with('\t'))
+            .map(|l| l.trim());
+        for entry in std::iter::once(detail.1.trim()).chain(continuation) {
+            let Some(entry) = entry.strip_prefix("Process ") else {
+                continue;
+            };
+            if let Some((pid, rest)) = entry.split_once(" waits for ") {
+                let lock = rest.split("; blocked by").next().unwrap_or(rest);
+                postgres_process(&mut deadlock, pid).waiting_for = Some(lock.to_string());
+            } else if let Some((pid, statement)) = entry.split_once(": ") {
+                postgres_process(&mut deadlock, pid).statement = Some(statement.to_string());
+            }
+        }
+
+        if let Some(first) = deadlock.transactions.first_mut() {
+            first.rolled_back = true;
+            deadlocks.push(deadlock);
+        }
+    }
+
+    deadlocks
+}
+
+/// Returns the transaction of process `pid`, adding it if it is new.
+fn postgres_process<'a>(deadlock: &'a mut Deadlock, pid: &str) -> &'a mut DeadlockTransaction {
+    let index = match deadlock.transactions.iter().position(|tx| tx.id == pid) {
+        Some(index) => index,
+        None => {
+            deadlock.transactions.push(DeadlockTransaction {
+                id: pid.to_string(),
+                ..Default::default()
+            });
+            deadlock.transactions.len() - 1
+        }
+    };
+    &mut deadlock.transactions[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_innodb_deadlock() {
+        let status = "\
+------------------------
+LATEST DETECTED DEADLOCK
+------------------------
+2024-05-01 10:00:00 0x7f2a
+*** (1) TRANSACTION:
+TRANSACTION 1801, ACTIVE 5 sec starting index read
+mysql tables in use 1, locked 1
+MySQL thread id 10, OS thread handle 140, query id 50 localhost root updating
+UPDATE accounts SET balance = 0 WHERE id = 2
+*** (1) WAITING FOR THIS LOCK TO BE GRANTED:
+RECORD LOCKS space id 2 page no 4 n bits 72 index PRIMARY of table `bank`.`accounts`
+*** (2) TRANSACTION:
+TRANSACTION 1802, ACTIVE 4 sec starting index read
+MySQL thread id 11, OS thread handle 141, query id 51 localhost root updating
+UPDATE accounts SET balance = 1 WHERE id = 1
+*** (2) HOLDS THE LOCK(S):
+RECORD LOCKS space id 2 page no 4 n bits 72 index PRIMARY of table `bank`.`accounts`
+*** WE ROLL BACK TRANSACTION (2)
+------------
+TRANSACTIONS
+------------
+";
+
+        let deadlock = parse_innodb_status(status).unwrap();
+        assert_eq!(deadlock.detected_at.as_deref(), Some("2024-05-01 10:00:00"));
+        assert_eq!(deadlock.transactions.len(), 2);
+        assert_eq!(deadlock.transactions[0].id, "1801");
+        assert_eq!(
+            deadlock.transactions[0].statement.as_deref(),
+            Some("UPDATE accounts SET balance = 0 WHERE id = 2")
+        );
+        assert!(deadlock.transactions[0]
+            .waiting_for
+            .as_deref()
+            .is_some_and(|lock| lock.contains("`bank`.`accounts`")));
+        assert!(!deadlock.transactions[0].rolled_back);
+        assert!(deadlock.transactions[1].waiting_for.is_none());
+        assert!(deadlock.transactions[1].rolled_back);
+
+        assert_eq!(parse_innodb_status("TRANSACTIONS\n------------\n"), None);
+    }
+
+    #[test]
+    fn test_parse_postgres_deadlock() {
+        let log = "\
+2024-05-01 10:00:00.123 UTC [123] ERROR:  deadlock detected
+2024-05-01 10:00:00.123 UTC [123] DETAIL:  Process 123 waits for ShareLock on transaction 456; blocked by process 789.
+\tProcess 789 waits for ShareLock on transaction 455; blocked by process 123.
+\tProcess 123: UPDATE accounts SET balance = 0 WHERE id = 2
+\tProcess 789: UPDATE accounts SET balance = 1 WHERE id = 1
+2024-05-01 10:00:00.123 UTC [123] HINT:  See server log for query details.
+";
+
+        let deadlocks = parse_postgres_log(log);
+        assert_eq!(deadlocks.len(), 1);
+        let deadlock = &deadlocks[0];
+        assert_eq!(
+            deadlock.detected_at.as_deref(),
+            Some("2024-05-01 10:00:00.123 UTC [123]")
+        );
+        assert_eq!(deadlock.transactions[0].id, "123");
+        assert_eq!(
+            deadlock.transactions[0].waiting_for.as_deref(),
+            Some("ShareLock on transaction 456")
+        );
+        assert!(deadlock.transactions[0].rolled_back);
+        assert_eq!(
+            deadlock.transactions[1].statement.as_deref(),
+            Some("UPDATE accounts SET balance = 1 WHERE id = 1")
+        );
+        assert!(!deadlock.transactions[1].rolled_back);
+    }
+}
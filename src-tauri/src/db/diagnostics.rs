@@ -0,0 +1,439 @@
+//! Step-by-step connection diagnostics. Where `test_connection` only reports that
+//! connecting failed, `diagnose` checks each stage on its own (DNS resolution, TCP, the TLS
+//! handshake, logging in, opening the database) and says which one failed and what to try.
+
+use super::connection::{read_ca_cert, PoolSize, SslMode, LIVENESS_TIMEOUT};
+use super::factory::create_connection;
+use super::rds_iam::RdsIamAuth;
+use native_tls::TlsConnector;
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// Longest a single network step may take.
+const STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// PostgreSQL's SSLRequest message: length 8, then the request code 80877103.
+const PG_SSL_REQUEST: [u8; 8] = [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f];
+
+/// MySQL capability flags: `CLIENT_PROTOCOL_41`, `CLIENT_SSL`, `CLIENT_SECURE_CONNECTION`.
+const MYSQL_PROTOCOL_41: u32 = 0x0200;
+const MYSQL_SSL: u32 = 0x0800;
+const MYSQL_SECURE_CONNECTION: u32 = 0x8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticStage {
+    Dns,
+    Tcp,
+    Tls,
+    Authentication,
+    Database,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Passed,
+    /// Passed, but not as configured, e.g. a preferred TLS connection falling back to
+    /// plain TCP.
+    Warning,
+    Failed,
+    /// Not checked because an earlier step failed, or not applicable.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticStep {
+    pub stage: DiagnosticStage,
+    pub status: StepStatus,
+    pub detail: String,
+    /// What to check when the step failed or warned.
+    pub suggestion: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Result of `diagnose`, with every stage in order.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionDiagnosis {
+    pub steps: Vec<DiagnosticStep>,
+    /// The first stage that failed, if any.
+    pub failed_stage: Option<DiagnosticStage>,
+}
+
+impl ConnectionDiagnosis {
+    fn push(
+        &mut self,
+        stage: DiagnosticStage,
+        started: Instant,
+        outcome: Result<(StepStatus, String, Option<&str>), (String, &str)>,
+    ) {
+        let (status, detail, suggestion) = match outcome {
+            Ok((status, detail, suggestion)) => (status, detail, suggestion),
+            Err((detail, suggestion)) => (StepStatus::Failed, detail, Some(suggestion)),
+        };
+        if status == StepStatus::Failed && self.failed_stage.is_none() {
+            self.failed_stage = Some(stage);
+        }
+        self.steps.push(DiagnosticStep {
+            stage,
+            status,
+            detail,
+            suggestion: suggestion.map(str::to_string),
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+
+    fn skip_remaining(&mut self, stages: &[DiagnosticStage]) {
+        for &stage in stages {
+            self.steps.push(DiagnosticStep {
+                stage,
+                status: StepStatus::Skipped,
+                detail: "Skipped after an earlier step failed".to_string(),
+                suggestion: None,
+                duration_ms: 0,
+            });
+        }
+    }
+}
+
+/// Checks each stage of connecting with the given settings, stopping at the first failure.
+#[allow(clippy::too_many_arguments)]
+pub async fn diagnose(
+    db_type: &str,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    database: &str,
+    ssl_mode: &str,
+    ssl_ca_cert: Option<&str>,
+    rds_iam: Option<RdsIamAuth>,
+) -> ConnectionDiagnosis {
+    use DiagnosticStage::*;
+    let mut diagnosis = ConnectionDiagnosis {
+        steps: Vec::new(),
+        failed_stage: None,
+    };
+    let is_postgres = db_type.to_lowercase().starts_with("postgres");
+
+    let started = Instant::now();
+    let lookup = (host.to_string(), port);
+    let addresses = tokio::task::spawn_blocking(move || lookup.to_socket_addrs())
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|addresses| addresses.map_err(|e| e.to_string()))
+        .map(|addresses| addresses.collect::<Vec<_>>());
+    let addresses: Vec<SocketAddr> = match addresses {
+        Ok(addresses) if !addresses.is_empty() => {
+            let listed: Vec<String> = addresses.iter().map(|a| a.ip().to_string()).collect();
+            let detail = format!("{} resolves to {}", host, listed.join(", "));
+            diagnosis.push(Dns, started, Ok((StepStatus::Passed, detail, None)));
+            addresses
+        }
+        result => {
+            let error = result.err().unwrap_or_else(|| "no addresses".to_string());
+            diagnosis.push(
+                Dns,
+                started,
+                Err((
+                    format!("Could not resolve {}: {}", host, error),
+                    "Check the host name for typos, and your DNS or VPN settings",
+                )),
+            );
+            diagnosis.skip_remaining(&[Tcp, Tls, Authentication, Database]);
+            return diagnosis;
+        }
+    };
+
+    let started = Instant::now();
+    let connected = tokio::task::spawn_blocking(move || connect_any(&addresses))
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+    let address = match connected {
+        Ok((address, _)) => {
+            let detail = format!("Connected to {}", address);
+            diagnosis.push(Tcp, started, Ok((StepStatus::Passed, detail, None)));
+            address
+        }
+        Err(e) => {
+            let suggestion = match e.kind() {
+                std::io::ErrorKind::ConnectionRefused => {
+                    "Nothing is listening on that port: check that the server is running and \
+                     the port is right"
+                }
+                _ => "Check that a firewall, security group or VPN isn't blocking the port",
+            };
+            let detail = format!("Could not connect to port {}: {}", port, e);
+            diagnosis.push(Tcp, started, Err((detail, suggestion)));
+            diagnosis.skip_remaining(&[Tls, Authentication, Database]);
+            return diagnosis;
+        }
+    };
+
+    let started = Instant::now();
+    match SslMode::parse(ssl_mode) {
+        Ok(mode) if !mode.uses_tls() => {
+            let detail = "TLS is disabled for this connection".to_string();
+            diagnosis.push(Tls, started, Ok((StepStatus::Skipped, detail, None)));
+        }
+        Ok(mode) => {
+            let ca_cert = match ssl_ca_cert.map(read_ca_cert).transpose() {
+                Ok(ca_cert) => ca_cert,
+                Err(e) => {
+                    let suggestion = "Choose a PEM file with the CA certificate";
+                    diagnosis.push(Tls, started, Err((e.message, suggestion)));
+                    diagnosis.skip_remaining(&[Authentication, Database]);
+                    return diagnosis;
+                }
+            };
+            let server_name = host.to_string();
+            let handshake = tokio::task::spawn_blocking(move || {
+                tls_handshake(address, &server_name, is_postgres, mode, ca_cert)
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()));
+            let outcome = match handshake {
+                Ok(true) => Ok((
+                    StepStatus::Passed,
+                    "TLS handshake succeeded".to_string(),
+                    None,
+                )),
+                Ok(false) if mode.allows_fallback() => Ok((
+                    StepStatus::Warning,
+                    "The server doesn't offer TLS; the connection falls back to plain TCP"
+                        .to_string(),
+                    Some("Enable TLS on the server to encrypt the connection"),
+                )),
+                Ok(false) => Err((
+                    "The server doesn't offer TLS".to_string(),
+                    "Enable TLS on the server, or set the SSL mode to preferred or disabled",
+                )),
+                Err(e) => Err((
+                    format!("TLS handshake failed: {}", e),
+                    if mode.verifies_certificate() {
+                        "The server certificate isn't trusted or doesn't match the host; \
+                         choose its CA certificate or use the required SSL mode"
+                    } else {
+                        "Check the server's TLS configuration"
+                    },
+                )),
+            };
+            let failed = outcome.is_err();
+            diagnosis.push(Tls, started, outcome);
+            if failed {
+                diagnosis.skip_remaining(&[Authentication, Database]);
+                return diagnosis;
+            }
+        }
+        Err(e) => {
+            diagnosis.push(Tls, started, Err((e.message, "Choose a valid SSL mode")));
+            diagnosis.skip_remaining(&[Authentication, Database]);
+            return diagnosis;
+        }
+    }
+
+    let started = Instant::now();
+    let connection = create_connection(
+        db_type,
+        host,
+        port,
+        username,
+        password,
+        database,
+        ssl_mode,
+        ssl_ca_cert,
+        PoolSize::default(),
+        None,
+        rds_iam,
+    )
+    .await;
+    let result = match connection {
+        Ok(conn) => {
+            let tested = tokio::time::timeout(LIVENESS_TIMEOUT, conn.test_connection()).await;
+            let _ = conn.disconnect().await;
+            match tested {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(e)) => Err(e.message),
+                Err(_) => Err("The server didn't answer a test query in time".to_string()),
+            }
+        }
+        Err(e) => Err(e.message),
+    };
+    match result {
+        Ok(()) => {
+            let detail = format!("Logged in as {}", username);
+            diagnosis.push(
+                Authentication,
+                started,
+                Ok((StepStatus::Passed, detail, None)),
+            );
+            let detail = format!("Opened database {}", database);
+            diagnosis.push(
+                Database,
+                Instant::now(),
+                Ok((StepStatus::Passed, detail, None)),
+            );
+        }
+        Err(message) => match classify_login_error(&message) {
+            (Database, suggestion) => {
+                let detail = format!("Logged in as {}", username);
+                diagnosis.push(
+                    Authentication,
+                    started,
+                    Ok((StepStatus::Passed, detail, None)),
+                );
+                diagnosis.push(Database, Instant::now(), Err((message, suggestion)));
+            }
+            (_, suggestion) => {
+                diagnosis.push(Authentication, started, Err((message, suggestion)));
+                diagnosis.skip_remaining(&[Database]);
+            }
+        },
+    }
+    diagnosis
+}
+
+/// Tells from a login error whether the database or the credentials were rejected, with a
+/// suggestion.
+pub fn classify_login_error(message: &str) -> (DiagnosticStage, &'static str) {
+    let message = message.to_lowercase();
+    if message.contains("unknown database")
+        || (message.contains("database") && message.contains("does not exist"))
+    {
+        (
+            DiagnosticStage::Database,
+            "Check the database name, or that the database was created",
+        )
+    } else if message.contains("pg_hba.conf") {
+        (
+            DiagnosticStage::Authentication,
+            "The server doesn't accept this user from this host; add a pg_hba.conf entry",
+        )
+    } else if message.contains("password") || message.contains("access denied") {
+        (
+            DiagnosticStage::Authentication,
+            "Check the user name and password, and that the user may connect from this host",
+        )
+    } else {
+        (
+            DiagnosticStage::Authentication,
+            "The server rejected the login; see its log for details",
+        )
+    }
+}
+
+/// Connects to the first address that accepts a connection.
+fn connect_any(addresses: &[SocketAddr]) -> std::io::Result<(SocketAddr, TcpStream)> {
+    let mut last_error = None;
+    for address in addresses {
+        match TcpStream::connect_timeout(address, STEP_TIMEOUT) {
+            Ok(stream) => return Ok((*address, stream)),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| std::io::Error::other("no addresses")))
+}
+
+/// Asks the server to switch to TLS the way its protocol does, then performs the handshake
+/// with the certificate checks of `mode`. Returns false if the server doesn't offer TLS.
+fn tls_handshake(
+    address: SocketAddr,
+    host: &str,
+    is_postgres: bool,
+    mode: SslMode,
+    ca_cert: Option<native_tls::Certificate>,
+) -> Result<bool, String> {
+    let mut stream =
+        TcpStream::connect_timeout(&address, STEP_TIMEOUT).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(STEP_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(STEP_TIMEOUT)))
+        .map_err(|e| e.to_string())?;
+
+    let offered = if is_postgres {
+        stream
+            .write_all(&PG_SSL_REQUEST)
+            .map_err(|e| e.to_string())?;
+        let mut answer = [0u8];
+        stream.read_exact(&mut answer).map_err(|e| e.to_string())?;
+        answer[0] == b'S'
+    } else {
+        mysql_request_tls(&mut stream)?
+    };
+    if !offered {
+        return Ok(false);
+    }
+
+    let mut builder = TlsConnector::builder();
+    if let Some(ca_cert) = ca_cert {
+        builder.add_root_certificate(ca_cert);
+    }
+    let connector = builder
+        .danger_accept_invalid_certs(!mode.verifies_certificate())
+        .danger_accept_invalid_hostnames(!mode.verifies_hostname())
+        .build()
+        .map_err(|e| e.to_string())?;
+    connector
+        .connect(host, stream)
+        .map(|_| true)
+        .map_err(|e| e.to_string())
+}
+
+/// Reads a MySQL server greeting and, if it offers TLS, sends the SSL request that starts
+/// the handshake. Returns false if the server doesn't offer TLS.
+fn mysql_request_tls(stream: &mut TcpStream) -> Result<bool, String> {
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).map_err(|e| e.to_string())?;
+    let length = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+    let mut greeting = vec![0u8; length];
+    stream
+        .read_exact(&mut greeting)
+        .map_err(|e| e.to_string())?;
+
+    // An error packet instead of a greeting, e.g. a host blocked after failed logins
+    if greeting.first() == Some(&0xff) {
+        return Err(String::from_utf8_lossy(greeting.get(3..).unwrap_or_default()).into_owned());
+    }
+    // Protocol version, NUL-terminated server version, thread id, 8 bytes of auth data and
+    // a filler byte come before the lower capability flags
+    let version_end = greeting
+        .iter()
+        .skip(1)
+        .position(|&b| b == 0)
+        .ok_or("Malformed server greeting")?;
+    let flags_at = 1 + version_end + 1 + 4 + 8 + 1;
+    let flags = greeting
+        .get(flags_at..flags_at + 2)
+        .ok_or("Malformed server greeting")?;
+    if u16::from_le_bytes([flags[0], flags[1]]) as u32 & MYSQL_SSL == 0 {
+        return Ok(false);
+    }
+
+    // Capability flags, max packet size, utf8 character set and 23 reserved bytes
+    let mut request = vec![32, 0, 0, 1];
+    request.extend((MYSQL_PROTOCOL_41 | MYSQL_SSL | MYSQL_SECURE_CONNECTION).to_le_bytes());
+    request.extend(16_777_216u32.to_le_bytes());
+    request.push(33);
+    request.extend([0u8; 23]);
+    stream.write_all(&request).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_login_error() {
+        let (stage, _) = classify_login_error("db error: FATAL: database \"shop\" does not exist");
+        assert_eq!(stage, DiagnosticStage::Database);
+        let (stage, _) = classify_login_error("Unknown database 'shop'");
+        assert_eq!(stage, DiagnosticStage::Database);
+        let (stage, suggestion) =
+            classify_login_error("Access denied for user 'app'@'10.0.0.2' (using password: YES)");
+        assert_eq!(stage, DiagnosticStage::Authentication);
+        assert!(suggestion.contains("password"));
+    }
+}
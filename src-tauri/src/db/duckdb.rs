@@ -0,0 +1,1094 @@
+//! DuckDB database files on this machine, opened in process with the `duckdb` crate (which
+//! bundles the engine). The connection's database is the file path; there is no server,
+//! so host, port and credentials are ignored.
+//!
+//! Calls block, so they run on the blocking thread pool with the connection locked for the
+//! whole call. A query running past the timeout is interrupted.
+
+use super::bulk_insert::INSERT_BATCH_ROWS;
+use super::column_metadata::ColumnMetadata;
+use super::connection::{
+    error_codes, ChunkedRows, DatabaseConnection, DbResult, QueryError, QueryResult, QueryTimer,
+    RowChunkSink, TableColumn, TableOperation, TableRef, TableRelationship, DEFAULT_QUERY_TIMEOUT,
+    MAX_QUERY_ROWS,
+};
+use super::sampling::{self, SampleMethod};
+use super::sql_dialect::SqlDialect;
+use super::statement::{self, Statement};
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use duckdb::core::{LogicalTypeHandle, LogicalTypeId};
+use duckdb::types::{TimeUnit, Value};
+use duckdb::{Connection as Database, InterruptHandle};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+use tracing::debug;
+
+const DIALECT: SqlDialect = SqlDialect::DuckDb;
+
+/// Days from 0001-01-01 (chrono's day 1) to the Unix epoch, where DuckDB counts dates from.
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+fn query_error(err: duckdb::Error) -> QueryError {
+    QueryError::with_code(err.to_string(), error_codes::QUERY_ERROR)
+}
+
+/// One statement's result: column names and types, then the rows (at most
+/// `MAX_QUERY_ROWS` of them) as DuckDB values.
+struct Fetched {
+    columns: Vec<String>,
+    column_metadata: Vec<ColumnMetadata>,
+    rows: Vec<Vec<Value>>,
+    row_count: usize,
+    affected_rows: Option<u64>,
+}
+
+/// Type name as DuckDB writes it in DDL.
+fn type_name(logical_type: &LogicalTypeHandle) -> String {
+    let name = match logical_type.id() {
+        LogicalTypeId::Boolean => "BOOLEAN",
+        LogicalTypeId::Tinyint => "TINYINT",
+        LogicalTypeId::Smallint => "SMALLINT",
+        LogicalTypeId::Integer => "INTEGER",
+        LogicalTypeId::Bigint => "BIGINT",
+        LogicalTypeId::Hugeint => "HUGEINT",
+        LogicalTypeId::UTinyint => "UTINYINT",
+        LogicalTypeId::USmallint => "USMALLINT",
+        LogicalTypeId::UInteger => "UINTEGER",
+        LogicalTypeId::UBigint => "UBIGINT",
+        LogicalTypeId::UHugeint => "UHUGEINT",
+        LogicalTypeId::Float => "FLOAT",
+        LogicalTypeId::Double => "DOUBLE",
+        LogicalTypeId::Decimal => {
+            return format!(
+                "DECIMAL({},{})",
+                logical_type.decimal_width(),
+                logical_type.decimal_scale()
+            )
+        }
+        LogicalTypeId::Varchar => "VARCHAR",
+        LogicalTypeId::Blob => "BLOB",
+        LogicalTypeId::Date => "DATE",
+        LogicalTypeId::Time => "TIME",
+        LogicalTypeId::TimeTZ => "TIME WITH TIME ZONE",
+        LogicalTypeId::Timestamp => "TIMESTAMP",
+        LogicalTypeId::TimestampS => "TIMESTAMP_S",
+        LogicalTypeId::TimestampMs => "TIMESTAMP_MS",
+        LogicalTypeId::TimestampNs => "TIMESTAMP_NS",
+        LogicalTypeId::TimestampTZ => "TIMESTAMP WITH TIME ZONE",
+        LogicalTypeId::Interval => "INTERVAL",
+        LogicalTypeId::Uuid => "UUID",
+        LogicalTypeId::Enum => "ENUM",
+        LogicalTypeId::List => "LIST",
+        LogicalTypeId::Array => "ARRAY",
+        LogicalTypeId::Struct => "STRUCT",
+        LogicalTypeId::Map => "MAP",
+        LogicalTypeId::Union => "UNION",
+        id => return format!("{:?}", id).to_uppercase(),
+    };
+    name.to_string()
+}
+
+fn column_metadata(name: &str, logical_type: &LogicalTypeHandle) -> ColumnMetadata {
+    let id = logical_type.id();
+    let (precision, scale) = if id == LogicalTypeId::Decimal {
+        (
+            Some(u32::from(logical_type.decimal_width())),
+            Some(u32::from(logical_type.decimal_scale())),
+        )
+    } else {
+        (None, None)
+    };
+    ColumnMetadata {
+        name: name.to_string(),
+        type_name: type_name(logical_type),
+        numeric: matches!(
+            id,
+            LogicalTypeId::Tinyint
+                | LogicalTypeId::Smallint
+                | LogicalTypeId::Integer
+                | LogicalTypeId::Bigint
+                | LogicalTypeId::Hugeint
+                | LogicalTypeId::UTinyint
+                | LogicalTypeId::USmallint
+                | LogicalTypeId::UInteger
+                | LogicalTypeId::UBigint
+                | LogicalTypeId::UHugeint
+                | LogicalTypeId::Float
+                | LogicalTypeId::Double
+                | LogicalTypeId::Decimal
+        ),
+        precision,
+        scale,
+        ..Default::default()
+    }
+}
+
+fn timestamp(unit: TimeUnit, value: i64) -> Option<NaiveDateTime> {
+    DateTime::from_timestamp_micros(unit.to_micros(value)).map(|t| t.naive_utc())
+}
+
+fn date(days: i32) -> Option<NaiveDate> {
+    NaiveDate::from_num_days_from_ce_opt(days.checked_add(UNIX_EPOCH_DAYS_FROM_CE)?)
+}
+
+fn time(unit: TimeUnit, value: i64) -> Option<NaiveTime> {
+    let micros = unit.to_micros(value);
+    NaiveTime::from_num_seconds_from_midnight_opt(
+        u32::try_from(micros / 1_000_000).ok()?,
+        u32::try_from(micros % 1_000_000 * 1_000).ok()?,
+    )
+}
+
+/// Formats an interval like DuckDB prints it, e.g. `1 month 2 days 03:04:05`.
+fn format_interval(months: i32, days: i32, nanos: i64) -> String {
+    let plural = |n: i64, unit: &str| {
+        if n.abs() == 1 {
+            format!("{} {}", n, unit)
+        } else {
+            format!("{} {}s", n, unit)
+        }
+    };
+    let mut parts = Vec::new();
+    let (years, months) = (months / 12, months % 12);
+    if years != 0 {
+        parts.push(plural(years.into(), "year"));
+    }
+    if months != 0 {
+        parts.push(plural(months.into(), "month"));
+    }
+    if days != 0 {
+        parts.push(plural(days.into(), "day"));
+    }
+    if nanos != 0 || parts.is_empty() {
+        let micros = nanos.unsigned_abs() / 1_000;
+        let seconds = micros / 1_000_000;
+        let mut clock = format!(
+            "{}{:02}:{:02}:{:02}",
+            if nanos < 0 { "-" } else { "" },
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        );
+        if !micros.is_multiple_of(1_000_000) {
+            clock.push_str(&format!(".{:06}", micros % 1_000_000));
+        }
+        parts.push(clock);
+    }
+    parts.join(" ")
+}
+
+fn value_to_json(value: &Value) -> serde_json::Value {
+    use serde_json::Value as Json;
+    let float = |value: f64| {
+        serde_json::Number::from_f64(value)
+            .map(Json::Number)
+            .unwrap_or_else(|| Json::String(value.to_string()))
+    };
+    let text = |value: Option<String>| value.map(Json::String).unwrap_or(Json::Null);
+    match value {
+        Value::Null => Json::Null,
+        Value::Boolean(v) => Json::Bool(*v),
+        Value::TinyInt(v) => Json::from(*v),
+        Value::SmallInt(v) => Json::from(*v),
+        Value::Int(v) => Json::from(*v),
+        Value::BigInt(v) => Json::from(*v),
+        Value::UTinyInt(v) => Json::from(*v),
+        Value::USmallInt(v) => Json::from(*v),
+        Value::UInt(v) => Json::from(*v),
+        Value::UBigInt(v) => Json::from(*v),
+        // Values outside 64 bits stay text, like decimals, so they aren't rounded
+        Value::HugeInt(v) => i64::try_from(*v)
+            .map(Json::from)
+            .unwrap_or_else(|_| Json::String(v.to_string())),
+        Value::UHugeInt(v) => u64::try_from(*v)
+            .map(Json::from)
+            .unwrap_or_else(|_| Json::String(v.to_string())),
+        Value::Float(v) => float(f64::from(*v)),
+        Value::Double(v) => float(*v),
+        Value::Decimal(v) => Json::String(v.to_string()),
+        Value::Text(v) | Value::Enum(v) => Json::String(v.clone()),
+        Value::Blob(v) | Value::Geometry(v) => {
+            Json::String(base64::engine::general_purpose::STANDARD.encode(v))
+        }
+        Value::Date32(days) => text(date(*days).map(|d| d.format("%Y-%m-%d").to_string())),
+        Value::Time64(unit, v) => {
+            text(time(*unit, *v).map(|t| t.format("%H:%M:%S%.f").to_string()))
+        }
+        Value::Timestamp(unit, v) => {
+            text(timestamp(*unit, *v).map(|t| t.format("%Y-%m-%d %H:%M:%S%.f").to_string()))
+        }
+        Value::Interval {
+            months,
+            days,
+            nanos,
+        } => Json::String(format_interval(*months, *days, *nanos)),
+        Value::List(items) | Value::Array(items) => {
+            Json::Array(items.iter().map(value_to_json).collect())
+        }
+        Value::Struct(fields) => Json::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), value_to_json(value)))
+                .collect(),
+        ),
+        Value::Map(entries) => Json::Object(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = match value_to_json(key) {
+                        Json::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    (key, value_to_json(value))
+                })
+                .collect(),
+        ),
+        Value::Union(value) => value_to_json(value),
+        other => Json::String(format!("{:?}", other)),
+    }
+}
+
+/// Formats a value as a literal for exports. DuckDB casts quoted text to the column type,
+/// so dates and times are written as text.
+fn value_to_sql(value: &Value) -> String {
+    let quoted = |value: Option<String>| {
+        value
+            .map(|v| DIALECT.quote_string(&v))
+            .unwrap_or_else(|| "NULL".to_string())
+    };
+    let list = |items: &[Value]| {
+        let items: Vec<String> = items.iter().map(value_to_sql).collect();
+        format!("[{}]", items.join(", "))
+    };
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Boolean(v) => DIALECT.format_bool(*v).to_string(),
+        Value::TinyInt(v) => v.to_string(),
+        Value::SmallInt(v) => v.to_string(),
+        Value::Int(v) => v.to_string(),
+        Value::BigInt(v) => v.to_string(),
+        Value::HugeInt(v) => v.to_string(),
+        Value::UTinyInt(v) => v.to_string(),
+        Value::USmallInt(v) => v.to_string(),
+        Value::UInt(v) => v.to_string(),
+        Value::UBigInt(v) => v.to_string(),
+        Value::UHugeInt(v) => v.to_string(),
+        Value::Float(v) if v.is_finite() => v.to_string(),
+        Value::Double(v) if v.is_finite() => v.to_string(),
+        // 'nan', 'inf' and '-inf' are read back as floats
+        Value::Float(v) => DIALECT.quote_string(&v.to_string()),
+        Value::Double(v) => DIALECT.quote_string(&v.to_string()),
+        Value::Decimal(v) => v.to_string(),
+        Value::Text(v) | Value::Enum(v) => DIALECT.quote_string(v),
+        Value::Blob(v) | Value::Geometry(v) => DIALECT.quote_bytes(v),
+        Value::Date32(days) => date(*days)
+            .map(|d| DIALECT.format_date(d))
+            .unwrap_or_else(|| "NULL".to_string()),
+        Value::Time64(unit, v) => {
+            quoted(time(*unit, *v).map(|t| t.format("%H:%M:%S%.f").to_string()))
+        }
+        Value::Timestamp(unit, v) => {
+            quoted(timestamp(*unit, *v).map(|t| t.format("%Y-%m-%d %H:%M:%S%.f").to_string()))
+        }
+        Value::Interval {
+            months,
+            days,
+            nanos,
+        } => format!(
+            "INTERVAL {}",
+            DIALECT.quote_string(&format_interval(*months, *days, *nanos))
+        ),
+        Value::List(items) | Value::Array(items) => list(items),
+        Value::Struct(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(name, value)| {
+                    format!("{}: {}", DIALECT.quote_string(name), value_to_sql(value))
+                })
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        Value::Map(entries) => {
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(key, value)| format!("{}: {}", value_to_sql(key), value_to_sql(value)))
+                .collect();
+            format!("MAP {{{}}}", entries.join(", "))
+        }
+        Value::Union(value) => value_to_sql(value),
+        other => DIALECT.quote_string(&format!("{:?}", other)),
+    }
+}
+
+fn text(row: &serde_json::Value, column: &str) -> Option<String> {
+    match row.get(column)? {
+        serde_json::Value::String(value) => Some(value.clone()),
+        serde_json::Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+fn flag(row: &serde_json::Value, column: &str) -> bool {
+    row.get(column).and_then(serde_json::Value::as_bool) == Some(true)
+}
+
+fn number(row: &serde_json::Value, column: &str) -> Option<i64> {
+    row.get(column).and_then(serde_json::Value::as_i64)
+}
+
+/// DuckDB database file connection.
+pub struct DuckDbConnection {
+    database: Arc<Mutex<Database>>,
+    /// Interrupts the running query from another thread.
+    interrupt: Arc<InterruptHandle>,
+    /// The database file.
+    path: String,
+    query_timeout: Duration,
+}
+
+impl DuckDbConnection {
+    /// Opens the database file at `path`, which must exist.
+    pub async fn new(path: &str, query_timeout: Option<Duration>) -> DbResult<Self> {
+        let path = path.trim().to_string();
+        if path.is_empty() {
+            return Err(QueryError::with_code(
+                "Choose a DuckDB database file",
+                error_codes::CONNECTION_ERROR,
+            ));
+        }
+        if !Path::new(&path).is_file() {
+            return Err(QueryError::with_code(
+                format!("DuckDB database file not found: {}", path),
+                error_codes::CONNECTION_ERROR,
+            ));
+        }
+
+        let open_path = path.clone();
+        let database = tokio::task::spawn_blocking(move || Database::open(open_path))
+            .await
+            .map_err(|e| {
+                QueryError::with_code(
+                    format!("Opening the database failed: {}", e),
+                    error_codes::CONNECTION_ERROR,
+                )
+            })?
+            // Another process holding the file open for writing is the usual cause
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::CONNECTION_ERROR))?;
+        debug!("Opened DuckDB database {}", path);
+
+        Ok(DuckDbConnection {
+            interrupt: database.interrupt_handle(),
+            database: Arc::new(Mutex::new(database)),
+            path,
+            query_timeout: query_timeout.unwrap_or(DEFAULT_QUERY_TIMEOUT),
+        })
+    }
+
+    /// Runs `work` with the connection on the blocking thread pool, interrupting it when
+    /// it runs past the query timeout.
+    async fn with_database<T, F>(&self, work: F) -> DbResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Database) -> DbResult<T> + Send + 'static,
+    {
+        let mut database = self.database.clone().lock_owned().await;
+        let mut task = tokio::task::spawn_blocking(move || work(&mut database));
+        let joined = match timeout(self.query_timeout, &mut task).await {
+            Ok(joined) => joined,
+            Err(_) => {
+                self.interrupt.interrupt();
+                // The interrupted call fails shortly after; the connection is free once
+                // it has
+                let _ = task.await;
+                return Err(QueryError::with_code(
+                    "Query timed out",
+                    error_codes::TIMEOUT_ERROR,
+                ));
+            }
+        };
+        joined.map_err(|e| {
+            QueryError::with_code(
+                format!("DuckDB call failed: {}", e),
+                error_codes::QUERY_ERROR,
+            )
+        })?
+    }
+
+    /// Runs one statement, keeping at most `MAX_QUERY_ROWS` rows. DML reports its count
+    /// as a one-column result, which becomes `affected_rows`.
+    fn run_statement(
+        database: &Database,
+        statement: &Statement,
+        timer: &mut QueryTimer,
+    ) -> DbResult<Fetched> {
+        let mut stmt = database.prepare(&statement.sql).map_err(query_error)?;
+        let mut rows = stmt.query([]).map_err(query_error)?;
+        timer.executed();
+
+        let executed = rows.as_ref().ok_or_else(|| {
+            QueryError::with_code("Statement returned no result", error_codes::QUERY_ERROR)
+        })?;
+        let columns = executed.column_names();
+        let column_metadata: Vec<ColumnMetadata> = columns
+            .iter()
+            .enumerate()
+            .map(|(index, name)| column_metadata(name, &executed.column_logical_type(index)))
+            .collect();
+
+        let mut fetched = Vec::new();
+        let mut row_count = 0;
+        while let Some(row) = rows.next().map_err(query_error)? {
+            row_count += 1;
+            if fetched.len() < MAX_QUERY_ROWS {
+                let values = (0..columns.len())
+                    .map(|index| row.get::<_, Value>(index))
+                    .collect::<duckdb::Result<Vec<_>>>()
+                    .map_err(query_error)?;
+                fetched.push(values);
+            }
+        }
+        timer.fetched();
+
+        let is_dml = matches!(
+            statement.leading_keyword(),
+            Some("INSERT" | "UPDATE" | "DELETE")
+        ) && !statement.has_keyword("RETURNING");
+        if is_dml && columns.len() == 1 && fetched.len() == 1 {
+            let affected_rows = match fetched[0].first() {
+                Some(Value::BigInt(count)) => u64::try_from(*count).ok(),
+                _ => None,
+            };
+            return Ok(Fetched {
+                columns: Vec::new(),
+                column_metadata: Vec::new(),
+                rows: Vec::new(),
+                row_count: 0,
+                affected_rows,
+            });
+        }
+        Ok(Fetched {
+            columns,
+            column_metadata,
+            rows: fetched,
+            row_count,
+            affected_rows: None,
+        })
+    }
+
+    /// Runs a metadata query and returns its rows as JSON objects.
+    fn query_rows(database: &Database, sql: &str) -> DbResult<Vec<serde_json::Value>> {
+        let statement = Statement {
+            sql: sql.to_string(),
+            keywords: vec!["SELECT".to_string()],
+        };
+        let fetched = Self::run_statement(database, &statement, &mut QueryTimer::start())?;
+        Ok(fetched
+            .rows
+            .iter()
+            .map(|row| Self::row_to_json(&fetched.columns, row))
+            .collect())
+    }
+
+    fn row_to_json(columns: &[String], row: &[Value]) -> serde_json::Value {
+        let mut row_map = serde_json::Map::with_capacity(columns.len());
+        for (column, value) in columns.iter().zip(row) {
+            row_map.insert(column.clone(), value_to_json(value));
+        }
+        serde_json::Value::Object(row_map)
+    }
+
+    /// Runs a statement without a result.
+    fn execute_batch(database: &Database, sql: &str) -> DbResult<()> {
+        database.execute_batch(sql).map_err(query_error)
+    }
+
+    /// The schema an unqualified table belongs to, as an SQL expression.
+    fn schema_expression(table: &TableRef) -> String {
+        match &table.schema {
+            Some(schema) => DIALECT.quote_string(schema),
+            None => "current_schema()".to_string(),
+        }
+    }
+
+    fn table_names(database: &Database) -> DbResult<Vec<String>> {
+        let rows = Self::query_rows(
+            database,
+            "SELECT table_name FROM information_schema.tables
+             WHERE table_catalog = current_database() AND table_schema = current_schema()
+                AND table_type = 'BASE TABLE'
+             ORDER BY table_name",
+        )?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| text(row, "table_name"))
+            .collect())
+    }
+
+    fn table_columns(database: &Database, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        let schema = Self::schema_expression(table);
+        let name = DIALECT.quote_string(&table.name);
+        let query = format!(
+            "SELECT
+                c.column_name,
+                c.data_type,
+                c.is_nullable = 'YES' AS is_nullable,
+                c.column_default,
+                c.character_maximum_length,
+                c.numeric_precision,
+                EXISTS (
+                    SELECT 1 FROM duckdb_constraints() k
+                    WHERE k.constraint_type = 'PRIMARY KEY'
+                        AND k.database_name = c.table_catalog
+                        AND k.schema_name = c.table_schema
+                        AND k.table_name = c.table_name
+                        AND list_contains(k.constraint_column_names, c.column_name)
+                ) AS is_primary
+             FROM information_schema.columns c
+             WHERE c.table_catalog = current_database() AND c.table_schema = {schema}
+                AND c.table_name = {name}
+             ORDER BY c.ordinal_position"
+        );
+        let rows = Self::query_rows(database, &query)?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let column_default = text(row, "column_default");
+                Some(TableColumn {
+                    name: text(row, "column_name")?,
+                    data_type: text(row, "data_type")?,
+                    is_nullable: flag(row, "is_nullable"),
+                    is_primary_key: flag(row, "is_primary"),
+                    // Auto-incrementing columns take their values from a sequence
+                    is_identity: column_default
+                        .as_deref()
+                        .is_some_and(|d| d.starts_with("nextval(")),
+                    column_default,
+                    character_maximum_length: number(row, "character_maximum_length"),
+                    numeric_precision: number(row, "numeric_precision"),
+                    identity_generation: None,
+                    is_generated: false,
+                    generation_expression: None,
+                    generation_kind: None,
+                    allowed_values: None,
+                })
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl DatabaseConnection for DuckDbConnection {
+    async fn test_connection(&self) -> DbResult<()> {
+        self.with_database(|database| Self::execute_batch(database, "SELECT 1"))
+            .await
+    }
+
+    async fn reconnect_if_broken(&self) -> DbResult<bool> {
+        // The database runs in process; there is no connection to lose
+        Ok(false)
+    }
+
+    async fn execute_query(&self, query: &str) -> DbResult<QueryResult> {
+        let mut timer = QueryTimer::start();
+        let statements = statement::split_statements(query);
+        let (fetched, mut timer) = self
+            .with_database(move |database| {
+                timer.acquired();
+                // Scripts run statement by statement, showing the result of the last one
+                let mut fetched = None;
+                for statement in &statements {
+                    fetched = Some(Self::run_statement(database, statement, &mut timer)?);
+                }
+                Ok((fetched, timer))
+            })
+            .await?;
+        let Some(fetched) = fetched else {
+            return Err(QueryError::with_code(
+                "Query is empty",
+                error_codes::QUERY_ERROR,
+            ));
+        };
+
+        let rows = fetched
+            .rows
+            .iter()
+            .map(|row| Self::row_to_json(&fetched.columns, row))
+            .collect();
+        timer.converted();
+
+        Ok(QueryResult {
+            columns: fetched.columns,
+            rows,
+            row_count: fetched.row_count,
+            execution_time: timer.execution_time(),
+            truncated: fetched.row_count > MAX_QUERY_ROWS,
+            affected_rows: fetched.affected_rows,
+            timing: timer.timing(),
+            column_metadata: fetched.column_metadata,
+        })
+    }
+
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult> {
+        // Local files are read quickly; the result is only chunked for the consumer
+        let mut result = self.execute_query(query).await?;
+        let mut chunks = ChunkedRows::new(result.columns.clone(), chunk_size, on_chunk);
+        for row in std::mem::take(&mut result.rows) {
+            if !chunks.push(row) {
+                break;
+            }
+        }
+        chunks.finish();
+        Ok(result)
+    }
+
+    async fn list_tables(&self) -> DbResult<Vec<String>> {
+        self.with_database(|database| Self::table_names(database))
+            .await
+    }
+
+    async fn list_databases(&self) -> DbResult<Vec<String>> {
+        // The opened file and any attached with ATTACH
+        self.with_database(|database| {
+            let rows = Self::query_rows(
+                database,
+                "SELECT database_name FROM duckdb_databases()
+                 WHERE NOT internal ORDER BY database_name",
+            )?;
+            Ok(rows
+                .iter()
+                .filter_map(|row| text(row, "database_name"))
+                .collect())
+        })
+        .await
+    }
+
+    async fn change_database(&self, database_name: &str) -> DbResult<()> {
+        let statement = format!("USE {}", DIALECT.quote_identifier(database_name));
+        self.with_database(move |database| Self::execute_batch(database, &statement))
+            .await
+    }
+
+    async fn set_default_schema(&self, schema: &str) -> DbResult<()> {
+        let statement = format!("SET schema = {}", DIALECT.quote_string(schema));
+        self.with_database(move |database| Self::execute_batch(database, &statement))
+            .await
+    }
+
+    async fn get_current_database(&self) -> DbResult<String> {
+        self.with_database(|database| {
+            let rows = Self::query_rows(database, "SELECT current_database() AS name")?;
+            Ok(rows
+                .first()
+                .and_then(|row| text(row, "name"))
+                .unwrap_or_default())
+        })
+        .await
+    }
+
+    async fn get_table_columns(&self, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        let table = table.clone();
+        self.with_database(move |database| Self::table_columns(database, &table))
+            .await
+    }
+
+    async fn get_table_relationships(&self) -> DbResult<Vec<TableRelationship>> {
+        self.with_database(|database| {
+            let rows = Self::query_rows(
+                database,
+                "SELECT schema_name, table_name, constraint_column_names, referenced_table,
+                    referenced_column_names, constraint_name
+                 FROM duckdb_constraints()
+                 WHERE constraint_type = 'FOREIGN KEY' AND database_name = current_database()
+                    AND schema_name = current_schema()
+                 ORDER BY table_name",
+            )?;
+            let mut relationships = Vec::new();
+            for row in &rows {
+                let (Some(from_table), Some(to_table), Some(constraint_name)) = (
+                    text(row, "table_name"),
+                    text(row, "referenced_table"),
+                    text(row, "constraint_name"),
+                ) else {
+                    continue;
+                };
+                let columns = |column: &str| {
+                    row.get(column)
+                        .and_then(serde_json::Value::as_array)
+                        .cloned()
+                        .unwrap_or_default()
+                };
+                // Composite keys list their columns in order on both sides
+                for (from, to) in columns("constraint_column_names")
+                    .iter()
+                    .zip(&columns("referenced_column_names"))
+                {
+                    let (Some(from_column), Some(to_column)) = (from.as_str(), to.as_str()) else {
+                        continue;
+                    };
+                    relationships.push(TableRelationship {
+                        from_schema: text(row, "schema_name"),
+                        from_table: from_table.clone(),
+                        from_column: from_column.to_string(),
+                        // Foreign keys reference tables in their own schema
+                        to_schema: text(row, "schema_name"),
+                        to_table: to_table.clone(),
+                        to_column: to_column.to_string(),
+                        constraint_name: constraint_name.clone(),
+                    });
+                }
+            }
+            Ok(relationships)
+        })
+        .await
+    }
+
+    async fn check_privileges(
+        &self,
+        _table: &TableRef,
+        _operation: TableOperation,
+    ) -> DbResult<bool> {
+        // A local file has no grants; it is opened for writing
+        Ok(true)
+    }
+
+    async fn disconnect(&self) -> DbResult<()> {
+        // The file is closed when the last reference to the connection is dropped
+        debug!("Disconnecting from DuckDB database {}", self.path);
+        Ok(())
+    }
+
+    async fn export_database_with_options(
+        &self,
+        include_drop: bool,
+        include_create: bool,
+        data_mode: &str,
+        selected_tables: &[TableRef],
+        max_insert_size: usize,
+    ) -> DbResult<String> {
+        if !matches!(data_mode, "insert" | "no_data") {
+            return Err(QueryError::with_code(
+                format!(
+                    "DuckDB exports support the insert and no_data modes, not '{}'",
+                    data_mode
+                ),
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let max_insert_size = max_insert_size.max(1);
+        let include_data = data_mode != "no_data";
+        let selected_tables = selected_tables.to_vec();
+
+        self.with_database(move |database| {
+            let tables_to_export = if selected_tables.is_empty() {
+                Self::table_names(database)?
+                    .into_iter()
+                    .map(TableRef::unqualified)
+                    .collect()
+            } else {
+                selected_tables
+            };
+
+            let mut sql_content = String::with_capacity(1024 * 1024);
+            for table in tables_to_export {
+                let qualified_table = DIALECT.qualified_name(&table);
+                let columns = Self::table_columns(database, &table)?;
+                if columns.is_empty() {
+                    return Err(QueryError::with_code(
+                        format!("Table \"{}\" not found", table),
+                        error_codes::QUERY_ERROR,
+                    ));
+                }
+                sql_content.push_str(&format!("\n-- Table: {}\n", table));
+
+                if include_drop {
+                    sql_content.push_str(&format!("DROP TABLE IF EXISTS {};\n", qualified_table));
+                }
+
+                if include_create {
+                    let rows = Self::query_rows(
+                        database,
+                        &format!(
+                            "SELECT sql FROM duckdb_tables()
+                             WHERE database_name = current_database() AND schema_name = {}
+                                AND table_name = {}",
+                            Self::schema_expression(&table),
+                            DIALECT.quote_string(&table.name)
+                        ),
+                    )?;
+                    let create = rows
+                        .first()
+                        .and_then(|row| text(row, "sql"))
+                        .unwrap_or_default();
+                    sql_content.push_str(create.trim().trim_end_matches(';'));
+                    sql_content.push_str(";\n\n");
+                }
+
+                if !include_data {
+                    continue;
+                }
+
+                let column_list = columns
+                    .iter()
+                    .map(|c| DIALECT.quote_identifier(&c.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let mut stmt = database
+                    .prepare(&format!("SELECT {} FROM {}", column_list, qualified_table))
+                    .map_err(query_error)?;
+                let mut rows = stmt.query([]).map_err(query_error)?;
+                let mut row_buffer: Vec<String> = Vec::with_capacity(max_insert_size);
+                let flush = |row_buffer: &mut Vec<String>, sql_content: &mut String| {
+                    sql_content.push_str(&format!(
+                        "INSERT INTO {} ({}) VALUES\n  {};\n",
+                        qualified_table,
+                        column_list,
+                        row_buffer.join(",\n  ")
+                    ));
+                    row_buffer.clear();
+                };
+                while let Some(row) = rows.next().map_err(query_error)? {
+                    let values = (0..columns.len())
+                        .map(|index| row.get::<_, Value>(index).map(|v| value_to_sql(&v)))
+                        .collect::<duckdb::Result<Vec<_>>>()
+                        .map_err(query_error)?;
+                    row_buffer.push(format!("({})", values.join(", ")));
+
+                    if row_buffer.len() >= max_insert_size {
+                        flush(&mut row_buffer, &mut sql_content);
+                    }
+                }
+                if !row_buffer.is_empty() {
+                    flush(&mut row_buffer, &mut sql_content);
+                }
+                sql_content.push('\n');
+            }
+
+            Ok(sql_content)
+        })
+        .await
+    }
+
+    async fn update_cell(
+        &self,
+        table: &TableRef,
+        column_name: &str,
+        new_value: Option<&str>,
+        primary_key_column: &str,
+        primary_key_value: &str,
+    ) -> DbResult<String> {
+        // DuckDB casts quoted text to the column types
+        let query = format!(
+            "UPDATE {} SET {} = {} WHERE {} = {}",
+            DIALECT.qualified_name(table),
+            DIALECT.quote_identifier(column_name),
+            new_value
+                .map(|value| DIALECT.quote_string(value))
+                .unwrap_or_else(|| "NULL".to_string()),
+            DIALECT.quote_identifier(primary_key_column),
+            DIALECT.quote_string(primary_key_value)
+        );
+        let statement = query.clone();
+        self.with_database(move |database| Self::execute_batch(database, &statement))
+            .await?;
+        Ok(query)
+    }
+
+    async fn insert_rows(
+        &self,
+        table: &TableRef,
+        columns: &[String],
+        rows: &[Vec<Option<String>>],
+    ) -> DbResult<u64> {
+        let column_list = columns
+            .iter()
+            .map(|name| DIALECT.quote_identifier(name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let statements: Vec<String> = rows
+            .chunks(INSERT_BATCH_ROWS)
+            .map(|batch| {
+                let values: Vec<String> = batch
+                    .iter()
+                    .map(|row| {
+                        let values: Vec<String> = row
+                            .iter()
+                            .map(|value| match value {
+                                Some(value) => DIALECT.quote_string(value),
+                                None => "NULL".to_string(),
+                            })
+                            .collect();
+                        format!("({})", values.join(", "))
+                    })
+                    .collect();
+                format!(
+                    "INSERT INTO {} ({}) VALUES {}",
+                    DIALECT.qualified_name(table),
+                    column_list,
+                    values.join(", ")
+                )
+            })
+            .collect();
+        let row_count = rows.len() as u64;
+        let table_name = table.to_string();
+
+        self.with_database(move |database| {
+            // The batches commit together
+            let transaction = database.transaction().map_err(query_error)?;
+            for statement in &statements {
+                transaction.execute_batch(statement).map_err(query_error)?;
+            }
+            transaction.commit().map_err(query_error)?;
+            debug!("Inserted {} rows into {}", row_count, table_name);
+            Ok(row_count)
+        })
+        .await
+    }
+
+    async fn get_view_definition(&self, view: &TableRef) -> DbResult<String> {
+        let query = format!(
+            "SELECT sql FROM duckdb_views()
+             WHERE database_name = current_database() AND schema_name = {}
+                AND view_name = {}",
+            Self::schema_expression(view),
+            DIALECT.quote_string(&view.name)
+        );
+        let view = view.clone();
+        self.with_database(move |database| {
+            let rows = Self::query_rows(database, &query)?;
+            let definition = rows.first().and_then(|row| text(row, "sql"));
+            // The SQL is the whole CREATE VIEW statement; the query follows the first AS
+            definition
+                .as_deref()
+                .and_then(|sql| {
+                    let start = sql.to_ascii_uppercase().find(" AS ")?;
+                    Some(sql[start + 4..].trim().trim_end_matches(';').to_string())
+                })
+                .ok_or_else(|| {
+                    QueryError::with_code(
+                        format!("View \"{}\" not found", view),
+                        error_codes::QUERY_ERROR,
+                    )
+                })
+        })
+        .await
+    }
+
+    async fn alter_view(&self, view: &TableRef, query: &str) -> DbResult<()> {
+        let statement = format!(
+            "CREATE OR REPLACE VIEW {} AS\n{}",
+            DIALECT.qualified_name(view),
+            query.trim().trim_end_matches(';')
+        );
+        self.with_database(move |database| Self::execute_batch(database, &statement))
+            .await
+    }
+
+    async fn build_sample_query(
+        &self,
+        table: &TableRef,
+        n: usize,
+        method: SampleMethod,
+    ) -> DbResult<String> {
+        let name = DIALECT.qualified_name(table);
+        if method == SampleMethod::Random {
+            return Ok(sampling::random_query(&name, "random()", n));
+        }
+        // Reservoir sampling picks exactly n rows uniformly in one pass
+        Ok(format!(
+            "SELECT * FROM {} USING SAMPLE {} ROWS",
+            name,
+            sampling::sample_size(n)
+        ))
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        DIALECT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tables_and_values() {
+        let path = std::env::temp_dir().join(format!("bloatsql-{}.duckdb", uuid::Uuid::new_v4()));
+        Database::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name VARCHAR NOT NULL);
+                 CREATE TABLE posts (
+                    id INTEGER PRIMARY KEY,
+                    user_id INTEGER REFERENCES users (id),
+                    price DECIMAL(10, 2),
+                    tags VARCHAR[],
+                    created TIMESTAMP
+                 );
+                 INSERT INTO users VALUES (1, 'it''s');
+                 INSERT INTO posts VALUES
+                    (1, 1, -1.5, ['a', 'b'], TIMESTAMP '2024-05-01 12:00:00.25');",
+            )
+            .unwrap();
+        let conn = DuckDbConnection::new(path.to_str().unwrap(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(conn.list_tables().await.unwrap(), ["posts", "users"]);
+        let columns = conn
+            .get_table_columns(&TableRef::unqualified("posts"))
+            .await
+            .unwrap();
+        assert_eq!(columns.len(), 5);
+        assert!(columns[0].is_primary_key && !columns[0].is_nullable);
+        assert_eq!(columns[2].data_type, "DECIMAL(10,2)");
+        let relationships = conn.get_table_relationships().await.unwrap();
+        assert_eq!(relationships[0].to_table, "users");
+
+        let result = conn
+            .execute_query("SELECT price, tags, created FROM posts")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows[0],
+            serde_json::json!({
+                "price": "-1.50",
+                "tags": ["a", "b"],
+                "created": "2024-05-01 12:00:00.250"
+            })
+        );
+        assert_eq!(result.column_metadata[0].precision, Some(10));
+        let updated = conn
+            .execute_query("UPDATE users SET name = 'x'")
+            .await
+            .unwrap();
+        assert_eq!(updated.affected_rows, Some(1));
+
+        let dump = conn
+            .export_database_with_options(false, false, "insert", &[], 100)
+            .await
+            .unwrap();
+        assert!(dump.contains(
+            "INSERT INTO \"posts\" (\"id\", \"user_id\", \"price\", \"tags\", \"created\") \
+             VALUES\n  (1, 1, -1.50, ['a', 'b'], '2024-05-01 12:00:00.250');"
+        ));
+        assert_eq!(
+            format_interval(14, 3, 3_723_000_000_000),
+            "1 year 2 months 3 days 01:02:03"
+        );
+        let error = conn.get_roles().await.unwrap_err();
+        assert_eq!(error.code.as_deref(), Some(error_codes::UNSUPPORTED));
+
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+    }
+}
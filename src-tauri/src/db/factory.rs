@@ -1,13 +1,347 @@
-use super::connection::{DatabaseConnection, DbResult, QueryError};
+use super::bigquery::BigQueryConnection;
+use super::cassandra::CassandraConnection;
+use super::clickhouse::ClickHouseConnection;
+use super::connection::{error_codes, DatabaseConnection, DbResult, PoolSize, QueryError, SslMode};
+use super::duckdb::DuckDbConnection;
 use super::mariadb::MariaDbConnection;
-use super::postgresql::PostgresConnection;
+use super::mongodb::MongoDbConnection;
+use super::mssql::MssqlConnection;
+use super::oracle::OracleConnection;
+use super::postgresql::{PostgresConnection, PostgresFlavor};
+use super::rds_iam::RdsIamAuth;
+use super::redis::RedisConnection;
+use super::serverless::{ServerlessConnection, ServerlessProvider};
+use serde::Serialize;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// SSL modes of the wire protocol drivers, in the order the connection form lists them.
+const SSL_MODES: [&str; 5] = [
+    "disabled",
+    "preferred",
+    "required",
+    "verify-ca",
+    "verify-full",
+];
+
+/// SSL modes of Oracle, whose client always checks the server certificate against its wallet.
+const ORACLE_SSL_MODES: [&str; 3] = ["disabled", "verify-ca", "verify-full"];
+
+/// SSL modes of drivers that only speak HTTPS.
+const HTTPS_SSL_MODES: [&str; 3] = ["verify-full", "verify-ca", "required"];
+
+/// SSL modes of Cassandra, whose driver can't fall back to plain TCP when TLS fails.
+const CQL_SSL_MODES: [&str; 4] = ["disabled", "required", "verify-ca", "verify-full"];
+
+/// SSL modes of MongoDB and Redis, whose drivers either skip certificate checks or check
+/// both the certificate and the host name.
+const STRICT_TLS_SSL_MODES: [&str; 3] = ["disabled", "required", "verify-full"];
+
+/// What a database type supports, so the connection form and UI can adapt to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct DriverInfo {
+    /// Canonical `db_type` to store for connections of this type.
+    pub db_type: &'static str,
+    /// Name to show in the UI.
+    pub name: &'static str,
+    /// None when the driver ignores the port.
+    pub default_port: Option<u16>,
+    pub ssl_modes: Vec<&'static str>,
+    pub default_ssl_mode: &'static str,
+    /// A custom CA certificate (`ssl_ca_cert`) can be trusted for verifying the server.
+    pub custom_ca_cert: bool,
+    /// The pool size can be set with `pool_min_size` and `pool_max_size`.
+    pub configurable_pool_size: bool,
+    /// Can log in to AWS RDS with IAM tokens instead of a password.
+    pub rds_iam_auth: bool,
+    /// The database is a file on this machine, given as the connection's `database`; host,
+    /// port and credentials are ignored.
+    pub file_based: bool,
+    pub features: DriverFeatures,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct DriverFeatures {
+    /// Statements can be grouped in a transaction across requests.
+    pub transactions: bool,
+    /// `list_databases` and `change_database` work.
+    pub multiple_databases: bool,
+    /// Tables are grouped in schemas within a database.
+    pub schemas: bool,
+    /// Large results can be paged with server-side cursors.
+    pub cursors: bool,
+}
+
+/// Returns what the driver for `db_type` (any alias, case-insensitive) supports.
+///
+/// # Errors
+/// Returns `INVALID_DB_TYPE` for unsupported database types.
+pub fn driver_info(db_type: &str) -> DbResult<DriverInfo> {
+    let info = match db_type.to_lowercase().as_str() {
+        "mariadb" | "mysql" => DriverInfo {
+            db_type: "mariadb",
+            name: "MariaDB / MySQL",
+            default_port: Some(3306),
+            ssl_modes: SSL_MODES.to_vec(),
+            default_ssl_mode: "preferred",
+            custom_ca_cert: true,
+            configurable_pool_size: true,
+            rds_iam_auth: true,
+            file_based: false,
+            features: DriverFeatures {
+                transactions: true,
+                multiple_databases: true,
+                schemas: false,
+                cursors: true,
+            },
+        },
+        "postgresql" | "postgres" => DriverInfo {
+            db_type: "postgresql",
+            name: "PostgreSQL",
+            default_port: Some(5432),
+            ssl_modes: SSL_MODES.to_vec(),
+            default_ssl_mode: "preferred",
+            custom_ca_cert: true,
+            configurable_pool_size: true,
+            rds_iam_auth: true,
+            file_based: false,
+            features: DriverFeatures {
+                transactions: true,
+                multiple_databases: true,
+                schemas: true,
+                cursors: true,
+            },
+        },
+        "cockroachdb" | "cockroach" => DriverInfo {
+            db_type: "cockroachdb",
+            name: "CockroachDB",
+            default_port: Some(26257),
+            ssl_modes: SSL_MODES.to_vec(),
+            default_ssl_mode: "preferred",
+            custom_ca_cert: true,
+            configurable_pool_size: true,
+            rds_iam_auth: false,
+            file_based: false,
+            // Its cursors only move forward, and result pages are fetched by offset
+            features: DriverFeatures {
+                transactions: true,
+                multiple_databases: true,
+                schemas: true,
+                cursors: false,
+            },
+        },
+        "mssql" | "sqlserver" => DriverInfo {
+            db_type: "mssql",
+            name: "SQL Server",
+            default_port: Some(1433),
+            ssl_modes: SSL_MODES.to_vec(),
+            default_ssl_mode: "preferred",
+            custom_ca_cert: false,
+            configurable_pool_size: false,
+            rds_iam_auth: false,
+            file_based: false,
+            features: DriverFeatures {
+                transactions: true,
+                multiple_databases: true,
+                schemas: true,
+                cursors: false,
+            },
+        },
+        "oracle" => DriverInfo {
+            db_type: "oracle",
+            name: "Oracle",
+            default_port: Some(1521),
+            ssl_modes: ORACLE_SSL_MODES.to_vec(),
+            default_ssl_mode: "disabled",
+            custom_ca_cert: false,
+            configurable_pool_size: false,
+            rds_iam_auth: false,
+            file_based: false,
+            // Connections are made to one service and statements commit on their own
+            features: DriverFeatures {
+                transactions: false,
+                multiple_databases: false,
+                schemas: true,
+                cursors: false,
+            },
+        },
+        "clickhouse" => DriverInfo {
+            db_type: "clickhouse",
+            name: "ClickHouse (HTTP)",
+            default_port: Some(8123),
+            ssl_modes: SSL_MODES.to_vec(),
+            default_ssl_mode: "disabled",
+            custom_ca_cert: false,
+            configurable_pool_size: false,
+            rds_iam_auth: false,
+            file_based: false,
+            // Each statement is a separate request without a session
+            features: DriverFeatures {
+                transactions: false,
+                multiple_databases: true,
+                schemas: false,
+                cursors: false,
+            },
+        },
+        "cassandra" | "scylladb" | "scylla" => DriverInfo {
+            db_type: "cassandra",
+            name: "Cassandra / ScyllaDB",
+            default_port: Some(9042),
+            ssl_modes: CQL_SSL_MODES.to_vec(),
+            default_ssl_mode: "disabled",
+            custom_ca_cert: false,
+            configurable_pool_size: false,
+            rds_iam_auth: false,
+            file_based: false,
+            // Keyspaces are the databases; CQL has no transactions
+            features: DriverFeatures {
+                transactions: false,
+                multiple_databases: true,
+                schemas: false,
+                cursors: false,
+            },
+        },
+        "mongodb" | "mongo" => DriverInfo {
+            db_type: "mongodb",
+            name: "MongoDB",
+            default_port: Some(27017),
+            ssl_modes: STRICT_TLS_SSL_MODES.to_vec(),
+            default_ssl_mode: "disabled",
+            custom_ca_cert: false,
+            configurable_pool_size: false,
+            rds_iam_auth: false,
+            file_based: false,
+            // Databases hold collections, which are browsed like tables
+            features: DriverFeatures {
+                transactions: false,
+                multiple_databases: true,
+                schemas: false,
+                cursors: false,
+            },
+        },
+        "redis" => DriverInfo {
+            db_type: "redis",
+            name: "Redis",
+            default_port: Some(6379),
+            ssl_modes: STRICT_TLS_SSL_MODES.to_vec(),
+            default_ssl_mode: "disabled",
+            custom_ca_cert: false,
+            configurable_pool_size: false,
+            rds_iam_auth: false,
+            file_based: false,
+            // Numbered databases hold keys, browsed by namespace
+            features: DriverFeatures {
+                transactions: false,
+                multiple_databases: true,
+                schemas: false,
+                cursors: false,
+            },
+        },
+        "bigquery" => DriverInfo {
+            db_type: "bigquery",
+            name: "BigQuery",
+            default_port: None,
+            ssl_modes: vec!["verify-full"],
+            default_ssl_mode: "verify-full",
+            custom_ca_cert: false,
+            configurable_pool_size: false,
+            rds_iam_auth: false,
+            file_based: false,
+            // Datasets are the databases; each query is a separate job
+            features: DriverFeatures {
+                transactions: false,
+                multiple_databases: true,
+                schemas: false,
+                cursors: false,
+            },
+        },
+        "duckdb" => DriverInfo {
+            db_type: "duckdb",
+            name: "DuckDB (file)",
+            default_port: None,
+            ssl_modes: vec!["disabled"],
+            default_ssl_mode: "disabled",
+            custom_ca_cert: false,
+            configurable_pool_size: false,
+            rds_iam_auth: false,
+            file_based: true,
+            // Schemas within the file, plus any databases attached with ATTACH
+            features: DriverFeatures {
+                transactions: false,
+                multiple_databases: true,
+                schemas: true,
+                cursors: false,
+            },
+        },
+        "neon" => DriverInfo {
+            db_type: "neon",
+            name: "Neon (HTTP)",
+            default_port: None,
+            ssl_modes: HTTPS_SSL_MODES.to_vec(),
+            default_ssl_mode: "verify-full",
+            custom_ca_cert: false,
+            configurable_pool_size: false,
+            rds_iam_auth: false,
+            file_based: false,
+            // Each statement is a separate request without a session
+            features: DriverFeatures {
+                transactions: false,
+                multiple_databases: true,
+                schemas: true,
+                cursors: false,
+            },
+        },
+        "planetscale" => DriverInfo {
+            db_type: "planetscale",
+            name: "PlanetScale (HTTP)",
+            default_port: None,
+            ssl_modes: HTTPS_SSL_MODES.to_vec(),
+            default_ssl_mode: "verify-full",
+            custom_ca_cert: false,
+            configurable_pool_size: false,
+            rds_iam_auth: false,
+            file_based: false,
+            // The session travels with each request, so transactions span them
+            features: DriverFeatures {
+                transactions: true,
+                multiple_databases: true,
+                schemas: false,
+                cursors: false,
+            },
+        },
+        _ => {
+            return Err(QueryError::with_code(
+                format!(
+                    "Unsupported database type: '{}'. Supported types: mariadb, mysql, postgresql, postgres, cockroachdb, mssql, sqlserver, oracle, clickhouse, cassandra, scylladb, mongodb, redis, bigquery, duckdb, neon, planetscale",
+                    db_type
+                ),
+                "INVALID_DB_TYPE",
+            ))
+        }
+    };
+    Ok(info)
+}
 
 /// Creates a database connection based on the specified database type.
 ///
 /// # Supported Database Types
 /// - "mariadb" or "mysql" - Creates a MariaDB/MySQL connection
 /// - "postgresql" or "postgres" - Creates a PostgreSQL connection
+/// - "cockroachdb" - Creates a PostgreSQL connection that reads CockroachDB's catalogs
+/// - "mssql" or "sqlserver" - Creates a Microsoft SQL Server connection
+/// - "oracle" - Creates an Oracle connection; the database is the service name
+/// - "clickhouse" - Creates a ClickHouse connection over its HTTP interface
+/// - "cassandra" or "scylladb" - Creates a CQL connection; the database is the keyspace
+/// - "mongodb" - Creates a MongoDB connection; collections are browsed as tables and the
+///   query editor takes `find` and `aggregate` calls
+/// - "redis" - Creates a Redis connection; the database is the database number, key
+///   namespaces are browsed as tables and the query editor takes Redis commands
+/// - "bigquery" - Connects to BigQuery with a service account key (its JSON or the key
+///   file's path) as the password; the username overrides the key's project, the database
+///   is the default dataset, and the host and port are ignored
+/// - "duckdb" - Opens a local DuckDB file; the database is the file path
+/// - "neon" or "planetscale" - Connects through the provider's HTTP API, for networks that
+///   block the database ports (the port is ignored)
 ///
 /// # Arguments
 /// * `db_type` - The type of database (case-insensitive)
@@ -16,14 +350,31 @@ use std::sync::Arc;
 /// * `username` - The database username
 /// * `password` - The database password
 /// * `database` - The database name to connect to
-/// * `ssl_mode` - The SSL mode ("disabled", "preferred", or "required")
+/// * `ssl_mode` - The SSL mode ("disabled", "preferred", "required", "verify-ca" or
+///   "verify-full", see `SslMode`)
+/// * `ssl_ca_cert` - PEM file of a CA to trust for verifying the server certificate, for
+///   drivers with `DriverInfo::custom_ca_cert`
+/// * `pool_size` - Bounds on pooled server connections, for drivers with
+///   `DriverInfo::configurable_pool_size`
+/// * `query_timeout` - Timeout for user statements, also set on the server where the
+///   database has one (e.g. `statement_timeout`, `max_execution_time`, `maxTimeMS`); `None`
+///   uses `DEFAULT_QUERY_TIMEOUT`
+/// * `rds_iam` - Log in with AWS RDS IAM tokens instead of `password`, for drivers with
+///   `DriverInfo::rds_iam_auth`. A fresh token is generated whenever the driver reconnects
 ///
 /// # Returns
 /// Returns `Arc<dyn DatabaseConnection>` ready to be inserted into ActiveConnection
 ///
 /// # Errors
 /// - Returns `INVALID_DB_TYPE` error code for unsupported database types
-/// - Propagates connection errors from the underlying database driver
+/// - Returns `SSL_ERROR` for a CA certificate on drivers that can't use one
+/// - Returns `INVALID_POOL_SIZE` for a pool size on drivers without a pool, or bounds that
+///   don't fit together
+/// - Returns `AUTH_ERROR` for RDS IAM authentication on drivers that can't use it, or
+///   without AWS credentials; `SSL_ERROR` for it without required TLS
+/// - Propagates connection errors from the underlying database driver, with the password
+///   removed
+#[allow(clippy::too_many_arguments)]
 pub async fn create_connection(
     db_type: &str,
     host: &str,
@@ -32,26 +383,179 @@ pub async fn create_connection(
     password: &str,
     database: &str,
     ssl_mode: &str,
+    ssl_ca_cert: Option<&str>,
+    pool_size: PoolSize,
+    query_timeout: Option<Duration>,
+    rds_iam: Option<RdsIamAuth>,
 ) -> DbResult<Arc<dyn DatabaseConnection>> {
-    match db_type.to_lowercase().as_str() {
-        "mariadb" | "mysql" => {
-            let conn = MariaDbConnection::new(host, port, username, password, database, ssl_mode)
-                .await?;
-            Ok(Arc::new(conn))
-        }
-        "postgresql" | "postgres" => {
-            let conn = PostgresConnection::new(host, port, username, password, database, ssl_mode)
-                .await?;
-            Ok(Arc::new(conn))
-        }
-        _ => Err(QueryError::with_code(
+    let info = driver_info(db_type)?;
+    // Other drivers would silently verify against the system trust store instead
+    if ssl_ca_cert.is_some() && !info.custom_ca_cert {
+        return Err(QueryError::with_code(
             format!(
-                "Unsupported database type: '{}'. Supported types: mariadb, mysql, postgresql, postgres",
-                db_type
+                "{} connections can't use a custom CA certificate",
+                info.name
             ),
+            error_codes::SSL_ERROR,
+        ));
+    }
+    if !pool_size.is_default() && !info.configurable_pool_size {
+        return Err(QueryError::with_code(
+            format!("{} connections don't have a configurable pool", info.name),
+            error_codes::INVALID_POOL_SIZE,
+        ));
+    }
+    if rds_iam.is_some() {
+        if !info.rds_iam_auth {
+            return Err(QueryError::with_code(
+                format!("{} connections can't use RDS IAM authentication", info.name),
+                error_codes::AUTH_ERROR,
+            ));
+        }
+        // The token is sent as a plain password, so it must not go out unencrypted
+        let ssl = SslMode::parse(ssl_mode)?;
+        if !ssl.uses_tls() || ssl.allows_fallback() {
+            return Err(QueryError::with_code(
+                "RDS IAM authentication needs the SSL mode \"required\" or stricter",
+                error_codes::SSL_ERROR,
+            ));
+        }
+    }
+    let conn: DbResult<Arc<dyn DatabaseConnection>> = match info.db_type {
+        "mariadb" => MariaDbConnection::new(
+            host,
+            port,
+            username,
+            password,
+            database,
+            ssl_mode,
+            ssl_ca_cert,
+            pool_size,
+            query_timeout,
+            rds_iam,
+        )
+        .await
+        .map(|conn| Arc::new(conn) as Arc<dyn DatabaseConnection>),
+        "postgresql" | "cockroachdb" => {
+            let flavor = if info.db_type == "cockroachdb" {
+                PostgresFlavor::Cockroach
+            } else {
+                PostgresFlavor::Postgres
+            };
+            PostgresConnection::new(
+                flavor,
+                host,
+                port,
+                username,
+                password,
+                database,
+                ssl_mode,
+                ssl_ca_cert,
+                pool_size,
+                query_timeout,
+                rds_iam,
+            )
+            .await
+            .map(|conn| Arc::new(conn) as Arc<dyn DatabaseConnection>)
+        }
+        "mssql" => MssqlConnection::new(
+            host,
+            port,
+            username,
+            password,
+            database,
+            ssl_mode,
+            query_timeout,
+        )
+        .await
+        .map(|conn| Arc::new(conn) as Arc<dyn DatabaseConnection>),
+        "oracle" => OracleConnection::new(
+            host,
+            port,
+            username,
+            password,
+            database,
+            ssl_mode,
+            query_timeout,
+        )
+        .await
+        .map(|conn| Arc::new(conn) as Arc<dyn DatabaseConnection>),
+        "clickhouse" => ClickHouseConnection::new(
+            host,
+            port,
+            username,
+            password,
+            database,
+            ssl_mode,
+            query_timeout,
+        )
+        .await
+        .map(|conn| Arc::new(conn) as Arc<dyn DatabaseConnection>),
+        "cassandra" => CassandraConnection::new(
+            host,
+            port,
+            username,
+            password,
+            database,
+            ssl_mode,
+            query_timeout,
+        )
+        .await
+        .map(|conn| Arc::new(conn) as Arc<dyn DatabaseConnection>),
+        "mongodb" => MongoDbConnection::new(
+            host,
+            port,
+            username,
+            password,
+            database,
+            ssl_mode,
+            query_timeout,
+        )
+        .await
+        .map(|conn| Arc::new(conn) as Arc<dyn DatabaseConnection>),
+        "redis" => RedisConnection::new(
+            host,
+            port,
+            username,
+            password,
+            database,
+            ssl_mode,
+            query_timeout,
+        )
+        .await
+        .map(|conn| Arc::new(conn) as Arc<dyn DatabaseConnection>),
+        "bigquery" => BigQueryConnection::new(username, password, database, query_timeout)
+            .await
+            .map(|conn| Arc::new(conn) as Arc<dyn DatabaseConnection>),
+        "duckdb" => DuckDbConnection::new(database, query_timeout)
+            .await
+            .map(|conn| Arc::new(conn) as Arc<dyn DatabaseConnection>),
+        "neon" | "planetscale" => {
+            let provider = if info.db_type == "neon" {
+                ServerlessProvider::Neon
+            } else {
+                ServerlessProvider::PlanetScale
+            };
+            ServerlessConnection::new(
+                provider,
+                host,
+                username,
+                password,
+                database,
+                ssl_mode,
+                query_timeout,
+            )
+            .await
+            .map(|conn| Arc::new(conn) as Arc<dyn DatabaseConnection>)
+        }
+        // A type driver_info knows but this match doesn't
+        other => Err(QueryError::with_code(
+            format!("No driver for database type '{}'", other),
             "INVALID_DB_TYPE",
         )),
-    }
+    };
+    // Drivers may echo connection parameters in their errors
+    conn.map_err(|e| e.without_secret(password))
 }
 
 #[cfg(test)]
@@ -71,6 +575,10 @@ mod tests {
                 "password",
                 "test",
                 "disabled",
+                None,
+                PoolSize::default(),
+                None,
+                None,
             )
             .await;
 
@@ -106,6 +614,10 @@ mod tests {
                 "password",
                 "test",
                 "disabled",
+                None,
+                PoolSize::default(),
+                None,
+                None,
             )
             .await;
 
@@ -140,6 +652,10 @@ mod tests {
                 "password",
                 "test",
                 "disabled",
+                None,
+                PoolSize::default(),
+                None,
+                None,
             )
             .await;
 
@@ -165,9 +681,114 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_driver_info() {
+        let info = driver_info("Postgres").unwrap();
+        assert_eq!(info.db_type, "postgresql");
+        assert_eq!(info.default_port, Some(5432));
+        assert!(info.features.schemas);
+
+        let info = driver_info("mysql").unwrap();
+        assert_eq!(info.db_type, "mariadb");
+        assert!(!info.features.schemas);
+
+        let info = driver_info("SqlServer").unwrap();
+        assert_eq!(info.db_type, "mssql");
+        assert_eq!(info.default_port, Some(1433));
+
+        let info = driver_info("neon").unwrap();
+        assert_eq!(info.default_port, None);
+        assert!(!info.ssl_modes.contains(&"disabled"));
+
+        assert_eq!(
+            driver_info("sqlite").unwrap_err().code.as_deref(),
+            Some("INVALID_DB_TYPE")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_ca_cert_needs_driver_support() {
+        assert!(driver_info("mysql").unwrap().custom_ca_cert);
+        assert!(!driver_info("mssql").unwrap().custom_ca_cert);
+
+        let result = create_connection(
+            "mssql",
+            "localhost",
+            1433,
+            "sa",
+            "password",
+            "master",
+            "verify-full",
+            Some("/etc/ssl/ca.pem"),
+            PoolSize::default(),
+            None,
+            None,
+        )
+        .await;
+        assert_eq!(
+            result.err().and_then(|e| e.code).as_deref(),
+            Some(error_codes::SSL_ERROR)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rds_iam_needs_driver_support_and_tls() {
+        let connect = |db_type: &'static str, ssl_mode: &'static str| {
+            create_connection(
+                db_type,
+                "mydb.abc123.us-east-1.rds.amazonaws.com",
+                5432,
+                "app",
+                "",
+                "app",
+                ssl_mode,
+                None,
+                PoolSize::default(),
+                None,
+                Some(RdsIamAuth::new(None, None)),
+            )
+        };
+        let code =
+            |result: DbResult<Arc<dyn DatabaseConnection>>| result.err().and_then(|e| e.code);
+
+        assert_eq!(
+            code(connect("mssql", "required").await).as_deref(),
+            Some(error_codes::AUTH_ERROR)
+        );
+        assert_eq!(
+            code(connect("postgresql", "preferred").await).as_deref(),
+            Some(error_codes::SSL_ERROR)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pool_size_needs_driver_support() {
+        assert!(driver_info("postgres").unwrap().configurable_pool_size);
+        assert!(!driver_info("duckdb").unwrap().configurable_pool_size);
+
+        let result = create_connection(
+            "duckdb",
+            "",
+            0,
+            "",
+            "",
+            ":memory:",
+            "disabled",
+            None,
+            PoolSize::new(None, Some(8)),
+            None,
+            None,
+        )
+        .await;
+        assert_eq!(
+            result.err().and_then(|e| e.code).as_deref(),
+            Some(error_codes::INVALID_POOL_SIZE)
+        );
+    }
+
     #[tokio::test]
     async fn test_invalid_db_type() {
-        let invalid_types = vec!["mongodb", "redis", "sqlite", "oracle", "mssql", ""];
+        let invalid_types = vec!["memcached", "sqlite", ""];
 
         for db_type in invalid_types {
             let result = create_connection(
@@ -178,6 +799,10 @@ mod tests {
                 "password",
                 "test",
                 "disabled",
+                None,
+                PoolSize::default(),
+                None,
+                None,
             )
             .await;
 
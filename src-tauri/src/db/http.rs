@@ -0,0 +1,227 @@
+//! HTTP client for drivers that reach their database through an HTTP API rather than its
+//! wire protocol, checking certificates as the connection's SSL mode asks.
+
+use super::connection::{error_codes, DbResult, QueryError, SslMode};
+use std::error::Error as _;
+use std::time::Duration;
+
+/// Largest response body that is read. Bigger ones are refused as they arrive rather than
+/// buffered whole.
+pub const MAX_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Where requests go: `https://host:port` when `tls` is set, `http://host:port` otherwise.
+#[derive(Debug, Clone)]
+pub struct HttpEndpoint {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+    /// Certificate checks when `tls` is set.
+    pub ssl_mode: SslMode,
+    client: reqwest::Client,
+}
+
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The body as JSON, or a `QUERY_ERROR` naming the status if it isn't.
+    pub fn json(&self) -> DbResult<serde_json::Value> {
+        serde_json::from_slice(&self.body).map_err(|_| {
+            QueryError::with_code(
+                format!(
+                    "Unexpected HTTP {} response: {}",
+                    self.status,
+                    String::from_utf8_lossy(&self.body).trim()
+                ),
+                error_codes::QUERY_ERROR,
+            )
+        })
+    }
+}
+
+impl HttpEndpoint {
+    /// # Errors
+    /// Returns `TLS_ERROR` if the TLS backend can't be set up.
+    pub fn new(host: &str, port: u16, tls: bool, ssl_mode: SslMode) -> DbResult<Self> {
+        let client = reqwest::Client::builder()
+            .tls_danger_accept_invalid_certs(!ssl_mode.verifies_certificate())
+            .tls_danger_accept_invalid_hostnames(!ssl_mode.verifies_hostname())
+            .build()
+            .map_err(|e| QueryError::with_code(describe(&e), error_codes::TLS_ERROR))?;
+        Ok(HttpEndpoint {
+            host: host.to_string(),
+            port,
+            tls,
+            ssl_mode,
+            client,
+        })
+    }
+
+    /// Sends a POST of `body` to `path` with the extra `headers`, waiting at most
+    /// `timeout` for the whole exchange.
+    pub async fn post(
+        &self,
+        path: &str,
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+        timeout: Duration,
+    ) -> DbResult<HttpResponse> {
+        self.request(reqwest::Method::POST, path, headers, body, timeout)
+            .await
+    }
+
+    /// Sends a GET of `path` with the extra `headers`.
+    pub async fn get(
+        &self,
+        path: &str,
+        headers: &[(&str, String)],
+        timeout: Duration,
+    ) -> DbResult<HttpResponse> {
+        self.request(reqwest::Method::GET, path, headers, Vec::new(), timeout)
+            .await
+    }
+
+    async fn request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+        timeout: Duration,
+    ) -> DbResult<HttpResponse> {
+        let mut request = self
+            .client
+            .request(method, self.url(path))
+            .timeout(timeout)
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        let mut response = request.send().await.map_err(request_error)?;
+        let status = response.status().as_u16();
+        if response
+            .content_length()
+            .is_some_and(|length| length > MAX_RESPONSE_BYTES as u64)
+        {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(request_error)? {
+            if body.len() + chunk.len() > MAX_RESPONSE_BYTES {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(HttpResponse { status, body })
+    }
+
+    fn url(&self, path: &str) -> String {
+        let scheme = if self.tls { "https" } else { "http" };
+        if self.host.contains(':') {
+            format!("{}://[{}]:{}{}", scheme, self.host, self.port, path)
+        } else {
+            format!("{}://{}:{}{}", scheme, self.host, self.port, path)
+        }
+    }
+}
+
+fn too_large() -> QueryError {
+    QueryError::with_code(
+        format!(
+            "The response is larger than {} MB; add a LIMIT to the query",
+            MAX_RESPONSE_BYTES / (1024 * 1024)
+        ),
+        error_codes::QUERY_ERROR,
+    )
+}
+
+/// Maps a failed request to `TIMEOUT_ERROR`, `TLS_ERROR` (so callers can fall back to plain
+/// HTTP) or `CONNECTION_ERROR`.
+fn request_error(e: reqwest::Error) -> QueryError {
+    if e.is_timeout() {
+        return QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR);
+    }
+    let mut source = e.source();
+    while let Some(cause) = source {
+        if cause.downcast_ref::<native_tls::Error>().is_some() {
+            return QueryError::with_code(cause.to_string(), error_codes::TLS_ERROR);
+        }
+        source = cause.source();
+    }
+    QueryError::with_code(describe(&e), error_codes::CONNECTION_ERROR)
+}
+
+/// The error with its causes, which `reqwest` leaves out of its own message.
+fn describe(e: &reqwest::Error) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves `response` to the first request on a local port.
+    async fn serve_once(response: &'static [u8]) -> HttpEndpoint {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request).await.unwrap();
+            stream.write_all(response).await.unwrap();
+        });
+        HttpEndpoint::new("127.0.0.1", port, false, SslMode::Disabled).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_post_reads_chunked_response() {
+        let endpoint = serve_once(
+            b"HTTP/1.1 400 Bad Request\r\nTransfer-Encoding: chunked\r\n\r\n\
+              4\r\n{\"a\"\r\n3;x=y\r\n:1}\r\n0\r\n\r\n",
+        )
+        .await;
+        let response = endpoint
+            .post("/", &[], b"{}".to_vec(), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 400);
+        assert!(!response.is_success());
+        assert_eq!(response.json().unwrap()["a"], 1);
+    }
+    #[tokio::test]
+    async fn test_oversized_response_is_refused() {
+        let endpoint = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 104857600\r\n\r\n{}").await;
+        let err = endpoint
+            .get("/", &[], Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code.as_deref(), Some(error_codes::QUERY_ERROR));
+        assert!(err.message.contains("larger than 64 MB"));
+    }
+
+    #[tokio::test]
+    async fn test_tls_to_plain_server_is_tls_error() {
+        let mut endpoint = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}").await;
+        endpoint.tls = true;
+        let err = endpoint
+            .get("/", &[], Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code.as_deref(), Some(error_codes::TLS_ERROR));
+    }
+}
@@ -0,0 +1,442 @@
+//! Heuristics for suggesting indexes from query plans.
+//!
+//! Drivers find full table scans in their EXPLAIN output; this module finds the columns a
+//! query filters or joins on, so they can be proposed as index candidates. Like `statement`,
+//! it only tokenizes SQL and does not parse it.
+
+use super::connection::{error_codes, DbResult, QueryError, TableColumn, TableRef};
+use super::statement;
+use serde::{Deserialize, Serialize};
+
+/// Tables estimated to have fewer rows than this are cheap to scan and not reported.
+pub const LARGE_TABLE_ROWS: u64 = 10_000;
+
+/// Most columns suggested for one index.
+const MAX_INDEX_COLUMNS: usize = 3;
+
+/// Longest index name PostgreSQL keeps (MariaDB/MySQL allow 64).
+const MAX_INDEX_NAME_LENGTH: usize = 63;
+
+/// Keywords that end a WHERE/ON/USING clause.
+const CLAUSE_END_KEYWORDS: &[&str] = &[
+    "SELECT",
+    "FROM",
+    "JOIN",
+    "INNER",
+    "LEFT",
+    "RIGHT",
+    "FULL",
+    "CROSS",
+    "NATURAL",
+    "GROUP",
+    "ORDER",
+    "HAVING",
+    "LIMIT",
+    "OFFSET",
+    "UNION",
+    "INTERSECT",
+    "EXCEPT",
+    "WINDOW",
+    "RETURNING",
+    "SET",
+    "VALUES",
+];
+
+/// Keywords that can follow a table in a FROM clause, so they are not taken as its alias.
+const NON_ALIAS_KEYWORDS: &[&str] = &[
+    "WHERE",
+    "ON",
+    "USING",
+    "JOIN",
+    "INNER",
+    "LEFT",
+    "RIGHT",
+    "FULL",
+    "CROSS",
+    "NATURAL",
+    "OUTER",
+    "GROUP",
+    "ORDER",
+    "HAVING",
+    "LIMIT",
+    "OFFSET",
+    "UNION",
+    "INTERSECT",
+    "EXCEPT",
+    "WINDOW",
+    "FOR",
+    "SET",
+    "RETURNING",
+    "FORCE",
+    "USE",
+    "IGNORE",
+    "STRAIGHT_JOIN",
+];
+
+/// A table read in full by a query, with a candidate index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexSuggestion {
+    pub table: TableRef,
+    /// Estimated number of rows in the table.
+    pub estimated_rows: u64,
+    /// Columns the query filters or joins the table on, in order of appearance; empty if
+    /// none were found.
+    pub columns: Vec<String>,
+    /// CREATE INDEX statement for `columns`, if there are any.
+    pub create_statement: Option<String>,
+}
+
+/// A column referenced in SQL, optionally qualified by a table name or alias.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnRef {
+    pub qualifier: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// Unquoted identifier or keyword.
+    Word(String),
+    /// Quoted identifier, without its quotes.
+    Quoted(String),
+    /// Any other character outside literals and comments.
+    Symbol(char),
+    /// String or numeric literal.
+    Literal,
+}
+
+impl Token {
+    fn identifier(&self) -> Option<&str> {
+        match self {
+            Token::Word(word) | Token::Quoted(word) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn is_keyword(&self, keywords: &[&str]) -> bool {
+        matches!(self, Token::Word(word) if keywords.contains(&word.to_ascii_uppercase().as_str()))
+    }
+}
+
+/// Fails unless `query` can be explained without running it (see `statement::is_explainable`).
+pub fn check_explainable(query: &str) -> DbResult<()> {
+    if statement::is_explainable(query) {
+        Ok(())
+    } else {
+        Err(QueryError::with_code(
+            "Only a single SELECT, INSERT, UPDATE or DELETE statement can be analyzed",
+            error_codes::QUERY_ERROR,
+        ))
+    }
+}
+
+/// Returns the columns referenced anywhere in `expression`, such as a condition from a plan.
+pub fn expression_columns(expression: &str) -> Vec<ColumnRef> {
+    column_refs(&tokenize(expression), |_| true)
+}
+
+/// Returns the columns referenced in the WHERE, ON and USING clauses of `sql`.
+pub fn condition_columns(sql: &str) -> Vec<ColumnRef> {
+    let tokens = tokenize(sql);
+    let mut in_condition = vec![false; tokens.len()];
+    let mut active = false;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_keyword(&["WHERE", "ON", "USING"]) {
+            active = true;
+        } else if token.is_keyword(CLAUSE_END_KEYWORDS) {
+            active = false;
+        }
+        in_condition[i] = active;
+    }
+    column_refs(&tokens, |i| in_condition[i])
+}
+
+/// Returns the tables named after FROM, JOIN and UPDATE in `sql` with their aliases (the
+/// table name when there is no alias).
+pub fn table_aliases(sql: &str) -> Vec<(String, TableRef)> {
+    let tokens = tokenize(sql);
+    let mut aliases = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if !tokens[i].is_keyword(&["FROM", "JOIN", "UPDATE"]) {
+            i += 1;
+            continue;
+        }
+        i += 1;
+        // Comma-separated tables continue the FROM clause
+        while let Some(first) = tokens.get(i).and_then(Token::identifier) {
+            let table = match (
+                tokens.get(i + 1),
+                tokens.get(i + 2).and_then(Token::identifier),
+            ) {
+                (Some(Token::Symbol('.')), Some(name)) => {
+                    i += 3;
+                    TableRef::new(Some(first.to_string()), name)
+                }
+                _ => {
+                    i += 1;
+                    TableRef::unqualified(first)
+                }
+            };
+            if tokens.get(i).is_some_and(|t| t.is_keyword(&["AS"])) {
+                i += 1;
+            }
+            let alias = match tokens.get(i) {
+                Some(token @ Token::Quoted(alias)) | Some(token @ Token::Word(alias))
+                    if !token.is_keyword(NON_ALIAS_KEYWORDS) =>
+                {
+                    i += 1;
+                    alias.clone()
+                }
+                _ => table.name.clone(),
+            };
+            aliases.push((alias, table));
+
+            if tokens.get(i) == Some(&Token::Symbol(',')) {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+    }
+    aliases
+}
+
+/// Picks the columns of a table that `refs` use, for a table known in the query by any of
+/// `names` (its name or alias). References without a qualifier are matched by column name.
+pub fn candidate_columns(
+    refs: &[ColumnRef],
+    names: &[&str],
+    columns: &[TableColumn],
+) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    for column_ref in refs {
+        let qualified_for_table = column_ref
+            .qualifier
+            .as_deref()
+            .is_none_or(|q| names.iter().any(|name| name.eq_ignore_ascii_case(q)));
+        if !qualified_for_table {
+            continue;
+        }
+        let Some(column) = columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(&column_ref.name))
+        else {
+            continue;
+        };
+        if !candidates.contains(&column.name) {
+            candidates.push(column.name.clone());
+        }
+        if candidates.len() == MAX_INDEX_COLUMNS {
+            break;
+        }
+    }
+    candidates
+}
+
+/// Name for an index on `columns` of `table`, shortened to the identifier length limit.
+pub fn index_name(table: &TableRef, columns: &[String]) -> String {
+    let mut name = format!("idx_{}_{}", table.name, columns.join("_"));
+    if name.len() > MAX_INDEX_NAME_LENGTH {
+        let mut end = MAX_INDEX_NAME_LENGTH;
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name.truncate(end);
+    }
+    name
+}
+
+/// Collects identifiers in token positions accepted by `include`, skipping function names
+/// and keeping `qualifier.column` pairs together.
+fn column_refs(tokens: &[Token], include: impl Fn(usize) -> bool) -> Vec<ColumnRef> {
+    let mut refs = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let Some(first) = tokens[i].identifier() else {
+            i += 1;
+            continue;
+        };
+        if i > 0 && tokens[i - 1] == Token::Symbol('.') {
+            // Trailing part of a longer name (schema.table.column)
+            i += 1;
+            continue;
+        }
+        let qualified = match (
+            tokens.get(i + 1),
+            tokens.get(i + 2).and_then(Token::identifier),
+        ) {
+            (Some(Token::Symbol('.')), Some(name)) => Some(name),
+            _ => None,
+        };
+        let (column_ref, next) = match qualified {
+            Some(name) => (
+                ColumnRef {
+                    qualifier: Some(first.to_string()),
+                    name: name.to_string(),
+                },
+                i + 3,
+            ),
+            None => (
+                ColumnRef {
+                    qualifier: None,
+                    name: first.to_string(),
+                },
+                i + 1,
+            ),
+        };
+        let is_call = tokens.get(next) == Some(&Token::Symbol('('));
+        if !is_call && include(i) {
+            refs.push(column_ref);
+        }
+        i = next;
+    }
+    refs
+}
+
+/// Splits SQL into identifiers, symbols and literals, dropping comments and whitespace.
+fn tokenize(sql: &str) -> Vec<Token> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' => {
+                i = skip_quoted(&chars, i, '\'').1;
+                tokens.push(Token::Literal);
+            }
+            '"' | '`' => {
+                let (text, next) = skip_quoted(&chars, i, c);
+                i = next;
+                tokens.push(Token::Quoted(text));
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Word(chars[start..i].iter().collect()));
+            }
+            c if c.is_ascii_digit() || c == '$' => {
+                // Numbers and $n parameters
+                i += 1;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+                    i += 1;
+                }
+                tokens.push(Token::Literal);
+            }
+            c if c.is_whitespace() => i += 1,
+            c => {
+                tokens.push(Token::Symbol(c));
+                i += 1;
+            }
+        }
+    }
+    tokens
+}
+
+/// Reads a quoted string or identifier starting at `start`, where doubled quotes stand for
+/// one. Returns its text and the index after the closing quote.
+fn skip_quoted(chars: &[char], start: usize, quote: char) -> (String, usize) {
+    let mut text = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        if chars[i] == quote {
+            if chars.get(i + 1) == Some(&quote) {
+                text.push(quote);
+                i += 2;
+                continue;
+            }
+            return (text, i + 1);
+        }
+        // Backslash escapes in MySQL strings
+        if chars[i] == '\\' && quote == '\'' {
+            i += 1;
+        }
+        if let Some(&c) = chars.get(i) {
+            text.push(c);
+        }
+        i += 1;
+    }
+    (text, i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str) -> TableColumn {
+        TableColumn {
+            name: name.to_string(),
+            data_type: "int".to_string(),
+            is_nullable: true,
+            is_primary_key: false,
+            column_default: None,
+            character_maximum_length: None,
+            numeric_precision: None,
+            is_identity: false,
+            identity_generation: None,
+            is_generated: false,
+            generation_expression: None,
+            generation_kind: None,
+            allowed_values: None,
+        }
+    }
+
+    #[test]
+    fn test_candidate_columns_come_from_filters_and_joins() {
+        let sql = "SELECT o.total, u.name FROM orders o JOIN app.users AS u ON u.id = o.user_id \
+                   WHERE o.status = 'open' AND lower(u.email) = 'a@b.c' ORDER BY o.created_at";
+
+        let aliases = table_aliases(sql);
+        assert_eq!(
+            aliases[0],
+            ("o".to_string(), TableRef::unqualified("orders"))
+        );
+        assert_eq!(
+            aliases[1],
+            (
+                "u".to_string(),
+                TableRef::new(Some("app".to_string()), "users")
+            )
+        );
+
+        let refs = condition_columns(sql);
+        let orders = [
+            column("id"),
+            column("user_id"),
+            column("status"),
+            column("created_at"),
+        ];
+        assert_eq!(
+            candidate_columns(&refs, &["o", "orders"], &orders),
+            vec!["user_id", "status"]
+        );
+        let users = [column("id"), column("email"), column("name")];
+        assert_eq!(
+            candidate_columns(&refs, &["u", "users"], &users),
+            vec!["id", "email"]
+        );
+
+        let plan_refs = expression_columns("((o.status)::text = 'open'::text)");
+        assert_eq!(
+            candidate_columns(&plan_refs, &["o"], &orders),
+            vec!["status"]
+        );
+    }
+}
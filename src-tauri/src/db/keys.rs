@@ -0,0 +1,66 @@
+//! Key browsing for key-value stores (Redis): keys are paged through with `SCAN`, as
+//! listing them all could block the server, and values are read and written whole.
+
+use serde::{Deserialize, Serialize};
+
+/// The type of a key's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyKind {
+    String,
+    List,
+    Set,
+    /// A sorted set, whose members have scores.
+    Zset,
+    Hash,
+    Stream,
+}
+
+impl KeyKind {
+    /// Reads the type `TYPE` reports; None for types of modules (JSON, time series, ...).
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "string" => Some(KeyKind::String),
+            "list" => Some(KeyKind::List),
+            "set" => Some(KeyKind::Set),
+            "zset" => Some(KeyKind::Zset),
+            "hash" => Some(KeyKind::Hash),
+            "stream" => Some(KeyKind::Stream),
+            _ => None,
+        }
+    }
+}
+
+/// A key found by `DatabaseConnection::scan_keys`.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyEntry {
+    pub key: String,
+    /// The type `TYPE` reports, which for module types is not a `KeyKind`.
+    pub kind: String,
+    /// Seconds until the key expires; None when it doesn't.
+    pub ttl: Option<i64>,
+}
+
+/// A page of keys. A page may be short or even empty while more keys remain.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyPage {
+    pub keys: Vec<KeyEntry>,
+    /// Cursor to pass for the next page; 0 once every key was seen.
+    pub cursor: u64,
+}
+
+/// A key's value, as read by `get_key` and written by `set_key`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyValue {
+    pub key: String,
+    pub kind: KeyKind,
+    /// Seconds until the key expires; None when it doesn't.
+    pub ttl: Option<i64>,
+    /// A string; an array for lists and sets; an array of `{"member", "score"}` objects for
+    /// sorted sets; an object for hashes; an array of `{"id", "fields"}` objects for streams.
+    pub value: serde_json::Value,
+    /// Whether only the first `MAX_QUERY_ROWS` items were read, in which case the value
+    /// can't be written back.
+    #[serde(default)]
+    pub truncated: bool,
+}
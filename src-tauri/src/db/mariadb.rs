@@ -1,32 +1,94 @@
+use super::aggregation::{self, AggregateRequest, BucketUnit, ChartData};
+use super::bulk_insert;
+use super::change_stream::{self, ChangeSink, RowChange};
+use super::column_metadata::{self, ColumnMetadata};
+use super::column_stats::{self, ColumnStats};
 use super::connection::{
-    error_codes, DatabaseConnection, DbResult, QueryError, QueryResult, TableColumn,
-    TableRelationship, DEFAULT_QUERY_TIMEOUT, MAX_QUERY_ROWS,
+    check_droppable_database, convert_rows, error_codes, read_ca_cert, ActiveTransaction,
+    ChunkedRows, DatabaseConnection, DatabaseSize, DbResult, HealthSnapshot, PoolSize, QueryError,
+    QueryResult, QueryStatistic, QueryTimer, QueryTiming, ResultCursor, RlsStatus, RowChunkSink,
+    SessionRoles, SslMode, TableBloat, TableColumn, TableOperation, TableOptions, TableRef,
+    TableRelationship, DEFAULT_QUERY_TIMEOUT, LIVENESS_TIMEOUT, MAX_QUERY_ROWS, SCRATCH_SCHEMA,
+    SERVER_TIMEOUT_GRACE,
 };
+use super::deadlocks::{self, DeadlockReport};
+use super::index_advisor::{self, IndexSuggestion};
+use super::rds_iam::RdsIamAuth;
+use super::routines::{
+    self, Argument, ParameterMode, RoutineKind, RoutineParameter, RoutineResult, RoutineSignature,
+};
+use super::sampling::{self, SampleMethod};
+use super::session_variables::{self, SessionVariable};
+use super::sql_dialect::SqlDialect;
+use super::text_search::{self, TextSearchRequest};
+use super::time_travel;
+use super::triggers::{TriggerEvent, TriggerLevel, TriggerSpec, TriggerTiming};
+use super::users::{self, DbUser, GrantTarget, PrivilegeGrant};
+use crate::redact::REDACTED;
 use async_trait::async_trait;
-use mysql_async::{prelude::*, Opts, OptsBuilder, Pool, PoolConstraints, PoolOpts, Value};
+use futures_util::StreamExt;
+use mysql_async::binlog::{
+    events::{Event, EventData},
+    row::BinlogRow,
+    value::BinlogValue,
+};
+use mysql_async::consts::{ColumnFlags, ColumnType};
+use mysql_async::{
+    prelude::*, BinlogStream, BinlogStreamRequest, Column, IsolationLevel, Opts, OptsBuilder,
+    Params, Pool, PoolConstraints, PoolOpts, TextProtocol, TxOpts, Value,
+};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio::time::timeout;
 use tracing::{debug, warn};
 
+/// Pooled connections kept open and the most opened at once, unless the connection sets
+/// its own pool size.
+const DEFAULT_POOL_MIN: usize = 1;
+const DEFAULT_POOL_MAX: usize = 5;
+
+/// For quoting that doesn't depend on the session's SQL mode. Literals that do take
+/// `quote_string` with the mode read by `no_backslash_escapes`.
+const DIALECT: SqlDialect = SqlDialect::MySql {
+    no_backslash_escapes: false,
+};
+
+/// SQL mode a dump is restored under, set at its top and reset at its end. Its literals are
+/// written for it (backslash escapes, whatever the exporting session's mode), and like
+/// mysqldump it keeps zeros in AUTO_INCREMENT columns.
+const DUMP_SQL_MODE: &str = "NO_AUTO_VALUE_ON_ZERO";
+
 /// MariaDB/MySQL database connection implementation.
 pub struct MariaDbConnection {
-    pool: Pool,
+    /// Replaced by `reconnect_if_broken` when its connections stop responding.
+    pool: std::sync::Mutex<Pool>,
     current_database: Arc<Mutex<String>>,
-    // Connection parameters stored for potential future reconnection
-    #[allow(dead_code)]
+    /// Role set via `set_role`. Pooled connections are reset when returned, so it is
+    /// applied to each one handed out by `get_conn`.
+    role: Mutex<Option<String>>,
+    /// Variables set via `set_session_variable`, applied like `role`.
+    session_variables: Mutex<Vec<(String, String)>>,
+    // Connection parameters stored for reconnection
     host: String,
-    #[allow(dead_code)]
     port: u16,
-    #[allow(dead_code)]
     username: String,
-    #[allow(dead_code)]
-    password: String,
-    #[allow(dead_code)]
+    /// Updated by `change_password` when the user changes their own password.
+    password: Mutex<String>,
     ssl_mode: String,
+    ssl_ca_cert: Option<String>,
+    pool_constraints: PoolConstraints,
+    /// Timeout for user statements.
+    query_timeout: Duration,
+    /// Server-side statement timeout applied like `role`, or `None` if the server has none.
+    timeout_assignment: Option<String>,
+    /// Logs in with RDS IAM tokens instead of `password`.
+    rds_iam: Option<RdsIamAuth>,
 }
 
 impl MariaDbConnection {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         host: &str,
         port: u16,
@@ -34,28 +96,60 @@ impl MariaDbConnection {
         password: &str,
         dbname: &str,
         ssl_mode: &str,
+        ssl_ca_cert: Option<&str>,
+        pool_size: PoolSize,
+        query_timeout: Option<Duration>,
+        rds_iam: Option<RdsIamAuth>,
     ) -> DbResult<Self> {
-        let pool = Self::create_pool(host, port, user, password, dbname, ssl_mode).await?;
+        let (min, max) = pool_size.resolve(DEFAULT_POOL_MIN, DEFAULT_POOL_MAX)?;
+        let query_timeout = query_timeout.unwrap_or(DEFAULT_QUERY_TIMEOUT);
+        let pool_constraints =
+            PoolConstraints::new(min, max).expect("pool size bounds are checked by resolve");
+        let login_password = match &rds_iam {
+            Some(iam) => iam.token(host, port, user)?,
+            None => password.to_string(),
+        };
+        let pool = Self::create_pool(
+            host,
+            port,
+            user,
+            &login_password,
+            dbname,
+            ssl_mode,
+            ssl_ca_cert,
+            pool_constraints,
+            rds_iam.is_some(),
+        )
+        .await?;
 
         // Verify connection works
-        let conn = pool.get_conn().await.map_err(|e| QueryError {
+        let mut conn = pool.get_conn().await.map_err(|e| QueryError {
             message: format!("Failed to connect: {}", e),
             code: Some(error_codes::CONNECTION_ERROR.to_string()),
             ..Default::default()
         })?;
+        let timeout_assignment = Self::server_timeout_assignment(&mut conn, query_timeout).await;
         drop(conn);
 
         Ok(MariaDbConnection {
-            pool,
+            pool: std::sync::Mutex::new(pool),
             current_database: Arc::new(Mutex::new(dbname.to_string())),
+            role: Mutex::new(None),
+            session_variables: Mutex::new(Vec::new()),
             host: host.to_string(),
             port,
             username: user.to_string(),
-            password: password.to_string(),
+            password: Mutex::new(password.to_string()),
             ssl_mode: ssl_mode.to_string(),
+            ssl_ca_cert: ssl_ca_cert.map(str::to_string),
+            pool_constraints,
+            query_timeout,
+            timeout_assignment,
+            rds_iam,
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn create_pool(
         host: &str,
         port: u16,
@@ -63,13 +157,33 @@ impl MariaDbConnection {
         password: &str,
         dbname: &str,
         ssl_mode: &str,
+        ssl_ca_cert: Option<&str>,
+        pool_constraints: PoolConstraints,
+        cleartext_password: bool,
     ) -> DbResult<Pool> {
+        let ssl_mode = SslMode::parse(ssl_mode)?;
+        let root_certs = match ssl_ca_cert {
+            Some(path) => {
+                let der = read_ca_cert(path)?.to_der().map_err(|e| {
+                    QueryError::with_code(
+                        format!("Invalid CA certificate {}: {}", path, e),
+                        error_codes::TLS_ERROR,
+                    )
+                })?;
+                vec![der.into()]
+            }
+            None => Vec::new(),
+        };
         let make_opts = |enable_ssl: bool| -> Opts {
-            let pool_opts =
-                PoolOpts::default().with_constraints(PoolConstraints::new(1, 5).unwrap());
+            let pool_opts = PoolOpts::default().with_constraints(pool_constraints);
 
             let ssl_opts = if enable_ssl {
-                Some(mysql_async::SslOpts::default().with_danger_accept_invalid_certs(true))
+                Some(
+                    mysql_async::SslOpts::default()
+                        .with_root_certs(root_certs.clone())
+                        .with_danger_accept_invalid_certs(!ssl_mode.verifies_certificate())
+                        .with_danger_skip_domain_validation(!ssl_mode.verifies_hostname()),
+                )
             } else {
                 None
             };
@@ -82,10 +196,12 @@ impl MariaDbConnection {
                 .db_name(Some(dbname.to_string()))
                 .pool_opts(pool_opts)
                 .ssl_opts(ssl_opts)
+                // RDS IAM tokens are sent with mysql_clear_password, over TLS
+                .enable_cleartext_plugin(cleartext_password)
                 .into()
         };
 
-        if ssl_mode == "required" || ssl_mode == "preferred" {
+        if ssl_mode.uses_tls() {
             let opts = make_opts(true);
             let pool = Pool::new(opts);
 
@@ -96,7 +212,7 @@ impl MariaDbConnection {
                     return Ok(pool);
                 }
                 Err(e) => {
-                    if ssl_mode == "required" {
+                    if !ssl_mode.allows_fallback() {
                         return Err(QueryError {
                             message: format!("SSL connection failed: {}", e),
                             code: Some(error_codes::SSL_ERROR.to_string()),
@@ -121,10 +237,100 @@ impl MariaDbConnection {
         Ok(pool)
     }
 
+    fn pool(&self) -> Pool {
+        self.pool.lock().unwrap().clone()
+    }
+
+    /// Returns the signature of the procedure or function named `routine`, if it exists.
+    async fn routine_signatures(
+        conn: &mut mysql_async::Conn,
+        routine: &TableRef,
+    ) -> DbResult<Vec<RoutineSignature>> {
+        let query = "SELECT r.ROUTINE_TYPE, p.PARAMETER_NAME, p.PARAMETER_MODE, p.DTD_IDENTIFIER
+                     FROM information_schema.ROUTINES r
+                     LEFT JOIN information_schema.PARAMETERS p
+                       ON p.SPECIFIC_SCHEMA = r.ROUTINE_SCHEMA
+                      AND p.SPECIFIC_NAME = r.SPECIFIC_NAME
+                      AND p.ORDINAL_POSITION > 0
+                     WHERE r.ROUTINE_SCHEMA = COALESCE(?, DATABASE()) AND r.ROUTINE_NAME = ?
+                     ORDER BY p.ORDINAL_POSITION";
+        let rows: Vec<(String, Option<String>, Option<String>, Option<String>)> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.exec(query, (routine.schema.as_deref(), routine.name.as_str())),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        let Some((routine_type, ..)) = rows.first() else {
+            return Ok(Vec::new());
+        };
+        let kind = if routine_type.eq_ignore_ascii_case("PROCEDURE") {
+            RoutineKind::Procedure
+        } else {
+            RoutineKind::Function
+        };
+        let parameters = rows
+            .iter()
+            .filter_map(|(_, name, mode, data_type)| {
+                Some(RoutineParameter {
+                    name: Some(name.clone()?),
+                    data_type: data_type.clone().unwrap_or_default(),
+                    // Function parameters have no mode; they are all IN
+                    mode: mode
+                        .as_deref()
+                        .map(ParameterMode::parse)
+                        .unwrap_or(ParameterMode::In),
+                    has_default: false,
+                })
+            })
+            .collect();
+        Ok(vec![RoutineSignature { kind, parameters }])
+    }
+
+    /// Replaces the pool with one that logs in with `password`, or a fresh RDS IAM token.
+    async fn replace_pool(&self, password: &str) -> DbResult<()> {
+        let password = match &self.rds_iam {
+            Some(iam) => iam.token(&self.host, self.port, &self.username)?,
+            None => password.to_string(),
+        };
+        // Queries pick the database with `USE` on every checkout, so a new pool loses no
+        // session state
+        let database = self.current_database.lock().await.clone();
+        let new_pool = Self::create_pool(
+            &self.host,
+            self.port,
+            &self.username,
+            &password,
+            &database,
+            &self.ssl_mode,
+            self.ssl_ca_cert.as_deref(),
+            self.pool_constraints,
+            self.rds_iam.is_some(),
+        )
+        .await?;
+        let pool = std::mem::replace(&mut *self.pool.lock().unwrap(), new_pool);
+        tokio::spawn(async move {
+            let _ = pool.disconnect().await;
+        });
+        Ok(())
+    }
+
     async fn get_conn(&self) -> DbResult<mysql_async::Conn> {
         let current_db = self.current_database.lock().await.clone();
 
-        let mut conn = self.pool.get_conn().await.map_err(|e| QueryError {
+        let conn = match self.pool().get_conn().await {
+            // The pool logs new connections in with the token it was created with, which
+            // expires after 15 minutes
+            Err(e) if self.rds_iam.is_some() => {
+                debug!("Renewing RDS IAM token after failed checkout: {}", e);
+                let password = self.password.lock().await.clone();
+                self.replace_pool(&password).await?;
+                self.pool().get_conn().await
+            }
+            conn => conn,
+        };
+        let mut conn = conn.map_err(|e| QueryError {
             message: e.to_string(),
             code: Some(error_codes::CONNECTION_ERROR.to_string()),
             ..Default::default()
@@ -138,22 +344,419 @@ impl MariaDbConnection {
             ..Default::default()
         })?;
 
+        if let Some(role) = self.role.lock().await.as_deref() {
+            Self::run_statement(&mut conn, &Self::set_role_statement(role)).await?;
+        }
+        // Session variables come after the timeout so they can override it
+        let variables = self.session_variables.lock().await;
+        let assignments: Vec<String> = self
+            .timeout_assignment
+            .iter()
+            .cloned()
+            .chain(
+                variables
+                    .iter()
+                    .map(|(name, value)| Self::variable_assignment(name, value)),
+            )
+            .collect();
+        drop(variables);
+        if !assignments.is_empty() {
+            let statement = format!("SET {}", assignments.join(", "));
+            Self::run_statement(&mut conn, &statement).await?;
+        }
+
         Ok(conn)
     }
 
     /// Escapes an identifier (table/column name) for safe use in SQL.
     #[inline]
     fn escape_identifier(name: &str) -> String {
-        name.replace('`', "``")
+        DIALECT.escape_identifier(name)
     }
 
-    /// Escapes a string value for safe use in SQL.
-    #[inline]
-    fn escape_string(value: &str) -> String {
-        value.replace('\'', "''").replace('\\', "\\\\")
+    /// Decodes the changes to `table` (qualified) in a binlog event.
+    fn binlog_changes(
+        stream: &BinlogStream,
+        event: &Event,
+        table: &TableRef,
+        columns: &[String],
+    ) -> DbResult<Vec<RowChange>> {
+        let error =
+            |e: std::io::Error| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR);
+        let Some(EventData::RowsEvent(rows)) = event.read_data().map_err(error)? else {
+            return Ok(Vec::new());
+        };
+        let Some(table_map) = stream.get_tme(rows.table_id()) else {
+            return Ok(Vec::new());
+        };
+        if Some(table_map.database_name().as_ref()) != table.schema.as_deref()
+            || table_map.table_name() != table.name.as_str()
+        {
+            return Ok(Vec::new());
+        }
+
+        let mut changes = Vec::new();
+        for row in rows.rows(table_map) {
+            let (before, after) = row.map_err(error)?;
+            let image =
+                |row: Option<BinlogRow>| row.map(|row| Self::binlog_row_to_json(columns, row));
+            changes.extend(RowChange::new(table.clone(), image(before), image(after)));
+        }
+        Ok(changes)
+    }
+
+    /// Converts a binlog row image to JSON, leaving out columns the image doesn't have.
+    fn binlog_row_to_json(columns: &[String], mut row: BinlogRow) -> serde_json::Value {
+        let mut row_map = serde_json::Map::with_capacity(columns.len());
+        for (i, column) in columns.iter().enumerate().take(row.len()) {
+            let value = match row.take(i) {
+                None => continue,
+                Some(BinlogValue::Value(value)) => Self::mysql_value_to_json(value),
+                Some(BinlogValue::Jsonb(value)) => {
+                    serde_json::Value::try_from(value).unwrap_or(serde_json::Value::Null)
+                }
+                // Partial JSON updates aren't decoded
+                Some(BinlogValue::JsonDiff(_)) => serde_json::Value::Null,
+            };
+            row_map.insert(column.clone(), value);
+        }
+        serde_json::Value::Object(row_map)
+    }
+
+    /// Quotes a table name, qualified by its schema (database) when it has one.
+    fn qualified_name(table: &TableRef) -> String {
+        DIALECT.qualified_name(table)
+    }
+
+    /// Quotes a string as a SQL literal for a session with or without
+    /// `NO_BACKSLASH_ESCAPES`.
+    fn quote_string(value: &str, no_backslash_escapes: bool) -> String {
+        SqlDialect::MySql {
+            no_backslash_escapes,
+        }
+        .quote_string(value)
+    }
+
+    /// Quotes raw column bytes as a SQL literal, see `SqlDialect::quote_bytes`.
+    fn quote_bytes(bytes: &[u8], no_backslash_escapes: bool) -> String {
+        SqlDialect::MySql {
+            no_backslash_escapes,
+        }
+        .quote_bytes(bytes)
+    }
+
+    /// Returns the type (`PROCEDURE` or `FUNCTION`) of `routine` and the statement that
+    /// creates it.
+    async fn routine_definition(
+        conn: &mut mysql_async::Conn,
+        routine: &TableRef,
+    ) -> DbResult<(String, String)> {
+        let not_found = || {
+            QueryError::with_code(
+                format!("Routine \"{}\" not found", routine),
+                error_codes::QUERY_ERROR,
+            )
+        };
+        let routine_type: Option<String> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.exec_first(
+                "SELECT ROUTINE_TYPE FROM information_schema.ROUTINES
+                 WHERE ROUTINE_SCHEMA = COALESCE(?, DATABASE()) AND ROUTINE_NAME = ?",
+                (routine.schema.as_deref(), routine.name.as_str()),
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        let routine_type = routine_type.ok_or_else(not_found)?;
+
+        let query = format!(
+            "SHOW CREATE {} {}",
+            routine_type,
+            Self::qualified_name(routine)
+        );
+        let row: Option<mysql_async::Row> = timeout(DEFAULT_QUERY_TIMEOUT, conn.query_first(query))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        // The source is NULL unless the user created the routine or may read mysql.proc
+        let definition = row
+            .and_then(|row| row.get::<Option<String>, _>(2).flatten())
+            .ok_or_else(|| {
+                QueryError::with_code(
+                    format!("Not allowed to read the source of \"{}\"", routine),
+                    error_codes::QUERY_ERROR,
+                )
+            })?;
+        Ok((routine_type, definition))
+    }
+
+    fn variable_assignment(name: &str, value: &str) -> String {
+        format!(
+            "SESSION {} = {}",
+            name,
+            session_variables::mysql_literal(value)
+        )
+    }
+
+    fn set_role_statement(role: &str) -> String {
+        format!("SET ROLE `{}`", Self::escape_identifier(role))
+    }
+
+    /// Reads the values of an `enum('a','b')` or `set(...)` column type.
+    pub(crate) fn enum_values(column_type: &str) -> Option<Vec<String>> {
+        let lower = column_type.to_ascii_lowercase();
+        let list = if lower.starts_with("enum(") {
+            &column_type[5..]
+        } else if lower.starts_with("set(") {
+            &column_type[4..]
+        } else {
+            return None;
+        };
+
+        let mut values = Vec::new();
+        let mut chars = list.chars().peekable();
+        loop {
+            if chars.next()? != '\'' {
+                return None;
+            }
+            let mut value = String::new();
+            loop {
+                match chars.next()? {
+                    '\'' if chars.peek() == Some(&'\'') => {
+                        chars.next();
+                        value.push('\'');
+                    }
+                    '\'' => break,
+                    '\\' => value.push(chars.next()?),
+                    c => value.push(c),
+                }
+            }
+            values.push(value);
+            match chars.next()? {
+                ',' => {}
+                ')' => return Some(values),
+                _ => return None,
+            }
+        }
+    }
+
+    async fn run_statement(conn: &mut mysql_async::Conn, statement: &str) -> DbResult<()> {
+        timeout(DEFAULT_QUERY_TIMEOUT, conn.query_drop(statement))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))
+    }
+
+    /// Quotes an account as `'name'@'host'`.
+    fn account_name(user: &DbUser, no_backslash_escapes: bool) -> String {
+        format!(
+            "{}@{}",
+            Self::quote_string(&user.name, no_backslash_escapes),
+            Self::quote_string(user.host(), no_backslash_escapes)
+        )
+    }
+
+    /// Runs a GRANT, or a REVOKE when `revoke` is set. Returns the statement.
+    async fn change_privileges(&self, grant: &PrivilegeGrant, revoke: bool) -> DbResult<String> {
+        let privileges = grant.privilege_list()?;
+        let target = match &grant.target {
+            GrantTarget::Database { name } | GrantTarget::Schema { name } => {
+                format!("`{}`.*", Self::escape_identifier(name))
+            }
+            GrantTarget::Table { table } => Self::qualified_name(table),
+        };
+        let mut conn = self.get_conn().await?;
+        let no_backslash_escapes = Self::no_backslash_escapes(&mut conn).await?;
+        let grantee = Self::account_name(&grant.user, no_backslash_escapes);
+        let statement = users::grant_statement(grant, &privileges, &target, &grantee, revoke);
+        Self::run_statement(&mut conn, &statement).await?;
+        Ok(statement)
+    }
+
+    /// Seconds the server lags behind its primary, or `None` if it is not a replica, the
+    /// lag is unknown or the user may not read the replication status.
+    async fn replication_lag(conn: &mut mysql_async::Conn) -> Option<f64> {
+        // SHOW SLAVE STATUS is gone from MySQL 8.4; older servers only know it
+        for query in ["SHOW REPLICA STATUS", "SHOW SLAVE STATUS"] {
+            match conn.query_first::<mysql_async::Row, _>(query).await {
+                Ok(status) => {
+                    return status.and_then(|row| {
+                        ["Seconds_Behind_Source", "Seconds_Behind_Master"]
+                            .iter()
+                            .find_map(|column| row.get::<Option<u64>, _>(*column).flatten())
+                            .map(|secs| secs as f64)
+                    });
+                }
+                Err(e) => debug!("{} failed: {}", query, e),
+            }
+        }
+        None
+    }
+
+    /// Returns the assignment that makes the server stop statements running longer than
+    /// `limit`: `max_statement_time` (seconds) on MariaDB, `max_execution_time`
+    /// (milliseconds, SELECT only) on MySQL. Returns `None` for servers too old to have
+    /// either, leaving only the client-side timeout.
+    async fn server_timeout_assignment(
+        conn: &mut mysql_async::Conn,
+        limit: Duration,
+    ) -> Option<String> {
+        let version: String = match conn.query_first("SELECT VERSION()").await {
+            Ok(version) => version.unwrap_or_default(),
+            Err(e) => {
+                warn!("Failed to read server version: {}", e);
+                return None;
+            }
+        };
+        let assignment = if version.contains("MariaDB") {
+            format!("SESSION max_statement_time = {}", limit.as_secs_f64())
+        } else {
+            format!("SESSION max_execution_time = {}", limit.as_millis())
+        };
+        match Self::run_statement(conn, &format!("SET {}", assignment)).await {
+            Ok(()) => Some(assignment),
+            Err(e) => {
+                warn!("Server-side statement timeout unavailable: {}", e.message);
+                None
+            }
+        }
+    }
+
+    /// How long the client waits for a user statement: a little longer than the server's
+    /// own timeout, so the server's error arrives first.
+    fn statement_deadline(&self) -> Duration {
+        self.query_timeout + SERVER_TIMEOUT_GRACE
+    }
+
+    /// Whether the session has `NO_BACKSLASH_ESCAPES` in its SQL mode.
+    async fn no_backslash_escapes(conn: &mut mysql_async::Conn) -> DbResult<bool> {
+        let sql_mode: String = conn
+            .query_first("SELECT @@SESSION.sql_mode")
+            .await
+            .map_err(|e| QueryError {
+                message: e.to_string(),
+                code: Some(error_codes::QUERY_ERROR.to_string()),
+                ..Default::default()
+            })?
+            .unwrap_or_default();
+        Ok(sql_mode
+            .split(',')
+            .any(|mode| mode.eq_ignore_ascii_case("NO_BACKSLASH_ESCAPES")))
     }
 
     #[inline]
+    fn row_to_json(columns: &[String], row: mysql_async::Row) -> serde_json::Value {
+        let mut row_map = serde_json::Map::with_capacity(columns.len());
+        for (i, col) in columns.iter().enumerate() {
+            let value: Value = row.get(i).unwrap_or(Value::NULL);
+            row_map.insert(col.clone(), Self::mysql_value_to_json(value));
+        }
+        serde_json::Value::Object(row_map)
+    }
+
+    /// Column metadata from a result set's column definitions.
+    fn column_metadata(columns: &[Column]) -> Vec<ColumnMetadata> {
+        columns
+            .iter()
+            .map(|column| {
+                let flags = column.flags();
+                let (precision, scale) = match column.column_type() {
+                    ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+                        let scale = u32::from(column.decimals());
+                        let precision = column_metadata::decimal_precision(
+                            column.column_length(),
+                            scale,
+                            flags.contains(ColumnFlags::UNSIGNED_FLAG),
+                        );
+                        (Some(precision), Some(scale))
+                    }
+                    _ => (None, None),
+                };
+                // Expressions have no original table
+                let table = column.org_table_str();
+                let (source_table, source_column) = if table.is_empty() {
+                    (None, None)
+                } else {
+                    (
+                        Some(TableRef::new(
+                            Some(column.schema_str().into_owned()),
+                            table.into_owned(),
+                        )),
+                        Some(column.org_name_str().into_owned()),
+                    )
+                };
+                ColumnMetadata {
+                    name: column.name_str().into_owned(),
+                    type_name: Self::type_name(column).to_string(),
+                    numeric: column.column_type().is_numeric_type(),
+                    nullable: Some(!flags.contains(ColumnFlags::NOT_NULL_FLAG)),
+                    source_table,
+                    source_column,
+                    precision,
+                    scale,
+                }
+            })
+            .collect()
+    }
+
+    /// Names a column's type the way `SHOW COLUMNS` would, without its length.
+    fn type_name(column: &Column) -> &'static str {
+        use ColumnType::*;
+        // The binary character set marks BLOB and BINARY columns
+        let binary = column.character_set() == 63;
+        let flags = column.flags();
+        match column.column_type() {
+            MYSQL_TYPE_DECIMAL | MYSQL_TYPE_NEWDECIMAL => "DECIMAL",
+            MYSQL_TYPE_TINY => "TINYINT",
+            MYSQL_TYPE_SHORT => "SMALLINT",
+            MYSQL_TYPE_INT24 => "MEDIUMINT",
+            MYSQL_TYPE_LONG => "INT",
+            MYSQL_TYPE_LONGLONG => "BIGINT",
+            MYSQL_TYPE_FLOAT => "FLOAT",
+            MYSQL_TYPE_DOUBLE => "DOUBLE",
+            MYSQL_TYPE_NULL => "NULL",
+            MYSQL_TYPE_TIMESTAMP | MYSQL_TYPE_TIMESTAMP2 => "TIMESTAMP",
+            MYSQL_TYPE_DATE | MYSQL_TYPE_NEWDATE => "DATE",
+            MYSQL_TYPE_TIME | MYSQL_TYPE_TIME2 => "TIME",
+            MYSQL_TYPE_DATETIME | MYSQL_TYPE_DATETIME2 => "DATETIME",
+            MYSQL_TYPE_YEAR => "YEAR",
+            MYSQL_TYPE_BIT => "BIT",
+            MYSQL_TYPE_JSON => "JSON",
+            MYSQL_TYPE_GEOMETRY => "GEOMETRY",
+            // ENUM and SET columns are sent as strings with a flag
+            _ if flags.contains(ColumnFlags::ENUM_FLAG) => "ENUM",
+            _ if flags.contains(ColumnFlags::SET_FLAG) => "SET",
+            MYSQL_TYPE_ENUM => "ENUM",
+            MYSQL_TYPE_SET => "SET",
+            MYSQL_TYPE_TINY_BLOB
+            | MYSQL_TYPE_MEDIUM_BLOB
+            | MYSQL_TYPE_LONG_BLOB
+            | MYSQL_TYPE_BLOB => {
+                if binary {
+                    "BLOB"
+                } else {
+                    "TEXT"
+                }
+            }
+            MYSQL_TYPE_VARCHAR | MYSQL_TYPE_VAR_STRING => {
+                if binary {
+                    "VARBINARY"
+                } else {
+                    "VARCHAR"
+                }
+            }
+            MYSQL_TYPE_STRING => {
+                if binary {
+                    "BINARY"
+                } else {
+                    "CHAR"
+                }
+            }
+            _ => "UNKNOWN",
+        }
+    }
+
     fn mysql_value_to_json(value: Value) -> serde_json::Value {
         match value {
             Value::NULL => serde_json::Value::Null,
@@ -178,14 +781,12 @@ impl MariaDbConnection {
         }
     }
 
+    /// Formats a value as a literal of a dump, which is restored under `DUMP_SQL_MODE`.
     #[inline]
     fn mysql_value_to_sql(value: Value) -> String {
         match value {
             Value::NULL => "NULL".to_string(),
-            Value::Bytes(b) => {
-                let s = String::from_utf8_lossy(&b);
-                format!("'{}'", Self::escape_string(&s))
-            }
+            Value::Bytes(b) => Self::quote_bytes(&b, false),
             Value::Int(i) => i.to_string(),
             Value::UInt(u) => u.to_string(),
             Value::Float(f) => f.to_string(),
@@ -200,7 +801,7 @@ impl MariaDbConnection {
     }
 
     fn format_insert_statement(
-        table_name: &str,
+        table: &TableRef,
         columns: &[String],
         rows: &[Vec<String>],
         data_mode: &str,
@@ -224,15 +825,153 @@ impl MariaDbConnection {
             .join(",\n  ");
 
         format!(
-            "{} INTO `{}` ({}) VALUES\n  {};\n",
+            "{} INTO {} ({}) VALUES\n  {};\n",
             statement_type,
-            Self::escape_identifier(table_name),
+            Self::qualified_name(table),
             column_list,
             values_list
         )
     }
 }
 
+/// Seconds the server waits for a cursor's client to read more rows before aborting
+/// (`net_write_timeout`, 60 by default), so a result can sit unread while it is browsed.
+const CURSOR_IDLE_TIMEOUT_SECS: u32 = 8 * 60 * 60;
+
+/// Asks a cursor task to skip `skip` rows, then read up to `count`.
+struct CursorRequest {
+    skip: usize,
+    count: usize,
+    reply: oneshot::Sender<DbResult<Vec<serde_json::Value>>>,
+}
+
+/// A result streamed from a dedicated connection and read forward on demand.
+///
+/// MySQL has no server-side cursors for ad-hoc text queries, so the rows are left unread on
+/// the wire and a task that owns the connection pulls them as they are requested.
+struct MariaDbCursor {
+    columns: Vec<String>,
+    /// Channel to the reading task and index of the next unread row; `None` once closed.
+    state: Mutex<Option<(mpsc::Sender<CursorRequest>, usize)>>,
+}
+
+impl MariaDbCursor {
+    /// Reads the result of `query` on `conn`, answering requests until the cursor is closed.
+    /// Opening fails if the query doesn't start returning rows within `deadline`.
+    async fn run(
+        mut conn: mysql_async::Conn,
+        query: String,
+        deadline: Duration,
+        opened: oneshot::Sender<DbResult<Vec<String>>>,
+        mut requests: mpsc::Receiver<CursorRequest>,
+    ) {
+        let mut result = match timeout(deadline, conn.query_iter(query)).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => {
+                let _ = opened.send(Err(QueryError::with_code(
+                    e.to_string(),
+                    error_codes::QUERY_ERROR,
+                )));
+                return;
+            }
+            Err(_) => {
+                let _ = opened.send(Err(QueryError::with_code(
+                    "Query timed out",
+                    error_codes::TIMEOUT_ERROR,
+                )));
+                return;
+            }
+        };
+        let columns: Vec<String> = result
+            .columns()
+            .map(|cols| cols.iter().map(|col| col.name_str().to_string()).collect())
+            .unwrap_or_default();
+
+        if opened.send(Ok(columns.clone())).is_ok() {
+            while let Some(request) = requests.recv().await {
+                let rows = Self::read_rows(&mut result, &columns, request.skip, request.count)
+                    .await
+                    .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR));
+                let _ = request.reply.send(rows);
+            }
+        }
+
+        // Quit without returning the connection to the pool, which would read the rest of
+        // the result first
+        drop(result);
+        if let Err(e) = conn.disconnect().await {
+            debug!("Failed to close cursor connection: {}", e);
+        }
+    }
+
+    async fn read_rows(
+        result: &mut mysql_async::QueryResult<'_, 'static, TextProtocol>,
+        columns: &[String],
+        skip: usize,
+        count: usize,
+    ) -> mysql_async::Result<Vec<serde_json::Value>> {
+        for _ in 0..skip {
+            if result.next().await?.is_none() {
+                return Ok(Vec::new());
+            }
+        }
+
+        let mut rows = Vec::with_capacity(count.min(MAX_QUERY_ROWS));
+        while rows.len() < count {
+            let row = match result.next().await? {
+                Some(row) => row,
+                None => break,
+            };
+            let mut row_map = serde_json::Map::with_capacity(columns.len());
+            for (i, col) in columns.iter().enumerate() {
+                let value: Value = row.get(i).unwrap_or(Value::NULL);
+                row_map.insert(col.clone(), MariaDbConnection::mysql_value_to_json(value));
+            }
+            rows.push(serde_json::Value::Object(row_map));
+        }
+        Ok(rows)
+    }
+}
+
+#[async_trait]
+impl ResultCursor for MariaDbCursor {
+    fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    async fn fetch(&self, offset: usize, count: usize) -> DbResult<Vec<serde_json::Value>> {
+        let closed = || QueryError::with_code("Cursor is closed", error_codes::QUERY_ERROR);
+        let mut state = self.state.lock().await;
+        let (requests, position) = state.as_mut().ok_or_else(closed)?;
+        if offset < *position {
+            return Err(QueryError::with_code(
+                "MySQL cursors can only move forward; reopen the cursor to go back",
+                error_codes::QUERY_ERROR,
+            ));
+        }
+
+        let (reply, response) = oneshot::channel();
+        requests
+            .send(CursorRequest {
+                skip: offset - *position,
+                count,
+                reply,
+            })
+            .await
+            .map_err(|_| closed())?;
+        let rows = response.await.map_err(|_| closed())??;
+
+        *position = offset + rows.len();
+        Ok(rows)
+    }
+
+    async fn close(&self) -> DbResult<()> {
+        // Dropping the sender ends the reading task
+        self.state.lock().await.take();
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl DatabaseConnection for MariaDbConnection {
     async fn test_connection(&self) -> DbResult<()> {
@@ -254,11 +993,29 @@ impl DatabaseConnection for MariaDbConnection {
         Ok(())
     }
 
+    async fn reconnect_if_broken(&self) -> DbResult<bool> {
+        let pool = self.pool();
+        let alive = match timeout(LIVENESS_TIMEOUT, pool.get_conn()).await {
+            Ok(Ok(mut conn)) => matches!(timeout(LIVENESS_TIMEOUT, conn.ping()).await, Ok(Ok(()))),
+            _ => false,
+        };
+        if alive {
+            return Ok(false);
+        }
+
+        let password = self.password.lock().await.clone();
+        self.replace_pool(&password).await?;
+
+        debug!("Re-established MariaDB connection pool");
+        Ok(false)
+    }
+
     async fn execute_query(&self, query: &str) -> DbResult<QueryResult> {
+        let mut timer = QueryTimer::start();
         let mut conn = self.get_conn().await?;
-        let start = std::time::Instant::now();
+        timer.acquired();
 
-        let result = timeout(DEFAULT_QUERY_TIMEOUT, conn.query_iter(query))
+        let result = timeout(self.statement_deadline(), conn.query_iter(query))
             .await
             .map_err(|_| QueryError {
                 message: "Query timed out".to_string(),
@@ -270,13 +1027,18 @@ impl DatabaseConnection for MariaDbConnection {
                 code: Some(error_codes::QUERY_ERROR.to_string()),
             ..Default::default()
             })?;
+        timer.executed();
 
         let columns: Vec<String> = result
             .columns()
             .map(|cols| cols.iter().map(|col| col.name_str().to_string()).collect())
             .unwrap_or_default();
+        let column_metadata = result
+            .columns()
+            .map(|cols| Self::column_metadata(&cols))
+            .unwrap_or_default();
 
-        let mut result_rows: Vec<serde_json::Value> = Vec::with_capacity(1000);
+        let mut raw_rows: Vec<mysql_async::Row> = Vec::with_capacity(1000);
         let mut row_count = 0;
         let mut truncated = false;
         let column_count = columns.len();
@@ -294,27 +1056,139 @@ impl DatabaseConnection for MariaDbConnection {
                 continue; // Count remaining rows but don't store them
             }
 
+            raw_rows.push(row);
+        }
+        timer.fetched();
+
+        let affected_rows = if column_count == 0 {
+            Some(result.affected_rows())
+        } else {
+            None
+        };
+        // Return the connection to the pool before converting
+        drop(result);
+        drop(conn);
+
+        let column_names = columns.clone();
+        let result_rows = convert_rows(raw_rows, move |row: &mysql_async::Row| {
             let mut row_map = serde_json::Map::with_capacity(column_count);
 
+            for (i, col) in column_names.iter().enumerate() {
+                let value: Value = row.get(i).unwrap_or(Value::NULL);
+                row_map.insert(col.clone(), Self::mysql_value_to_json(value));
+            }
+
+            serde_json::Value::Object(row_map)
+        })
+        .await?;
+        timer.converted();
+
+        Ok(QueryResult {
+            columns,
+            rows: result_rows,
+            row_count,
+            execution_time: timer.execution_time(),
+            truncated,
+            affected_rows,
+            timing: timer.timing(),
+            column_metadata,
+        })
+    }
+
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult> {
+        let mut timer = QueryTimer::start();
+        let mut conn = self.get_conn().await?;
+        timer.acquired();
+
+        let mut result = timeout(self.statement_deadline(), conn.query_iter(query))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        timer.executed();
+
+        let columns: Vec<String> = result
+            .columns()
+            .map(|cols| cols.iter().map(|col| col.name_str().to_string()).collect())
+            .unwrap_or_default();
+        let column_metadata = result
+            .columns()
+            .map(|cols| Self::column_metadata(&cols))
+            .unwrap_or_default();
+
+        let mut chunks = ChunkedRows::new(columns.clone(), chunk_size, on_chunk);
+        while let Some(row) = result
+            .next()
+            .await
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?
+        {
+            timer.fetched();
+            let mut row_map = serde_json::Map::with_capacity(columns.len());
             for (i, col) in columns.iter().enumerate() {
                 let value: Value = row.get(i).unwrap_or(Value::NULL);
                 row_map.insert(col.clone(), Self::mysql_value_to_json(value));
             }
 
-            result_rows.push(serde_json::Value::Object(row_map));
+            if !chunks.push(serde_json::Value::Object(row_map)) {
+                break;
+            }
+            timer.converted();
         }
+        timer.fetched();
 
-        let execution_time = start.elapsed().as_millis();
+        let affected_rows = if columns.is_empty() {
+            Some(result.affected_rows())
+        } else {
+            None
+        };
+        let (row_count, truncated) = chunks.finish();
 
         Ok(QueryResult {
             columns,
-            rows: result_rows,
+            rows: Vec::new(),
             row_count,
-            execution_time,
+            execution_time: timer.execution_time(),
             truncated,
+            affected_rows,
+            timing: timer.timing(),
+            column_metadata,
         })
     }
 
+    async fn open_cursor(&self, query: &str) -> DbResult<Box<dyn ResultCursor>> {
+        let mut conn = self.get_conn().await?;
+        let query_timeout = format!(
+            "SET SESSION net_write_timeout = {}",
+            CURSOR_IDLE_TIMEOUT_SECS
+        );
+        conn.query_drop(query_timeout)
+            .await
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        let (opened, columns) = oneshot::channel();
+        let (requests, receiver) = mpsc::channel(1);
+        tokio::spawn(MariaDbCursor::run(
+            conn,
+            query.to_string(),
+            self.statement_deadline(),
+            opened,
+            receiver,
+        ));
+        let columns = columns.await.map_err(|_| {
+            QueryError::with_code("Cursor task stopped", error_codes::QUERY_ERROR)
+        })??;
+
+        debug!("Opened MariaDB cursor");
+        Ok(Box::new(MariaDbCursor {
+            columns,
+            state: Mutex::new(Some((requests, 0))),
+        }))
+    }
+
     async fn list_tables(&self) -> DbResult<Vec<String>> {
         let mut conn = self.get_conn().await?;
 
@@ -347,7 +1221,7 @@ impl DatabaseConnection for MariaDbConnection {
     }
 
     async fn list_databases(&self) -> DbResult<Vec<String>> {
-        let mut conn = self.pool.get_conn().await.map_err(|e| QueryError {
+        let mut conn = self.pool().get_conn().await.map_err(|e| QueryError {
             message: e.to_string(),
             code: Some(error_codes::CONNECTION_ERROR.to_string()),
             ..Default::default()
@@ -383,7 +1257,7 @@ impl DatabaseConnection for MariaDbConnection {
 
     async fn change_database(&self, database_name: &str) -> DbResult<()> {
         // Verify the database exists by trying to use it
-        let mut conn = self.pool.get_conn().await.map_err(|e| QueryError {
+        let mut conn = self.pool().get_conn().await.map_err(|e| QueryError {
             message: e.to_string(),
             code: Some(error_codes::CONNECTION_ERROR.to_string()),
             ..Default::default()
@@ -404,24 +1278,32 @@ impl DatabaseConnection for MariaDbConnection {
         Ok(())
     }
 
+    async fn set_default_schema(&self, schema: &str) -> DbResult<()> {
+        // MariaDB/MySQL schemas are databases
+        self.change_database(schema).await
+    }
+
     async fn get_current_database(&self) -> DbResult<String> {
         let current_db = self.current_database.lock().await;
         Ok(current_db.clone())
     }
 
-    async fn get_table_columns(&self, table_name: &str) -> DbResult<Vec<TableColumn>> {
+    async fn get_table_columns(&self, table: &TableRef) -> DbResult<Vec<TableColumn>> {
         let mut conn = self.get_conn().await?;
 
-        // Get current database name
-        let db_name: String = conn
-            .query_first("SELECT DATABASE()")
-            .await
-            .map_err(|e| QueryError {
-                message: e.to_string(),
-                code: Some(error_codes::QUERY_ERROR.to_string()),
-            ..Default::default()
-            })?
-            .unwrap_or_default();
+        // Unqualified tables live in the current database
+        let db_name: String = match &table.schema {
+            Some(schema) => schema.clone(),
+            None => conn
+                .query_first("SELECT DATABASE()")
+                .await
+                .map_err(|e| QueryError {
+                    message: e.to_string(),
+                    code: Some(error_codes::QUERY_ERROR.to_string()),
+                    ..Default::default()
+                })?
+                .unwrap_or_default(),
+        };
 
         let query = "SELECT
                         c.COLUMN_NAME,
@@ -430,7 +1312,9 @@ impl DatabaseConnection for MariaDbConnection {
                         c.COLUMN_KEY,
                         c.COLUMN_DEFAULT,
                         c.CHARACTER_MAXIMUM_LENGTH,
-                        c.NUMERIC_PRECISION
+                        c.NUMERIC_PRECISION,
+                        c.EXTRA,
+                        c.GENERATION_EXPRESSION
                      FROM information_schema.COLUMNS c
                      WHERE c.TABLE_SCHEMA = ?
                         AND c.TABLE_NAME = ?
@@ -438,7 +1322,7 @@ impl DatabaseConnection for MariaDbConnection {
 
         let result = timeout(
             DEFAULT_QUERY_TIMEOUT,
-            conn.exec_iter(query, (&db_name, table_name)),
+            conn.exec_iter(query, (&db_name, &table.name)),
         )
         .await
         .map_err(|_| QueryError {
@@ -467,6 +1351,8 @@ impl DatabaseConnection for MariaDbConnection {
             let column_default: Value = row.get(4).unwrap_or(Value::NULL);
             let character_maximum_length: Value = row.get(5).unwrap_or(Value::NULL);
             let numeric_precision: Value = row.get(6).unwrap_or(Value::NULL);
+            let extra: Value = row.get(7).unwrap_or(Value::NULL);
+            let generation_expression: Value = row.get(8).unwrap_or(Value::NULL);
 
             // Helper to convert Value to String
             let value_to_string = |v: Value| -> String {
@@ -495,14 +1381,33 @@ impl DatabaseConnection for MariaDbConnection {
                 }
             };
 
+            // "VIRTUAL GENERATED" or "STORED GENERATED" ("PERSISTENT GENERATED" on older
+            // MariaDB); MySQL's "DEFAULT_GENERATED" marks expression defaults instead
+            let extra = value_to_string(extra).to_uppercase();
+            let generation_kind = if extra.contains("VIRTUAL GENERATED") {
+                Some("VIRTUAL".to_string())
+            } else if extra.contains("STORED GENERATED") || extra.contains("PERSISTENT GENERATED") {
+                Some("STORED".to_string())
+            } else {
+                None
+            };
+
+            let data_type = value_to_string(column_type);
             columns.push(TableColumn {
                 name: value_to_string(name),
-                data_type: value_to_string(column_type),
+                allowed_values: Self::enum_values(&data_type),
+                data_type,
                 is_nullable: value_to_string(nullable) == "YES",
                 is_primary_key: value_to_string(key) == "PRI",
                 column_default: value_to_option_string(column_default),
                 character_maximum_length: value_to_option_i64(character_maximum_length),
                 numeric_precision: value_to_option_i64(numeric_precision),
+                is_identity: extra.contains("AUTO_INCREMENT"),
+                identity_generation: None,
+                is_generated: generation_kind.is_some(),
+                generation_expression: value_to_option_string(generation_expression)
+                    .filter(|_| generation_kind.is_some()),
+                generation_kind,
             });
         }
 
@@ -523,8 +1428,10 @@ impl DatabaseConnection for MariaDbConnection {
             .unwrap_or_default();
 
         let query = "SELECT
+                        kcu.TABLE_SCHEMA,
                         kcu.TABLE_NAME,
                         kcu.COLUMN_NAME,
+                        kcu.REFERENCED_TABLE_SCHEMA,
                         kcu.REFERENCED_TABLE_NAME,
                         kcu.REFERENCED_COLUMN_NAME,
                         kcu.CONSTRAINT_NAME
@@ -554,15 +1461,19 @@ impl DatabaseConnection for MariaDbConnection {
             code: Some(error_codes::QUERY_ERROR.to_string()),
             ..Default::default()
         })? {
-            let from_table: String = row.get(0).unwrap_or_default();
-            let from_column: String = row.get(1).unwrap_or_default();
-            let to_table: String = row.get(2).unwrap_or_default();
-            let to_column: String = row.get(3).unwrap_or_default();
-            let constraint_name: String = row.get(4).unwrap_or_default();
+            let from_schema: Option<String> = row.get(0).unwrap_or_default();
+            let from_table: String = row.get(1).unwrap_or_default();
+            let from_column: String = row.get(2).unwrap_or_default();
+            let to_schema: Option<String> = row.get(3).unwrap_or_default();
+            let to_table: String = row.get(4).unwrap_or_default();
+            let to_column: String = row.get(5).unwrap_or_default();
+            let constraint_name: String = row.get(6).unwrap_or_default();
 
             relationships.push(TableRelationship {
+                from_schema,
                 from_table,
                 from_column,
+                to_schema,
                 to_table,
                 to_column,
                 constraint_name,
@@ -572,10 +1483,337 @@ impl DatabaseConnection for MariaDbConnection {
         Ok(relationships)
     }
 
-    async fn disconnect(&self) -> DbResult<()> {
-        self.pool.clone().disconnect().await.map_err(|e| QueryError {
-            message: e.to_string(),
-            code: Some(error_codes::CONNECTION_ERROR.to_string()),
+    async fn check_privileges(
+        &self,
+        table: &TableRef,
+        operation: TableOperation,
+    ) -> DbResult<bool> {
+        let mut conn = self.get_conn().await?;
+
+        // Grants are listed per 'user'@'host' grantee; database grants may use LIKE patterns
+        let query = "SELECT COUNT(*) FROM (
+                        SELECT GRANTEE, PRIVILEGE_TYPE
+                        FROM information_schema.USER_PRIVILEGES
+                        UNION ALL
+                        SELECT GRANTEE, PRIVILEGE_TYPE
+                        FROM information_schema.SCHEMA_PRIVILEGES
+                        WHERE ? LIKE TABLE_SCHEMA
+                        UNION ALL
+                        SELECT GRANTEE, PRIVILEGE_TYPE
+                        FROM information_schema.TABLE_PRIVILEGES
+                        WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ?
+                     ) p
+                     WHERE p.PRIVILEGE_TYPE = ?
+                        AND p.GRANTEE = CONCAT('''', SUBSTRING_INDEX(CURRENT_USER(), '@', 1),
+                            '''@''', SUBSTRING_INDEX(CURRENT_USER(), '@', -1), '''')";
+
+        let schema = match &table.schema {
+            Some(schema) => schema.clone(),
+            None => self.current_database.lock().await.clone(),
+        };
+        let grants: Option<u64> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.exec_first(
+                query,
+                (&schema, &schema, &table.name, operation.privilege()),
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        Ok(grants.unwrap_or(0) > 0)
+    }
+
+    async fn get_query_statistics(&self, limit: usize) -> DbResult<Vec<QueryStatistic>> {
+        let mut conn = self.get_conn().await?;
+
+        // The digest table exists but stays empty while performance_schema is off
+        let enabled: Option<bool> = conn
+            .query_first("SELECT @@performance_schema")
+            .await
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        if enabled != Some(true) {
+            return Err(QueryError::with_code(
+                "performance_schema is disabled on this server",
+                error_codes::QUERY_ERROR,
+            )
+            .with_hint("Set performance_schema = ON in the server configuration and restart it"));
+        }
+
+        // Timer columns are in picoseconds
+        let query = "SELECT SCHEMA_NAME, DIGEST_TEXT, COUNT_STAR,
+                        SUM_TIMER_WAIT / 1e9, AVG_TIMER_WAIT / 1e9,
+                        SUM_ROWS_SENT + SUM_ROWS_AFFECTED
+                     FROM performance_schema.events_statements_summary_by_digest
+                     WHERE DIGEST_TEXT IS NOT NULL
+                     ORDER BY SUM_TIMER_WAIT DESC
+                     LIMIT ?";
+
+        let rows: Vec<mysql_async::Row> =
+            timeout(DEFAULT_QUERY_TIMEOUT, conn.exec(query, (limit as u64,)))
+                .await
+                .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+                .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| QueryStatistic {
+                database: row.get(0).unwrap_or_default(),
+                query: row.get(1).unwrap_or_default(),
+                calls: row.get(2).unwrap_or_default(),
+                total_time_ms: row.get(3).unwrap_or_default(),
+                mean_time_ms: row.get(4).unwrap_or_default(),
+                rows: row.get(5).unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    async fn suggest_indexes(&self, query: &str) -> DbResult<Vec<IndexSuggestion>> {
+        index_advisor::check_explainable(query)?;
+        let mut conn = self.get_conn().await?;
+
+        // One row per table access; `table` is the alias the query uses
+        let plan: Vec<mysql_async::Row> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.query(format!("EXPLAIN {}", query)),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        drop(conn);
+
+        let aliases = index_advisor::table_aliases(query);
+        let refs = index_advisor::condition_columns(query);
+
+        let mut suggestions: Vec<IndexSuggestion> = Vec::new();
+        for row in &plan {
+            let access_type: Option<String> = row.get("type").flatten();
+            let alias: Option<String> = row.get("table").flatten();
+            let estimated_rows: Option<u64> = row.get("rows").flatten();
+            let (Some("ALL"), Some(alias), Some(estimated_rows)) =
+                (access_type.as_deref(), alias, estimated_rows)
+            else {
+                continue;
+            };
+            // Derived tables and subquery results (`<derived2>`) have no indexes to add
+            if estimated_rows < index_advisor::LARGE_TABLE_ROWS || alias.starts_with('<') {
+                continue;
+            }
+            let table = aliases
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&alias))
+                .map(|(_, table)| table.clone())
+                .unwrap_or_else(|| TableRef::unqualified(alias.as_str()));
+            if suggestions.iter().any(|s| s.table == table) {
+                continue;
+            }
+
+            let table_columns = self.get_table_columns(&table).await?;
+            let columns =
+                index_advisor::candidate_columns(&refs, &[&alias, &table.name], &table_columns);
+            let create_statement = (!columns.is_empty()).then(|| {
+                let column_list: Vec<String> = columns
+                    .iter()
+                    .map(|c| format!("`{}`", Self::escape_identifier(c)))
+                    .collect();
+                format!(
+                    "CREATE INDEX `{}` ON {} ({})",
+                    Self::escape_identifier(&index_advisor::index_name(&table, &columns)),
+                    Self::qualified_name(&table),
+                    column_list.join(", ")
+                )
+            });
+            suggestions.push(IndexSuggestion {
+                table,
+                estimated_rows,
+                columns,
+                create_statement,
+            });
+        }
+
+        Ok(suggestions)
+    }
+
+    async fn get_bloat_report(&self) -> DbResult<Vec<TableBloat>> {
+        let mut conn = self.get_conn().await?;
+
+        let query = "SELECT TABLE_SCHEMA, TABLE_NAME, COALESCE(TABLE_ROWS, 0),
+                        COALESCE(DATA_LENGTH, 0) + COALESCE(INDEX_LENGTH, 0),
+                        COALESCE(DATA_FREE, 0)
+                     FROM information_schema.TABLES
+                     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_TYPE = 'BASE TABLE'";
+
+        let rows: Vec<(String, String, u64, u64, u64)> =
+            timeout(DEFAULT_QUERY_TIMEOUT, conn.query(query))
+                .await
+                .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+                .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        let mut report: Vec<TableBloat> = rows
+            .into_iter()
+            .map(|(schema, name, live_rows, used_bytes, free_bytes)| {
+                let table = TableRef::new(Some(schema), name);
+                let mut bloat =
+                    TableBloat::new(table, live_rows, None, used_bytes + free_bytes, free_bytes);
+                if bloat.needs_maintenance() {
+                    bloat.recommendation = Some(format!(
+                        "OPTIMIZE TABLE {}",
+                        Self::qualified_name(&bloat.table)
+                    ));
+                }
+                bloat
+            })
+            .collect();
+        report.sort_by_key(|bloat| std::cmp::Reverse(bloat.wasted_bytes));
+
+        Ok(report)
+    }
+
+    async fn get_deadlocks(&self) -> DbResult<DeadlockReport> {
+        let mut conn = self.get_conn().await?;
+
+        // Columns are Type, Name and Status
+        let status: Option<(String, String, String)> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.query_first("SHOW ENGINE INNODB STATUS"),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        // The counter is an InnoDB metric that may be disabled or missing
+        let total_deadlocks: Option<u64> = conn
+            .query_first(
+                "SELECT COUNT FROM information_schema.INNODB_METRICS
+                 WHERE NAME = 'lock_deadlocks' AND STATUS = 'enabled'",
+            )
+            .await
+            .unwrap_or(None);
+
+        Ok(DeadlockReport {
+            total_deadlocks,
+            deadlocks: status
+                .and_then(|(_, _, status)| deadlocks::parse_innodb_status(&status))
+                .into_iter()
+                .collect(),
+            note: Some(
+                "InnoDB only keeps the latest deadlock. Enable innodb_print_all_deadlocks to \
+                 write every deadlock to the server's error log."
+                    .to_string(),
+            ),
+        })
+    }
+
+    async fn get_active_transactions(&self) -> DbResult<Vec<ActiveTransaction>> {
+        let mut conn = self.get_conn().await?;
+
+        // A session idle between statements of an open transaction shows as 'Sleep'
+        let query = "SELECT t.trx_mysql_thread_id, p.USER, p.HOST, p.DB,
+                        CASE WHEN p.COMMAND = 'Sleep' THEN 'idle in transaction'
+                             ELSE LOWER(t.trx_state) END,
+                        CAST(t.trx_started AS CHAR),
+                        GREATEST(TIMESTAMPDIFF(SECOND, t.trx_started, NOW()), 0),
+                        t.trx_query
+                     FROM information_schema.INNODB_TRX t
+                     LEFT JOIN information_schema.PROCESSLIST p
+                        ON p.ID = t.trx_mysql_thread_id
+                     WHERE t.trx_mysql_thread_id <> CONNECTION_ID()
+                     ORDER BY t.trx_started";
+
+        let rows: Vec<mysql_async::Row> = timeout(DEFAULT_QUERY_TIMEOUT, conn.query(query))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ActiveTransaction {
+                session_id: row.get(0).unwrap_or_default(),
+                user: row.get(1).unwrap_or_default(),
+                client: row.get(2).unwrap_or_default(),
+                database: row.get(3).unwrap_or_default(),
+                state: row
+                    .get::<Option<String>, _>(4)
+                    .flatten()
+                    .unwrap_or_else(|| "unknown".to_string()),
+                started_at: row.get(5).unwrap_or_default(),
+                age_secs: row.get(6).unwrap_or_default(),
+                statement: row.get(7).unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    async fn get_health_snapshot(&self) -> DbResult<HealthSnapshot> {
+        let mut conn = self.get_conn().await?;
+
+        let stats_query = "SELECT
+                (SELECT COUNT(*) FROM information_schema.PROCESSLIST),
+                (SELECT COUNT(*) FROM information_schema.PROCESSLIST WHERE COMMAND <> 'Sleep'),
+                @@max_connections";
+        let sizes_query = "SELECT TABLE_SCHEMA,
+                              SUM(COALESCE(DATA_LENGTH, 0) + COALESCE(INDEX_LENGTH, 0))
+                           FROM information_schema.TABLES
+                           GROUP BY TABLE_SCHEMA
+                           ORDER BY 2 DESC";
+
+        let (total_connections, active_connections, max_connections): (u64, u64, u64) =
+            timeout(DEFAULT_QUERY_TIMEOUT, conn.query_first(stats_query))
+                .await
+                .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+                .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?
+                .unwrap_or_default();
+        let sizes: Vec<(String, u64)> = timeout(DEFAULT_QUERY_TIMEOUT, conn.query(sizes_query))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        let buffer_pool: Vec<(String, u64)> = conn
+            .query(
+                "SHOW GLOBAL STATUS WHERE Variable_name IN
+                    ('Innodb_buffer_pool_read_requests', 'Innodb_buffer_pool_reads')",
+            )
+            .await
+            .unwrap_or_default();
+        let status = |name: &str| {
+            buffer_pool
+                .iter()
+                .find(|(variable, _)| variable.eq_ignore_ascii_case(name))
+                .map(|(_, value)| *value)
+        };
+        // Requests are logical reads; reads are the ones that had to go to disk
+        let cache_hit_ratio = match (
+            status("Innodb_buffer_pool_read_requests"),
+            status("Innodb_buffer_pool_reads"),
+        ) {
+            (Some(requests), Some(reads)) if requests > 0 => {
+                Some(1.0 - reads.min(requests) as f64 / requests as f64)
+            }
+            _ => None,
+        };
+
+        let replication_lag_secs = Self::replication_lag(&mut conn).await;
+        drop(conn);
+        let transactions = self.get_active_transactions().await?;
+
+        Ok(HealthSnapshot {
+            total_connections,
+            active_connections,
+            max_connections: Some(max_connections),
+            cache_hit_ratio,
+            replication_lag_secs,
+            longest_transaction: transactions.into_iter().next(),
+            database_sizes: sizes
+                .into_iter()
+                .map(|(name, bytes)| DatabaseSize { name, bytes })
+                .collect(),
+        })
+    }
+
+    async fn disconnect(&self) -> DbResult<()> {
+        self.pool().disconnect().await.map_err(|e| QueryError {
+            message: e.to_string(),
+            code: Some(error_codes::CONNECTION_ERROR.to_string()),
             ..Default::default()
         })?;
 
@@ -585,7 +1823,7 @@ impl DatabaseConnection for MariaDbConnection {
 
     async fn update_cell(
         &self,
-        table_name: &str,
+        table: &TableRef,
         column_name: &str,
         new_value: Option<&str>,
         primary_key_column: &str,
@@ -593,41 +1831,32 @@ impl DatabaseConnection for MariaDbConnection {
     ) -> DbResult<String> {
         let mut conn = self.get_conn().await?;
 
-        // Build the logged query with actual values for display purposes
-        let logged_query = match new_value {
-            Some(value) => {
-                format!(
-                    "UPDATE `{}` SET `{}` = '{}' WHERE `{}` = '{}'",
-                    Self::escape_identifier(table_name),
-                    Self::escape_identifier(column_name),
-                    Self::escape_string(value),
-                    Self::escape_identifier(primary_key_column),
-                    Self::escape_string(primary_key_value)
-                )
-            }
-            None => {
-                format!(
-                    "UPDATE `{}` SET `{}` = NULL WHERE `{}` = '{}'",
-                    Self::escape_identifier(table_name),
-                    Self::escape_identifier(column_name),
-                    Self::escape_identifier(primary_key_column),
-                    Self::escape_string(primary_key_value)
-                )
-            }
-        };
+        // Build the logged query with actual values for display purposes, quoted the way
+        // this session would parse them
+        let no_backslash_escapes = Self::no_backslash_escapes(&mut conn).await?;
+        let logged_query = format!(
+            "UPDATE {} SET `{}` = {} WHERE `{}` = {}",
+            Self::qualified_name(table),
+            Self::escape_identifier(column_name),
+            new_value
+                .map(|value| Self::quote_string(value, no_backslash_escapes))
+                .unwrap_or_else(|| "NULL".to_string()),
+            Self::escape_identifier(primary_key_column),
+            Self::quote_string(primary_key_value, no_backslash_escapes)
+        );
 
         // Handle NULL and non-NULL cases separately to avoid type serialization issues
         match new_value {
             Some(value) => {
                 let query = format!(
-                    "UPDATE `{}` SET `{}` = ? WHERE `{}` = ?",
-                    Self::escape_identifier(table_name),
+                    "UPDATE {} SET `{}` = ? WHERE `{}` = ?",
+                    Self::qualified_name(table),
                     Self::escape_identifier(column_name),
                     Self::escape_identifier(primary_key_column)
                 );
 
                 timeout(
-                    DEFAULT_QUERY_TIMEOUT,
+                    self.statement_deadline(),
                     conn.exec_drop(&query, (value, primary_key_value)),
                 )
                 .await
@@ -644,14 +1873,14 @@ impl DatabaseConnection for MariaDbConnection {
             }
             None => {
                 let query = format!(
-                    "UPDATE `{}` SET `{}` = NULL WHERE `{}` = ?",
-                    Self::escape_identifier(table_name),
+                    "UPDATE {} SET `{}` = NULL WHERE `{}` = ?",
+                    Self::qualified_name(table),
                     Self::escape_identifier(column_name),
                     Self::escape_identifier(primary_key_column)
                 );
 
                 timeout(
-                    DEFAULT_QUERY_TIMEOUT,
+                    self.statement_deadline(),
                     conn.exec_drop(&query, (primary_key_value,)),
                 )
                 .await
@@ -676,21 +1905,45 @@ impl DatabaseConnection for MariaDbConnection {
         include_drop: bool,
         include_create: bool,
         data_mode: &str,
-        selected_tables: &[String],
+        selected_tables: &[TableRef],
         max_insert_size: usize,
     ) -> DbResult<String> {
         let mut conn = self.get_conn().await?;
 
         let mut sql_content = String::with_capacity(1024 * 1024);
-
-        let tables_to_export = if selected_tables.is_empty() {
-            let result = conn.query_iter("SHOW TABLES").await.map_err(|e| QueryError {
+        sql_content.push_str(&format!(
+            "SET @OLD_SQL_MODE = @@SESSION.sql_mode, SESSION sql_mode = '{}';\n",
+            DUMP_SQL_MODE
+        ));
+
+        // Read every table from one snapshot so writes made while the export pages through
+        // the data don't leave the dump inconsistent (InnoDB; other engines aren't
+        // transactional). Dropping the transaction on an early return rolls it back.
+        let mut tx_opts = TxOpts::default();
+        tx_opts
+            .with_isolation_level(IsolationLevel::RepeatableRead)
+            .with_consistent_snapshot(true)
+            .with_readonly(true);
+        let mut transaction = conn
+            .start_transaction(tx_opts)
+            .await
+            .map_err(|e| QueryError {
                 message: e.to_string(),
                 code: Some(error_codes::QUERY_ERROR.to_string()),
-            ..Default::default()
+                ..Default::default()
             })?;
 
-            let mut tables: Vec<String> = Vec::new();
+        let tables_to_export = if selected_tables.is_empty() {
+            let result = transaction
+                .query_iter("SHOW TABLES")
+                .await
+                .map_err(|e| QueryError {
+                    message: e.to_string(),
+                    code: Some(error_codes::QUERY_ERROR.to_string()),
+                    ..Default::default()
+                })?;
+
+            let mut tables: Vec<TableRef> = Vec::new();
             let mut result = result;
             while let Some(row) = result.next().await.map_err(|e| QueryError {
                 message: e.to_string(),
@@ -698,36 +1951,31 @@ impl DatabaseConnection for MariaDbConnection {
             ..Default::default()
             })? {
                 let table_name: String = row.get(0).unwrap_or_default();
-                tables.push(table_name);
+                tables.push(TableRef::unqualified(table_name));
             }
             tables
         } else {
             selected_tables.to_vec()
         };
 
-        for table_name in tables_to_export {
-            sql_content.push_str(&format!("\n-- Table: {}\n", table_name));
+        for table in tables_to_export {
+            let qualified_table = Self::qualified_name(&table);
+            sql_content.push_str(&format!("\n-- Table: {}\n", table));
 
             if include_drop {
-                sql_content.push_str(&format!(
-                    "DROP TABLE IF EXISTS `{}`;\n",
-                    Self::escape_identifier(&table_name)
-                ));
+                sql_content.push_str(&format!("DROP TABLE IF EXISTS {};\n", qualified_table));
             }
 
             if include_create {
-                let create_query = format!(
-                    "SHOW CREATE TABLE `{}`",
-                    Self::escape_identifier(&table_name)
-                );
-                let create_result =
-                    conn.query_iter(create_query.as_str())
-                        .await
-                        .map_err(|e| QueryError {
-                            message: e.to_string(),
-                            code: Some(error_codes::QUERY_ERROR.to_string()),
-            ..Default::default()
-                        })?;
+                let create_query = format!("SHOW CREATE TABLE {}", qualified_table);
+                let create_result = transaction
+                    .query_iter(create_query.as_str())
+                    .await
+                    .map_err(|e| QueryError {
+                        message: e.to_string(),
+                        code: Some(error_codes::QUERY_ERROR.to_string()),
+                        ..Default::default()
+                    })?;
 
                 let mut create_result = create_result;
                 if let Some(row) = create_result.next().await.map_err(|e| QueryError {
@@ -736,7 +1984,18 @@ impl DatabaseConnection for MariaDbConnection {
             ..Default::default()
                 })? {
                     let create_statement: String = row.get(1).unwrap_or_default();
-                    sql_content.push_str(&create_statement);
+                    // SHOW CREATE TABLE never qualifies the name, so restore the schema
+                    // the rest of the dump uses for this table
+                    let unqualified =
+                        format!("CREATE TABLE `{}`", Self::escape_identifier(&table.name));
+                    match create_statement.strip_prefix(&unqualified) {
+                        Some(rest) if table.schema.is_some() => {
+                            sql_content.push_str("CREATE TABLE ");
+                            sql_content.push_str(&qualified_table);
+                            sql_content.push_str(rest);
+                        }
+                        _ => sql_content.push_str(&create_statement),
+                    }
                     sql_content.push_str(";\n\n");
                 }
             }
@@ -747,14 +2006,13 @@ impl DatabaseConnection for MariaDbConnection {
 
                 loop {
                     let data_query = format!(
-                        "SELECT * FROM `{}` LIMIT {} OFFSET {}",
-                        Self::escape_identifier(&table_name),
-                        BATCH_SIZE,
-                        offset
+                        "SELECT * FROM {} LIMIT {} OFFSET {}",
+                        qualified_table, BATCH_SIZE, offset
                     );
 
                     let data_result =
-                        conn.query_iter(data_query.as_str())
+                        transaction
+                            .query_iter(data_query.as_str())
                             .await
                             .map_err(|e| QueryError {
                                 message: e.to_string(),
@@ -788,7 +2046,7 @@ impl DatabaseConnection for MariaDbConnection {
 
                         if row_buffer.len() >= max_insert_size {
                             sql_content.push_str(&Self::format_insert_statement(
-                                &table_name,
+                                &table,
                                 &columns,
                                 &row_buffer,
                                 data_mode,
@@ -799,7 +2057,7 @@ impl DatabaseConnection for MariaDbConnection {
 
                     if !row_buffer.is_empty() {
                         sql_content.push_str(&Self::format_insert_statement(
-                            &table_name,
+                            &table,
                             &columns,
                             &row_buffer,
                             data_mode,
@@ -817,6 +2075,1043 @@ impl DatabaseConnection for MariaDbConnection {
             }
         }
 
+        transaction.commit().await.map_err(|e| QueryError {
+            message: e.to_string(),
+            code: Some(error_codes::QUERY_ERROR.to_string()),
+            ..Default::default()
+        })?;
+
+        sql_content.push_str("SET SESSION sql_mode = @OLD_SQL_MODE;\n");
         Ok(sql_content)
     }
+
+    async fn insert_rows(
+        &self,
+        table: &TableRef,
+        columns: &[String],
+        rows: &[Vec<Option<String>>],
+    ) -> DbResult<u64> {
+        let mut conn = self.get_conn().await?;
+        let column_list = columns
+            .iter()
+            .map(|name| format!("`{}`", Self::escape_identifier(name)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let row_placeholders = format!("({})", vec!["?"; columns.len()].join(", "));
+
+        // All or nothing for transactional engines; dropping the transaction on an early
+        // return rolls it back
+        let mut transaction = conn
+            .start_transaction(TxOpts::default())
+            .await
+            .map_err(|e| QueryError {
+                message: e.to_string(),
+                code: Some(error_codes::QUERY_ERROR.to_string()),
+                ..Default::default()
+            })?;
+
+        let mut inserted = 0;
+        for batch in rows.chunks(bulk_insert::batch_size(columns.len())) {
+            let query = format!(
+                "INSERT INTO {} ({}) VALUES {}",
+                Self::qualified_name(table),
+                column_list,
+                vec![row_placeholders.as_str(); batch.len()].join(", ")
+            );
+            // MariaDB converts the text values to the column types
+            let params: Vec<Value> = batch
+                .iter()
+                .flatten()
+                .map(|value| match value {
+                    Some(value) => Value::Bytes(value.clone().into_bytes()),
+                    None => Value::NULL,
+                })
+                .collect();
+
+            timeout(
+                self.statement_deadline(),
+                transaction.exec_drop(&query, Params::Positional(params)),
+            )
+            .await
+            .map_err(|_| QueryError {
+                message: "Insert timed out".to_string(),
+                code: Some(error_codes::TIMEOUT_ERROR.to_string()),
+                ..Default::default()
+            })?
+            .map_err(|e| QueryError {
+                message: e.to_string(),
+                code: Some(error_codes::QUERY_ERROR.to_string()),
+                ..Default::default()
+            })?;
+            inserted += transaction.affected_rows();
+        }
+
+        transaction.commit().await.map_err(|e| QueryError {
+            message: e.to_string(),
+            code: Some(error_codes::QUERY_ERROR.to_string()),
+            ..Default::default()
+        })?;
+        debug!("Inserted {} rows into {}", inserted, table);
+        Ok(inserted)
+    }
+
+    async fn call_routine(
+        &self,
+        routine: &TableRef,
+        args: &HashMap<String, Option<String>>,
+    ) -> DbResult<RoutineResult> {
+        let query_error =
+            |e: mysql_async::Error| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR);
+        let mut conn = self.get_conn().await?;
+        let signatures = Self::routine_signatures(&mut conn, routine).await?;
+        let (signature, arguments) = routines::resolve_call(routine, &signatures, args)?;
+
+        // OUT and INOUT parameters go through user variables, read back after the call
+        let mut params = Vec::new();
+        let mut placeholders = Vec::new();
+        let mut outputs = Vec::new();
+        for (i, (parameter, argument)) in signature.parameters.iter().zip(&arguments).enumerate() {
+            let value = |value: &Option<&str>| match value {
+                Some(value) => Value::Bytes(value.as_bytes().to_vec()),
+                None => Value::NULL,
+            };
+            match (parameter.mode, argument) {
+                (ParameterMode::In, Argument::Value(v)) => {
+                    params.push(value(v));
+                    placeholders.push("?".to_string());
+                }
+                (_, Argument::Value(_)) | (_, Argument::Output) => {
+                    let variable = format!("@_bloatsql_out_{}", i);
+                    let initial = match argument {
+                        Argument::Value(v) => value(v),
+                        _ => Value::NULL,
+                    };
+                    timeout(
+                        DEFAULT_QUERY_TIMEOUT,
+                        conn.exec_drop(format!("SET {} = ?", variable), (initial,)),
+                    )
+                    .await
+                    .map_err(|_| {
+                        QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR)
+                    })?
+                    .map_err(query_error)?;
+                    outputs.push((variable.clone(), routines::argument_key(parameter, i)));
+                    placeholders.push(variable);
+                }
+                (_, Argument::Omitted) => {}
+            }
+        }
+
+        let query = match signature.kind {
+            RoutineKind::Procedure => format!(
+                "CALL {}({})",
+                Self::qualified_name(routine),
+                placeholders.join(", ")
+            ),
+            RoutineKind::Function => format!(
+                "SELECT {}({}) AS `{}`",
+                Self::qualified_name(routine),
+                placeholders.join(", "),
+                Self::escape_identifier(&routine.name)
+            ),
+        };
+        debug!("Calling routine: {}", query);
+
+        // A procedure can return any number of result sets
+        let started = std::time::Instant::now();
+        let mut result_sets = Vec::new();
+        let mut result = timeout(
+            self.statement_deadline(),
+            conn.exec_iter(query.as_str(), Params::Positional(params)),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(query_error)?;
+        while !result.is_empty() {
+            let columns: Vec<String> = result
+                .columns()
+                .map(|cols| cols.iter().map(|col| col.name_str().to_string()).collect())
+                .unwrap_or_default();
+            let rows: Vec<mysql_async::Row> = result.collect().await.map_err(query_error)?;
+            if columns.is_empty() {
+                continue;
+            }
+            let rows: Vec<serde_json::Value> = rows
+                .into_iter()
+                .map(|row| Self::row_to_json(&columns, row))
+                .collect();
+            result_sets.push(QueryResult {
+                columns,
+                row_count: rows.len(),
+                rows,
+                execution_time: started.elapsed().as_millis(),
+                truncated: false,
+                affected_rows: None,
+                timing: QueryTiming::default(),
+                column_metadata: Vec::new(),
+            });
+        }
+        drop(result);
+
+        let mut output = serde_json::Map::new();
+        if !outputs.is_empty() {
+            let select = outputs
+                .iter()
+                .map(|(variable, name)| {
+                    format!("{} AS `{}`", variable, Self::escape_identifier(name))
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let names: Vec<String> = outputs.into_iter().map(|(_, name)| name).collect();
+            let row: Option<mysql_async::Row> = timeout(
+                DEFAULT_QUERY_TIMEOUT,
+                conn.query_first(format!("SELECT {}", select)),
+            )
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(query_error)?;
+            if let Some(serde_json::Value::Object(values)) =
+                row.map(|row| Self::row_to_json(&names, row))
+            {
+                output = values;
+            }
+        }
+
+        Ok(RoutineResult {
+            result_sets,
+            output,
+        })
+    }
+
+    async fn get_routine_definition(&self, routine: &TableRef) -> DbResult<String> {
+        let mut conn = self.get_conn().await?;
+        let (_, definition) = Self::routine_definition(&mut conn, routine).await?;
+        Ok(definition)
+    }
+
+    async fn save_routine_definition(
+        &self,
+        replaces: Option<&TableRef>,
+        source: &str,
+    ) -> DbResult<()> {
+        let statements = routines::split_script(source);
+        let mut conn = self.get_conn().await?;
+
+        // Routine DDL commits implicitly, so the previous definition is kept to restore
+        let previous = match replaces {
+            Some(routine) => {
+                let (routine_type, definition) =
+                    Self::routine_definition(&mut conn, routine).await?;
+                let drop = format!(
+                    "DROP {} IF EXISTS {}",
+                    routine_type,
+                    Self::qualified_name(routine)
+                );
+                Self::run_statement(&mut conn, &drop).await?;
+                Some((drop, definition))
+            }
+            None => None,
+        };
+
+        for statement in &statements {
+            let Err(mut e) = Self::run_statement(&mut conn, statement).await else {
+                continue;
+            };
+            if let Some((drop, definition)) = &previous {
+                let restored = match Self::run_statement(&mut conn, drop).await {
+                    Ok(()) => Self::run_statement(&mut conn, definition).await,
+                    Err(e) => Err(e),
+                };
+                match restored {
+                    Ok(()) => e
+                        .message
+                        .push_str(" (the previous definition was restored)"),
+                    Err(restore_error) => {
+                        warn!(
+                            "Failed to restore routine definition: {}",
+                            restore_error.message
+                        )
+                    }
+                }
+            }
+            return Err(e);
+        }
+        debug!("Saved routine definition ({} statements)", statements.len());
+        Ok(())
+    }
+
+    async fn get_view_definition(&self, view: &TableRef) -> DbResult<String> {
+        let mut conn = self.get_conn().await?;
+        let definition: Option<String> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.exec_first(
+                "SELECT VIEW_DEFINITION FROM information_schema.VIEWS
+                 WHERE TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ?",
+                (view.schema.as_deref(), view.name.as_str()),
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        definition.ok_or_else(|| {
+            QueryError::with_code(
+                format!("View \"{}\" not found", view),
+                error_codes::QUERY_ERROR,
+            )
+        })
+    }
+
+    async fn alter_view(&self, view: &TableRef, query: &str) -> DbResult<()> {
+        let mut conn = self.get_conn().await?;
+        Self::run_statement(&mut conn, &format!("EXPLAIN {}", query)).await?;
+        let statement = format!(
+            "CREATE OR REPLACE VIEW {} AS {}",
+            Self::qualified_name(view),
+            query
+        );
+        Self::run_statement(&mut conn, &statement).await?;
+        debug!("Redefined view {}", view);
+        Ok(())
+    }
+
+    async fn create_trigger(&self, spec: &TriggerSpec) -> DbResult<()> {
+        let ddl = self.trigger_ddl(spec)?;
+        let mut conn = self.get_conn().await?;
+        Self::run_statement(&mut conn, &ddl).await?;
+        debug!("Created trigger {} on {}", spec.name, spec.table);
+        Ok(())
+    }
+
+    fn trigger_ddl(&self, spec: &TriggerSpec) -> DbResult<String> {
+        spec.validate()?;
+        let unsupported = if spec.timing == TriggerTiming::InsteadOf {
+            Some("INSTEAD OF triggers")
+        } else if spec.level == TriggerLevel::Statement {
+            Some("Statement-level triggers")
+        } else if spec.events.contains(&TriggerEvent::Truncate) {
+            Some("TRUNCATE triggers")
+        } else if spec.events_clause().contains(" OR ") {
+            Some("Triggers on more than one event")
+        } else {
+            None
+        };
+        if let Some(feature) = unsupported {
+            return Err(QueryError::with_code(
+                format!("{} are not supported by MySQL", feature),
+                error_codes::QUERY_ERROR,
+            ));
+        }
+
+        // The body is wrapped in a block so it can hold several statements; MySQL has no
+        // WHEN clause, so a condition becomes an IF around it
+        let body = spec.body.trim().trim_end_matches(';');
+        let body = match &spec.when {
+            Some(condition) => format!(
+                "BEGIN\n  IF {} THEN\n    {};\n  END IF;\nEND",
+                condition, body
+            ),
+            None => format!("BEGIN\n  {};\nEND", body),
+        };
+        // Triggers live in their table's schema
+        let name = TableRef::new(spec.table.schema.clone(), spec.name.clone());
+        Ok(format!(
+            "CREATE TRIGGER {} {} {} ON {} FOR EACH ROW\n{}",
+            Self::qualified_name(&name),
+            spec.timing.keyword(),
+            spec.events_clause(),
+            Self::qualified_name(&spec.table),
+            body
+        ))
+    }
+
+    async fn drop_trigger(&self, name: &str, table: &TableRef) -> DbResult<String> {
+        let ddl = self.drop_trigger_ddl(name, table)?;
+        let mut conn = self.get_conn().await?;
+        Self::run_statement(&mut conn, &ddl).await?;
+        debug!("Dropped trigger {} on {}", name, table);
+        Ok(ddl)
+    }
+
+    fn drop_trigger_ddl(&self, name: &str, table: &TableRef) -> DbResult<String> {
+        // Trigger names are unique per schema, so there is no ON clause
+        let trigger = TableRef::new(table.schema.clone(), name.to_string());
+        Ok(format!("DROP TRIGGER {}", Self::qualified_name(&trigger)))
+    }
+
+    async fn reset_identity(&self, table: &TableRef, value: Option<i64>) -> DbResult<i64> {
+        let mut conn = self.get_conn().await?;
+        let column: Option<String> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.exec_first(
+                "SELECT COLUMN_NAME FROM information_schema.COLUMNS
+                 WHERE TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ?
+                   AND EXTRA LIKE '%auto_increment%'",
+                (table.schema.as_deref(), table.name.as_str()),
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        let Some(column) = column else {
+            return Err(QueryError::with_code(
+                format!("Table {} has no AUTO_INCREMENT column", table),
+                error_codes::QUERY_ERROR,
+            ));
+        };
+
+        let next = match value {
+            Some(value) => value,
+            None => {
+                let query = format!(
+                    "SELECT COALESCE(MAX(`{}`), 0) + 1 FROM {}",
+                    Self::escape_identifier(&column),
+                    Self::qualified_name(table)
+                );
+                let next: Option<i64> = timeout(DEFAULT_QUERY_TIMEOUT, conn.query_first(query))
+                    .await
+                    .map_err(|_| {
+                        QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR)
+                    })?
+                    .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+                next.unwrap_or(1)
+            }
+        };
+        // InnoDB raises a value below the current maximum to one past it
+        let statement = format!(
+            "ALTER TABLE {} AUTO_INCREMENT = {}",
+            Self::qualified_name(table),
+            next
+        );
+        Self::run_statement(&mut conn, &statement).await?;
+        debug!("Reset AUTO_INCREMENT of {} to {}", table, next);
+        Ok(next)
+    }
+
+    async fn get_roles(&self) -> DbResult<SessionRoles> {
+        let mut conn = self.get_conn().await?;
+        let available: Vec<String> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.query(
+                "SELECT DISTINCT ROLE_NAME FROM information_schema.APPLICABLE_ROLES
+                 ORDER BY ROLE_NAME",
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        Ok(SessionRoles {
+            user: self.username.clone(),
+            role: self.role.lock().await.clone(),
+            available,
+        })
+    }
+
+    async fn set_role(&self, role: Option<&str>) -> DbResult<()> {
+        if let Some(role) = role {
+            // Fails here rather than on every later statement if the role can't be used
+            let mut conn =
+                self.pool().get_conn().await.map_err(|e| {
+                    QueryError::with_code(e.to_string(), error_codes::CONNECTION_ERROR)
+                })?;
+            Self::run_statement(&mut conn, &Self::set_role_statement(role)).await?;
+        }
+        *self.role.lock().await = role.map(str::to_string);
+
+        debug!("Set role to {:?}", role);
+        Ok(())
+    }
+
+    async fn get_session_variables(&self, filter: Option<&str>) -> DbResult<Vec<SessionVariable>> {
+        let mut conn = self.get_conn().await?;
+        // SHOW doesn't take parameters; `_` and `%` in the filter match loosely
+        let pattern = DIALECT.escape_string(filter.unwrap_or_default());
+        let query = format!("SHOW SESSION VARIABLES LIKE '%{}%'", pattern);
+        let rows: Vec<(String, Option<String>)> = timeout(DEFAULT_QUERY_TIMEOUT, conn.query(query))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(name, value)| SessionVariable {
+                name,
+                value: value.unwrap_or_default(),
+                unit: None,
+                description: None,
+                settable: true,
+            })
+            .collect())
+    }
+
+    async fn set_session_variable(&self, name: &str, value: &str) -> DbResult<()> {
+        session_variables::check_name(name)?;
+        // Checked on a connection of its own first, so a rejected value isn't remembered
+        let mut conn = self.get_conn().await?;
+        let statement = format!("SET {}", Self::variable_assignment(name, value));
+        Self::run_statement(&mut conn, &statement).await?;
+        session_variables::remember(&mut *self.session_variables.lock().await, name, value);
+
+        debug!("Set session variable {} to {}", name, value);
+        Ok(())
+    }
+
+    async fn aggregate_table(&self, request: &AggregateRequest) -> DbResult<ChartData> {
+        let (query, params) = aggregation::build_query(
+            request,
+            &Self::qualified_name(&request.table),
+            &AggregateDialect,
+        )?;
+        debug!("Aggregating: {}", query);
+
+        let mut conn = self.get_conn().await?;
+        let params: Vec<Value> = params.into_iter().map(Value::from).collect();
+        let started = std::time::Instant::now();
+        let rows: Vec<mysql_async::Row> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.exec(query.as_str(), Params::Positional(params)),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        let columns: Vec<String> = rows
+            .first()
+            .map(|row| {
+                row.columns_ref()
+                    .iter()
+                    .map(|column| column.name_str().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let rows: Vec<serde_json::Value> = rows
+            .into_iter()
+            .map(|row| Self::row_to_json(&columns, row))
+            .collect();
+        let result = QueryResult {
+            columns,
+            row_count: rows.len(),
+            rows,
+            execution_time: started.elapsed().as_millis(),
+            truncated: false,
+            affected_rows: None,
+            timing: QueryTiming::default(),
+            column_metadata: Vec::new(),
+        };
+        Ok(aggregation::to_chart(request, &result))
+    }
+
+    async fn get_text_search_configs(&self) -> DbResult<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn build_text_search(&self, request: &TextSearchRequest) -> DbResult<String> {
+        request.validate()?;
+        let mut conn = self.get_conn().await?;
+        let no_backslash_escapes = Self::no_backslash_escapes(&mut conn).await?;
+        let columns: Vec<String> = request
+            .columns
+            .iter()
+            .map(|column| format!("`{}`", Self::escape_identifier(column)))
+            .collect();
+        Ok(text_search::match_query(
+            request,
+            &Self::qualified_name(&request.table),
+            &columns,
+            &Self::quote_string(&request.query, no_backslash_escapes),
+        ))
+    }
+
+    async fn get_rls_policies(&self, _table: &TableRef) -> DbResult<RlsStatus> {
+        Ok(RlsStatus::default())
+    }
+
+    async fn create_database(
+        &self,
+        name: &str,
+        encoding: Option<&str>,
+        owner: Option<&str>,
+    ) -> DbResult<String> {
+        if owner.is_some() {
+            return Err(QueryError::with_code(
+                "MySQL databases have no owner; grant privileges on the database instead",
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let mut conn = self.get_conn().await?;
+        let mut statement = format!("CREATE DATABASE `{}`", Self::escape_identifier(name));
+        if let Some(charset) = encoding {
+            let no_backslash_escapes = Self::no_backslash_escapes(&mut conn).await?;
+            statement.push_str(&format!(
+                " CHARACTER SET {}",
+                Self::quote_string(charset, no_backslash_escapes)
+            ));
+        }
+        Self::run_statement(&mut conn, &statement).await?;
+        debug!("Created database {}", name);
+        Ok(statement)
+    }
+
+    async fn drop_database(&self, name: &str) -> DbResult<String> {
+        let current = self.current_database.lock().await.clone();
+        let system_databases = ["mysql", "information_schema", "performance_schema", "sys"];
+        check_droppable_database(name, &current, &system_databases)?;
+
+        let mut conn = self.get_conn().await?;
+        let statement = format!("DROP DATABASE `{}`", Self::escape_identifier(name));
+        Self::run_statement(&mut conn, &statement).await?;
+        debug!("Dropped database {}", name);
+        Ok(statement)
+    }
+
+    async fn create_user(&self, user: &DbUser, password: Option<&str>) -> DbResult<String> {
+        user.validate()?;
+        let mut conn = self.get_conn().await?;
+        let no_backslash_escapes = Self::no_backslash_escapes(&mut conn).await?;
+        let create = |password: Option<&str>| {
+            let account = format!(
+                "CREATE USER {}",
+                Self::account_name(user, no_backslash_escapes)
+            );
+            match password {
+                Some(password) => format!(
+                    "{} IDENTIFIED BY {}",
+                    account,
+                    Self::quote_string(password, no_backslash_escapes)
+                ),
+                None => account,
+            }
+        };
+        Self::run_statement(&mut conn, &create(password)).await?;
+        debug!("Created user {}@{}", user.name, user.host());
+        Ok(create(password.map(|_| REDACTED)))
+    }
+
+    async fn drop_user(&self, user: &DbUser) -> DbResult<String> {
+        user.validate()?;
+        let mut conn = self.get_conn().await?;
+        let no_backslash_escapes = Self::no_backslash_escapes(&mut conn).await?;
+        let statement = format!(
+            "DROP USER {}",
+            Self::account_name(user, no_backslash_escapes)
+        );
+        Self::run_statement(&mut conn, &statement).await?;
+        debug!("Dropped user {}@{}", user.name, user.host());
+        Ok(statement)
+    }
+
+    async fn change_password(&self, user: &DbUser, password: &str) -> DbResult<String> {
+        user.validate()?;
+        let mut conn = self.get_conn().await?;
+        let no_backslash_escapes = Self::no_backslash_escapes(&mut conn).await?;
+        let is_own_user = user.name == self.username;
+        // The host the connection's account was matched by isn't known, the server's is
+        let account = if is_own_user && user.host.is_none() {
+            "CURRENT_USER()".to_string()
+        } else {
+            Self::account_name(user, no_backslash_escapes)
+        };
+        let alter = |password: &str| {
+            format!(
+                "ALTER USER {} IDENTIFIED BY {}",
+                account,
+                Self::quote_string(password, no_backslash_escapes)
+            )
+        };
+        Self::run_statement(&mut conn, &alter(password)).await?;
+        drop(conn);
+
+        // The pool logs new connections in with the password it was created with
+        if is_own_user {
+            *self.password.lock().await = password.to_string();
+            self.replace_pool(password).await?;
+        }
+        debug!("Changed password of {}@{}", user.name, user.host());
+        Ok(alter(REDACTED))
+    }
+
+    async fn grant_privileges(&self, grant: &PrivilegeGrant) -> DbResult<String> {
+        self.change_privileges(grant, false).await
+    }
+
+    async fn revoke_privileges(&self, grant: &PrivilegeGrant) -> DbResult<String> {
+        self.change_privileges(grant, true).await
+    }
+
+    async fn get_table_options(&self, table: &TableRef) -> DbResult<TableOptions> {
+        let mut conn = self.get_conn().await?;
+        let database = match &table.schema {
+            Some(schema) => schema.clone(),
+            None => self.current_database.lock().await.clone(),
+        };
+        type OptionsRow = (
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+        );
+        let row: Option<OptionsRow> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.exec_first(
+                "SELECT ENGINE, ROW_FORMAT, TABLE_COLLATION, CREATE_OPTIONS
+                 FROM information_schema.TABLES
+                 WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ?",
+                (&database, &table.name),
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        let Some((engine, row_format, collation, create_options)) = row else {
+            return Err(QueryError::with_code(
+                format!("Table '{}' not found", table.name),
+                error_codes::QUERY_ERROR,
+            ));
+        };
+
+        // InnoDB tablespaces are listed under different names by MySQL 8 and MariaDB, and
+        // not at all without the PROCESS privilege
+        let tablespace: Option<String> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.exec_first(
+                "SELECT s.NAME FROM information_schema.INNODB_TABLES t
+                 JOIN information_schema.INNODB_TABLESPACES s ON s.SPACE = t.SPACE
+                 WHERE t.NAME = ?",
+                (format!("{}/{}", database, table.name),),
+            ),
+        )
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .flatten();
+
+        let mut options = TableOptions {
+            engine,
+            row_format,
+            tablespace,
+            collation,
+            ..Default::default()
+        };
+        options.add_parameters(create_options.iter().flat_map(|o| o.split_whitespace()));
+        Ok(options)
+    }
+
+    async fn get_column_stats(&self, table: &TableRef, column: &str) -> DbResult<ColumnStats> {
+        let mut conn = self.get_conn().await?;
+        let name = Self::qualified_name(table);
+        let database = match &table.schema {
+            Some(schema) => schema.clone(),
+            None => self.current_database.lock().await.clone(),
+        };
+        let mut stats = ColumnStats::default();
+
+        // Only MySQL 8 keeps histograms, after ANALYZE TABLE ... UPDATE HISTOGRAM
+        let histogram: Option<Option<String>> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.exec_first(
+                "SELECT CAST(HISTOGRAM AS CHAR) FROM information_schema.COLUMN_STATISTICS
+                 WHERE SCHEMA_NAME = ? AND TABLE_NAME = ? AND COLUMN_NAME = ?",
+                (&database, &table.name, column),
+            ),
+        )
+        .await
+        .ok()
+        .and_then(Result::ok);
+        if let Some(Ok(histogram)) = histogram.flatten().map(|h| serde_json::from_str(&h)) {
+            stats.histogram_bounds = column_stats::mysql_histogram_bounds(&histogram);
+        }
+
+        let text = |value: Value| match Self::mysql_value_to_json(value) {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some(s),
+            other => Some(other.to_string()),
+        };
+        let column = format!("`{}`", Self::escape_identifier(column));
+        let counts: Option<(u64, u64, u64)> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.query_first(column_stats::summary_query(&name, &column)),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+
+        let range_query = column_stats::range_query(&name, &column, str::to_string);
+        let range: Option<(Value, Value)> =
+            timeout(DEFAULT_QUERY_TIMEOUT, conn.query_first(range_query))
+                .await
+                .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+                .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        let range = range.map_or((None, None), |(min, max)| (text(min), text(max)));
+
+        let most_common: Vec<(Value, u64)> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.query(column_stats::most_common_query(&name, &column)),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        let most_common = most_common
+            .into_iter()
+            .filter_map(|(value, count)| Some((text(value)?, count)))
+            .collect();
+
+        stats.add_sample(counts.unwrap_or_default(), range, most_common);
+        Ok(stats)
+    }
+
+    async fn build_sample_query(
+        &self,
+        table: &TableRef,
+        n: usize,
+        _method: SampleMethod,
+    ) -> DbResult<String> {
+        // MySQL has no TABLESAMPLE
+        Ok(sampling::random_query(
+            &Self::qualified_name(table),
+            "RAND()",
+            n,
+        ))
+    }
+
+    async fn supports_time_travel(&self, table: &TableRef) -> DbResult<bool> {
+        let mut conn = self.get_conn().await?;
+        let database = match &table.schema {
+            Some(schema) => schema.clone(),
+            None => self.current_database.lock().await.clone(),
+        };
+        let table_type: Option<String> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.exec_first(
+                "SELECT TABLE_TYPE FROM information_schema.TABLES
+                 WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ?",
+                (&database, &table.name),
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        match table_type {
+            // MySQL has no system-versioned tables
+            Some(table_type) => Ok(table_type == "SYSTEM VERSIONED"),
+            None => Err(QueryError::with_code(
+                format!("Table '{}' not found", table.name),
+                error_codes::QUERY_ERROR,
+            )),
+        }
+    }
+
+    fn as_of_query(&self, query: &str, as_of: chrono::NaiveDateTime) -> DbResult<String> {
+        Ok(time_travel::system_versioning_query(query, as_of))
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        DIALECT
+    }
+
+    async fn create_scratch_schema(&self) -> DbResult<()> {
+        let mut conn = self.get_conn().await?;
+        let statement = format!("CREATE DATABASE IF NOT EXISTS `{}`", SCRATCH_SCHEMA);
+        Self::run_statement(&mut conn, &statement).await
+    }
+
+    async fn drop_scratch_schema(&self) -> DbResult<()> {
+        let mut conn = self.get_conn().await?;
+        let statement = format!("DROP DATABASE IF EXISTS `{}`", SCRATCH_SCHEMA);
+        Self::run_statement(&mut conn, &statement).await?;
+        debug!("Dropped scratch database");
+        Ok(())
+    }
+
+    async fn materialize_query(
+        &self,
+        query: &str,
+        table: &str,
+        temporary: bool,
+    ) -> DbResult<TableRef> {
+        if temporary {
+            return Err(QueryError::with_code(
+                "Temporary tables are not kept between queries on MySQL; use the scratch database",
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let created = TableRef::new(Some(SCRATCH_SCHEMA.to_string()), table);
+        let mut conn = self.get_conn().await?;
+        Self::run_statement(
+            &mut conn,
+            &format!("CREATE DATABASE IF NOT EXISTS `{}`", SCRATCH_SCHEMA),
+        )
+        .await?;
+        let statement = format!(
+            "CREATE TABLE {} AS {}",
+            Self::qualified_name(&created),
+            query
+        );
+        Self::run_statement(&mut conn, &statement).await?;
+        debug!("Materialized query into {}", created);
+        Ok(created)
+    }
+
+    async fn tail_changes(&self, table: &TableRef, on_changes: ChangeSink<'_>) -> DbResult<()> {
+        let mut conn = self.get_conn().await?;
+        let table = TableRef {
+            schema: match &table.schema {
+                Some(schema) => Some(schema.clone()),
+                None => Some(self.current_database.lock().await.clone()),
+            },
+            name: table.name.clone(),
+        };
+        let database = table.schema.clone().unwrap_or_default();
+
+        // Row events only name their columns with binlog_row_metadata = FULL
+        let columns: Vec<String> = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            conn.exec(
+                "SELECT COLUMN_NAME FROM information_schema.COLUMNS
+                 WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ?
+                 ORDER BY ORDINAL_POSITION",
+                (&database, &table.name),
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        if columns.is_empty() {
+            return Err(QueryError::with_code(
+                format!("Table {} not found", table),
+                error_codes::QUERY_ERROR,
+            ));
+        }
+
+        // Start at the end of the current binlog; MySQL 8.4 renamed SHOW MASTER STATUS
+        let status: Option<mysql_async::Row> = match conn.query_first("SHOW MASTER STATUS").await {
+            Ok(status) => status,
+            Err(_) => conn
+                .query_first("SHOW BINARY LOG STATUS")
+                .await
+                .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?,
+        };
+        let Some((Some(file), Some(position))) =
+            status.map(|row| (row.get::<String, _>(0), row.get::<u64, _>(1)))
+        else {
+            return Err(QueryError::with_code(
+                "Binary logging is disabled on this server",
+                error_codes::QUERY_ERROR,
+            ));
+        };
+
+        // The stream registers as a replica, so it needs an id no real replica uses
+        let server_id = rand::random::<u32>() | 0x8000_0000;
+        let request = BinlogStreamRequest::new(server_id)
+            .with_filename(file.as_bytes())
+            .with_pos(position);
+        let mut stream = conn
+            .get_binlog_stream(request)
+            .await
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?;
+        debug!("Tailing changes to {} from {}:{}", table, file, position);
+
+        loop {
+            let event = match timeout(change_stream::POLL_INTERVAL, stream.next()).await {
+                Err(_) => {
+                    if on_changes(Vec::new()) {
+                        continue;
+                    }
+                    break;
+                }
+                Ok(None) => break,
+                Ok(Some(event)) => event
+                    .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))?,
+            };
+            let changes = Self::binlog_changes(&stream, &event, &table, &columns)?;
+            if !on_changes(changes) {
+                break;
+            }
+        }
+        stream
+            .close()
+            .await
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::QUERY_ERROR))
+    }
+}
+
+/// MySQL has no `date_trunc`, so buckets are formatted back into the start of the period.
+struct AggregateDialect;
+
+impl aggregation::Dialect for AggregateDialect {
+    fn quote(&self, column: &str) -> String {
+        format!("`{}`", MariaDbConnection::escape_identifier(column))
+    }
+
+    fn parameter(&self, _index: usize, _column: &str) -> String {
+        "?".to_string()
+    }
+
+    fn bucket(&self, column: &str, unit: BucketUnit) -> String {
+        let format = match unit {
+            BucketUnit::Minute => "%Y-%m-%d %H:%i:00",
+            BucketUnit::Hour => "%Y-%m-%d %H:00:00",
+            BucketUnit::Day => "%Y-%m-%d",
+            BucketUnit::Month => "%Y-%m-01",
+            BucketUnit::Year => "%Y-01-01",
+            BucketUnit::Week => {
+                return format!("DATE_SUB(DATE({0}), INTERVAL WEEKDAY({0}) DAY)", column)
+            }
+            BucketUnit::Quarter => {
+                return format!(
+                    "MAKEDATE(YEAR({0}), 1) + INTERVAL (QUARTER({0}) - 1) QUARTER",
+                    column
+                )
+            }
+        };
+        format!("DATE_FORMAT({}, '{}')", column, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_literals_follow_sql_mode() {
+        let value = "it's a\\b\n\0\x1a\"";
+        assert_eq!(
+            MariaDbConnection::quote_string(value, false),
+            "'it\\'s a\\\\b\\n\\0\\Z\\\"'"
+        );
+        assert_eq!(
+            MariaDbConnection::quote_string(value, true),
+            "'it''s a\\b\n\0\x1a\"'"
+        );
+        assert_eq!(
+            MariaDbConnection::quote_bytes(&[0xff, 0x00], false),
+            "X'FF00'"
+        );
+    }
+
+    #[test]
+    fn test_enum_values() {
+        let values = MariaDbConnection::enum_values("enum('small','it''s','a,b')").unwrap();
+        assert_eq!(values, ["small", "it's", "a,b"]);
+        assert_eq!(MariaDbConnection::enum_values("varchar(20)"), None);
+
+        let column = TableColumn {
+            name: "tags".to_string(),
+            data_type: "set('red','green')".to_string(),
+            is_nullable: true,
+            is_primary_key: false,
+            column_default: None,
+            character_maximum_length: None,
+            numeric_precision: None,
+            is_identity: false,
+            identity_generation: None,
+            is_generated: false,
+            generation_expression: None,
+            generation_kind: None,
+            allowed_values: MariaDbConnection::enum_values("set('red','green')"),
+        };
+        assert!(column.check_allowed_value("Red,green").is_ok());
+        assert!(column.check_allowed_value("").is_ok());
+        assert!(column.check_allowed_value("red,blue").is_err());
+    }
 }
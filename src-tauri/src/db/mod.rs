@@ -1,7 +1,56 @@
+pub mod aggregation;
+pub mod bigquery;
+pub mod bulk_insert;
+pub mod cassandra;
+pub mod change_stream;
+pub mod clickhouse;
+pub mod column_metadata;
+pub mod column_stats;
 pub mod connection;
+pub mod deadlocks;
+pub mod diagnostics;
+pub mod duckdb;
 pub mod factory;
+pub mod http;
+pub mod index_advisor;
+pub mod keys;
 pub mod mariadb;
+pub mod mongodb;
+pub mod mssql;
+pub mod oracle;
 pub mod postgresql;
+pub mod rds_iam;
+pub mod redis;
+pub mod routines;
+pub mod sampling;
+pub mod serverless;
+pub mod session_variables;
+pub mod sql_dialect;
+pub mod statement;
+pub mod text_search;
+pub mod time_travel;
+pub mod triggers;
+pub mod users;
 
-pub use connection::{DatabaseConnection, QueryResult, TableColumn, TableRelationship};
-pub use factory::create_connection;
+pub use aggregation::{AggregateRequest, ChartData};
+pub use bulk_insert::PasteError;
+pub use change_stream::RowChange;
+pub use column_metadata::ColumnMetadata;
+pub use column_stats::ColumnStats;
+pub use connection::{
+    ActiveTransaction, DatabaseConnection, DbResult, HealthSnapshot, PoolSize, QueryResult,
+    QueryStatistic, QueryTiming, ResultCursor, RlsStatus, RowChunk, SessionRoles, SslMode,
+    TableBloat, TableColumn, TableOperation, TableOptions, TableRef, TableRelationship,
+};
+pub use deadlocks::DeadlockReport;
+pub use diagnostics::ConnectionDiagnosis;
+pub use factory::{create_connection, driver_info, DriverInfo};
+pub use index_advisor::IndexSuggestion;
+pub use keys::{KeyPage, KeyValue};
+pub use rds_iam::RdsIamAuth;
+pub use routines::RoutineResult;
+pub use sampling::SampleMethod;
+pub use session_variables::SessionVariable;
+pub use text_search::TextSearchRequest;
+pub use triggers::TriggerSpec;
+pub use users::{DbUser, PrivilegeGrant};
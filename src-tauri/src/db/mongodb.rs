@@ -0,0 +1,850 @@
+//! MongoDB, with the official `mongodb` driver. The server's databases are the connection's
+//! databases and their collections its tables; documents come back as rows with a column
+//! for every top-level field.
+//!
+//! The query editor takes a `find` or `aggregate` call in the shell's syntax, e.g.
+//! `db.users.find({"age": {"$gt": 30}})`, with extended JSON arguments. Browsing a
+//! collection sends `SELECT * FROM "users"`, which reads it like `find({})`. Cells are
+//! edited with `$set` on the document's `_id`.
+
+use super::column_metadata::ColumnMetadata;
+use super::connection::{
+    check_droppable_database, error_codes, unsupported, ChunkedRows, DatabaseConnection, DbResult,
+    QueryError, QueryResult, QueryTimer, RowChunkSink, SslMode, TableColumn, TableOperation,
+    TableRef, TableRelationship, DEFAULT_QUERY_TIMEOUT, MAX_QUERY_ROWS,
+};
+use super::sampling::{self, SampleMethod};
+use super::sql_dialect::SqlDialect;
+use super::statement;
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::DateTime;
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use mongodb::error::ErrorKind;
+use mongodb::options::{ClientOptions, Credential, ServerAddress, Tls, TlsOptions};
+use mongodb::{Client, Collection};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::debug;
+
+const DIALECT: SqlDialect = SqlDialect::MongoDb;
+
+const SYSTEM_DATABASES: [&str; 3] = ["admin", "config", "local"];
+
+/// Documents read to find a collection's fields.
+const SCHEMA_SAMPLE_SIZE: i64 = 100;
+
+/// MaxTimeMSExpired, returned when a command outlives its `maxTimeMS`.
+const MAX_TIME_EXPIRED: i32 = 50;
+
+const QUERY_USAGE: &str = "MongoDB queries take the form db.<collection>.find(<filter>) or \
+                           db.<collection>.aggregate(<pipeline>), with extended JSON arguments";
+
+fn query_error(message: impl std::fmt::Display) -> QueryError {
+    QueryError::with_code(message.to_string(), error_codes::QUERY_ERROR)
+}
+
+/// Keeps the server's own message for failed commands, and gives timeouts and lost
+/// connections their own error codes.
+fn mongo_error(err: mongodb::error::Error) -> QueryError {
+    match err.kind.as_ref() {
+        ErrorKind::Command(command) if command.code == MAX_TIME_EXPIRED => {
+            QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR)
+        }
+        ErrorKind::Command(command) => query_error(&command.message),
+        ErrorKind::Authentication { .. }
+        | ErrorKind::ServerSelection { .. }
+        | ErrorKind::DnsResolve { .. }
+        | ErrorKind::Io(_)
+        | ErrorKind::ConnectionPoolCleared { .. } => {
+            QueryError::with_code(err.to_string(), error_codes::CONNECTION_ERROR)
+        }
+        ErrorKind::InvalidTlsConfig { .. } => {
+            QueryError::with_code(err.to_string(), error_codes::TLS_ERROR)
+        }
+        _ => query_error(err),
+    }
+}
+
+/// A statement of the query editor.
+#[derive(Debug, PartialEq)]
+enum MongoQuery {
+    Find {
+        collection: TableRef,
+        filter: Document,
+        projection: Option<Document>,
+    },
+    Aggregate {
+        collection: TableRef,
+        pipeline: Vec<Document>,
+    },
+}
+
+fn parse_query(query: &str) -> DbResult<MongoQuery> {
+    let query = query.trim().trim_end_matches(';').trim_end();
+    if let Some(call) = query.strip_prefix("db.") {
+        return parse_call(call);
+    }
+    if let Some(collection) = statement::browsed_table(query) {
+        return Ok(MongoQuery::Find {
+            collection,
+            filter: Document::new(),
+            projection: None,
+        });
+    }
+    Err(query_error(QUERY_USAGE))
+}
+
+/// Parses `<collection>.find(...)` or `<collection>.aggregate(...)`, the collection being
+/// a plain name or `getCollection("name")`.
+fn parse_call(call: &str) -> DbResult<MongoQuery> {
+    let (collection, method, args) = [".find(", ".aggregate("]
+        .iter()
+        .filter_map(|method| call.find(method).map(|at| (at, method)))
+        .min()
+        .map(|(at, method)| {
+            (
+                &call[..at],
+                &method[1..method.len() - 1],
+                &call[at + method.len()..],
+            )
+        })
+        .ok_or_else(|| query_error(QUERY_USAGE))?;
+    let args = args
+        .strip_suffix(')')
+        .ok_or_else(|| query_error(QUERY_USAGE))?;
+    let collection = match collection
+        .strip_prefix("getCollection(")
+        .and_then(|name| name.strip_suffix(')'))
+    {
+        Some(name) => serde_json::from_str::<String>(name).map_err(query_error)?,
+        None => collection.to_string(),
+    };
+    if collection.is_empty() {
+        return Err(query_error(QUERY_USAGE));
+    }
+    let collection = TableRef::unqualified(collection);
+
+    let args: Vec<serde_json::Value> =
+        serde_json::from_str(&format!("[{}]", args)).map_err(|e| {
+            query_error(format!(
+                "The arguments of {} aren't valid JSON: {}",
+                method, e
+            ))
+        })?;
+    let mut args = args
+        .into_iter()
+        .map(|arg| Bson::try_from(arg).map_err(query_error))
+        .collect::<DbResult<Vec<Bson>>>()?
+        .into_iter();
+    let document = |arg: Bson, what: &str| match arg {
+        Bson::Document(document) => Ok(document),
+        _ => Err(query_error(format!("The {} must be a document", what))),
+    };
+
+    match method {
+        "find" => {
+            let filter = match args.next() {
+                Some(filter) => document(filter, "filter")?,
+                None => Document::new(),
+            };
+            let projection = args
+                .next()
+                .map(|projection| document(projection, "projection"))
+                .transpose()?;
+            Ok(MongoQuery::Find {
+                collection,
+                filter,
+                projection,
+            })
+        }
+        _ => {
+            let Some(Bson::Array(stages)) = args.next() else {
+                return Err(query_error("The pipeline must be an array of stages"));
+            };
+            let pipeline = stages
+                .into_iter()
+                .map(|stage| document(stage, "stage"))
+                .collect::<DbResult<Vec<_>>>()?;
+            Ok(MongoQuery::Aggregate {
+                collection,
+                pipeline,
+            })
+        }
+    }
+}
+
+/// The shell's name for a value's type, as `$type` takes it.
+fn type_name(value: &Bson) -> &'static str {
+    match value {
+        Bson::Double(_) => "double",
+        Bson::String(_) => "string",
+        Bson::Document(_) => "object",
+        Bson::Array(_) => "array",
+        Bson::Binary(_) => "binData",
+        Bson::Undefined => "undefined",
+        Bson::ObjectId(_) => "objectId",
+        Bson::Boolean(_) => "bool",
+        Bson::DateTime(_) => "date",
+        Bson::Null => "null",
+        Bson::RegularExpression(_) => "regex",
+        Bson::DbPointer(_) => "dbPointer",
+        Bson::JavaScriptCode(_) => "javascript",
+        Bson::Symbol(_) => "symbol",
+        Bson::JavaScriptCodeWithScope(_) => "javascriptWithScope",
+        Bson::Int32(_) => "int",
+        Bson::Timestamp(_) => "timestamp",
+        Bson::Int64(_) => "long",
+        Bson::Decimal128(_) => "decimal",
+        Bson::MinKey => "minKey",
+        Bson::MaxKey => "maxKey",
+    }
+}
+
+fn value_to_json(value: &Bson) -> serde_json::Value {
+    use serde_json::Value as Json;
+    match value {
+        Bson::Null | Bson::Undefined => Json::Null,
+        Bson::Boolean(v) => Json::Bool(*v),
+        Bson::Int32(v) => Json::from(*v),
+        Bson::Int64(v) => Json::from(*v),
+        Bson::Double(v) => serde_json::Number::from_f64(*v)
+            .map(Json::Number)
+            .unwrap_or_else(|| Json::String(v.to_string())),
+        Bson::String(v) | Bson::Symbol(v) | Bson::JavaScriptCode(v) => Json::String(v.clone()),
+        // Object ids show as the hex string `ObjectId(...)` takes, which edits look up by
+        Bson::ObjectId(v) => Json::String(v.to_hex()),
+        Bson::DateTime(v) => DateTime::from_timestamp_millis(v.timestamp_millis())
+            .map(|t| Json::String(t.format("%Y-%m-%d %H:%M:%S%.3f").to_string()))
+            .unwrap_or_else(|| Json::from(v.timestamp_millis())),
+        Bson::Binary(v) => Json::String(base64::engine::general_purpose::STANDARD.encode(&v.bytes)),
+        // Decimals stay text so they aren't rounded
+        Bson::Decimal128(v) => Json::String(v.to_string()),
+        Bson::Array(items) => Json::Array(items.iter().map(value_to_json).collect()),
+        Bson::Document(document) => document_to_json(document),
+        other => other.clone().into_relaxed_extjson(),
+    }
+}
+
+fn document_to_json(document: &Document) -> serde_json::Value {
+    serde_json::Value::Object(
+        document
+            .iter()
+            .map(|(key, value)| (key.clone(), value_to_json(value)))
+            .collect(),
+    )
+}
+
+/// Columns for every top-level field of `documents`, in the order they first appear.
+/// A field's type is that of its first value that isn't null.
+fn columns_of(documents: &[Document]) -> Vec<ColumnMetadata> {
+    let mut columns: Vec<ColumnMetadata> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for document in documents {
+        for (key, value) in document {
+            let column = match positions.get(key.as_str()) {
+                Some(&position) => &mut columns[position],
+                None => {
+                    positions.insert(key, columns.len());
+                    columns.push(ColumnMetadata {
+                        name: key.clone(),
+                        type_name: "null".to_string(),
+                        nullable: Some(key != "_id"),
+                        ..Default::default()
+                    });
+                    columns.last_mut().unwrap()
+                }
+            };
+            if column.type_name == "null" {
+                column.type_name = type_name(value).to_string();
+                column.numeric = matches!(
+                    value,
+                    Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_) | Bson::Decimal128(_)
+                );
+            }
+        }
+    }
+    columns
+}
+
+/// A row of `columns`, fields a document lacks being null.
+fn document_to_row(columns: &[String], document: &Document) -> serde_json::Value {
+    let mut row_map = serde_json::Map::with_capacity(columns.len());
+    for column in columns {
+        let value = document
+            .get(column)
+            .map(value_to_json)
+            .unwrap_or(serde_json::Value::Null);
+        row_map.insert(column.clone(), value);
+    }
+    serde_json::Value::Object(row_map)
+}
+
+/// The `_id`s a value shown in the grid may stand for: an object id from its hex string,
+/// the value read as extended JSON, and the text itself.
+fn id_candidates(text: &str) -> Vec<Bson> {
+    let mut candidates = Vec::new();
+    if let Ok(id) = ObjectId::parse_str(text) {
+        candidates.push(Bson::ObjectId(id));
+    }
+    let json = serde_json::from_str::<serde_json::Value>(text).ok();
+    if let Some(value) = json.and_then(|json| Bson::try_from(json).ok()) {
+        candidates.push(value);
+    }
+    candidates.push(Bson::String(text.to_string()));
+    candidates
+}
+
+/// The value a cell edit stores: text for string fields, otherwise the text read as
+/// extended JSON, so numbers, booleans and documents keep their type.
+fn edited_value(current: Option<&Bson>, text: &str) -> Bson {
+    if matches!(current, Some(Bson::String(_))) {
+        return Bson::String(text.to_string());
+    }
+    serde_json::from_str::<serde_json::Value>(text)
+        .ok()
+        .and_then(|json| Bson::try_from(json).ok())
+        .unwrap_or_else(|| Bson::String(text.to_string()))
+}
+
+fn shell_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+/// The shell expression for the database `table` is in: `db`, or another one by name.
+fn shell_database(table: &TableRef) -> String {
+    match &table.schema {
+        Some(database) => format!("db.getSiblingDB({})", shell_string(database)),
+        None => "db".to_string(),
+    }
+}
+
+/// The shell expression for a collection, e.g. `db.getCollection("users")`.
+fn shell_collection(table: &TableRef) -> String {
+    format!(
+        "{}.getCollection({})",
+        shell_database(table),
+        shell_string(&table.name)
+    )
+}
+
+fn insert_many(collection: &str, documents: &[String]) -> String {
+    format!(
+        "{}.insertMany(EJSON.deserialize([\n  {}\n]));\n",
+        collection,
+        documents.join(",\n  ")
+    )
+}
+
+fn extjson(document: Document) -> String {
+    Bson::Document(document).into_relaxed_extjson().to_string()
+}
+
+pub struct MongoDbConnection {
+    client: Client,
+    database: Mutex<String>,
+    /// Server-side time limit (`maxTimeMS`) of user queries.
+    query_timeout: Duration,
+}
+
+impl MongoDbConnection {
+    /// Connects to the server at `host`, authenticating against the `admin` database when a
+    /// user is given.
+    pub async fn new(
+        host: &str,
+        port: u16,
+        username: &str,
+        password: &str,
+        database: &str,
+        ssl_mode: &str,
+        query_timeout: Option<Duration>,
+    ) -> DbResult<Self> {
+        let tls = match SslMode::parse(ssl_mode)? {
+            SslMode::Disabled => Tls::Disabled,
+            SslMode::Required => Tls::Enabled(
+                TlsOptions::builder()
+                    .allow_invalid_certificates(true)
+                    .build(),
+            ),
+            SslMode::VerifyFull => Tls::Enabled(TlsOptions::default()),
+            _ => {
+                return Err(QueryError::with_code(
+                    "MongoDB connections either skip or fully verify certificates; choose \
+                     disabled, required or verify-full",
+                    error_codes::SSL_ERROR,
+                ))
+            }
+        };
+        let credential = (!username.is_empty()).then(|| {
+            Credential::builder()
+                .username(username.to_string())
+                .password(password.to_string())
+                .build()
+        });
+        let options = ClientOptions::builder()
+            .hosts(vec![ServerAddress::Tcp {
+                host: host.to_string(),
+                port: Some(port),
+            }])
+            .credential(credential)
+            .tls(tls)
+            .app_name("BloatSQL".to_string())
+            // Only the server connected to is used, as for SSH tunnels the replica set's
+            // other members are out of reach
+            .direct_connection(true)
+            .server_selection_timeout(DEFAULT_QUERY_TIMEOUT)
+            .build();
+        let client = Client::with_options(options).map_err(mongo_error)?;
+
+        let connection = MongoDbConnection {
+            client,
+            database: Mutex::new(database.to_string()),
+            query_timeout: query_timeout.unwrap_or(DEFAULT_QUERY_TIMEOUT),
+        };
+        // The driver connects lazily; the first command shows whether the server is there
+        connection.test_connection().await?;
+        debug!("MongoDB connection established to {}:{}", host, port);
+        Ok(connection)
+    }
+
+    fn current_database(&self) -> String {
+        self.database.lock().unwrap().clone()
+    }
+
+    /// The collection `table` names, in its own database or the current one.
+    fn collection(&self, table: &TableRef) -> DbResult<Collection<Document>> {
+        let database = match &table.schema {
+            Some(database) => database.clone(),
+            None => self.current_database(),
+        };
+        if database.is_empty() {
+            return Err(query_error("No database is selected"));
+        }
+        Ok(self.client.database(&database).collection(&table.name))
+    }
+
+    /// Runs a query, reading up to `MAX_QUERY_ROWS` documents.
+    async fn run(
+        &self,
+        query: MongoQuery,
+        timer: &mut QueryTimer,
+    ) -> DbResult<(Vec<Document>, bool)> {
+        let mut cursor = match query {
+            MongoQuery::Find {
+                collection,
+                filter,
+                projection,
+            } => {
+                let collection = self.collection(&collection)?;
+                let mut find = collection.find(filter).max_time(self.query_timeout);
+                if let Some(projection) = projection {
+                    find = find.projection(projection);
+                }
+                find.await.map_err(mongo_error)?
+            }
+            MongoQuery::Aggregate {
+                collection,
+                pipeline,
+            } => self
+                .collection(&collection)?
+                .aggregate(pipeline)
+                .max_time(self.query_timeout)
+                .await
+                .map_err(mongo_error)?,
+        };
+        timer.executed();
+
+        let mut documents = Vec::new();
+        let mut truncated = false;
+        while let Some(document) = cursor.try_next().await.map_err(mongo_error)? {
+            if documents.len() == MAX_QUERY_ROWS {
+                truncated = true;
+                break;
+            }
+            documents.push(document);
+        }
+        timer.fetched();
+        Ok((documents, truncated))
+    }
+}
+
+#[async_trait]
+impl DatabaseConnection for MongoDbConnection {
+    async fn test_connection(&self) -> DbResult<()> {
+        self.client
+            .database("admin")
+            .run_command(doc! { "ping": 1 })
+            .await
+            .map(|_| ())
+            .map_err(mongo_error)
+    }
+
+    async fn reconnect_if_broken(&self) -> DbResult<bool> {
+        // The driver reconnects by itself
+        Ok(false)
+    }
+
+    async fn execute_query(&self, query: &str) -> DbResult<QueryResult> {
+        let mut timer = QueryTimer::start();
+        let query = parse_query(query)?;
+        timer.acquired();
+        let (documents, truncated) = self.run(query, &mut timer).await?;
+
+        let column_metadata = columns_of(&documents);
+        let columns: Vec<String> = column_metadata.iter().map(|c| c.name.clone()).collect();
+        let rows: Vec<serde_json::Value> = documents
+            .iter()
+            .map(|document| document_to_row(&columns, document))
+            .collect();
+        timer.converted();
+
+        Ok(QueryResult {
+            columns,
+            row_count: rows.len(),
+            rows,
+            execution_time: timer.execution_time(),
+            truncated,
+            affected_rows: None,
+            timing: timer.timing(),
+            column_metadata,
+        })
+    }
+
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult> {
+        // Columns are only known once every document is read, so results are chunked
+        // after the fact
+        let mut result = self.execute_query(query).await?;
+        let mut chunks = ChunkedRows::new(result.columns.clone(), chunk_size, on_chunk);
+        for row in std::mem::take(&mut result.rows) {
+            if !chunks.push(row) {
+                break;
+            }
+        }
+        chunks.finish();
+        Ok(result)
+    }
+
+    async fn list_tables(&self) -> DbResult<Vec<String>> {
+        let database = self.current_database();
+        if database.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut collections = self
+            .client
+            .database(&database)
+            .list_collection_names()
+            .await
+            .map_err(mongo_error)?;
+        collections.sort();
+        Ok(collections)
+    }
+
+    async fn list_databases(&self) -> DbResult<Vec<String>> {
+        let mut databases = self
+            .client
+            .list_database_names()
+            .await
+            .map_err(mongo_error)?;
+        databases.sort();
+        Ok(databases)
+    }
+
+    async fn change_database(&self, database_name: &str) -> DbResult<()> {
+        *self.database.lock().unwrap() = database_name.to_string();
+        Ok(())
+    }
+
+    async fn set_default_schema(&self, schema: &str) -> DbResult<()> {
+        self.change_database(schema).await
+    }
+
+    async fn get_current_database(&self) -> DbResult<String> {
+        Ok(self.current_database())
+    }
+
+    async fn get_table_columns(&self, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        // Collections have no schema; the fields are those of a sample of documents
+        let documents: Vec<Document> = self
+            .collection(table)?
+            .find(Document::new())
+            .limit(SCHEMA_SAMPLE_SIZE)
+            .max_time(DEFAULT_QUERY_TIMEOUT)
+            .await
+            .map_err(mongo_error)?
+            .try_collect()
+            .await
+            .map_err(mongo_error)?;
+        Ok(columns_of(&documents)
+            .into_iter()
+            .map(|column| TableColumn {
+                is_primary_key: column.name == "_id",
+                name: column.name,
+                data_type: column.type_name,
+                is_nullable: column.nullable != Some(false),
+                column_default: None,
+                character_maximum_length: None,
+                numeric_precision: None,
+                is_identity: false,
+                identity_generation: None,
+                is_generated: false,
+                generation_expression: None,
+                generation_kind: None,
+                allowed_values: None,
+            })
+            .collect())
+    }
+
+    async fn get_table_relationships(&self) -> DbResult<Vec<TableRelationship>> {
+        // Collections have no foreign keys
+        Ok(Vec::new())
+    }
+
+    async fn check_privileges(
+        &self,
+        _table: &TableRef,
+        operation: TableOperation,
+    ) -> DbResult<bool> {
+        // Cells are edited with `$set`; a missing privilege shows when the update runs
+        Ok(matches!(
+            operation,
+            TableOperation::Select | TableOperation::Update
+        ))
+    }
+
+    async fn disconnect(&self) -> DbResult<()> {
+        // The driver closes its connections when the client is dropped
+        debug!("Disconnecting from MongoDB");
+        Ok(())
+    }
+
+    async fn export_database_with_options(
+        &self,
+        include_drop: bool,
+        include_create: bool,
+        data_mode: &str,
+        selected_tables: &[TableRef],
+        max_insert_size: usize,
+    ) -> DbResult<String> {
+        if !matches!(data_mode, "insert" | "no_data") {
+            return Err(query_error(format!(
+                "MongoDB exports support the insert and no_data modes, not '{}'",
+                data_mode
+            )));
+        }
+        let collections = if selected_tables.is_empty() {
+            self.list_tables()
+                .await?
+                .into_iter()
+                .map(TableRef::unqualified)
+                .collect()
+        } else {
+            selected_tables.to_vec()
+        };
+
+        // The export is a script for mongosh, documents being written as canonical
+        // extended JSON so their types survive
+        let mut script = String::with_capacity(1024 * 1024);
+        for table in &collections {
+            let collection = shell_collection(table);
+            script.push_str(&format!("\n// Collection: {}\n", table));
+
+            if include_drop {
+                script.push_str(&format!("{}.drop();\n", collection));
+            }
+
+            if include_create {
+                script.push_str(&format!(
+                    "{}.createCollection({});\n\n",
+                    shell_database(table),
+                    shell_string(&table.name)
+                ));
+            }
+
+            if data_mode == "no_data" {
+                continue;
+            }
+
+            let mut cursor = self
+                .collection(table)?
+                .find(Document::new())
+                .await
+                .map_err(mongo_error)?;
+            let mut batch = Vec::new();
+            while let Some(document) = cursor.try_next().await.map_err(mongo_error)? {
+                batch.push(
+                    Bson::Document(document)
+                        .into_canonical_extjson()
+                        .to_string(),
+                );
+                if batch.len() >= max_insert_size.max(1) {
+                    script.push_str(&insert_many(&collection, &batch));
+                    batch.clear();
+                }
+            }
+            if !batch.is_empty() {
+                script.push_str(&insert_many(&collection, &batch));
+            }
+            script.push('\n');
+        }
+
+        Ok(script)
+    }
+
+    async fn update_cell(
+        &self,
+        table: &TableRef,
+        column_name: &str,
+        new_value: Option<&str>,
+        primary_key_column: &str,
+        primary_key_value: &str,
+    ) -> DbResult<String> {
+        if primary_key_column != "_id" {
+            return Err(query_error("MongoDB documents are edited by their _id"));
+        }
+        let collection = self.collection(table)?;
+        let lookup = doc! { "_id": { "$in": id_candidates(primary_key_value) } };
+        let document = collection
+            .find_one(lookup)
+            .await
+            .map_err(mongo_error)?
+            .ok_or_else(|| query_error("The document was not found"))?;
+
+        let value = match new_value {
+            Some(text) => edited_value(document.get(column_name), text),
+            None => Bson::Null,
+        };
+        let filter = doc! { "_id": document.get("_id").cloned().unwrap_or(Bson::Null) };
+        let update = doc! { "$set": { column_name: value } };
+        collection
+            .update_one(filter.clone(), update.clone())
+            .await
+            .map_err(mongo_error)?;
+
+        Ok(format!(
+            "{}.updateOne({}, {})",
+            shell_collection(table),
+            extjson(filter),
+            extjson(update)
+        ))
+    }
+
+    async fn create_database(
+        &self,
+        _name: &str,
+        _encoding: Option<&str>,
+        _owner: Option<&str>,
+    ) -> DbResult<String> {
+        // The server creates a database when its first collection is written
+        unsupported("Creating empty databases")
+    }
+
+    async fn drop_database(&self, name: &str) -> DbResult<String> {
+        let current = self.current_database();
+        check_droppable_database(name, &current, &SYSTEM_DATABASES)?;
+
+        self.client
+            .database(name)
+            .drop()
+            .await
+            .map_err(mongo_error)?;
+        debug!("Dropped database {}", name);
+        Ok(format!(
+            "db.getSiblingDB({}).dropDatabase()",
+            shell_string(name)
+        ))
+    }
+
+    async fn build_sample_query(
+        &self,
+        table: &TableRef,
+        n: usize,
+        _method: SampleMethod,
+    ) -> DbResult<String> {
+        // `$sample` picks documents at random however the collection is stored
+        Ok(format!(
+            "{}.aggregate([{{\"$sample\": {{\"size\": {}}}}}])",
+            shell_collection(table),
+            sampling::sample_size(n)
+        ))
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        DIALECT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_queries_and_values() {
+        assert_eq!(
+            parse_query(r#"db.users.find({"age": {"$gt": 30}}, {"name": 1});"#).unwrap(),
+            MongoQuery::Find {
+                collection: TableRef::unqualified("users"),
+                filter: doc! { "age": { "$gt": 30 } },
+                projection: Some(doc! { "name": 1 }),
+            }
+        );
+        assert_eq!(
+            parse_query(r#"db.getCollection("a.b").aggregate([{"$limit": 5}])"#).unwrap(),
+            MongoQuery::Aggregate {
+                collection: TableRef::unqualified("a.b"),
+                pipeline: vec![doc! { "$limit": 5 }],
+            }
+        );
+        assert_eq!(
+            parse_query(r#"select * from "shop"."order""s""#).unwrap(),
+            MongoQuery::Find {
+                collection: TableRef::new(Some("shop".to_string()), "order\"s"),
+                filter: Document::new(),
+                projection: None,
+            }
+        );
+        assert!(parse_query("db.users.find({age: 1})").is_err());
+        assert!(parse_query("db.users.aggregate({})").is_err());
+        assert!(parse_query("SELECT name FROM users").is_err());
+
+        let id = ObjectId::parse_str("65f1c0ffee0000000000abcd").unwrap();
+        let documents = vec![
+            doc! { "_id": id, "note": Bson::Null },
+            doc! { "_id": 2_i64, "note": "hi", "price": 1.5 },
+        ];
+        let columns = columns_of(&documents);
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["_id", "note", "price"]);
+        assert_eq!(columns[0].type_name, "objectId");
+        assert_eq!(columns[0].nullable, Some(false));
+        assert_eq!(columns[1].type_name, "string");
+        assert!(columns[2].numeric);
+        let columns: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        assert_eq!(
+            document_to_row(&columns, &documents[0]),
+            json!({"_id": "65f1c0ffee0000000000abcd", "note": null, "price": null})
+        );
+
+        assert_eq!(
+            id_candidates("65f1c0ffee0000000000abcd")[0],
+            Bson::ObjectId(id)
+        );
+        assert_eq!(id_candidates("2")[0], Bson::Int32(2));
+        assert_eq!(
+            edited_value(Some(&Bson::String("1".into())), "2"),
+            Bson::String("2".into())
+        );
+        assert_eq!(edited_value(Some(&Bson::Int32(1)), "2"), Bson::Int32(2));
+        assert_eq!(
+            edited_value(None, "not json"),
+            Bson::String("not json".into())
+        );
+    }
+}
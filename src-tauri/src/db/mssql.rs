@@ -0,0 +1,1242 @@
+//! Microsoft SQL Server over TDS, with tiberius.
+//!
+//! SQL Server keeps the current database, temporary tables and transactions per
+//! connection, so everything runs on one session connection, like PostgreSQL's.
+
+use super::column_metadata::ColumnMetadata;
+use super::connection::{
+    check_droppable_database, convert_rows, error_codes, ChunkedRows, DatabaseConnection, DbResult,
+    QueryError, QueryResult, QueryTimer, RowChunkSink, SslMode, TableColumn, TableOperation,
+    TableRef, TableRelationship, DEFAULT_QUERY_TIMEOUT, LIVENESS_TIMEOUT, MAX_QUERY_ROWS,
+};
+use super::sampling::{self, SampleMethod, RANDOM_SCAN_LIMIT};
+use super::sql_dialect::SqlDialect;
+use super::statement;
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use futures_util::TryStreamExt;
+use std::time::Duration;
+use tiberius::error::Error as TdsError;
+use tiberius::numeric::Numeric;
+use tiberius::{
+    AuthMethod, Client, Column, ColumnData, ColumnType, Config, EncryptionLevel, FromSql,
+    QueryItem, Row, ToSql,
+};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
+use tracing::{debug, warn};
+
+type TdsClient = Client<Compat<TcpStream>>;
+
+const DIALECT: SqlDialect = SqlDialect::SqlServer;
+
+/// Rows a single `INSERT ... VALUES` may list.
+const MAX_INSERT_ROWS: usize = 1_000;
+
+/// Parameters a single request may bind.
+const MAX_PARAMETERS: usize = 2_100;
+
+/// Databases `drop_database` refuses to drop.
+const SYSTEM_DATABASES: [&str; 4] = ["master", "tempdb", "model", "msdb"];
+
+/// Maps a tiberius error to a `QueryError`; errors raised by the server keep their number
+/// and line the way SQL Server Management Studio prints them.
+fn tds_error(err: TdsError, code: &str) -> QueryError {
+    match err {
+        TdsError::Server(token) => {
+            QueryError::with_code(token.message(), code).with_detail(format!(
+                "Msg {}, Level {}, State {}, Line {}",
+                token.code(),
+                token.class(),
+                token.state(),
+                token.line()
+            ))
+        }
+        TdsError::Tls(message) => QueryError::with_code(
+            format!("TLS connection failed: {}", message),
+            error_codes::TLS_ERROR,
+        ),
+        TdsError::Io { message, .. } => {
+            QueryError::with_code(message, error_codes::CONNECTION_ERROR)
+        }
+        other => QueryError::with_code(other.to_string(), code),
+    }
+}
+
+fn timed_out() -> QueryError {
+    QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR)
+}
+
+fn text(row: &Row, column: &str) -> Option<String> {
+    row.try_get::<&str, _>(column)
+        .ok()
+        .flatten()
+        .map(str::to_string)
+}
+
+fn number(row: &Row, column: &str) -> Option<i32> {
+    row.try_get::<i32, _>(column).ok().flatten()
+}
+
+fn flag(row: &Row, column: &str) -> bool {
+    row.try_get::<bool, _>(column)
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+}
+
+/// SQL Server database connection implementation.
+pub struct MssqlConnection {
+    /// The session connection. Replaced by `reconnect_if_broken` when it stops responding.
+    client: Mutex<TdsClient>,
+    current_database: Mutex<String>,
+    // Connection parameters stored for reconnection
+    host: String,
+    port: u16,
+    username: String,
+    password: String,
+    ssl_mode: SslMode,
+    /// Timeout for user statements.
+    query_timeout: Duration,
+}
+
+impl MssqlConnection {
+    pub async fn new(
+        host: &str,
+        port: u16,
+        user: &str,
+        password: &str,
+        dbname: &str,
+        ssl_mode: &str,
+        query_timeout: Option<Duration>,
+    ) -> DbResult<Self> {
+        let ssl_mode = SslMode::parse(ssl_mode)?;
+        let client = Self::connect(host, port, user, password, dbname, ssl_mode).await?;
+        Ok(MssqlConnection {
+            client: Mutex::new(client),
+            current_database: Mutex::new(dbname.to_string()),
+            host: host.to_string(),
+            port,
+            username: user.to_string(),
+            password: password.to_string(),
+            ssl_mode,
+            query_timeout: query_timeout.unwrap_or(DEFAULT_QUERY_TIMEOUT),
+        })
+    }
+
+    /// Connects with TLS as `ssl_mode` asks, falling back to an unencrypted connection
+    /// for `preferred`.
+    async fn connect(
+        host: &str,
+        port: u16,
+        user: &str,
+        password: &str,
+        dbname: &str,
+        ssl_mode: SslMode,
+    ) -> DbResult<TdsClient> {
+        if ssl_mode.uses_tls() {
+            match Self::connect_with(host, port, user, password, dbname, ssl_mode).await {
+                Ok(client) => {
+                    debug!("SQL Server TLS connection established");
+                    return Ok(client);
+                }
+                Err(e) if ssl_mode.allows_fallback() => {
+                    warn!(
+                        "TLS connection failed, falling back to non-TLS: {}",
+                        e.message
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let client =
+            Self::connect_with(host, port, user, password, dbname, SslMode::Disabled).await?;
+        debug!("SQL Server non-TLS connection established");
+        Ok(client)
+    }
+
+    async fn connect_with(
+        host: &str,
+        port: u16,
+        user: &str,
+        password: &str,
+        dbname: &str,
+        ssl_mode: SslMode,
+    ) -> DbResult<TdsClient> {
+        let mut config = Config::new();
+        config.host(host);
+        config.port(port);
+        if !dbname.is_empty() {
+            config.database(dbname);
+        }
+        config.authentication(AuthMethod::sql_server(user, password));
+        config.application_name("BloatSQL");
+        // native-tls always checks the host name of verified certificates, so verify-ca is
+        // as strict as verify-full
+        if ssl_mode.uses_tls() {
+            config.encryption(EncryptionLevel::Required);
+            if !ssl_mode.verifies_certificate() {
+                config.trust_cert();
+            }
+        } else {
+            config.encryption(EncryptionLevel::NotSupported);
+        }
+
+        let connect = async {
+            let client = match Self::open(config.clone()).await {
+                // Azure SQL redirects logins to the node serving the database
+                Err(TdsError::Routing { host, port }) => {
+                    config.host(host);
+                    config.port(port);
+                    Self::open(config).await
+                }
+                result => result,
+            };
+            client.map_err(|e| tds_error(e, error_codes::CONNECTION_ERROR))
+        };
+        timeout(DEFAULT_QUERY_TIMEOUT, connect).await.map_err(|_| {
+            QueryError::with_code("Connection timed out", error_codes::TIMEOUT_ERROR)
+        })?
+    }
+
+    async fn open(config: Config) -> tiberius::Result<TdsClient> {
+        let tcp = TcpStream::connect(config.get_addr()).await?;
+        tcp.set_nodelay(true)?;
+        Client::connect(config, tcp.compat_write()).await
+    }
+
+    /// Runs a parameterized query (`@P1`, `@P2`, ...) and returns its first result set.
+    async fn query_rows(
+        client: &mut TdsClient,
+        query: &str,
+        params: &[&dyn ToSql],
+    ) -> DbResult<Vec<Row>> {
+        let rows = async { client.query(query, params).await?.into_first_result().await };
+        timeout(DEFAULT_QUERY_TIMEOUT, rows)
+            .await
+            .map_err(|_| timed_out())?
+            .map_err(|e| tds_error(e, error_codes::QUERY_ERROR))
+    }
+
+    /// Runs statements as one batch, discarding their results.
+    async fn run_batch(client: &mut TdsClient, sql: &str) -> DbResult<()> {
+        let results = async { client.simple_query(sql).await?.into_results().await };
+        timeout(DEFAULT_QUERY_TIMEOUT, results)
+            .await
+            .map_err(|_| timed_out())?
+            .map_err(|e| tds_error(e, error_codes::QUERY_ERROR))?;
+        Ok(())
+    }
+
+    async fn table_names(client: &mut TdsClient) -> DbResult<Vec<String>> {
+        let query = "SELECT TABLE_NAME AS name FROM INFORMATION_SCHEMA.TABLES
+                     WHERE TABLE_SCHEMA = SCHEMA_NAME() AND TABLE_TYPE = 'BASE TABLE'
+                     ORDER BY TABLE_NAME";
+        let rows = Self::query_rows(client, query, &[]).await?;
+        Ok(rows.iter().filter_map(|row| text(row, "name")).collect())
+    }
+
+    async fn table_columns(client: &mut TdsClient, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        let query = "SELECT
+                        c.COLUMN_NAME AS name,
+                        c.DATA_TYPE AS data_type,
+                        CAST(c.CHARACTER_MAXIMUM_LENGTH AS int) AS character_maximum_length,
+                        CAST(c.NUMERIC_PRECISION AS int) AS numeric_precision,
+                        CAST(c.NUMERIC_SCALE AS int) AS numeric_scale,
+                        CAST(c.DATETIME_PRECISION AS int) AS datetime_precision,
+                        c.IS_NULLABLE AS is_nullable,
+                        c.COLUMN_DEFAULT AS column_default,
+                        CAST(CASE WHEN pk.COLUMN_NAME IS NULL THEN 0 ELSE 1 END AS bit)
+                            AS is_primary,
+                        CAST(COLUMNPROPERTY(t.object_id, c.COLUMN_NAME, 'IsIdentity') AS bit)
+                            AS is_identity,
+                        cc.definition AS generation_expression,
+                        cc.is_persisted
+                     FROM INFORMATION_SCHEMA.COLUMNS c
+                     CROSS APPLY (
+                        SELECT OBJECT_ID(QUOTENAME(c.TABLE_SCHEMA) + '.' + QUOTENAME(c.TABLE_NAME))
+                            AS object_id
+                     ) t
+                     LEFT JOIN (
+                        SELECT ku.TABLE_SCHEMA, ku.TABLE_NAME, ku.COLUMN_NAME
+                        FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS tc
+                        JOIN INFORMATION_SCHEMA.KEY_COLUMN_USAGE ku
+                            ON tc.CONSTRAINT_NAME = ku.CONSTRAINT_NAME
+                            AND tc.CONSTRAINT_SCHEMA = ku.CONSTRAINT_SCHEMA
+                        WHERE tc.CONSTRAINT_TYPE = 'PRIMARY KEY'
+                     ) pk ON pk.TABLE_SCHEMA = c.TABLE_SCHEMA
+                        AND pk.TABLE_NAME = c.TABLE_NAME
+                        AND pk.COLUMN_NAME = c.COLUMN_NAME
+                     LEFT JOIN sys.computed_columns cc
+                        ON cc.object_id = t.object_id AND cc.name = c.COLUMN_NAME
+                     WHERE c.TABLE_SCHEMA = COALESCE(@P1, SCHEMA_NAME()) AND c.TABLE_NAME = @P2
+                     ORDER BY c.ORDINAL_POSITION";
+        let rows = Self::query_rows(
+            client,
+            query,
+            &[&table.schema.as_deref(), &table.name.as_str()],
+        )
+        .await?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let data_type = text(row, "data_type")?;
+                let is_generated = text(row, "generation_expression").is_some();
+                Some(TableColumn {
+                    name: text(row, "name")?,
+                    data_type: full_type(
+                        &data_type,
+                        number(row, "character_maximum_length"),
+                        number(row, "numeric_precision"),
+                        number(row, "numeric_scale"),
+                        number(row, "datetime_precision"),
+                    ),
+                    is_nullable: text(row, "is_nullable").as_deref() == Some("YES"),
+                    is_primary_key: flag(row, "is_primary"),
+                    column_default: text(row, "column_default"),
+                    character_maximum_length: number(row, "character_maximum_length")
+                        .map(i64::from),
+                    numeric_precision: number(row, "numeric_precision").map(i64::from),
+                    is_identity: flag(row, "is_identity"),
+                    identity_generation: None,
+                    is_generated,
+                    generation_expression: text(row, "generation_expression"),
+                    generation_kind: is_generated.then(|| {
+                        if flag(row, "is_persisted") {
+                            "STORED".to_string()
+                        } else {
+                            "VIRTUAL".to_string()
+                        }
+                    }),
+                    allowed_values: None,
+                })
+            })
+            .collect())
+    }
+
+    /// Returns the source of a view, procedure or function, as the statement that created
+    /// it.
+    async fn object_definition(&self, object: &TableRef, kind: &str) -> DbResult<String> {
+        let mut client = self.client.lock().await;
+        let name = DIALECT.qualified_name(object);
+        let rows = Self::query_rows(
+            &mut client,
+            "SELECT OBJECT_DEFINITION(OBJECT_ID(@P1)) AS definition",
+            &[&name.as_str()],
+        )
+        .await?;
+        rows.first()
+            .and_then(|row| text(row, "definition"))
+            .ok_or_else(|| {
+                QueryError::with_code(
+                    format!("{} \"{}\" not found", kind, object),
+                    error_codes::QUERY_ERROR,
+                )
+            })
+    }
+
+    fn column_metadata(columns: &[Column]) -> Vec<ColumnMetadata> {
+        // TDS result metadata carries no nullability, precision or source table
+        columns
+            .iter()
+            .map(|column| ColumnMetadata {
+                name: column.name().to_string(),
+                type_name: type_name(column.column_type()).to_string(),
+                numeric: matches!(
+                    column.column_type(),
+                    ColumnType::Int1
+                        | ColumnType::Int2
+                        | ColumnType::Int4
+                        | ColumnType::Int8
+                        | ColumnType::Intn
+                        | ColumnType::Float4
+                        | ColumnType::Float8
+                        | ColumnType::Floatn
+                        | ColumnType::Money
+                        | ColumnType::Money4
+                        | ColumnType::Decimaln
+                        | ColumnType::Numericn
+                ),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn row_to_json(columns: &[String], row: &Row) -> serde_json::Value {
+        let mut row_map = serde_json::Map::with_capacity(columns.len());
+        for (name, (_, value)) in columns.iter().zip(row.cells()) {
+            row_map.insert(name.clone(), Self::value_to_json(value));
+        }
+        serde_json::Value::Object(row_map)
+    }
+
+    fn value_to_json(value: &ColumnData<'static>) -> serde_json::Value {
+        use serde_json::Value;
+        match value {
+            ColumnData::U8(v) => v.map(Value::from).unwrap_or(Value::Null),
+            ColumnData::I16(v) => v.map(Value::from).unwrap_or(Value::Null),
+            ColumnData::I32(v) => v.map(Value::from).unwrap_or(Value::Null),
+            ColumnData::I64(v) => v.map(Value::from).unwrap_or(Value::Null),
+            ColumnData::F32(v) => v
+                .and_then(|v| serde_json::Number::from_f64(v as f64))
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            ColumnData::F64(v) => v
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            ColumnData::Bit(v) => v.map(Value::Bool).unwrap_or(Value::Null),
+            ColumnData::String(v) => v
+                .as_ref()
+                .map(|v| Value::String(v.to_string()))
+                .unwrap_or(Value::Null),
+            // SQL Server prints uniqueidentifiers in upper case
+            ColumnData::Guid(v) => v
+                .map(|v| Value::String(v.to_string().to_uppercase()))
+                .unwrap_or(Value::Null),
+            ColumnData::Binary(v) => v
+                .as_ref()
+                .map(|v| Value::String(base64::engine::general_purpose::STANDARD.encode(v)))
+                .unwrap_or(Value::Null),
+            // Kept as text so precision isn't lost to floats
+            ColumnData::Numeric(v) => v
+                .map(|v| Value::String(format_numeric(v)))
+                .unwrap_or(Value::Null),
+            ColumnData::Xml(v) => v
+                .as_ref()
+                .map(|v| Value::String(v.to_string()))
+                .unwrap_or(Value::Null),
+            temporal => format_temporal(temporal)
+                .map(Value::String)
+                .unwrap_or(Value::Null),
+        }
+    }
+
+    fn value_to_sql(value: &ColumnData<'static>) -> String {
+        let literal = match value {
+            ColumnData::U8(v) => v.map(|v| v.to_string()),
+            ColumnData::I16(v) => v.map(|v| v.to_string()),
+            ColumnData::I32(v) => v.map(|v| v.to_string()),
+            ColumnData::I64(v) => v.map(|v| v.to_string()),
+            ColumnData::F32(v) => v.map(|v| v.to_string()),
+            ColumnData::F64(v) => v.map(|v| v.to_string()),
+            ColumnData::Bit(v) => v.map(|v| DIALECT.format_bool(v).to_string()),
+            ColumnData::String(v) => v.as_ref().map(|v| DIALECT.quote_string(v)),
+            ColumnData::Guid(v) => v.map(|v| format!("'{}'", v.to_string().to_uppercase())),
+            ColumnData::Binary(v) => v.as_ref().map(|v| DIALECT.quote_bytes(v)),
+            ColumnData::Numeric(v) => v.map(format_numeric),
+            ColumnData::Xml(v) => v.as_ref().map(|v| DIALECT.quote_string(&v.to_string())),
+            temporal => format_temporal(temporal).map(|v| format!("'{}'", v)),
+        };
+        literal.unwrap_or_else(|| "NULL".to_string())
+    }
+
+    fn create_table_statement(qualified_table: &str, columns: &[TableColumn]) -> String {
+        let mut definitions: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                let name = DIALECT.quote_identifier(&column.name);
+                if let Some(expression) = &column.generation_expression {
+                    let persisted = if column.generation_kind.as_deref() == Some("STORED") {
+                        " PERSISTED"
+                    } else {
+                        ""
+                    };
+                    return format!("{} AS {}{}", name, expression, persisted);
+                }
+                let mut definition = format!("{} {}", name, column.data_type);
+                if column.is_identity {
+                    definition.push_str(" IDENTITY");
+                }
+                definition.push_str(if column.is_nullable {
+                    " NULL"
+                } else {
+                    " NOT NULL"
+                });
+                if let Some(default) = &column.column_default {
+                    definition.push_str(" DEFAULT ");
+                    definition.push_str(default);
+                }
+                definition
+            })
+            .collect();
+
+        let primary_key: Vec<String> = columns
+            .iter()
+            .filter(|column| column.is_primary_key)
+            .map(|column| DIALECT.quote_identifier(&column.name))
+            .collect();
+        if !primary_key.is_empty() {
+            definitions.push(format!("PRIMARY KEY ({})", primary_key.join(", ")));
+        }
+
+        format!(
+            "CREATE TABLE {} (\n  {}\n)",
+            qualified_table,
+            definitions.join(",\n  ")
+        )
+    }
+}
+
+/// Spells out a column type with its length, precision or scale, e.g. `nvarchar(max)` or
+/// `decimal(10,2)`, from the parts `INFORMATION_SCHEMA.COLUMNS` reports.
+fn full_type(
+    data_type: &str,
+    character_maximum_length: Option<i32>,
+    numeric_precision: Option<i32>,
+    numeric_scale: Option<i32>,
+    datetime_precision: Option<i32>,
+) -> String {
+    match data_type {
+        "char" | "varchar" | "nchar" | "nvarchar" | "binary" | "varbinary" => {
+            match character_maximum_length {
+                Some(-1) => format!("{}(max)", data_type),
+                Some(length) => format!("{}({})", data_type, length),
+                None => data_type.to_string(),
+            }
+        }
+        "decimal" | "numeric" => match (numeric_precision, numeric_scale) {
+            (Some(precision), Some(scale)) => format!("{}({},{})", data_type, precision, scale),
+            _ => data_type.to_string(),
+        },
+        "datetime2" | "datetimeoffset" | "time" => match datetime_precision {
+            Some(precision) => format!("{}({})", data_type, precision),
+            None => data_type.to_string(),
+        },
+        _ => data_type.to_string(),
+    }
+}
+
+/// Name of a result column's type. TDS sends nullable integers, floats and datetimes as
+/// variable-length types whose size is not exposed, so those are named by their widest
+/// form.
+fn type_name(column_type: ColumnType) -> &'static str {
+    match column_type {
+        ColumnType::Null => "null",
+        ColumnType::Bit | ColumnType::Bitn => "bit",
+        ColumnType::Int1 => "tinyint",
+        ColumnType::Int2 => "smallint",
+        ColumnType::Int4 => "int",
+        ColumnType::Int8 | ColumnType::Intn => "bigint",
+        ColumnType::Float4 => "real",
+        ColumnType::Float8 | ColumnType::Floatn => "float",
+        ColumnType::Money => "money",
+        ColumnType::Money4 => "smallmoney",
+        ColumnType::Datetime4 => "smalldatetime",
+        ColumnType::Datetime | ColumnType::Datetimen => "datetime",
+        ColumnType::Guid => "uniqueidentifier",
+        ColumnType::Decimaln => "decimal",
+        ColumnType::Numericn => "numeric",
+        ColumnType::Daten => "date",
+        ColumnType::Timen => "time",
+        ColumnType::Datetime2 => "datetime2",
+        ColumnType::DatetimeOffsetn => "datetimeoffset",
+        ColumnType::BigVarBin => "varbinary",
+        ColumnType::BigVarChar => "varchar",
+        ColumnType::BigBinary => "binary",
+        ColumnType::BigChar => "char",
+        ColumnType::NVarchar => "nvarchar",
+        ColumnType::NChar => "nchar",
+        ColumnType::Xml => "xml",
+        ColumnType::Udt => "udt",
+        ColumnType::Text => "text",
+        ColumnType::Image => "image",
+        ColumnType::NText => "ntext",
+        ColumnType::SSVariant => "sql_variant",
+    }
+}
+
+/// Formats a decimal value. `Numeric`'s `Display` puts a second sign on the fraction of
+/// negative values.
+fn format_numeric(value: Numeric) -> String {
+    let scale = u32::from(value.scale());
+    let sign = if value.value() < 0 { "-" } else { "" };
+    let digits = value.value().unsigned_abs();
+    if scale == 0 {
+        return format!("{}{}", sign, digits);
+    }
+    let divisor = 10u128.pow(scale);
+    format!(
+        "{}{}.{:0width$}",
+        sign,
+        digits / divisor,
+        digits % divisor,
+        width = scale as usize
+    )
+}
+
+/// Formats date and time values the way SQL Server parses them back; None for other types
+/// and NULL.
+fn format_temporal(value: &ColumnData<'static>) -> Option<String> {
+    match value {
+        ColumnData::DateTime(_) | ColumnData::SmallDateTime(_) | ColumnData::DateTime2(_) => {
+            NaiveDateTime::from_sql(value)
+                .ok()
+                .flatten()
+                .map(|v| v.format("%Y-%m-%d %H:%M:%S%.f").to_string())
+        }
+        ColumnData::Date(_) => NaiveDate::from_sql(value)
+            .ok()
+            .flatten()
+            .map(|v| v.format("%Y-%m-%d").to_string()),
+        ColumnData::Time(_) => NaiveTime::from_sql(value)
+            .ok()
+            .flatten()
+            .map(|v| v.format("%H:%M:%S%.f").to_string()),
+        ColumnData::DateTimeOffset(_) => chrono::DateTime::<FixedOffset>::from_sql(value)
+            .ok()
+            .flatten()
+            .map(|v| v.format("%Y-%m-%d %H:%M:%S%.f %:z").to_string()),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl DatabaseConnection for MssqlConnection {
+    async fn test_connection(&self) -> DbResult<()> {
+        let mut client = self.client.lock().await;
+        Self::run_batch(&mut client, "SELECT 1").await
+    }
+
+    async fn reconnect_if_broken(&self) -> DbResult<bool> {
+        // A query stuck on a dead socket holds the client until it times out, and the next
+        // check looks again
+        let Ok(mut client) = timeout(LIVENESS_TIMEOUT, self.client.lock()).await else {
+            debug!("Session client is busy, skipping liveness check");
+            return Ok(false);
+        };
+        let alive = matches!(
+            timeout(LIVENESS_TIMEOUT, Self::run_batch(&mut client, "SELECT 1")).await,
+            Ok(Ok(()))
+        );
+        if alive {
+            return Ok(false);
+        }
+
+        let database = self.current_database.lock().await.clone();
+        *client = Self::connect(
+            &self.host,
+            self.port,
+            &self.username,
+            &self.password,
+            &database,
+            self.ssl_mode,
+        )
+        .await?;
+
+        debug!("Re-established SQL Server session connection");
+        Ok(true)
+    }
+
+    async fn execute_query(&self, query: &str) -> DbResult<QueryResult> {
+        let mut timer = QueryTimer::start();
+        let mut client = self.client.lock().await;
+        timer.acquired();
+
+        // The driver doesn't report row counts of batches, so a data change is followed by
+        // a query for its count
+        let counts_rows = statement::split_statements(query).last().is_some_and(|s| {
+            matches!(
+                s.leading_keyword(),
+                Some("INSERT") | Some("UPDATE") | Some("DELETE") | Some("MERGE")
+            )
+        });
+        let batch = if counts_rows {
+            format!("{}\nSELECT @@ROWCOUNT AS affected_rows", query)
+        } else {
+            query.to_string()
+        };
+
+        let mut columns: Vec<Column> = Vec::new();
+        let mut raw_rows: Vec<Row> = Vec::with_capacity(1000);
+        let mut row_count = 0;
+        let mut result_sets = 0;
+        let read = async {
+            let mut stream = client.simple_query(batch).await?;
+            timer.executed();
+            while let Some(item) = stream.try_next().await? {
+                match item {
+                    QueryItem::Metadata(metadata) => {
+                        if result_sets == 0 {
+                            columns = metadata.columns().to_vec();
+                        }
+                        result_sets += 1;
+                    }
+                    // Only the first result set is shown
+                    QueryItem::Row(row) if row.result_index() == 0 => {
+                        row_count += 1;
+                        if row_count <= MAX_QUERY_ROWS {
+                            raw_rows.push(row);
+                        }
+                    }
+                    QueryItem::Row(_) => {}
+                }
+            }
+            Ok::<_, TdsError>(())
+        };
+        timeout(self.query_timeout, read)
+            .await
+            .map_err(|_| timed_out())?
+            .map_err(|e| tds_error(e, error_codes::QUERY_ERROR))?;
+        timer.fetched();
+        drop(client);
+
+        let affected_rows = if counts_rows && result_sets == 1 {
+            // The only result is the count
+            let count = raw_rows.pop().and_then(|row| number(&row, "affected_rows"));
+            columns.clear();
+            row_count = 0;
+            count.map(|count| count as u64)
+        } else {
+            None
+        };
+        let truncated = row_count > MAX_QUERY_ROWS;
+
+        let column_names: Vec<String> = columns.iter().map(|c| c.name().to_string()).collect();
+        let column_metadata = Self::column_metadata(&columns);
+        let names = column_names.clone();
+        let rows = convert_rows(raw_rows, move |row: &Row| Self::row_to_json(&names, row)).await?;
+        timer.converted();
+
+        Ok(QueryResult {
+            columns: column_names,
+            rows,
+            row_count,
+            execution_time: timer.execution_time(),
+            truncated,
+            affected_rows,
+            timing: timer.timing(),
+            column_metadata,
+        })
+    }
+
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult> {
+        if !statement::is_query(query) {
+            // Data changes return their count rather than rows; they are only chunked for
+            // the consumer
+            let mut result = self.execute_query(query).await?;
+            let mut chunks = ChunkedRows::new(result.columns.clone(), chunk_size, on_chunk);
+            for row in std::mem::take(&mut result.rows) {
+                if !chunks.push(row) {
+                    break;
+                }
+            }
+            chunks.finish();
+            return Ok(result);
+        }
+
+        let mut timer = QueryTimer::start();
+        let mut client = self.client.lock().await;
+        timer.acquired();
+
+        let mut stream = timeout(self.query_timeout, client.simple_query(query))
+            .await
+            .map_err(|_| timed_out())?
+            .map_err(|e| tds_error(e, error_codes::QUERY_ERROR))?;
+        timer.executed();
+
+        let result_columns = stream
+            .columns()
+            .await
+            .map_err(|e| tds_error(e, error_codes::QUERY_ERROR))?
+            .map(<[Column]>::to_vec)
+            .unwrap_or_default();
+        let columns: Vec<String> = result_columns
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        let column_metadata = Self::column_metadata(&result_columns);
+
+        // Rows left unread when the sink stops are drained before the next query
+        let mut chunks = ChunkedRows::new(columns.clone(), chunk_size, on_chunk);
+        while let Some(item) = stream
+            .try_next()
+            .await
+            .map_err(|e| tds_error(e, error_codes::QUERY_ERROR))?
+        {
+            timer.fetched();
+            let QueryItem::Row(row) = item else {
+                continue;
+            };
+            if row.result_index() > 0 {
+                break;
+            }
+            if !chunks.push(Self::row_to_json(&columns, &row)) {
+                break;
+            }
+            timer.converted();
+        }
+        timer.fetched();
+        let (row_count, truncated) = chunks.finish();
+
+        Ok(QueryResult {
+            columns,
+            rows: Vec::new(),
+            row_count,
+            execution_time: timer.execution_time(),
+            truncated,
+            affected_rows: None,
+            timing: timer.timing(),
+            column_metadata,
+        })
+    }
+
+    async fn list_tables(&self) -> DbResult<Vec<String>> {
+        let mut client = self.client.lock().await;
+        Self::table_names(&mut client).await
+    }
+
+    async fn list_databases(&self) -> DbResult<Vec<String>> {
+        let mut client = self.client.lock().await;
+        let query = "SELECT name FROM sys.databases WHERE HAS_DBACCESS(name) = 1 ORDER BY name";
+        let rows = Self::query_rows(&mut client, query, &[]).await?;
+        Ok(rows.iter().filter_map(|row| text(row, "name")).collect())
+    }
+
+    async fn change_database(&self, database_name: &str) -> DbResult<()> {
+        let mut client = self.client.lock().await;
+        let statement = format!("USE {}", DIALECT.quote_identifier(database_name));
+        Self::run_batch(&mut client, &statement).await?;
+        *self.current_database.lock().await = database_name.to_string();
+        Ok(())
+    }
+
+    async fn set_default_schema(&self, _schema: &str) -> DbResult<()> {
+        Err(QueryError::with_code(
+            "SQL Server sets the default schema per user, not per session",
+            error_codes::QUERY_ERROR,
+        ))
+    }
+
+    async fn get_current_database(&self) -> DbResult<String> {
+        let mut client = self.client.lock().await;
+        let rows = Self::query_rows(&mut client, "SELECT DB_NAME() AS name", &[]).await?;
+        Ok(rows
+            .first()
+            .and_then(|row| text(row, "name"))
+            .unwrap_or_default())
+    }
+
+    async fn get_table_columns(&self, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        let mut client = self.client.lock().await;
+        Self::table_columns(&mut client, table).await
+    }
+
+    async fn get_table_relationships(&self) -> DbResult<Vec<TableRelationship>> {
+        let mut client = self.client.lock().await;
+        let query = "SELECT
+                        SCHEMA_NAME(fk.schema_id) AS from_schema,
+                        OBJECT_NAME(fk.parent_object_id) AS from_table,
+                        COL_NAME(fkc.parent_object_id, fkc.parent_column_id) AS from_column,
+                        OBJECT_SCHEMA_NAME(fk.referenced_object_id) AS to_schema,
+                        OBJECT_NAME(fk.referenced_object_id) AS to_table,
+                        COL_NAME(fkc.referenced_object_id, fkc.referenced_column_id)
+                            AS to_column,
+                        fk.name AS constraint_name
+                     FROM sys.foreign_keys fk
+                     JOIN sys.foreign_key_columns fkc ON fkc.constraint_object_id = fk.object_id
+                     WHERE fk.schema_id = SCHEMA_ID()
+                     ORDER BY from_table, fk.name, fkc.constraint_column_id";
+        let rows = Self::query_rows(&mut client, query, &[]).await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                Some(TableRelationship {
+                    from_schema: text(row, "from_schema"),
+                    from_table: text(row, "from_table")?,
+                    from_column: text(row, "from_column")?,
+                    to_schema: text(row, "to_schema"),
+                    to_table: text(row, "to_table")?,
+                    to_column: text(row, "to_column")?,
+                    constraint_name: text(row, "constraint_name")?,
+                })
+            })
+            .collect())
+    }
+
+    async fn check_privileges(
+        &self,
+        table: &TableRef,
+        operation: TableOperation,
+    ) -> DbResult<bool> {
+        // Dropping a table takes CONTROL on it (or ALTER on its schema)
+        let permission = match operation {
+            TableOperation::Drop => "CONTROL",
+            operation => operation.privilege(),
+        };
+        let name = DIALECT.qualified_name(table);
+        let mut client = self.client.lock().await;
+        let rows = Self::query_rows(
+            &mut client,
+            "SELECT CAST(HAS_PERMS_BY_NAME(@P1, 'OBJECT', @P2) AS bit) AS allowed",
+            &[&name.as_str(), &permission],
+        )
+        .await?;
+        // NULL when the table doesn't exist
+        rows.first()
+            .and_then(|row| row.try_get::<bool, _>("allowed").ok().flatten())
+            .ok_or_else(|| {
+                QueryError::with_code(
+                    format!("Table \"{}\" not found", table),
+                    error_codes::QUERY_ERROR,
+                )
+            })
+    }
+
+    async fn disconnect(&self) -> DbResult<()> {
+        // The connection closes when the client is dropped with the session
+        debug!("SQL Server connection disconnected");
+        Ok(())
+    }
+
+    async fn export_database_with_options(
+        &self,
+        include_drop: bool,
+        include_create: bool,
+        data_mode: &str,
+        selected_tables: &[TableRef],
+        max_insert_size: usize,
+    ) -> DbResult<String> {
+        if !matches!(data_mode, "insert" | "no_data") {
+            return Err(QueryError::with_code(
+                format!(
+                    "SQL Server exports support the insert and no_data modes, not '{}'",
+                    data_mode
+                ),
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let max_insert_size = max_insert_size.clamp(1, MAX_INSERT_ROWS);
+        let mut client = self.client.lock().await;
+
+        let tables_to_export = if selected_tables.is_empty() {
+            Self::table_names(&mut client)
+                .await?
+                .into_iter()
+                .map(TableRef::unqualified)
+                .collect()
+        } else {
+            selected_tables.to_vec()
+        };
+
+        let mut sql_content = String::with_capacity(1024 * 1024);
+        for table in tables_to_export {
+            let qualified_table = DIALECT.qualified_name(&table);
+            let columns = Self::table_columns(&mut client, &table).await?;
+            if columns.is_empty() {
+                return Err(QueryError::with_code(
+                    format!("Table \"{}\" not found", table),
+                    error_codes::QUERY_ERROR,
+                ));
+            }
+            sql_content.push_str(&format!("\n-- Table: {}\n", table));
+
+            if include_drop {
+                sql_content.push_str(&format!("DROP TABLE IF EXISTS {};\n", qualified_table));
+            }
+
+            if include_create {
+                sql_content.push_str(&Self::create_table_statement(&qualified_table, &columns));
+                sql_content.push_str(";\n\n");
+            }
+
+            if data_mode == "no_data" {
+                continue;
+            }
+
+            // Computed columns can't be written
+            let stored: Vec<&TableColumn> = columns.iter().filter(|c| !c.is_generated).collect();
+            let column_list = stored
+                .iter()
+                .map(|c| DIALECT.quote_identifier(&c.name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let insert_prefix = format!(
+                "INSERT INTO {} ({}) VALUES\n  ",
+                qualified_table, column_list
+            );
+            let has_identity = stored.iter().any(|c| c.is_identity);
+            if has_identity {
+                sql_content.push_str(&format!("SET IDENTITY_INSERT {} ON;\n", qualified_table));
+            }
+
+            let query = format!("SELECT {} FROM {}", column_list, qualified_table);
+            let mut stream = timeout(DEFAULT_QUERY_TIMEOUT, client.simple_query(query))
+                .await
+                .map_err(|_| timed_out())?
+                .map_err(|e| tds_error(e, error_codes::QUERY_ERROR))?;
+            let mut row_buffer: Vec<String> = Vec::with_capacity(max_insert_size);
+            while let Some(item) = stream
+                .try_next()
+                .await
+                .map_err(|e| tds_error(e, error_codes::QUERY_ERROR))?
+            {
+                let QueryItem::Row(row) = item else {
+                    continue;
+                };
+                let values: Vec<String> = row
+                    .cells()
+                    .map(|(_, value)| Self::value_to_sql(value))
+                    .collect();
+                row_buffer.push(format!("({})", values.join(", ")));
+
+                if row_buffer.len() >= max_insert_size {
+                    sql_content.push_str(&insert_prefix);
+                    sql_content.push_str(&row_buffer.join(",\n  "));
+                    sql_content.push_str(";\n");
+                    row_buffer.clear();
+                }
+            }
+            if !row_buffer.is_empty() {
+                sql_content.push_str(&insert_prefix);
+                sql_content.push_str(&row_buffer.join(",\n  "));
+                sql_content.push_str(";\n");
+            }
+
+            if has_identity {
+                sql_content.push_str(&format!("SET IDENTITY_INSERT {} OFF;\n", qualified_table));
+            }
+            sql_content.push('\n');
+        }
+
+        Ok(sql_content)
+    }
+
+    async fn update_cell(
+        &self,
+        table: &TableRef,
+        column_name: &str,
+        new_value: Option<&str>,
+        primary_key_column: &str,
+        primary_key_value: &str,
+    ) -> DbResult<String> {
+        let logged_query = format!(
+            "UPDATE {} SET {} = {} WHERE {} = {}",
+            DIALECT.qualified_name(table),
+            DIALECT.quote_identifier(column_name),
+            new_value
+                .map(|value| DIALECT.quote_string(value))
+                .unwrap_or_else(|| "NULL".to_string()),
+            DIALECT.quote_identifier(primary_key_column),
+            DIALECT.quote_string(primary_key_value)
+        );
+
+        // SQL Server converts the text values to the column types
+        let query = format!(
+            "UPDATE {} SET {} = @P1 WHERE {} = @P2",
+            DIALECT.qualified_name(table),
+            DIALECT.quote_identifier(column_name),
+            DIALECT.quote_identifier(primary_key_column)
+        );
+        let mut client = self.client.lock().await;
+        timeout(
+            self.query_timeout,
+            client.execute(query, &[&new_value, &primary_key_value]),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Update timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| tds_error(e, error_codes::QUERY_ERROR))?;
+
+        Ok(logged_query)
+    }
+
+    async fn insert_rows(
+        &self,
+        table: &TableRef,
+        columns: &[String],
+        rows: &[Vec<Option<String>>],
+    ) -> DbResult<u64> {
+        let column_list = columns
+            .iter()
+            .map(|name| DIALECT.quote_identifier(name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let batch_rows =
+            (MAX_PARAMETERS / columns.len().max(1)).clamp(1, super::bulk_insert::INSERT_BATCH_ROWS);
+
+        let mut client = self.client.lock().await;
+        Self::run_batch(&mut client, "BEGIN TRANSACTION").await?;
+
+        let mut inserted = 0;
+        for batch in rows.chunks(batch_rows) {
+            let mut parameter = 0;
+            let values = batch
+                .iter()
+                .map(|row| {
+                    let placeholders: Vec<String> = row
+                        .iter()
+                        .map(|_| {
+                            parameter += 1;
+                            format!("@P{}", parameter)
+                        })
+                        .collect();
+                    format!("({})", placeholders.join(", "))
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let query = format!(
+                "INSERT INTO {} ({}) VALUES {}",
+                DIALECT.qualified_name(table),
+                column_list,
+                values
+            );
+            let values: Vec<Option<&str>> = batch.iter().flatten().map(Option::as_deref).collect();
+            let params: Vec<&dyn ToSql> = values.iter().map(|v| v as &dyn ToSql).collect();
+
+            let result = timeout(self.query_timeout, client.execute(query, &params))
+                .await
+                .map_err(|_| QueryError::with_code("Insert timed out", error_codes::TIMEOUT_ERROR))
+                .and_then(|result| result.map_err(|e| tds_error(e, error_codes::QUERY_ERROR)));
+            match result {
+                Ok(result) => inserted += result.total(),
+                Err(e) => {
+                    if let Err(rollback) =
+                        Self::run_batch(&mut client, "IF @@TRANCOUNT > 0 ROLLBACK").await
+                    {
+                        warn!("Failed to roll back insert: {}", rollback.message);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Self::run_batch(&mut client, "COMMIT").await?;
+        debug!("Inserted {} rows into {}", inserted, table);
+        Ok(inserted)
+    }
+
+    async fn get_routine_definition(&self, routine: &TableRef) -> DbResult<String> {
+        self.object_definition(routine, "Routine").await
+    }
+
+    async fn get_view_definition(&self, view: &TableRef) -> DbResult<String> {
+        self.object_definition(view, "View").await
+    }
+
+    async fn alter_view(&self, view: &TableRef, query: &str) -> DbResult<()> {
+        // CREATE OR ALTER fails without touching the view if the query doesn't compile
+        let statement = format!(
+            "CREATE OR ALTER VIEW {} AS\n{}",
+            DIALECT.qualified_name(view),
+            query
+        );
+        let mut client = self.client.lock().await;
+        Self::run_batch(&mut client, &statement).await
+    }
+
+    async fn get_text_search_configs(&self) -> DbResult<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn create_database(
+        &self,
+        name: &str,
+        encoding: Option<&str>,
+        owner: Option<&str>,
+    ) -> DbResult<String> {
+        let mut statement = format!("CREATE DATABASE {}", DIALECT.quote_identifier(name));
+        // SQL Server picks the encoding with the collation
+        if let Some(collation) = encoding {
+            if !collation
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                return Err(QueryError::with_code(
+                    format!("Invalid collation name '{}'", collation),
+                    error_codes::QUERY_ERROR,
+                ));
+            }
+            statement.push_str(&format!(" COLLATE {}", collation));
+        }
+        if let Some(owner) = owner {
+            statement.push_str(&format!(
+                ";\nALTER AUTHORIZATION ON DATABASE::{} TO {}",
+                DIALECT.quote_identifier(name),
+                DIALECT.quote_identifier(owner)
+            ));
+        }
+
+        let mut client = self.client.lock().await;
+        Self::run_batch(&mut client, &statement).await?;
+        debug!("Created database {}", name);
+        Ok(statement)
+    }
+
+    async fn drop_database(&self, name: &str) -> DbResult<String> {
+        let current = self.current_database.lock().await.clone();
+        check_droppable_database(name, &current, &SYSTEM_DATABASES)?;
+
+        let statement = format!("DROP DATABASE {}", DIALECT.quote_identifier(name));
+        let mut client = self.client.lock().await;
+        Self::run_batch(&mut client, &statement).await?;
+        debug!("Dropped database {}", name);
+        Ok(statement)
+    }
+
+    async fn build_sample_query(
+        &self,
+        table: &TableRef,
+        n: usize,
+        _method: SampleMethod,
+    ) -> DbResult<String> {
+        // T-SQL has no LIMIT, and shuffles with NEWID()
+        Ok(format!(
+            "SELECT TOP {} * FROM (SELECT TOP {} * FROM {}) sample ORDER BY NEWID()",
+            sampling::sample_size(n),
+            RANDOM_SCAN_LIMIT,
+            DIALECT.qualified_name(table)
+        ))
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        DIALECT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_types_and_literals() {
+        assert_eq!(
+            full_type("nvarchar", Some(-1), None, None, None),
+            "nvarchar(max)"
+        );
+        assert_eq!(
+            full_type("decimal", None, Some(10), Some(2), None),
+            "decimal(10,2)"
+        );
+        assert_eq!(
+            full_type("datetime2", None, None, None, Some(7)),
+            "datetime2(7)"
+        );
+        assert_eq!(full_type("int", None, Some(10), Some(0), None), "int");
+
+        let numeric = ColumnData::Numeric(Some(Numeric::new_with_scale(-150, 2)));
+        assert_eq!(MssqlConnection::value_to_json(&numeric), "-1.50");
+        assert_eq!(MssqlConnection::value_to_sql(&numeric), "-1.50");
+        assert_eq!(
+            MssqlConnection::value_to_sql(&ColumnData::String(Some("it's".into()))),
+            "N'it''s'"
+        );
+        assert_eq!(
+            MssqlConnection::value_to_sql(&ColumnData::Bit(Some(true))),
+            "1"
+        );
+        assert_eq!(
+            MssqlConnection::value_to_sql(&ColumnData::I32(None)),
+            "NULL"
+        );
+    }
+}
@@ -0,0 +1,1334 @@
+//! Oracle Database through ODPI-C, with the `oracle` crate. It loads the Oracle Client
+//! libraries (Instant Client is enough) when the first connection is made.
+//!
+//! The driver's calls block, so they run on the blocking thread pool with the session
+//! connection locked for the whole call. A connection is made to one service; its schemas
+//! are the app's schemas.
+
+use super::column_metadata::ColumnMetadata;
+use super::connection::{
+    convert_rows, error_codes, ChunkedRows, DatabaseConnection, DbResult, QueryError, QueryResult,
+    QueryTimer, RowChunkSink, SslMode, TableColumn, TableOperation, TableRef, TableRelationship,
+    DEFAULT_QUERY_TIMEOUT, LIVENESS_TIMEOUT, MAX_QUERY_ROWS,
+};
+use super::sampling::{self, SampleMethod, RANDOM_SCAN_LIMIT};
+use super::sql_dialect::SqlDialect;
+use super::statement::{self, Statement};
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use oracle::sql_type::{OracleType, ToSql};
+use oracle::{ColumnInfo, Connection as Session, Connector, Row, SqlValue};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex, OwnedMutexGuard};
+use tokio::time::timeout;
+use tracing::{debug, warn};
+
+const DIALECT: SqlDialect = SqlDialect::Oracle;
+
+/// ODPI-C error raised when a call runs past the call timeout.
+const DPI_CALL_TIMEOUT: i32 = 1067;
+
+/// Rows buffered between the fetching thread and `execute_query_streaming`.
+const STREAM_BUFFER_ROWS: usize = 1_000;
+
+/// Date and time formats of the session, matching how values are shown so edited cells
+/// convert back.
+const SESSION_FORMATS: &str = "ALTER SESSION SET
+    NLS_DATE_FORMAT = 'YYYY-MM-DD HH24:MI:SS'
+    NLS_TIMESTAMP_FORMAT = 'YYYY-MM-DD HH24:MI:SS.FF'
+    NLS_TIMESTAMP_TZ_FORMAT = 'YYYY-MM-DD HH24:MI:SS.FF TZH:TZM'";
+
+/// Schema names are compared with this when a `TableRef` has none.
+const CURRENT_SCHEMA: &str = "SYS_CONTEXT('USERENV', 'CURRENT_SCHEMA')";
+
+fn oracle_error(err: oracle::Error, code: &str) -> QueryError {
+    if err.dpi_code() == Some(DPI_CALL_TIMEOUT) {
+        return QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR);
+    }
+    match err.db_error() {
+        // The message starts with the ORA- number
+        Some(db_error) if db_error.offset() > 0 => QueryError::with_code(db_error.message(), code)
+            .with_detail(format!("Error at position {}", db_error.offset())),
+        Some(db_error) => QueryError::with_code(db_error.message(), code),
+        None => QueryError::with_code(err.to_string(), code),
+    }
+}
+
+fn query_error(err: oracle::Error) -> QueryError {
+    oracle_error(err, error_codes::QUERY_ERROR)
+}
+
+fn text(row: &Row, column: &str) -> Option<String> {
+    row.get::<_, Option<String>>(column).ok().flatten()
+}
+
+fn number(row: &Row, column: &str) -> Option<i64> {
+    row.get::<_, Option<i64>>(column).ok().flatten()
+}
+
+fn flag(row: &Row, column: &str) -> bool {
+    text(row, column).as_deref() == Some("YES")
+}
+
+/// Whether a script is a PL/SQL block or unit, which is sent whole because its statements
+/// end with semicolons too.
+fn is_plsql(statement: &Statement) -> bool {
+    let mut keywords = statement.keywords.iter().map(String::as_str);
+    match keywords.next() {
+        Some("BEGIN" | "DECLARE") => true,
+        Some("CREATE") => keywords
+            .find(|k| !matches!(*k, "OR" | "REPLACE" | "EDITIONABLE" | "NONEDITIONABLE"))
+            .is_some_and(|k| {
+                matches!(k, "PROCEDURE" | "FUNCTION" | "PACKAGE" | "TRIGGER" | "TYPE")
+            }),
+        _ => false,
+    }
+}
+
+/// EZConnect string for `service` on `host`, over TLS (`tcps`) when `ssl_mode` asks for it.
+/// The client checks the server certificate against its wallet; the host name check is
+/// turned off below verify-full.
+fn connect_string(host: &str, port: u16, service: &str, ssl_mode: SslMode) -> String {
+    if !ssl_mode.uses_tls() {
+        return format!("//{}:{}/{}", host, port, service);
+    }
+    let dn_match = if ssl_mode.verifies_hostname() {
+        "yes"
+    } else {
+        "no"
+    };
+    format!(
+        "tcps://{}:{}/{}?ssl_server_dn_match={}",
+        host, port, service, dn_match
+    )
+}
+
+/// Oracle database connection implementation.
+pub struct OracleConnection {
+    /// The session connection. Replaced by `reconnect_if_broken` when it stops responding.
+    session: Arc<Mutex<Session>>,
+    service: String,
+    // Connection parameters stored for reconnection
+    host: String,
+    port: u16,
+    username: String,
+    password: String,
+    ssl_mode: SslMode,
+    /// Call timeout of the session, which bounds every round trip to the server.
+    query_timeout: Duration,
+}
+
+impl OracleConnection {
+    pub async fn new(
+        host: &str,
+        port: u16,
+        user: &str,
+        password: &str,
+        service: &str,
+        ssl_mode: &str,
+        query_timeout: Option<Duration>,
+    ) -> DbResult<Self> {
+        let ssl_mode = SslMode::parse(ssl_mode)?;
+        let query_timeout = query_timeout.unwrap_or(DEFAULT_QUERY_TIMEOUT);
+        let connection = OracleConnection {
+            session: Arc::new(Mutex::new(
+                Self::connect_blocking(
+                    host.to_string(),
+                    port,
+                    user.to_string(),
+                    password.to_string(),
+                    service.to_string(),
+                    ssl_mode,
+                    query_timeout,
+                )
+                .await?,
+            )),
+            service: service.to_string(),
+            host: host.to_string(),
+            port,
+            username: user.to_string(),
+            password: password.to_string(),
+            ssl_mode,
+            query_timeout,
+        };
+        Ok(connection)
+    }
+
+    async fn connect_blocking(
+        host: String,
+        port: u16,
+        user: String,
+        password: String,
+        service: String,
+        ssl_mode: SslMode,
+        call_timeout: Duration,
+    ) -> DbResult<Session> {
+        let connect = tokio::task::spawn_blocking(move || {
+            Self::connect(
+                &host,
+                port,
+                &user,
+                &password,
+                &service,
+                ssl_mode,
+                call_timeout,
+            )
+        });
+        timeout(DEFAULT_QUERY_TIMEOUT, connect)
+            .await
+            .map_err(|_| QueryError::with_code("Connection timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| {
+                QueryError::with_code(
+                    format!("Connection task failed: {}", e),
+                    error_codes::CONNECTION_ERROR,
+                )
+            })?
+    }
+
+    /// Connects with TLS as `ssl_mode` asks, falling back to an unencrypted connection
+    /// for `preferred`. Calls on the session time out after `call_timeout`.
+    fn connect(
+        host: &str,
+        port: u16,
+        user: &str,
+        password: &str,
+        service: &str,
+        ssl_mode: SslMode,
+        call_timeout: Duration,
+    ) -> DbResult<Session> {
+        if ssl_mode.uses_tls() {
+            match Self::connect_with(host, port, user, password, service, ssl_mode, call_timeout) {
+                Ok(session) => {
+                    debug!("Oracle TLS connection established");
+                    return Ok(session);
+                }
+                Err(e) if ssl_mode.allows_fallback() => {
+                    warn!(
+                        "TLS connection failed, falling back to non-TLS: {}",
+                        e.message
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let session = Self::connect_with(
+            host,
+            port,
+            user,
+            password,
+            service,
+            SslMode::Disabled,
+            call_timeout,
+        )?;
+        debug!("Oracle non-TLS connection established");
+        Ok(session)
+    }
+
+    fn connect_with(
+        host: &str,
+        port: u16,
+        user: &str,
+        password: &str,
+        service: &str,
+        ssl_mode: SslMode,
+        call_timeout: Duration,
+    ) -> DbResult<Session> {
+        let connect = || {
+            let mut session = Connector::new(
+                user,
+                password,
+                connect_string(host, port, service, ssl_mode),
+            )
+            .connect()?;
+            // Statements commit like on the other drivers; insert_rows groups its batches
+            session.set_autocommit(true);
+            session.set_call_timeout(Some(call_timeout))?;
+            session.set_module("BloatSQL")?;
+            session.execute(SESSION_FORMATS, &[])?;
+            Ok(session)
+        };
+        connect().map_err(|e| oracle_error(e, error_codes::CONNECTION_ERROR))
+    }
+
+    /// Runs `work` with the session connection on the blocking thread pool.
+    async fn with_session<T, F>(&self, work: F) -> DbResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Session) -> DbResult<T> + Send + 'static,
+    {
+        let session = self.session.clone().lock_owned().await;
+        Self::run_blocking(session, work).await
+    }
+
+    async fn run_blocking<T, F>(mut session: OwnedMutexGuard<Session>, work: F) -> DbResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Session) -> DbResult<T> + Send + 'static,
+    {
+        tokio::task::spawn_blocking(move || work(&mut session))
+            .await
+            .map_err(|e| {
+                QueryError::with_code(
+                    format!("Oracle call failed: {}", e),
+                    error_codes::QUERY_ERROR,
+                )
+            })?
+    }
+
+    fn table_names(session: &Session) -> DbResult<Vec<String>> {
+        let query = format!(
+            "SELECT TABLE_NAME FROM ALL_TABLES
+             WHERE OWNER = {} AND NESTED = 'NO' AND SECONDARY = 'N' AND DROPPED = 'NO'
+             ORDER BY TABLE_NAME",
+            CURRENT_SCHEMA
+        );
+        let rows = session
+            .query_as::<String>(&query, &[])
+            .map_err(query_error)?;
+        rows.collect::<oracle::Result<Vec<_>>>()
+            .map_err(query_error)
+    }
+
+    fn table_columns(session: &Session, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        // DATA_DEFAULT is a LONG, which can only be read as it is
+        let query = format!(
+            "SELECT
+                c.COLUMN_NAME, c.DATA_TYPE, c.DATA_LENGTH, c.CHAR_LENGTH, c.CHAR_USED,
+                c.DATA_PRECISION, c.DATA_SCALE, c.NULLABLE, c.DATA_DEFAULT,
+                c.IDENTITY_COLUMN, c.VIRTUAL_COLUMN,
+                (SELECT i.GENERATION_TYPE FROM ALL_TAB_IDENTITY_COLS i
+                 WHERE i.OWNER = c.OWNER AND i.TABLE_NAME = c.TABLE_NAME
+                    AND i.COLUMN_NAME = c.COLUMN_NAME) AS GENERATION_TYPE,
+                CASE WHEN EXISTS (
+                    SELECT 1 FROM ALL_CONSTRAINTS k
+                    JOIN ALL_CONS_COLUMNS kc
+                        ON kc.OWNER = k.OWNER AND kc.CONSTRAINT_NAME = k.CONSTRAINT_NAME
+                    WHERE k.CONSTRAINT_TYPE = 'P' AND k.OWNER = c.OWNER
+                        AND k.TABLE_NAME = c.TABLE_NAME AND kc.COLUMN_NAME = c.COLUMN_NAME
+                ) THEN 'YES' ELSE 'NO' END AS IS_PRIMARY
+             FROM ALL_TAB_COLS c
+             WHERE c.OWNER = NVL(:schema_name, {}) AND c.TABLE_NAME = :table_name
+                AND c.HIDDEN_COLUMN = 'NO'
+             ORDER BY c.COLUMN_ID",
+            CURRENT_SCHEMA
+        );
+        let rows = session
+            .query_named(
+                &query,
+                &[
+                    ("schema_name", &table.schema.as_deref()),
+                    ("table_name", &table.name.as_str()),
+                ],
+            )
+            .map_err(query_error)?;
+
+        let mut columns = Vec::new();
+        for row in rows {
+            let row = row.map_err(query_error)?;
+            let (Some(name), Some(data_type)) =
+                (text(&row, "COLUMN_NAME"), text(&row, "DATA_TYPE"))
+            else {
+                continue;
+            };
+            let is_identity = flag(&row, "IDENTITY_COLUMN");
+            let is_generated = flag(&row, "VIRTUAL_COLUMN");
+            // Oracle keeps the expression of virtual columns and the sequence of identity
+            // columns as their default
+            let default = text(&row, "DATA_DEFAULT")
+                .map(|default| default.trim().to_string())
+                .filter(|default| !default.is_empty());
+            let character_maximum_length = match data_type.as_str() {
+                "CHAR" | "NCHAR" | "VARCHAR2" | "NVARCHAR2" => number(&row, "CHAR_LENGTH"),
+                "RAW" => number(&row, "DATA_LENGTH"),
+                _ => None,
+            };
+            columns.push(TableColumn {
+                data_type: full_type(
+                    &data_type,
+                    character_maximum_length,
+                    text(&row, "CHAR_USED").as_deref() == Some("C"),
+                    number(&row, "DATA_PRECISION"),
+                    number(&row, "DATA_SCALE"),
+                ),
+                name,
+                is_nullable: text(&row, "NULLABLE").as_deref() == Some("Y"),
+                is_primary_key: flag(&row, "IS_PRIMARY"),
+                column_default: default.clone().filter(|_| !is_identity && !is_generated),
+                character_maximum_length,
+                numeric_precision: number(&row, "DATA_PRECISION"),
+                is_identity,
+                identity_generation: text(&row, "GENERATION_TYPE"),
+                is_generated,
+                generation_expression: default.filter(|_| is_generated),
+                generation_kind: is_generated.then(|| "VIRTUAL".to_string()),
+                allowed_values: None,
+            });
+        }
+        Ok(columns)
+    }
+
+    /// Runs one statement (or PL/SQL unit), keeping at most `MAX_QUERY_ROWS` rows.
+    fn run_statement(session: &Session, sql: &str, timer: &mut QueryTimer) -> DbResult<Fetched> {
+        let mut stmt = session.statement(sql).build().map_err(query_error)?;
+        if !stmt.is_query() {
+            stmt.execute(&[]).map_err(query_error)?;
+            timer.executed();
+            let affected_rows = if stmt.is_dml() {
+                Some(stmt.row_count().map_err(query_error)?)
+            } else {
+                None
+            };
+            return Ok(Fetched {
+                columns: Vec::new(),
+                rows: Vec::new(),
+                row_count: 0,
+                affected_rows,
+            });
+        }
+
+        let result_set = stmt.query(&[]).map_err(query_error)?;
+        timer.executed();
+        let columns = result_set.column_info().to_vec();
+        let mut rows = Vec::new();
+        let mut row_count = 0;
+        for row in result_set {
+            let row = row.map_err(query_error)?;
+            row_count += 1;
+            if rows.len() < MAX_QUERY_ROWS {
+                rows.push(row);
+            }
+        }
+        timer.fetched();
+        Ok(Fetched {
+            columns,
+            rows,
+            row_count,
+            affected_rows: None,
+        })
+    }
+
+    fn column_metadata(columns: &[ColumnInfo]) -> Vec<ColumnMetadata> {
+        columns
+            .iter()
+            .map(|column| {
+                let (precision, scale) = match column.oracle_type() {
+                    // Precision 0 is an unconstrained NUMBER
+                    OracleType::Number(precision, scale) if *precision > 0 => {
+                        (Some(u32::from(*precision)), u32::try_from(*scale).ok())
+                    }
+                    _ => (None, None),
+                };
+                ColumnMetadata {
+                    name: column.name().to_string(),
+                    type_name: type_name(column.oracle_type()).to_string(),
+                    numeric: matches!(
+                        column.oracle_type(),
+                        OracleType::Number(..)
+                            | OracleType::Float(_)
+                            | OracleType::BinaryFloat
+                            | OracleType::BinaryDouble
+                            | OracleType::Int64
+                    ),
+                    nullable: Some(column.nullable()),
+                    precision,
+                    scale,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn row_to_json(row: &Row) -> serde_json::Value {
+        let mut row_map = serde_json::Map::with_capacity(row.column_info().len());
+        for (column, value) in row.column_info().iter().zip(row.sql_values()) {
+            row_map.insert(
+                column.name().to_string(),
+                Self::value_to_json(value, column.oracle_type()),
+            );
+        }
+        serde_json::Value::Object(row_map)
+    }
+
+    fn value_to_json(value: &SqlValue, oracle_type: &OracleType) -> serde_json::Value {
+        use serde_json::Value;
+        if value.is_null().unwrap_or(true) {
+            return Value::Null;
+        }
+        let formatted = |format: &str| {
+            value
+                .get::<NaiveDateTime>()
+                .map(|v| Value::String(v.format(format).to_string()))
+                .unwrap_or(Value::Null)
+        };
+        match oracle_type {
+            // NUMBER covers integers and decimals alike; values that aren't integers stay
+            // text so precision isn't lost to floats
+            OracleType::Number(..) | OracleType::Float(_) | OracleType::Int64 => {
+                match value.get::<String>() {
+                    Ok(text) => text
+                        .parse::<i64>()
+                        .map(Value::from)
+                        .unwrap_or(Value::String(text)),
+                    Err(_) => Value::Null,
+                }
+            }
+            OracleType::BinaryFloat | OracleType::BinaryDouble => value
+                .get::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            OracleType::Boolean => value.get::<bool>().map(Value::Bool).unwrap_or(Value::Null),
+            OracleType::Date => formatted("%Y-%m-%d %H:%M:%S"),
+            OracleType::Timestamp(_) | OracleType::TimestampLTZ(_) => {
+                formatted("%Y-%m-%d %H:%M:%S%.f")
+            }
+            OracleType::TimestampTZ(_) => value
+                .get::<DateTime<FixedOffset>>()
+                .map(|v| Value::String(v.format("%Y-%m-%d %H:%M:%S%.f %:z").to_string()))
+                .unwrap_or(Value::Null),
+            OracleType::Raw(_) | OracleType::BLOB | OracleType::LongRaw => value
+                .get::<Vec<u8>>()
+                .map(|v| Value::String(base64::engine::general_purpose::STANDARD.encode(v)))
+                .unwrap_or(Value::Null),
+            _ => Value::String(value.to_string()),
+        }
+    }
+
+    fn value_to_sql(value: &SqlValue, oracle_type: &OracleType) -> String {
+        if value.is_null().unwrap_or(true) {
+            return "NULL".to_string();
+        }
+        let timestamp = |format: &str| {
+            value
+                .get::<NaiveDateTime>()
+                .map(|v| format!("TIMESTAMP '{}'", v.format(format)))
+                .unwrap_or_else(|_| "NULL".to_string())
+        };
+        match oracle_type {
+            OracleType::Number(..)
+            | OracleType::Float(_)
+            | OracleType::Int64
+            | OracleType::BinaryFloat
+            | OracleType::BinaryDouble => value.to_string(),
+            OracleType::Boolean => value
+                .get::<bool>()
+                .map(|v| DIALECT.format_bool(v).to_string())
+                .unwrap_or_else(|_| "NULL".to_string()),
+            OracleType::Date => timestamp("%Y-%m-%d %H:%M:%S"),
+            OracleType::Timestamp(_) | OracleType::TimestampLTZ(_) => {
+                timestamp("%Y-%m-%d %H:%M:%S%.f")
+            }
+            OracleType::TimestampTZ(_) => value
+                .get::<DateTime<FixedOffset>>()
+                .map(|v| format!("TIMESTAMP '{}'", v.format("%Y-%m-%d %H:%M:%S%.f %:z")))
+                .unwrap_or_else(|_| "NULL".to_string()),
+            OracleType::IntervalDS(leading, fraction) => format!(
+                "INTERVAL '{}' DAY({}) TO SECOND({})",
+                value, leading, fraction
+            ),
+            OracleType::IntervalYM(leading) => {
+                format!("INTERVAL '{}' YEAR({}) TO MONTH", value, leading)
+            }
+            OracleType::Raw(_) | OracleType::BLOB | OracleType::LongRaw => value
+                .get::<Vec<u8>>()
+                .map(|v| DIALECT.quote_bytes(&v))
+                .unwrap_or_else(|_| "NULL".to_string()),
+            _ => DIALECT.quote_string(&value.to_string()),
+        }
+    }
+}
+
+/// A statement's result before conversion.
+struct Fetched {
+    columns: Vec<ColumnInfo>,
+    rows: Vec<Row>,
+    row_count: usize,
+    affected_rows: Option<u64>,
+}
+
+enum StreamItem {
+    Columns(Vec<ColumnInfo>),
+    Row(Row),
+}
+
+/// Spells out a column type from the parts `ALL_TAB_COLS` reports, e.g. `NUMBER(10,2)` or
+/// `VARCHAR2(20 CHAR)`. Date, timestamp and interval types come with their precision.
+fn full_type(
+    data_type: &str,
+    character_maximum_length: Option<i64>,
+    char_semantics: bool,
+    precision: Option<i64>,
+    scale: Option<i64>,
+) -> String {
+    match (data_type, character_maximum_length) {
+        ("VARCHAR2" | "CHAR", Some(length)) if char_semantics => {
+            format!("{}({} CHAR)", data_type, length)
+        }
+        ("VARCHAR2" | "CHAR" | "NVARCHAR2" | "NCHAR" | "RAW", Some(length)) => {
+            format!("{}({})", data_type, length)
+        }
+        ("NUMBER", _) => match (precision, scale) {
+            (Some(precision), Some(0)) => format!("NUMBER({})", precision),
+            (Some(precision), Some(scale)) => format!("NUMBER({},{})", precision, scale),
+            // NUMBER(*,0)
+            (None, Some(0)) => "INTEGER".to_string(),
+            _ => "NUMBER".to_string(),
+        },
+        ("FLOAT", _) => match precision {
+            Some(precision) => format!("FLOAT({})", precision),
+            None => "FLOAT".to_string(),
+        },
+        _ => data_type.to_string(),
+    }
+}
+
+/// Name of a result column's type, without its length or precision.
+fn type_name(oracle_type: &OracleType) -> &'static str {
+    match oracle_type {
+        OracleType::Varchar2(_) => "VARCHAR2",
+        OracleType::NVarchar2(_) => "NVARCHAR2",
+        OracleType::Char(_) => "CHAR",
+        OracleType::NChar(_) => "NCHAR",
+        OracleType::Rowid => "ROWID",
+        OracleType::Raw(_) => "RAW",
+        OracleType::BinaryFloat => "BINARY_FLOAT",
+        OracleType::BinaryDouble => "BINARY_DOUBLE",
+        OracleType::Number(..) | OracleType::Int64 | OracleType::UInt64 => "NUMBER",
+        OracleType::Float(_) => "FLOAT",
+        OracleType::Date => "DATE",
+        OracleType::Timestamp(_) => "TIMESTAMP",
+        OracleType::TimestampTZ(_) => "TIMESTAMP WITH TIME ZONE",
+        OracleType::TimestampLTZ(_) => "TIMESTAMP WITH LOCAL TIME ZONE",
+        OracleType::IntervalDS(..) => "INTERVAL DAY TO SECOND",
+        OracleType::IntervalYM(_) => "INTERVAL YEAR TO MONTH",
+        OracleType::CLOB => "CLOB",
+        OracleType::NCLOB => "NCLOB",
+        OracleType::BLOB => "BLOB",
+        OracleType::BFILE => "BFILE",
+        OracleType::RefCursor => "REF CURSOR",
+        OracleType::Boolean => "BOOLEAN",
+        OracleType::Object(_) => "OBJECT",
+        OracleType::Long => "LONG",
+        OracleType::LongRaw => "LONG RAW",
+        OracleType::Json => "JSON",
+        OracleType::Xml => "XMLTYPE",
+    }
+}
+
+#[async_trait]
+impl DatabaseConnection for OracleConnection {
+    async fn test_connection(&self) -> DbResult<()> {
+        self.with_session(|session| session.ping().map_err(query_error))
+            .await
+    }
+
+    async fn reconnect_if_broken(&self) -> DbResult<bool> {
+        // A query stuck on a dead socket holds the session until the call timeout, and the
+        // next check looks again
+        let Ok(session) = timeout(LIVENESS_TIMEOUT, self.session.clone().lock_owned()).await else {
+            debug!("Session connection is busy, skipping liveness check");
+            return Ok(false);
+        };
+        let (host, port, username, password, service, ssl_mode, call_timeout) = (
+            self.host.clone(),
+            self.port,
+            self.username.clone(),
+            self.password.clone(),
+            self.service.clone(),
+            self.ssl_mode,
+            self.query_timeout,
+        );
+        let reconnected = Self::run_blocking(session, move |session| {
+            let alive = session
+                .set_call_timeout(Some(LIVENESS_TIMEOUT))
+                .and_then(|_| session.ping())
+                .and_then(|_| session.set_call_timeout(Some(call_timeout)))
+                .is_ok();
+            if alive {
+                return Ok(false);
+            }
+            *session = Self::connect(
+                &host,
+                port,
+                &username,
+                &password,
+                &service,
+                ssl_mode,
+                call_timeout,
+            )?;
+            Ok(true)
+        })
+        .await?;
+
+        if reconnected {
+            debug!("Re-established Oracle session connection");
+        }
+        Ok(reconnected)
+    }
+
+    async fn execute_query(&self, query: &str) -> DbResult<QueryResult> {
+        let mut timer = QueryTimer::start();
+        let session = self.session.clone().lock_owned().await;
+        timer.acquired();
+
+        // Scripts run statement by statement, showing the result of the last one
+        let statements = statement::split_statements(query);
+        let scripts: Vec<String> = match statements.first() {
+            Some(first) if is_plsql(first) => {
+                vec![query.trim().trim_end_matches('/').trim_end().to_string()]
+            }
+            _ => statements.into_iter().map(|s| s.sql).collect(),
+        };
+        let (fetched, mut timer) = Self::run_blocking(session, move |session| {
+            let mut fetched = None;
+            for sql in &scripts {
+                fetched = Some(Self::run_statement(session, sql, &mut timer)?);
+            }
+            Ok((fetched, timer))
+        })
+        .await?;
+        let Some(fetched) = fetched else {
+            return Err(QueryError::with_code(
+                "Query is empty",
+                error_codes::QUERY_ERROR,
+            ));
+        };
+
+        let columns: Vec<String> = fetched
+            .columns
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        let column_metadata = Self::column_metadata(&fetched.columns);
+        let rows = convert_rows(fetched.rows, Self::row_to_json).await?;
+        timer.converted();
+
+        Ok(QueryResult {
+            columns,
+            rows,
+            row_count: fetched.row_count,
+            execution_time: timer.execution_time(),
+            truncated: fetched.row_count > MAX_QUERY_ROWS,
+            affected_rows: fetched.affected_rows,
+            timing: timer.timing(),
+            column_metadata,
+        })
+    }
+
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult> {
+        let statements = statement::split_statements(query);
+        let [single] = statements.as_slice() else {
+            return self.execute_query(query).await;
+        };
+        if !single.is_query() {
+            // Other statements return their count rather than rows; they are only chunked
+            // for the consumer
+            let mut result = self.execute_query(query).await?;
+            let mut chunks = ChunkedRows::new(result.columns.clone(), chunk_size, on_chunk);
+            for row in std::mem::take(&mut result.rows) {
+                if !chunks.push(row) {
+                    break;
+                }
+            }
+            chunks.finish();
+            return Ok(result);
+        }
+
+        let mut timer = QueryTimer::start();
+        let session = self.session.clone().lock_owned().await;
+        timer.acquired();
+
+        // The fetching thread stops once the receiver is dropped
+        let (sender, mut receiver) = mpsc::channel(STREAM_BUFFER_ROWS);
+        let sql = single.sql.clone();
+        let fetch = Self::run_blocking(session, move |session| {
+            let result_set = session.query(&sql, &[]).map_err(query_error)?;
+            let columns = result_set.column_info().to_vec();
+            if sender.blocking_send(StreamItem::Columns(columns)).is_err() {
+                return Ok(());
+            }
+            for row in result_set {
+                let row = row.map_err(query_error)?;
+                if sender.blocking_send(StreamItem::Row(row)).is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        tokio::pin!(fetch);
+
+        let mut result_columns = Vec::new();
+        let mut chunks: Option<ChunkedRows<'_>> = None;
+        let mut on_chunk = Some(on_chunk);
+        let mut fetched = None;
+        loop {
+            tokio::select! {
+                item = receiver.recv() => match item {
+                    Some(StreamItem::Columns(columns)) => {
+                        timer.executed();
+                        let names = columns.iter().map(|c| c.name().to_string()).collect();
+                        if let Some(sink) = on_chunk.take() {
+                            chunks = Some(ChunkedRows::new(names, chunk_size, sink));
+                        }
+                        result_columns = columns;
+                    }
+                    Some(StreamItem::Row(row)) => {
+                        timer.fetched();
+                        let Some(rows) = chunks.as_mut() else {
+                            continue;
+                        };
+                        if !rows.push(Self::row_to_json(&row)) {
+                            break;
+                        }
+                        timer.converted();
+                    }
+                    None => break,
+                },
+                result = &mut fetch, if fetched.is_none() => fetched = Some(result),
+            }
+        }
+        drop(receiver);
+        match fetched {
+            Some(result) => result?,
+            None => fetch.await?,
+        }
+        timer.fetched();
+        let (row_count, truncated) = chunks.map(ChunkedRows::finish).unwrap_or_default();
+
+        Ok(QueryResult {
+            columns: result_columns
+                .iter()
+                .map(|c| c.name().to_string())
+                .collect(),
+            rows: Vec::new(),
+            row_count,
+            execution_time: timer.execution_time(),
+            truncated,
+            affected_rows: None,
+            timing: timer.timing(),
+            column_metadata: Self::column_metadata(&result_columns),
+        })
+    }
+
+    async fn list_tables(&self) -> DbResult<Vec<String>> {
+        self.with_session(|session| Self::table_names(session))
+            .await
+    }
+
+    async fn list_databases(&self) -> DbResult<Vec<String>> {
+        Ok(vec![self.service.clone()])
+    }
+
+    async fn change_database(&self, database_name: &str) -> DbResult<()> {
+        if database_name == self.service {
+            return Ok(());
+        }
+        Err(QueryError::with_code(
+            "An Oracle connection is made to one service; connect again to switch",
+            error_codes::QUERY_ERROR,
+        ))
+    }
+
+    async fn set_default_schema(&self, schema: &str) -> DbResult<()> {
+        let statement = format!(
+            "ALTER SESSION SET CURRENT_SCHEMA = {}",
+            DIALECT.quote_identifier(schema)
+        );
+        self.with_session(move |session| {
+            session
+                .execute(&statement, &[])
+                .map(|_| ())
+                .map_err(query_error)
+        })
+        .await
+    }
+
+    async fn get_current_database(&self) -> DbResult<String> {
+        Ok(self.service.clone())
+    }
+
+    async fn get_table_columns(&self, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        let table = table.clone();
+        self.with_session(move |session| Self::table_columns(session, &table))
+            .await
+    }
+
+    async fn get_table_relationships(&self) -> DbResult<Vec<TableRelationship>> {
+        let query = format!(
+            "SELECT
+                a.OWNER AS FROM_SCHEMA, a.TABLE_NAME AS FROM_TABLE, ac.COLUMN_NAME AS FROM_COLUMN,
+                r.OWNER AS TO_SCHEMA, r.TABLE_NAME AS TO_TABLE, rc.COLUMN_NAME AS TO_COLUMN,
+                a.CONSTRAINT_NAME
+             FROM ALL_CONSTRAINTS a
+             JOIN ALL_CONS_COLUMNS ac
+                ON ac.OWNER = a.OWNER AND ac.CONSTRAINT_NAME = a.CONSTRAINT_NAME
+             JOIN ALL_CONSTRAINTS r
+                ON r.OWNER = a.R_OWNER AND r.CONSTRAINT_NAME = a.R_CONSTRAINT_NAME
+             JOIN ALL_CONS_COLUMNS rc
+                ON rc.OWNER = r.OWNER AND rc.CONSTRAINT_NAME = r.CONSTRAINT_NAME
+                AND rc.POSITION = ac.POSITION
+             WHERE a.CONSTRAINT_TYPE = 'R' AND a.OWNER = {}
+             ORDER BY a.TABLE_NAME, a.CONSTRAINT_NAME, ac.POSITION",
+            CURRENT_SCHEMA
+        );
+        self.with_session(move |session| {
+            let rows = session.query(&query, &[]).map_err(query_error)?;
+            let mut relationships = Vec::new();
+            for row in rows {
+                let row = row.map_err(query_error)?;
+                let relationship = (|| {
+                    Some(TableRelationship {
+                        from_schema: text(&row, "FROM_SCHEMA"),
+                        from_table: text(&row, "FROM_TABLE")?,
+                        from_column: text(&row, "FROM_COLUMN")?,
+                        to_schema: text(&row, "TO_SCHEMA"),
+                        to_table: text(&row, "TO_TABLE")?,
+                        to_column: text(&row, "TO_COLUMN")?,
+                        constraint_name: text(&row, "CONSTRAINT_NAME")?,
+                    })
+                })();
+                relationships.extend(relationship);
+            }
+            Ok(relationships)
+        })
+        .await
+    }
+
+    async fn check_privileges(
+        &self,
+        table: &TableRef,
+        operation: TableOperation,
+    ) -> DbResult<bool> {
+        // Owners may do anything with their tables; others need an object grant (to them,
+        // an enabled role or PUBLIC) or the ANY TABLE system privilege
+        let query = format!(
+            "SELECT CASE
+                WHEN NOT EXISTS (
+                    SELECT 1 FROM ALL_OBJECTS WHERE OWNER = NVL(:schema_name, {schema})
+                        AND OBJECT_NAME = :table_name AND OBJECT_TYPE IN ('TABLE', 'VIEW')
+                ) THEN NULL
+                WHEN NVL(:schema_name, {schema}) = USER THEN 'YES'
+                WHEN EXISTS (
+                    SELECT 1 FROM ALL_TAB_PRIVS WHERE TABLE_SCHEMA = NVL(:schema_name, {schema})
+                        AND TABLE_NAME = :table_name AND PRIVILEGE = :privilege
+                        AND (GRANTEE IN (USER, 'PUBLIC')
+                            OR GRANTEE IN (SELECT ROLE FROM SESSION_ROLES))
+                ) THEN 'YES'
+                WHEN EXISTS (
+                    SELECT 1 FROM SESSION_PRIVS WHERE PRIVILEGE = :privilege || ' ANY TABLE'
+                ) THEN 'YES'
+                ELSE 'NO' END AS ALLOWED
+             FROM DUAL",
+            schema = CURRENT_SCHEMA
+        );
+        let table = table.clone();
+        let privilege = operation.privilege();
+        self.with_session(move |session| {
+            let row = session
+                .query_row_named(
+                    &query,
+                    &[
+                        ("schema_name", &table.schema.as_deref()),
+                        ("table_name", &table.name.as_str()),
+                        ("privilege", &privilege),
+                    ],
+                )
+                .map_err(query_error)?;
+            // NULL when the table doesn't exist
+            text(&row, "ALLOWED")
+                .map(|allowed| allowed == "YES")
+                .ok_or_else(|| {
+                    QueryError::with_code(
+                        format!("Table \"{}\" not found", table),
+                        error_codes::QUERY_ERROR,
+                    )
+                })
+        })
+        .await
+    }
+
+    async fn disconnect(&self) -> DbResult<()> {
+        self.with_session(|session| session.close().map_err(query_error))
+            .await?;
+        debug!("Oracle connection disconnected");
+        Ok(())
+    }
+
+    async fn export_database_with_options(
+        &self,
+        include_drop: bool,
+        include_create: bool,
+        data_mode: &str,
+        selected_tables: &[TableRef],
+        max_insert_size: usize,
+    ) -> DbResult<String> {
+        if !matches!(data_mode, "insert" | "no_data") {
+            return Err(QueryError::with_code(
+                format!(
+                    "Oracle exports support the insert and no_data modes, not '{}'",
+                    data_mode
+                ),
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let max_insert_size = max_insert_size.max(1);
+        let include_data = data_mode != "no_data";
+        let selected_tables = selected_tables.to_vec();
+
+        self.with_session(move |session| {
+            let tables_to_export = if selected_tables.is_empty() {
+                Self::table_names(session)?
+                    .into_iter()
+                    .map(TableRef::unqualified)
+                    .collect()
+            } else {
+                selected_tables
+            };
+            if include_create {
+                // Leave out tablespaces and storage, which rarely exist on the target
+                session
+                    .execute(
+                        "BEGIN
+                            DBMS_METADATA.SET_TRANSFORM_PARAM(
+                                DBMS_METADATA.SESSION_TRANSFORM, 'SEGMENT_ATTRIBUTES', FALSE);
+                            DBMS_METADATA.SET_TRANSFORM_PARAM(
+                                DBMS_METADATA.SESSION_TRANSFORM, 'EMIT_SCHEMA', FALSE);
+                         END;",
+                        &[],
+                    )
+                    .map_err(query_error)?;
+            }
+
+            let mut sql_content = String::with_capacity(1024 * 1024);
+            for table in tables_to_export {
+                let qualified_table = DIALECT.qualified_name(&table);
+                let columns = Self::table_columns(session, &table)?;
+                if columns.is_empty() {
+                    return Err(QueryError::with_code(
+                        format!("Table \"{}\" not found", table),
+                        error_codes::QUERY_ERROR,
+                    ));
+                }
+                sql_content.push_str(&format!("\n-- Table: {}\n", table));
+
+                if include_drop {
+                    // Oracle has no DROP TABLE IF EXISTS before 23ai; ORA-00942 is a
+                    // missing table
+                    sql_content.push_str(&format!(
+                        "BEGIN\n  EXECUTE IMMEDIATE 'DROP TABLE {} CASCADE CONSTRAINTS';\n\
+                         EXCEPTION\n  WHEN OTHERS THEN\n    \
+                         IF SQLCODE != -942 THEN RAISE; END IF;\nEND;\n/\n",
+                        DIALECT.escape_string(&qualified_table)
+                    ));
+                }
+
+                if include_create {
+                    let row = session
+                        .query_row_named(
+                            &format!(
+                                "SELECT DBMS_METADATA.GET_DDL('TABLE', :table_name,
+                                    NVL(:schema_name, {})) AS DDL FROM DUAL",
+                                CURRENT_SCHEMA
+                            ),
+                            &[
+                                ("table_name", &table.name.as_str()),
+                                ("schema_name", &table.schema.as_deref()),
+                            ],
+                        )
+                        .map_err(query_error)?;
+                    sql_content.push_str(text(&row, "DDL").unwrap_or_default().trim());
+                    sql_content.push_str(";\n\n");
+                }
+
+                if !include_data {
+                    continue;
+                }
+
+                // Virtual columns can't be written
+                let column_list = columns
+                    .iter()
+                    .filter(|c| !c.is_generated)
+                    .map(|c| DIALECT.quote_identifier(&c.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let into = format!("  INTO {} ({}) VALUES ", qualified_table, column_list);
+                let query = format!("SELECT {} FROM {}", column_list, qualified_table);
+                let rows = session.query(&query, &[]).map_err(query_error)?;
+                // INSERT ALL inserts several rows in one statement before 23ai
+                let mut row_buffer: Vec<String> = Vec::with_capacity(max_insert_size);
+                let flush = |row_buffer: &mut Vec<String>, sql_content: &mut String| {
+                    sql_content.push_str("INSERT ALL\n");
+                    for values in row_buffer.drain(..) {
+                        sql_content.push_str(&into);
+                        sql_content.push_str(&values);
+                        sql_content.push('\n');
+                    }
+                    sql_content.push_str("SELECT 1 FROM DUAL;\n");
+                };
+                for row in rows {
+                    let row = row.map_err(query_error)?;
+                    let values: Vec<String> = row
+                        .column_info()
+                        .iter()
+                        .zip(row.sql_values())
+                        .map(|(column, value)| Self::value_to_sql(value, column.oracle_type()))
+                        .collect();
+                    row_buffer.push(format!("({})", values.join(", ")));
+
+                    if row_buffer.len() >= max_insert_size {
+                        flush(&mut row_buffer, &mut sql_content);
+                    }
+                }
+                if !row_buffer.is_empty() {
+                    flush(&mut row_buffer, &mut sql_content);
+                }
+                sql_content.push('\n');
+            }
+
+            Ok(sql_content)
+        })
+        .await
+    }
+
+    async fn update_cell(
+        &self,
+        table: &TableRef,
+        column_name: &str,
+        new_value: Option<&str>,
+        primary_key_column: &str,
+        primary_key_value: &str,
+    ) -> DbResult<String> {
+        let logged_query = format!(
+            "UPDATE {} SET {} = {} WHERE {} = {}",
+            DIALECT.qualified_name(table),
+            DIALECT.quote_identifier(column_name),
+            new_value
+                .map(|value| DIALECT.quote_string(value))
+                .unwrap_or_else(|| "NULL".to_string()),
+            DIALECT.quote_identifier(primary_key_column),
+            DIALECT.quote_string(primary_key_value)
+        );
+
+        // Oracle converts the text values to the column types, dates by the session formats
+        let query = format!(
+            "UPDATE {} SET {} = :1 WHERE {} = :2",
+            DIALECT.qualified_name(table),
+            DIALECT.quote_identifier(column_name),
+            DIALECT.quote_identifier(primary_key_column)
+        );
+        let new_value = new_value.map(str::to_string);
+        let primary_key_value = primary_key_value.to_string();
+        self.with_session(move |session| {
+            session
+                .execute(&query, &[&new_value, &primary_key_value])
+                .map_err(query_error)
+        })
+        .await?;
+
+        Ok(logged_query)
+    }
+
+    async fn insert_rows(
+        &self,
+        table: &TableRef,
+        columns: &[String],
+        rows: &[Vec<Option<String>>],
+    ) -> DbResult<u64> {
+        let column_list = columns
+            .iter()
+            .map(|name| DIALECT.quote_identifier(name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let placeholders = (1..=columns.len())
+            .map(|i| format!(":{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let query = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            DIALECT.qualified_name(table),
+            column_list,
+            placeholders
+        );
+        let rows = rows.to_vec();
+        let table_name = table.to_string();
+
+        self.with_session(move |session| {
+            // The batches commit together
+            session.set_autocommit(false);
+            let insert = |session: &Session| {
+                let mut batch = session
+                    .batch(&query, super::bulk_insert::INSERT_BATCH_ROWS)
+                    .build()?;
+                for row in &rows {
+                    let params: Vec<&dyn ToSql> = row.iter().map(|v| v as &dyn ToSql).collect();
+                    batch.append_row(&params)?;
+                }
+                batch.execute()?;
+                session.commit()
+            };
+            let result = insert(session);
+            if result.is_err() {
+                if let Err(rollback) = session.rollback() {
+                    warn!("Failed to roll back insert: {}", rollback);
+                }
+            }
+            session.set_autocommit(true);
+            result.map_err(query_error)?;
+
+            debug!("Inserted {} rows into {}", rows.len(), table_name);
+            Ok(rows.len() as u64)
+        })
+        .await
+    }
+
+    async fn get_routine_definition(&self, routine: &TableRef) -> DbResult<String> {
+        let query = format!(
+            "SELECT TEXT FROM ALL_SOURCE
+             WHERE OWNER = NVL(:schema_name, {}) AND NAME = :routine_name
+                AND TYPE IN ('FUNCTION', 'PROCEDURE')
+             ORDER BY LINE",
+            CURRENT_SCHEMA
+        );
+        let routine = routine.clone();
+        self.with_session(move |session| {
+            let lines = session
+                .query_named(
+                    &query,
+                    &[
+                        ("schema_name", &routine.schema.as_deref()),
+                        ("routine_name", &routine.name.as_str()),
+                    ],
+                )
+                .map_err(query_error)?;
+            let mut source = String::new();
+            for line in lines {
+                let line = line.map_err(query_error)?;
+                source.push_str(&text(&line, "TEXT").unwrap_or_default());
+            }
+            if source.is_empty() {
+                return Err(QueryError::with_code(
+                    format!("Routine \"{}\" not found", routine),
+                    error_codes::QUERY_ERROR,
+                ));
+            }
+            // ALL_SOURCE starts at the routine's name
+            Ok(format!("CREATE OR REPLACE {}", source.trim_end()))
+        })
+        .await
+    }
+
+    async fn get_view_definition(&self, view: &TableRef) -> DbResult<String> {
+        let query = format!(
+            "SELECT TEXT FROM ALL_VIEWS
+             WHERE OWNER = NVL(:schema_name, {}) AND VIEW_NAME = :view_name",
+            CURRENT_SCHEMA
+        );
+        let view = view.clone();
+        self.with_session(move |session| {
+            let rows = session
+                .query_named(
+                    &query,
+                    &[
+                        ("schema_name", &view.schema.as_deref()),
+                        ("view_name", &view.name.as_str()),
+                    ],
+                )
+                .map_err(query_error)?;
+            for row in rows {
+                let row = row.map_err(query_error)?;
+                if let Some(definition) = text(&row, "TEXT") {
+                    return Ok(definition);
+                }
+            }
+            Err(QueryError::with_code(
+                format!("View \"{}\" not found", view),
+                error_codes::QUERY_ERROR,
+            ))
+        })
+        .await
+    }
+
+    async fn alter_view(&self, view: &TableRef, query: &str) -> DbResult<()> {
+        // CREATE OR REPLACE fails without touching the view if the query doesn't compile
+        let statement = format!(
+            "CREATE OR REPLACE VIEW {} AS\n{}",
+            DIALECT.qualified_name(view),
+            query.trim().trim_end_matches(';')
+        );
+        self.with_session(move |session| {
+            session
+                .execute(&statement, &[])
+                .map(|_| ())
+                .map_err(query_error)
+        })
+        .await
+    }
+
+    async fn get_text_search_configs(&self) -> DbResult<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn build_sample_query(
+        &self,
+        table: &TableRef,
+        n: usize,
+        _method: SampleMethod,
+    ) -> DbResult<String> {
+        Ok(format!(
+            "SELECT * FROM (SELECT * FROM {} FETCH FIRST {} ROWS ONLY) \
+             ORDER BY DBMS_RANDOM.VALUE FETCH FIRST {} ROWS ONLY",
+            DIALECT.qualified_name(table),
+            RANDOM_SCAN_LIMIT,
+            sampling::sample_size(n)
+        ))
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        DIALECT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts_and_types() {
+        let first = |sql: &str| statement::split_statements(sql).remove(0);
+        assert!(is_plsql(&first("BEGIN dbms_output.put_line('x'); END;")));
+        assert!(is_plsql(&first(
+            "CREATE OR REPLACE EDITIONABLE FUNCTION f RETURN NUMBER IS BEGIN RETURN 1; END;"
+        )));
+        assert!(!is_plsql(&first(
+            "CREATE TABLE t (type VARCHAR2(10)); SELECT 1 FROM dual"
+        )));
+
+        assert_eq!(
+            connect_string("db", 1521, "ORCLPDB1", SslMode::Disabled),
+            "//db:1521/ORCLPDB1"
+        );
+        assert_eq!(
+            connect_string("db", 2484, "ORCLPDB1", SslMode::VerifyCa),
+            "tcps://db:2484/ORCLPDB1?ssl_server_dn_match=no"
+        );
+
+        assert_eq!(
+            full_type("VARCHAR2", Some(20), true, None, None),
+            "VARCHAR2(20 CHAR)"
+        );
+        assert_eq!(
+            full_type("NUMBER", None, false, Some(10), Some(2)),
+            "NUMBER(10,2)"
+        );
+        assert_eq!(full_type("NUMBER", None, false, None, Some(0)), "INTEGER");
+    }
+}
@@ -1,14 +1,44 @@
+use super::aggregation::{self, AggregateRequest, BucketUnit, ChartData};
+use super::bulk_insert;
+use super::change_stream::{self, ChangeSink};
+use super::column_metadata::{self, ColumnMetadata};
+use super::column_stats::{self, ColumnStats, CommonValue};
 use super::connection::{
-    error_codes, DatabaseConnection, DbResult, QueryError, QueryResult, TableColumn,
-    TableRelationship, DEFAULT_QUERY_TIMEOUT, MAX_QUERY_ROWS,
+    check_droppable_database, convert_rows, error_codes, read_ca_cert, ActiveTransaction,
+    ChunkedRows, DatabaseConnection, DatabaseSize, DbResult, HealthSnapshot, PoolSize, QueryError,
+    QueryResult, QueryStatistic, QueryTimer, QueryTiming, ResultCursor, RlsPolicy, RlsStatus,
+    RowChunkSink, SessionRoles, SslMode, TableBloat, TableColumn, TableOperation, TableOptions,
+    TableRef, TableRelationship, DEFAULT_QUERY_TIMEOUT, LIVENESS_TIMEOUT, MAX_QUERY_ROWS,
+    SCRATCH_SCHEMA, SERVER_TIMEOUT_GRACE,
 };
+use super::deadlocks::{self, DeadlockReport};
+use super::index_advisor::{self, IndexSuggestion};
+use super::rds_iam::RdsIamAuth;
+use super::routines::{
+    self, Argument, ParameterMode, RoutineKind, RoutineParameter, RoutineResult, RoutineSignature,
+};
+use super::sampling::{self, SampleMethod};
+use super::session_variables::{self, SessionVariable};
+use super::sql_dialect::SqlDialect;
+use super::text_search::{self, TextSearchRequest};
+use super::triggers::{TriggerLevel, TriggerSpec};
+use super::users::{self, DbUser, GrantTarget, PrivilegeGrant};
+use crate::redact::REDACTED;
 use async_trait::async_trait;
+use futures_util::{pin_mut, TryStreamExt};
 use native_tls::TlsConnector;
 use postgres_native_tls::MakeTlsConnector;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore, SemaphorePermit};
 use tokio::time::timeout;
-use tokio_postgres::{types::Type, Client, NoTls, Row};
+use tokio_postgres::{
+    types::{ToSql, Type},
+    Client, Column, IsolationLevel, NoTls, Row,
+};
 use tracing::{debug, error, warn};
 
 /// Converts a tokio_postgres error to a QueryError with full details.
@@ -42,6 +72,8 @@ fn pg_error_to_query_error(err: tokio_postgres::Error, code: &str) -> QueryError
                 "23505" => Some("Value already exists (unique constraint violation)"),
                 "42703" => Some("Check column name spelling"),
                 "42P01" => Some("Check table name spelling"),
+                "40001" => Some("The transaction conflicted with another one; run it again"),
+                "57014" => Some("The statement was canceled or exceeded the query timeout"),
                 _ => None,
             };
             if let Some(h) = hint {
@@ -56,41 +88,209 @@ fn pg_error_to_query_error(err: tokio_postgres::Error, code: &str) -> QueryError
     }
 }
 
+/// Maximum number of pooled clients leased for metadata and background operations, unless
+/// the connection sets its own pool size.
+const DEFAULT_POOL_SIZE: usize = 4;
+
+/// Bytes read from the end of the server log when looking for deadlocks.
+const LOG_TAIL_BYTES: usize = 1024 * 1024;
+
+const DIALECT: SqlDialect = SqlDialect::Postgres;
+
+/// `get_table_columns` on CockroachDB, with the same columns and types as the PostgreSQL
+/// query (CockroachDB's `information_schema` lengths are INT8). Hidden columns such as the
+/// implicit `rowid` key are left out, primary key constraints are matched by table since
+/// older versions name every table's `primary`, and `unique_rowid()` defaults count as
+/// identities like `nextval` ones.
+const COCKROACH_COLUMNS_QUERY: &str = "SELECT
+        c.column_name,
+        c.udt_name,
+        c.is_nullable,
+        pk.column_name IS NOT NULL,
+        c.column_default,
+        c.character_maximum_length::int4,
+        c.numeric_precision::int4,
+        c.is_identity = 'YES'
+            OR COALESCE(c.column_default = 'unique_rowid()', false)
+            OR COALESCE(c.column_default LIKE 'nextval(%', false),
+        c.identity_generation::text,
+        c.is_generated = 'ALWAYS',
+        c.generation_expression::text,
+        NULL::text
+     FROM information_schema.columns c
+     LEFT JOIN (
+        SELECT ku.column_name
+        FROM information_schema.table_constraints tc
+        JOIN information_schema.key_column_usage ku
+            ON tc.constraint_name = ku.constraint_name
+            AND tc.table_schema = ku.table_schema
+            AND tc.table_name = ku.table_name
+        WHERE tc.constraint_type = 'PRIMARY KEY'
+            AND tc.table_name = $1
+            AND tc.table_schema = COALESCE($2::text, current_schema())
+     ) pk ON c.column_name = pk.column_name
+     WHERE c.table_name = $1
+        AND c.table_schema = COALESCE($2::text, current_schema())
+        AND c.is_hidden = 'NO'
+     ORDER BY c.ordinal_position";
+
+/// Clients for operations that don't depend on session state, so sidebar refreshes and
+/// exports don't wait behind a long-running query on the primary client.
+struct ClientPool {
+    idle: std::sync::Mutex<Vec<Client>>,
+    permits: Semaphore,
+    /// Bumped when pooled clients become stale (database or search_path changed).
+    generation: AtomicU64,
+}
+
+impl ClientPool {
+    fn new(max_size: usize) -> Self {
+        ClientPool {
+            idle: std::sync::Mutex::new(Vec::new()),
+            permits: Semaphore::new(max_size),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Drops idle clients and makes leased ones close instead of returning to the pool.
+    fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.idle.lock().unwrap().clear();
+    }
+}
+
+/// A client leased from the pool for a single operation; returned to the pool on drop.
+struct PooledClient<'a> {
+    pool: &'a ClientPool,
+    client: Option<Client>,
+    generation: u64,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl Deref for PooledClient<'_> {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client.as_ref().expect("client is only taken on drop")
+    }
+}
+
+impl DerefMut for PooledClient<'_> {
+    fn deref_mut(&mut self) -> &mut Client {
+        self.client.as_mut().expect("client is only taken on drop")
+    }
+}
+
+impl Drop for PooledClient<'_> {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            if !client.is_closed() && self.pool.generation.load(Ordering::SeqCst) == self.generation
+            {
+                self.pool.idle.lock().unwrap().push(client);
+            }
+        }
+    }
+}
+
+/// Servers spoken to over the PostgreSQL protocol whose catalogs differ from PostgreSQL's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostgresFlavor {
+    /// PostgreSQL itself.
+    Postgres,
+    /// CockroachDB, which has its own `SHOW` statements and hidden columns (`rowid`).
+    Cockroach,
+}
+
 /// PostgreSQL database connection implementation.
 pub struct PostgresConnection {
+    flavor: PostgresFlavor,
+    /// Client for user queries, kept separate from the pool so session state
+    /// (SET, open transactions, temporary tables) persists between them.
     client: Arc<Mutex<Client>>,
+    pool: ClientPool,
     host: String,
     port: u16,
     username: String,
-    password: String,
+    /// Updated by `change_password` when the user changes their own password.
+    password: Mutex<String>,
     current_database: Arc<Mutex<String>>,
     ssl_mode: String,
+    ssl_ca_cert: Option<String>,
+    /// Timeout for user statements, also set as `statement_timeout` on every client.
+    query_timeout: Duration,
+    /// Logs clients in with RDS IAM tokens instead of `password`.
+    rds_iam: Option<RdsIamAuth>,
+    /// Schema set via `set_default_schema`, reapplied when `change_database` reconnects.
+    default_schema: Arc<Mutex<Option<String>>>,
+    /// Role set via `set_role`, applied to every client.
+    role: Arc<Mutex<Option<String>>>,
+    /// Variables set via `set_session_variable`, applied to every client.
+    session_variables: Arc<Mutex<Vec<(String, String)>>>,
 }
 
 impl PostgresConnection {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
+        flavor: PostgresFlavor,
         host: &str,
         port: u16,
         username: &str,
         password: &str,
         database: &str,
         ssl_mode: &str,
+        ssl_ca_cert: Option<&str>,
+        pool_size: PoolSize,
+        query_timeout: Option<Duration>,
+        rds_iam: Option<RdsIamAuth>,
     ) -> DbResult<Self> {
-        let client =
-            Self::create_client(host, port, username, password, database, ssl_mode).await?;
+        // The session client is always there, so by default no pooled clients are opened
+        // up front
+        let (min_pooled, max_pooled) = pool_size.resolve(0, DEFAULT_POOL_SIZE)?;
+        let query_timeout = query_timeout.unwrap_or(DEFAULT_QUERY_TIMEOUT);
+        let login_password = match &rds_iam {
+            Some(iam) => iam.token(host, port, username)?,
+            None => password.to_string(),
+        };
+        let client = Self::create_client(
+            host,
+            port,
+            username,
+            &login_password,
+            database,
+            ssl_mode,
+            ssl_ca_cert,
+            query_timeout,
+        )
+        .await?;
 
-        Ok(PostgresConnection {
+        let connection = PostgresConnection {
+            flavor,
             client: Arc::new(Mutex::new(client)),
+            pool: ClientPool::new(max_pooled),
             host: host.to_string(),
             port,
             username: username.to_string(),
-            password: password.to_string(),
+            password: Mutex::new(password.to_string()),
             current_database: Arc::new(Mutex::new(database.to_string())),
             ssl_mode: ssl_mode.to_string(),
-        })
+            ssl_ca_cert: ssl_ca_cert.map(str::to_string),
+            query_timeout,
+            rds_iam,
+            default_schema: Arc::new(Mutex::new(None)),
+            role: Arc::new(Mutex::new(None)),
+            session_variables: Arc::new(Mutex::new(Vec::new())),
+        };
+        for _ in 0..min_pooled {
+            let client = connection.connect_session(database).await?;
+            connection.pool.idle.lock().unwrap().push(client);
+        }
+        Ok(connection)
     }
 
     /// Creates a new PostgreSQL client with the specified parameters.
+    ///
+    /// The server cancels statements running longer than `statement_timeout`.
+    #[allow(clippy::too_many_arguments)]
     async fn create_client(
         host: &str,
         port: u16,
@@ -98,15 +298,32 @@ impl PostgresConnection {
         password: &str,
         database: &str,
         ssl_mode: &str,
+        ssl_ca_cert: Option<&str>,
+        statement_timeout: Duration,
     ) -> DbResult<Client> {
-        let config = format!(
-            "host={} port={} user={} password={} dbname={}",
-            host, port, username, password, database
-        );
-
-        if ssl_mode == "required" || ssl_mode == "preferred" {
-            let connector = TlsConnector::builder()
-                .danger_accept_invalid_certs(true)
+        // Built field by field rather than as a connection string, so the password can't
+        // end up in a string that gets logged or echoed in an error
+        let mut config = tokio_postgres::Config::new();
+        config
+            .host(host)
+            .port(port)
+            .user(username)
+            .password(password)
+            .dbname(database)
+            .options(&format!(
+                "-c statement_timeout={}",
+                statement_timeout.as_millis()
+            ));
+        let ssl_mode = SslMode::parse(ssl_mode)?;
+
+        if ssl_mode.uses_tls() {
+            let mut builder = TlsConnector::builder();
+            if let Some(path) = ssl_ca_cert {
+                builder.add_root_certificate(read_ca_cert(path)?);
+            }
+            let connector = builder
+                .danger_accept_invalid_certs(!ssl_mode.verifies_certificate())
+                .danger_accept_invalid_hostnames(!ssl_mode.verifies_hostname())
                 .build()
                 .map_err(|e| QueryError {
                     message: format!("TLS configuration error: {}", e),
@@ -115,8 +332,14 @@ impl PostgresConnection {
                 })?;
 
             let tls_connector = MakeTlsConnector::new(connector);
+            // Without sslmode=require the driver silently uses plain TCP when the server
+            // doesn't support TLS
+            let mut tls_config = config.clone();
+            if !ssl_mode.allows_fallback() {
+                tls_config.ssl_mode(tokio_postgres::config::SslMode::Require);
+            }
 
-            match tokio_postgres::connect(&config, tls_connector).await {
+            match tls_config.connect(tls_connector).await {
                 Ok((client, connection)) => {
                     tokio::spawn(async move {
                         if let Err(e) = connection.await {
@@ -127,7 +350,7 @@ impl PostgresConnection {
                     return Ok(client);
                 }
                 Err(e) => {
-                    if ssl_mode == "required" {
+                    if !ssl_mode.allows_fallback() {
                         return Err(QueryError {
                             message: format!("SSL connection failed: {}", e),
                             code: Some(error_codes::SSL_ERROR.to_string()),
@@ -140,13 +363,11 @@ impl PostgresConnection {
         }
 
         // No SSL or fallback from preferred
-        let (client, connection) = tokio_postgres::connect(&config, NoTls)
-            .await
-            .map_err(|e| QueryError {
-                message: format!("Connection failed: {}", e),
-                code: Some(error_codes::CONNECTION_ERROR.to_string()),
+        let (client, connection) = config.connect(NoTls).await.map_err(|e| QueryError {
+            message: format!("Connection failed: {}", e),
+            code: Some(error_codes::CONNECTION_ERROR.to_string()),
             ..Default::default()
-            })?;
+        })?;
 
         tokio::spawn(async move {
             if let Err(e) = connection.await {
@@ -158,16 +379,364 @@ impl PostgresConnection {
         Ok(client)
     }
 
+    /// Leases a pooled client, connecting a new one if none is idle.
+    ///
+    /// Waits if the pool's maximum number of clients are already leased.
+    async fn lease(&self) -> DbResult<PooledClient<'_>> {
+        let permit = self
+            .pool
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::CONNECTION_ERROR))?;
+        let generation = self.pool.generation.load(Ordering::SeqCst);
+
+        let idle = {
+            let mut idle = self.pool.idle.lock().unwrap();
+            std::iter::from_fn(|| idle.pop()).find(|client| !client.is_closed())
+        };
+        let client = match idle {
+            Some(client) => client,
+            None => {
+                let database = self.current_database.lock().await.clone();
+                self.connect_session(&database).await?
+            }
+        };
+
+        Ok(PooledClient {
+            pool: &self.pool,
+            client: Some(client),
+            generation,
+            _permit: permit,
+        })
+    }
+
+    /// Walks an EXPLAIN (FORMAT JSON) plan, collecting sequential scans with their alias and
+    /// every filter and join condition.
+    fn collect_plan_nodes(
+        node: &serde_json::Value,
+        scans: &mut Vec<(TableRef, String)>,
+        conditions: &mut Vec<String>,
+    ) {
+        if node.get("Node Type").and_then(|t| t.as_str()) == Some("Seq Scan") {
+            if let Some(relation) = node.get("Relation Name").and_then(|r| r.as_str()) {
+                let schema = node.get("Schema").and_then(|s| s.as_str());
+                let alias = node
+                    .get("Alias")
+                    .and_then(|a| a.as_str())
+                    .unwrap_or(relation);
+                scans.push((
+                    TableRef::new(schema.map(str::to_string), relation),
+                    alias.to_string(),
+                ));
+            }
+        }
+        for key in [
+            "Filter",
+            "Index Cond",
+            "Recheck Cond",
+            "Hash Cond",
+            "Merge Cond",
+            "Join Filter",
+        ] {
+            if let Some(condition) = node.get(key).and_then(|c| c.as_str()) {
+                conditions.push(condition.to_string());
+            }
+        }
+        for child in node
+            .get("Plans")
+            .and_then(|p| p.as_array())
+            .into_iter()
+            .flatten()
+        {
+            Self::collect_plan_nodes(child, scans, conditions);
+        }
+    }
+
+    /// How long the client waits for a user statement: a little longer than the server's
+    /// `statement_timeout`, so the server's own error arrives first.
+    fn statement_deadline(&self) -> Duration {
+        self.query_timeout + SERVER_TIMEOUT_GRACE
+    }
+
+    /// Connects a new client to `database` with the session state set on this connection
+    /// (default schema, role, session variables) applied.
+    async fn connect_session(&self, database: &str) -> DbResult<Client> {
+        // IAM tokens expire after 15 minutes, so each client gets a fresh one
+        let password = match &self.rds_iam {
+            Some(iam) => iam.token(&self.host, self.port, &self.username)?,
+            None => self.password.lock().await.clone(),
+        };
+        let client = Self::create_client(
+            &self.host,
+            self.port,
+            &self.username,
+            &password,
+            database,
+            &self.ssl_mode,
+            self.ssl_ca_cert.as_deref(),
+            self.query_timeout,
+        )
+        .await?;
+        if let Some(schema) = self.default_schema.lock().await.as_deref() {
+            Self::apply_search_path(&client, schema).await?;
+        }
+        if let Some(role) = self.role.lock().await.as_deref() {
+            Self::apply_role(&client, Some(role)).await?;
+        }
+        for (name, value) in self.session_variables.lock().await.iter() {
+            Self::apply_variable(&client, name, value).await?;
+        }
+        Ok(client)
+    }
+
+    /// Sets a session variable on the given client.
+    async fn apply_variable(client: &Client, name: &str, value: &str) -> DbResult<()> {
+        timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.execute("SELECT set_config($1, $2, false)", &[&name, &value]),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        Ok(())
+    }
+
+    /// Runs `SET ROLE` on the given client, or `RESET ROLE` for `None`.
+    async fn apply_role(client: &Client, role: Option<&str>) -> DbResult<()> {
+        let query = match role {
+            Some(role) => format!("SET ROLE \"{}\"", Self::escape_identifier(role)),
+            None => "RESET ROLE".to_string(),
+        };
+        timeout(DEFAULT_QUERY_TIMEOUT, client.simple_query(&query))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        Ok(())
+    }
+
+    /// Sets `search_path` on the given client so unqualified names resolve in `schema`.
+    async fn apply_search_path(client: &Client, schema: &str) -> DbResult<()> {
+        let query = format!("SET search_path TO \"{}\"", Self::escape_identifier(schema));
+        timeout(DEFAULT_QUERY_TIMEOUT, client.simple_query(&query))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        Ok(())
+    }
+
+    /// Returns the type of each of `columns` in `table`, as `format_type` spells it.
+    ///
+    /// Values from the UI arrive as text, so writes bind them as text and let PostgreSQL
+    /// cast them to these types with the types' own input functions.
+    async fn column_types(
+        client: &Client,
+        table: &TableRef,
+        columns: &[&str],
+    ) -> DbResult<Vec<String>> {
+        // Before PostgreSQL 14 to_regclass takes a cstring, which text can't be bound to
+        let type_query = "SELECT a.attname, format_type(a.atttypid, a.atttypmod)
+                          FROM pg_attribute a
+                          WHERE a.attrelid = to_regclass($1::text::cstring)
+                            AND a.attname = ANY($2)
+                            AND a.attnum > 0 AND NOT a.attisdropped";
+        let qualified_table = Self::qualified_name(table);
+        let type_rows = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query(type_query, &[&qualified_table, &columns]),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        columns
+            .iter()
+            .map(|name| {
+                type_rows
+                    .iter()
+                    .find(|row| row.get::<_, String>(0) == *name)
+                    .map(|row| row.get::<_, String>(1))
+                    .ok_or_else(|| {
+                        QueryError::with_code(
+                            format!("Column \"{}\" not found in table \"{}\"", name, table),
+                            error_codes::QUERY_ERROR,
+                        )
+                    })
+            })
+            .collect()
+    }
+
     /// Escapes an identifier (table/column name) for safe use in SQL.
     #[inline]
     fn escape_identifier(name: &str) -> String {
-        name.replace('"', "\"\"")
+        DIALECT.escape_identifier(name)
+    }
+
+    /// Converts rows read outside `execute_query`, e.g. by `call_routine`, to a result.
+    async fn rows_to_result(rows: Vec<Row>, execution_time: u128) -> DbResult<QueryResult> {
+        let columns: Vec<String> = rows
+            .first()
+            .map(|row| row.columns().iter().map(|c| c.name().to_string()).collect())
+            .unwrap_or_default();
+        let row_count = rows.len();
+        let column_names = columns.clone();
+        let rows = convert_rows(rows, move |row: &Row| {
+            let mut row_map = serde_json::Map::with_capacity(column_names.len());
+            for (i, col_name) in column_names.iter().enumerate() {
+                let value = Self::pg_value_to_json(row, i, row.columns()[i].type_());
+                row_map.insert(col_name.clone(), value);
+            }
+            serde_json::Value::Object(row_map)
+        })
+        .await?;
+
+        Ok(QueryResult {
+            columns,
+            rows,
+            row_count,
+            execution_time,
+            truncated: false,
+            affected_rows: None,
+            timing: QueryTiming::default(),
+            column_metadata: Vec::new(),
+        })
+    }
+
+    /// Returns the signatures of the procedures and functions named `routine`.
+    async fn routine_signatures(
+        client: &Client,
+        routine: &TableRef,
+    ) -> DbResult<Vec<RoutineSignature>> {
+        let query = "SELECT r.specific_name::text, r.routine_type::text,
+                            p.parameter_name::text, p.parameter_mode::text,
+                            format('%I.%I', p.udt_schema, p.udt_name),
+                            p.parameter_default IS NOT NULL
+                     FROM information_schema.routines r
+                     LEFT JOIN information_schema.parameters p
+                       ON p.specific_schema = r.specific_schema
+                      AND p.specific_name = r.specific_name
+                     WHERE r.routine_schema::text = COALESCE($1::text, current_schema()::text)
+                       AND r.routine_name::text = $2::text
+                     ORDER BY r.specific_name, p.ordinal_position";
+        let rows = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query(query, &[&routine.schema, &routine.name]),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        let mut signatures: Vec<(String, RoutineSignature)> = Vec::new();
+        for row in rows {
+            let specific_name: String = row.get(0);
+            if signatures.last().map(|(name, _)| name) != Some(&specific_name) {
+                let kind = match row.get::<_, Option<String>>(1).as_deref() {
+                    Some("PROCEDURE") => RoutineKind::Procedure,
+                    _ => RoutineKind::Function,
+                };
+                let signature = RoutineSignature {
+                    kind,
+                    parameters: Vec::new(),
+                };
+                signatures.push((specific_name, signature));
+            }
+            // Routines without parameters have one row with NULL parameter columns
+            let Some(mode) = row.get::<_, Option<String>>(3) else {
+                continue;
+            };
+            let parameter = RoutineParameter {
+                name: row.get::<_, Option<String>>(2).filter(|n| !n.is_empty()),
+                data_type: row.get(4),
+                mode: ParameterMode::parse(&mode),
+                has_default: row.get(5),
+            };
+            if let Some((_, signature)) = signatures.last_mut() {
+                signature.parameters.push(parameter);
+            }
+        }
+        Ok(signatures.into_iter().map(|(_, s)| s).collect())
+    }
+
+    /// Quotes a table name, qualified by its schema when it has one.
+    fn qualified_name(table: &TableRef) -> String {
+        DIALECT.qualified_name(table)
     }
 
     /// Escapes a string value for safe use in SQL.
     #[inline]
     fn escape_string(value: &str) -> String {
-        value.replace('\'', "''")
+        DIALECT.escape_string(value)
+    }
+
+    /// Column metadata from a statement's columns. Nullability and source columns are looked
+    /// up in the catalog for columns read straight from a table, and left unknown if that
+    /// fails.
+    async fn column_metadata(client: &Client, columns: &[Column]) -> Vec<ColumnMetadata> {
+        let mut metadata: Vec<ColumnMetadata> = columns
+            .iter()
+            .map(|column| {
+                let (precision, scale) = if *column.type_() == Type::NUMERIC {
+                    column_metadata::numeric_precision_scale(column.type_modifier())
+                } else {
+                    (None, None)
+                };
+                ColumnMetadata {
+                    name: column.name().to_string(),
+                    type_name: column.type_().name().to_string(),
+                    numeric: matches!(
+                        *column.type_(),
+                        Type::INT2
+                            | Type::INT4
+                            | Type::INT8
+                            | Type::FLOAT4
+                            | Type::FLOAT8
+                            | Type::NUMERIC
+                            | Type::OID
+                    ),
+                    precision,
+                    scale,
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        let (tables, attnums): (Vec<u32>, Vec<i16>) = columns
+            .iter()
+            .filter_map(|column| Some((column.table_oid()?, column.column_id()?)))
+            .unzip();
+        if tables.is_empty() {
+            return metadata;
+        }
+        let query = "SELECT a.attrelid, a.attnum, n.nspname::text, c.relname::text,
+                            a.attname::text, a.attnotnull
+                     FROM unnest($1::oid[], $2::int2[]) AS s(relid, attnum)
+                     JOIN pg_attribute a ON a.attrelid = s.relid AND a.attnum = s.attnum
+                     JOIN pg_class c ON c.oid = a.attrelid
+                     JOIN pg_namespace n ON n.oid = c.relnamespace";
+        let rows = match timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query(query, &[&tables, &attnums]),
+        )
+        .await
+        {
+            Ok(Ok(rows)) => rows,
+            Ok(Err(e)) => {
+                debug!("Failed to look up result column sources: {}", e);
+                return metadata;
+            }
+            Err(_) => return metadata,
+        };
+        for (column, metadata) in columns.iter().zip(&mut metadata) {
+            let Some(row) = rows.iter().find(|row| {
+                column.table_oid() == Some(row.get(0)) && column.column_id() == Some(row.get(1))
+            }) else {
+                continue;
+            };
+            metadata.source_table = Some(TableRef::new(Some(row.get(2)), row.get::<_, String>(3)));
+            metadata.source_column = Some(row.get(4));
+            metadata.nullable = Some(!row.get::<_, bool>(5));
+        }
+        metadata
     }
 
     #[inline]
@@ -282,7 +851,7 @@ impl PostgresConnection {
                 .try_get::<_, Option<bool>>(idx)
                 .ok()
                 .flatten()
-                .map(|v| if v { "TRUE" } else { "FALSE" }.to_string())
+                .map(|v| DIALECT.format_bool(v).to_string())
                 .unwrap_or_else(|| "NULL".to_string()),
 
             Type::INT2 | Type::INT4 | Type::INT8 | Type::FLOAT4 | Type::FLOAT8 => row
@@ -302,21 +871,28 @@ impl PostgresConnection {
                 .try_get::<_, Option<chrono::NaiveDateTime>>(idx)
                 .ok()
                 .flatten()
-                .map(|v| format!("'{}'", v.format("%Y-%m-%d %H:%M:%S")))
+                .map(|v| DIALECT.format_timestamp(v))
                 .unwrap_or_else(|| "NULL".to_string()),
 
             Type::DATE => row
                 .try_get::<_, Option<chrono::NaiveDate>>(idx)
                 .ok()
                 .flatten()
-                .map(|v| format!("'{}'", v.format("%Y-%m-%d")))
+                .map(|v| DIALECT.format_date(v))
                 .unwrap_or_else(|| "NULL".to_string()),
 
             Type::TIME | Type::TIMETZ => row
                 .try_get::<_, Option<chrono::NaiveTime>>(idx)
                 .ok()
                 .flatten()
-                .map(|v| format!("'{}'", v.format("%H:%M:%S")))
+                .map(|v| DIALECT.format_time(v))
+                .unwrap_or_else(|| "NULL".to_string()),
+
+            Type::BYTEA => row
+                .try_get::<_, Option<Vec<u8>>>(idx)
+                .ok()
+                .flatten()
+                .map(|v| DIALECT.quote_bytes(&v))
                 .unwrap_or_else(|| "NULL".to_string()),
 
             _ => row
@@ -329,7 +905,7 @@ impl PostgresConnection {
     }
 
     fn format_insert_statement(
-        table_name: &str,
+        table: &TableRef,
         columns: &[String],
         rows: &[Vec<String>],
         data_mode: &str,
@@ -364,19 +940,105 @@ impl PostgresConnection {
         };
 
         format!(
-            "INSERT INTO \"{}\" ({}) VALUES\n  {}{};\n",
-            Self::escape_identifier(table_name),
+            "INSERT INTO {} ({}) VALUES\n  {}{};\n",
+            Self::qualified_name(table),
             column_list,
             values_list,
             conflict_clause
         )
     }
+
+    /// Runs a GRANT, or a REVOKE when `revoke` is set. Returns the statement.
+    async fn change_privileges(&self, grant: &PrivilegeGrant, revoke: bool) -> DbResult<String> {
+        let privileges = grant.privilege_list()?;
+        let target = match &grant.target {
+            GrantTarget::Database { name } => {
+                format!("DATABASE \"{}\"", Self::escape_identifier(name))
+            }
+            GrantTarget::Schema { name } => format!("SCHEMA \"{}\"", Self::escape_identifier(name)),
+            GrantTarget::Table { table } => format!("TABLE {}", Self::qualified_name(table)),
+        };
+        let grantee = format!("\"{}\"", Self::escape_identifier(&grant.user.name));
+        let statement = users::grant_statement(grant, &privileges, &target, &grantee, revoke);
+
+        let client = self.lease().await?;
+        timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        Ok(statement)
+    }
+}
+
+/// Name of the cursor declared on a `PostgresCursor`'s client.
+const CURSOR_NAME: &str = "bloatsql_cursor";
+
+/// A `SCROLL` cursor declared in a read-only transaction on its own client, so it neither
+/// blocks nor is affected by queries on the primary client.
+struct PostgresCursor {
+    /// `None` once the cursor is closed.
+    client: Mutex<Option<Client>>,
+    columns: Vec<String>,
+}
+
+#[async_trait]
+impl ResultCursor for PostgresCursor {
+    fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    async fn fetch(&self, offset: usize, count: usize) -> DbResult<Vec<serde_json::Value>> {
+        let client = self.client.lock().await;
+        let client = client
+            .as_ref()
+            .ok_or_else(|| QueryError::with_code("Cursor is closed", error_codes::QUERY_ERROR))?;
+        // FETCH FORWARD 0 would return the current row again
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        // Position 0 is before the first row, so the fetch starts at row `offset`
+        let query = format!("MOVE ABSOLUTE {} IN {}", offset, CURSOR_NAME);
+        timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&query))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        let query = format!("FETCH FORWARD {} FROM {}", count, CURSOR_NAME);
+        let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(&query, &[]))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let mut row_map = serde_json::Map::with_capacity(self.columns.len());
+                for (i, col_name) in self.columns.iter().enumerate() {
+                    let col_type = row.columns()[i].type_();
+                    let value = PostgresConnection::pg_value_to_json(row, i, col_type);
+                    row_map.insert(col_name.clone(), value);
+                }
+                serde_json::Value::Object(row_map)
+            })
+            .collect())
+    }
+
+    async fn close(&self) -> DbResult<()> {
+        if let Some(client) = self.client.lock().await.take() {
+            // Dropping the client closes the connection, which also ends the transaction
+            if let Err(e) = client.batch_execute("ROLLBACK").await {
+                debug!("Failed to roll back cursor transaction: {}", e);
+            }
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl DatabaseConnection for PostgresConnection {
     async fn test_connection(&self) -> DbResult<()> {
-        let client = self.client.lock().await;
+        let client = self.lease().await?;
 
         timeout(DEFAULT_QUERY_TIMEOUT, client.simple_query("SELECT 1"))
             .await
@@ -394,67 +1056,245 @@ impl DatabaseConnection for PostgresConnection {
         Ok(())
     }
 
+    async fn reconnect_if_broken(&self) -> DbResult<bool> {
+        // Idle pooled clients are cheap to replace, so they are dropped without checking
+        self.pool.invalidate();
+
+        // A busy client is still in use; a query stuck on a dead socket holds it until the
+        // query times out, and the next check looks again
+        let Ok(mut client) = timeout(LIVENESS_TIMEOUT, self.client.lock()).await else {
+            debug!("Session client is busy, skipping liveness check");
+            return Ok(false);
+        };
+        let alive = !client.is_closed()
+            && matches!(
+                timeout(LIVENESS_TIMEOUT, client.simple_query("SELECT 1")).await,
+                Ok(Ok(_))
+            );
+        if alive {
+            return Ok(false);
+        }
+
+        let database = self.current_database.lock().await.clone();
+        *client = self.connect_session(&database).await?;
+
+        debug!("Re-established PostgreSQL session connection");
+        Ok(true)
+    }
+
     async fn execute_query(&self, query: &str) -> DbResult<QueryResult> {
+        let mut timer = QueryTimer::start();
         let client = self.client.lock().await;
-        let start = std::time::Instant::now();
-
-        let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(query, &[]))
+        timer.acquired();
+
+        // Streamed so the first row marks the end of server execution; rows past the limit
+        // are counted but not kept. Whether rows come back is read from the prepared
+        // statement, as keywords can't tell (SELECT nextval, FOR UPDATE, EXPLAIN ANALYZE, CALL)
+        let fetch = async {
+            let prepared = client.prepare(query).await?;
+            let stream = client
+                .query_raw(&prepared, std::iter::empty::<&(dyn ToSql + Sync)>())
+                .await?;
+            pin_mut!(stream);
+            let mut rows = Vec::new();
+            let mut total_rows = 0;
+            while let Some(row) = stream.try_next().await? {
+                if total_rows == 0 {
+                    timer.executed();
+                }
+                total_rows += 1;
+                if rows.len() < MAX_QUERY_ROWS {
+                    rows.push(row);
+                }
+            }
+            if total_rows == 0 {
+                timer.executed();
+            } else {
+                timer.fetched();
+            }
+            // Statements without a result set report their count in the command tag
+            let affected_rows = if prepared.columns().is_empty() {
+                stream.rows_affected()
+            } else {
+                None
+            };
+            Ok::<_, tokio_postgres::Error>((prepared, rows, total_rows, affected_rows))
+        };
+        let (prepared, rows, total_rows, affected_rows) = timeout(self.statement_deadline(), fetch)
             .await
             .map_err(|_| QueryError {
                 message: "Query timed out".to_string(),
                 code: Some(error_codes::TIMEOUT_ERROR.to_string()),
-            ..Default::default()
+                ..Default::default()
             })?
             .map_err(|e| QueryError {
                 message: e.to_string(),
                 code: Some(error_codes::QUERY_ERROR.to_string()),
-            ..Default::default()
+                ..Default::default()
             })?;
+        let column_metadata = Self::column_metadata(&client, prepared.columns()).await;
+        // Release the client before converting so other queries can use it
+        drop(client);
 
-        let columns: Vec<String> = if !rows.is_empty() {
-            rows[0]
-                .columns()
-                .iter()
-                .map(|col| col.name().to_string())
-                .collect()
-        } else {
-            Vec::new()
-        };
+        let columns: Vec<String> = prepared
+            .columns()
+            .iter()
+            .map(|col| col.name().to_string())
+            .collect();
 
-        let total_rows = rows.len();
         let truncated = total_rows > MAX_QUERY_ROWS;
-        let rows_to_process = if truncated { MAX_QUERY_ROWS } else { total_rows };
-
-        let mut result_rows = Vec::with_capacity(rows_to_process);
-
-        for row in rows.iter().take(rows_to_process) {
-            let mut row_map = serde_json::Map::with_capacity(columns.len());
+        let column_names = columns.clone();
+        let result_rows = convert_rows(rows, move |row: &Row| {
+            let mut row_map = serde_json::Map::with_capacity(column_names.len());
 
-            for (i, col_name) in columns.iter().enumerate() {
+            for (i, col_name) in column_names.iter().enumerate() {
                 let col_type = row.columns()[i].type_();
                 let value = Self::pg_value_to_json(row, i, col_type);
                 row_map.insert(col_name.clone(), value);
             }
 
-            result_rows.push(serde_json::Value::Object(row_map));
-        }
-
-        let execution_time = start.elapsed().as_millis();
+            serde_json::Value::Object(row_map)
+        })
+        .await?;
+        timer.converted();
 
         Ok(QueryResult {
             columns,
             rows: result_rows,
             row_count: total_rows,
-            execution_time,
+            execution_time: timer.execution_time(),
             truncated,
+            affected_rows,
+            timing: timer.timing(),
+            column_metadata,
         })
     }
 
-    async fn list_tables(&self) -> DbResult<Vec<String>> {
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult> {
+        let mut timer = QueryTimer::start();
         let client = self.client.lock().await;
+        timer.acquired();
+
+        // Preparing first gives the columns even when no rows are returned
+        let prepared = timeout(self.statement_deadline(), client.prepare(query))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        let columns: Vec<String> = prepared
+            .columns()
+            .iter()
+            .map(|col| col.name().to_string())
+            .collect();
+
+        let stream = timeout(
+            self.statement_deadline(),
+            client.query_raw(&prepared, std::iter::empty::<&(dyn ToSql + Sync)>()),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        pin_mut!(stream);
+
+        let mut chunks = ChunkedRows::new(columns.clone(), chunk_size, on_chunk);
+        let mut first_row = true;
+        while let Some(row) = stream
+            .try_next()
+            .await
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?
+        {
+            if first_row {
+                timer.executed();
+                first_row = false;
+            } else {
+                timer.fetched();
+            }
+            let mut row_map = serde_json::Map::with_capacity(columns.len());
+            for (i, col_name) in columns.iter().enumerate() {
+                let col_type = prepared.columns()[i].type_();
+                row_map.insert(col_name.clone(), Self::pg_value_to_json(&row, i, col_type));
+            }
+
+            if !chunks.push(serde_json::Value::Object(row_map)) {
+                break;
+            }
+            timer.converted();
+        }
+        if first_row {
+            timer.executed();
+        } else {
+            timer.fetched();
+        }
+
+        let affected_rows = if columns.is_empty() {
+            stream.rows_affected()
+        } else {
+            None
+        };
+        let (row_count, truncated) = chunks.finish();
+        let column_metadata = Self::column_metadata(&client, prepared.columns()).await;
+
+        Ok(QueryResult {
+            columns,
+            rows: Vec::new(),
+            row_count,
+            execution_time: timer.execution_time(),
+            truncated,
+            affected_rows,
+            timing: timer.timing(),
+            column_metadata,
+        })
+    }
+
+    async fn open_cursor(&self, query: &str) -> DbResult<Box<dyn ResultCursor>> {
+        if self.flavor == PostgresFlavor::Cockroach {
+            return Err(QueryError::with_code(
+                "CockroachDB cursors can't scroll",
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        let database = self.current_database.lock().await.clone();
+        let client = self.connect_session(&database).await?;
+
+        // Cursors only exist inside a transaction; it is kept open until the cursor is closed
+        let declare = format!(
+            "BEGIN READ ONLY; DECLARE {} SCROLL CURSOR FOR {}",
+            CURSOR_NAME,
+            query.trim().trim_end_matches(';')
+        );
+        timeout(self.statement_deadline(), client.batch_execute(&declare))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        // Preparing a FETCH describes the cursor's columns without reading any rows
+        let fetch = format!("FETCH FORWARD ALL FROM {}", CURSOR_NAME);
+        let prepared = timeout(DEFAULT_QUERY_TIMEOUT, client.prepare(&fetch))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        let columns = prepared
+            .columns()
+            .iter()
+            .map(|col| col.name().to_string())
+            .collect();
+
+        debug!("Opened PostgreSQL cursor");
+        Ok(Box::new(PostgresCursor {
+            client: Mutex::new(Some(client)),
+            columns,
+        }))
+    }
+
+    async fn list_tables(&self) -> DbResult<Vec<String>> {
+        let client = self.lease().await?;
 
         let query = "SELECT table_name FROM information_schema.tables
-                     WHERE table_schema = 'public' AND table_type = 'BASE TABLE'
+                     WHERE table_schema = current_schema() AND table_type = 'BASE TABLE'
                      ORDER BY table_name";
 
         let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(query, &[]))
@@ -479,11 +1319,19 @@ impl DatabaseConnection for PostgresConnection {
     }
 
     async fn list_databases(&self) -> DbResult<Vec<String>> {
-        let client = self.client.lock().await;
+        let client = self.lease().await?;
 
-        let query = "SELECT datname FROM pg_database
-                     WHERE datistemplate = false
-                     ORDER BY datname";
+        let query = match self.flavor {
+            PostgresFlavor::Postgres => {
+                "SELECT datname FROM pg_database
+                 WHERE datistemplate = false
+                 ORDER BY datname"
+            }
+            // pg_database is emulated on CockroachDB; SHOW DATABASES is its own listing
+            PostgresFlavor::Cockroach => {
+                "SELECT database_name FROM [SHOW DATABASES] ORDER BY database_name"
+            }
+        };
 
         let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(query, &[]))
             .await
@@ -508,15 +1356,7 @@ impl DatabaseConnection for PostgresConnection {
 
     async fn change_database(&self, database_name: &str) -> DbResult<()> {
         // PostgreSQL doesn't have USE statement, we need to reconnect
-        let new_client = Self::create_client(
-            &self.host,
-            self.port,
-            &self.username,
-            &self.password,
-            database_name,
-            &self.ssl_mode,
-        )
-        .await?;
+        let new_client = self.connect_session(database_name).await?;
 
         // Replace the client
         let mut client = self.client.lock().await;
@@ -525,53 +1365,82 @@ impl DatabaseConnection for PostgresConnection {
         // Update current database
         let mut current_db = self.current_database.lock().await;
         *current_db = database_name.to_string();
+        self.pool.invalidate();
 
         debug!("Changed database to: {}", database_name);
         Ok(())
     }
 
+    async fn set_default_schema(&self, schema: &str) -> DbResult<()> {
+        let client = self.client.lock().await;
+        Self::apply_search_path(&client, schema).await?;
+
+        *self.default_schema.lock().await = Some(schema.to_string());
+        self.pool.invalidate();
+
+        debug!("Set search_path to: {}", schema);
+        Ok(())
+    }
+
     async fn get_current_database(&self) -> DbResult<String> {
         let current_db = self.current_database.lock().await;
         Ok(current_db.clone())
     }
 
-    async fn get_table_columns(&self, table_name: &str) -> DbResult<Vec<TableColumn>> {
-        let client = self.client.lock().await;
+    async fn get_table_columns(&self, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        let client = self.lease().await?;
 
-        let query = "SELECT
+        let query = match self.flavor {
+            PostgresFlavor::Postgres => {
+                "SELECT
                         c.column_name,
                         c.udt_name,
                         c.is_nullable,
                         CASE WHEN pk.column_name IS NOT NULL THEN true ELSE false END as is_primary,
                         c.column_default,
                         c.character_maximum_length,
-                        c.numeric_precision
+                        c.numeric_precision,
+                        c.is_identity = 'YES' OR COALESCE(c.column_default LIKE 'nextval(%', false),
+                        c.identity_generation::text,
+                        c.is_generated = 'ALWAYS',
+                        c.generation_expression::text,
+                        (SELECT CASE a.attgenerated WHEN 's' THEN 'STORED' WHEN 'v' THEN 'VIRTUAL' END
+                         FROM pg_attribute a
+                         WHERE a.attrelid = format('%I.%I', c.table_schema, c.table_name)::regclass
+                           AND a.attname = c.column_name)
                      FROM information_schema.columns c
                      LEFT JOIN (
                         SELECT ku.column_name
                         FROM information_schema.table_constraints tc
                         JOIN information_schema.key_column_usage ku
                             ON tc.constraint_name = ku.constraint_name
+                            AND tc.constraint_schema = ku.constraint_schema
                         WHERE tc.constraint_type = 'PRIMARY KEY'
                             AND tc.table_name = $1
-                            AND tc.table_schema = 'public'
+                            AND tc.table_schema = COALESCE($2::text, current_schema())
                      ) pk ON c.column_name = pk.column_name
                      WHERE c.table_name = $1
-                        AND c.table_schema = 'public'
-                     ORDER BY c.ordinal_position";
+                        AND c.table_schema = COALESCE($2::text, current_schema())
+                     ORDER BY c.ordinal_position"
+            }
+            PostgresFlavor::Cockroach => COCKROACH_COLUMNS_QUERY,
+        };
 
-        let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(query, &[&table_name]))
-            .await
-            .map_err(|_| QueryError {
-                message: "Query timed out".to_string(),
-                code: Some(error_codes::TIMEOUT_ERROR.to_string()),
+        let rows = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query(query, &[&table.name, &table.schema]),
+        )
+        .await
+        .map_err(|_| QueryError {
+            message: "Query timed out".to_string(),
+            code: Some(error_codes::TIMEOUT_ERROR.to_string()),
             ..Default::default()
-            })?
-            .map_err(|e| QueryError {
-                message: e.to_string(),
-                code: Some(error_codes::QUERY_ERROR.to_string()),
+        })?
+        .map_err(|e| QueryError {
+            message: e.to_string(),
+            code: Some(error_codes::QUERY_ERROR.to_string()),
             ..Default::default()
-            })?;
+        })?;
 
         let columns: Vec<TableColumn> = rows
             .iter()
@@ -584,6 +1453,12 @@ impl DatabaseConnection for PostgresConnection {
                     column_default: row.try_get::<_, String>(4).ok(),
                     character_maximum_length: row.try_get::<_, i32>(5).ok().map(|v| v as i64),
                     numeric_precision: row.try_get::<_, i32>(6).ok().map(|v| v as i64),
+                    is_identity: row.try_get::<_, bool>(7).ok()?,
+                    identity_generation: row.try_get::<_, String>(8).ok(),
+                    is_generated: row.try_get::<_, bool>(9).ok()?,
+                    generation_expression: row.try_get::<_, String>(10).ok(),
+                    generation_kind: row.try_get::<_, String>(11).ok(),
+                    allowed_values: None,
                 })
             })
             .collect();
@@ -592,23 +1467,25 @@ impl DatabaseConnection for PostgresConnection {
     }
 
     async fn get_table_relationships(&self) -> DbResult<Vec<TableRelationship>> {
-        let client = self.client.lock().await;
+        let client = self.lease().await?;
 
         let query = "SELECT
+                        tc.table_schema AS from_schema,
                         tc.table_name AS from_table,
                         kcu.column_name AS from_column,
+                        ccu.table_schema AS to_schema,
                         ccu.table_name AS to_table,
                         ccu.column_name AS to_column,
                         tc.constraint_name
                      FROM information_schema.table_constraints tc
                      JOIN information_schema.key_column_usage kcu
                         ON tc.constraint_name = kcu.constraint_name
-                        AND tc.table_schema = kcu.table_schema
+                        AND tc.constraint_schema = kcu.constraint_schema
                      JOIN information_schema.constraint_column_usage ccu
                         ON ccu.constraint_name = tc.constraint_name
-                        AND ccu.table_schema = tc.table_schema
+                        AND ccu.constraint_schema = tc.constraint_schema
                      WHERE tc.constraint_type = 'FOREIGN KEY'
-                        AND tc.table_schema = 'public'
+                        AND tc.table_schema = current_schema()
                      ORDER BY tc.table_name";
 
         let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(query, &[]))
@@ -628,11 +1505,13 @@ impl DatabaseConnection for PostgresConnection {
             .iter()
             .filter_map(|row| {
                 Some(TableRelationship {
-                    from_table: row.try_get::<_, String>(0).ok()?,
-                    from_column: row.try_get::<_, String>(1).ok()?,
-                    to_table: row.try_get::<_, String>(2).ok()?,
-                    to_column: row.try_get::<_, String>(3).ok()?,
-                    constraint_name: row.try_get::<_, String>(4).ok()?,
+                    from_schema: row.try_get::<_, String>(0).ok(),
+                    from_table: row.try_get::<_, String>(1).ok()?,
+                    from_column: row.try_get::<_, String>(2).ok()?,
+                    to_schema: row.try_get::<_, String>(3).ok(),
+                    to_table: row.try_get::<_, String>(4).ok()?,
+                    to_column: row.try_get::<_, String>(5).ok()?,
+                    constraint_name: row.try_get::<_, String>(6).ok()?,
                 })
             })
             .collect();
@@ -640,6 +1519,385 @@ impl DatabaseConnection for PostgresConnection {
         Ok(relationships)
     }
 
+    async fn check_privileges(
+        &self,
+        table: &TableRef,
+        operation: TableOperation,
+    ) -> DbResult<bool> {
+        let client = self.lease().await?;
+
+        let query = "SELECT CASE WHEN $2 IN ('ALTER', 'DROP')
+                             THEN pg_has_role(c.relowner, 'USAGE')
+                             ELSE has_table_privilege(c.oid, $2)
+                        END
+                     FROM pg_class c
+                     WHERE c.oid = to_regclass($1::text::cstring)";
+
+        let row = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query_opt(
+                query,
+                &[&Self::qualified_name(table), &operation.privilege()],
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?
+        .ok_or_else(|| {
+            QueryError::with_code(
+                format!("Table \"{}\" not found", table),
+                error_codes::QUERY_ERROR,
+            )
+        })?;
+
+        Ok(row.get(0))
+    }
+
+    async fn get_query_statistics(&self, limit: usize) -> DbResult<Vec<QueryStatistic>> {
+        let client = self.lease().await?;
+
+        let schema: Option<String> = client
+            .query_opt(
+                "SELECT n.nspname
+                 FROM pg_extension e
+                 JOIN pg_namespace n ON n.oid = e.extnamespace
+                 WHERE e.extname = 'pg_stat_statements'",
+                &[],
+            )
+            .await
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?
+            .map(|row| row.get(0));
+        let Some(schema) = schema else {
+            return Err(QueryError::with_code(
+                "The pg_stat_statements extension is not installed in this database",
+                error_codes::QUERY_ERROR,
+            )
+            .with_hint(
+                "Add pg_stat_statements to shared_preload_libraries and run \
+                 CREATE EXTENSION pg_stat_statements",
+            ));
+        };
+
+        // The timing columns were renamed in PostgreSQL 13
+        let version: i32 = client
+            .query_one("SELECT current_setting('server_version_num')::int", &[])
+            .await
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?
+            .get(0);
+        let (total, mean) = if version >= 130000 {
+            ("total_exec_time", "mean_exec_time")
+        } else {
+            ("total_time", "mean_time")
+        };
+        let query = format!(
+            "SELECT d.datname, s.query, s.calls, s.{total}, s.{mean}, s.rows
+             FROM \"{schema}\".pg_stat_statements s
+             LEFT JOIN pg_database d ON d.oid = s.dbid
+             ORDER BY s.{total} DESC
+             LIMIT $1",
+            total = total,
+            mean = mean,
+            schema = Self::escape_identifier(&schema)
+        );
+
+        let rows = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query(&query, &[&(limit as i64)]),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| QueryStatistic {
+                database: row.get(0),
+                query: row.get::<_, Option<String>>(1).unwrap_or_default(),
+                calls: row.get::<_, i64>(2) as u64,
+                total_time_ms: row.get(3),
+                mean_time_ms: row.get(4),
+                rows: row.get::<_, i64>(5) as u64,
+            })
+            .collect())
+    }
+
+    async fn suggest_indexes(&self, query: &str) -> DbResult<Vec<IndexSuggestion>> {
+        index_advisor::check_explainable(query)?;
+        let client = self.lease().await?;
+
+        // VERBOSE adds each table's schema and qualifies the columns in conditions
+        let explain = format!("EXPLAIN (VERBOSE, FORMAT JSON) {}", query);
+        let plan: serde_json::Value =
+            timeout(DEFAULT_QUERY_TIMEOUT, client.query_one(&explain, &[]))
+                .await
+                .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+                .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?
+                .get(0);
+
+        let mut scans = Vec::new();
+        let mut conditions = Vec::new();
+        if let Some(root) = plan.get(0).and_then(|p| p.get("Plan")) {
+            Self::collect_plan_nodes(root, &mut scans, &mut conditions);
+        }
+        let refs: Vec<_> = conditions
+            .iter()
+            .flat_map(|condition| index_advisor::expression_columns(condition))
+            .collect();
+
+        let mut large_scans: Vec<(TableRef, String, u64)> = Vec::new();
+        for (table, alias) in scans {
+            if large_scans.iter().any(|(scanned, _, _)| *scanned == table) {
+                continue;
+            }
+            let estimated_rows = timeout(
+                DEFAULT_QUERY_TIMEOUT,
+                client.query_opt(
+                    "SELECT GREATEST(reltuples, 0)::bigint FROM pg_class WHERE oid = to_regclass($1::text::cstring)",
+                    &[&Self::qualified_name(&table)],
+                ),
+            )
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?
+            .map_or(0, |row| row.get::<_, i64>(0) as u64);
+            if estimated_rows >= index_advisor::LARGE_TABLE_ROWS {
+                large_scans.push((table, alias, estimated_rows));
+            }
+        }
+        // Column lookups lease their own client
+        drop(client);
+
+        let mut suggestions = Vec::with_capacity(large_scans.len());
+        for (table, alias, estimated_rows) in large_scans {
+            let table_columns = self.get_table_columns(&table).await?;
+            let columns =
+                index_advisor::candidate_columns(&refs, &[&alias, &table.name], &table_columns);
+            let create_statement = (!columns.is_empty()).then(|| {
+                let column_list: Vec<String> = columns
+                    .iter()
+                    .map(|c| format!("\"{}\"", Self::escape_identifier(c)))
+                    .collect();
+                format!(
+                    "CREATE INDEX \"{}\" ON {} ({})",
+                    Self::escape_identifier(&index_advisor::index_name(&table, &columns)),
+                    Self::qualified_name(&table),
+                    column_list.join(", ")
+                )
+            });
+            suggestions.push(IndexSuggestion {
+                table,
+                estimated_rows,
+                columns,
+                create_statement,
+            });
+        }
+
+        Ok(suggestions)
+    }
+
+    async fn get_bloat_report(&self) -> DbResult<Vec<TableBloat>> {
+        let client = self.lease().await?;
+
+        let query = "SELECT schemaname, relname, n_live_tup, n_dead_tup,
+                        pg_total_relation_size(relid), pg_table_size(relid)
+                     FROM pg_stat_user_tables";
+
+        let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(query, &[]))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        let mut report: Vec<TableBloat> = rows
+            .iter()
+            .map(|row| {
+                let table = TableRef::new(row.get(0), row.get::<_, String>(1));
+                let live_rows = row.get::<_, i64>(2).max(0) as u64;
+                let dead_rows = row.get::<_, i64>(3).max(0) as u64;
+                let table_bytes = row.get::<_, i64>(4).max(0) as u64;
+                let heap_bytes = row.get::<_, i64>(5).max(0) as u64;
+                // Dead rows take about as much space as live ones
+                let wasted_bytes = match live_rows + dead_rows {
+                    0 => 0,
+                    total => (heap_bytes as f64 * dead_rows as f64 / total as f64) as u64,
+                };
+
+                let mut bloat =
+                    TableBloat::new(table, live_rows, Some(dead_rows), table_bytes, wasted_bytes);
+                if bloat.needs_maintenance() {
+                    bloat.recommendation = Some(format!(
+                        "VACUUM (ANALYZE) {}",
+                        Self::qualified_name(&bloat.table)
+                    ));
+                }
+                bloat
+            })
+            .collect();
+        report.sort_by_key(|bloat| std::cmp::Reverse(bloat.wasted_bytes));
+
+        Ok(report)
+    }
+
+    async fn get_deadlocks(&self) -> DbResult<DeadlockReport> {
+        let client = self.lease().await?;
+
+        let total_deadlocks = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query_opt(
+                "SELECT deadlocks FROM pg_stat_database WHERE datname = current_database()",
+                &[],
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?
+        .map(|row| row.get::<_, i64>(0).max(0) as u64);
+
+        // Deadlock details only go to the server log
+        let log_file: Option<String> = match client
+            .query_one("SELECT pg_current_logfile('stderr')", &[])
+            .await
+        {
+            Ok(row) => row.get(0),
+            Err(e) => {
+                debug!("Could not locate the server log: {}", e);
+                None
+            }
+        };
+        let Some(log_file) = log_file else {
+            return Ok(DeadlockReport {
+                total_deadlocks,
+                deadlocks: Vec::new(),
+                note: Some(
+                    "The server does not log to a file this user can locate; deadlock \
+                     details are only in the server log."
+                        .to_string(),
+                ),
+            });
+        };
+
+        // Read as bytes, since the tail may start inside a multi-byte character
+        let tail = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query_one(
+                "SELECT pg_read_binary_file($1, GREATEST((pg_stat_file($1)).size - $2, 0), $2)",
+                &[&log_file, &(LOG_TAIL_BYTES as i64)],
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?;
+        match tail {
+            Ok(row) => {
+                let log: Vec<u8> = row.get(0);
+                Ok(DeadlockReport {
+                    total_deadlocks,
+                    deadlocks: deadlocks::parse_postgres_log(&String::from_utf8_lossy(&log)),
+                    note: Some(format!(
+                        "Only the end of the current server log ({}) is searched.",
+                        log_file
+                    )),
+                })
+            }
+            Err(e) => Ok(DeadlockReport {
+                total_deadlocks,
+                deadlocks: Vec::new(),
+                note: Some(format!(
+                    "Could not read the server log ({}): {}. Reading it needs the \
+                     pg_read_server_files role.",
+                    log_file,
+                    pg_error_to_query_error(e, error_codes::QUERY_ERROR).message
+                )),
+            }),
+        }
+    }
+
+    async fn get_active_transactions(&self) -> DbResult<Vec<ActiveTransaction>> {
+        let client = self.lease().await?;
+
+        let query = "SELECT pid, usename::text, client_addr::text, datname::text, state,
+                        xact_start::text,
+                        GREATEST(EXTRACT(EPOCH FROM now() - xact_start), 0)::bigint,
+                        query
+                     FROM pg_stat_activity
+                     WHERE xact_start IS NOT NULL AND pid <> pg_backend_pid()
+                     ORDER BY xact_start";
+
+        let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(query, &[]))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ActiveTransaction {
+                session_id: row.get::<_, i32>(0) as u64,
+                user: row.get(1),
+                client: row.get(2),
+                database: row.get(3),
+                // Sessions of other users show no state without pg_read_all_stats
+                state: row
+                    .get::<_, Option<String>>(4)
+                    .unwrap_or_else(|| "unknown".to_string()),
+                started_at: row.get(5),
+                age_secs: row.get::<_, i64>(6) as u64,
+                statement: row.get(7),
+            })
+            .collect())
+    }
+
+    async fn get_health_snapshot(&self) -> DbResult<HealthSnapshot> {
+        let client = self.lease().await?;
+
+        let stats_query = "SELECT
+                (SELECT count(*) FROM pg_stat_activity WHERE backend_type = 'client backend'),
+                (SELECT count(*) FROM pg_stat_activity
+                    WHERE backend_type = 'client backend' AND state = 'active'),
+                current_setting('max_connections')::bigint,
+                (SELECT sum(blks_hit)::float8 / NULLIF(sum(blks_hit) + sum(blks_read), 0)
+                    FROM pg_stat_database),
+                CASE WHEN pg_is_in_recovery()
+                    THEN EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp())::float8
+                END";
+        let sizes_query = "SELECT datname::text, pg_database_size(oid)
+                           FROM pg_database
+                           WHERE datallowconn AND has_database_privilege(oid, 'CONNECT')
+                           ORDER BY 2 DESC";
+
+        // Both statements are sent before either result is read, in one round trip
+        let stats = async {
+            client
+                .query_one(stats_query, &[])
+                .await
+                .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))
+        };
+        let sizes = async {
+            client
+                .query(sizes_query, &[])
+                .await
+                .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))
+        };
+        let (stats, sizes, transactions) = timeout(DEFAULT_QUERY_TIMEOUT, async {
+            tokio::try_join!(stats, sizes, self.get_active_transactions())
+        })
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))??;
+
+        Ok(HealthSnapshot {
+            total_connections: stats.get::<_, i64>(0) as u64,
+            active_connections: stats.get::<_, i64>(1) as u64,
+            max_connections: Some(stats.get::<_, i64>(2) as u64),
+            cache_hit_ratio: stats.get(3),
+            replication_lag_secs: stats.get(4),
+            longest_transaction: transactions.into_iter().next(),
+            database_sizes: sizes
+                .iter()
+                .map(|row| DatabaseSize {
+                    name: row.get(0),
+                    bytes: row.get::<_, i64>(1) as u64,
+                })
+                .collect(),
+        })
+    }
+
     async fn disconnect(&self) -> DbResult<()> {
         // PostgreSQL client automatically disconnects when dropped
         debug!("PostgreSQL connection disconnected");
@@ -648,22 +1906,22 @@ impl DatabaseConnection for PostgresConnection {
 
     async fn update_cell(
         &self,
-        table_name: &str,
+        table: &TableRef,
         column_name: &str,
         new_value: Option<&str>,
         primary_key_column: &str,
         primary_key_value: &str,
     ) -> DbResult<String> {
+        // The session client, so the edit sees the session's role and settings and joins an
+        // open transaction
         let client = self.client.lock().await;
 
-        // Build UPDATE query with proper escaping
-        // We use simple_query to avoid type inference issues with parameterized queries
-        // since we don't know the column type and need PostgreSQL to handle the conversion
-        let query = match new_value {
+        // Build the logged query with actual values for display purposes
+        let logged_query = match new_value {
             Some(value) => {
                 format!(
-                    "UPDATE \"{}\" SET \"{}\" = '{}' WHERE \"{}\" = '{}'",
-                    Self::escape_identifier(table_name),
+                    "UPDATE {} SET \"{}\" = '{}' WHERE \"{}\" = '{}'",
+                    Self::qualified_name(table),
                     Self::escape_identifier(column_name),
                     Self::escape_string(value),
                     Self::escape_identifier(primary_key_column),
@@ -672,8 +1930,8 @@ impl DatabaseConnection for PostgresConnection {
             }
             None => {
                 format!(
-                    "UPDATE \"{}\" SET \"{}\" = NULL WHERE \"{}\" = '{}'",
-                    Self::escape_identifier(table_name),
+                    "UPDATE {} SET \"{}\" = NULL WHERE \"{}\" = '{}'",
+                    Self::qualified_name(table),
                     Self::escape_identifier(column_name),
                     Self::escape_identifier(primary_key_column),
                     Self::escape_string(primary_key_value)
@@ -681,17 +1939,33 @@ impl DatabaseConnection for PostgresConnection {
             }
         };
 
-        debug!("Executing update query: {}", query);
+        let column_types =
+            Self::column_types(&client, table, &[column_name, primary_key_column]).await?;
+        let qualified_table = Self::qualified_name(table);
+
+        let query = format!(
+            "UPDATE {} SET \"{}\" = CAST($1::text AS {}) WHERE \"{}\" = CAST($2::text AS {})",
+            qualified_table,
+            Self::escape_identifier(column_name),
+            column_types[0],
+            Self::escape_identifier(primary_key_column),
+            column_types[1]
+        );
 
-        timeout(DEFAULT_QUERY_TIMEOUT, client.simple_query(&query))
-            .await
-            .map_err(|_| {
-                QueryError::with_code("Update operation timed out", error_codes::TIMEOUT_ERROR)
-                    .with_hint("The database took too long to respond. Try again or check database load.")
-            })?
-            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Executing update query: {}", query);
 
-        Ok(query)
+        timeout(
+            self.statement_deadline(),
+            client.execute(&query, &[&new_value, &primary_key_value]),
+        )
+        .await
+        .map_err(|_| {
+            QueryError::with_code("Update operation timed out", error_codes::TIMEOUT_ERROR)
+                .with_hint("The database took too long to respond. Try again or check database load.")
+        })?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        Ok(logged_query)
     }
 
     async fn export_database_with_options(
@@ -699,41 +1973,80 @@ impl DatabaseConnection for PostgresConnection {
         include_drop: bool,
         include_create: bool,
         data_mode: &str,
-        selected_tables: &[String],
+        selected_tables: &[TableRef],
         max_insert_size: usize,
     ) -> DbResult<String> {
-        let client = self.client.lock().await;
+        let mut client = self.lease().await?;
         let mut sql_content = String::with_capacity(1024 * 1024);
 
+        // Read every table from one snapshot so writes made while the export pages through
+        // the data don't leave the dump inconsistent. Dropping the transaction on an early
+        // return rolls it back before the client is reused.
+        let transaction = client
+            .build_transaction()
+            .isolation_level(IsolationLevel::RepeatableRead)
+            .read_only(true)
+            .start()
+            .await
+            .map_err(|e| QueryError {
+                message: e.to_string(),
+                code: Some(error_codes::QUERY_ERROR.to_string()),
+                ..Default::default()
+            })?;
+
         let tables_to_export = if selected_tables.is_empty() {
             let query = "SELECT table_name FROM information_schema.tables
-                         WHERE table_schema = 'public' AND table_type = 'BASE TABLE'
+                         WHERE table_schema = current_schema() AND table_type = 'BASE TABLE'
                          ORDER BY table_name";
 
-            let rows = client.query(query, &[]).await.map_err(|e| QueryError {
-                message: e.to_string(),
-                code: Some(error_codes::QUERY_ERROR.to_string()),
-            ..Default::default()
-            })?;
+            let rows = transaction
+                .query(query, &[])
+                .await
+                .map_err(|e| QueryError {
+                    message: e.to_string(),
+                    code: Some(error_codes::QUERY_ERROR.to_string()),
+                    ..Default::default()
+                })?;
 
             rows.iter()
                 .filter_map(|row| row.try_get::<_, String>(0).ok())
+                .map(TableRef::unqualified)
                 .collect()
         } else {
             selected_tables.to_vec()
         };
 
-        for table_name in tables_to_export {
-            sql_content.push_str(&format!("\n-- Table: {}\n", table_name));
+        for table in tables_to_export {
+            let qualified_table = Self::qualified_name(&table);
+            sql_content.push_str(&format!("\n-- Table: {}\n", table));
 
             if include_drop {
                 sql_content.push_str(&format!(
-                    "DROP TABLE IF EXISTS \"{}\" CASCADE;\n",
-                    Self::escape_identifier(&table_name)
+                    "DROP TABLE IF EXISTS {} CASCADE;\n",
+                    qualified_table
                 ));
             }
 
-            if include_create {
+            if include_create && self.flavor == PostgresFlavor::Cockroach {
+                // information_schema can't describe the primary key, column families or
+                // computed columns, so CockroachDB writes the statement itself
+                let row = transaction
+                    .query_one(
+                        &format!(
+                            "SELECT create_statement FROM [SHOW CREATE TABLE {}]",
+                            qualified_table
+                        ),
+                        &[],
+                    )
+                    .await
+                    .map_err(|e| QueryError {
+                        message: e.to_string(),
+                        code: Some(error_codes::QUERY_ERROR.to_string()),
+                        ..Default::default()
+                    })?;
+                sql_content.push_str(&row.get::<_, String>(0));
+                sql_content.push_str(";\n\n");
+            } else if include_create {
                 let columns_query = "SELECT
                         column_name,
                         data_type,
@@ -741,11 +2054,12 @@ impl DatabaseConnection for PostgresConnection {
                         is_nullable,
                         column_default
                      FROM information_schema.columns
-                     WHERE table_name = $1 AND table_schema = 'public'
+                     WHERE table_name = $1
+                        AND table_schema = COALESCE($2::text, current_schema())
                      ORDER BY ordinal_position";
 
-                let col_rows = client
-                    .query(columns_query, &[&table_name])
+                let col_rows = transaction
+                    .query(columns_query, &[&table.name, &table.schema])
                     .await
                     .map_err(|e| QueryError {
                         message: e.to_string(),
@@ -753,10 +2067,7 @@ impl DatabaseConnection for PostgresConnection {
             ..Default::default()
                     })?;
 
-                sql_content.push_str(&format!(
-                    "CREATE TABLE \"{}\" (\n",
-                    Self::escape_identifier(&table_name)
-                ));
+                sql_content.push_str(&format!("CREATE TABLE {} (\n", qualified_table));
 
                 let col_defs: Vec<String> = col_rows
                     .iter()
@@ -799,17 +2110,19 @@ impl DatabaseConnection for PostgresConnection {
 
                 loop {
                     let data_query = format!(
-                        "SELECT * FROM \"{}\" LIMIT {} OFFSET {}",
-                        Self::escape_identifier(&table_name),
-                        BATCH_SIZE,
-                        offset
+                        "SELECT * FROM {} LIMIT {} OFFSET {}",
+                        qualified_table, BATCH_SIZE, offset
                     );
 
-                    let data_rows = client.query(&data_query, &[]).await.map_err(|e| QueryError {
-                        message: e.to_string(),
-                        code: Some(error_codes::QUERY_ERROR.to_string()),
-            ..Default::default()
-                    })?;
+                    let data_rows =
+                        transaction
+                            .query(&data_query, &[])
+                            .await
+                            .map_err(|e| QueryError {
+                                message: e.to_string(),
+                                code: Some(error_codes::QUERY_ERROR.to_string()),
+                                ..Default::default()
+                            })?;
 
                     if data_rows.is_empty() {
                         break;
@@ -839,7 +2152,7 @@ impl DatabaseConnection for PostgresConnection {
 
                         if row_buffer.len() >= max_insert_size {
                             sql_content.push_str(&Self::format_insert_statement(
-                                &table_name,
+                                &table,
                                 &columns,
                                 &row_buffer,
                                 data_mode,
@@ -850,7 +2163,7 @@ impl DatabaseConnection for PostgresConnection {
 
                     if !row_buffer.is_empty() {
                         sql_content.push_str(&Self::format_insert_statement(
-                            &table_name,
+                            &table,
                             &columns,
                             &row_buffer,
                             data_mode,
@@ -868,6 +2181,1046 @@ impl DatabaseConnection for PostgresConnection {
             }
         }
 
+        transaction.commit().await.map_err(|e| QueryError {
+            message: e.to_string(),
+            code: Some(error_codes::QUERY_ERROR.to_string()),
+            ..Default::default()
+        })?;
+
         Ok(sql_content)
     }
+
+    async fn insert_rows(
+        &self,
+        table: &TableRef,
+        columns: &[String],
+        rows: &[Vec<Option<String>>],
+    ) -> DbResult<u64> {
+        let mut client = self.lease().await?;
+        let column_names: Vec<&str> = columns.iter().map(String::as_str).collect();
+        let column_types = Self::column_types(&client, table, &column_names).await?;
+        let column_list = columns
+            .iter()
+            .map(|name| format!("\"{}\"", Self::escape_identifier(name)))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        // All or nothing: dropping the transaction on an early return rolls it back
+        let transaction = client
+            .transaction()
+            .await
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        let mut inserted = 0;
+        for batch in rows.chunks(bulk_insert::batch_size(columns.len())) {
+            let mut params: Vec<&(dyn ToSql + Sync)> =
+                Vec::with_capacity(batch.len() * columns.len());
+            let values = batch
+                .iter()
+                .map(|row| {
+                    let placeholders = row
+                        .iter()
+                        .zip(&column_types)
+                        .map(|(value, column_type)| {
+                            params.push(value);
+                            format!("CAST(${}::text AS {})", params.len(), column_type)
+                        })
+                        .collect::<Vec<_>>();
+                    format!("({})", placeholders.join(", "))
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let query = format!(
+                "INSERT INTO {} ({}) VALUES {}",
+                Self::qualified_name(table),
+                column_list,
+                values
+            );
+
+            inserted += timeout(
+                self.statement_deadline(),
+                transaction.execute(&query, &params),
+            )
+            .await
+            .map_err(|_| QueryError::with_code("Insert timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Inserted {} rows into {}", inserted, table);
+        Ok(inserted)
+    }
+
+    async fn call_routine(
+        &self,
+        routine: &TableRef,
+        args: &HashMap<String, Option<String>>,
+    ) -> DbResult<RoutineResult> {
+        // The session client, so the call sees the session's settings and transaction
+        let client = self.client.lock().await;
+        let signatures = Self::routine_signatures(&client, routine).await?;
+        let (signature, arguments) = routines::resolve_call(routine, &signatures, args)?;
+
+        let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+        let mut rendered = Vec::new();
+        let bound = signature
+            .parameters
+            .iter()
+            .filter(|p| !(signature.kind == RoutineKind::Function && p.mode == ParameterMode::Out));
+        for (parameter, argument) in bound.zip(&arguments) {
+            let value = match argument {
+                Argument::Omitted => continue,
+                Argument::Output => "NULL".to_string(),
+                Argument::Value(value) => {
+                    params.push(value);
+                    format!("CAST(${}::text AS {})", params.len(), parameter.data_type)
+                }
+            };
+            rendered.push(match &parameter.name {
+                Some(name) => format!("\"{}\" => {}", Self::escape_identifier(name), value),
+                None => value,
+            });
+        }
+        let query = match signature.kind {
+            RoutineKind::Procedure => format!(
+                "CALL {}({})",
+                Self::qualified_name(routine),
+                rendered.join(", ")
+            ),
+            RoutineKind::Function => format!(
+                "SELECT * FROM {}({})",
+                Self::qualified_name(routine),
+                rendered.join(", ")
+            ),
+        };
+        debug!("Calling routine: {}", query);
+
+        let started = std::time::Instant::now();
+        let rows = timeout(self.statement_deadline(), client.query(&query, &params))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        drop(client);
+        let result = Self::rows_to_result(rows, started.elapsed().as_millis()).await?;
+
+        // Procedures return their OUT and INOUT parameters as a single row
+        Ok(match signature.kind {
+            RoutineKind::Procedure => RoutineResult {
+                output: match result.rows.into_iter().next() {
+                    Some(serde_json::Value::Object(output)) => output,
+                    _ => serde_json::Map::new(),
+                },
+                ..Default::default()
+            },
+            RoutineKind::Function => RoutineResult {
+                result_sets: vec![result],
+                ..Default::default()
+            },
+        })
+    }
+
+    async fn get_routine_definition(&self, routine: &TableRef) -> DbResult<String> {
+        let client = self.lease().await?;
+        // Aggregates and window functions have no source pg_get_functiondef can print
+        let query = "SELECT string_agg(pg_get_functiondef(p.oid) || ';', E'\\n\\n' ORDER BY p.oid)
+                     FROM pg_proc p
+                     JOIN pg_namespace n ON n.oid = p.pronamespace
+                     WHERE n.nspname = COALESCE($1::text, current_schema())
+                       AND p.proname = $2::text
+                       AND p.prokind IN ('f', 'p')";
+        let row = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query_one(query, &[&routine.schema, &routine.name]),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        row.get::<_, Option<String>>(0).ok_or_else(|| {
+            QueryError::with_code(
+                format!("Routine \"{}\" not found", routine),
+                error_codes::QUERY_ERROR,
+            )
+        })
+    }
+
+    async fn save_routine_definition(
+        &self,
+        _replaces: Option<&TableRef>,
+        source: &str,
+    ) -> DbResult<()> {
+        // DDL is transactional, so a failing statement leaves the routines as they were
+        let mut client = self.lease().await?;
+        let transaction = client
+            .transaction()
+            .await
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        timeout(DEFAULT_QUERY_TIMEOUT, transaction.batch_execute(source))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        transaction
+            .commit()
+            .await
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Saved routine definition");
+        Ok(())
+    }
+
+    async fn get_view_definition(&self, view: &TableRef) -> DbResult<String> {
+        let client = self.lease().await?;
+        let query = "SELECT pg_get_viewdef(c.oid, true)
+                     FROM pg_class c
+                     JOIN pg_namespace n ON n.oid = c.relnamespace
+                     WHERE n.nspname = COALESCE($1::text, current_schema())
+                       AND c.relname = $2::text
+                       AND c.relkind = 'v'";
+        let row = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query_opt(query, &[&view.schema, &view.name]),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        let row = row.ok_or_else(|| {
+            QueryError::with_code(
+                format!("View \"{}\" not found", view),
+                error_codes::QUERY_ERROR,
+            )
+        })?;
+        let definition: String = row.get(0);
+        Ok(definition.trim().trim_end_matches(';').to_string())
+    }
+
+    async fn alter_view(&self, view: &TableRef, query: &str) -> DbResult<()> {
+        let client = self.lease().await?;
+        timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.batch_execute(&format!("EXPLAIN {}", query)),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        let statement = format!(
+            "CREATE OR REPLACE VIEW {} AS {}",
+            Self::qualified_name(view),
+            query
+        );
+        timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Redefined view {}", view);
+        Ok(())
+    }
+
+    async fn create_trigger(&self, spec: &TriggerSpec) -> DbResult<()> {
+        let ddl = self.trigger_ddl(spec)?;
+
+        // Both statements or neither
+        let mut client = self.lease().await?;
+        let transaction = client
+            .transaction()
+            .await
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        timeout(DEFAULT_QUERY_TIMEOUT, transaction.batch_execute(&ddl))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        transaction
+            .commit()
+            .await
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Created trigger {} on {}", spec.name, spec.table);
+        Ok(())
+    }
+
+    fn trigger_ddl(&self, spec: &TriggerSpec) -> DbResult<String> {
+        spec.validate()?;
+        if spec.body.contains("$bloatsql$") {
+            return Err(QueryError::with_code(
+                "Trigger body can't contain $bloatsql$",
+                error_codes::QUERY_ERROR,
+            ));
+        }
+
+        let function =
+            Self::qualified_name(&TableRef::new(spec.table.schema.clone(), spec.name.clone()));
+        let level = match spec.level {
+            TriggerLevel::Row => "ROW",
+            TriggerLevel::Statement => "STATEMENT",
+        };
+        let when = match &spec.when {
+            Some(condition) => format!(" WHEN ({})", condition),
+            None => String::new(),
+        };
+        Ok(format!(
+            "CREATE FUNCTION {function}() RETURNS trigger LANGUAGE plpgsql AS $bloatsql$
+BEGIN
+{body}
+END
+$bloatsql$;
+CREATE TRIGGER \"{name}\" {timing} {events} ON {table} FOR EACH {level}{when} EXECUTE FUNCTION {function}();",
+            function = function,
+            body = spec.body.trim(),
+            name = Self::escape_identifier(&spec.name),
+            timing = spec.timing.keyword(),
+            events = spec.events_clause(),
+            table = Self::qualified_name(&spec.table),
+            level = level,
+            when = when,
+        ))
+    }
+
+    async fn drop_trigger(&self, name: &str, table: &TableRef) -> DbResult<String> {
+        let mut client = self.lease().await?;
+        let transaction = client
+            .transaction()
+            .await
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        // The function is the trigger's own if it is named after it and no other trigger
+        // uses it
+        let query = "SELECT t.tgfoid::regprocedure::text,
+                            p.proname = t.tgname AND NOT EXISTS (
+                                SELECT 1 FROM pg_trigger o
+                                WHERE o.tgfoid = t.tgfoid AND o.oid <> t.oid
+                            )
+                     FROM pg_trigger t
+                     JOIN pg_proc p ON p.oid = t.tgfoid
+                     WHERE t.tgrelid = $1::text::regclass AND t.tgname = $2::text";
+        let row = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            transaction.query_opt(query, &[&Self::qualified_name(table), &name]),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?
+        .ok_or_else(|| {
+            QueryError::with_code(
+                format!("Trigger \"{}\" not found on {}", name, table),
+                error_codes::QUERY_ERROR,
+            )
+        })?;
+        let function: String = row.get(0);
+        let owns_function: bool = row.get(1);
+
+        let mut ddl = format!("{};", self.drop_trigger_ddl(name, table)?);
+        if owns_function {
+            ddl.push_str(&format!("\nDROP FUNCTION {};", function));
+        }
+        timeout(DEFAULT_QUERY_TIMEOUT, transaction.batch_execute(&ddl))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        transaction
+            .commit()
+            .await
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Dropped trigger {} on {}", name, table);
+        Ok(ddl)
+    }
+
+    fn drop_trigger_ddl(&self, name: &str, table: &TableRef) -> DbResult<String> {
+        Ok(format!(
+            "DROP TRIGGER \"{}\" ON {}",
+            Self::escape_identifier(name),
+            Self::qualified_name(table)
+        ))
+    }
+
+    async fn reset_identity(&self, table: &TableRef, value: Option<i64>) -> DbResult<i64> {
+        let client = self.lease().await?;
+        let qualified = Self::qualified_name(table);
+        let query = "SELECT a.attname::text, pg_get_serial_sequence($1, a.attname)
+                     FROM pg_attribute a
+                     WHERE a.attrelid = $1::regclass
+                       AND a.attnum > 0
+                       AND NOT a.attisdropped
+                       AND pg_get_serial_sequence($1, a.attname) IS NOT NULL
+                     ORDER BY a.attnum";
+        let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(query, &[&qualified]))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        if rows.is_empty() {
+            return Err(QueryError::with_code(
+                format!("Table {} has no identity column", table),
+                error_codes::QUERY_ERROR,
+            ));
+        }
+
+        let mut first = None;
+        for row in rows {
+            let column: String = row.get(0);
+            let sequence: String = row.get(1);
+            let next = match value {
+                Some(value) => value,
+                None => {
+                    let query = format!(
+                        "SELECT COALESCE(MAX(\"{}\"), 0)::bigint + 1 FROM {}",
+                        Self::escape_identifier(&column),
+                        qualified
+                    );
+                    timeout(DEFAULT_QUERY_TIMEOUT, client.query_one(&query, &[]))
+                        .await
+                        .map_err(|_| {
+                            QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR)
+                        })?
+                        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?
+                        .get(0)
+                }
+            };
+            // pg_get_serial_sequence returns the sequence name already quoted
+            let statement = format!("ALTER SEQUENCE {} RESTART WITH {}", sequence, next);
+            timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement))
+                .await
+                .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+                .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+            debug!("Reset identity {}.{} to {}", table, column, next);
+            first.get_or_insert(next);
+        }
+        Ok(first.unwrap_or_default())
+    }
+
+    async fn get_roles(&self) -> DbResult<SessionRoles> {
+        let client = self.lease().await?;
+        // Built-in pg_* roles are left out; superusers are members of every role
+        let query = "SELECT rolname::text FROM pg_roles
+                     WHERE pg_has_role(session_user, oid, 'MEMBER')
+                       AND rolname <> session_user
+                       AND rolname NOT LIKE 'pg\\_%'
+                     ORDER BY rolname";
+        let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(query, &[]))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        Ok(SessionRoles {
+            user: self.username.clone(),
+            role: self.role.lock().await.clone(),
+            available: rows.iter().map(|row| row.get(0)).collect(),
+        })
+    }
+
+    async fn set_role(&self, role: Option<&str>) -> DbResult<()> {
+        let client = self.client.lock().await;
+        Self::apply_role(&client, role).await?;
+        *self.role.lock().await = role.map(str::to_string);
+        // Pooled clients get the role when they are next connected
+        self.pool.invalidate();
+
+        debug!("Set role to {:?}", role);
+        Ok(())
+    }
+
+    async fn get_session_variables(&self, filter: Option<&str>) -> DbResult<Vec<SessionVariable>> {
+        // The session client, where variables set by the user's own queries live too
+        let client = self.client.lock().await;
+        let query = "SELECT name::text, setting, unit, short_desc,
+                            context IN ('user', 'superuser')
+                     FROM pg_settings
+                     WHERE $1::text IS NULL OR strpos(lower(name), lower($1::text)) > 0
+                     ORDER BY name";
+        let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(query, &[&filter]))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| SessionVariable {
+                name: row.get(0),
+                value: row.get::<_, Option<String>>(1).unwrap_or_default(),
+                unit: row.get(2),
+                description: row.get(3),
+                settable: row.get(4),
+            })
+            .collect())
+    }
+
+    async fn set_session_variable(&self, name: &str, value: &str) -> DbResult<()> {
+        session_variables::check_name(name)?;
+        let client = self.client.lock().await;
+        Self::apply_variable(&client, name, value).await?;
+        session_variables::remember(&mut *self.session_variables.lock().await, name, value);
+        // Pooled clients get the variable when they are next connected
+        self.pool.invalidate();
+
+        debug!("Set session variable {} to {}", name, value);
+        Ok(())
+    }
+
+    async fn aggregate_table(&self, request: &AggregateRequest) -> DbResult<ChartData> {
+        let client = self.lease().await?;
+        let filtered: Vec<&str> = request
+            .filters
+            .iter()
+            .map(|filter| filter.column.as_str())
+            .collect();
+        let types = Self::column_types(&client, &request.table, &filtered).await?;
+        let dialect = AggregateDialect {
+            columns: filtered.into_iter().zip(types).collect(),
+        };
+        let (query, params) =
+            aggregation::build_query(request, &Self::qualified_name(&request.table), &dialect)?;
+        debug!("Aggregating: {}", query);
+
+        let params: Vec<&(dyn ToSql + Sync)> =
+            params.iter().map(|p| p as &(dyn ToSql + Sync)).collect();
+        let started = std::time::Instant::now();
+        let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(&query, &params))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        drop(client);
+        let result = Self::rows_to_result(rows, started.elapsed().as_millis()).await?;
+        Ok(aggregation::to_chart(request, &result))
+    }
+
+    async fn get_text_search_configs(&self) -> DbResult<Vec<String>> {
+        let client = self.lease().await?;
+        let query = "SELECT CASE WHEN n.nspname = 'pg_catalog' THEN c.cfgname::text
+                                 ELSE format('%I.%I', n.nspname, c.cfgname) END
+                     FROM pg_ts_config c
+                     JOIN pg_namespace n ON n.oid = c.cfgnamespace
+                     ORDER BY 1";
+        let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(query, &[]))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    async fn build_text_search(&self, request: &TextSearchRequest) -> DbResult<String> {
+        request.validate()?;
+        let columns: Vec<String> = request
+            .columns
+            .iter()
+            .map(|column| format!("\"{}\"", Self::escape_identifier(column)))
+            .collect();
+        let config = request
+            .config
+            .as_deref()
+            .map(|config| format!("'{}'", Self::escape_string(config)));
+        Ok(text_search::tsvector_query(
+            request,
+            &Self::qualified_name(&request.table),
+            &columns,
+            config.as_deref(),
+            &format!("'{}'", Self::escape_string(&request.query)),
+        ))
+    }
+
+    async fn get_rls_policies(&self, table: &TableRef) -> DbResult<RlsStatus> {
+        let client = self.lease().await?;
+        let query =
+            "SELECT n.nspname::text, c.relname::text, c.relrowsecurity, c.relforcerowsecurity,
+                            r.rolsuper OR r.rolbypassrls
+                                OR (NOT c.relforcerowsecurity AND pg_has_role(c.relowner, 'USAGE'))
+                     FROM pg_class c
+                     JOIN pg_namespace n ON n.oid = c.relnamespace
+                     JOIN pg_roles r ON r.rolname = current_user
+                     WHERE c.oid = $1::text::regclass";
+        let name = Self::qualified_name(table);
+        let row = timeout(DEFAULT_QUERY_TIMEOUT, client.query_one(query, &[&name]))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        let (schema, relation): (String, String) = (row.get(0), row.get(1));
+
+        let query = "SELECT policyname::text, permissive = 'PERMISSIVE', roles::text[], cmd,
+                            qual, with_check
+                     FROM pg_policies
+                     WHERE schemaname = $1 AND tablename = $2
+                     ORDER BY policyname";
+        let policies = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query(query, &[&schema, &relation]),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        Ok(RlsStatus {
+            enabled: row.get(2),
+            forced: row.get(3),
+            bypassed: row.get(4),
+            policies: policies
+                .iter()
+                .map(|policy| RlsPolicy {
+                    name: policy.get(0),
+                    permissive: policy.get(1),
+                    roles: policy.get(2),
+                    command: policy.get(3),
+                    using: policy.get(4),
+                    with_check: policy.get(5),
+                })
+                .collect(),
+        })
+    }
+
+    async fn create_database(
+        &self,
+        name: &str,
+        encoding: Option<&str>,
+        owner: Option<&str>,
+    ) -> DbResult<String> {
+        let mut statement = format!("CREATE DATABASE \"{}\"", Self::escape_identifier(name));
+        if let Some(owner) = owner {
+            statement.push_str(&format!(" OWNER \"{}\"", Self::escape_identifier(owner)));
+        }
+        // Only template0 can be copied into an encoding other than its own
+        if let Some(encoding) = encoding {
+            statement.push_str(&format!(
+                " ENCODING '{}' TEMPLATE template0",
+                Self::escape_string(encoding)
+            ));
+        }
+
+        let client = self.lease().await?;
+        timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Created database {}", name);
+        Ok(statement)
+    }
+
+    async fn drop_database(&self, name: &str) -> DbResult<String> {
+        let current = self.current_database.lock().await.clone();
+        check_droppable_database(name, &current, &["postgres", "template0", "template1"])?;
+
+        let statement = format!("DROP DATABASE \"{}\"", Self::escape_identifier(name));
+        let client = self.lease().await?;
+        timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Dropped database {}", name);
+        Ok(statement)
+    }
+
+    async fn create_user(&self, user: &DbUser, password: Option<&str>) -> DbResult<String> {
+        user.validate()?;
+        let create = |password: Option<&str>| {
+            let role = format!(
+                "CREATE ROLE \"{}\" LOGIN",
+                Self::escape_identifier(&user.name)
+            );
+            match password {
+                Some(password) => format!("{} PASSWORD '{}'", role, Self::escape_string(password)),
+                None => role,
+            }
+        };
+        let statement = create(password);
+
+        let client = self.lease().await?;
+        timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Created user {}", user.name);
+        Ok(create(password.map(|_| REDACTED)))
+    }
+
+    async fn drop_user(&self, user: &DbUser) -> DbResult<String> {
+        user.validate()?;
+        let statement = format!("DROP ROLE \"{}\"", Self::escape_identifier(&user.name));
+        let client = self.lease().await?;
+        timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Dropped user {}", user.name);
+        Ok(statement)
+    }
+
+    async fn change_password(&self, user: &DbUser, password: &str) -> DbResult<String> {
+        user.validate()?;
+        let alter = |password: &str| {
+            format!(
+                "ALTER ROLE \"{}\" PASSWORD '{}'",
+                Self::escape_identifier(&user.name),
+                Self::escape_string(password)
+            )
+        };
+        let statement = alter(password);
+
+        let client = self.lease().await?;
+        timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        // Connected clients stay logged in; new ones need the new password
+        if user.name == self.username {
+            *self.password.lock().await = password.to_string();
+        }
+        debug!("Changed password of {}", user.name);
+        Ok(alter(REDACTED))
+    }
+
+    async fn grant_privileges(&self, grant: &PrivilegeGrant) -> DbResult<String> {
+        self.change_privileges(grant, false).await
+    }
+
+    async fn revoke_privileges(&self, grant: &PrivilegeGrant) -> DbResult<String> {
+        self.change_privileges(grant, true).await
+    }
+
+    async fn get_table_options(&self, table: &TableRef) -> DbResult<TableOptions> {
+        let client = self.lease().await?;
+        // Tables in the default tablespace have reltablespace 0
+        let query = "SELECT am.amname::text,
+                            CASE c.relpersistence WHEN 'u' THEN 'unlogged'
+                                                  WHEN 't' THEN 'temporary'
+                                                  ELSE 'permanent' END,
+                            t.spcname::text, d.datcollate::text, c.reloptions::text[]
+                     FROM pg_class c
+                     JOIN pg_database d ON d.datname = current_database()
+                     LEFT JOIN pg_am am ON am.oid = c.relam
+                     LEFT JOIN pg_tablespace t
+                         ON t.oid = COALESCE(NULLIF(c.reltablespace, 0), d.dattablespace)
+                     WHERE c.oid = $1::text::regclass";
+        let name = Self::qualified_name(table);
+        let row = timeout(DEFAULT_QUERY_TIMEOUT, client.query_one(query, &[&name]))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        let mut options = TableOptions {
+            engine: row.get(0),
+            persistence: row.get(1),
+            tablespace: row.get(2),
+            collation: row.get(3),
+            ..Default::default()
+        };
+        let parameters: Option<Vec<String>> = row.get(4);
+        options.add_parameters(parameters.iter().flatten().map(String::as_str));
+        Ok(options)
+    }
+
+    async fn get_column_stats(&self, table: &TableRef, column: &str) -> DbResult<ColumnStats> {
+        let client = self.lease().await?;
+        let name = Self::qualified_name(table);
+        let query = "SELECT s.null_frac::float8,
+                            (CASE WHEN s.n_distinct < 0 THEN -s.n_distinct * greatest(c.reltuples, 0)
+                                  ELSE s.n_distinct END)::float8,
+                            s.most_common_vals::text::text[], s.most_common_freqs::float8[],
+                            s.histogram_bounds::text::text[]
+                     FROM pg_class c
+                     JOIN pg_namespace n ON n.oid = c.relnamespace
+                     JOIN pg_stats s ON s.schemaname = n.nspname AND s.tablename = c.relname
+                     WHERE c.oid = $1::text::regclass AND s.attname = $2
+                     ORDER BY s.inherited
+                     LIMIT 1";
+        let row = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query_opt(query, &[&name, &column]),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        // Without ANALYZE there are no statistics, and everything comes from the sample
+        let mut stats = ColumnStats::default();
+        if let Some(row) = row {
+            stats.null_fraction = row.get(0);
+            stats.distinct_count = row.get(1);
+            let values: Option<Vec<String>> = row.get(2);
+            let frequencies: Option<Vec<f64>> = row.get(3);
+            if let (Some(values), Some(frequencies)) = (values, frequencies) {
+                stats.most_common = values
+                    .into_iter()
+                    .zip(frequencies)
+                    .map(|(value, frequency)| CommonValue { value, frequency })
+                    .collect();
+            }
+            stats.histogram_bounds = row.get::<_, Option<Vec<String>>>(4).unwrap_or_default();
+        }
+
+        let column = format!("\"{}\"", Self::escape_identifier(column));
+        let as_text = |expression: &str| format!("CAST({} AS text)", expression);
+        let summary = timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.query_one(&column_stats::summary_query(&name, &as_text(&column)), &[]),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        let counts = (
+            summary.get::<_, i64>(0) as u64,
+            summary.get::<_, i64>(1) as u64,
+            summary.get::<_, i64>(2) as u64,
+        );
+
+        // Types without an ordering (json, boolean) have no range
+        let range_query = column_stats::range_query(&name, &column, as_text);
+        let range = match timeout(DEFAULT_QUERY_TIMEOUT, client.query_one(&range_query, &[])).await
+        {
+            Ok(Ok(row)) => (row.get(0), row.get(1)),
+            _ => (None, None),
+        };
+
+        let mut most_common = Vec::new();
+        if stats.most_common.is_empty() {
+            let query = column_stats::most_common_query(&name, &as_text(&column));
+            let rows = timeout(DEFAULT_QUERY_TIMEOUT, client.query(&query, &[]))
+                .await
+                .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+                .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+            most_common = rows
+                .iter()
+                .map(|row| (row.get(0), row.get::<_, i64>(1) as u64))
+                .collect();
+        }
+
+        stats.add_sample(counts, range, most_common);
+        Ok(stats)
+    }
+
+    async fn build_sample_query(
+        &self,
+        table: &TableRef,
+        n: usize,
+        method: SampleMethod,
+    ) -> DbResult<String> {
+        let name = Self::qualified_name(table);
+        if method == SampleMethod::Random {
+            return Ok(sampling::random_query(&name, "random()", n));
+        }
+
+        let client = self.lease().await?;
+        let query = "SELECT reltuples::float8 FROM pg_class WHERE oid = $1::text::regclass";
+        let row = timeout(DEFAULT_QUERY_TIMEOUT, client.query_one(query, &[&name]))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+
+        // reltuples is -1 (or 0 before PostgreSQL 14) until the table is first analyzed
+        Ok(match sampling::sample_percent(n, row.get(0)) {
+            Some(percent) => sampling::tablesample_query(&name, method, percent, n),
+            None => sampling::random_query(&name, "random()", n),
+        })
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        DIALECT
+    }
+
+    async fn create_scratch_schema(&self) -> DbResult<()> {
+        let client = self.lease().await?;
+        let statement = format!(
+            "CREATE SCHEMA IF NOT EXISTS \"{}\" AUTHORIZATION CURRENT_USER",
+            SCRATCH_SCHEMA
+        );
+        timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        Ok(())
+    }
+
+    async fn drop_scratch_schema(&self) -> DbResult<()> {
+        let client = self.lease().await?;
+        let statement = format!("DROP SCHEMA IF EXISTS \"{}\" CASCADE", SCRATCH_SCHEMA);
+        timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement))
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Dropped scratch schema");
+        Ok(())
+    }
+
+    async fn materialize_query(
+        &self,
+        query: &str,
+        table: &str,
+        temporary: bool,
+    ) -> DbResult<TableRef> {
+        let created = if temporary {
+            TableRef::unqualified(table)
+        } else {
+            self.create_scratch_schema().await?;
+            TableRef::new(Some(SCRATCH_SCHEMA.to_string()), table)
+        };
+        let statement = format!(
+            "CREATE {}TABLE {} AS {}",
+            if temporary { "TEMPORARY " } else { "" },
+            Self::qualified_name(&created),
+            query
+        );
+        // Temporary tables belong to the session client, which runs the user's queries
+        let result = if temporary {
+            let client = self.client.lock().await;
+            timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement)).await
+        } else {
+            let client = self.lease().await?;
+            timeout(DEFAULT_QUERY_TIMEOUT, client.batch_execute(&statement)).await
+        };
+        result
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Materialized query into {}", created);
+        Ok(created)
+    }
+
+    async fn tail_changes(&self, table: &TableRef, on_changes: ChangeSink<'_>) -> DbResult<()> {
+        // A session of its own: the temporary slot is dropped when it ends
+        let database = self.current_database.lock().await.clone();
+        let client = self.connect_session(&database).await?;
+        let slot = format!("bloatsql_tail_{}", uuid::Uuid::new_v4().simple());
+        timeout(
+            DEFAULT_QUERY_TIMEOUT,
+            client.execute(
+                "SELECT pg_create_logical_replication_slot($1, 'wal2json', true)",
+                &[&slot],
+            ),
+        )
+        .await
+        .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+        .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+        debug!("Tailing changes to {} through slot {}", table, slot);
+
+        let query = "SELECT data FROM pg_logical_slot_get_changes($1, NULL, NULL,
+                        'format-version', '2', 'include-transaction', 'false', 'add-tables', $2)";
+        let filter = change_stream::wal2json_table_filter(table);
+        let mut interval = tokio::time::interval(change_stream::POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let rows = timeout(
+                DEFAULT_QUERY_TIMEOUT,
+                client.query(query, &[&slot, &filter]),
+            )
+            .await
+            .map_err(|_| QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR))?
+            .map_err(|e| pg_error_to_query_error(e, error_codes::QUERY_ERROR))?;
+            let changes = rows
+                .iter()
+                .filter_map(|row| change_stream::parse_wal2json(row.get(0)))
+                .collect();
+            if !on_changes(changes) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Binds aggregate filter values as text cast to the filtered column's type.
+struct AggregateDialect<'a> {
+    /// Type of each filtered column.
+    columns: Vec<(&'a str, String)>,
+}
+
+impl aggregation::Dialect for AggregateDialect<'_> {
+    fn quote(&self, column: &str) -> String {
+        format!("\"{}\"", PostgresConnection::escape_identifier(column))
+    }
+
+    fn parameter(&self, index: usize, column: &str) -> String {
+        let data_type = self
+            .columns
+            .iter()
+            .find(|(name, _)| *name == column)
+            .map(|(_, data_type)| data_type.as_str())
+            .unwrap_or("text");
+        format!("CAST(${}::text AS {})", index, data_type)
+    }
+
+    fn bucket(&self, column: &str, unit: BucketUnit) -> String {
+        format!("date_trunc('{}', {})", unit.name(), column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_postgres::config::Host;
+
+    /// Connects to the server in `BLOATSQL_TEST_POSTGRES`, a libpq connection string such as
+    /// `host=localhost user=postgres password=secret dbname=postgres`. Tests that need a
+    /// server are skipped without one.
+    async fn test_connection() -> Option<PostgresConnection> {
+        let config: tokio_postgres::Config = std::env::var("BLOATSQL_TEST_POSTGRES")
+            .ok()?
+            .parse()
+            .expect("Invalid BLOATSQL_TEST_POSTGRES");
+        let host = match config.get_hosts().first() {
+            Some(Host::Tcp(host)) => host.clone(),
+            _ => "localhost".to_string(),
+        };
+        let password = config
+            .get_password()
+            .map(|password| String::from_utf8_lossy(password).into_owned())
+            .unwrap_or_default();
+        let conn = PostgresConnection::new(
+            PostgresFlavor::Postgres,
+            &host,
+            config.get_ports().first().copied().unwrap_or(5432),
+            config.get_user().unwrap_or("postgres"),
+            &password,
+            config.get_dbname().unwrap_or("postgres"),
+            "disabled",
+            None,
+            PoolSize::default(),
+            None,
+            None,
+        )
+        .await
+        .expect("Failed to connect to BLOATSQL_TEST_POSTGRES");
+        Some(conn)
+    }
+
+    #[tokio::test]
+    async fn test_select_for_update_returns_rows() {
+        let Some(conn) = test_connection().await else {
+            return;
+        };
+        let created = conn
+            .execute_query(
+                "CREATE TEMP TABLE locked AS SELECT g AS id FROM generate_series(1, 3) g",
+            )
+            .await
+            .unwrap();
+        assert_eq!(created.affected_rows, Some(3));
+
+        let result = conn
+            .execute_query("SELECT id FROM locked ORDER BY id FOR UPDATE")
+            .await
+            .unwrap();
+        assert_eq!(result.columns, ["id"]);
+        assert_eq!(result.row_count, 3);
+        assert_eq!(result.rows[0]["id"], 1);
+        assert_eq!(result.affected_rows, None);
+
+        let updated = conn
+            .execute_query("UPDATE locked SET id = id + 1")
+            .await
+            .unwrap();
+        assert!(updated.columns.is_empty());
+        assert_eq!(updated.affected_rows, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_explain_analyze_returns_plan() {
+        let Some(conn) = test_connection().await else {
+            return;
+        };
+        let result = conn
+            .execute_query("EXPLAIN ANALYZE SELECT * FROM generate_series(1, 10)")
+            .await
+            .unwrap();
+        assert_eq!(result.columns, ["QUERY PLAN"]);
+        assert!(result.row_count > 0);
+        assert!(result.rows[0]["QUERY PLAN"]
+            .as_str()
+            .unwrap()
+            .contains("Function Scan"));
+    }
 }
@@ -0,0 +1,1028 @@
+//! Redis, with the `redis` crate over one multiplexed connection. The numbered databases
+//! are the connection's databases, and key namespaces (the part of a key before its first
+//! `:`) its tables: browsing `user:*` lists the matching keys with their type, expiry and,
+//! for strings, value. Values of every type are read and written through the key browser
+//! (`scan_keys`, `get_key`, `set_key`, `delete_key`).
+//!
+//! The query editor takes commands as redis-cli does, one per line. Values that aren't
+//! UTF-8 are shown as base64.
+
+use super::column_metadata::ColumnMetadata;
+use super::connection::{
+    error_codes, unsupported, ChunkedRows, DatabaseConnection, DbResult, QueryError, QueryResult,
+    QueryTimer, RowChunkSink, SslMode, TableColumn, TableOperation, TableRef, TableRelationship,
+    DEFAULT_QUERY_TIMEOUT, LIVENESS_TIMEOUT, MAX_QUERY_ROWS,
+};
+use super::keys::{KeyEntry, KeyKind, KeyPage, KeyValue};
+use super::sql_dialect::SqlDialect;
+use super::statement;
+use async_trait::async_trait;
+use base64::Engine;
+use redis::aio::MultiplexedConnection;
+use redis::{
+    AsyncConnectionConfig, ConnectionAddr, ConnectionInfo, ErrorKind, RedisConnectionInfo,
+    RedisError, Value,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::timeout;
+use tracing::debug;
+
+const DIALECT: SqlDialect = SqlDialect::Redis;
+
+/// Keys `list_tables` scans for namespaces, so a large keyspace doesn't hold it up.
+const NAMESPACE_SCAN_LIMIT: usize = 10_000;
+
+/// Keys asked for per `SCAN` call.
+const SCAN_COUNT: usize = 1_000;
+
+/// Columns of a browsed namespace.
+const KEY_COLUMNS: [&str; 4] = ["key", "type", "ttl", "value"];
+
+fn query_error(message: impl std::fmt::Display) -> QueryError {
+    QueryError::with_code(message.to_string(), error_codes::QUERY_ERROR)
+}
+
+/// Keeps the server's own message for failed commands, and gives timeouts and lost
+/// connections their own error codes.
+fn redis_error(err: RedisError) -> QueryError {
+    if err.is_timeout() {
+        return QueryError::with_code("Query timed out", error_codes::TIMEOUT_ERROR);
+    }
+    if err.is_io_error() || err.kind() == ErrorKind::AuthenticationFailed {
+        return QueryError::with_code(err.to_string(), error_codes::CONNECTION_ERROR);
+    }
+    query_error(err)
+}
+
+/// Splits a command line into arguments as redis-cli does: double-quoted arguments take
+/// `\n`, `\r`, `\t`, `\xHH`, `\"` and `\\` escapes, single-quoted ones only `\'`.
+fn split_command(line: &str) -> DbResult<Vec<Vec<u8>>> {
+    let unbalanced = || query_error(format!("Unbalanced quotes in: {}", line));
+    let push = |arg: &mut Vec<u8>, c: char| {
+        arg.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+    };
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            return Ok(args);
+        };
+        let mut arg = Vec::new();
+        match first {
+            '"' => {
+                chars.next();
+                loop {
+                    match chars.next().ok_or_else(unbalanced)? {
+                        '"' => break,
+                        '\\' => match chars.next().ok_or_else(unbalanced)? {
+                            'n' => arg.push(b'\n'),
+                            'r' => arg.push(b'\r'),
+                            't' => arg.push(b'\t'),
+                            'x' => {
+                                let hex: String = chars.by_ref().take(2).collect();
+                                let byte = u8::from_str_radix(&hex, 16)
+                                    .ok()
+                                    .filter(|_| hex.len() == 2)
+                                    .ok_or_else(|| {
+                                        query_error(format!("Invalid escape \\x{}", hex))
+                                    })?;
+                                arg.push(byte);
+                            }
+                            c => push(&mut arg, c),
+                        },
+                        c => push(&mut arg, c),
+                    }
+                }
+            }
+            '\'' => {
+                chars.next();
+                loop {
+                    match chars.next().ok_or_else(unbalanced)? {
+                        '\'' => break,
+                        '\\' if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            arg.push(b'\'');
+                        }
+                        c => push(&mut arg, c),
+                    }
+                }
+            }
+            _ => {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    push(&mut arg, c);
+                }
+            }
+        }
+        // A closing quote must end the argument
+        if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+            return Err(unbalanced());
+        }
+        args.push(arg);
+    }
+}
+
+/// Quotes an argument for redis-cli when it needs it, as `split_command` reads it back.
+fn quote_arg(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && !arg
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '\\'));
+    if plain {
+        return arg.to_string();
+    }
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A command as it would be typed in redis-cli, for the audit log.
+fn command_text(command: &[String]) -> String {
+    command
+        .iter()
+        .map(|arg| quote_arg(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Escapes glob characters, so a key matches only itself as a `SCAN` pattern.
+fn escape_glob(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The pattern a key is browsed under: its namespace, or the key itself when it has none.
+fn namespace_pattern(key: &str) -> String {
+    match key.split_once(':') {
+        Some((namespace, _)) => format!("{}:*", escape_glob(namespace)),
+        None => escape_glob(key),
+    }
+}
+
+fn value_to_json(value: &Value) -> serde_json::Value {
+    use serde_json::Value as Json;
+    match value {
+        Value::Nil => Json::Null,
+        Value::Int(v) => Json::from(*v),
+        Value::BulkString(bytes) => match std::str::from_utf8(bytes) {
+            Ok(text) => Json::String(text.to_string()),
+            Err(_) => Json::String(base64::engine::general_purpose::STANDARD.encode(bytes)),
+        },
+        Value::SimpleString(text) | Value::VerbatimString { text, .. } => {
+            Json::String(text.clone())
+        }
+        Value::Okay => Json::String("OK".to_string()),
+        Value::Array(items) | Value::Set(items) | Value::Push { data: items, .. } => {
+            Json::Array(items.iter().map(value_to_json).collect())
+        }
+        Value::Map(entries) => Json::Object(
+            entries
+                .iter()
+                .map(|(key, value)| (text(key), value_to_json(value)))
+                .collect(),
+        ),
+        Value::Attribute { data, .. } => value_to_json(data),
+        Value::Double(v) => serde_json::Number::from_f64(*v)
+            .map(Json::Number)
+            .unwrap_or_else(|| Json::String(v.to_string())),
+        Value::Boolean(v) => Json::Bool(*v),
+        Value::BigNumber(v) => Json::String(v.to_string()),
+        Value::ServerError(e) => Json::String(match e.details() {
+            Some(details) => format!("{} {}", e.code(), details),
+            None => e.code().to_string(),
+        }),
+    }
+}
+
+/// A value as text, e.g. a hash field name.
+fn text(value: &Value) -> String {
+    match value_to_json(value) {
+        serde_json::Value::String(text) => text,
+        other => other.to_string(),
+    }
+}
+
+/// Rows for a command's reply: a row per item of an array, a `field`/`value` row per entry
+/// of a map, or a single row.
+fn reply_rows(reply: &Value) -> (Vec<String>, Vec<serde_json::Value>) {
+    match reply {
+        Value::Array(items) | Value::Set(items) => (
+            vec!["value".to_string()],
+            items
+                .iter()
+                .map(|item| serde_json::json!({ "value": value_to_json(item) }))
+                .collect(),
+        ),
+        Value::Map(entries) => (
+            vec!["field".to_string(), "value".to_string()],
+            entries
+                .iter()
+                .map(|(field, value)| {
+                    serde_json::json!({ "field": text(field), "value": value_to_json(value) })
+                })
+                .collect(),
+        ),
+        Value::Attribute { data, .. } => reply_rows(data),
+        other => (
+            vec!["value".to_string()],
+            vec![serde_json::json!({ "value": value_to_json(other) })],
+        ),
+    }
+}
+
+/// The TTL `TTL` reports: None for keys without expiry (-1) or that are gone (-2).
+fn ttl(seconds: i64) -> Option<i64> {
+    (seconds >= 0).then_some(seconds)
+}
+
+/// An item of a collection value as a command argument.
+fn item_arg(item: &serde_json::Value) -> DbResult<String> {
+    match item {
+        serde_json::Value::String(text) => Ok(text.clone()),
+        serde_json::Value::Number(_) | serde_json::Value::Bool(_) => Ok(item.to_string()),
+        _ => Err(query_error(format!(
+            "Redis values hold strings, not {}",
+            item
+        ))),
+    }
+}
+
+/// The commands that replace a key with `value`, run in one `MULTI` block.
+fn set_commands(value: &KeyValue) -> DbResult<Vec<Vec<String>>> {
+    if value.truncated {
+        return Err(query_error(format!(
+            "Only part of \"{}\" was read, so it can't be written back",
+            value.key
+        )));
+    }
+    let key = value.key.clone();
+    let shape_error =
+        |shape: &str| query_error(format!("A {:?} value must be {}", value.kind, shape));
+    let items = || match &value.value {
+        serde_json::Value::Array(items) => Ok(items),
+        _ => Err(shape_error("an array")),
+    };
+
+    let mut commands = vec![vec!["DEL".to_string(), key.clone()]];
+    let mut write = match value.kind {
+        KeyKind::String => {
+            commands.clear();
+            vec!["SET".to_string(), key.clone(), item_arg(&value.value)?]
+        }
+        KeyKind::List => vec!["RPUSH".to_string(), key.clone()],
+        KeyKind::Set => vec!["SADD".to_string(), key.clone()],
+        KeyKind::Zset => vec!["ZADD".to_string(), key.clone()],
+        KeyKind::Hash => vec!["HSET".to_string(), key.clone()],
+        KeyKind::Stream => {
+            return Err(query_error(
+                "Streams are only appended to; add entries with XADD",
+            ))
+        }
+    };
+    match value.kind {
+        KeyKind::List | KeyKind::Set => {
+            for item in items()? {
+                write.push(item_arg(item)?);
+            }
+        }
+        KeyKind::Zset => {
+            for item in items()? {
+                let (Some(member), Some(score)) = (item.get("member"), item.get("score")) else {
+                    return Err(shape_error("an array of {\"member\", \"score\"} objects"));
+                };
+                write.push(item_arg(score)?);
+                write.push(item_arg(member)?);
+            }
+        }
+        KeyKind::Hash => {
+            let serde_json::Value::Object(fields) = &value.value else {
+                return Err(shape_error("an object"));
+            };
+            for (field, item) in fields {
+                write.push(field.clone());
+                write.push(item_arg(item)?);
+            }
+        }
+        KeyKind::String | KeyKind::Stream => {}
+    }
+    // Collections can't be empty; an empty one is just deleted
+    if write.len() > 2 {
+        commands.push(write);
+    }
+    if let Some(ttl) = value.ttl {
+        commands.push(vec!["EXPIRE".to_string(), key, ttl.to_string()]);
+    }
+    Ok(commands)
+}
+
+pub struct RedisConnection {
+    info: ConnectionInfo,
+    /// The current database and its connection; multiplexed connections are cheap to clone.
+    connection: Mutex<(i64, MultiplexedConnection)>,
+    /// How long a command waits for its response.
+    query_timeout: Duration,
+}
+
+impl RedisConnection {
+    /// Connects to the server at `host`; the database is a database number, 0 by default.
+    pub async fn new(
+        host: &str,
+        port: u16,
+        username: &str,
+        password: &str,
+        database: &str,
+        ssl_mode: &str,
+        query_timeout: Option<Duration>,
+    ) -> DbResult<Self> {
+        let tls = |insecure: bool| ConnectionAddr::TcpTls {
+            host: host.to_string(),
+            port,
+            insecure,
+            tls_params: None,
+        };
+        let addr = match SslMode::parse(ssl_mode)? {
+            SslMode::Disabled => ConnectionAddr::Tcp(host.to_string(), port),
+            SslMode::Required => tls(true),
+            SslMode::VerifyFull => tls(false),
+            _ => {
+                return Err(QueryError::with_code(
+                    "Redis connections either skip or fully verify certificates; choose \
+                     disabled, required or verify-full",
+                    error_codes::SSL_ERROR,
+                ))
+            }
+        };
+        let info = ConnectionInfo {
+            addr,
+            redis: RedisConnectionInfo {
+                db: Self::database_number(database)?,
+                username: (!username.is_empty()).then(|| username.to_string()),
+                password: (!password.is_empty()).then(|| password.to_string()),
+                ..Default::default()
+            },
+        };
+        let query_timeout = query_timeout.unwrap_or(DEFAULT_QUERY_TIMEOUT);
+        let connection = Self::open(&info, query_timeout).await?;
+        debug!("Redis connection established to {}:{}", host, port);
+        Ok(RedisConnection {
+            connection: Mutex::new((info.redis.db, connection)),
+            info,
+            query_timeout,
+        })
+    }
+
+    fn database_number(database: &str) -> DbResult<i64> {
+        if database.is_empty() {
+            return Ok(0);
+        }
+        database.parse().map_err(|_| {
+            query_error(format!(
+                "Redis databases are numbered, and \"{}\" is not a number",
+                database
+            ))
+        })
+    }
+
+    async fn open(
+        info: &ConnectionInfo,
+        response_timeout: Duration,
+    ) -> DbResult<MultiplexedConnection> {
+        let client = redis::Client::open(info.clone())
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::CONNECTION_ERROR))?;
+        let config = AsyncConnectionConfig::new()
+            .set_connection_timeout(DEFAULT_QUERY_TIMEOUT)
+            .set_response_timeout(response_timeout);
+        client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await
+            .map_err(|e| QueryError::with_code(e.to_string(), error_codes::CONNECTION_ERROR))
+    }
+
+    fn conn(&self) -> MultiplexedConnection {
+        self.connection.lock().unwrap().1.clone()
+    }
+
+    /// Connects to database `db`, replacing the current connection.
+    async fn switch_to(&self, db: i64) -> DbResult<()> {
+        let mut info = self.info.clone();
+        info.redis.db = db;
+        let connection = Self::open(&info, self.query_timeout).await?;
+        *self.connection.lock().unwrap() = (db, connection);
+        Ok(())
+    }
+
+    async fn query<T: redis::FromRedisValue>(&self, command: &redis::Cmd) -> DbResult<T> {
+        command
+            .query_async(&mut self.conn())
+            .await
+            .map_err(redis_error)
+    }
+
+    /// Runs `commands` in one `MULTI` block, so they apply together.
+    async fn run_atomic(&self, commands: &[Vec<String>]) -> DbResult<()> {
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for command in commands {
+            pipe.cmd(&command[0]).arg(&command[1..]).ignore();
+        }
+        pipe.query_async::<()>(&mut self.conn())
+            .await
+            .map_err(redis_error)
+    }
+
+    /// One page of `SCAN`.
+    async fn scan(&self, pattern: &str, cursor: u64, count: usize) -> DbResult<(u64, Vec<String>)> {
+        self.query(
+            redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(pattern)
+                .arg("COUNT")
+                .arg(count),
+        )
+        .await
+    }
+
+    /// The type and TTL of each key, in one round trip.
+    async fn describe_keys(&self, keys: Vec<String>) -> DbResult<Vec<KeyEntry>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut pipe = redis::pipe();
+        for key in &keys {
+            pipe.cmd("TYPE").arg(key).cmd("TTL").arg(key);
+        }
+        let replies: Vec<(String, i64)> = pipe
+            .query_async::<Vec<Value>>(&mut self.conn())
+            .await
+            .map_err(redis_error)?
+            .chunks(2)
+            .map(|reply| {
+                let seconds = match reply.get(1) {
+                    Some(Value::Int(seconds)) => *seconds,
+                    _ => -1,
+                };
+                (reply.first().map(text).unwrap_or_default(), seconds)
+            })
+            .collect();
+        Ok(keys
+            .into_iter()
+            .zip(replies)
+            // Keys deleted since the scan are left out
+            .filter(|(_, (kind, _))| kind != "none")
+            .map(|(key, (kind, seconds))| KeyEntry {
+                key,
+                kind,
+                ttl: ttl(seconds),
+            })
+            .collect())
+    }
+
+    /// Keys matching `pattern` as rows of `KEY_COLUMNS`, strings with their value.
+    async fn browse(&self, pattern: &str, timer: &mut QueryTimer) -> DbResult<QueryResult> {
+        let mut keys = Vec::new();
+        let mut cursor = 0;
+        let mut truncated = false;
+        loop {
+            let (next, page) = self.scan(pattern, cursor, SCAN_COUNT).await?;
+            keys.extend(page);
+            cursor = next;
+            if keys.len() > MAX_QUERY_ROWS {
+                keys.truncate(MAX_QUERY_ROWS);
+                truncated = true;
+                break;
+            }
+            if cursor == 0 {
+                break;
+            }
+        }
+        keys.sort();
+        timer.executed();
+
+        let entries = self.describe_keys(keys).await?;
+        let strings: Vec<&str> = entries
+            .iter()
+            .filter(|entry| entry.kind == "string")
+            .map(|entry| entry.key.as_str())
+            .collect();
+        let mut values: HashMap<&str, serde_json::Value> = HashMap::new();
+        if !strings.is_empty() {
+            let replies: Vec<Value> = self.query(redis::cmd("MGET").arg(&strings)).await?;
+            values = strings
+                .iter()
+                .copied()
+                .zip(replies.iter().map(value_to_json))
+                .collect();
+        }
+        timer.fetched();
+
+        let rows: Vec<serde_json::Value> = entries
+            .iter()
+            .map(|entry| {
+                serde_json::json!({
+                    "key": entry.key,
+                    "type": entry.kind,
+                    "ttl": entry.ttl,
+                    "value": values.get(entry.key.as_str()).cloned().unwrap_or_default(),
+                })
+            })
+            .collect();
+        let column_metadata: Vec<ColumnMetadata> = KEY_COLUMNS
+            .iter()
+            .map(|name| ColumnMetadata {
+                name: name.to_string(),
+                type_name: if *name == "ttl" { "integer" } else { "string" }.to_string(),
+                numeric: *name == "ttl",
+                nullable: Some(*name != "key"),
+                source_table: Some(TableRef::unqualified(pattern)),
+                source_column: Some(name.to_string()),
+                ..Default::default()
+            })
+            .collect();
+        timer.converted();
+
+        Ok(QueryResult {
+            columns: KEY_COLUMNS.iter().map(|name| name.to_string()).collect(),
+            row_count: rows.len(),
+            rows,
+            execution_time: timer.execution_time(),
+            truncated,
+            affected_rows: None,
+            timing: timer.timing(),
+            column_metadata,
+        })
+    }
+
+    /// Reads a set, hash or sorted set with `SSCAN`, `HSCAN` or `ZSCAN`, up to
+    /// `MAX_QUERY_ROWS` items. Hashes and sorted sets come back as flat pairs.
+    async fn scan_items(&self, command: &str, key: &str) -> DbResult<(Vec<Value>, bool)> {
+        let pair = if command == "SSCAN" { 1 } else { 2 };
+        let mut items = Vec::new();
+        let mut cursor = 0u64;
+        loop {
+            let (next, page): (u64, Vec<Value>) = self
+                .query(
+                    redis::cmd(command)
+                        .arg(key)
+                        .arg(cursor)
+                        .arg("COUNT")
+                        .arg(SCAN_COUNT),
+                )
+                .await?;
+            items.extend(page);
+            cursor = next;
+            if items.len() > MAX_QUERY_ROWS * pair {
+                items.truncate(MAX_QUERY_ROWS * pair);
+                return Ok((items, true));
+            }
+            if cursor == 0 {
+                return Ok((items, false));
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DatabaseConnection for RedisConnection {
+    async fn test_connection(&self) -> DbResult<()> {
+        self.query::<()>(&redis::cmd("PING")).await
+    }
+
+    async fn reconnect_if_broken(&self) -> DbResult<bool> {
+        let alive = matches!(
+            timeout(LIVENESS_TIMEOUT, self.test_connection()).await,
+            Ok(Ok(()))
+        );
+        if alive {
+            return Ok(false);
+        }
+        let db = self.connection.lock().unwrap().0;
+        self.switch_to(db).await?;
+        debug!("Reconnected to Redis");
+        Ok(true)
+    }
+
+    async fn execute_query(&self, query: &str) -> DbResult<QueryResult> {
+        let mut timer = QueryTimer::start();
+        timer.acquired();
+        if let Some(table) = statement::browsed_table(query) {
+            return self.browse(&table.name, &mut timer).await;
+        }
+
+        // One command per line, showing the reply of the last one
+        let mut reply = None;
+        for line in query.lines() {
+            let args = split_command(line)?;
+            let Some((name, args)) = args.split_first() else {
+                continue;
+            };
+            let mut command = redis::cmd(&String::from_utf8_lossy(name));
+            for arg in args {
+                command.arg(arg.as_slice());
+            }
+            reply = Some(self.query::<Value>(&command).await?);
+        }
+        let Some(reply) = reply else {
+            return Err(query_error("Query is empty"));
+        };
+        timer.executed();
+        timer.fetched();
+        let (columns, rows) = reply_rows(&reply);
+        timer.converted();
+
+        Ok(QueryResult {
+            column_metadata: columns
+                .iter()
+                .map(|name| ColumnMetadata {
+                    name: name.clone(),
+                    ..Default::default()
+                })
+                .collect(),
+            columns,
+            row_count: rows.len(),
+            rows,
+            execution_time: timer.execution_time(),
+            truncated: false,
+            affected_rows: None,
+            timing: timer.timing(),
+        })
+    }
+
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult> {
+        // Replies are read whole, so they are only chunked for the consumer
+        let mut result = self.execute_query(query).await?;
+        let mut chunks = ChunkedRows::new(result.columns.clone(), chunk_size, on_chunk);
+        for row in std::mem::take(&mut result.rows) {
+            if !chunks.push(row) {
+                break;
+            }
+        }
+        chunks.finish();
+        Ok(result)
+    }
+
+    async fn list_tables(&self) -> DbResult<Vec<String>> {
+        let mut namespaces = BTreeSet::new();
+        let mut scanned = 0;
+        let mut cursor = 0;
+        loop {
+            let (next, keys) = self.scan("*", cursor, SCAN_COUNT).await?;
+            scanned += keys.len();
+            namespaces.extend(keys.iter().map(|key| namespace_pattern(key)));
+            cursor = next;
+            if cursor == 0 || scanned >= NAMESPACE_SCAN_LIMIT {
+                break;
+            }
+        }
+        Ok(namespaces.into_iter().collect())
+    }
+
+    async fn list_databases(&self) -> DbResult<Vec<String>> {
+        // Managed servers often refuse CONFIG; 16 is the default number of databases
+        let count = self
+            .query::<Vec<String>>(redis::cmd("CONFIG").arg("GET").arg("databases"))
+            .await
+            .ok()
+            .and_then(|reply| reply.get(1)?.parse::<usize>().ok())
+            .unwrap_or(16);
+        Ok((0..count).map(|db| db.to_string()).collect())
+    }
+
+    async fn change_database(&self, database_name: &str) -> DbResult<()> {
+        // SELECT would switch every user of the multiplexed connection at some point
+        // between their commands, so each database gets a connection of its own
+        self.switch_to(Self::database_number(database_name)?).await
+    }
+
+    async fn set_default_schema(&self, schema: &str) -> DbResult<()> {
+        self.change_database(schema).await
+    }
+
+    async fn get_current_database(&self) -> DbResult<String> {
+        Ok(self.connection.lock().unwrap().0.to_string())
+    }
+
+    async fn get_table_columns(&self, _table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        Ok(KEY_COLUMNS
+            .iter()
+            .map(|name| TableColumn {
+                name: name.to_string(),
+                data_type: if *name == "ttl" { "integer" } else { "string" }.to_string(),
+                is_nullable: *name != "key",
+                is_primary_key: *name == "key",
+                column_default: None,
+                character_maximum_length: None,
+                numeric_precision: None,
+                is_identity: false,
+                identity_generation: None,
+                is_generated: false,
+                generation_expression: None,
+                generation_kind: None,
+                allowed_values: None,
+            })
+            .collect())
+    }
+
+    async fn get_table_relationships(&self) -> DbResult<Vec<TableRelationship>> {
+        Ok(Vec::new())
+    }
+
+    async fn check_privileges(
+        &self,
+        _table: &TableRef,
+        operation: TableOperation,
+    ) -> DbResult<bool> {
+        // ACLs show when a command runs
+        Ok(matches!(
+            operation,
+            TableOperation::Select | TableOperation::Update | TableOperation::Delete
+        ))
+    }
+
+    async fn disconnect(&self) -> DbResult<()> {
+        // The connection closes when it is dropped
+        debug!("Disconnecting from Redis");
+        Ok(())
+    }
+
+    async fn export_database_with_options(
+        &self,
+        _include_drop: bool,
+        _include_create: bool,
+        _data_mode: &str,
+        _selected_tables: &[TableRef],
+        _max_insert_size: usize,
+    ) -> DbResult<String> {
+        // Snapshots of a Redis server are taken with BGSAVE or redis-cli --rdb
+        unsupported("Exports")
+    }
+
+    async fn update_cell(
+        &self,
+        _table: &TableRef,
+        column_name: &str,
+        new_value: Option<&str>,
+        primary_key_column: &str,
+        primary_key_value: &str,
+    ) -> DbResult<String> {
+        if primary_key_column != "key" {
+            return Err(query_error("Redis keys are edited by their name"));
+        }
+        let key = primary_key_value.to_string();
+        let command = match (column_name, new_value) {
+            ("value", Some(value)) => {
+                let kind: String = self.query(redis::cmd("TYPE").arg(&key)).await?;
+                if kind != "string" {
+                    return Err(query_error(format!(
+                        "\"{}\" is a {}; edit it in the key browser",
+                        key, kind
+                    )));
+                }
+                vec![
+                    "SET".to_string(),
+                    key,
+                    value.to_string(),
+                    "KEEPTTL".to_string(),
+                ]
+            }
+            ("value", None) => return Err(query_error("Redis values can't be null")),
+            ("ttl", Some(seconds)) => {
+                let seconds: i64 = seconds
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|seconds| *seconds > 0)
+                    .ok_or_else(|| query_error("The TTL must be a positive number of seconds"))?;
+                vec!["EXPIRE".to_string(), key, seconds.to_string()]
+            }
+            ("ttl", None) => vec!["PERSIST".to_string(), key],
+            _ => {
+                return Err(query_error(format!(
+                    "Column \"{}\" can't be edited",
+                    column_name
+                )))
+            }
+        };
+        self.query::<Value>(redis::cmd(&command[0]).arg(&command[1..]))
+            .await?;
+        Ok(command_text(&command))
+    }
+
+    async fn scan_keys(&self, pattern: &str, cursor: u64, count: usize) -> DbResult<KeyPage> {
+        let (cursor, keys) = self.scan(pattern, cursor, count.max(1)).await?;
+        Ok(KeyPage {
+            keys: self.describe_keys(keys).await?,
+            cursor,
+        })
+    }
+
+    async fn get_key(&self, key: &str) -> DbResult<KeyValue> {
+        let kind: String = self.query(redis::cmd("TYPE").arg(key)).await?;
+        if kind == "none" {
+            return Err(query_error(format!("Key \"{}\" doesn't exist", key)));
+        }
+        let kind = KeyKind::parse(&kind)
+            .ok_or_else(|| query_error(format!("Values of type {} can't be shown", kind)))?;
+        let seconds: i64 = self.query(redis::cmd("TTL").arg(key)).await?;
+        let limit = MAX_QUERY_ROWS as i64;
+
+        let (value, truncated) = match kind {
+            KeyKind::String => {
+                let value: Value = self.query(redis::cmd("GET").arg(key)).await?;
+                (value_to_json(&value), false)
+            }
+            KeyKind::List => {
+                let items: Vec<Value> = self
+                    .query(redis::cmd("LRANGE").arg(key).arg(0).arg(limit))
+                    .await?;
+                let truncated = items.len() > MAX_QUERY_ROWS;
+                let items = items.iter().take(MAX_QUERY_ROWS).map(value_to_json);
+                (serde_json::Value::Array(items.collect()), truncated)
+            }
+            KeyKind::Set => {
+                let (items, truncated) = self.scan_items("SSCAN", key).await?;
+                let mut items: Vec<String> = items.iter().map(text).collect();
+                items.sort();
+                (serde_json::json!(items), truncated)
+            }
+            KeyKind::Zset => {
+                // ZRANGE keeps members in score order, which ZSCAN doesn't
+                let items: Vec<Value> = self
+                    .query(
+                        redis::cmd("ZRANGE")
+                            .arg(key)
+                            .arg(0)
+                            .arg(limit)
+                            .arg("WITHSCORES"),
+                    )
+                    .await?;
+                let truncated = items.len() > MAX_QUERY_ROWS * 2;
+                let members = items
+                    .chunks(2)
+                    .take(MAX_QUERY_ROWS)
+                    .map(|pair| {
+                        let score = text(&pair[1]);
+                        serde_json::json!({
+                            "member": text(&pair[0]),
+                            "score": score.parse::<f64>().map(serde_json::Value::from)
+                                .unwrap_or(serde_json::Value::String(score)),
+                        })
+                    })
+                    .collect();
+                (serde_json::Value::Array(members), truncated)
+            }
+            KeyKind::Hash => {
+                let (items, truncated) = self.scan_items("HSCAN", key).await?;
+                let fields = items
+                    .chunks(2)
+                    .map(|pair| (text(&pair[0]), value_to_json(&pair[1])))
+                    .collect();
+                (serde_json::Value::Object(fields), truncated)
+            }
+            KeyKind::Stream => {
+                let entries: Vec<(String, Value)> = self
+                    .query(
+                        redis::cmd("XRANGE")
+                            .arg(key)
+                            .arg("-")
+                            .arg("+")
+                            .arg("COUNT")
+                            .arg(limit + 1),
+                    )
+                    .await?;
+                let truncated = entries.len() > MAX_QUERY_ROWS;
+                let entries = entries
+                    .iter()
+                    .take(MAX_QUERY_ROWS)
+                    .map(|(id, fields)| {
+                        let fields = match fields {
+                            Value::Array(items) => serde_json::Value::Object(
+                                items
+                                    .chunks(2)
+                                    .map(|pair| (text(&pair[0]), value_to_json(&pair[1])))
+                                    .collect(),
+                            ),
+                            other => value_to_json(other),
+                        };
+                        serde_json::json!({ "id": id, "fields": fields })
+                    })
+                    .collect();
+                (serde_json::Value::Array(entries), truncated)
+            }
+        };
+
+        Ok(KeyValue {
+            key: key.to_string(),
+            kind,
+            ttl: ttl(seconds),
+            value,
+            truncated,
+        })
+    }
+
+    async fn set_key(&self, value: &KeyValue) -> DbResult<String> {
+        let commands = set_commands(value)?;
+        self.run_atomic(&commands).await?;
+        Ok(commands
+            .iter()
+            .map(|command| command_text(command))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    async fn delete_key(&self, key: &str) -> DbResult<String> {
+        let command = vec!["DEL".to_string(), key.to_string()];
+        let deleted: i64 = self.query(redis::cmd("DEL").arg(key)).await?;
+        if deleted == 0 {
+            return Err(query_error(format!("Key \"{}\" doesn't exist", key)));
+        }
+        Ok(command_text(&command))
+    }
+
+    async fn create_database(
+        &self,
+        _name: &str,
+        _encoding: Option<&str>,
+        _owner: Option<&str>,
+    ) -> DbResult<String> {
+        // The server has a fixed number of databases, set by its configuration
+        unsupported("Creating databases")
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        DIALECT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_commands_and_values() {
+        let args = split_command(r#"SET "user:1 name" 'it\'s' "\x41\n""#).unwrap();
+        assert_eq!(args, [&b"SET"[..], b"user:1 name", b"it's", b"A\n"]);
+        assert!(split_command(r#"GET "key"x"#).is_err());
+        assert!(split_command(r#"GET "key"#).is_err());
+        for arg in ["plain", "two words", "quote\"s", "tab\tand\\", ""] {
+            let parsed = split_command(&quote_arg(arg)).unwrap();
+            assert_eq!(parsed, [arg.as_bytes()]);
+        }
+        assert_eq!(namespace_pattern("user:1:name"), "user:*");
+        assert_eq!(namespace_pattern("a*b"), "a\\*b");
+
+        let value = KeyValue {
+            key: "scores".to_string(),
+            kind: KeyKind::Zset,
+            ttl: Some(60),
+            value: json!([{"member": "ann", "score": 1.5}]),
+            truncated: false,
+        };
+        assert_eq!(
+            set_commands(&value).unwrap(),
+            [
+                vec!["DEL", "scores"],
+                vec!["ZADD", "scores", "1.5", "ann"],
+                vec!["EXPIRE", "scores", "60"],
+            ]
+        );
+        let value = KeyValue {
+            kind: KeyKind::Hash,
+            ttl: None,
+            value: json!([]),
+            ..value
+        };
+        assert!(set_commands(&value).is_err());
+        let value = KeyValue {
+            value: json!({}),
+            ..value
+        };
+        assert_eq!(set_commands(&value).unwrap(), [vec!["DEL", "scores"]]);
+    }
+}
@@ -0,0 +1,283 @@
+//! Calling stored procedures and functions by parameter name, so users don't have to write
+//! dialect-specific CALL/SELECT syntax. Drivers introspect the routine's signatures and
+//! render the call; matching the given arguments to a signature is shared here, as is
+//! splitting the source of stored code edited in the app.
+
+use super::connection::{error_codes, QueryError, QueryResult, TableRef};
+use super::statement;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutineKind {
+    Procedure,
+    Function,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterMode {
+    In,
+    Out,
+    InOut,
+}
+
+impl ParameterMode {
+    /// Parses `information_schema.parameters.parameter_mode`.
+    pub fn parse(mode: &str) -> Self {
+        match mode.to_uppercase().as_str() {
+            "OUT" => ParameterMode::Out,
+            "INOUT" => ParameterMode::InOut,
+            _ => ParameterMode::In,
+        }
+    }
+}
+
+/// A parameter of a stored procedure or function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutineParameter {
+    /// `None` for unnamed parameters, which are passed as `$1`, `$2`, ... by position.
+    pub name: Option<String>,
+    /// Type to cast the bound text value to.
+    pub data_type: String,
+    pub mode: ParameterMode,
+    /// Whether the parameter may be omitted (PostgreSQL `DEFAULT`).
+    pub has_default: bool,
+}
+
+/// One signature of a routine; PostgreSQL routines can be overloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutineSignature {
+    pub kind: RoutineKind,
+    /// Parameters in declaration order.
+    pub parameters: Vec<RoutineParameter>,
+}
+
+/// How a parameter is filled in a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Argument<'a> {
+    /// Bound to the given text value, or NULL.
+    Value(Option<&'a str>),
+    /// An OUT parameter of a procedure, read back after the call.
+    Output,
+    /// Left out so the parameter's default applies.
+    Omitted,
+}
+
+/// Output of `DatabaseConnection::call_routine`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RoutineResult {
+    /// Result sets returned by the routine, in order. A function's return value is a
+    /// result set of its own.
+    pub result_sets: Vec<QueryResult>,
+    /// Values of OUT and INOUT parameters, by parameter name.
+    pub output: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Name `args` use for the parameter at `index` (0-based).
+pub fn argument_key(parameter: &RoutineParameter, index: usize) -> String {
+    parameter
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("${}", index + 1))
+}
+
+/// Picks the signature of `routine` that `args` (values by parameter name) fit, and how to
+/// fill each of its parameters.
+pub fn resolve_call<'a>(
+    routine: &TableRef,
+    signatures: &'a [RoutineSignature],
+    args: &'a HashMap<String, Option<String>>,
+) -> Result<(&'a RoutineSignature, Vec<Argument<'a>>), QueryError> {
+    if signatures.is_empty() {
+        return Err(QueryError::with_code(
+            format!("Routine \"{}\" not found", routine),
+            error_codes::QUERY_ERROR,
+        ));
+    }
+
+    let mut matches = Vec::new();
+    let mut last_error = String::new();
+    for signature in signatures {
+        match bind_arguments(signature, args) {
+            Ok(arguments) => matches.push((signature, arguments)),
+            Err(e) => last_error = e,
+        }
+    }
+
+    match matches.len() {
+        1 => Ok(matches.remove(0)),
+        0 if signatures.len() == 1 => {
+            Err(QueryError::with_code(last_error, error_codes::QUERY_ERROR))
+        }
+        0 => Err(QueryError::with_code(
+            format!("No overload of \"{}\" takes the given parameters", routine),
+            error_codes::QUERY_ERROR,
+        )),
+        n => Err(QueryError::with_code(
+            format!(
+                "Call to \"{}\" is ambiguous: {} overloads take the given parameters",
+                routine, n
+            ),
+            error_codes::QUERY_ERROR,
+        )),
+    }
+}
+
+fn bind_arguments<'a>(
+    signature: &RoutineSignature,
+    args: &'a HashMap<String, Option<String>>,
+) -> Result<Vec<Argument<'a>>, String> {
+    let keys: Vec<String> = signature
+        .parameters
+        .iter()
+        .enumerate()
+        .map(|(i, parameter)| argument_key(parameter, i))
+        .collect();
+    if let Some(unknown) = args.keys().find(|key| !keys.contains(key)) {
+        return Err(format!("Unknown parameter \"{}\"", unknown));
+    }
+
+    let mut arguments = Vec::with_capacity(keys.len());
+    for (parameter, key) in signature.parameters.iter().zip(&keys) {
+        let value = args.get(key).map(Option::as_deref);
+        let argument = match (parameter.mode, value) {
+            (ParameterMode::Out, Some(_)) => {
+                return Err(format!("\"{}\" is an OUT parameter", key));
+            }
+            // Functions return their OUT parameters as columns instead of taking them
+            (ParameterMode::Out, None) if signature.kind == RoutineKind::Function => {
+                continue;
+            }
+            (ParameterMode::Out, None) => Argument::Output,
+            (_, Some(value)) => Argument::Value(value),
+            (_, None) if parameter.has_default => Argument::Omitted,
+            (ParameterMode::InOut, None) => Argument::Value(None),
+            (ParameterMode::In, None) => {
+                return Err(format!("Missing value for parameter \"{}\"", key));
+            }
+        };
+        arguments.push(argument);
+    }
+    Ok(arguments)
+}
+
+/// Splits stored-code source into the statements to run.
+///
+/// Scripts written for the `mysql` client change the statement delimiter with `DELIMITER`
+/// lines so routine bodies can contain semicolons. Only the client understands them, so
+/// they are applied here. Without any `DELIMITER` line the source is one statement.
+pub fn split_script(source: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut delimiter = None;
+    let mut block = String::new();
+    for line in source.lines() {
+        if let Some(next) = delimiter_directive(line) {
+            split_block(&block, delimiter.as_deref(), &mut statements);
+            block.clear();
+            delimiter = Some(next.to_string());
+            continue;
+        }
+        block.push_str(line);
+        block.push('\n');
+    }
+    split_block(&block, delimiter.as_deref(), &mut statements);
+    statements
+}
+
+fn split_block(block: &str, delimiter: Option<&str>, statements: &mut Vec<String>) {
+    match delimiter {
+        None => statements.push(block.trim().trim_end_matches(';').trim_end().to_string()),
+        Some(";") => statements.extend(
+            statement::split_statements(block)
+                .into_iter()
+                .map(|statement| statement.sql),
+        ),
+        Some(delimiter) => statements.extend(block.split(delimiter).map(|s| s.trim().to_string())),
+    }
+    statements.retain(|statement| !statement.is_empty());
+}
+
+/// Returns the new delimiter if `line` is a `DELIMITER` directive.
+fn delimiter_directive(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    if !words.next()?.eq_ignore_ascii_case("DELIMITER") {
+        return None;
+    }
+    words.next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameter(name: &str, mode: ParameterMode, has_default: bool) -> RoutineParameter {
+        RoutineParameter {
+            name: Some(name.to_string()),
+            data_type: "integer".to_string(),
+            mode,
+            has_default,
+        }
+    }
+
+    #[test]
+    fn test_resolve_call() {
+        let routine = TableRef::unqualified("transfer");
+        let procedure = RoutineSignature {
+            kind: RoutineKind::Procedure,
+            parameters: vec![
+                parameter("amount", ParameterMode::In, false),
+                parameter("fee", ParameterMode::In, true),
+                parameter("balance", ParameterMode::Out, false),
+            ],
+        };
+        let args = HashMap::from([("amount".to_string(), Some("10".to_string()))]);
+        let (_, arguments) =
+            resolve_call(&routine, std::slice::from_ref(&procedure), &args).unwrap();
+        assert_eq!(
+            arguments,
+            vec![
+                Argument::Value(Some("10")),
+                Argument::Omitted,
+                Argument::Output
+            ]
+        );
+
+        let missing = HashMap::new();
+        let e = resolve_call(&routine, std::slice::from_ref(&procedure), &missing).unwrap_err();
+        assert_eq!(e.message, "Missing value for parameter \"amount\"");
+
+        // Overloads are told apart by the parameters given
+        let function = RoutineSignature {
+            kind: RoutineKind::Function,
+            parameters: vec![
+                parameter("account", ParameterMode::In, false),
+                parameter("balance", ParameterMode::Out, false),
+            ],
+        };
+        let signatures = [procedure, function];
+        let args = HashMap::from([("account".to_string(), None)]);
+        let (signature, arguments) = resolve_call(&routine, &signatures, &args).unwrap();
+        assert_eq!(signature.kind, RoutineKind::Function);
+        assert_eq!(arguments, vec![Argument::Value(None)]);
+    }
+
+    #[test]
+    fn test_split_script() {
+        let script = "DELIMITER $$\nCREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\nEND$$\n\
+                      DELIMITER ;\nCALL p();\nCALL p();\n";
+        assert_eq!(
+            split_script(script),
+            [
+                "CREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\nEND",
+                "CALL p()",
+                "CALL p()"
+            ]
+        );
+        assert_eq!(
+            split_script("CREATE FUNCTION f() RETURNS INT\nBEGIN\n  RETURN 1;\nEND;\n"),
+            ["CREATE FUNCTION f() RETURNS INT\nBEGIN\n  RETURN 1;\nEND"]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Random samples of a table's rows, for peeking at representative data in huge tables.
+//! PostgreSQL reads a share of the table with `TABLESAMPLE`; elsewhere, and when the table
+//! size is unknown, rows are shuffled with `ORDER BY RANDOM()`, over a bounded scan.
+
+use super::connection::MAX_QUERY_ROWS;
+use serde::Deserialize;
+
+/// Rows read by the `ORDER BY RANDOM()` fallback, which sorts everything it reads. Tables
+/// larger than this are sampled from their first rows only.
+pub const RANDOM_SCAN_LIMIT: u64 = 100_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleMethod {
+    /// Random pages (`TABLESAMPLE SYSTEM`): fastest, but rows stored together come together.
+    #[default]
+    System,
+    /// Random rows (`TABLESAMPLE BERNOULLI`): reads the whole table.
+    Bernoulli,
+    /// Shuffles the rows (`ORDER BY RANDOM()`), over at most `RANDOM_SCAN_LIMIT` rows.
+    Random,
+}
+
+/// Clamps the requested sample size to what a result may hold.
+pub fn sample_size(n: usize) -> usize {
+    n.clamp(1, MAX_QUERY_ROWS)
+}
+
+/// Percentage of a table of about `estimated_rows` rows to read for `n` of them, with some
+/// margin as sampling yields a varying number of rows. None when the table size is unknown
+/// or small enough to shuffle whole.
+pub fn sample_percent(n: usize, estimated_rows: f64) -> Option<f64> {
+    if estimated_rows <= RANDOM_SCAN_LIMIT as f64 {
+        return None;
+    }
+    Some((sample_size(n) as f64 * 2.0 / estimated_rows * 100.0).min(100.0))
+}
+
+/// Builds a `TABLESAMPLE` query for `n` rows of `table` (quoted). Random sampling isn't a
+/// tablesample method, and takes `random_query`.
+pub fn tablesample_query(table: &str, method: SampleMethod, percent: f64, n: usize) -> String {
+    let method = match method {
+        SampleMethod::Bernoulli => "BERNOULLI",
+        _ => "SYSTEM",
+    };
+    format!(
+        "SELECT * FROM {} TABLESAMPLE {} ({}) LIMIT {}",
+        table,
+        method,
+        percent,
+        sample_size(n)
+    )
+}
+
+/// Builds a query shuffling the first `RANDOM_SCAN_LIMIT` rows of `table` (quoted) with
+/// `random` (`RANDOM()`, `RAND()`) and returning `n` of them.
+pub fn random_query(table: &str, random: &str, n: usize) -> String {
+    format!(
+        "SELECT * FROM (SELECT * FROM {} LIMIT {}) sample ORDER BY {} LIMIT {}",
+        table,
+        RANDOM_SCAN_LIMIT,
+        random,
+        sample_size(n)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_queries() {
+        assert_eq!(sample_percent(100, 50_000.0), None);
+        assert_eq!(sample_percent(100, 1_000_000.0), Some(0.02));
+        assert_eq!(sample_percent(MAX_QUERY_ROWS * 10, 200_000.0), Some(10.0));
+
+        assert_eq!(
+            tablesample_query("\"events\"", SampleMethod::Bernoulli, 0.02, 100),
+            "SELECT * FROM \"events\" TABLESAMPLE BERNOULLI (0.02) LIMIT 100"
+        );
+        assert_eq!(
+            random_query("`events`", "RAND()", 0),
+            "SELECT * FROM (SELECT * FROM `events` LIMIT 100000) sample ORDER BY RAND() LIMIT 1"
+        );
+    }
+}
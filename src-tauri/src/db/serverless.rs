@@ -0,0 +1,770 @@
+//! Serverless databases reached through their providers' HTTP SQL APIs instead of the
+//! PostgreSQL or MySQL wire protocol, for networks where ports 5432 and 3306 are blocked:
+//! Neon's `/sql` endpoint (the one its serverless driver uses for one-shot queries) and
+//! PlanetScale's `Execute` API.
+//!
+//! Every statement is its own HTTP request. Neon keeps no session between them, so
+//! transactions, session variables and cursors are unavailable; PlanetScale carries its
+//! session along with each request.
+
+use super::connection::{
+    error_codes, unsupported, ChunkedRows, DatabaseConnection, DbResult, QueryError, QueryResult,
+    QueryTiming, RowChunkSink, SslMode, TableColumn, TableOperation, TableRef, TableRelationship,
+    DEFAULT_QUERY_TIMEOUT, MAX_QUERY_ROWS,
+};
+use super::http::HttpEndpoint;
+use super::mariadb::MariaDbConnection;
+use super::sampling::{self, SampleMethod};
+use super::sql_dialect::SqlDialect;
+use async_trait::async_trait;
+use base64::Engine;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerlessProvider {
+    /// Neon serverless PostgreSQL.
+    Neon,
+    /// PlanetScale (MySQL on Vitess).
+    PlanetScale,
+}
+
+impl ServerlessProvider {
+    fn name(self) -> &'static str {
+        match self {
+            ServerlessProvider::Neon => "Neon",
+            ServerlessProvider::PlanetScale => "PlanetScale",
+        }
+    }
+}
+
+pub struct ServerlessConnection {
+    provider: ServerlessProvider,
+    endpoint: HttpEndpoint,
+    /// The database host, e.g. `ep-name-123.us-east-2.aws.neon.tech`.
+    host: String,
+    username: String,
+    password: String,
+    /// Neon selects the database with the connection string sent with each request.
+    database: Mutex<String>,
+    /// PlanetScale's session, returned with each response and sent back with the next.
+    session: Mutex<Value>,
+    /// How long a request waits for its response.
+    query_timeout: Duration,
+}
+
+impl ServerlessConnection {
+    /// Connects to `host` over HTTPS and checks the credentials with a query. The port is
+    /// ignored: both APIs are served on 443.
+    pub async fn new(
+        provider: ServerlessProvider,
+        host: &str,
+        username: &str,
+        password: &str,
+        database: &str,
+        ssl_mode: &str,
+        query_timeout: Option<Duration>,
+    ) -> DbResult<Self> {
+        // The APIs only accept HTTPS, so "disabled" verifies like the providers' drivers do
+        let ssl_mode = match SslMode::parse(ssl_mode)? {
+            SslMode::Disabled => SslMode::VerifyFull,
+            mode => mode,
+        };
+        let api_host = match provider {
+            // ep-name.region.aws.neon.tech is served at api.region.aws.neon.tech
+            ServerlessProvider::Neon => match host.split_once('.') {
+                Some((_, domain)) => format!("api.{}", domain),
+                None => host.to_string(),
+            },
+            ServerlessProvider::PlanetScale => host.to_string(),
+        };
+        let conn = ServerlessConnection {
+            provider,
+            endpoint: HttpEndpoint::new(&api_host, 443, true, ssl_mode)?,
+            host: host.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            database: Mutex::new(database.to_string()),
+            session: Mutex::new(Value::Null),
+            query_timeout: query_timeout.unwrap_or(DEFAULT_QUERY_TIMEOUT),
+        };
+        if provider == ServerlessProvider::PlanetScale && !database.is_empty() {
+            conn.execute(&format!(
+                "USE {}",
+                conn.dialect().quote_identifier(database)
+            ))
+            .await?;
+        }
+        conn.test_connection().await?;
+        Ok(conn)
+    }
+
+    /// Runs one statement and converts its result.
+    async fn execute(&self, query: &str) -> DbResult<QueryResult> {
+        let start = Instant::now();
+        let response = match self.provider {
+            ServerlessProvider::Neon => self.post_neon(query).await?,
+            ServerlessProvider::PlanetScale => self.post_planetscale(query).await?,
+        };
+        let execution = start.elapsed();
+        let (columns, mut rows, affected_rows) = match self.provider {
+            ServerlessProvider::Neon => neon_rows(&response),
+            ServerlessProvider::PlanetScale => planetscale_rows(&response)?,
+        };
+        let truncated = rows.len() > MAX_QUERY_ROWS;
+        rows.truncate(MAX_QUERY_ROWS);
+        let conversion = start.elapsed() - execution;
+
+        Ok(QueryResult {
+            columns,
+            row_count: rows.len(),
+            rows,
+            execution_time: start.elapsed().as_millis(),
+            truncated,
+            affected_rows,
+            timing: QueryTiming {
+                execution: execution.as_millis(),
+                conversion: conversion.as_millis(),
+                ..Default::default()
+            },
+            column_metadata: Vec::new(),
+        })
+    }
+
+    async fn post_neon(&self, query: &str) -> DbResult<Value> {
+        let encode = |value: &str| utf8_percent_encode(value, NON_ALPHANUMERIC).to_string();
+        let database = self.database.lock().unwrap().clone();
+        let connection_string = format!(
+            "postgresql://{}:{}@{}/{}?sslmode=require",
+            encode(&self.username),
+            encode(&self.password),
+            self.host,
+            encode(&database)
+        );
+        let body = json!({ "query": query, "params": [] });
+        let response = self
+            .endpoint
+            .post(
+                "/sql",
+                &[
+                    ("Content-Type", "application/json".to_string()),
+                    ("Neon-Connection-String", connection_string),
+                    // Values as text and rows as arrays, converted by column type below
+                    ("Neon-Raw-Text-Output", "true".to_string()),
+                    ("Neon-Array-Mode", "true".to_string()),
+                ],
+                body.to_string().into_bytes(),
+                self.query_timeout,
+            )
+            .await?;
+        let value = response.json()?;
+        if !response.is_success() {
+            let text = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+            let mut error = QueryError::with_code(
+                text("message").unwrap_or_else(|| format!("HTTP {}", response.status)),
+                error_codes::QUERY_ERROR,
+            );
+            error.detail = text("detail");
+            error.hint = text("hint");
+            return Err(error);
+        }
+        Ok(value)
+    }
+
+    async fn post_planetscale(&self, query: &str) -> DbResult<Value> {
+        let credentials = base64::engine::general_purpose::STANDARD
+            .encode(format!("{}:{}", self.username, self.password));
+        let session = self.session.lock().unwrap().clone();
+        let body = json!({ "query": query, "session": session });
+        let response = self
+            .endpoint
+            .post(
+                "/psdb.v1alpha1.Database/Execute",
+                &[
+                    ("Content-Type", "application/json".to_string()),
+                    ("Authorization", format!("Basic {}", credentials)),
+                ],
+                body.to_string().into_bytes(),
+                self.query_timeout,
+            )
+            .await?;
+        if response.status == 401 {
+            return Err(QueryError::with_code(
+                "PlanetScale rejected the username or password",
+                error_codes::CONNECTION_ERROR,
+            ));
+        }
+        let value = response.json()?;
+        if let Some(session) = value.get("session") {
+            *self.session.lock().unwrap() = session.clone();
+        }
+        if let Some(error) = value.get("error").filter(|error| !error.is_null()) {
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("PlanetScale returned an error");
+            return Err(QueryError::with_code(message, error_codes::QUERY_ERROR));
+        }
+        if !response.is_success() {
+            return Err(QueryError::with_code(
+                format!("Unexpected HTTP {} response", response.status),
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Runs a metadata query and returns its rows.
+    async fn query_rows(&self, query: &str) -> DbResult<Vec<Value>> {
+        Ok(self.execute(query).await?.rows)
+    }
+
+    /// The schema (PostgreSQL) or database (MySQL) an unqualified table belongs to, as
+    /// an SQL expression.
+    fn schema_expression(&self, table: &TableRef) -> String {
+        match (&table.schema, self.provider) {
+            (Some(schema), _) => self.dialect().quote_string(schema),
+            (None, ServerlessProvider::Neon) => "current_schema()".to_string(),
+            (None, ServerlessProvider::PlanetScale) => "DATABASE()".to_string(),
+        }
+    }
+}
+
+fn text(row: &Value, column: &str) -> Option<String> {
+    match row.get(column)? {
+        Value::String(value) => Some(value.clone()),
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+fn flag(row: &Value, column: &str) -> bool {
+    match row.get(column) {
+        Some(Value::Bool(value)) => *value,
+        Some(Value::Number(value)) => value.as_i64() == Some(1),
+        _ => false,
+    }
+}
+
+fn number(row: &Value, column: &str) -> Option<i64> {
+    match row.get(column)? {
+        Value::Number(value) => value.as_i64(),
+        Value::String(value) => value.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Converts a Neon response. Values arrive as PostgreSQL's text output and are turned into
+/// JSON numbers, booleans and objects by type OID, like the wire protocol driver does.
+fn neon_rows(response: &Value) -> (Vec<String>, Vec<Value>, Option<u64>) {
+    let fields = response
+        .get("fields")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let columns: Vec<String> = fields
+        .iter()
+        .map(|field| text(field, "name").unwrap_or_default())
+        .collect();
+    let types: Vec<i64> = fields
+        .iter()
+        .map(|field| number(field, "dataTypeID").unwrap_or(0))
+        .collect();
+
+    let rows = response
+        .get("rows")
+        .and_then(Value::as_array)
+        .map(|rows| {
+            rows.iter()
+                .map(|row| {
+                    let mut row_map = serde_json::Map::with_capacity(columns.len());
+                    let values = row.as_array().map(Vec::as_slice).unwrap_or_default();
+                    for ((column, type_id), value) in columns.iter().zip(&types).zip(values) {
+                        row_map.insert(column.clone(), neon_value(*type_id, value));
+                    }
+                    Value::Object(row_map)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Only statements without a result report affected rows, as with the wire driver
+    let affected_rows = if fields.is_empty() {
+        response.get("rowCount").and_then(Value::as_u64)
+    } else {
+        None
+    };
+    (columns, rows, affected_rows)
+}
+
+fn neon_value(type_id: i64, value: &Value) -> Value {
+    let Some(raw) = value.as_str() else {
+        return value.clone();
+    };
+    match type_id {
+        // bool
+        16 => Value::Bool(raw == "t"),
+        // int8, int2, int4, oid
+        20 | 21 | 23 | 26 => raw
+            .parse::<i64>()
+            .map(Value::from)
+            .unwrap_or_else(|_| Value::String(raw.to_string())),
+        // float4, float8; NaN and infinities have no JSON number
+        700 | 701 => raw
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::String(raw.to_string())),
+        // json, jsonb
+        114 | 3802 => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+        _ => Value::String(raw.to_string()),
+    }
+}
+
+/// Converts a PlanetScale response. Each row is one base64 string holding all its values
+/// back to back, with their lengths listed alongside (-1 for NULL).
+fn planetscale_rows(response: &Value) -> DbResult<(Vec<String>, Vec<Value>, Option<u64>)> {
+    let Some(result) = response.get("result").filter(|result| !result.is_null()) else {
+        return Ok((Vec::new(), Vec::new(), None));
+    };
+    let fields = result
+        .get("fields")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let columns: Vec<String> = fields
+        .iter()
+        .map(|field| text(field, "name").unwrap_or_default())
+        .collect();
+    let types: Vec<String> = fields
+        .iter()
+        .map(|field| text(field, "type").unwrap_or_default())
+        .collect();
+
+    let invalid = || {
+        QueryError::with_code(
+            "Invalid row in PlanetScale response",
+            error_codes::QUERY_ERROR,
+        )
+    };
+    let mut rows = Vec::new();
+    for row in result
+        .get("rows")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+    {
+        let values = match row.get("values").and_then(Value::as_str) {
+            Some(values) => base64::engine::general_purpose::STANDARD
+                .decode(values)
+                .map_err(|_| invalid())?,
+            None => Vec::new(),
+        };
+        let lengths = row
+            .get("lengths")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let mut row_map = serde_json::Map::with_capacity(columns.len());
+        let mut offset = 0;
+        for (index, column) in columns.iter().enumerate() {
+            let length = lengths
+                .get(index)
+                .and_then(|length| match length {
+                    Value::String(length) => length.parse::<i64>().ok(),
+                    length => length.as_i64(),
+                })
+                .ok_or_else(invalid)?;
+            let value = if length < 0 {
+                Value::Null
+            } else {
+                let end = offset + length as usize;
+                let bytes = values.get(offset..end).ok_or_else(invalid)?;
+                offset = end;
+                planetscale_value(&types[index], bytes)
+            };
+            row_map.insert(column.clone(), value);
+        }
+        rows.push(Value::Object(row_map));
+    }
+
+    let affected_rows = if columns.is_empty() {
+        result.get("rowsAffected").and_then(|rows| match rows {
+            Value::String(rows) => rows.parse().ok(),
+            rows => rows.as_u64(),
+        })
+    } else {
+        None
+    };
+    Ok((columns, rows, affected_rows))
+}
+
+fn planetscale_value(column_type: &str, bytes: &[u8]) -> Value {
+    let raw = String::from_utf8_lossy(bytes).into_owned();
+    match column_type {
+        "INT8" | "INT16" | "INT24" | "INT32" | "INT64" | "UINT8" | "UINT16" | "UINT24"
+        | "UINT32" | "YEAR" => raw
+            .parse::<i64>()
+            .map(Value::from)
+            .unwrap_or(Value::String(raw)),
+        "UINT64" => raw
+            .parse::<u64>()
+            .map(Value::from)
+            .unwrap_or(Value::String(raw)),
+        "FLOAT32" | "FLOAT64" => raw
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .unwrap_or(Value::String(raw)),
+        "JSON" => serde_json::from_str(&raw).unwrap_or(Value::String(raw)),
+        _ => Value::String(raw),
+    }
+}
+
+#[async_trait]
+impl DatabaseConnection for ServerlessConnection {
+    async fn test_connection(&self) -> DbResult<()> {
+        self.execute("SELECT 1").await.map(|_| ())
+    }
+
+    async fn reconnect_if_broken(&self) -> DbResult<bool> {
+        // Each request opens its own connection; there is nothing to re-establish
+        Ok(false)
+    }
+
+    async fn execute_query(&self, query: &str) -> DbResult<QueryResult> {
+        self.execute(query).await
+    }
+
+    async fn execute_query_streaming(
+        &self,
+        query: &str,
+        chunk_size: usize,
+        on_chunk: RowChunkSink<'_>,
+    ) -> DbResult<QueryResult> {
+        // The whole result comes in one response; it is only chunked for the consumer
+        let mut result = self.execute(query).await?;
+        let mut chunks = ChunkedRows::new(result.columns.clone(), chunk_size, on_chunk);
+        for row in std::mem::take(&mut result.rows) {
+            if !chunks.push(row) {
+                break;
+            }
+        }
+        chunks.finish();
+        Ok(result)
+    }
+
+    async fn list_tables(&self) -> DbResult<Vec<String>> {
+        let query = match self.provider {
+            ServerlessProvider::Neon => {
+                "SELECT table_name FROM information_schema.tables
+                 WHERE table_schema = current_schema() AND table_type = 'BASE TABLE'
+                 ORDER BY table_name"
+            }
+            ServerlessProvider::PlanetScale => {
+                "SELECT TABLE_NAME AS table_name FROM information_schema.TABLES
+                 WHERE TABLE_SCHEMA = DATABASE() AND TABLE_TYPE = 'BASE TABLE'
+                 ORDER BY TABLE_NAME"
+            }
+        };
+        let rows = self.query_rows(query).await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| text(row, "table_name"))
+            .collect())
+    }
+
+    async fn list_databases(&self) -> DbResult<Vec<String>> {
+        let query = match self.provider {
+            ServerlessProvider::Neon => {
+                "SELECT datname AS name FROM pg_database WHERE NOT datistemplate ORDER BY datname"
+            }
+            ServerlessProvider::PlanetScale => {
+                "SELECT SCHEMA_NAME AS name FROM information_schema.SCHEMATA
+                 ORDER BY SCHEMA_NAME"
+            }
+        };
+        let rows = self.query_rows(query).await?;
+        Ok(rows.iter().filter_map(|row| text(row, "name")).collect())
+    }
+
+    async fn change_database(&self, database_name: &str) -> DbResult<()> {
+        match self.provider {
+            ServerlessProvider::Neon => {
+                let previous = std::mem::replace(
+                    &mut *self.database.lock().unwrap(),
+                    database_name.to_string(),
+                );
+                if let Err(e) = self.test_connection().await {
+                    *self.database.lock().unwrap() = previous;
+                    return Err(e);
+                }
+                Ok(())
+            }
+            ServerlessProvider::PlanetScale => {
+                self.execute(&format!(
+                    "USE {}",
+                    self.dialect().quote_identifier(database_name)
+                ))
+                .await?;
+                *self.database.lock().unwrap() = database_name.to_string();
+                Ok(())
+            }
+        }
+    }
+
+    async fn set_default_schema(&self, schema: &str) -> DbResult<()> {
+        match self.provider {
+            // search_path would only last for one request
+            ServerlessProvider::Neon => unsupported("Changing the default schema"),
+            ServerlessProvider::PlanetScale => self.change_database(schema).await,
+        }
+    }
+
+    async fn get_current_database(&self) -> DbResult<String> {
+        match self.provider {
+            ServerlessProvider::Neon => Ok(self.database.lock().unwrap().clone()),
+            ServerlessProvider::PlanetScale => {
+                let rows = self.query_rows("SELECT DATABASE() AS name").await?;
+                Ok(rows
+                    .first()
+                    .and_then(|row| text(row, "name"))
+                    .unwrap_or_default())
+            }
+        }
+    }
+
+    async fn get_table_columns(&self, table: &TableRef) -> DbResult<Vec<TableColumn>> {
+        let name = self.dialect().quote_string(&table.name);
+        let schema = self.schema_expression(table);
+        let query = match self.provider {
+            ServerlessProvider::Neon => format!(
+                "SELECT
+                    c.column_name AS name,
+                    c.udt_name AS data_type,
+                    c.is_nullable,
+                    pk.column_name IS NOT NULL AS is_primary,
+                    c.column_default,
+                    c.character_maximum_length,
+                    c.numeric_precision,
+                    c.is_identity = 'YES' OR COALESCE(c.column_default LIKE 'nextval(%', false)
+                        AS is_identity,
+                    c.identity_generation::text AS identity_generation,
+                    c.is_generated = 'ALWAYS' AS is_generated,
+                    c.generation_expression::text AS generation_expression
+                 FROM information_schema.columns c
+                 LEFT JOIN (
+                    SELECT ku.column_name
+                    FROM information_schema.table_constraints tc
+                    JOIN information_schema.key_column_usage ku
+                        ON tc.constraint_name = ku.constraint_name
+                        AND tc.constraint_schema = ku.constraint_schema
+                    WHERE tc.constraint_type = 'PRIMARY KEY'
+                        AND tc.table_name = {name}
+                        AND tc.table_schema = {schema}
+                 ) pk ON c.column_name = pk.column_name
+                 WHERE c.table_name = {name} AND c.table_schema = {schema}
+                 ORDER BY c.ordinal_position"
+            ),
+            ServerlessProvider::PlanetScale => format!(
+                "SELECT
+                    COLUMN_NAME AS name,
+                    COLUMN_TYPE AS data_type,
+                    IS_NULLABLE AS is_nullable,
+                    COLUMN_KEY = 'PRI' AS is_primary,
+                    COLUMN_DEFAULT AS column_default,
+                    CHARACTER_MAXIMUM_LENGTH AS character_maximum_length,
+                    NUMERIC_PRECISION AS numeric_precision,
+                    EXTRA LIKE '%auto_increment%' AS is_identity,
+                    NULL AS identity_generation,
+                    EXTRA LIKE '%VIRTUAL GENERATED%' OR EXTRA LIKE '%STORED GENERATED%'
+                        AS is_generated,
+                    NULLIF(GENERATION_EXPRESSION, '') AS generation_expression,
+                    EXTRA AS extra
+                 FROM information_schema.COLUMNS
+                 WHERE TABLE_SCHEMA = {schema} AND TABLE_NAME = {name}
+                 ORDER BY ORDINAL_POSITION"
+            ),
+        };
+
+        let rows = self.query_rows(&query).await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let data_type = text(row, "data_type")?;
+                let is_generated = flag(row, "is_generated");
+                let generation_kind = match self.provider {
+                    ServerlessProvider::Neon => is_generated.then(|| "STORED".to_string()),
+                    ServerlessProvider::PlanetScale => {
+                        let extra = text(row, "extra").unwrap_or_default().to_uppercase();
+                        if extra.contains("VIRTUAL GENERATED") {
+                            Some("VIRTUAL".to_string())
+                        } else if extra.contains("STORED GENERATED") {
+                            Some("STORED".to_string())
+                        } else {
+                            None
+                        }
+                    }
+                };
+                Some(TableColumn {
+                    name: text(row, "name")?,
+                    allowed_values: match self.provider {
+                        ServerlessProvider::Neon => None,
+                        ServerlessProvider::PlanetScale => {
+                            MariaDbConnection::enum_values(&data_type)
+                        }
+                    },
+                    data_type,
+                    is_nullable: text(row, "is_nullable").as_deref() == Some("YES"),
+                    is_primary_key: flag(row, "is_primary"),
+                    column_default: text(row, "column_default"),
+                    character_maximum_length: number(row, "character_maximum_length"),
+                    numeric_precision: number(row, "numeric_precision"),
+                    is_identity: flag(row, "is_identity"),
+                    identity_generation: text(row, "identity_generation"),
+                    is_generated,
+                    generation_expression: text(row, "generation_expression"),
+                    generation_kind,
+                })
+            })
+            .collect())
+    }
+
+    async fn get_table_relationships(&self) -> DbResult<Vec<TableRelationship>> {
+        let query = match self.provider {
+            ServerlessProvider::Neon => {
+                "SELECT
+                    tc.table_schema AS from_schema,
+                    tc.table_name AS from_table,
+                    kcu.column_name AS from_column,
+                    ccu.table_schema AS to_schema,
+                    ccu.table_name AS to_table,
+                    ccu.column_name AS to_column,
+                    tc.constraint_name
+                 FROM information_schema.table_constraints tc
+                 JOIN information_schema.key_column_usage kcu
+                    ON tc.constraint_name = kcu.constraint_name
+                    AND tc.constraint_schema = kcu.constraint_schema
+                 JOIN information_schema.constraint_column_usage ccu
+                    ON ccu.constraint_name = tc.constraint_name
+                    AND ccu.constraint_schema = tc.constraint_schema
+                 WHERE tc.constraint_type = 'FOREIGN KEY'
+                    AND tc.table_schema = current_schema()
+                 ORDER BY tc.table_name"
+            }
+            ServerlessProvider::PlanetScale => {
+                "SELECT
+                    TABLE_SCHEMA AS from_schema,
+                    TABLE_NAME AS from_table,
+                    COLUMN_NAME AS from_column,
+                    REFERENCED_TABLE_SCHEMA AS to_schema,
+                    REFERENCED_TABLE_NAME AS to_table,
+                    REFERENCED_COLUMN_NAME AS to_column,
+                    CONSTRAINT_NAME AS constraint_name
+                 FROM information_schema.KEY_COLUMN_USAGE
+                 WHERE TABLE_SCHEMA = DATABASE() AND REFERENCED_TABLE_NAME IS NOT NULL
+                 ORDER BY TABLE_NAME, ORDINAL_POSITION"
+            }
+        };
+        let rows = self.query_rows(query).await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                Some(TableRelationship {
+                    from_schema: text(row, "from_schema"),
+                    from_table: text(row, "from_table")?,
+                    from_column: text(row, "from_column")?,
+                    to_schema: text(row, "to_schema"),
+                    to_table: text(row, "to_table")?,
+                    to_column: text(row, "to_column")?,
+                    constraint_name: text(row, "constraint_name")?,
+                })
+            })
+            .collect())
+    }
+
+    async fn check_privileges(
+        &self,
+        _table: &TableRef,
+        _operation: TableOperation,
+    ) -> DbResult<bool> {
+        // Editing goes through statements written in the query editor
+        Ok(false)
+    }
+
+    async fn disconnect(&self) -> DbResult<()> {
+        *self.session.lock().unwrap() = Value::Null;
+        Ok(())
+    }
+
+    async fn build_sample_query(
+        &self,
+        table: &TableRef,
+        n: usize,
+        _method: SampleMethod,
+    ) -> DbResult<String> {
+        let random = match self.provider {
+            ServerlessProvider::Neon => "RANDOM()",
+            ServerlessProvider::PlanetScale => "RAND()",
+        };
+        Ok(sampling::random_query(
+            &self.dialect().qualified_name(table),
+            random,
+            n,
+        ))
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        match self.provider {
+            ServerlessProvider::Neon => SqlDialect::Postgres,
+            ServerlessProvider::PlanetScale => SqlDialect::MySql {
+                no_backslash_escapes: false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_planetscale_rows() {
+        let response = json!({
+            "session": {},
+            "result": {
+                "fields": [
+                    { "name": "id", "type": "INT64" },
+                    { "name": "name", "type": "VARCHAR" },
+                    { "name": "meta", "type": "JSON" }
+                ],
+                "rows": [
+                    { "lengths": ["2", "-1", "7"], "values": "NDJ7ImEiOjF9" }
+                ]
+            }
+        });
+        let (columns, rows, affected) = planetscale_rows(&response).unwrap();
+        assert_eq!(columns, ["id", "name", "meta"]);
+        assert_eq!(
+            rows,
+            [json!({ "id": 42, "name": null, "meta": { "a": 1 } })]
+        );
+        assert_eq!(affected, None);
+
+        let (columns, rows, affected) = neon_rows(&json!({
+            "command": "UPDATE",
+            "rowCount": 3,
+            "fields": [],
+            "rows": []
+        }));
+        assert!(columns.is_empty() && rows.is_empty());
+        assert_eq!(affected, Some(3));
+        assert_eq!(neon_value(16, &json!("t")), json!(true));
+        assert_eq!(neon_value(1700, &json!("1.50")), json!("1.50"));
+    }
+}
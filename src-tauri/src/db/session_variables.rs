@@ -0,0 +1,83 @@
+//! Session variables (`work_mem`, `sql_mode`, ...) shown and changed from the UI. Values
+//! set through the app are remembered by the drivers and applied to every connection they
+//! open for the session, so they survive pooling and reconnects.
+
+use super::connection::{error_codes, QueryError};
+use super::sql_dialect::SqlDialect;
+use serde::{Deserialize, Serialize};
+
+/// A session variable and its current value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionVariable {
+    pub name: String,
+    pub value: String,
+    /// Unit of the value, e.g. `kB` or `ms` (PostgreSQL only).
+    pub unit: Option<String>,
+    pub description: Option<String>,
+    /// Whether the variable can be changed for the session; superuser-only PostgreSQL
+    /// settings count as settable. Always true for MySQL, which doesn't say.
+    pub settable: bool,
+}
+
+/// Rejects names that aren't plain (optionally dotted) identifiers, since names can't be
+/// bound as parameters.
+pub fn check_name(name: &str) -> Result<(), QueryError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if valid {
+        Ok(())
+    } else {
+        Err(QueryError::with_code(
+            format!("Invalid variable name \"{}\"", name),
+            error_codes::QUERY_ERROR,
+        ))
+    }
+}
+
+/// Renders `value` for MySQL's `SET`, which rejects quoted numbers for numeric variables:
+/// numbers and ON/OFF/DEFAULT are left bare, anything else becomes a string literal.
+pub fn mysql_literal(value: &str) -> String {
+    let value = value.trim();
+    let is_keyword = ["ON", "OFF", "DEFAULT", "TRUE", "FALSE"]
+        .iter()
+        .any(|keyword| value.eq_ignore_ascii_case(keyword));
+    if is_keyword || value.parse::<f64>().is_ok_and(f64::is_finite) {
+        value.to_string()
+    } else {
+        SqlDialect::MySql {
+            no_backslash_escapes: false,
+        }
+        .quote_string(value)
+    }
+}
+
+/// Records `value` for `name` in the variables set this session, replacing an earlier one.
+pub fn remember(variables: &mut Vec<(String, String)>, name: &str, value: &str) {
+    variables.retain(|(set, _)| !set.eq_ignore_ascii_case(name));
+    variables.push((name.to_string(), value.to_string()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_and_literals() {
+        assert!(check_name("work_mem").is_ok());
+        assert!(check_name("auto_explain.log_min_duration").is_ok());
+        assert!(check_name("sql_mode; DROP TABLE t").is_err());
+
+        assert_eq!(mysql_literal("262144"), "262144");
+        assert_eq!(mysql_literal("off"), "off");
+        assert_eq!(mysql_literal("ANSI_QUOTES,STRICT"), "'ANSI_QUOTES,STRICT'");
+        assert_eq!(mysql_literal("it's"), "'it\\'s'");
+        assert_eq!(mysql_literal("inf"), "'inf'");
+
+        let mut variables = Vec::new();
+        remember(&mut variables, "work_mem", "4MB");
+        remember(&mut variables, "WORK_MEM", "64MB");
+        assert_eq!(variables, [("WORK_MEM".to_string(), "64MB".to_string())]);
+    }
+}
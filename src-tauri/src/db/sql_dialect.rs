@@ -0,0 +1,243 @@
+//! Identifier quoting and literal formatting for the SQL the app writes itself: cell
+//! updates, exports, DDL and filters. The drivers go through here so every feature quotes
+//! the same way.
+
+use super::connection::TableRef;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlDialect {
+    /// PostgreSQL, with `standard_conforming_strings` on (the default since 9.1), so
+    /// backslashes in string literals are literal.
+    Postgres,
+    /// MySQL and MariaDB. Under the `NO_BACKSLASH_ESCAPES` SQL mode backslashes are
+    /// literal; otherwise they start escape sequences.
+    MySql { no_backslash_escapes: bool },
+    /// Microsoft SQL Server (T-SQL).
+    SqlServer,
+    /// Oracle Database.
+    Oracle,
+    /// ClickHouse, whose string literals always take backslash escapes.
+    ClickHouse,
+    /// DuckDB, which follows PostgreSQL's quoting.
+    DuckDb,
+    /// CQL, the query language of Cassandra and ScyllaDB.
+    Cql,
+    /// GoogleSQL on BigQuery, whose quoted identifiers and strings take backslash escapes.
+    BigQuery,
+    /// MongoDB, which has no SQL: the app only writes `SELECT * FROM "collection"` to browse
+    /// a collection, which the driver reads as a `find`. Quoting follows PostgreSQL.
+    MongoDb,
+    /// Redis, which has no SQL either: browsing a key pattern is read the same way as
+    /// MongoDB's collections, and quoting follows PostgreSQL.
+    Redis,
+}
+
+impl SqlDialect {
+    /// Opening and closing identifier quotes.
+    fn identifier_quotes(self) -> (char, char) {
+        match self {
+            SqlDialect::Postgres
+            | SqlDialect::Oracle
+            | SqlDialect::DuckDb
+            | SqlDialect::Cql
+            | SqlDialect::MongoDb
+            | SqlDialect::Redis => ('"', '"'),
+            SqlDialect::MySql { .. } | SqlDialect::ClickHouse | SqlDialect::BigQuery => ('`', '`'),
+            SqlDialect::SqlServer => ('[', ']'),
+        }
+    }
+
+    /// Escapes an identifier for use between the dialect's identifier quotes.
+    pub fn escape_identifier(self, name: &str) -> String {
+        if self == SqlDialect::BigQuery {
+            return name.replace('\\', "\\\\").replace('`', "\\`");
+        }
+        let (_, close) = self.identifier_quotes();
+        name.replace(close, &format!("{}{}", close, close))
+    }
+
+    /// Quotes an identifier (table, column, schema name).
+    pub fn quote_identifier(self, name: &str) -> String {
+        let (open, close) = self.identifier_quotes();
+        format!("{}{}{}", open, self.escape_identifier(name), close)
+    }
+
+    /// Quotes a table name, qualified by its schema (database on MySQL) when it has one.
+    pub fn qualified_name(self, table: &TableRef) -> String {
+        match &table.schema {
+            Some(schema) => format!(
+                "{}.{}",
+                self.quote_identifier(schema),
+                self.quote_identifier(&table.name)
+            ),
+            None => self.quote_identifier(&table.name),
+        }
+    }
+
+    /// Escapes a string for use between single quotes.
+    ///
+    /// With backslash escapes (MySQL's default) every character `mysql_real_escape_string`
+    /// escapes is escaped; otherwise only quotes need doubling. ClickHouse and BigQuery only
+    /// need backslashes and quotes escaped.
+    pub fn escape_string(self, value: &str) -> String {
+        match self {
+            SqlDialect::Postgres
+            | SqlDialect::SqlServer
+            | SqlDialect::Oracle
+            | SqlDialect::DuckDb
+            | SqlDialect::Cql
+            | SqlDialect::MongoDb
+            | SqlDialect::Redis
+            | SqlDialect::MySql {
+                no_backslash_escapes: true,
+            } => value.replace('\'', "''"),
+            SqlDialect::ClickHouse | SqlDialect::BigQuery => {
+                value.replace('\\', "\\\\").replace('\'', "\\'")
+            }
+            SqlDialect::MySql {
+                no_backslash_escapes: false,
+            } => {
+                let mut escaped = String::with_capacity(value.len());
+                for c in value.chars() {
+                    match c {
+                        '\0' => escaped.push_str("\\0"),
+                        '\n' => escaped.push_str("\\n"),
+                        '\r' => escaped.push_str("\\r"),
+                        '\x1a' => escaped.push_str("\\Z"),
+                        '\\' | '\'' | '"' => {
+                            escaped.push('\\');
+                            escaped.push(c);
+                        }
+                        _ => escaped.push(c),
+                    }
+                }
+                escaped
+            }
+        }
+    }
+
+    /// Quotes a string as a SQL literal. SQL Server takes an `N'...'` literal, which keeps
+    /// characters outside the database's code page.
+    pub fn quote_string(self, value: &str) -> String {
+        match self {
+            SqlDialect::SqlServer => format!("N'{}'", self.escape_string(value)),
+            _ => format!("'{}'", self.escape_string(value)),
+        }
+    }
+
+    /// Formats raw bytes as a literal. PostgreSQL takes a `bytea` hex literal, DuckDB a blob
+    /// of `\x` escapes, SQL Server and CQL a `0x` constant, Oracle `HEXTORAW` and BigQuery
+    /// `FROM_HEX`; MySQL and ClickHouse take valid UTF-8 as a string, so text stays
+    /// readable, and anything else as a hex literal (`unhex` on ClickHouse) so binary values
+    /// survive the round trip.
+    pub fn quote_bytes(self, bytes: &[u8]) -> String {
+        let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        match self {
+            SqlDialect::Postgres | SqlDialect::MongoDb | SqlDialect::Redis => {
+                format!("'\\x{}'::bytea", hex)
+            }
+            SqlDialect::DuckDb => {
+                let escaped: String = bytes.iter().map(|b| format!("\\x{:02X}", b)).collect();
+                format!("'{}'::BLOB", escaped)
+            }
+            SqlDialect::SqlServer | SqlDialect::Cql => format!("0x{}", hex),
+            SqlDialect::Oracle => format!("HEXTORAW('{}')", hex),
+            SqlDialect::BigQuery => format!("FROM_HEX('{}')", hex),
+            SqlDialect::MySql { .. } => match std::str::from_utf8(bytes) {
+                Ok(s) => self.quote_string(s),
+                Err(_) => format!("X'{}'", hex),
+            },
+            SqlDialect::ClickHouse => match std::str::from_utf8(bytes) {
+                Ok(s) => self.quote_string(s),
+                Err(_) => format!("unhex('{}')", hex),
+            },
+        }
+    }
+
+    /// SQL Server and Oracle (before 23ai) have no boolean literals; flag columns take 1
+    /// and 0.
+    pub fn format_bool(self, value: bool) -> &'static str {
+        match (self, value) {
+            (SqlDialect::SqlServer | SqlDialect::Oracle, true) => "1",
+            (SqlDialect::SqlServer | SqlDialect::Oracle, false) => "0",
+            (_, true) => "TRUE",
+            (_, false) => "FALSE",
+        }
+    }
+
+    /// Oracle reads quoted dates by the session's `NLS_DATE_FORMAT`, so it gets ANSI
+    /// literals.
+    pub fn format_date(self, value: NaiveDate) -> String {
+        match self {
+            SqlDialect::Oracle => format!("DATE '{}'", value.format("%Y-%m-%d")),
+            _ => format!("'{}'", value.format("%Y-%m-%d")),
+        }
+    }
+
+    pub fn format_time(self, value: NaiveTime) -> String {
+        format!("'{}'", value.format("%H:%M:%S"))
+    }
+
+    pub fn format_timestamp(self, value: NaiveDateTime) -> String {
+        match self {
+            SqlDialect::Oracle => format!("TIMESTAMP '{}'", value.format("%Y-%m-%d %H:%M:%S")),
+            _ => format!("'{}'", value.format("%Y-%m-%d %H:%M:%S")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoting() {
+        let mysql = SqlDialect::MySql {
+            no_backslash_escapes: false,
+        };
+        let table = TableRef::new(Some("shop".to_string()), "order\"s`");
+        assert_eq!(
+            SqlDialect::Postgres.qualified_name(&table),
+            "\"shop\".\"order\"\"s`\""
+        );
+        assert_eq!(mysql.qualified_name(&table), "`shop`.`order\"s```");
+        assert_eq!(SqlDialect::SqlServer.quote_identifier("a]b[c"), "[a]]b[c]");
+
+        assert_eq!(SqlDialect::Postgres.quote_string("it's \\n"), "'it''s \\n'");
+        assert_eq!(mysql.quote_string("it's \\\n"), "'it\\'s \\\\\\n'");
+        assert_eq!(
+            SqlDialect::MySql {
+                no_backslash_escapes: true
+            }
+            .quote_string("it's \\"),
+            "'it''s \\'"
+        );
+
+        assert_eq!(
+            SqlDialect::Postgres.quote_bytes(&[0xde, 0xad]),
+            "'\\xDEAD'::bytea"
+        );
+        assert_eq!(mysql.quote_bytes(b"abc"), "'abc'");
+        assert_eq!(mysql.quote_bytes(&[0xff, 0x00]), "X'FF00'");
+        assert_eq!(SqlDialect::SqlServer.quote_bytes(&[0xff, 0x00]), "0xFF00");
+        assert_eq!(SqlDialect::SqlServer.quote_string("it's"), "N'it''s'");
+        assert_eq!(
+            SqlDialect::Oracle.quote_bytes(&[0xca, 0xfe]),
+            "HEXTORAW('CAFE')"
+        );
+        assert_eq!(
+            SqlDialect::ClickHouse.quote_string("it's \\"),
+            "'it\\'s \\\\'"
+        );
+        assert_eq!(SqlDialect::ClickHouse.quote_bytes(&[0xff]), "unhex('FF')");
+        assert_eq!(
+            SqlDialect::DuckDb.quote_bytes(&[0xde, 0xad]),
+            "'\\xDE\\xAD'::BLOB"
+        );
+        assert_eq!(
+            SqlDialect::BigQuery.quote_identifier("odd`name\\"),
+            "`odd\\`name\\\\`"
+        );
+    }
+}
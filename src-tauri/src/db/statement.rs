@@ -0,0 +1,914 @@
+//! Lightweight SQL statement inspection used for client-side safety checks.
+//!
+//! This is not a full parser: it understands quoting, comments and statement
+//! boundaries well enough to classify what a script is about to do. The read-only check for
+//! read-only connections (`is_read_only`) and the WHERE check of `confirmation_reason` also
+//! parse statements with `sqlparser`.
+
+use super::connection::TableRef;
+use super::sql_dialect::SqlDialect;
+use sqlparser::ast::{
+    self, Expr, ObjectName, ObjectNamePart, SetExpr, TableFactor, Visit, Visitor,
+};
+use sqlparser::dialect::{
+    BigQueryDialect, ClickHouseDialect, Dialect, DuckDbDialect, GenericDialect, MsSqlDialect,
+    MySqlDialect, OracleDialect, PostgreSqlDialect,
+};
+use sqlparser::parser::Parser;
+use std::ops::ControlFlow;
+
+/// Built-in functions that change data or server state even when called from a SELECT.
+const WRITING_FUNCTIONS: &[&str] = &[
+    // PostgreSQL
+    "NEXTVAL",
+    "SETVAL",
+    "SET_CONFIG",
+    "PG_TERMINATE_BACKEND",
+    "PG_CANCEL_BACKEND",
+    "PG_RELOAD_CONF",
+    "PG_ROTATE_LOGFILE",
+    "PG_SWITCH_WAL",
+    "LO_CREATE",
+    "LO_IMPORT",
+    "LO_EXPORT",
+    "LO_UNLINK",
+    "LO_PUT",
+    "DBLINK_EXEC",
+    // MySQL/MariaDB
+    "GET_LOCK",
+    "RELEASE_LOCK",
+    "RELEASE_ALL_LOCKS",
+];
+
+/// Built-in functions that only compute a value. On read-only connections any other function
+/// call is rejected, since a user-defined function (or one like `dblink`) may write.
+const READ_ONLY_FUNCTIONS: &[&str] = &[
+    // Aggregates and window functions
+    "COUNT",
+    "SUM",
+    "AVG",
+    "MIN",
+    "MAX",
+    "STRING_AGG",
+    "GROUP_CONCAT",
+    "LISTAGG",
+    "ARRAY_AGG",
+    "JSON_AGG",
+    "JSONB_AGG",
+    "BOOL_AND",
+    "BOOL_OR",
+    "EVERY",
+    "STDDEV",
+    "STDDEV_POP",
+    "STDDEV_SAMP",
+    "VARIANCE",
+    "VAR_POP",
+    "VAR_SAMP",
+    "ROW_NUMBER",
+    "RANK",
+    "DENSE_RANK",
+    "PERCENT_RANK",
+    "CUME_DIST",
+    "NTILE",
+    "LAG",
+    "LEAD",
+    "FIRST_VALUE",
+    "LAST_VALUE",
+    "NTH_VALUE",
+    // Conditionals and conversions
+    "COALESCE",
+    "NULLIF",
+    "IFNULL",
+    "ISNULL",
+    "NVL",
+    "IF",
+    "IIF",
+    "GREATEST",
+    "LEAST",
+    "CAST",
+    "CONVERT",
+    "TO_CHAR",
+    "TO_DATE",
+    "TO_NUMBER",
+    "TO_TIMESTAMP",
+    // Numbers
+    "ABS",
+    "SIGN",
+    "ROUND",
+    "TRUNC",
+    "TRUNCATE",
+    "FLOOR",
+    "CEIL",
+    "CEILING",
+    "MOD",
+    "POWER",
+    "POW",
+    "SQRT",
+    "EXP",
+    "LN",
+    "LOG",
+    "LOG10",
+    "RANDOM",
+    "RAND",
+    // Strings
+    "LENGTH",
+    "CHAR_LENGTH",
+    "CHARACTER_LENGTH",
+    "OCTET_LENGTH",
+    "LEN",
+    "LOWER",
+    "UPPER",
+    "INITCAP",
+    "CONCAT",
+    "CONCAT_WS",
+    "SUBSTR",
+    "SUBSTRING",
+    "LEFT",
+    "RIGHT",
+    "LPAD",
+    "RPAD",
+    "TRIM",
+    "LTRIM",
+    "RTRIM",
+    "REPLACE",
+    "REVERSE",
+    "REPEAT",
+    "POSITION",
+    "STRPOS",
+    "INSTR",
+    "LOCATE",
+    "SPLIT_PART",
+    "FORMAT",
+    "ASCII",
+    "CHR",
+    "CHAR",
+    "MD5",
+    "SHA1",
+    "SHA2",
+    "REGEXP_LIKE",
+    "REGEXP_REPLACE",
+    "REGEXP_SUBSTR",
+    "REGEXP_MATCHES",
+    // Dates and times
+    "NOW",
+    "CURRENT_DATE",
+    "CURRENT_TIME",
+    "CURRENT_TIMESTAMP",
+    "LOCALTIME",
+    "LOCALTIMESTAMP",
+    "CURDATE",
+    "CURTIME",
+    "SYSDATE",
+    "GETDATE",
+    "DATE",
+    "DATE_TRUNC",
+    "DATE_PART",
+    "DATE_FORMAT",
+    "DATE_ADD",
+    "DATE_SUB",
+    "DATEDIFF",
+    "DATEADD",
+    "EXTRACT",
+    "AGE",
+    "YEAR",
+    "MONTH",
+    "DAY",
+    "HOUR",
+    "MINUTE",
+    "SECOND",
+    "UNIX_TIMESTAMP",
+    "FROM_UNIXTIME",
+    // JSON and arrays
+    "JSON_EXTRACT",
+    "JSON_UNQUOTE",
+    "JSON_OBJECT",
+    "JSON_ARRAY",
+    "JSON_BUILD_OBJECT",
+    "JSON_BUILD_ARRAY",
+    "JSONB_BUILD_OBJECT",
+    "JSONB_BUILD_ARRAY",
+    "JSON_EXTRACT_PATH_TEXT",
+    "TO_JSON",
+    "TO_JSONB",
+    "ARRAY_LENGTH",
+    "CARDINALITY",
+    "UNNEST",
+    "GENERATE_SERIES",
+    // Session and server information
+    "VERSION",
+    "DATABASE",
+    "SCHEMA",
+    "USER",
+    "CURRENT_USER",
+    "SESSION_USER",
+    "CURRENT_SCHEMA",
+    "CURRENT_DATABASE",
+    "CURRENT_CATALOG",
+    "PG_TYPEOF",
+    "PG_SIZE_PRETTY",
+    "PG_RELATION_SIZE",
+    "PG_TOTAL_RELATION_SIZE",
+    "PG_DATABASE_SIZE",
+];
+
+/// A single statement from a script with its comments removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    /// Statement text without comments or the trailing semicolon.
+    pub sql: String,
+    /// Unquoted words in the statement, uppercased (string literals and quoted identifiers excluded).
+    pub keywords: Vec<String>,
+}
+
+impl Statement {
+    /// Returns the first keyword of the statement (e.g. `SELECT`).
+    pub fn leading_keyword(&self) -> Option<&str> {
+        self.keywords.first().map(String::as_str)
+    }
+
+    /// Returns true if the statement contains the given (uppercase) keyword.
+    pub fn has_keyword(&self, keyword: &str) -> bool {
+        self.keywords.iter().any(|k| k == keyword)
+    }
+
+    /// Returns true if the statement only reads data.
+    pub fn is_read_only(&self) -> bool {
+        if self
+            .keywords
+            .iter()
+            .any(|k| WRITING_FUNCTIONS.contains(&k.as_str()))
+        {
+            return false;
+        }
+        match self.leading_keyword() {
+            Some("SELECT") => !self.has_keyword("INTO") && !self.has_keyword("FOR"),
+            Some("WITH") => !["INSERT", "UPDATE", "DELETE", "MERGE", "INTO"]
+                .iter()
+                .any(|k| self.has_keyword(k)),
+            Some("EXPLAIN") => !self.has_keyword("ANALYZE") && !self.has_keyword("ANALYSE"),
+            Some("SHOW") | Some("DESCRIBE") | Some("DESC") | Some("VALUES") | Some("TABLE")
+            | Some("USE") => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the statement changes or removes existing data or schema objects
+    /// (UPDATE, DELETE, DDL, privilege changes). Plain INSERTs are not considered destructive.
+    pub fn is_destructive(&self) -> bool {
+        match self.leading_keyword() {
+            Some("UPDATE") | Some("DELETE") | Some("MERGE") | Some("REPLACE")
+            | Some("TRUNCATE") | Some("CREATE") | Some("ALTER") | Some("DROP") | Some("RENAME")
+            | Some("COMMENT") | Some("GRANT") | Some("REVOKE") => true,
+            Some("WITH") => ["UPDATE", "DELETE", "MERGE"]
+                .iter()
+                .any(|k| self.has_keyword(k)),
+            Some("INSERT") => self.has_keyword("UPDATE"),
+            _ => false,
+        }
+    }
+
+    /// Returns why the statement should be confirmed before running if it is a TRUNCATE or
+    /// DROP. UPDATE and DELETE need a parse to find their WHERE (see `confirmation_reason`).
+    pub fn confirmation_reason(&self) -> Option<&'static str> {
+        match self.leading_keyword() {
+            Some("TRUNCATE") => Some("TRUNCATE removes every row"),
+            Some("DROP") => Some("DROP removes the object and its data"),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the statement is a query that only returns rows (SELECT, VALUES, TABLE
+    /// or a read-only WITH), as opposed to SHOW/EXPLAIN-style commands.
+    pub fn is_query(&self) -> bool {
+        matches!(
+            self.leading_keyword(),
+            Some("SELECT") | Some("WITH") | Some("VALUES") | Some("TABLE")
+        ) && self.is_read_only()
+    }
+
+    /// Returns true if plain EXPLAIN (which plans without executing) accepts the statement.
+    pub fn is_explainable(&self) -> bool {
+        matches!(
+            self.leading_keyword(),
+            Some("SELECT")
+                | Some("WITH")
+                | Some("INSERT")
+                | Some("UPDATE")
+                | Some("DELETE")
+                | Some("REPLACE")
+        )
+    }
+
+    /// Returns true if the statement changes schema objects (tables, columns, keys).
+    pub fn changes_schema(&self) -> bool {
+        matches!(
+            self.leading_keyword(),
+            Some("CREATE") | Some("ALTER") | Some("DROP") | Some("RENAME") | Some("COMMENT")
+        )
+    }
+}
+
+/// Splits a script into statements.
+///
+/// Semicolons inside string literals, quoted identifiers, comments and
+/// PostgreSQL dollar-quoted bodies do not end a statement. Empty statements are skipped.
+pub fn split_statements(sql: &str) -> Vec<Statement> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut keywords = Vec::new();
+    let mut word = String::new();
+    let mut in_executable_comment = false;
+    let mut i = 0;
+
+    fn flush_word(word: &mut String, keywords: &mut Vec<String>) {
+        if !word.is_empty() {
+            keywords.push(word.to_uppercase());
+            word.clear();
+        }
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        match c {
+            '-' if next == Some('-') => {
+                flush_word(&mut word, &mut keywords);
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                current.push(' ');
+                continue;
+            }
+            '#' => {
+                // MySQL line comment
+                flush_word(&mut word, &mut keywords);
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                current.push(' ');
+                continue;
+            }
+            '/' if next == Some('*')
+                && (chars.get(i + 2) == Some(&'!')
+                    || (chars.get(i + 2) == Some(&'M') && chars.get(i + 3) == Some(&'!'))) =>
+            {
+                // MySQL/MariaDB executable comment: the server runs its contents, so they are
+                // read as part of the statement and kept in its text. The optional version
+                // number is not a keyword.
+                flush_word(&mut word, &mut keywords);
+                let start = i;
+                i += if chars[i + 2] == 'M' { 4 } else { 3 };
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                current.extend(&chars[start..i]);
+                in_executable_comment = true;
+                continue;
+            }
+            '*' if in_executable_comment && next == Some('/') => {
+                flush_word(&mut word, &mut keywords);
+                in_executable_comment = false;
+                current.push_str("*/");
+                i += 2;
+                continue;
+            }
+            '/' if next == Some('*') => {
+                flush_word(&mut word, &mut keywords);
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+                current.push(' ');
+                continue;
+            }
+            '\'' | '"' | '`' => {
+                flush_word(&mut word, &mut keywords);
+                let quote = c;
+                current.push(c);
+                i += 1;
+                while i < chars.len() {
+                    current.push(chars[i]);
+                    if chars[i] == '\\' && quote == '\'' && i + 1 < chars.len() {
+                        current.push(chars[i + 1]);
+                        i += 2;
+                        continue;
+                    }
+                    if chars[i] == quote {
+                        if chars.get(i + 1) == Some(&quote) {
+                            current.push(quote);
+                            i += 2;
+                            continue;
+                        }
+                        break;
+                    }
+                    i += 1;
+                }
+                i += 1;
+                continue;
+            }
+            '$' if word.is_empty() => {
+                // PostgreSQL dollar quoting: $$...$$ or $tag$...$tag$
+                let tag_end = chars[i + 1..]
+                    .iter()
+                    .position(|ch| !(ch.is_alphanumeric() || *ch == '_'))
+                    .map(|p| i + 1 + p);
+                if let Some(end) = tag_end.filter(|&end| chars[end] == '$') {
+                    let tag: String = chars[i..=end].iter().collect();
+                    let body_start = end + 1;
+                    let rest: String = chars[body_start..].iter().collect();
+                    let body_len = rest.find(&tag).unwrap_or(rest.len());
+                    let consumed =
+                        body_start + rest[..body_len].chars().count() + tag.chars().count();
+                    let consumed = consumed.min(chars.len());
+                    current.extend(&chars[i..consumed]);
+                    i = consumed;
+                    continue;
+                }
+            }
+            ';' => {
+                flush_word(&mut word, &mut keywords);
+                push_statement(&mut statements, &mut current, &mut keywords);
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+        } else {
+            flush_word(&mut word, &mut keywords);
+        }
+        current.push(c);
+        i += 1;
+    }
+
+    flush_word(&mut word, &mut keywords);
+    push_statement(&mut statements, &mut current, &mut keywords);
+    statements
+}
+
+fn push_statement(
+    statements: &mut Vec<Statement>,
+    current: &mut String,
+    keywords: &mut Vec<String>,
+) {
+    let sql = current.trim();
+    if !sql.is_empty() {
+        statements.push(Statement {
+            sql: sql.to_string(),
+            keywords: std::mem::take(keywords),
+        });
+    }
+    current.clear();
+    keywords.clear();
+}
+
+/// Returns true if any statement in `sql` is destructive (see `Statement::is_destructive`).
+pub fn is_destructive(sql: &str) -> bool {
+    split_statements(sql).iter().any(Statement::is_destructive)
+}
+
+/// Returns true if any statement in `sql` changes the schema (see `Statement::changes_schema`).
+pub fn changes_schema(sql: &str) -> bool {
+    split_statements(sql).iter().any(Statement::changes_schema)
+}
+
+/// Returns true if every statement in `sql` only reads data, for enforcing read-only
+/// connections.
+///
+/// On top of `Statement::is_read_only`, each statement is parsed with `sqlparser` in the
+/// connection's dialect and every part of it must be a read. The check fails closed:
+/// statements that don't parse, MySQL executable comments (which the parser skips) and calls
+/// to functions other than `READ_ONLY_FUNCTIONS` count as writes.
+pub fn is_read_only(sql: &str, dialect: SqlDialect) -> bool {
+    let parser_dialect = parser_dialect(dialect);
+    split_statements(sql).iter().all(|statement| {
+        statement.is_read_only()
+            && !statement.sql.contains("/*!")
+            && !statement.sql.contains("/*M!")
+            && Parser::parse_sql(parser_dialect.as_ref(), &statement.sql).is_ok_and(|parsed| {
+                parsed
+                    .iter()
+                    .all(|parsed| parsed.visit(&mut ReadOnlyCheck).is_continue())
+            })
+    })
+}
+
+/// The `sqlparser` dialect for a connection's dialect; drivers it has none for use the
+/// generic one.
+fn parser_dialect(dialect: SqlDialect) -> Box<dyn Dialect> {
+    match dialect {
+        SqlDialect::Postgres => Box::new(PostgreSqlDialect {}),
+        SqlDialect::MySql { .. } => Box::new(MySqlDialect {}),
+        SqlDialect::SqlServer => Box::new(MsSqlDialect {}),
+        SqlDialect::Oracle => Box::new(OracleDialect {}),
+        SqlDialect::ClickHouse => Box::new(ClickHouseDialect {}),
+        SqlDialect::DuckDb => Box::new(DuckDbDialect {}),
+        SqlDialect::BigQuery => Box::new(BigQueryDialect {}),
+        SqlDialect::Cql | SqlDialect::MongoDb | SqlDialect::Redis => Box::new(GenericDialect {}),
+    }
+}
+
+/// Walks a parsed statement and breaks at the first part that may write.
+struct ReadOnlyCheck;
+
+impl ReadOnlyCheck {
+    /// Allows calls to unqualified `READ_ONLY_FUNCTIONS` only; a qualified or quoted name may
+    /// be a user-defined function.
+    fn check_function(name: &ObjectName) -> ControlFlow<()> {
+        match name.0.as_slice() {
+            [ObjectNamePart::Identifier(ident)]
+                if ident.quote_style.is_none()
+                    && READ_ONLY_FUNCTIONS.contains(&ident.value.to_uppercase().as_str()) =>
+            {
+                ControlFlow::Continue(())
+            }
+            _ => ControlFlow::Break(()),
+        }
+    }
+}
+
+impl Visitor for ReadOnlyCheck {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, statement: &ast::Statement) -> ControlFlow<()> {
+        let read_only = match statement {
+            ast::Statement::Explain {
+                analyze, options, ..
+            } => {
+                !analyze
+                    && !options.iter().flatten().any(|option| {
+                        option.name.value.eq_ignore_ascii_case("ANALYZE")
+                            || option.name.value.eq_ignore_ascii_case("ANALYSE")
+                    })
+            }
+            ast::Statement::Query(_)
+            | ast::Statement::ExplainTable { .. }
+            | ast::Statement::Use(_)
+            | ast::Statement::ShowFunctions { .. }
+            | ast::Statement::ShowVariable { .. }
+            | ast::Statement::ShowStatus { .. }
+            | ast::Statement::ShowVariables { .. }
+            | ast::Statement::ShowCreate { .. }
+            | ast::Statement::ShowColumns { .. }
+            | ast::Statement::ShowCatalogs { .. }
+            | ast::Statement::ShowDatabases { .. }
+            | ast::Statement::ShowProcessList { .. }
+            | ast::Statement::ShowSchemas { .. }
+            | ast::Statement::ShowCharset(_)
+            | ast::Statement::ShowObjects(_)
+            | ast::Statement::ShowTables { .. }
+            | ast::Statement::ShowViews { .. }
+            | ast::Statement::ShowCollation { .. } => true,
+            _ => false,
+        };
+        if read_only {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    }
+
+    fn pre_visit_query(&mut self, query: &ast::Query) -> ControlFlow<()> {
+        // FOR UPDATE and FOR SHARE lock rows
+        if query.locks.is_empty() {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    }
+
+    fn pre_visit_select(&mut self, select: &ast::Select) -> ControlFlow<()> {
+        // SELECT INTO creates a table on PostgreSQL and SQL Server
+        if select.into.is_none() {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    }
+
+    fn pre_visit_table_factor(&mut self, table_factor: &TableFactor) -> ControlFlow<()> {
+        match table_factor {
+            TableFactor::Table {
+                name,
+                args: Some(_),
+                ..
+            }
+            | TableFactor::Function { name, .. } => Self::check_function(name),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::Function(function) => Self::check_function(&function.name),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+/// Returns why the first statement in `sql` that needs confirmation should be confirmed:
+/// TRUNCATE and DROP (see `Statement::confirmation_reason`), and UPDATE or DELETE without a
+/// WHERE clause.
+///
+/// UPDATEs and DELETEs are parsed with `sqlparser` in the connection's dialect, so a WHERE in
+/// a subquery doesn't count and ones under WITH are found too. Those that don't parse are
+/// confirmed, since their WHERE can't be checked.
+pub fn confirmation_reason(sql: &str, dialect: SqlDialect) -> Option<&'static str> {
+    let parser_dialect = parser_dialect(dialect);
+    split_statements(sql).iter().find_map(|statement| {
+        if let Some(reason) = statement.confirmation_reason() {
+            return Some(reason);
+        }
+        if !statement.has_keyword("UPDATE") && !statement.has_keyword("DELETE") {
+            return None;
+        }
+        match Parser::parse_sql(parser_dialect.as_ref(), &statement.sql) {
+            Ok(parsed) => parsed.iter().find_map(unfiltered_change),
+            Err(_) => {
+                let changes = matches!(
+                    statement.leading_keyword(),
+                    Some("UPDATE" | "DELETE" | "WITH")
+                );
+                changes.then_some("The statement could not be checked for a WHERE clause")
+            }
+        }
+    })
+}
+
+/// Returns why a parsed UPDATE or DELETE without a WHERE clause of its own needs
+/// confirmation, looking inside WITH queries for them as well.
+fn unfiltered_change(statement: &ast::Statement) -> Option<&'static str> {
+    match statement {
+        ast::Statement::Update(update) if update.selection.is_none() => {
+            Some("UPDATE without WHERE changes every row")
+        }
+        ast::Statement::Delete(delete) if delete.selection.is_none() => {
+            Some("DELETE without WHERE removes every row")
+        }
+        ast::Statement::Query(query) => unfiltered_change_in_query(query),
+        _ => None,
+    }
+}
+
+/// Finds an unfiltered UPDATE or DELETE in the body or the common table expressions of a
+/// query (`WITH ... DELETE`, or PostgreSQL's `WITH gone AS (DELETE ...) SELECT ...`).
+fn unfiltered_change_in_query(query: &ast::Query) -> Option<&'static str> {
+    let in_ctes = query
+        .with
+        .iter()
+        .flat_map(|with| &with.cte_tables)
+        .find_map(|cte| unfiltered_change_in_query(&cte.query));
+    in_ctes.or_else(|| match query.body.as_ref() {
+        SetExpr::Update(statement) | SetExpr::Delete(statement) => unfiltered_change(statement),
+        SetExpr::Query(query) => unfiltered_change_in_query(query),
+        _ => None,
+    })
+}
+
+/// Returns true if `sql` has at least one statement and all are queries (see
+/// `Statement::is_query`).
+pub fn is_query(sql: &str) -> bool {
+    let statements = split_statements(sql);
+    !statements.is_empty() && statements.iter().all(Statement::is_query)
+}
+
+/// Returns true if `sql` is a single statement that EXPLAIN accepts (see
+/// `Statement::is_explainable`).
+pub fn is_explainable(sql: &str) -> bool {
+    let statements = split_statements(sql);
+    statements.len() == 1 && statements[0].is_explainable()
+}
+
+/// Returns the table of a `SELECT * FROM <table>` statement, as the app writes it to browse
+/// a table, for drivers that otherwise take no SQL (MongoDB, Redis).
+pub fn browsed_table(sql: &str) -> Option<TableRef> {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    sql.get(..14)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("SELECT * FROM "))?;
+    match parse_name(&sql[14..])?.as_slice() {
+        [name] => Some(TableRef::unqualified(name.clone())),
+        [schema, name] => Some(TableRef::new(Some(schema.clone()), name)),
+        _ => None,
+    }
+}
+
+/// Splits a possibly qualified name into its parts, each bare or quoted with `"`, `` ` ``
+/// or `[]` and a doubled closing quote for a quote inside.
+fn parse_name(text: &str) -> Option<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut chars = text.trim().chars().peekable();
+    loop {
+        let mut part = String::new();
+        match chars.peek() {
+            Some(&open @ ('"' | '`' | '[')) => {
+                chars.next();
+                let close = if open == '[' { ']' } else { open };
+                loop {
+                    let c = chars.next()?;
+                    if c != close {
+                        part.push(c);
+                    } else if chars.peek() == Some(&close) {
+                        chars.next();
+                        part.push(close);
+                    } else {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c.is_whitespace() {
+                        break;
+                    }
+                    part.push(c);
+                    chars.next();
+                }
+            }
+        }
+        if part.is_empty() {
+            return None;
+        }
+        parts.push(part);
+        match chars.next() {
+            None => return Some(parts),
+            Some('.') => continue,
+            Some(_) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_ignores_quoted_semicolons_and_comments() {
+        let statements = split_statements(
+            "SELECT 'a;b' AS \"x;y\"; -- trailing; comment\n/* block; */ DELETE FROM t;;",
+        );
+
+        assert_eq!(statements.len(), 2);
+        assert_eq!(statements[0].sql, "SELECT 'a;b' AS \"x;y\"");
+        assert_eq!(statements[1].leading_keyword(), Some("DELETE"));
+    }
+
+    #[test]
+    fn test_split_dollar_quoted_body() {
+        let statements = split_statements(
+            "CREATE FUNCTION f() RETURNS int AS $body$ SELECT 1; $body$ LANGUAGE sql; SELECT 2",
+        );
+
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].sql.ends_with("LANGUAGE sql"));
+    }
+
+    #[test]
+    fn test_read_only_classification() {
+        let pg = SqlDialect::Postgres;
+        let mysql = SqlDialect::MySql {
+            no_backslash_escapes: false,
+        };
+        assert!(is_read_only("select * from users; show tables", mysql));
+        assert!(is_read_only("WITH t AS (SELECT 1) SELECT * FROM t", pg));
+        assert!(is_read_only("SELECT 'delete from users'", pg));
+        assert!(is_read_only("EXPLAIN SELECT 1", pg));
+        assert!(is_read_only(
+            "SELECT lower(name), count(*) FROM users GROUP BY 1",
+            pg
+        ));
+        assert!(is_read_only("SELECT * FROM generate_series(1, 3)", pg));
+        assert!(is_read_only("WITH t (a) AS (SELECT 1) SELECT a FROM t", pg));
+
+        assert!(!is_read_only("SELECT 1; UPDATE users SET name = 'x'", pg));
+        assert!(!is_read_only(
+            "WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d",
+            pg
+        ));
+        assert!(!is_read_only("SELECT * INTO backup FROM users", pg));
+        assert!(!is_read_only("EXPLAIN ANALYZE DELETE FROM users", pg));
+        assert!(!is_read_only("EXPLAIN (ANALYZE) SELECT 1", pg));
+        assert!(!is_read_only("/* SELECT */ DROP TABLE users", pg));
+        assert!(!is_read_only(
+            "SELECT 1 /*!50000 , sleep(1) INTO @x */",
+            mysql
+        ));
+        assert!(!is_read_only("SELECT 1 /*M! ; DROP TABLE users */", mysql));
+        assert!(!is_read_only("SELECT 1 /*!50000 , my_writer() */", mysql));
+        assert!(!is_read_only("SELECT nextval('users_id_seq')", pg));
+        assert!(!is_read_only("SELECT pg_terminate_backend(42)", pg));
+        assert!(!is_read_only("SELECT * FROM users FOR UPDATE", pg));
+        assert!(!is_read_only("SELECT my_writer()", pg));
+        assert!(!is_read_only("SELECT app.lower(name) FROM users", pg));
+        assert!(!is_read_only(
+            "SELECT * FROM dblink('db', 'DELETE FROM t') AS t(n int)",
+            pg
+        ));
+        assert!(!is_read_only("SELECT * FROM users WHERE (", pg));
+    }
+
+    #[test]
+    fn test_destructive_classification() {
+        assert!(is_destructive("update users set name = 'x' where id = 1"));
+        assert!(is_destructive("SELECT 1; DROP TABLE users"));
+        assert!(is_destructive(
+            "INSERT INTO t (id) VALUES (1) ON DUPLICATE KEY UPDATE id = 1"
+        ));
+
+        assert!(!is_destructive("INSERT INTO t (id) VALUES (1)"));
+        assert!(!is_destructive("SELECT * FROM deleted_rows"));
+    }
+
+    #[test]
+    fn test_statements_needing_confirmation() {
+        let pg = SqlDialect::Postgres;
+        assert!(confirmation_reason("UPDATE users SET active = false", pg).is_some());
+        assert!(confirmation_reason("SELECT 1; delete from users", pg).is_some());
+        assert!(confirmation_reason("TRUNCATE audit", pg).is_some());
+        assert!(confirmation_reason("DROP TABLE users", pg).is_some());
+
+        assert!(confirmation_reason("UPDATE users SET active = false WHERE id = 1", pg).is_none());
+        assert!(confirmation_reason("DELETE FROM users WHERE id = 1", pg).is_none());
+        assert!(confirmation_reason("SELECT * FROM users", pg).is_none());
+        assert!(confirmation_reason("SELECT * FROM users FOR UPDATE", pg).is_none());
+    }
+
+    #[test]
+    fn test_where_is_found_in_the_parsed_statement() {
+        let pg = SqlDialect::Postgres;
+        let mysql = SqlDialect::MySql {
+            no_backslash_escapes: false,
+        };
+
+        // DELETE ... USING
+        assert!(confirmation_reason("DELETE FROM orders USING customers", pg).is_some());
+        assert!(confirmation_reason(
+            "DELETE FROM orders USING customers WHERE orders.customer_id = customers.id",
+            pg
+        )
+        .is_none());
+
+        // Under WITH, and in a data-modifying common table expression
+        assert!(confirmation_reason(
+            "WITH stale AS (SELECT id FROM sessions WHERE expired) DELETE FROM sessions",
+            pg
+        )
+        .is_some());
+        assert!(confirmation_reason(
+            "WITH stale AS (SELECT id FROM sessions WHERE expired)
+             DELETE FROM sessions WHERE id IN (SELECT id FROM stale)",
+            pg
+        )
+        .is_none());
+        assert!(confirmation_reason(
+            "WITH gone AS (DELETE FROM sessions RETURNING id) SELECT count(*) FROM gone",
+            pg
+        )
+        .is_some());
+
+        // A WHERE only in a subquery doesn't limit the rows changed
+        assert!(confirmation_reason(
+            "UPDATE users SET plan = (SELECT plan FROM plans WHERE plans.id = 1)",
+            pg
+        )
+        .is_some());
+        assert!(confirmation_reason(
+            "DELETE FROM users WHERE id IN (SELECT user_id FROM bans WHERE permanent)",
+            pg
+        )
+        .is_none());
+
+        assert!(confirmation_reason(
+            "DELETE o FROM orders o JOIN customers c ON o.customer_id = c.id",
+            mysql
+        )
+        .is_some());
+        assert!(confirmation_reason("UPDATE users SET `where` = 1", mysql).is_some());
+
+        // Statements that don't parse can't be checked
+        assert!(confirmation_reason("UPDATE users SET WHERE id = 1", pg).is_some());
+    }
+
+    #[test]
+    fn test_schema_change_classification() {
+        assert!(changes_schema("ALTER TABLE users ADD COLUMN age int"));
+        assert!(changes_schema("SELECT 1; drop view v"));
+
+        assert!(!changes_schema("DELETE FROM users"));
+        assert!(!changes_schema("SELECT 'create table t'"));
+    }
+
+    #[test]
+    fn test_explainable_statements() {
+        assert!(is_explainable("SELECT * FROM users WHERE id = 1;"));
+        assert!(is_explainable("delete from users where id = 1"));
+
+        assert!(!is_explainable("EXPLAIN ANALYZE DELETE FROM users"));
+        assert!(!is_explainable("ANALYZE users"));
+        assert!(!is_explainable("SELECT 1; SELECT 2"));
+    }
+}
@@ -0,0 +1,147 @@
+//! Full-text searches over chosen columns of a table. The SQL is generated so the UI can
+//! show it (and open it in an editor) as well as run it: PostgreSQL matches
+//! `to_tsvector` against `websearch_to_tsquery`, MySQL uses `MATCH ... AGAINST`.
+
+use super::connection::{error_codes, QueryError, TableRef, MAX_QUERY_ROWS};
+use serde::Deserialize;
+
+/// A search for `query` in `columns` of `table`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TextSearchRequest {
+    pub table: TableRef,
+    pub columns: Vec<String>,
+    /// Search text. On PostgreSQL it uses web search syntax (`"exact phrase"`, `or`,
+    /// `-excluded`); on MySQL boolean mode operators apply when `boolean_mode` is set.
+    pub query: String,
+    /// Text search configuration, e.g. `english` (PostgreSQL only). Defaults to the
+    /// server's `default_text_search_config`.
+    #[serde(default)]
+    pub config: Option<String>,
+    /// Searches `IN BOOLEAN MODE` instead of natural language mode (MySQL only).
+    #[serde(default)]
+    pub boolean_mode: bool,
+    /// Maximum number of rows returned, best matches first.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl TextSearchRequest {
+    pub fn validate(&self) -> Result<(), QueryError> {
+        let error = |message: &str| Err(QueryError::with_code(message, error_codes::QUERY_ERROR));
+        if self.columns.is_empty() {
+            return error("Choose at least one column to search");
+        }
+        if self.query.trim().is_empty() {
+            return error("Search text is required");
+        }
+        Ok(())
+    }
+
+    fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(MAX_QUERY_ROWS)
+            .clamp(1, MAX_QUERY_ROWS)
+    }
+}
+
+/// Builds a PostgreSQL search. `table` and `columns` are quoted; `config` and `query` are
+/// string literals.
+pub fn tsvector_query(
+    request: &TextSearchRequest,
+    table: &str,
+    columns: &[String],
+    config: Option<&str>,
+    query: &str,
+) -> String {
+    // A single column is searched as is, so an expression index on it can be used
+    let document = match columns {
+        [column] => column.clone(),
+        _ => format!("concat_ws(' ', {})", columns.join(", ")),
+    };
+    let (vector, tsquery) = match config {
+        Some(config) => (
+            format!("to_tsvector({}::regconfig, {})", config, document),
+            format!("websearch_to_tsquery({}::regconfig, {})", config, query),
+        ),
+        None => (
+            format!("to_tsvector({})", document),
+            format!("websearch_to_tsquery({})", query),
+        ),
+    };
+    format!(
+        "SELECT * FROM {} WHERE {} @@ {} ORDER BY ts_rank({}, {}) DESC LIMIT {}",
+        table,
+        vector,
+        tsquery,
+        vector,
+        tsquery,
+        request.limit()
+    )
+}
+
+/// Builds a MySQL search. `table` and `columns` are quoted and `query` is a string literal.
+/// The columns need a FULLTEXT index covering exactly them.
+pub fn match_query(
+    request: &TextSearchRequest,
+    table: &str,
+    columns: &[String],
+    query: &str,
+) -> String {
+    let mode = if request.boolean_mode {
+        "IN BOOLEAN MODE"
+    } else {
+        "IN NATURAL LANGUAGE MODE"
+    };
+    let matches = format!(
+        "MATCH ({}) AGAINST ({} {})",
+        columns.join(", "),
+        query,
+        mode
+    );
+    format!(
+        "SELECT * FROM {} WHERE {} ORDER BY {} DESC LIMIT {}",
+        table,
+        matches,
+        matches,
+        request.limit()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_queries() {
+        let mut request = TextSearchRequest {
+            table: TableRef::unqualified("posts"),
+            columns: vec!["title".to_string(), "body".to_string()],
+            query: "rust -java".to_string(),
+            config: None,
+            boolean_mode: true,
+            limit: Some(20),
+        };
+        assert!(request.validate().is_ok());
+
+        let columns = ["\"title\"".to_string()];
+        assert_eq!(
+            tsvector_query(&request, "\"posts\"", &columns, Some("'english'"), "'rust -java'"),
+            "SELECT * FROM \"posts\" \
+             WHERE to_tsvector('english'::regconfig, \"title\") @@ websearch_to_tsquery('english'::regconfig, 'rust -java') \
+             ORDER BY ts_rank(to_tsvector('english'::regconfig, \"title\"), websearch_to_tsquery('english'::regconfig, 'rust -java')) DESC \
+             LIMIT 20"
+        );
+
+        let columns = ["`title`".to_string(), "`body`".to_string()];
+        assert_eq!(
+            match_query(&request, "`posts`", &columns, "'rust -java'"),
+            "SELECT * FROM `posts` \
+             WHERE MATCH (`title`, `body`) AGAINST ('rust -java' IN BOOLEAN MODE) \
+             ORDER BY MATCH (`title`, `body`) AGAINST ('rust -java' IN BOOLEAN MODE) DESC \
+             LIMIT 20"
+        );
+
+        request.columns.clear();
+        assert!(request.validate().is_err());
+    }
+}
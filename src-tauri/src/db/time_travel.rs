@@ -0,0 +1,58 @@
+//! Queries of a table's past contents. MariaDB keeps the history of system-versioned tables
+//! and reads it with `FOR SYSTEM_TIME AS OF`; the `system_versioning_asof` variable applies
+//! that to every versioned table of a statement, so any query can be run as of a point in
+//! time.
+
+use super::connection::{error_codes, DbResult, QueryError};
+use chrono::NaiveDateTime;
+
+/// Formats accepted for `as_of`, interpreted in the session's time zone.
+const AS_OF_FORMATS: [&str; 2] = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"];
+
+/// Parses the `as_of` option of table browsing and `execute_query`.
+///
+/// # Errors
+/// Returns `QUERY_ERROR` unless it is a `YYYY-MM-DD HH:MM:SS[.ffffff]` timestamp.
+pub fn parse_as_of(as_of: &str) -> DbResult<NaiveDateTime> {
+    let as_of = as_of.trim();
+    AS_OF_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(as_of, format).ok())
+        .ok_or_else(|| {
+            QueryError::with_code(
+                format!(
+                    "Invalid point in time '{}', expected YYYY-MM-DD HH:MM:SS",
+                    as_of
+                ),
+                error_codes::QUERY_ERROR,
+            )
+        })
+}
+
+/// Wraps `query` so MariaDB runs it against the system-versioned tables as they were at
+/// `as_of`. Tables without system versioning are read as they are now.
+pub fn system_versioning_query(query: &str, as_of: NaiveDateTime) -> String {
+    format!(
+        "SET STATEMENT system_versioning_asof = '{}' FOR {}",
+        as_of.format("%Y-%m-%d %H:%M:%S%.6f"),
+        query.trim().trim_end_matches(';')
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_versioning_query() {
+        let as_of = parse_as_of("2024-03-01T12:30:00").unwrap();
+        assert_eq!(as_of, parse_as_of(" 2024-03-01 12:30:00.000 ").unwrap());
+        assert_eq!(
+            system_versioning_query("SELECT * FROM `orders`;\n", as_of),
+            "SET STATEMENT system_versioning_asof = '2024-03-01 12:30:00.000000' \
+             FOR SELECT * FROM `orders`"
+        );
+        assert!(parse_as_of("yesterday").is_err());
+        assert!(parse_as_of("2024-03-01").is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! Trigger definitions built in the app, so triggers (e.g. for auditing) can be created
+//! without writing dialect-specific DDL. Drivers render the DDL; the checks both dialects
+//! share are here.
+
+use super::connection::{error_codes, QueryError, TableRef};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerTiming {
+    Before,
+    After,
+    /// PostgreSQL only, on views.
+    InsteadOf,
+}
+
+impl TriggerTiming {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            TriggerTiming::Before => "BEFORE",
+            TriggerTiming::After => "AFTER",
+            TriggerTiming::InsteadOf => "INSTEAD OF",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerEvent {
+    Insert,
+    Update,
+    Delete,
+    /// PostgreSQL only, with statement-level triggers.
+    Truncate,
+}
+
+impl TriggerEvent {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            TriggerEvent::Insert => "INSERT",
+            TriggerEvent::Update => "UPDATE",
+            TriggerEvent::Delete => "DELETE",
+            TriggerEvent::Truncate => "TRUNCATE",
+        }
+    }
+}
+
+/// Whether a trigger fires once per changed row or once per statement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerLevel {
+    #[default]
+    Row,
+    /// PostgreSQL only.
+    Statement,
+}
+
+/// A trigger to create.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TriggerSpec {
+    pub name: String,
+    /// Table (or, for INSTEAD OF triggers, view) the trigger is on.
+    pub table: TableRef,
+    pub timing: TriggerTiming,
+    pub events: Vec<TriggerEvent>,
+    #[serde(default)]
+    pub level: TriggerLevel,
+    /// Condition the trigger only fires under, e.g. `NEW.status <> OLD.status`.
+    #[serde(default)]
+    pub when: Option<String>,
+    /// Statements the trigger runs. On PostgreSQL they are the body of a PL/pgSQL trigger
+    /// function named after the trigger, so row-level BEFORE triggers must `RETURN NEW`
+    /// (or NULL to skip the row). On MySQL they may be wrapped in `BEGIN ... END`.
+    pub body: String,
+}
+
+impl TriggerSpec {
+    /// Checks what both dialects require of a trigger.
+    pub fn validate(&self) -> Result<(), QueryError> {
+        let error = |message: &str| Err(QueryError::with_code(message, error_codes::QUERY_ERROR));
+        if self.name.trim().is_empty() {
+            return error("Trigger name is required");
+        }
+        if self.events.is_empty() {
+            return error("A trigger needs at least one event");
+        }
+        if self.body.trim().is_empty() {
+            return error("Trigger body is required");
+        }
+        if self.events.contains(&TriggerEvent::Truncate) && self.level == TriggerLevel::Row {
+            return error("TRUNCATE triggers must fire per statement");
+        }
+        if self.timing == TriggerTiming::InsteadOf
+            && (self.level != TriggerLevel::Row || self.when.is_some())
+        {
+            return error("INSTEAD OF triggers must fire per row and can't have a condition");
+        }
+        Ok(())
+    }
+
+    /// The trigger's events joined with `OR`, e.g. `INSERT OR UPDATE`.
+    pub fn events_clause(&self) -> String {
+        let mut events: Vec<&str> = Vec::new();
+        for event in &self.events {
+            if !events.contains(&event.keyword()) {
+                events.push(event.keyword());
+            }
+        }
+        events.join(" OR ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_and_events_clause() {
+        let mut spec = TriggerSpec {
+            name: "orders_audit".to_string(),
+            table: TableRef::unqualified("orders"),
+            timing: TriggerTiming::After,
+            events: vec![
+                TriggerEvent::Insert,
+                TriggerEvent::Update,
+                TriggerEvent::Insert,
+            ],
+            level: TriggerLevel::Row,
+            when: None,
+            body: "INSERT INTO audit VALUES (NEW.id);".to_string(),
+        };
+        assert!(spec.validate().is_ok());
+        assert_eq!(spec.events_clause(), "INSERT OR UPDATE");
+
+        spec.events.push(TriggerEvent::Truncate);
+        assert_eq!(
+            spec.validate().unwrap_err().message,
+            "TRUNCATE triggers must fire per statement"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Database user administration: creating and dropping users (login roles on PostgreSQL,
+//! accounts on MySQL) and granting or revoking privileges.
+
+use super::connection::{error_codes, QueryError, TableRef};
+use serde::Deserialize;
+
+/// A PostgreSQL login role or MySQL account.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DbUser {
+    pub name: String,
+    /// Host a MySQL account connects from, `%` (any host) by default. Unused on PostgreSQL.
+    #[serde(default)]
+    pub host: Option<String>,
+}
+
+impl DbUser {
+    pub fn host(&self) -> &str {
+        self.host.as_deref().unwrap_or("%")
+    }
+
+    pub fn validate(&self) -> Result<(), QueryError> {
+        if self.name.trim().is_empty() {
+            return Err(QueryError::with_code(
+                "User name is required",
+                error_codes::QUERY_ERROR,
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What privileges are granted on.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GrantTarget {
+    /// The database itself on PostgreSQL (`CONNECT`, `CREATE`, `TEMPORARY`); everything in
+    /// it on MySQL.
+    Database {
+        name: String,
+    },
+    /// A schema on PostgreSQL (`USAGE`, `CREATE`); the same as a database on MySQL.
+    Schema {
+        name: String,
+    },
+    Table {
+        table: TableRef,
+    },
+}
+
+/// Privileges to grant to or revoke from a user.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PrivilegeGrant {
+    pub user: DbUser,
+    /// Privilege names, e.g. `SELECT`, `INSERT` or `ALL PRIVILEGES`.
+    pub privileges: Vec<String>,
+    pub target: GrantTarget,
+    /// Lets the user grant the privileges to others. Ignored when revoking.
+    #[serde(default)]
+    pub with_grant_option: bool,
+}
+
+impl PrivilegeGrant {
+    /// Checks the grant and returns its privileges as they go in the statement. Privilege
+    /// names are keywords, which can't be quoted, so only letters and spaces are accepted.
+    pub fn privilege_list(&self) -> Result<String, QueryError> {
+        self.user.validate()?;
+        let error = |message: String| Err(QueryError::with_code(message, error_codes::QUERY_ERROR));
+        if self.privileges.is_empty() {
+            return error("Choose at least one privilege".to_string());
+        }
+        let mut privileges = Vec::with_capacity(self.privileges.len());
+        for privilege in &self.privileges {
+            let privilege = privilege.split_whitespace().collect::<Vec<_>>().join(" ");
+            if privilege.is_empty()
+                || !privilege
+                    .chars()
+                    .all(|c| c.is_ascii_alphabetic() || c == ' ')
+            {
+                return error(format!("'{}' is not a privilege", privilege));
+            }
+            privileges.push(privilege.to_uppercase());
+        }
+        Ok(privileges.join(", "))
+    }
+}
+
+/// Builds a GRANT, or a REVOKE when `revoke` is set, from the quoted target and grantee.
+pub fn grant_statement(
+    grant: &PrivilegeGrant,
+    privileges: &str,
+    target: &str,
+    grantee: &str,
+    revoke: bool,
+) -> String {
+    if revoke {
+        format!("REVOKE {} ON {} FROM {}", privileges, target, grantee)
+    } else if grant.with_grant_option {
+        format!(
+            "GRANT {} ON {} TO {} WITH GRANT OPTION",
+            privileges, target, grantee
+        )
+    } else {
+        format!("GRANT {} ON {} TO {}", privileges, target, grantee)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grant_statements() {
+        let mut grant = PrivilegeGrant {
+            user: DbUser {
+                name: "reporting".to_string(),
+                host: None,
+            },
+            privileges: vec!["select".to_string(), " all  privileges".to_string()],
+            target: GrantTarget::Table {
+                table: TableRef::unqualified("orders"),
+            },
+            with_grant_option: true,
+        };
+        let privileges = grant.privilege_list().unwrap();
+        assert_eq!(privileges, "SELECT, ALL PRIVILEGES");
+        assert_eq!(
+            grant_statement(&grant, &privileges, "`shop`.*", "'reporting'@'%'", false),
+            "GRANT SELECT, ALL PRIVILEGES ON `shop`.* TO 'reporting'@'%' WITH GRANT OPTION"
+        );
+        assert_eq!(
+            grant_statement(&grant, "SELECT", "TABLE \"orders\"", "\"reporting\"", true),
+            "REVOKE SELECT ON TABLE \"orders\" FROM \"reporting\""
+        );
+
+        grant.privileges = vec!["SELECT; DROP TABLE orders".to_string()];
+        assert!(grant.privilege_list().is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Diagnostic bundles to attach to bug reports: version info, settings, recent logs and
+//! errors, and connection metadata without names, hosts, users or secrets.
+//!
+//! Logs and error messages are included with the names, hosts, users and databases of
+//! saved connections replaced by placeholders. Other text in them is kept as logged, such
+//! as the host of a connection that was never saved or table names in statements.
+
+use crate::logging;
+use crate::storage::{AppSettings, AuditEntry, ConnectionPreferences, DataDir, StoredConnection};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Most recent daily log files included in a bundle.
+const MAX_LOG_FILES: usize = 3;
+
+/// Everything that goes into a bundle, gathered by the caller.
+pub struct DebugBundle {
+    pub data_dir: DataDir,
+    pub settings: AppSettings,
+    pub connections: Vec<StoredConnection>,
+    /// Recent failed statements from the audit log.
+    pub errors: Vec<AuditEntry>,
+}
+
+#[derive(Serialize)]
+struct BundleInfo<'a> {
+    app_version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    generated_at: String,
+    data_dir: &'a DataDir,
+    settings: &'a AppSettings,
+}
+
+/// Connection metadata that helps reproduce issues without identifying the server.
+#[derive(Serialize)]
+struct AnonymizedConnection<'a> {
+    /// Random id, to match log lines that mention it.
+    id: &'a str,
+    db_type: &'a str,
+    port: i32,
+    ssl_mode: &'a str,
+    custom_ca_cert: bool,
+    ssh_tunnel: bool,
+    rds_iam: bool,
+    in_group: bool,
+    use_count: i64,
+    deleted: bool,
+    preferences: &'a ConnectionPreferences,
+}
+
+impl<'a> From<&'a StoredConnection> for AnonymizedConnection<'a> {
+    fn from(conn: &'a StoredConnection) -> Self {
+        AnonymizedConnection {
+            id: &conn.id,
+            db_type: &conn.db_type,
+            port: conn.port,
+            ssl_mode: &conn.ssl_mode,
+            custom_ca_cert: conn.ssl_ca_cert.is_some(),
+            ssh_tunnel: conn.ssh.is_some(),
+            rds_iam: conn.rds_iam.is_some(),
+            in_group: conn.group_id.is_some(),
+            use_count: conn.use_count,
+            deleted: conn.deleted_at.is_some(),
+            preferences: &conn.preferences,
+        }
+    }
+}
+
+/// A failed statement, without the statement text or the database it ran in.
+#[derive(Serialize)]
+struct RecentError<'a> {
+    executed_at: &'a str,
+    command: &'a str,
+    error: String,
+}
+
+/// Replaces identifying values of saved connections in logs and error messages.
+struct Redactor {
+    /// Each value with its placeholder, longest value first so that a host isn't partly
+    /// replaced by a shorter value it contains.
+    replacements: Vec<(String, String)>,
+}
+
+impl Redactor {
+    fn new(connections: &[StoredConnection]) -> Self {
+        let mut values = Vec::new();
+        for conn in connections {
+            let id = conn.id.as_str();
+            values.push((conn.name.as_str(), "name", id));
+            values.push((conn.host.as_str(), "host", id));
+            values.push((conn.username.as_str(), "user", id));
+            values.push((conn.database.as_str(), "database", id));
+            if let Some(ssh) = &conn.ssh {
+                values.push((ssh.host.as_str(), "ssh host", id));
+                values.push((ssh.username.as_str(), "ssh user", id));
+            }
+        }
+        Self::from_values(values)
+    }
+
+    /// Builds a redactor from `(value, kind, connection id)` triples; the placeholder
+    /// names the kind and the connection id, which `connections.json` lists.
+    fn from_values<'a>(values: impl IntoIterator<Item = (&'a str, &'a str, &'a str)>) -> Self {
+        let mut replacements: Vec<(String, String)> = values
+            .into_iter()
+            .filter(|(value, _, _)| !value.trim().is_empty())
+            .map(|(value, kind, id)| (value.to_string(), format!("[{} of {}]", kind, id)))
+            .collect();
+        replacements.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        replacements.dedup_by(|a, b| a.0 == b.0);
+        Redactor { replacements }
+    }
+
+    /// Replaces whole-word occurrences of the values in `text`, so that e.g. a user named
+    /// `app` doesn't turn `application` into a placeholder.
+    fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (value, placeholder) in &self.replacements {
+            text = replace_words(&text, value, placeholder);
+        }
+        text
+    }
+}
+
+fn replace_words(text: &str, value: &str, placeholder: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find(value) {
+        let end = at + value.len();
+        let starts_word = rest[..at].chars().next_back().is_none_or(|c| !is_word(c))
+            || !value.starts_with(is_word);
+        let ends_word =
+            rest[end..].chars().next().is_none_or(|c| !is_word(c)) || !value.ends_with(is_word);
+        out.push_str(&rest[..at]);
+        if starts_word && ends_word {
+            out.push_str(placeholder);
+        } else {
+            out.push_str(&rest[at..end]);
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+impl DebugBundle {
+    /// Writes the bundle to `path` as a zip file.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut zip = ZipWriter::new(File::create(path)?);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        let info = BundleInfo {
+            app_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            data_dir: &self.data_dir,
+            settings: &self.settings,
+        };
+        let redactor = Redactor::new(&self.connections);
+        let connections: Vec<AnonymizedConnection> = self
+            .connections
+            .iter()
+            .map(AnonymizedConnection::from)
+            .collect();
+        let errors: Vec<RecentError> = self
+            .errors
+            .iter()
+            .filter_map(|entry| {
+                Some(RecentError {
+                    executed_at: &entry.executed_at,
+                    command: &entry.command,
+                    error: redactor.redact(entry.error.as_deref()?),
+                })
+            })
+            .collect();
+
+        for (name, json) in [
+            ("info.json", serde_json::to_vec_pretty(&info)?),
+            ("connections.json", serde_json::to_vec_pretty(&connections)?),
+            ("errors.json", serde_json::to_vec_pretty(&errors)?),
+        ] {
+            zip.start_file(name, options)?;
+            zip.write_all(&json)?;
+        }
+
+        for log_file in recent_log_files(&self.data_dir.path.join(logging::LOG_DIR)) {
+            let Some(name) = log_file.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let log = fs::read(&log_file)?;
+            zip.start_file(format!("logs/{}", name), options)?;
+            zip.write_all(redactor.redact(&String::from_utf8_lossy(&log)).as_bytes())?;
+        }
+
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+/// Returns the newest `MAX_LOG_FILES` log files. Daily files are named by date, so the
+/// newest sort last.
+fn recent_log_files(log_dir: &Path) -> Vec<std::path::PathBuf> {
+    let mut files: Vec<_> = fs::read_dir(log_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    let skip = files.len().saturating_sub(MAX_LOG_FILES);
+    files.split_off(skip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_replaces_whole_values() {
+        let redactor = Redactor::from_values([
+            ("Production", "name", "c1"),
+            ("db.example.com", "host", "c1"),
+            ("app", "user", "c1"),
+            ("db", "database", "c1"),
+        ]);
+        assert_eq!(
+            redactor.redact(
+                "Connected to Production at db.example.com:5432 as app (application_name=x)"
+            ),
+            "Connected to [name of c1] at [host of c1]:5432 as [user of c1] \
+             (application_name=x)"
+        );
+        assert_eq!(
+            redactor.redact("database db, dbx and db_old"),
+            "database [database of c1], dbx and db_old"
+        );
+    }
+
+    #[test]
+    fn test_redact_skips_empty_values() {
+        let redactor = Redactor::from_values([("", "user", "c1"), ("  ", "database", "c1")]);
+        assert_eq!(redactor.redact("nothing to hide"), "nothing to hide");
+    }
+}
@@ -0,0 +1,202 @@
+//! Manifests written next to exports: what was exported, from which server, with which
+//! options, and a SHA-256 checksum of each file, so a dump can be verified before it is
+//! restored and audited later.
+
+use crate::commands::ExportOptions;
+use crate::db::{DatabaseConnection, TableRef};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Identifies a manifest file.
+const MANIFEST_FORMAT: &str = "bloatsql-export-manifest";
+
+/// Version of the manifest format.
+const MANIFEST_VERSION: u32 = 1;
+
+/// Suffix appended to the dump's file name to name its manifest.
+const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub format: String,
+    pub version: u32,
+    /// Version of the app that wrote the export.
+    pub app_version: String,
+    /// RFC 3339 timestamp.
+    pub created_at: String,
+    /// As reported by `SELECT version()`; None if the server didn't say.
+    pub server_version: Option<String>,
+    pub database: String,
+    pub options: ExportOptions,
+    pub files: Vec<ManifestFile>,
+    pub tables: Vec<ManifestTable>,
+}
+
+/// A file of the export, named relative to the manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub name: String,
+    pub bytes: u64,
+    /// Lowercase hex.
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestTable {
+    #[serde(default)]
+    pub schema: Option<String>,
+    pub name: String,
+    /// Rows counted once the export finished, so a table written to meanwhile may differ
+    /// from the dump. None if counting failed.
+    pub row_count: Option<u64>,
+}
+
+/// Result of checking the files of a manifest against their checksums.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestVerification {
+    pub verified: bool,
+    /// Files that are missing or whose size or checksum differs.
+    pub mismatched: Vec<String>,
+}
+
+/// Returns where the manifest of the export at `export_path` goes.
+pub fn manifest_path(export_path: &Path) -> PathBuf {
+    let mut name = export_path.file_name().unwrap_or_default().to_os_string();
+    name.push(MANIFEST_SUFFIX);
+    export_path.with_file_name(name)
+}
+
+/// Size and SHA-256 checksum of the file at `path`.
+pub fn describe_file(path: &Path) -> io::Result<ManifestFile> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let bytes = io::copy(&mut file, &mut hasher)?;
+    Ok(ManifestFile {
+        name: path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+        bytes,
+        sha256: hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+    })
+}
+
+/// Gathers the manifest of the export just written to `export_path` from `conn`, and
+/// writes it next to the export. Returns the manifest's path.
+pub async fn write_manifest(
+    conn: &dyn DatabaseConnection,
+    options: &ExportOptions,
+    export_path: &Path,
+) -> Result<PathBuf, String> {
+    let server_version = conn
+        .execute_query("SELECT version() AS version")
+        .await
+        .ok()
+        .and_then(|result| result.rows.into_iter().next())
+        .and_then(|row| row.get("version")?.as_str().map(str::to_string));
+    let database = conn.get_current_database().await.map_err(|e| e.message)?;
+
+    // An empty selection exports every table of the current schema
+    let tables = if options.selected_tables.is_empty() {
+        conn.list_tables()
+            .await
+            .map_err(|e| e.message)?
+            .into_iter()
+            .map(TableRef::unqualified)
+            .collect()
+    } else {
+        options.selected_tables.clone()
+    };
+    let mut manifest_tables = Vec::with_capacity(tables.len());
+    for table in tables {
+        let query = format!(
+            "SELECT COUNT(*) AS row_count FROM {}",
+            conn.dialect().qualified_name(&table)
+        );
+        let row_count = conn.execute_query(&query).await.ok().and_then(|result| {
+            let row = result.rows.into_iter().next()?;
+            match row.get("row_count")? {
+                serde_json::Value::Number(count) => count.as_u64(),
+                serde_json::Value::String(count) => count.parse().ok(),
+                _ => None,
+            }
+        });
+        manifest_tables.push(ManifestTable {
+            schema: table.schema,
+            name: table.name,
+            row_count,
+        });
+    }
+
+    let file = describe_file(export_path)
+        .map_err(|e| format!("Failed to read {}: {}", export_path.display(), e))?;
+    let manifest = ExportManifest {
+        format: MANIFEST_FORMAT.to_string(),
+        version: MANIFEST_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        server_version,
+        database,
+        options: options.clone(),
+        files: vec![file],
+        tables: manifest_tables,
+    };
+
+    let path = manifest_path(export_path);
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    tokio::fs::write(&path, json)
+        .await
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+    Ok(path)
+}
+
+/// Checks the files listed in the manifest at `path` against their sizes and checksums.
+pub fn verify_manifest(path: &Path) -> Result<ManifestVerification, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let manifest: ExportManifest =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid manifest: {}", e))?;
+    if manifest.format != MANIFEST_FORMAT {
+        return Err("Not a BloatSQL export manifest".to_string());
+    }
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mismatched: Vec<String> = manifest
+        .files
+        .iter()
+        .filter(|expected| describe_file(&dir.join(&expected.name)).ok().as_ref() != Some(expected))
+        .map(|file| file.name.clone())
+        .collect();
+    Ok(ManifestVerification {
+        verified: mismatched.is_empty(),
+        mismatched,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_file() {
+        let path = std::env::temp_dir().join(format!("bloatsql-test-{}.sql", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "abc").unwrap();
+        let file = describe_file(&path).unwrap();
+        assert_eq!(file.bytes, 3);
+        assert_eq!(
+            file.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(manifest_path(&path)
+            .to_string_lossy()
+            .ends_with(".sql.manifest.json"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+//! Compact encodings for large command responses.
+//!
+//! Query results are normally sent as JSON with one object per row, repeating every column
+//! name. The MessagePack format sends one array per column instead and is returned as a raw
+//! binary payload, avoiding JSON serialization on both sides of the IPC bridge.
+
+use crate::commands::QueryResult;
+use crate::db::ColumnMetadata;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Encoding requested by the frontend for query results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultFormat {
+    /// `QueryResult` as JSON, with one object per row.
+    #[default]
+    Json,
+    /// A MessagePack map with the `QueryResult` fields, where `rows` is replaced by
+    /// `column_data`: one array of values per column, in `columns` order.
+    Msgpack,
+}
+
+/// `QueryResult` in the `ResultFormat::Msgpack` layout, with its fields in the same order.
+/// MessagePack has no 128-bit integers, so times are sent as u64.
+#[derive(Serialize)]
+struct ColumnarResult<'a> {
+    columns: &'a [String],
+    column_data: Vec<Vec<Value>>,
+    row_count: usize,
+    execution_time: u64,
+    truncated: bool,
+    affected_rows: Option<u64>,
+    timing: ColumnarTiming,
+    column_metadata: &'a [ColumnMetadata],
+    cached: bool,
+}
+
+/// `QueryTiming` with u64 milliseconds.
+#[derive(Serialize)]
+struct ColumnarTiming {
+    acquire: u64,
+    execution: u64,
+    fetch: u64,
+    conversion: u64,
+}
+
+fn millis(ms: u128) -> u64 {
+    ms.min(u64::MAX as u128) as u64
+}
+
+/// Encodes a query result in the columnar MessagePack layout described by
+/// `ResultFormat::Msgpack`.
+pub fn encode_result_msgpack(result: QueryResult) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    let mut column_data: Vec<Vec<Value>> = result
+        .columns
+        .iter()
+        .map(|_| Vec::with_capacity(result.rows.len()))
+        .collect();
+    for row in result.rows {
+        let mut row = match row {
+            Value::Object(map) => map,
+            _ => continue,
+        };
+        for (values, column) in column_data.iter_mut().zip(&result.columns) {
+            values.push(row.remove(column).unwrap_or(Value::Null));
+        }
+    }
+
+    rmp_serde::to_vec_named(&ColumnarResult {
+        columns: &result.columns,
+        column_data,
+        row_count: result.row_count,
+        execution_time: millis(result.execution_time),
+        truncated: result.truncated,
+        affected_rows: result.affected_rows,
+        timing: ColumnarTiming {
+            acquire: millis(result.timing.acquire),
+            execution: millis(result.timing.execution),
+            fetch: millis(result.timing.fetch),
+            conversion: millis(result.timing.conversion),
+        },
+        column_metadata: &result.column_metadata,
+        cached: result.cached,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::QueryTiming;
+    use serde_json::json;
+
+    fn encode(value: Value) -> Vec<u8> {
+        rmp_serde::to_vec(&value).unwrap()
+    }
+
+    #[test]
+    fn test_msgpack_scalars() {
+        assert_eq!(encode(json!(null)), vec![0xc0]);
+        assert_eq!(encode(json!(true)), vec![0xc3]);
+        assert_eq!(encode(json!(5)), vec![0x05]);
+        assert_eq!(encode(json!(300)), vec![0xcd, 0x01, 0x2c]);
+        assert_eq!(encode(json!(-1)), vec![0xff]);
+        assert_eq!(encode(json!(-200)), vec![0xd1, 0xff, 0x38]);
+        assert_eq!(encode(json!(1.5)), vec![0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(encode(json!("ab")), vec![0xa2, b'a', b'b']);
+        assert_eq!(encode(json!([1, "a"])), vec![0x92, 0x01, 0xa1, b'a']);
+    }
+
+    #[test]
+    fn test_result_is_encoded_by_column() {
+        let result = QueryResult {
+            columns: vec!["id".to_string(), "name".to_string()],
+            rows: vec![
+                json!({"id": 1, "name": "a"}),
+                json!({"id": 2, "name": null}),
+            ],
+            row_count: 2,
+            execution_time: 3,
+            truncated: false,
+            affected_rows: None,
+            timing: QueryTiming {
+                acquire: 1,
+                execution: 2,
+                fetch: 0,
+                conversion: 1,
+            },
+            column_metadata: Vec::new(),
+            cached: false,
+        };
+
+        let mut expected = vec![0x89];
+        expected.extend_from_slice(b"\xa7columns\x92\xa2id\xa4name");
+        expected.extend_from_slice(b"\xabcolumn_data\x92\x92\x01\x02\x92\xa1a\xc0");
+        expected.extend_from_slice(b"\xa9row_count\x02");
+        expected.extend_from_slice(b"\xaeexecution_time\x03");
+        expected.extend_from_slice(b"\xa9truncated\xc2");
+        expected.extend_from_slice(b"\xadaffected_rows\xc0");
+        expected.extend_from_slice(b"\xa6timing\x84\xa7acquire\x01\xa9execution\x02");
+        expected.extend_from_slice(b"\xa5fetch\x00\xaaconversion\x01");
+        expected.extend_from_slice(b"\xafcolumn_metadata\x90");
+        expected.extend_from_slice(b"\xa6cached\xc2");
+        assert_eq!(encode_result_msgpack(result).unwrap(), expected);
+    }
+}
@@ -0,0 +1,78 @@
+//! Application logs, written to daily-rotated files in the data directory so issues can be
+//! diagnosed from release builds, and to stdout in debug builds.
+
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::level_filters::LevelFilter;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, Registry};
+
+/// Directory under the data directory that holds the log files.
+pub const LOG_DIR: &str = "logs";
+
+/// Number of daily log files kept before the oldest is deleted.
+const MAX_LOG_FILES: usize = 14;
+
+/// Level used when none is configured.
+const DEFAULT_LEVEL: LevelFilter = if cfg!(debug_assertions) {
+    LevelFilter::DEBUG
+} else {
+    LevelFilter::INFO
+};
+
+static LEVEL_HANDLE: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+/// Installs the global subscriber, logging at `level` (see `parse_level`) or the default.
+pub fn init(log_dir: &Path, level: Option<&str>) {
+    let level = level
+        .and_then(|level| parse_level(level).ok())
+        .unwrap_or(DEFAULT_LEVEL);
+    let (filter, handle) = reload::Layer::new(level);
+
+    let file = match RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("bloatsql")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir)
+    {
+        Ok(appender) => Some(fmt::layer().with_ansi(false).with_writer(appender)),
+        Err(e) => {
+            eprintln!("Failed to open log directory {}: {}", log_dir.display(), e);
+            None
+        }
+    };
+    let stdout = cfg!(debug_assertions).then(|| fmt::layer().with_target(false));
+
+    match tracing_subscriber::registry()
+        .with(filter)
+        .with(file)
+        .with(stdout)
+        .try_init()
+    {
+        Ok(()) => {
+            let _ = LEVEL_HANDLE.set(handle);
+        }
+        Err(e) => eprintln!("Failed to initialize logging: {}", e),
+    }
+}
+
+/// Changes the level of the running subscriber.
+pub fn set_level(level: &str) -> Result<(), String> {
+    let level = parse_level(level)?;
+    LEVEL_HANDLE
+        .get()
+        .ok_or_else(|| "Logging is not initialized".to_string())?
+        .reload(level)
+        .map_err(|e| e.to_string())
+}
+
+/// Parses "off", "error", "warn", "info", "debug" or "trace" (case-insensitive).
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level.trim().parse().map_err(|_| {
+        format!(
+            "Unknown log level '{}'. Supported levels: off, error, warn, info, debug, trace",
+            level
+        )
+    })
+}
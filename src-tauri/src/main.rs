@@ -1,61 +1,278 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod cli;
 mod commands;
 mod db;
+mod debug_bundle;
+mod deep_link;
+mod export_manifest;
+mod ipc;
+mod logging;
+mod notify;
+mod redact;
+mod results;
 mod storage;
 
 use std::sync::Arc;
-use storage::ConnectionsStore;
+use storage::{
+    AuditLog, ConnectionSync, ConnectionsStore, ExportProfileStore, QueryDocumentStore,
+    QueryLibrary, RecoveryStore, SchemaCache, SettingsStore, SlowQueryLog,
+};
 use tauri::Manager;
 
 fn main() {
-    // Initialize tracing for debug builds
-    #[cfg(debug_assertions)]
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::DEBUG)
-        .with_target(false)
-        .init();
+    if let Some(code) = cli::run_subcommand() {
+        std::process::exit(code);
+    }
 
     tauri::Builder::default()
+        // Must come first so links opened while running reach the existing instance
+        .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+            cli::handle(app, argv.into_iter().skip(1).collect(), cwd.into());
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_os::init())
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
-            let app_dir = app.path().app_data_dir().unwrap_or_default();
+            let data_dir =
+                storage::data_dir::resolve(app.path().app_data_dir().unwrap_or_default());
+            let app_dir = data_dir.path.clone();
             if !app_dir.exists() {
                 std::fs::create_dir_all(&app_dir).ok();
             }
 
             let db_path = app_dir.join("connections.db");
-            let store =
-                Arc::new(ConnectionsStore::new(db_path).expect("Failed to initialize storage"));
-            let active_connection: Arc<
-                tokio::sync::Mutex<Option<Arc<dyn crate::db::DatabaseConnection>>>,
-            > = Arc::new(tokio::sync::Mutex::new(None));
+            let settings = Arc::new(
+                SettingsStore::new(db_path.clone()).expect("Failed to initialize settings"),
+            );
+            let app_settings = settings.get().unwrap_or_default();
+            logging::init(
+                &app_dir.join(logging::LOG_DIR),
+                app_settings.log_level.as_deref(),
+            );
+
+            let store = Arc::new(
+                ConnectionsStore::new(db_path.clone(), data_dir.source)
+                    .expect("Failed to initialize storage"),
+            );
+            let audit_log =
+                Arc::new(AuditLog::new(db_path.clone()).expect("Failed to initialize audit log"));
+            let export_profiles = Arc::new(
+                ExportProfileStore::new(db_path.clone())
+                    .expect("Failed to initialize export profiles"),
+            );
+            let query_library = Arc::new(
+                QueryLibrary::new(db_path.clone()).expect("Failed to initialize query library"),
+            );
+            let query_documents = Arc::new(
+                QueryDocumentStore::new(db_path.clone())
+                    .expect("Failed to initialize query documents"),
+            );
+            let recovery = Arc::new(
+                RecoveryStore::new(db_path.clone()).expect("Failed to initialize recovery store"),
+            );
+            let slow_log = Arc::new(
+                SlowQueryLog::new(db_path.clone()).expect("Failed to initialize slow query log"),
+            );
+            slow_log.set_threshold(app_settings.slow_query_threshold_ms);
+            let schema_cache =
+                Arc::new(SchemaCache::new(db_path).expect("Failed to initialize schema cache"));
+            let sync_path = app_settings
+                .connection_sync_path
+                .map(std::path::PathBuf::from);
+            let connection_sync = Arc::new(ConnectionSync::new(store.clone(), sync_path));
+            commands::spawn_connection_sync_watcher(app.handle().clone(), connection_sync.clone());
+            commands::spawn_resume_watcher(app.handle().clone());
 
+            app.manage(data_dir);
             app.manage(store);
-            app.manage(active_connection);
+            app.manage(audit_log);
+            app.manage(slow_log);
+            app.manage(export_profiles);
+            app.manage(query_library);
+            app.manage(query_documents);
+            app.manage(recovery);
+            app.manage(schema_cache);
+            app.manage(settings);
+            app.manage(connection_sync);
+            app.manage(commands::WindowSessions::default());
+            let result_store = results::ResultStore::default();
+            if let Some(mb) = app_settings.result_memory_budget_mb {
+                result_store.set_memory_budget((mb as usize).saturating_mul(1024 * 1024));
+            }
+            app.manage(result_store);
+            redact::set_hide_data_values(app_settings.hide_data_in_logs);
+            app.manage(results::CursorStore::default());
+            app.manage(results::QueryCache::default());
+            app.manage(commands::SqlFileWatches::default());
+            app.manage(commands::ChangeTails::default());
+            app.manage(cli::PendingLaunch::default());
+            app.manage(notify::Notifier::new(
+                app_settings.notification_threshold_secs,
+            ));
+            deep_link::init(app.handle());
+            cli::handle(
+                app.handle(),
+                std::env::args().skip(1).collect(),
+                std::env::current_dir().unwrap_or_default(),
+            );
 
             Ok(())
         })
+        .on_window_event(|window, event| {
+            let app = window.app_handle().clone();
+            let label = window.label().to_string();
+            match event {
+                tauri::WindowEvent::Destroyed => {
+                    tauri::async_runtime::spawn(async move {
+                        commands::close_window(&app, &label).await;
+                    });
+                }
+                tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) => {
+                    let paths = paths.clone();
+                    tauri::async_runtime::spawn(async move {
+                        commands::handle_dropped_files(&app, &label, paths).await;
+                    });
+                }
+                _ => {}
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             commands::close_splashscreen,
+            commands::take_launch_request,
+            commands::save_recovery_snapshot,
+            commands::recover_session,
+            commands::handle_dropped_file,
+            commands::parse_connection_url,
             commands::save_connection,
             commands::get_connections,
+            commands::search_connections,
+            commands::get_connection_tags,
             commands::delete_connection,
+            commands::get_deleted_connections,
+            commands::restore_connection,
+            commands::purge_connection,
+            commands::update_connection_preferences,
+            commands::trust_ssh_host_key,
+            commands::get_store_lock_state,
+            commands::unlock_store,
+            commands::lock_store,
+            commands::set_master_password,
+            commands::save_group,
+            commands::get_groups,
+            commands::delete_group,
+            commands::reorder_groups,
+            commands::move_connection_to_group,
             commands::test_connection,
             commands::connect_to_database,
             commands::execute_query,
+            commands::execute_query_encoded,
+            commands::execute_query_deferred,
+            commands::get_result_rows,
+            commands::release_result,
+            commands::open_cursor,
+            commands::fetch_cursor_rows,
+            commands::close_cursor,
+            commands::clear_query_cache,
+            commands::execute_query_streamed,
+            commands::watch_sql_file,
+            commands::unwatch_sql_file,
             commands::list_tables,
             commands::list_databases,
             commands::change_database,
             commands::get_current_database,
             commands::get_table_columns,
             commands::get_table_relationships,
+            commands::check_table_privileges,
+            commands::get_query_statistics,
+            commands::analyze_query_indexes,
+            commands::get_bloat_report,
+            commands::get_deadlocks,
+            commands::get_active_transactions,
+            commands::get_health_snapshot,
             commands::disconnect_from_database,
+            commands::open_window,
             commands::export_database,
+            commands::verify_export_manifest,
+            commands::save_export_profile,
+            commands::get_export_profiles,
+            commands::delete_export_profile,
             commands::update_cell,
+            commands::paste_rows,
+            commands::call_routine,
+            commands::get_routine_definition,
+            commands::save_routine_definition,
+            commands::get_view_definition,
+            commands::alter_view,
+            commands::create_trigger,
+            commands::drop_trigger,
+            commands::reset_identity,
+            commands::get_roles,
+            commands::set_role,
+            commands::get_session_variables,
+            commands::set_session_variable,
+            commands::aggregate_table,
+            commands::get_text_search_configs,
+            commands::build_text_search,
+            commands::text_search,
+            commands::tail_changes,
+            commands::untail_changes,
+            commands::get_rls_policies,
+            commands::scan_keys,
+            commands::get_key,
+            commands::set_key,
+            commands::delete_key,
+            commands::get_column_stats,
+            commands::sample_rows,
+            commands::get_time_travel_support,
+            commands::browse_table,
+            commands::get_table_options,
+            commands::create_database,
+            commands::drop_database,
+            commands::create_user,
+            commands::drop_user,
+            commands::change_db_user_password,
+            commands::grant_privileges,
+            commands::revoke_privileges,
+            commands::diagnose_connection,
+            commands::get_driver_info,
+            commands::create_scratch_schema,
+            commands::drop_scratch_schema,
+            commands::materialize_query,
+            commands::save_query,
+            commands::get_saved_queries,
+            commands::delete_saved_query,
+            commands::export_query_library,
+            commands::import_query_library,
+            commands::autosave_query_document,
+            commands::save_query_document,
+            commands::get_query_documents,
+            commands::get_closed_query_documents,
+            commands::close_query_document,
+            commands::delete_query_document,
+            commands::get_query_document_revisions,
+            commands::restore_query_document_revision,
             commands::write_text_file,
             commands::ping_connection,
+            commands::get_connection_health,
+            commands::get_audit_log,
+            commands::export_audit_log,
+            commands::get_slow_queries,
+            commands::get_data_dir,
+            commands::set_data_dir_override,
+            commands::generate_debug_bundle,
+            commands::get_settings,
+            commands::set_connection_sync_path,
+            commands::set_result_memory_budget,
+            commands::set_hide_data_in_logs,
+            commands::set_log_level,
+            commands::set_slow_query_threshold,
+            commands::set_notification_threshold,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
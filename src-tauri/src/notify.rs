@@ -0,0 +1,169 @@
+//! Native notifications when a long query, export or import finishes while the window it
+//! runs in is in the background, so users can switch away during multi-minute operations.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{Manager, WebviewWindow};
+use tauri_plugin_notification::NotificationExt;
+use tracing::warn;
+
+/// Operations shorter than this don't notify unless configured otherwise.
+pub const DEFAULT_THRESHOLD_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Query,
+    Export,
+    Import,
+}
+
+impl Operation {
+    fn name(self) -> &'static str {
+        match self {
+            Operation::Query => "Query",
+            Operation::Export => "Export",
+            Operation::Import => "Import",
+        }
+    }
+}
+
+/// Decides which finished operations notify.
+pub struct Notifier {
+    /// 0 disables notifications.
+    threshold_secs: AtomicU64,
+}
+
+impl Notifier {
+    pub fn new(threshold_secs: Option<u64>) -> Self {
+        Notifier {
+            threshold_secs: AtomicU64::new(threshold_secs.unwrap_or(DEFAULT_THRESHOLD_SECS)),
+        }
+    }
+
+    /// Sets how long operations must run to notify; `None` restores the default and 0
+    /// turns notifications off.
+    pub fn set_threshold(&self, threshold_secs: Option<u64>) {
+        self.threshold_secs.store(
+            threshold_secs.unwrap_or(DEFAULT_THRESHOLD_SECS),
+            Ordering::Relaxed,
+        );
+    }
+
+    fn should_notify(&self, elapsed: Duration) -> bool {
+        let threshold = self.threshold_secs.load(Ordering::Relaxed);
+        threshold > 0 && elapsed.as_secs() >= threshold
+    }
+}
+
+/// Notifies that `operation` finished after `elapsed` with `outcome` (the number of rows,
+/// if known, or the error), if it ran long enough and `window` isn't in the foreground.
+pub fn finished(
+    window: &WebviewWindow,
+    operation: Operation,
+    elapsed: Duration,
+    outcome: Result<Option<u64>, &str>,
+) {
+    if !window.state::<Notifier>().should_notify(elapsed) {
+        return;
+    }
+    let in_foreground = window.is_focused().unwrap_or(false)
+        && !window.is_minimized().unwrap_or(false)
+        && window.is_visible().unwrap_or(true);
+    if in_foreground {
+        return;
+    }
+
+    let (title, body) = message(operation, elapsed, outcome);
+    if let Err(e) = window
+        .notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+    {
+        warn!("Failed to show notification: {}", e);
+    }
+}
+
+fn message(
+    operation: Operation,
+    elapsed: Duration,
+    outcome: Result<Option<u64>, &str>,
+) -> (String, String) {
+    let duration = format_duration(elapsed);
+    match outcome {
+        Ok(rows) => {
+            let title = format!("{} finished", operation.name());
+            let body = match rows {
+                Some(1) => format!("1 row in {}", duration),
+                Some(rows) => format!("{} rows in {}", group_thousands(rows), duration),
+                None => format!("Took {}", duration),
+            };
+            (title, body)
+        }
+        Err(error) => (
+            format!("{} failed", operation.name()),
+            format!("After {}: {}", duration, error),
+        ),
+    }
+}
+
+/// "45s", "2m 13s", "1h 5m".
+fn format_duration(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, _) => format!("{}h {}m", h, m),
+    }
+}
+
+fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        assert_eq!(
+            message(
+                Operation::Query,
+                Duration::from_secs(133),
+                Ok(Some(1_234_567))
+            ),
+            (
+                "Query finished".to_string(),
+                "1,234,567 rows in 2m 13s".to_string()
+            )
+        );
+        assert_eq!(
+            message(
+                Operation::Export,
+                Duration::from_secs(3900),
+                Err("disk full")
+            )
+            .1,
+            "After 1h 5m: disk full"
+        );
+        assert_eq!(
+            message(Operation::Import, Duration::from_secs(45), Ok(None)).1,
+            "Took 45s"
+        );
+
+        let notifier = Notifier::new(Some(0));
+        assert!(!notifier.should_notify(Duration::from_secs(600)));
+        notifier.set_threshold(None);
+        assert!(notifier.should_notify(Duration::from_secs(DEFAULT_THRESHOLD_SECS)));
+    }
+}
@@ -0,0 +1,74 @@
+//! Keeps secrets, and optionally table data, out of logs and error messages.
+//!
+//! Connection secrets are always scrubbed from errors returned while connecting. Data values
+//! (edited cells, key values, server error details quoting rows) are logged unless hidden
+//! with the `hide_data_in_logs` setting, for shared or demo machines.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Text that replaces redacted values.
+pub const REDACTED: &str = "<redacted>";
+
+static HIDE_DATA_VALUES: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether data values are left out of logs.
+pub fn set_hide_data_values(hide: bool) {
+    HIDE_DATA_VALUES.store(hide, Ordering::Relaxed);
+}
+
+/// Wraps a data value so it is only logged while data logging is enabled.
+pub fn data<T: ?Sized>(value: &T) -> DataValue<'_, T> {
+    DataValue(value)
+}
+
+/// A data value that formats as `REDACTED` while data logging is disabled.
+pub struct DataValue<'a, T: ?Sized>(&'a T);
+
+impl<T: fmt::Debug + ?Sized> fmt::Debug for DataValue<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if HIDE_DATA_VALUES.load(Ordering::Relaxed) {
+            f.write_str(REDACTED)
+        } else {
+            self.0.fmt(f)
+        }
+    }
+}
+
+impl<T: fmt::Display + ?Sized> fmt::Display for DataValue<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if HIDE_DATA_VALUES.load(Ordering::Relaxed) {
+            f.write_str(REDACTED)
+        } else {
+            self.0.fmt(f)
+        }
+    }
+}
+
+/// Replaces every occurrence of `secret` in `text`. Empty secrets leave the text unchanged.
+pub fn redact_secret(text: &str, secret: &str) -> String {
+    if secret.is_empty() {
+        text.to_string()
+    } else {
+        text.replace(secret, REDACTED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_and_hidden_data_are_redacted() {
+        assert_eq!(
+            redact_secret("password=hunter2 host=db", "hunter2"),
+            "password=<redacted> host=db"
+        );
+        assert_eq!(redact_secret("no secret", ""), "no secret");
+
+        set_hide_data_values(true);
+        assert_eq!(format!("{:?}", data(&Some("42"))), REDACTED);
+        set_hide_data_values(false);
+        assert_eq!(format!("{:?}", data(&Some("42"))), "Some(\"42\")");
+    }
+}
@@ -0,0 +1,475 @@
+//! Backend-side storage of query results: stored results the frontend fetches a window at a
+//! time, open server-side cursors and the cache of repeated read-only queries.
+
+use crate::db::{statement, QueryResult, ResultCursor};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Number of results kept; the oldest is dropped when another is stored.
+const MAX_STORED_RESULTS: usize = 8;
+
+/// Memory that stored results may use before new results are spilled to disk.
+pub const DEFAULT_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+
+/// Rough memory used by a JSON value, for accounting rows against the memory budget.
+fn approx_size(value: &Value) -> usize {
+    std::mem::size_of::<Value>()
+        + match value {
+            Value::String(s) => s.len(),
+            Value::Array(items) => items.iter().map(approx_size).sum(),
+            Value::Object(map) => map.iter().map(|(k, v)| k.len() + approx_size(v)).sum(),
+            _ => 0,
+        }
+}
+
+/// Rows written to a temporary file as JSON lines, read back by seeking to a row's offset.
+struct SpilledRows {
+    path: PathBuf,
+    /// `None` only while being dropped, so the file is closed before it is removed.
+    file: Option<File>,
+    /// Start of each row in the file, followed by the end of the last row.
+    offsets: Vec<u64>,
+}
+
+impl SpilledRows {
+    fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn read(&self, offset: usize, count: usize) -> io::Result<Vec<Value>> {
+        let end = offset.saturating_add(count).min(self.len());
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+
+        let (start_pos, end_pos) = (self.offsets[offset], self.offsets[end]);
+        let mut buf = vec![0; (end_pos - start_pos) as usize];
+        let mut file = self.file.as_ref().expect("file is only taken on drop");
+        file.seek(SeekFrom::Start(start_pos))?;
+        file.read_exact(&mut buf)?;
+
+        buf.split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).map_err(io::Error::from))
+            .collect()
+    }
+}
+
+impl Drop for SpilledRows {
+    fn drop(&mut self) {
+        drop(self.file.take());
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!(
+                "Failed to remove spilled result {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// A spill file being written.
+struct SpillWriter {
+    rows: SpilledRows,
+    writer: BufWriter<File>,
+}
+
+impl SpillWriter {
+    fn create() -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("bloatsql-result-{}.jsonl", Uuid::new_v4()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(SpillWriter {
+            writer: BufWriter::new(file.try_clone()?),
+            rows: SpilledRows {
+                path,
+                file: Some(file),
+                offsets: vec![0],
+            },
+        })
+    }
+
+    fn write(&mut self, row: &Value) -> io::Result<()> {
+        let mut line = serde_json::to_vec(row)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        let end = self.rows.offsets.last().copied().unwrap_or(0) + line.len() as u64;
+        self.rows.offsets.push(end);
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<SpilledRows> {
+        self.writer.flush()?;
+        Ok(self.rows)
+    }
+}
+
+/// Collects the rows of a result for `ResultStore::insert`, moving them to a temporary file
+/// once they exceed the memory budget left when the builder was created.
+pub struct ResultBuilder {
+    rows: Vec<Value>,
+    size: usize,
+    budget: usize,
+    spill: Option<SpillWriter>,
+}
+
+impl ResultBuilder {
+    pub fn push(&mut self, row: Value) -> io::Result<()> {
+        if let Some(spill) = &mut self.spill {
+            return spill.write(&row);
+        }
+
+        self.size += approx_size(&row);
+        self.rows.push(row);
+        if self.size > self.budget {
+            let mut spill = SpillWriter::create()?;
+            for row in self.rows.drain(..) {
+                spill.write(&row)?;
+            }
+            debug!("Result exceeded the memory budget; spilled to disk");
+            self.size = 0;
+            self.spill = Some(spill);
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.spill {
+            Some(spill) => spill.rows.len(),
+            None => self.rows.len(),
+        }
+    }
+}
+
+enum StoredRows {
+    Memory(Vec<Value>),
+    Disk(SpilledRows),
+}
+
+struct StoredResult {
+    id: String,
+    rows: StoredRows,
+    /// Approximate memory used by in-memory rows.
+    size: usize,
+}
+
+/// Holds the rows of recent query results by result id.
+///
+/// Results are kept in memory up to a total memory budget; rows beyond it are spilled to a
+/// temporary file and read back a window at a time.
+pub struct ResultStore {
+    results: Mutex<VecDeque<StoredResult>>,
+    memory_budget: AtomicUsize,
+}
+
+impl Default for ResultStore {
+    fn default() -> Self {
+        ResultStore {
+            results: Mutex::new(VecDeque::new()),
+            memory_budget: AtomicUsize::new(DEFAULT_MEMORY_BUDGET),
+        }
+    }
+}
+
+impl ResultStore {
+    /// Sets the memory stored results may use; applies to results stored afterwards.
+    pub fn set_memory_budget(&self, bytes: usize) {
+        self.memory_budget.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Starts collecting the rows of a new result.
+    pub fn builder(&self) -> ResultBuilder {
+        let used: usize = self.results.lock().unwrap().iter().map(|r| r.size).sum();
+        ResultBuilder {
+            rows: Vec::new(),
+            size: 0,
+            budget: self
+                .memory_budget
+                .load(Ordering::Relaxed)
+                .saturating_sub(used),
+            spill: None,
+        }
+    }
+
+    /// Stores the collected rows and returns the id to fetch them with.
+    pub fn insert(&self, builder: ResultBuilder) -> io::Result<String> {
+        let (rows, size) = match builder.spill {
+            Some(spill) => (StoredRows::Disk(spill.finish()?), 0),
+            None => (StoredRows::Memory(builder.rows), builder.size),
+        };
+
+        let id = Uuid::new_v4().to_string();
+        let mut results = self.results.lock().unwrap();
+        if results.len() >= MAX_STORED_RESULTS {
+            results.pop_front();
+        }
+        results.push_back(StoredResult {
+            id: id.clone(),
+            rows,
+            size,
+        });
+        Ok(id)
+    }
+
+    /// Returns up to `count` rows starting at `offset`, or `None` if the result is unknown
+    /// (never stored, released or evicted).
+    pub fn get_rows(
+        &self,
+        id: &str,
+        offset: usize,
+        count: usize,
+    ) -> io::Result<Option<Vec<Value>>> {
+        let results = self.results.lock().unwrap();
+        let result = match results.iter().find(|r| r.id == id) {
+            Some(result) => result,
+            None => return Ok(None),
+        };
+        match &result.rows {
+            StoredRows::Memory(rows) => Ok(Some(
+                rows.iter().skip(offset).take(count).cloned().collect(),
+            )),
+            StoredRows::Disk(rows) => rows.read(offset, count).map(Some),
+        }
+    }
+
+    /// Drops a stored result. Returns false if it was not stored.
+    pub fn remove(&self, id: &str) -> bool {
+        let mut results = self.results.lock().unwrap();
+        let len = results.len();
+        results.retain(|r| r.id != id);
+        results.len() != len
+    }
+
+    pub fn clear(&self) {
+        self.results.lock().unwrap().clear();
+    }
+}
+
+/// Number of cursors kept open; each holds a database connection.
+const MAX_OPEN_CURSORS: usize = 4;
+
+/// Holds open server-side cursors by cursor id.
+#[derive(Default)]
+pub struct CursorStore {
+    cursors: Mutex<VecDeque<(String, Arc<dyn ResultCursor>)>>,
+}
+
+impl CursorStore {
+    /// Stores a cursor and returns its id, along with the oldest cursor if it had to be
+    /// dropped to stay under `MAX_OPEN_CURSORS`. The caller should close that cursor.
+    pub fn insert(&self, cursor: Arc<dyn ResultCursor>) -> (String, Option<Arc<dyn ResultCursor>>) {
+        let id = Uuid::new_v4().to_string();
+        let mut cursors = self.cursors.lock().unwrap();
+        let evicted = if cursors.len() >= MAX_OPEN_CURSORS {
+            cursors.pop_front().map(|(_, cursor)| cursor)
+        } else {
+            None
+        };
+        cursors.push_back((id.clone(), cursor));
+        (id, evicted)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<dyn ResultCursor>> {
+        let cursors = self.cursors.lock().unwrap();
+        cursors
+            .iter()
+            .find(|(cursor_id, _)| cursor_id == id)
+            .map(|(_, cursor)| cursor.clone())
+    }
+
+    pub fn remove(&self, id: &str) -> Option<Arc<dyn ResultCursor>> {
+        let mut cursors = self.cursors.lock().unwrap();
+        let index = cursors.iter().position(|(cursor_id, _)| cursor_id == id)?;
+        cursors.remove(index).map(|(_, cursor)| cursor)
+    }
+
+    /// Removes and returns all cursors so they can be closed.
+    pub fn take_all(&self) -> Vec<Arc<dyn ResultCursor>> {
+        let mut cursors = self.cursors.lock().unwrap();
+        cursors.drain(..).map(|(_, cursor)| cursor).collect()
+    }
+}
+
+/// Number of query results kept by `QueryCache`.
+const MAX_CACHED_RESULTS: usize = 32;
+
+/// Results with more rows than this are not cached; the cache is meant for small, frequently
+/// repeated queries.
+const MAX_CACHED_ROWS: usize = 1_000;
+
+struct CachedResult {
+    connection_id: String,
+    database: String,
+    /// Query as returned by `normalize_query`.
+    query: String,
+    result: QueryResult,
+    cached_at: Instant,
+}
+
+impl CachedResult {
+    fn matches(&self, connection_id: &str, database: &str, query: &str) -> bool {
+        self.connection_id == connection_id && self.database == database && self.query == query
+    }
+}
+
+/// Reuses results of read-only queries for a while, keyed by connection, database and query.
+#[derive(Default)]
+pub struct QueryCache {
+    entries: Mutex<VecDeque<CachedResult>>,
+}
+
+impl QueryCache {
+    /// Returns the cached result of `query` if it was cached less than `ttl` ago.
+    pub fn get(
+        &self,
+        connection_id: &str,
+        database: &str,
+        query: &str,
+        ttl: Duration,
+    ) -> Option<QueryResult> {
+        let query = normalize_query(query);
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .find(|e| e.matches(connection_id, database, &query))
+            .filter(|e| e.cached_at.elapsed() < ttl)
+            .map(|e| e.result.clone())
+    }
+
+    /// Caches the result of `query`, replacing an older result of the same query.
+    pub fn insert(&self, connection_id: &str, database: &str, query: &str, result: &QueryResult) {
+        if result.rows.len() > MAX_CACHED_ROWS {
+            return;
+        }
+
+        let query = normalize_query(query);
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|e| !e.matches(connection_id, database, &query));
+        if entries.len() >= MAX_CACHED_RESULTS {
+            entries.pop_front();
+        }
+        entries.push_back(CachedResult {
+            connection_id: connection_id.to_string(),
+            database: database.to_string(),
+            query,
+            result: result.clone(),
+            cached_at: Instant::now(),
+        });
+    }
+
+    /// Drops the cached results of a connection, e.g. after it modified data.
+    pub fn invalidate(&self, connection_id: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|e| e.connection_id != connection_id);
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// Strips comments and surrounding whitespace so trivially different spellings of a query
+/// share a cache entry.
+fn normalize_query(query: &str) -> String {
+    statement::split_statements(query)
+        .iter()
+        .map(|s| s.sql.as_str())
+        .collect::<Vec<_>>()
+        .join(";\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn store_rows(store: &ResultStore, count: usize) -> String {
+        let mut builder = store.builder();
+        for i in 0..count {
+            builder.push(json!({ "n": i })).unwrap();
+        }
+        store.insert(builder).unwrap()
+    }
+
+    #[test]
+    fn test_rows_are_fetched_by_window_and_oldest_results_evicted() {
+        let store = ResultStore::default();
+        let id = store_rows(&store, 10);
+
+        assert_eq!(
+            store.get_rows(&id, 8, 5).unwrap(),
+            Some(vec![json!({"n": 8}), json!({"n": 9})])
+        );
+        assert_eq!(store.get_rows(&id, 20, 5).unwrap(), Some(vec![]));
+
+        for _ in 0..MAX_STORED_RESULTS {
+            store_rows(&store, 0);
+        }
+        assert_eq!(store.get_rows(&id, 0, 1).unwrap(), None);
+        assert!(!store.remove(&id));
+    }
+
+    #[test]
+    fn test_rows_over_memory_budget_are_spilled_to_disk() {
+        let store = ResultStore::default();
+        store.set_memory_budget(1024);
+        let id = store_rows(&store, 100);
+
+        let path = match &store.results.lock().unwrap()[0].rows {
+            StoredRows::Disk(rows) => rows.path.clone(),
+            StoredRows::Memory(_) => panic!("rows were kept in memory"),
+        };
+        assert_eq!(
+            store.get_rows(&id, 98, 5).unwrap(),
+            Some(vec![json!({"n": 98}), json!({"n": 99})])
+        );
+        assert_eq!(
+            store.get_rows(&id, 0, 1).unwrap(),
+            Some(vec![json!({"n": 0})])
+        );
+
+        assert!(store.remove(&id));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_query_cache_expires_and_is_invalidated_per_connection() {
+        let cache = QueryCache::default();
+        let result = QueryResult {
+            columns: vec!["n".to_string()],
+            rows: vec![json!({ "n": 1 })],
+            row_count: 1,
+            execution_time: 5,
+            truncated: false,
+            affected_rows: None,
+            timing: Default::default(),
+            column_metadata: Vec::new(),
+        };
+        let ttl = Duration::from_secs(60);
+        cache.insert("a", "app", "SELECT 1 AS n;", &result);
+        cache.insert("b", "app", "SELECT 1 AS n", &result);
+
+        assert!(cache
+            .get("a", "app", "  SELECT 1 AS n -- count\n", ttl)
+            .is_some());
+        assert!(cache.get("a", "other", "SELECT 1 AS n", ttl).is_none());
+        assert!(cache
+            .get("a", "app", "SELECT 1 AS n", Duration::ZERO)
+            .is_none());
+
+        cache.invalidate("a");
+        assert!(cache.get("a", "app", "SELECT 1 AS n", ttl).is_none());
+        assert!(cache.get("b", "app", "SELECT 1 AS n", ttl).is_some());
+    }
+}
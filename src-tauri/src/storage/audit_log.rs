@@ -0,0 +1,214 @@
+use rusqlite::{params, Connection, Result as SqlResult};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A destructive statement executed by the app.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Assigned by the store; ignored when recording.
+    #[serde(default)]
+    pub id: i64,
+    /// UTC timestamp (`YYYY-MM-DD HH:MM:SS`); assigned by the store when recording.
+    #[serde(default)]
+    pub executed_at: String,
+    pub connection_id: Option<String>,
+    pub connection_name: String,
+    pub database: String,
+    pub statement: String,
+    pub affected_rows: Option<u64>,
+    /// Command that issued the statement (e.g. `execute_query`, `update_cell`).
+    pub command: String,
+    /// Error message if the statement failed.
+    pub error: Option<String>,
+}
+
+/// Filter for reading the audit log. All fields are optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditLogFilter {
+    pub connection_id: Option<String>,
+    /// Inclusive lower bound on `executed_at`.
+    pub since: Option<String>,
+    /// Exclusive upper bound on `executed_at`.
+    pub until: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// File format for exported audit log entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditExportFormat {
+    Csv,
+    Json,
+}
+
+/// Columns of the CSV export, in order.
+const CSV_HEADER: &str =
+    "id,executed_at,connection_id,connection_name,database,command,statement,affected_rows,error";
+
+/// Renders entries as a CSV or JSON document.
+pub fn format_entries(entries: &[AuditEntry], format: AuditExportFormat) -> String {
+    match format {
+        AuditExportFormat::Json => serde_json::to_string_pretty(entries).unwrap_or_default(),
+        AuditExportFormat::Csv => {
+            let mut csv = String::from(CSV_HEADER);
+            csv.push_str("\r\n");
+            for entry in entries {
+                let fields = [
+                    entry.id.to_string(),
+                    entry.executed_at.clone(),
+                    entry.connection_id.clone().unwrap_or_default(),
+                    entry.connection_name.clone(),
+                    entry.database.clone(),
+                    entry.command.clone(),
+                    entry.statement.clone(),
+                    entry
+                        .affected_rows
+                        .map(|n| n.to_string())
+                        .unwrap_or_default(),
+                    entry.error.clone().unwrap_or_default(),
+                ];
+                let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                csv.push_str(&row.join(","));
+                csv.push_str("\r\n");
+            }
+            csv
+        }
+    }
+}
+
+/// Quotes a CSV field (RFC 4180) when it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Append-only log of destructive operations, stored in SQLite.
+///
+/// Triggers reject UPDATE and DELETE on the table so entries cannot be altered through the app.
+pub struct AuditLog {
+    db: Mutex<Connection>,
+}
+
+impl AuditLog {
+    pub fn new(db_path: PathBuf) -> SqlResult<Self> {
+        let db = Connection::open(&db_path)?;
+        // Shares the database file with `ConnectionsStore`
+        db.busy_timeout(Duration::from_secs(5))?;
+        let log = AuditLog { db: Mutex::new(db) };
+        log.init_tables()?;
+        Ok(log)
+    }
+
+    fn init_tables(&self) -> SqlResult<()> {
+        let db = self.db.lock().unwrap();
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                executed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                connection_id TEXT,
+                connection_name TEXT NOT NULL,
+                database TEXT NOT NULL,
+                statement TEXT NOT NULL,
+                affected_rows INTEGER,
+                command TEXT NOT NULL,
+                error TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_audit_log_executed_at ON audit_log (executed_at);
+            CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'audit log is append-only');
+            END;
+            CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'audit log is append-only');
+            END;",
+        )
+    }
+
+    /// Appends an entry. `id` and `executed_at` are assigned by the store.
+    pub fn record(&self, entry: &AuditEntry) -> SqlResult<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO audit_log (connection_id, connection_name, database, statement, affected_rows, command, error)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                &entry.connection_id,
+                &entry.connection_name,
+                &entry.database,
+                &entry.statement,
+                entry.affected_rows.map(|n| n as i64),
+                &entry.command,
+                &entry.error
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Returns entries matching the filter, newest first.
+    pub fn get_entries(&self, filter: &AuditLogFilter) -> SqlResult<Vec<AuditEntry>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT id, executed_at, connection_id, connection_name, database, statement,
+                    affected_rows, command, error
+             FROM audit_log
+             WHERE (?1 IS NULL OR connection_id = ?1)
+                AND (?2 IS NULL OR executed_at >= ?2)
+                AND (?3 IS NULL OR executed_at < ?3)
+             ORDER BY id DESC
+             LIMIT ?4",
+        )?;
+
+        let limit = filter.limit.map(i64::from).unwrap_or(-1);
+        let entries = stmt.query_map(
+            params![&filter.connection_id, &filter.since, &filter.until, limit],
+            |row| {
+                Ok(AuditEntry {
+                    id: row.get(0)?,
+                    executed_at: row.get(1)?,
+                    connection_id: row.get(2)?,
+                    connection_name: row.get(3)?,
+                    database: row.get(4)?,
+                    statement: row.get(5)?,
+                    affected_rows: row.get::<_, Option<i64>>(6)?.map(|n| n as u64),
+                    command: row.get(7)?,
+                    error: row.get(8)?,
+                })
+            },
+        )?;
+
+        entries.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_export_quotes_statements() {
+        let entry = AuditEntry {
+            id: 7,
+            executed_at: "2024-05-01 10:00:00".to_string(),
+            connection_id: None,
+            connection_name: "prod".to_string(),
+            database: "app".to_string(),
+            statement: "DELETE FROM t\nWHERE name = \"a,b\"".to_string(),
+            affected_rows: Some(2),
+            command: "execute_query".to_string(),
+            error: None,
+        };
+
+        let csv = format_entries(&[entry], AuditExportFormat::Csv);
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("7,2024-05-01 10:00:00,,prod,app,execute_query,\"DELETE FROM t")
+        );
+        assert!(csv.ends_with("WHERE name = \"\"a,b\"\"\",2,\r\n"));
+    }
+}
@@ -0,0 +1,363 @@
+use super::connections_store::{
+    ConnectionGroup, ConnectionPreferences, ConnectionSort, ConnectionsStore, RdsIamConfig,
+    SshConfig, StoredConnection,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Version of the sync file format.
+const SYNC_FILE_VERSION: u32 = 1;
+
+/// Contents of the sync file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SyncFile {
+    version: u32,
+    groups: Vec<ConnectionGroup>,
+    connections: Vec<SyncedConnection>,
+}
+
+/// The non-secret fields of a stored connection.
+///
+/// Passwords and SSH secrets never leave the encrypted store; they are kept from the local
+/// copy when a definition is imported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncedConnection {
+    id: String,
+    name: String,
+    db_type: String,
+    host: String,
+    port: i32,
+    username: String,
+    database: String,
+    #[serde(default = "default_ssl_mode")]
+    ssl_mode: String,
+    #[serde(default)]
+    ssl_ca_cert: Option<String>,
+    #[serde(default)]
+    pool_min_size: Option<u32>,
+    #[serde(default)]
+    pool_max_size: Option<u32>,
+    #[serde(default)]
+    group_id: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    preferences: ConnectionPreferences,
+    #[serde(default)]
+    ssh: Option<SshConfig>,
+    #[serde(default)]
+    rds_iam: Option<RdsIamConfig>,
+}
+
+fn default_ssl_mode() -> String {
+    "preferred".to_string()
+}
+
+impl From<&StoredConnection> for SyncedConnection {
+    fn from(conn: &StoredConnection) -> Self {
+        SyncedConnection {
+            id: conn.id.clone(),
+            name: conn.name.clone(),
+            db_type: conn.db_type.clone(),
+            host: conn.host.clone(),
+            port: conn.port,
+            username: conn.username.clone(),
+            database: conn.database.clone(),
+            ssl_mode: conn.ssl_mode.clone(),
+            ssl_ca_cert: conn.ssl_ca_cert.clone(),
+            pool_min_size: conn.pool_min_size,
+            pool_max_size: conn.pool_max_size,
+            group_id: conn.group_id.clone(),
+            tags: conn.tags.clone(),
+            preferences: conn.preferences.clone(),
+            ssh: conn.ssh.as_ref().map(|ssh| SshConfig {
+                password: None,
+                key_passphrase: None,
+                ..ssh.clone()
+            }),
+            rds_iam: conn.rds_iam.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct SyncState {
+    path: Option<PathBuf>,
+    /// Last content written to or read from the file, used to ignore our own writes.
+    last_content: Option<String>,
+}
+
+/// Mirrors connection definitions to a human-readable JSON file so they can be shared and
+/// version-controlled, and imports changes made to that file.
+pub struct ConnectionSync {
+    store: Arc<ConnectionsStore>,
+    state: Mutex<SyncState>,
+}
+
+fn to_io_error(e: rusqlite::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+impl ConnectionSync {
+    pub fn new(store: Arc<ConnectionsStore>, path: Option<PathBuf>) -> Self {
+        ConnectionSync {
+            store,
+            state: Mutex::new(SyncState {
+                path,
+                last_content: None,
+            }),
+        }
+    }
+
+    /// Enables syncing with `path`, or disables it with `None`.
+    ///
+    /// If the file already exists its connections are merged into the store first (without
+    /// removing local ones); the file is then rewritten with the merged set.
+    pub fn set_path(&self, path: Option<PathBuf>) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.path = path;
+        state.last_content = None;
+
+        let path = match &state.path {
+            Some(path) => path.clone(),
+            None => return Ok(()),
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(content) => self.import(&content, false)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        self.write_file(&mut state)
+    }
+
+    /// Writes the current connections to the sync file, if syncing is enabled.
+    pub fn export(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        self.write_file(&mut state)
+    }
+
+    fn write_file(&self, state: &mut SyncState) -> io::Result<()> {
+        let path = match &state.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let file = SyncFile {
+            version: SYNC_FILE_VERSION,
+            groups: self.store.get_all_groups().map_err(to_io_error)?,
+            connections: self
+                .store
+                .get_all_connections(ConnectionSort::Name)
+                .map_err(to_io_error)?
+                .iter()
+                .map(SyncedConnection::from)
+                .collect(),
+        };
+        let content = serde_json::to_string_pretty(&file)? + "\n";
+        if state.last_content.as_deref() == Some(content.as_str()) {
+            return Ok(());
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write through a temporary file so readers never see a partial file
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, &content)?;
+        std::fs::rename(&tmp_path, path)?;
+
+        state.last_content = Some(content);
+        Ok(())
+    }
+
+    /// Imports the sync file if it changed since it was last read or written.
+    ///
+    /// Returns true if the store was updated. Connections that were removed from the file
+    /// are moved to the trash.
+    pub fn poll(&self) -> io::Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        let path = match &state.path {
+            Some(path) => path.clone(),
+            None => return Ok(false),
+        };
+
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if state.last_content.as_deref() == Some(content.as_str()) {
+            return Ok(false);
+        }
+
+        // Remember the content even if it fails to parse so a broken file is reported once
+        state.last_content = Some(content.clone());
+        self.import(&content, true)?;
+        Ok(true)
+    }
+
+    fn import(&self, content: &str, remove_missing: bool) -> io::Result<()> {
+        let file: SyncFile = serde_json::from_str(content)?;
+        if file.version > SYNC_FILE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported sync file version {}", file.version),
+            ));
+        }
+
+        for group in file.groups.into_iter().filter(|g| !g.id.is_empty()) {
+            self.store.save_group(group).map_err(to_io_error)?;
+        }
+
+        let mut synced_ids = HashSet::new();
+        for synced in file.connections {
+            if synced.id.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Connection '{}' in sync file has no id", synced.name),
+                ));
+            }
+            let id = synced.id.clone();
+            let local = self.store.get_connection(&id).map_err(to_io_error)?;
+            self.store
+                .save_connection(merge_local_secrets(synced, local))
+                .map_err(to_io_error)?;
+            // The file is the source of truth, so a listed connection is taken out of the trash
+            self.store.restore_connection(&id).map_err(to_io_error)?;
+            synced_ids.insert(id);
+        }
+
+        if remove_missing {
+            for conn in self
+                .store
+                .get_all_connections(ConnectionSort::Created)
+                .map_err(to_io_error)?
+            {
+                if !synced_ids.contains(&conn.id) {
+                    self.store
+                        .delete_connection(&conn.id)
+                        .map_err(to_io_error)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds a connection from a synced definition, keeping the secrets of the local copy.
+fn merge_local_secrets(
+    synced: SyncedConnection,
+    local: Option<StoredConnection>,
+) -> StoredConnection {
+    let (password, local_ssh, last_used_at, use_count) = match local {
+        Some(local) => (
+            local.password_encrypted,
+            local.ssh,
+            local.last_used_at,
+            local.use_count,
+        ),
+        None => (String::new(), None, None, 0),
+    };
+
+    let ssh = synced.ssh.map(|ssh| match local_ssh {
+        Some(local_ssh) => SshConfig {
+            password: local_ssh.password,
+            key_passphrase: local_ssh.key_passphrase,
+            ..ssh
+        },
+        None => ssh,
+    });
+
+    StoredConnection {
+        id: synced.id,
+        name: synced.name,
+        db_type: synced.db_type,
+        host: synced.host,
+        port: synced.port,
+        username: synced.username,
+        password_encrypted: password,
+        database: synced.database,
+        ssl_mode: synced.ssl_mode,
+        ssl_ca_cert: synced.ssl_ca_cert,
+        pool_min_size: synced.pool_min_size,
+        pool_max_size: synced.pool_max_size,
+        group_id: synced.group_id,
+        tags: synced.tags,
+        last_used_at,
+        use_count,
+        preferences: synced.preferences,
+        ssh,
+        rds_iam: synced.rds_iam,
+        deleted_at: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::data_dir::DataDirSource;
+
+    #[test]
+    fn test_sync_file_round_trip_keeps_secrets_local() {
+        let dir = std::env::temp_dir().join(format!("bloatsql-sync-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = Arc::new(
+            ConnectionsStore::new(dir.join("connections.db"), DataDirSource::Default).unwrap(),
+        );
+        let sync_path = dir.join("connections.json");
+        let sync = ConnectionSync::new(store.clone(), None);
+        sync.set_path(Some(sync_path.clone())).unwrap();
+
+        let saved = store
+            .save_connection(StoredConnection {
+                id: String::new(),
+                name: "Production".to_string(),
+                db_type: "postgresql".to_string(),
+                host: "db.internal".to_string(),
+                port: 5432,
+                username: "app".to_string(),
+                password_encrypted: "hunter2".to_string(),
+                database: "app".to_string(),
+                ssl_mode: "required".to_string(),
+                ssl_ca_cert: None,
+                pool_min_size: None,
+                pool_max_size: None,
+                group_id: None,
+                tags: vec!["prod".to_string()],
+                last_used_at: None,
+                use_count: 0,
+                preferences: ConnectionPreferences::default(),
+                ssh: None,
+                rds_iam: None,
+                deleted_at: None,
+            })
+            .unwrap();
+        sync.export().unwrap();
+
+        let content = std::fs::read_to_string(&sync_path).unwrap();
+        assert!(content.contains("db.internal"));
+        assert!(!content.contains("hunter2"));
+        assert!(!sync.poll().unwrap());
+
+        // Simulate a teammate renaming the connection in the shared file
+        std::fs::write(&sync_path, content.replace("Production", "Prod (primary)")).unwrap();
+        assert!(sync.poll().unwrap());
+        let updated = store.get_connection(&saved.id).unwrap().unwrap();
+        assert_eq!(updated.name, "Prod (primary)");
+        assert_eq!(updated.password_encrypted, "hunter2");
+
+        std::fs::write(&sync_path, r#"{"version": 1, "connections": []}"#).unwrap();
+        assert!(sync.poll().unwrap());
+        assert!(store
+            .get_all_connections(ConnectionSort::Created)
+            .unwrap()
+            .is_empty());
+        assert_eq!(store.get_deleted_connections().unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,3 +1,10 @@
+use super::data_dir::DataDirSource;
+use super::key_store::{KeyBackend, KeyLocation, OsKeyBackend, KEY_LENGTH};
+use super::master_password::{self, StoreLocked, VERIFIER_PLAINTEXT};
+use super::secret_store::{
+    password_account, ssh_key_passphrase_account, ssh_password_account, OsKeyring, SecretBackend,
+    SecretUnavailable, IN_SECRET_BACKEND,
+};
 use aes_gcm::{
     aead::{Aead, KeyInit, OsRng},
     Aes256Gcm, Nonce,
@@ -6,16 +13,17 @@ use rand::RngCore;
 use rusqlite::{params, Connection, Result as SqlResult};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
-use tracing::warn;
+use tracing::{debug, warn};
 use uuid::Uuid;
 
-/// Length of the encryption key in bytes (256 bits for AES-256).
-const KEY_LENGTH: usize = 32;
-
 /// Length of the nonce in bytes (96 bits for AES-GCM).
 const NONCE_LENGTH: usize = 12;
 
+/// Days a deleted connection stays in the trash before it is purged permanently.
+pub const TRASH_RETENTION_DAYS: u32 = 30;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredConnection {
     pub id: String,
@@ -27,65 +35,261 @@ pub struct StoredConnection {
     pub password_encrypted: String,
     pub database: String,
     pub ssl_mode: String,
+    /// PEM file of the CA that signed the server certificate, for the `verify-ca` and
+    /// `verify-full` SSL modes; `None` uses the system trust store.
+    pub ssl_ca_cert: Option<String>,
+    /// Server connections kept open in the pool; `None` uses the driver default.
+    pub pool_min_size: Option<u32>,
+    /// Most server connections the pool may open at once; `None` uses the driver default.
+    pub pool_max_size: Option<u32>,
+    pub group_id: Option<String>,
+    pub tags: Vec<String>,
+    /// When the app last connected successfully (set by `record_connection_use`).
+    pub last_used_at: Option<String>,
+    /// Number of successful connects.
+    pub use_count: i64,
+    pub preferences: ConnectionPreferences,
+    /// SSH tunnel settings; `None` connects directly.
+    pub ssh: Option<SshConfig>,
+    /// Logs in to AWS RDS with IAM tokens instead of the password when set.
+    pub rds_iam: Option<RdsIamConfig>,
+    /// When the connection was moved to the trash; `None` for active connections.
+    pub deleted_at: Option<String>,
+}
+
+/// How an SSH tunnel authenticates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SshAuthMethod {
+    #[default]
+    Password,
+    PrivateKey,
+}
+
+/// How an SSH server's host key is checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostKeyPolicy {
+    /// Only connect if the key matches `known_host_key`.
+    Strict,
+    /// Trust and record the key on first connect, then require it to match.
+    #[default]
+    AcceptNew,
+    /// Skip host key verification.
+    Insecure,
+}
+
+/// AWS RDS IAM authentication settings for a connection. Tokens are generated from the
+/// local AWS credentials at connect time, so nothing secret is stored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RdsIamConfig {
+    /// Profile in the shared AWS config files; `None` uses the environment's credentials
+    /// or `AWS_PROFILE`, then `default`.
+    pub profile: Option<String>,
+    /// Region of the database; `None` reads it from the RDS host name or the AWS config.
+    pub region: Option<String>,
+}
+
+/// SSH tunnel settings for a connection.
+///
+/// `password` and `key_passphrase` are stored like database passwords. Private keys are referenced by path and never copied into the store.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SshConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub auth_method: SshAuthMethod,
+    pub password: Option<String>,
+    pub private_key_path: Option<String>,
+    pub key_passphrase: Option<String>,
+    pub host_key_policy: HostKeyPolicy,
+    /// Trusted host key fingerprint for this connection (the per-connection known_hosts entry).
+    pub known_host_key: Option<String>,
+}
+
+/// Per-connection settings applied while the connection is active.
+///
+/// Stored as a JSON column so new preferences don't require schema migrations.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionPreferences {
+    /// Maximum rows returned by `execute_query` (capped by `MAX_QUERY_ROWS`).
+    pub row_limit: Option<usize>,
+    /// Query timeout in seconds, enforced by the app and, from the next connect, by
+    /// MariaDB/MySQL and PostgreSQL servers; `None` uses the drivers' default.
+    pub query_timeout_secs: Option<u64>,
+    /// Rejects statements that modify data or schema.
+    pub read_only: bool,
+    /// Requires `force` for UPDATE/DELETE without WHERE, TRUNCATE and DROP.
+    pub confirm_dangerous_statements: bool,
+    /// Seconds the results of read-only queries are reused from the query cache;
+    /// `None` disables caching.
+    pub result_cache_ttl_secs: Option<u64>,
+    /// Schema (PostgreSQL) or database (MariaDB/MySQL) selected after connecting.
+    pub default_schema: Option<String>,
+    /// Accent color shown in the UI for this connection.
+    pub color: Option<String>,
+    /// Environment the connection points at, so the UI can warn louder about production.
+    pub environment: Option<ConnectionEnvironment>,
+    /// Seconds between keepalive pings while connected; `Some(0)` turns them off.
+    pub keepalive_interval_secs: Option<u64>,
+}
+
+/// Deployment environment a connection is tagged with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionEnvironment {
+    Production,
+    Staging,
+    Development,
+}
+
+impl ConnectionEnvironment {
+    /// The tag as serialized, for messages.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConnectionEnvironment::Production => "production",
+            ConnectionEnvironment::Staging => "staging",
+            ConnectionEnvironment::Development => "development",
+        }
+    }
+}
+
+/// Sort order for listing stored connections.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionSort {
+    /// Newest connections first.
+    #[default]
+    Created,
+    /// Alphabetical by name.
+    Name,
+    /// Most recently used first; never-used connections last.
+    LastUsed,
+    /// Most frequently used first.
+    UseCount,
+}
+
+impl ConnectionSort {
+    fn order_by(self) -> &'static str {
+        match self {
+            ConnectionSort::Created => "created_at DESC",
+            ConnectionSort::Name => "name COLLATE NOCASE",
+            ConnectionSort::LastUsed => {
+                "last_used_at IS NULL, last_used_at DESC, name COLLATE NOCASE"
+            }
+            ConnectionSort::UseCount => "use_count DESC, last_used_at DESC, name COLLATE NOCASE",
+        }
+    }
+}
+
+/// A folder used to organize stored connections.
+///
+/// Groups can be nested through `parent_id`; siblings are ordered by `sort_order`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionGroup {
+    pub id: String,
+    pub name: String,
+    pub parent_id: Option<String>,
+    pub sort_order: i32,
 }
 
+/// Columns selected when reading a `StoredConnection`, in the order expected by `map_connection_row`.
+///
+/// Tags are aggregated into a JSON array so a connection and its tags load in a single row.
+const CONNECTION_COLUMNS: &str =
+    "id, name, db_type, host, port, username, password_encrypted, database, ssl_mode, group_id,
+     (SELECT json_group_array(tag) FROM (
+        SELECT tag FROM connection_tags WHERE connection_id = connections.id ORDER BY tag
+     )),
+     last_used_at, use_count, preferences, ssh_config, deleted_at, ssl_ca_cert,
+     pool_min_size, pool_max_size, rds_iam";
+
 /// Manages persistent storage of database connections using SQLite.
 ///
-/// Passwords are encrypted using AES-256-GCM before storage.
+/// Passwords are kept in the `SecretBackend` (the OS credential store by default), or
+/// encrypted using AES-256-GCM into the database when there is none or it fails. With a
+/// master password set they are always encrypted into the database, with a key derived from
+/// it (see `master_password`).
 pub struct ConnectionsStore {
     db: Mutex<Connection>,
-    encryption_key: [u8; KEY_LENGTH],
+    /// `None` while locked by the master password.
+    encryption_key: Mutex<Option<[u8; KEY_LENGTH]>>,
+    has_master_password: AtomicBool,
+    keys: KeyLocation,
+    secrets: Option<Box<dyn SecretBackend>>,
 }
 
 impl ConnectionsStore {
-    pub fn new(db_path: PathBuf) -> SqlResult<Self> {
-        let db = Connection::open(&db_path)?;
+    /// Opens the store in a data directory chosen by `data_dir`, which decides whether the
+    /// encryption key may move to the OS credential store (see `key_store`).
+    pub fn new(db_path: PathBuf, data_dir: DataDirSource) -> SqlResult<Self> {
+        let install_id = install_id(&Connection::open(&db_path)?)?;
+        // Tests use throwaway databases, so their passwords stay out of the real store
+        let (secrets, key_backend) = if cfg!(test) {
+            (None, None)
+        } else {
+            let secrets = OsKeyring::open(&install_id, &db_path.to_string_lossy())
+                .map(|keyring| Box::new(keyring) as Box<dyn SecretBackend>);
+            (secrets, Some(Box::new(OsKeyBackend) as Box<dyn KeyBackend>))
+        };
+        let keys = KeyLocation {
+            key_path: db_path.with_extension("key"),
+            install_id,
+            backend: key_backend,
+            keep_file: data_dir.keeps_key_file(),
+        };
+        Self::open(db_path, secrets, keys)
+    }
+
+    /// Opens the store with passwords kept in `secrets`, moving any still encrypted into the
+    /// database there. With `None` they are encrypted into the database.
+    pub fn with_secret_backend(
+        db_path: PathBuf,
+        secrets: Option<Box<dyn SecretBackend>>,
+    ) -> SqlResult<Self> {
+        let keys = KeyLocation::file(db_path.with_extension("key"));
+        Self::open(db_path, secrets, keys)
+    }
 
-        // Load or generate encryption key
-        let key_path = db_path.with_extension("key");
-        let encryption_key = Self::load_or_generate_key(&key_path);
+    fn open(
+        db_path: PathBuf,
+        secrets: Option<Box<dyn SecretBackend>>,
+        keys: KeyLocation,
+    ) -> SqlResult<Self> {
+        let db = Connection::open(&db_path)?;
 
         let store = ConnectionsStore {
             db: Mutex::new(db),
-            encryption_key,
+            encryption_key: Mutex::new(None),
+            has_master_password: AtomicBool::new(false),
+            keys,
+            secrets,
         };
         store.init_tables()?;
-        Ok(store)
-    }
 
-    /// Loads an existing encryption key or generates a new one.
-    fn load_or_generate_key(key_path: &PathBuf) -> [u8; KEY_LENGTH] {
-        if key_path.exists() {
-            if let Ok(key_data) = std::fs::read(key_path) {
-                if key_data.len() == KEY_LENGTH {
-                    let mut key = [0u8; KEY_LENGTH];
-                    key.copy_from_slice(&key_data);
-                    return key;
-                }
-            }
-            warn!("Invalid key file, generating new key");
+        // With a master password the store starts locked; otherwise load or generate the
+        // encryption key, still needed to read passwords saved without a secret backend
+        if store.master_password_row()?.is_some() {
+            store.has_master_password.store(true, Ordering::SeqCst);
+        } else {
+            *store.encryption_key.lock().unwrap() = Some(store.keys.load_or_generate_key());
         }
 
-        // Generate new key
-        let mut key = [0u8; KEY_LENGTH];
-        OsRng.fill_bytes(&mut key);
-
-        // Save key to file (with restrictive permissions on Unix)
-        if let Err(e) = std::fs::write(key_path, &key) {
-            warn!("Failed to save encryption key: {}", e);
+        match store.migrate_secrets() {
+            Ok(0) => {}
+            Ok(migrated) => debug!("Moved {} secrets to the secret backend", migrated),
+            Err(e) => warn!("Failed to move secrets to the secret backend: {}", e),
         }
 
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            if let Ok(metadata) = std::fs::metadata(key_path) {
-                let mut perms = metadata.permissions();
-                perms.set_mode(0o600);
-                let _ = std::fs::set_permissions(key_path, perms);
-            }
+        match store.purge_deleted_connections(TRASH_RETENTION_DAYS) {
+            Ok(0) => {}
+            Ok(purged) => debug!("Purged {} connections from the trash", purged),
+            Err(e) => warn!("Failed to purge trashed connections: {}", e),
         }
-
-        key
+        Ok(store)
     }
 
     fn init_tables(&self) -> SqlResult<()> {
@@ -105,14 +309,220 @@ impl ConnectionsStore {
             )",
             [],
         )?;
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS groups (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                parent_id TEXT,
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS connection_tags (
+                connection_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (connection_id, tag)
+            )",
+            [],
+        )?;
         // Graceful migration for older databases
         let _ = db.execute(
             "ALTER TABLE connections ADD COLUMN ssl_mode TEXT NOT NULL DEFAULT 'preferred'",
             [],
         );
+        let _ = db.execute("ALTER TABLE connections ADD COLUMN group_id TEXT", []);
+        let _ = db.execute(
+            "ALTER TABLE connections ADD COLUMN last_used_at DATETIME",
+            [],
+        );
+        let _ = db.execute(
+            "ALTER TABLE connections ADD COLUMN use_count INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        let _ = db.execute(
+            "ALTER TABLE connections ADD COLUMN preferences TEXT NOT NULL DEFAULT '{}'",
+            [],
+        );
+        let _ = db.execute("ALTER TABLE connections ADD COLUMN ssh_config TEXT", []);
+        let _ = db.execute("ALTER TABLE connections ADD COLUMN deleted_at DATETIME", []);
+        let _ = db.execute("ALTER TABLE connections ADD COLUMN ssl_ca_cert TEXT", []);
+        let _ = db.execute(
+            "ALTER TABLE connections ADD COLUMN pool_min_size INTEGER",
+            [],
+        );
+        let _ = db.execute(
+            "ALTER TABLE connections ADD COLUMN pool_max_size INTEGER",
+            [],
+        );
+        let _ = db.execute("ALTER TABLE connections ADD COLUMN rds_iam TEXT", []);
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS master_password (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                salt TEXT NOT NULL,
+                verifier TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Whether a master password protects the stored passwords.
+    pub fn has_master_password(&self) -> bool {
+        self.has_master_password.load(Ordering::SeqCst)
+    }
+
+    /// Whether the store is waiting for its master password to be entered.
+    pub fn is_locked(&self) -> bool {
+        self.encryption_key.lock().unwrap().is_none()
+    }
+
+    /// Unlocks the store with its master password.
+    ///
+    /// Returns false if the password is wrong or no master password is set.
+    pub fn unlock(&self, password: &str) -> SqlResult<bool> {
+        use base64::{engine::general_purpose, Engine as _};
+
+        let Some((salt, verifier)) = self.master_password_row()? else {
+            return Ok(false);
+        };
+        let salt = general_purpose::STANDARD
+            .decode(salt)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let key = master_password::derive_key(password, &salt)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+        let verified = general_purpose::STANDARD
+            .decode(verifier)
+            .ok()
+            .and_then(|combined| decrypt_with(&key, &combined).ok())
+            .is_some_and(|plaintext| plaintext == VERIFIER_PLAINTEXT.as_bytes());
+        if verified {
+            *self.encryption_key.lock().unwrap() = Some(key);
+        }
+        Ok(verified)
+    }
+
+    /// Forgets the key derived from the master password until `unlock` is called again.
+    ///
+    /// Returns false if no master password is set, as the store can't be locked then.
+    pub fn lock(&self) -> bool {
+        if !self.has_master_password() {
+            return false;
+        }
+        *self.encryption_key.lock().unwrap() = None;
+        true
+    }
+
+    /// Sets, changes or (with `None`) removes the master password, re-encrypting every
+    /// stored secret. The store must be unlocked.
+    ///
+    /// Removing it moves secrets back to the secret backend, or encrypts them with the key
+    /// from `key_store`.
+    pub fn set_master_password(&self, password: Option<&str>) -> SqlResult<()> {
+        use base64::{engine::general_purpose, Engine as _};
+
+        self.ensure_unlocked()?;
+        let rows = self.secret_rows()?;
+        let mut plaintext = Vec::with_capacity(rows.len());
+        for (id, password, ssh) in rows {
+            let password = self.open_secret(&password_account(&id), &password)?;
+            let ssh = ssh
+                .map(|ssh| self.decrypt_ssh_secrets(&id, ssh))
+                .transpose()?;
+            plaintext.push((id, password, ssh));
+        }
+
+        let (key, master_row) = match password {
+            Some(password) => {
+                let salt = master_password::generate_salt();
+                let key = master_password::derive_key(password, &salt)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+                let salt = general_purpose::STANDARD.encode(salt);
+                (key, Some((salt, encrypt_with(&key, VERIFIER_PLAINTEXT))))
+            }
+            None => (self.keys.load_or_generate_key(), None),
+        };
+        let previous_key = self.encryption_key.lock().unwrap().replace(key);
+        let previous_master = self
+            .has_master_password
+            .swap(master_row.is_some(), Ordering::SeqCst);
+
+        let result = self.write_secrets(plaintext, master_row);
+        if result.is_err() {
+            *self.encryption_key.lock().unwrap() = previous_key;
+            self.has_master_password
+                .store(previous_master, Ordering::SeqCst);
+        }
+        result
+    }
+
+    /// Saves re-sealed secrets along with the new master password row, in one transaction.
+    fn write_secrets(
+        &self,
+        plaintext: Vec<(String, String, Option<SshConfig>)>,
+        master_row: Option<(String, String)>,
+    ) -> SqlResult<()> {
+        let mut sealed = Vec::with_capacity(plaintext.len());
+        let mut stale = Vec::new();
+        for (id, password, ssh) in plaintext {
+            let password = self.seal_secret(&password_account(&id), &password, &mut stale)?;
+            let ssh_config = ssh
+                .map(|ssh| {
+                    let ssh = self.encrypt_ssh_secrets(&id, &ssh, &mut stale)?;
+                    serde_json::to_string(&ssh)
+                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+                })
+                .transpose()?;
+            sealed.push((id, password, ssh_config));
+        }
+
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        for (id, password, ssh_config) in sealed {
+            tx.execute(
+                "UPDATE connections SET password_encrypted = ?, ssh_config = ? WHERE id = ?",
+                params![password, ssh_config, id],
+            )?;
+        }
+        tx.execute("DELETE FROM master_password", [])?;
+        if let Some((salt, verifier)) = master_row {
+            tx.execute(
+                "INSERT INTO master_password (id, salt, verifier) VALUES (1, ?, ?)",
+                params![salt, verifier],
+            )?;
+        }
+        tx.commit()?;
+        self.forget_stale_secrets(stale);
         Ok(())
     }
 
+    /// The salt and verifier of the master password, if one is set.
+    fn master_password_row(&self) -> SqlResult<Option<(String, String)>> {
+        let db = self.db.lock().unwrap();
+        match db.query_row(
+            "SELECT salt, verifier FROM master_password WHERE id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ) {
+            Ok(row) => Ok(Some(row)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Fails with `StoreLocked` while the store is locked.
+    fn ensure_unlocked(&self) -> SqlResult<()> {
+        self.key().map(|_| ())
+    }
+
+    fn key(&self) -> SqlResult<[u8; KEY_LENGTH]> {
+        self.encryption_key
+            .lock()
+            .unwrap()
+            .ok_or_else(|| rusqlite::Error::ToSqlConversionFailure(Box::new(StoreLocked)))
+    }
+
     pub fn save_connection(&self, conn: StoredConnection) -> SqlResult<StoredConnection> {
         let id = if conn.id.is_empty() {
             Uuid::new_v4().to_string()
@@ -120,12 +530,49 @@ impl ConnectionsStore {
             conn.id.clone()
         };
 
-        let password_encrypted = self.encrypt_password(&conn.password_encrypted);
-        let db = self.db.lock().unwrap();
+        let mut stale = Vec::new();
+        let password_encrypted =
+            self.seal_secret(&password_account(&id), &conn.password_encrypted, &mut stale)?;
+        let tags = normalize_tags(&conn.tags);
+        let preferences = serde_json::to_string(&conn.preferences)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let ssh_config = conn
+            .ssh
+            .as_ref()
+            .map(|ssh| self.encrypt_ssh_secrets(&id, ssh, &mut stale))
+            .transpose()?
+            .map(|ssh| serde_json::to_string(&ssh))
+            .transpose()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let rds_iam = conn
+            .rds_iam
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
 
-        db.execute(
-            "INSERT OR REPLACE INTO connections (id, name, db_type, host, port, username, password_encrypted, database, ssl_mode)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        // Upsert rather than REPLACE so usage statistics survive edits
+        tx.execute(
+            "INSERT INTO connections (id, name, db_type, host, port, username, password_encrypted, database, ssl_mode, group_id, preferences, ssh_config, ssl_ca_cert, pool_min_size, pool_max_size, rds_iam)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                db_type = excluded.db_type,
+                host = excluded.host,
+                port = excluded.port,
+                username = excluded.username,
+                password_encrypted = excluded.password_encrypted,
+                database = excluded.database,
+                ssl_mode = excluded.ssl_mode,
+                group_id = excluded.group_id,
+                preferences = excluded.preferences,
+                ssh_config = excluded.ssh_config,
+                ssl_ca_cert = excluded.ssl_ca_cert,
+                pool_min_size = excluded.pool_min_size,
+                pool_max_size = excluded.pool_max_size,
+                rds_iam = excluded.rds_iam",
             params![
                 &id,
                 &conn.name,
@@ -135,46 +582,44 @@ impl ConnectionsStore {
                 &conn.username,
                 password_encrypted,
                 &conn.database,
-                &conn.ssl_mode
+                &conn.ssl_mode,
+                &conn.group_id,
+                preferences,
+                ssh_config,
+                &conn.ssl_ca_cert,
+                conn.pool_min_size,
+                conn.pool_max_size,
+                rds_iam
             ],
         )?;
 
-        Ok(StoredConnection {
-            id,
-            name: conn.name,
-            db_type: conn.db_type,
-            host: conn.host,
-            port: conn.port,
-            username: conn.username,
-            password_encrypted: conn.password_encrypted,
-            database: conn.database,
-            ssl_mode: conn.ssl_mode,
-        })
+        tx.execute(
+            "DELETE FROM connection_tags WHERE connection_id = ?",
+            params![&id],
+        )?;
+        for tag in &tags {
+            tx.execute(
+                "INSERT INTO connection_tags (connection_id, tag) VALUES (?, ?)",
+                params![&id, tag],
+            )?;
+        }
+        tx.commit()?;
+        drop(db);
+        self.forget_stale_secrets(stale);
+
+        Ok(StoredConnection { id, tags, ..conn })
     }
 
-    pub fn get_all_connections(&self) -> SqlResult<Vec<StoredConnection>> {
+    pub fn get_all_connections(&self, sort: ConnectionSort) -> SqlResult<Vec<StoredConnection>> {
+        self.ensure_unlocked()?;
         let db = self.db.lock().unwrap();
-        let mut stmt = db.prepare(
-            "SELECT id, name, db_type, host, port, username, password_encrypted, database, ssl_mode
-             FROM connections ORDER BY created_at DESC",
-        )?;
-
-        let connections = stmt.query_map([], |row| {
-            let password_encrypted: String = row.get(6)?;
-            let password = self.decrypt_password(&password_encrypted);
+        let mut stmt = db.prepare(&format!(
+            "SELECT {} FROM connections WHERE deleted_at IS NULL ORDER BY {}",
+            CONNECTION_COLUMNS,
+            sort.order_by()
+        ))?;
 
-            Ok(StoredConnection {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                db_type: row.get(2)?,
-                host: row.get(3)?,
-                port: row.get(4)?,
-                username: row.get(5)?,
-                password_encrypted: password,
-                database: row.get(7)?,
-                ssl_mode: row.get(8)?,
-            })
-        })?;
+        let connections = stmt.query_map([], |row| self.map_connection_row(row))?;
 
         let mut result = Vec::new();
         for conn in connections {
@@ -185,30 +630,15 @@ impl ConnectionsStore {
         Ok(result)
     }
 
-    #[allow(dead_code)]
     pub fn get_connection(&self, id: &str) -> SqlResult<Option<StoredConnection>> {
+        self.ensure_unlocked()?;
         let db = self.db.lock().unwrap();
-        let mut stmt = db.prepare(
-            "SELECT id, name, db_type, host, port, username, password_encrypted, database, ssl_mode
-             FROM connections WHERE id = ?",
-        )?;
-
-        let result = stmt.query_row(params![id], |row| {
-            let password_encrypted: String = row.get(6)?;
-            let password = self.decrypt_password(&password_encrypted);
+        let mut stmt = db.prepare(&format!(
+            "SELECT {} FROM connections WHERE id = ?",
+            CONNECTION_COLUMNS
+        ))?;
 
-            Ok(StoredConnection {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                db_type: row.get(2)?,
-                host: row.get(3)?,
-                port: row.get(4)?,
-                username: row.get(5)?,
-                password_encrypted: password,
-                database: row.get(7)?,
-                ssl_mode: row.get(8)?,
-            })
-        });
+        let result = stmt.query_row(params![id], |row| self.map_connection_row(row));
 
         match result {
             Ok(conn) => Ok(Some(conn)),
@@ -217,48 +647,572 @@ impl ConnectionsStore {
         }
     }
 
+    /// Builds a `StoredConnection` from a row selected with `CONNECTION_COLUMNS`,
+    /// decrypting the password.
+    fn map_connection_row(&self, row: &rusqlite::Row) -> SqlResult<StoredConnection> {
+        let id: String = row.get(0)?;
+        let password_encrypted: String = row.get(6)?;
+        let password = self.open_secret(&password_account(&id), &password_encrypted)?;
+
+        Ok(StoredConnection {
+            name: row.get(1)?,
+            db_type: row.get(2)?,
+            host: row.get(3)?,
+            port: row.get(4)?,
+            username: row.get(5)?,
+            password_encrypted: password,
+            database: row.get(7)?,
+            ssl_mode: row.get(8)?,
+            group_id: row.get(9)?,
+            tags: serde_json::from_str(&row.get::<_, String>(10)?).unwrap_or_default(),
+            last_used_at: row.get(11)?,
+            use_count: row.get(12)?,
+            preferences: serde_json::from_str(&row.get::<_, String>(13)?).unwrap_or_default(),
+            ssh: row
+                .get::<_, Option<String>>(14)?
+                .and_then(|json| serde_json::from_str::<SshConfig>(&json).ok())
+                .map(|ssh| self.decrypt_ssh_secrets(&id, ssh))
+                .transpose()?,
+            deleted_at: row.get(15)?,
+            ssl_ca_cert: row.get(16)?,
+            pool_min_size: row.get(17)?,
+            pool_max_size: row.get(18)?,
+            rds_iam: row
+                .get::<_, Option<String>>(19)?
+                .and_then(|json| serde_json::from_str(&json).ok()),
+            id,
+        })
+    }
+
+    /// Records the trusted SSH host key fingerprint for a connection.
+    ///
+    /// Used by the accept-new policy on first connect and when the user re-trusts a changed key.
+    pub fn trust_ssh_host_key(&self, id: &str, fingerprint: &str) -> SqlResult<bool> {
+        let db = self.db.lock().unwrap();
+        let rows_updated = db.execute(
+            "UPDATE connections
+             SET ssh_config = json_set(ssh_config, '$.known_host_key', ?)
+             WHERE id = ? AND ssh_config IS NOT NULL",
+            params![fingerprint, id],
+        )?;
+        Ok(rows_updated > 0)
+    }
+
+    /// Seals the SSH secrets of connection `id`, adding the accounts to remove from the secret
+    /// backend once saved to `stale` (see `seal_secret`).
+    fn encrypt_ssh_secrets(
+        &self,
+        id: &str,
+        ssh: &SshConfig,
+        stale: &mut Vec<String>,
+    ) -> SqlResult<SshConfig> {
+        let mut seal = |account: String, secret: Option<&str>| match secret {
+            Some(secret) => self.seal_secret(&account, secret, stale).map(Some),
+            None => {
+                stale.push(account);
+                Ok(None)
+            }
+        };
+        Ok(SshConfig {
+            password: seal(ssh_password_account(id), ssh.password.as_deref())?,
+            key_passphrase: seal(
+                ssh_key_passphrase_account(id),
+                ssh.key_passphrase.as_deref(),
+            )?,
+            ..ssh.clone()
+        })
+    }
+
+    fn decrypt_ssh_secrets(&self, id: &str, ssh: SshConfig) -> SqlResult<SshConfig> {
+        Ok(SshConfig {
+            password: ssh
+                .password
+                .as_deref()
+                .map(|p| self.open_secret(&ssh_password_account(id), p))
+                .transpose()?,
+            key_passphrase: ssh
+                .key_passphrase
+                .as_deref()
+                .map(|p| self.open_secret(&ssh_key_passphrase_account(id), p))
+                .transpose()?,
+            ..ssh
+        })
+    }
+
+    /// Replaces the preferences of a stored connection.
+    pub fn update_preferences(
+        &self,
+        id: &str,
+        preferences: &ConnectionPreferences,
+    ) -> SqlResult<bool> {
+        let preferences = serde_json::to_string(preferences)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let db = self.db.lock().unwrap();
+        let rows_updated = db.execute(
+            "UPDATE connections SET preferences = ? WHERE id = ?",
+            params![preferences, id],
+        )?;
+        Ok(rows_updated > 0)
+    }
+
+    /// Replaces the saved password of a stored connection.
+    pub fn update_password(&self, id: &str, password: &str) -> SqlResult<bool> {
+        let mut stale = Vec::new();
+        let password_encrypted = self.seal_secret(&password_account(id), password, &mut stale)?;
+        let rows_updated = self.db.lock().unwrap().execute(
+            "UPDATE connections SET password_encrypted = ? WHERE id = ?",
+            params![password_encrypted, id],
+        )?;
+        self.forget_stale_secrets(stale);
+        Ok(rows_updated > 0)
+    }
+
+    /// Records a successful connect: bumps `use_count` and sets `last_used_at` to now.
+    pub fn record_connection_use(&self, id: &str) -> SqlResult<bool> {
+        let db = self.db.lock().unwrap();
+        let rows_updated = db.execute(
+            "UPDATE connections
+             SET last_used_at = CURRENT_TIMESTAMP, use_count = use_count + 1
+             WHERE id = ?",
+            params![id],
+        )?;
+        Ok(rows_updated > 0)
+    }
+
+    /// Moves a connection to the trash. It can be restored until it is purged.
     pub fn delete_connection(&self, id: &str) -> SqlResult<bool> {
         let db = self.db.lock().unwrap();
-        let rows_deleted = db.execute("DELETE FROM connections WHERE id = ?", params![id])?;
+        let rows_updated = db.execute(
+            "UPDATE connections SET deleted_at = CURRENT_TIMESTAMP
+             WHERE id = ? AND deleted_at IS NULL",
+            params![id],
+        )?;
+        Ok(rows_updated > 0)
+    }
+
+    /// Returns connections in the trash, most recently deleted first.
+    pub fn get_deleted_connections(&self) -> SqlResult<Vec<StoredConnection>> {
+        self.ensure_unlocked()?;
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {} FROM connections WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+            CONNECTION_COLUMNS
+        ))?;
+
+        let connections = stmt.query_map([], |row| self.map_connection_row(row))?;
+        connections.collect()
+    }
+
+    /// Takes a connection out of the trash.
+    pub fn restore_connection(&self, id: &str) -> SqlResult<bool> {
+        let db = self.db.lock().unwrap();
+        let rows_updated = db.execute(
+            "UPDATE connections SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+            params![id],
+        )?;
+        Ok(rows_updated > 0)
+    }
+
+    /// Permanently deletes a connection that is in the trash.
+    pub fn purge_connection(&self, id: &str) -> SqlResult<bool> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        let rows_deleted = tx.execute(
+            "DELETE FROM connections WHERE id = ? AND deleted_at IS NOT NULL",
+            params![id],
+        )?;
+        if rows_deleted > 0 {
+            tx.execute(
+                "DELETE FROM connection_tags WHERE connection_id = ?",
+                params![id],
+            )?;
+        }
+        tx.commit()?;
+        if rows_deleted > 0 {
+            self.forget_secrets(id);
+        }
         Ok(rows_deleted > 0)
     }
 
-    /// Encrypts a password using AES-256-GCM.
+    /// Permanently deletes connections that have been in the trash for at least `days` days.
     ///
-    /// Returns a base64-encoded string containing: nonce || ciphertext
-    fn encrypt_password(&self, password: &str) -> String {
-        use base64::{engine::general_purpose, Engine as _};
+    /// Returns the number of connections purged.
+    pub fn purge_deleted_connections(&self, days: u32) -> SqlResult<usize> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        let cutoff = format!("-{} days", days);
+        let purged_ids = tx
+            .prepare(
+                "SELECT id FROM connections
+                 WHERE deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?)",
+            )?
+            .query_map(params![&cutoff], |row| row.get::<_, String>(0))?
+            .collect::<SqlResult<Vec<_>>>()?;
+        tx.execute(
+            "DELETE FROM connection_tags WHERE connection_id IN (
+                SELECT id FROM connections
+                WHERE deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?)
+             )",
+            params![&cutoff],
+        )?;
+        let purged = tx.execute(
+            "DELETE FROM connections
+             WHERE deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?)",
+            params![&cutoff],
+        )?;
+        tx.commit()?;
+        for id in &purged_ids {
+            self.forget_secrets(id);
+        }
+        Ok(purged)
+    }
+
+    /// Searches connections by name, host or tag.
+    ///
+    /// The query is split on whitespace and every term must match (case-insensitive substring)
+    /// at least one of those fields. An empty query returns all connections.
+    pub fn search_connections(&self, query: &str) -> SqlResult<Vec<StoredConnection>> {
+        self.ensure_unlocked()?;
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|term| format!("%{}%", escape_like(&term.to_lowercase())))
+            .collect();
+
+        let conditions = terms.iter().map(|_| {
+            "(lower(name) LIKE ? ESCAPE '\\' OR lower(host) LIKE ? ESCAPE '\\'
+                  OR EXISTS (SELECT 1 FROM connection_tags t
+                             WHERE t.connection_id = connections.id
+                                AND lower(t.tag) LIKE ? ESCAPE '\\'))"
+        });
+
+        let where_clause = std::iter::once("deleted_at IS NULL")
+            .chain(conditions)
+            .collect::<Vec<_>>()
+            .join(" AND ");
+
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {} FROM connections WHERE {} ORDER BY name COLLATE NOCASE",
+            CONNECTION_COLUMNS, where_clause
+        ))?;
+
+        let params = terms.iter().flat_map(|term| [term, term, term]);
+        let connections = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+            self.map_connection_row(row)
+        })?;
+
+        connections.collect()
+    }
+
+    /// Returns every distinct tag in use, sorted alphabetically.
+    pub fn get_all_tags(&self) -> SqlResult<Vec<String>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT DISTINCT t.tag FROM connection_tags t
+             JOIN connections c ON c.id = t.connection_id
+             WHERE c.deleted_at IS NULL
+             ORDER BY t.tag COLLATE NOCASE",
+        )?;
+        let tags = stmt.query_map([], |row| row.get(0))?;
+        tags.collect()
+    }
+
+    /// Moves a connection into a group, or back to the top level when `group_id` is `None`.
+    pub fn move_connection_to_group(&self, id: &str, group_id: Option<&str>) -> SqlResult<bool> {
+        let db = self.db.lock().unwrap();
+        let rows_updated = db.execute(
+            "UPDATE connections SET group_id = ? WHERE id = ?",
+            params![group_id, id],
+        )?;
+        Ok(rows_updated > 0)
+    }
+
+    pub fn save_group(&self, group: ConnectionGroup) -> SqlResult<ConnectionGroup> {
+        let id = if group.id.is_empty() {
+            Uuid::new_v4().to_string()
+        } else {
+            group.id.clone()
+        };
+
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO groups (id, name, parent_id, sort_order) VALUES (?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                parent_id = excluded.parent_id,
+                sort_order = excluded.sort_order",
+            params![&id, &group.name, &group.parent_id, group.sort_order],
+        )?;
+
+        Ok(ConnectionGroup { id, ..group })
+    }
+
+    /// Returns all groups ordered for display: by parent, then `sort_order`, then name.
+    pub fn get_all_groups(&self) -> SqlResult<Vec<ConnectionGroup>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT id, name, parent_id, sort_order FROM groups
+             ORDER BY parent_id, sort_order, name",
+        )?;
+
+        let groups = stmt.query_map([], |row| {
+            Ok(ConnectionGroup {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                parent_id: row.get(2)?,
+                sort_order: row.get(3)?,
+            })
+        })?;
+
+        groups.collect()
+    }
+
+    /// Deletes a group without losing its contents.
+    ///
+    /// Child groups and connections are moved up to the deleted group's parent.
+    pub fn delete_group(&self, id: &str) -> SqlResult<bool> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+
+        let parent_id: Option<String> = match tx.query_row(
+            "SELECT parent_id FROM groups WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+        ) {
+            Ok(parent_id) => parent_id,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        tx.execute(
+            "UPDATE groups SET parent_id = ? WHERE parent_id = ?",
+            params![&parent_id, id],
+        )?;
+        tx.execute(
+            "UPDATE connections SET group_id = ? WHERE group_id = ?",
+            params![&parent_id, id],
+        )?;
+        tx.execute("DELETE FROM groups WHERE id = ?", params![id])?;
+        tx.commit()?;
+
+        Ok(true)
+    }
+
+    /// Sets the order of sibling groups to match the order of `ordered_ids`.
+    pub fn reorder_groups(&self, ordered_ids: &[String]) -> SqlResult<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        for (index, id) in ordered_ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE groups SET sort_order = ? WHERE id = ?",
+                params![index as i32, id],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Returns true if placing `group_id` under `parent_id` would make a group its own ancestor.
+    pub fn is_group_descendant(&self, group_id: &str, parent_id: &str) -> SqlResult<bool> {
+        let db = self.db.lock().unwrap();
+        let mut current = Some(parent_id.to_string());
+
+        while let Some(id) = current {
+            if id == group_id {
+                return Ok(true);
+            }
+            current = match db.query_row(
+                "SELECT parent_id FROM groups WHERE id = ?",
+                params![&id],
+                |row| row.get(0),
+            ) {
+                Ok(parent) => parent,
+                Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                Err(e) => return Err(e),
+            };
+        }
+
+        Ok(false)
+    }
+
+    /// Moves passwords still encrypted into the database to the secret backend.
+    ///
+    /// Returns the number of secrets moved.
+    fn migrate_secrets(&self) -> SqlResult<usize> {
+        if self.secrets.is_none() || self.has_master_password() {
+            return Ok(0);
+        }
+
+        let mut migrated = 0;
+        let mut stale = Vec::new();
+        for (id, password, ssh) in self.secret_rows()? {
+            if let Some(sealed) =
+                self.migrate_secret(&password_account(&id), &password, &mut stale)?
+            {
+                let db = self.db.lock().unwrap();
+                db.execute(
+                    "UPDATE connections SET password_encrypted = ? WHERE id = ?",
+                    params![sealed, &id],
+                )?;
+                migrated += 1;
+            }
+
+            let Some(mut ssh) = ssh else {
+                continue;
+            };
+            let mut changed = false;
+            for (account, secret) in [
+                (ssh_password_account(&id), &mut ssh.password),
+                (ssh_key_passphrase_account(&id), &mut ssh.key_passphrase),
+            ] {
+                let stored = secret.as_deref().unwrap_or_default();
+                if let Some(sealed) = self.migrate_secret(&account, stored, &mut stale)? {
+                    *secret = Some(sealed);
+                    changed = true;
+                    migrated += 1;
+                }
+            }
+            if changed {
+                let ssh_config = serde_json::to_string(&ssh)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                let db = self.db.lock().unwrap();
+                db.execute(
+                    "UPDATE connections SET ssh_config = ? WHERE id = ?",
+                    params![ssh_config, &id],
+                )?;
+            }
+        }
+        self.forget_stale_secrets(stale);
+        Ok(migrated)
+    }
+
+    /// Every connection's id with its stored password and SSH config, secrets still sealed.
+    fn secret_rows(&self) -> SqlResult<Vec<(String, String, Option<SshConfig>)>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare("SELECT id, password_encrypted, ssh_config FROM connections")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?
+                    .and_then(|json| serde_json::from_str::<SshConfig>(&json).ok()),
+            ))
+        })?;
+        rows.collect()
+    }
+
+    /// Moves one secret encrypted into the database to the secret backend, returning what to
+    /// store in its place; `None` if there is nothing to move or moving it failed.
+    fn migrate_secret(
+        &self,
+        account: &str,
+        stored: &str,
+        stale: &mut Vec<String>,
+    ) -> SqlResult<Option<String>> {
+        if stored.is_empty() || stored == IN_SECRET_BACKEND {
+            return Ok(None);
+        }
+        let sealed = self.seal_secret(account, &self.decrypt_password(stored)?, stale)?;
+        Ok((sealed == IN_SECRET_BACKEND).then_some(sealed))
+    }
+
+    /// Stores a secret in the secret backend and returns the marker to save in its place, or
+    /// returns it encrypted when there is no backend, storing fails or a master password is
+    /// set.
+    ///
+    /// A backend entry the saved value no longer points to is added to `stale` instead of
+    /// removed, as the row still pointing to it may fail to save; callers pass `stale` to
+    /// `forget_stale_secrets` once it is saved.
+    fn seal_secret(
+        &self,
+        account: &str,
+        secret: &str,
+        stale: &mut Vec<String>,
+    ) -> SqlResult<String> {
+        if secret.is_empty() {
+            stale.push(account.to_string());
+            return Ok(String::new());
+        }
+        match &self.secrets {
+            Some(_) if self.has_master_password() => stale.push(account.to_string()),
+            Some(secrets) => match secrets.set(account, secret) {
+                Ok(()) => return Ok(IN_SECRET_BACKEND.to_string()),
+                Err(e) => warn!(
+                    "Failed to store {} in the secret backend, encrypting it instead: {}",
+                    account, e
+                ),
+            },
+            None => {}
+        }
+        self.encrypt_password(secret)
+    }
 
-        let cipher = Aes256Gcm::new_from_slice(&self.encryption_key)
-            .expect("Invalid key length");
+    /// Reads back a secret saved by `seal_secret`, failing when the secret backend no longer
+    /// has it.
+    fn open_secret(&self, account: &str, stored: &str) -> SqlResult<String> {
+        if stored != IN_SECRET_BACKEND {
+            return self.decrypt_password(stored);
+        }
+        let secret = match &self.secrets {
+            Some(secrets) => secrets.get(account),
+            None => Err("no secret backend is available".to_string()),
+        };
+        let reason = match secret {
+            Ok(Some(secret)) => return Ok(secret),
+            Ok(None) => None,
+            Err(e) => Some(e),
+        };
+        // An empty password in its place would be sent to the server, or saved over the
+        // real one
+        Err(rusqlite::Error::ToSqlConversionFailure(Box::new(
+            SecretUnavailable {
+                account: account.to_string(),
+                reason,
+            },
+        )))
+    }
 
-        // Generate random nonce
-        let mut nonce_bytes = [0u8; NONCE_LENGTH];
-        OsRng.fill_bytes(&mut nonce_bytes);
-        let nonce = Nonce::from_slice(&nonce_bytes);
+    fn forget_secret(&self, account: &str) {
+        if let Some(secrets) = &self.secrets {
+            if let Err(e) = secrets.delete(account) {
+                warn!(
+                    "Failed to remove {} from the secret backend: {}",
+                    account, e
+                );
+            }
+        }
+    }
 
-        // Encrypt
-        let ciphertext = cipher
-            .encrypt(nonce, password.as_bytes())
-            .expect("Encryption failed");
+    /// Removes the secrets `seal_secret` left for removal once the values replacing them are
+    /// saved.
+    fn forget_stale_secrets(&self, stale: Vec<String>) {
+        for account in stale {
+            self.forget_secret(&account);
+        }
+    }
 
-        // Combine nonce and ciphertext
-        let mut combined = Vec::with_capacity(NONCE_LENGTH + ciphertext.len());
-        combined.extend_from_slice(&nonce_bytes);
-        combined.extend_from_slice(&ciphertext);
+    /// Removes every secret of a purged connection from the secret backend.
+    fn forget_secrets(&self, id: &str) {
+        self.forget_secret(&password_account(id));
+        self.forget_secret(&ssh_password_account(id));
+        self.forget_secret(&ssh_key_passphrase_account(id));
+    }
 
-        general_purpose::STANDARD.encode(&combined)
+    /// Encrypts a password using AES-256-GCM.
+    ///
+    /// Returns a base64-encoded string containing: nonce || ciphertext
+    fn encrypt_password(&self, password: &str) -> SqlResult<String> {
+        Ok(encrypt_with(&self.key()?, password))
     }
 
     /// Decrypts a password encrypted with AES-256-GCM.
     ///
     /// Falls back to base64 decoding for backwards compatibility with old data.
-    fn decrypt_password(&self, encrypted: &str) -> String {
+    fn decrypt_password(&self, encrypted: &str) -> SqlResult<String> {
         use base64::{engine::general_purpose, Engine as _};
 
         let combined = match general_purpose::STANDARD.decode(encrypted) {
             Ok(data) => data,
-            Err(_) => return encrypted.to_string(),
+            Err(_) => return Ok(encrypted.to_string()),
         };
 
         // Check if this looks like old base64-only encoded password
@@ -266,23 +1220,449 @@ impl ConnectionsStore {
         if combined.len() < NONCE_LENGTH + 16 {
             // 16 is minimum ciphertext size with auth tag
             // Try to interpret as plain base64 (backwards compatibility)
-            return String::from_utf8_lossy(&combined).to_string();
+            return Ok(String::from_utf8_lossy(&combined).to_string());
+        }
+
+        match decrypt_with(&self.key()?, &combined) {
+            Ok(plaintext) => Ok(String::from_utf8_lossy(&plaintext).to_string()),
+            // Decryption failed, might be old format - try base64 decode
+            Err(_) => Ok(String::from_utf8_lossy(&combined).to_string()),
+        }
+    }
+}
+
+/// Encrypts `plaintext` with AES-256-GCM under a random nonce, returning base64 of
+/// nonce || ciphertext.
+fn encrypt_with(key: &[u8; KEY_LENGTH], plaintext: &str) -> String {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let cipher = Aes256Gcm::new_from_slice(key).expect("Invalid key length");
+
+    // Generate random nonce
+    let mut nonce_bytes = [0u8; NONCE_LENGTH];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    // Encrypt
+    let ciphertext = cipher
+        .encrypt(nonce, plaintext.as_bytes())
+        .expect("Encryption failed");
+
+    // Combine nonce and ciphertext
+    let mut combined = Vec::with_capacity(NONCE_LENGTH + ciphertext.len());
+    combined.extend_from_slice(&nonce_bytes);
+    combined.extend_from_slice(&ciphertext);
+
+    general_purpose::STANDARD.encode(&combined)
+}
+
+/// Decrypts nonce || ciphertext from `encrypt_with`; fails unless it was encrypted with `key`.
+fn decrypt_with(key: &[u8; KEY_LENGTH], combined: &[u8]) -> Result<Vec<u8>, aes_gcm::Error> {
+    if combined.len() < NONCE_LENGTH {
+        return Err(aes_gcm::Error);
+    }
+    let cipher = Aes256Gcm::new_from_slice(key).expect("Invalid key length");
+    let nonce = Nonce::from_slice(&combined[..NONCE_LENGTH]);
+    cipher.decrypt(nonce, &combined[NONCE_LENGTH..])
+}
+
+/// Trims tags, drops empty ones and removes case-insensitive duplicates, keeping first spelling.
+/// Returns the id naming this data directory in the OS credential store, generating it on
+/// first use. It lives in the database so it stays the same when the directory moves.
+fn install_id(db: &Connection) -> SqlResult<String> {
+    db.execute(
+        "CREATE TABLE IF NOT EXISTS install (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            install_id TEXT NOT NULL
+        )",
+        [],
+    )?;
+    db.execute(
+        "INSERT OR IGNORE INTO install (id, install_id) VALUES (1, ?1)",
+        params![Uuid::new_v4().to_string()],
+    )?;
+    db.query_row("SELECT install_id FROM install WHERE id = 1", [], |row| {
+        row.get(0)
+    })
+}
+
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut result: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !result.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            result.push(tag.to_string());
+        }
+    }
+    result
+}
+
+/// Escapes `%`, `_` and the escape character itself for use in a `LIKE ... ESCAPE '\'` pattern.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store() -> ConnectionsStore {
+        let path = std::env::temp_dir().join(format!("bloatsql-test-{}.db", Uuid::new_v4()));
+        ConnectionsStore::new(path, DataDirSource::Default).expect("Failed to create test store")
+    }
+
+    #[test]
+    fn test_install_id_stays_when_database_moves() {
+        let path = std::env::temp_dir().join(format!("bloatsql-test-{}.db", Uuid::new_v4()));
+        let id = install_id(&Connection::open(&path).unwrap()).unwrap();
+        assert_eq!(install_id(&Connection::open(&path).unwrap()).unwrap(), id);
+
+        let moved = path.with_extension("moved.db");
+        std::fs::rename(&path, &moved).unwrap();
+        assert_eq!(install_id(&Connection::open(&moved).unwrap()).unwrap(), id);
+        let _ = std::fs::remove_file(&moved);
+    }
+
+    fn group(name: &str, parent_id: Option<&str>) -> ConnectionGroup {
+        ConnectionGroup {
+            id: String::new(),
+            name: name.to_string(),
+            parent_id: parent_id.map(str::to_string),
+            sort_order: 0,
+        }
+    }
+
+    fn connection(name: &str, host: &str, tags: &[&str]) -> StoredConnection {
+        StoredConnection {
+            id: String::new(),
+            name: name.to_string(),
+            db_type: "postgresql".to_string(),
+            host: host.to_string(),
+            port: 5432,
+            username: "postgres".to_string(),
+            password_encrypted: "secret".to_string(),
+            database: "postgres".to_string(),
+            ssl_mode: "disabled".to_string(),
+            ssl_ca_cert: None,
+            pool_min_size: None,
+            pool_max_size: None,
+            group_id: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            last_used_at: None,
+            use_count: 0,
+            preferences: ConnectionPreferences::default(),
+            ssh: None,
+            rds_iam: None,
+            deleted_at: None,
         }
+    }
+
+    #[test]
+    fn test_search_connections_by_name_host_and_tag() {
+        let store = temp_store();
+        store
+            .save_connection(connection("Billing", "db1.acme.io", &["prod", "Acme"]))
+            .unwrap();
+        store
+            .save_connection(connection(
+                "Reports",
+                "10.0.0.5",
+                &["staging", " acme ", ""],
+            ))
+            .unwrap();
+        store
+            .save_connection(connection("Scratch_pad", "localhost", &[]))
+            .unwrap();
 
-        let cipher = match Aes256Gcm::new_from_slice(&self.encryption_key) {
-            Ok(c) => c,
-            Err(_) => return encrypted.to_string(),
+        let names = |query: &str| -> Vec<String> {
+            store
+                .search_connections(query)
+                .unwrap()
+                .into_iter()
+                .map(|c| c.name)
+                .collect()
         };
 
-        let nonce = Nonce::from_slice(&combined[..NONCE_LENGTH]);
-        let ciphertext = &combined[NONCE_LENGTH..];
+        assert_eq!(names(""), vec!["Billing", "Reports", "Scratch_pad"]);
+        assert_eq!(names("acme"), vec!["Billing", "Reports"]);
+        assert_eq!(names("acme PROD"), vec!["Billing"]);
+        assert_eq!(names("10.0"), vec!["Reports"]);
+        assert_eq!(names("h_p"), vec!["Scratch_pad"]);
+        assert_eq!(names("s_r"), Vec::<String>::new());
 
-        match cipher.decrypt(nonce, ciphertext) {
-            Ok(plaintext) => String::from_utf8_lossy(&plaintext).to_string(),
-            Err(_) => {
-                // Decryption failed, might be old format - try base64 decode
-                String::from_utf8_lossy(&combined).to_string()
-            }
+        let reports = store.search_connections("reports").unwrap();
+        assert_eq!(reports[0].tags, vec!["acme", "staging"]);
+    }
+
+    #[test]
+    fn test_ssh_secrets_are_encrypted_at_rest() {
+        let store = temp_store();
+        let mut conn = connection("Bastion", "10.0.0.5", &[]);
+        conn.ssh = Some(SshConfig {
+            host: "bastion.acme.io".to_string(),
+            port: 22,
+            username: "deploy".to_string(),
+            auth_method: SshAuthMethod::PrivateKey,
+            private_key_path: Some("/home/deploy/.ssh/id_ed25519".to_string()),
+            key_passphrase: Some("hunter2".to_string()),
+            ..Default::default()
+        });
+        let saved = store.save_connection(conn).unwrap();
+
+        let raw: String = store
+            .db
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT ssh_config FROM connections WHERE id = ?",
+                params![&saved.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!raw.contains("hunter2"));
+
+        assert!(store.trust_ssh_host_key(&saved.id, "SHA256:abc").unwrap());
+        let ssh = store
+            .get_connection(&saved.id)
+            .unwrap()
+            .unwrap()
+            .ssh
+            .unwrap();
+        assert_eq!(ssh.key_passphrase.as_deref(), Some("hunter2"));
+        assert_eq!(ssh.known_host_key.as_deref(), Some("SHA256:abc"));
+    }
+
+    /// Secrets kept in a map shared between clones, so tests can look inside.
+    #[derive(Clone, Default)]
+    struct MemorySecrets(std::sync::Arc<Mutex<std::collections::HashMap<String, String>>>);
+
+    impl SecretBackend for MemorySecrets {
+        fn set(&self, account: &str, secret: &str) -> Result<(), String> {
+            let mut secrets = self.0.lock().unwrap();
+            secrets.insert(account.to_string(), secret.to_string());
+            Ok(())
         }
+
+        fn get(&self, account: &str) -> Result<Option<String>, String> {
+            Ok(self.0.lock().unwrap().get(account).cloned())
+        }
+
+        fn delete(&self, account: &str) -> Result<(), String> {
+            self.0.lock().unwrap().remove(account);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_encrypted_secrets_move_to_secret_backend() {
+        let path = std::env::temp_dir().join(format!("bloatsql-test-{}.db", Uuid::new_v4()));
+        let mut conn = connection("Bastion", "10.0.0.5", &[]);
+        conn.ssh = Some(SshConfig {
+            host: "bastion.acme.io".to_string(),
+            username: "deploy".to_string(),
+            password: Some("hunter2".to_string()),
+            ..Default::default()
+        });
+        let id = ConnectionsStore::with_secret_backend(path.clone(), None)
+            .unwrap()
+            .save_connection(conn)
+            .unwrap()
+            .id;
+
+        let secrets = MemorySecrets::default();
+        let store =
+            ConnectionsStore::with_secret_backend(path, Some(Box::new(secrets.clone()))).unwrap();
+        let raw: String = store
+            .db
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT password_encrypted FROM connections WHERE id = ?",
+                params![&id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(raw, IN_SECRET_BACKEND);
+        assert_eq!(
+            secrets.get(&password_account(&id)).unwrap().as_deref(),
+            Some("secret")
+        );
+
+        let saved = store.get_connection(&id).unwrap().unwrap();
+        assert_eq!(saved.password_encrypted, "secret");
+        assert_eq!(saved.ssh.unwrap().password.as_deref(), Some("hunter2"));
+
+        assert!(store.delete_connection(&id).unwrap());
+        assert!(store.purge_connection(&id).unwrap());
+        assert!(secrets.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_master_password_locks_secrets() {
+        let path = std::env::temp_dir().join(format!("bloatsql-test-{}.db", Uuid::new_v4()));
+        let secrets = MemorySecrets::default();
+        let store =
+            ConnectionsStore::with_secret_backend(path.clone(), Some(Box::new(secrets.clone())))
+                .unwrap();
+        let id = store
+            .save_connection(connection("Billing", "db1.acme.io", &[]))
+            .unwrap()
+            .id;
+        assert!(!store.lock());
+
+        store.set_master_password(Some("correct horse")).unwrap();
+        assert!(secrets.0.lock().unwrap().is_empty());
+        assert!(store.lock());
+        let err = store.get_connection(&id).unwrap_err();
+        assert!(err.to_string().contains("locked"));
+        assert!(store.get_all_connections(ConnectionSort::Created).is_err());
+
+        // Reopening starts locked, without the key from `key_store`
+        let store =
+            ConnectionsStore::with_secret_backend(path, Some(Box::new(secrets.clone()))).unwrap();
+        assert!(store.has_master_password() && store.is_locked());
+        assert!(!store.unlock("battery staple").unwrap());
+        assert!(store.unlock("correct horse").unwrap());
+        let saved = store.get_connection(&id).unwrap().unwrap();
+        assert_eq!(saved.password_encrypted, "secret");
+
+        store.set_master_password(None).unwrap();
+        assert!(!store.has_master_password() && !store.lock());
+        assert_eq!(
+            secrets.get(&password_account(&id)).unwrap().as_deref(),
+            Some("secret")
+        );
+    }
+
+    #[test]
+    fn test_failed_master_password_change_keeps_secrets() {
+        let path = std::env::temp_dir().join(format!("bloatsql-test-{}.db", Uuid::new_v4()));
+        let secrets = MemorySecrets::default();
+        let store =
+            ConnectionsStore::with_secret_backend(path, Some(Box::new(secrets.clone()))).unwrap();
+        let mut conn = connection("Bastion", "10.0.0.5", &[]);
+        conn.ssh = Some(SshConfig {
+            host: "bastion.acme.io".to_string(),
+            username: "deploy".to_string(),
+            password: Some("hunter2".to_string()),
+            ..Default::default()
+        });
+        let id = store.save_connection(conn).unwrap().id;
+
+        // Saving the master password fails after every secret was sealed again
+        store
+            .db
+            .lock()
+            .unwrap()
+            .execute_batch(
+                "CREATE TRIGGER fail_master_password BEFORE INSERT ON master_password
+                 BEGIN SELECT RAISE(ABORT, 'disk I/O error'); END",
+            )
+            .unwrap();
+        assert!(store.set_master_password(Some("correct horse")).is_err());
+        assert!(!store.has_master_password());
+
+        assert_eq!(secrets.0.lock().unwrap().len(), 2);
+        let saved = store.get_connection(&id).unwrap().unwrap();
+        assert_eq!(saved.password_encrypted, "secret");
+        assert_eq!(saved.ssh.unwrap().password.as_deref(), Some("hunter2"));
+    }
+
+    #[test]
+    fn test_missing_backend_secret_is_an_error() {
+        let path = std::env::temp_dir().join(format!("bloatsql-test-{}.db", Uuid::new_v4()));
+        let secrets = MemorySecrets::default();
+        let store =
+            ConnectionsStore::with_secret_backend(path, Some(Box::new(secrets.clone()))).unwrap();
+        let id = store
+            .save_connection(connection("Billing", "db1.acme.io", &[]))
+            .unwrap()
+            .id;
+
+        secrets.delete(&password_account(&id)).unwrap();
+        let err = store.get_connection(&id).unwrap_err();
+        assert!(err.to_string().contains("missing from the secret backend"));
+        assert!(store.set_master_password(Some("correct horse")).is_err());
+    }
+
+    #[test]
+    fn test_environment_is_saved_with_preferences() {
+        let store = temp_store();
+        let mut conn = connection("Billing", "db1.acme.io", &[]);
+        conn.preferences.environment = Some(ConnectionEnvironment::Production);
+        conn.preferences.color = Some("#e03131".to_string());
+        let saved = store.save_connection(conn).unwrap();
+
+        let loaded = store.get_connection(&saved.id).unwrap().unwrap();
+        assert_eq!(
+            loaded.preferences.environment,
+            Some(ConnectionEnvironment::Production)
+        );
+        assert_eq!(loaded.preferences.color.as_deref(), Some("#e03131"));
+        let json = serde_json::to_value(&loaded.preferences).unwrap();
+        assert_eq!(json["environment"], "production");
+    }
+
+    #[test]
+    fn test_group_descendant_detection() {
+        let store = temp_store();
+        let root = store.save_group(group("Clients", None)).unwrap();
+        let child = store.save_group(group("Acme", Some(&root.id))).unwrap();
+        let grandchild = store.save_group(group("Prod", Some(&child.id))).unwrap();
+
+        assert!(store.is_group_descendant(&root.id, &grandchild.id).unwrap());
+        assert!(store.is_group_descendant(&root.id, &root.id).unwrap());
+        assert!(!store.is_group_descendant(&grandchild.id, &root.id).unwrap());
+    }
+
+    #[test]
+    fn test_delete_group_reparents_children() {
+        let store = temp_store();
+        let root = store.save_group(group("Clients", None)).unwrap();
+        let child = store.save_group(group("Acme", Some(&root.id))).unwrap();
+        let grandchild = store.save_group(group("Prod", Some(&child.id))).unwrap();
+
+        assert!(store.delete_group(&child.id).unwrap());
+        assert!(!store.delete_group(&child.id).unwrap());
+
+        let groups = store.get_all_groups().unwrap();
+        let moved = groups.iter().find(|g| g.id == grandchild.id).unwrap();
+        assert_eq!(moved.parent_id.as_deref(), Some(root.id.as_str()));
+    }
+
+    #[test]
+    fn test_deleted_connections_can_be_restored_or_purged() {
+        let store = temp_store();
+        let kept = store
+            .save_connection(connection("Staging", "staging.internal", &["stage"]))
+            .unwrap();
+        let purged = store
+            .save_connection(connection("Scratch", "localhost", &["tmp"]))
+            .unwrap();
+
+        assert!(store.delete_connection(&kept.id).unwrap());
+        assert!(store.delete_connection(&purged.id).unwrap());
+        assert!(store
+            .get_all_connections(ConnectionSort::Created)
+            .unwrap()
+            .is_empty());
+        assert!(store.search_connections("stag").unwrap().is_empty());
+        assert!(store.get_all_tags().unwrap().is_empty());
+        assert_eq!(store.get_deleted_connections().unwrap().len(), 2);
+
+        assert!(store.restore_connection(&kept.id).unwrap());
+        assert!(store.purge_connection(&purged.id).unwrap());
+        assert!(!store.purge_connection(&kept.id).unwrap());
+
+        let active = store.get_all_connections(ConnectionSort::Created).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].tags, vec!["stage"]);
+        assert!(store.get_deleted_connections().unwrap().is_empty());
+        assert_eq!(
+            store.get_connection(&purged.id).unwrap().map(|c| c.id),
+            None
+        );
     }
 }
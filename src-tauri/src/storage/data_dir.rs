@@ -0,0 +1,125 @@
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File that, when placed next to the executable, switches the app to portable mode.
+const PORTABLE_MARKER: &str = "portable";
+
+/// Command-line flag that switches the app to portable mode.
+const PORTABLE_FLAG: &str = "--portable";
+
+/// Directory next to the executable that holds app data in portable mode.
+const PORTABLE_DATA_DIR: &str = "data";
+
+/// Environment variable that overrides the data directory.
+pub const DATA_DIR_ENV: &str = "BLOATSQL_DATA_DIR";
+
+/// File in the OS app-data dir holding a user-chosen data directory.
+const OVERRIDE_FILE: &str = "data_dir";
+
+/// Why a data directory was chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataDirSource {
+    /// The OS app-data dir.
+    Default,
+    /// Next to the executable (portable mode).
+    Portable,
+    /// The `BLOATSQL_DATA_DIR` environment variable.
+    Environment,
+    /// The override saved with `set_override`.
+    Setting,
+}
+
+impl DataDirSource {
+    /// Whether the encryption key stays in the data directory's key file rather than moving
+    /// to the OS credential store. Only the OS app-data dir belongs to this machine; the
+    /// others may be carried elsewhere and must bring their key with them.
+    pub fn keeps_key_file(self) -> bool {
+        self != DataDirSource::Default
+    }
+}
+
+/// Where the app keeps its connections database, encryption key and other state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DataDir {
+    pub path: PathBuf,
+    pub source: DataDirSource,
+}
+
+/// Resolves the data directory.
+///
+/// In order of precedence:
+/// 1. the `BLOATSQL_DATA_DIR` environment variable, e.g. for pointing tests at a scratch dir;
+/// 2. portable mode, enabled by the `--portable` flag or a `portable` marker file next to the
+///    executable, which stores data in a `data` directory beside it;
+/// 3. the override saved in the OS app-data dir (see `set_override`), e.g. for roaming profiles;
+/// 4. `app_data_dir` itself.
+pub fn resolve(app_data_dir: PathBuf) -> DataDir {
+    if let Some(path) = std::env::var_os(DATA_DIR_ENV).filter(|p| !p.is_empty()) {
+        return DataDir {
+            path: PathBuf::from(path),
+            source: DataDirSource::Environment,
+        };
+    }
+
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+
+    if let Some(exe_dir) = exe_dir {
+        let flagged = std::env::args().skip(1).any(|arg| arg == PORTABLE_FLAG);
+        if flagged || exe_dir.join(PORTABLE_MARKER).exists() {
+            return DataDir {
+                path: exe_dir.join(PORTABLE_DATA_DIR),
+                source: DataDirSource::Portable,
+            };
+        }
+    }
+
+    if let Some(path) = read_override(&app_data_dir) {
+        return DataDir {
+            path,
+            source: DataDirSource::Setting,
+        };
+    }
+
+    DataDir {
+        path: app_data_dir,
+        source: DataDirSource::Default,
+    }
+}
+
+fn read_override(app_data_dir: &Path) -> Option<PathBuf> {
+    let content = std::fs::read_to_string(app_data_dir.join(OVERRIDE_FILE)).ok()?;
+    let path = content.trim();
+    if path.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
+/// Saves (or with `None`, clears) the data directory override. Takes effect on next launch.
+///
+/// The override is stored in the OS app-data dir since it has to be found before the data
+/// directory is known.
+pub fn set_override(app_data_dir: &Path, path: Option<&Path>) -> io::Result<()> {
+    let override_file = app_data_dir.join(OVERRIDE_FILE);
+    match path {
+        Some(path) => {
+            if !path.is_absolute() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Data directory must be an absolute path",
+                ));
+            }
+            std::fs::create_dir_all(app_data_dir)?;
+            std::fs::write(override_file, path.to_string_lossy().as_bytes())
+        }
+        None => match std::fs::remove_file(override_file) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        },
+    }
+}
@@ -0,0 +1,89 @@
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Named export settings, so an export configured in the UI can be repeated from the
+/// command line.
+///
+/// The options are stored as JSON, like `AppSettings`, so the export options can change
+/// without schema migrations.
+pub struct ExportProfileStore {
+    db: Mutex<Connection>,
+}
+
+impl ExportProfileStore {
+    pub fn new(db_path: PathBuf) -> SqlResult<Self> {
+        let db = Connection::open(&db_path)?;
+        // Shares the database file with `ConnectionsStore`
+        db.busy_timeout(Duration::from_secs(5))?;
+        let store = ExportProfileStore { db: Mutex::new(db) };
+        store.init_tables()?;
+        Ok(store)
+    }
+
+    fn init_tables(&self) -> SqlResult<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS export_profiles (
+                name TEXT PRIMARY KEY,
+                options TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Saves `options` under `name`, replacing a profile with the same name.
+    pub fn save<T: Serialize>(&self, name: &str, options: &T) -> SqlResult<()> {
+        let value = serde_json::to_string(options)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO export_profiles (name, options) VALUES (?, ?)
+             ON CONFLICT(name) DO UPDATE SET options = excluded.options",
+            params![name, value],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the options saved under `name`. Profiles that no longer deserialize are
+    /// treated as missing.
+    pub fn get<T: DeserializeOwned>(&self, name: &str) -> SqlResult<Option<T>> {
+        let db = self.db.lock().unwrap();
+        let value: Option<String> = db
+            .query_row(
+                "SELECT options FROM export_profiles WHERE name = ?",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value.and_then(|value| serde_json::from_str(&value).ok()))
+    }
+
+    /// Returns all profiles by name, skipping those that no longer deserialize.
+    pub fn get_all<T: DeserializeOwned>(&self) -> SqlResult<Vec<(String, T)>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare("SELECT name, options FROM export_profiles ORDER BY name")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut profiles = Vec::new();
+        for row in rows {
+            let (name, value) = row?;
+            if let Ok(options) = serde_json::from_str(&value) {
+                profiles.push((name, options));
+            }
+        }
+        Ok(profiles)
+    }
+
+    pub fn delete(&self, name: &str) -> SqlResult<bool> {
+        let db = self.db.lock().unwrap();
+        let deleted = db.execute("DELETE FROM export_profiles WHERE name = ?", params![name])?;
+        Ok(deleted > 0)
+    }
+}
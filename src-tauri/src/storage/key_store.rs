@@ -0,0 +1,341 @@
+//! Storage for the key that encrypts saved passwords.
+//!
+//! The key is kept in the OS credential store: the Keychain on macOS, the Credential Manager
+//! (protected by DPAPI) on Windows and the Secret Service on Linux. Its entry is named after
+//! the install id of the data directory, which stays the same wherever the directory is.
+//! Older versions kept the key in a `.key` file next to the database. That file is moved into
+//! the credential store on first start, and removed only once the key reads back from there,
+//! so passwords encrypted with it stay readable.
+//!
+//! Portable and relocated data directories keep the key file, since they are meant to be
+//! taken to other machines, as do setups without a credential store.
+
+use aes_gcm::aead::OsRng;
+use rand::RngCore;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Length of the encryption key in bytes (256 bits for AES-256).
+pub const KEY_LENGTH: usize = 32;
+
+/// Service name the key (and, through `secret_store`, passwords) are stored under in the OS
+/// credential store.
+pub const KEYRING_SERVICE: &str = "bloatSQL";
+
+/// Somewhere outside the data directory to keep the key, looked up by account name.
+pub trait KeyBackend: Send + Sync {
+    /// Stores `key` under `account`, replacing any earlier one.
+    fn set(&self, account: &str, key: &[u8]) -> Result<(), String>;
+
+    /// Returns the key stored under `account`, if there is one.
+    fn get(&self, account: &str) -> Result<Option<Vec<u8>>, String>;
+
+    /// Removes the key stored under `account`; removing a missing one is not an error.
+    fn delete(&self, account: &str) -> Result<(), String>;
+}
+
+/// Keys in the OS credential store, one entry per account.
+pub struct OsKeyBackend;
+
+impl OsKeyBackend {
+    fn entry(account: &str) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(KEYRING_SERVICE, account).map_err(|e| e.to_string())
+    }
+}
+
+impl KeyBackend for OsKeyBackend {
+    fn set(&self, account: &str, key: &[u8]) -> Result<(), String> {
+        Self::entry(account)?
+            .set_secret(key)
+            .map_err(|e| e.to_string())
+    }
+
+    fn get(&self, account: &str) -> Result<Option<Vec<u8>>, String> {
+        match Self::entry(account)?.get_secret() {
+            Ok(key) => Ok(Some(key)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn delete(&self, account: &str) -> Result<(), String> {
+        match Self::entry(account)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// Where the encryption key of a data directory is kept.
+pub struct KeyLocation {
+    /// The key file, used when the key can't be or isn't kept in `backend`.
+    pub key_path: PathBuf,
+    /// Names the key's entry in `backend` (see `ConnectionsStore`).
+    pub install_id: String,
+    /// The OS credential store; `None` without one.
+    pub backend: Option<Box<dyn KeyBackend>>,
+    /// Keeps the key in its file rather than in `backend`, for portable and relocated data
+    /// directories.
+    pub keep_file: bool,
+}
+
+impl KeyLocation {
+    /// Only the key file at `key_path`.
+    pub fn file(key_path: PathBuf) -> Self {
+        KeyLocation {
+            key_path,
+            install_id: String::new(),
+            backend: None,
+            keep_file: true,
+        }
+    }
+
+    /// Loads the key, moving a key file into the backend (or, with `keep_file`, a key an
+    /// older version moved there back into its file), or generates a new key.
+    pub fn load_or_generate_key(&self) -> [u8; KEY_LENGTH] {
+        let account = format!("{}#encryption-key", self.install_id);
+        // Versions before install ids named the entry after the key file's path, which
+        // changes when the data directory moves
+        let legacy_account = self.key_path.to_string_lossy().into_owned();
+
+        let mut backend = self.backend.as_deref();
+        if let (Some(store), false) = (backend, self.keep_file) {
+            match read_key(store, &account) {
+                Ok(Some(key)) => return key,
+                Ok(None) => {}
+                Err(e) => {
+                    // Leave the stored key alone; it may be readable again next time
+                    warn!(
+                        "Failed to read the OS credential store, using the key file: {}",
+                        e
+                    );
+                    backend = None;
+                }
+            }
+        }
+
+        let file_key = read_key_file(&self.key_path);
+        let legacy_key = match (file_key, backend) {
+            (None, Some(store)) => read_key(store, &legacy_account).unwrap_or_else(|e| {
+                warn!("Failed to read the OS credential store: {}", e);
+                None
+            }),
+            _ => None,
+        };
+        let key = file_key.or(legacy_key).unwrap_or_else(|| {
+            let mut key = [0u8; KEY_LENGTH];
+            OsRng.fill_bytes(&mut key);
+            key
+        });
+
+        if let (Some(store), false) = (backend, self.keep_file) {
+            if save_key(store, &account, &key) {
+                if file_key.is_some() {
+                    match std::fs::remove_file(&self.key_path) {
+                        Ok(()) => debug!("Moved encryption key to the OS credential store"),
+                        Err(e) => warn!("Failed to remove migrated key file: {}", e),
+                    }
+                }
+                if legacy_key.is_some() {
+                    forget_key(store, &legacy_account);
+                }
+                return key;
+            }
+        }
+
+        if file_key.is_none() {
+            write_key_file(&self.key_path, &key);
+            if let (Some(store), Some(_)) = (backend, legacy_key) {
+                if read_key_file(&self.key_path) == Some(key) {
+                    debug!("Moved encryption key back to the key file");
+                    forget_key(store, &legacy_account);
+                }
+            }
+        }
+        key
+    }
+}
+
+/// Reads the key stored under `account`; an invalid one reads as missing, to be replaced.
+fn read_key(store: &dyn KeyBackend, account: &str) -> Result<Option<[u8; KEY_LENGTH]>, String> {
+    Ok(store.get(account)?.and_then(|secret| {
+        let key = <[u8; KEY_LENGTH]>::try_from(secret.as_slice()).ok();
+        if key.is_none() {
+            warn!("Invalid key in the OS credential store, replacing it");
+        }
+        key
+    }))
+}
+
+/// Stores the key under `account` and returns whether it reads back unchanged.
+fn save_key(store: &dyn KeyBackend, account: &str, key: &[u8; KEY_LENGTH]) -> bool {
+    if let Err(e) = store.set(account, key) {
+        warn!("Failed to save key in the OS credential store: {}", e);
+        return false;
+    }
+    match read_key(store, account) {
+        Ok(Some(stored)) if stored == *key => true,
+        Ok(_) => {
+            warn!(
+                "The key saved in the OS credential store doesn't read back, keeping the key file"
+            );
+            false
+        }
+        Err(e) => {
+            warn!(
+                "Failed to read back the key from the OS credential store, keeping the key file: {}",
+                e
+            );
+            false
+        }
+    }
+}
+
+fn forget_key(store: &dyn KeyBackend, account: &str) {
+    if let Err(e) = store.delete(account) {
+        warn!(
+            "Failed to remove the old key from the OS credential store: {}",
+            e
+        );
+    }
+}
+
+fn read_key_file(key_path: &Path) -> Option<[u8; KEY_LENGTH]> {
+    if !key_path.exists() {
+        return None;
+    }
+    match std::fs::read(key_path) {
+        Ok(key_data) if key_data.len() == KEY_LENGTH => {
+            let mut key = [0u8; KEY_LENGTH];
+            key.copy_from_slice(&key_data);
+            Some(key)
+        }
+        _ => {
+            warn!("Invalid key file, generating new key");
+            None
+        }
+    }
+}
+
+/// Saves the key to a file (with restrictive permissions on Unix).
+fn write_key_file(key_path: &Path, key: &[u8; KEY_LENGTH]) {
+    if let Err(e) = std::fs::write(key_path, key) {
+        warn!("Failed to save encryption key: {}", e);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = std::fs::metadata(key_path) {
+            let mut perms = metadata.permissions();
+            perms.set_mode(0o600);
+            let _ = std::fs::set_permissions(key_path, perms);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    /// Keys kept in a map shared between clones, so tests can look inside. With `lossy` set,
+    /// keys are accepted but not kept, like a credential store that fails silently.
+    #[derive(Clone, Default)]
+    struct MemoryKeys {
+        keys: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+        lossy: bool,
+    }
+
+    impl KeyBackend for MemoryKeys {
+        fn set(&self, account: &str, key: &[u8]) -> Result<(), String> {
+            if !self.lossy {
+                let mut keys = self.keys.lock().unwrap();
+                keys.insert(account.to_string(), key.to_vec());
+            }
+            Ok(())
+        }
+
+        fn get(&self, account: &str) -> Result<Option<Vec<u8>>, String> {
+            Ok(self.keys.lock().unwrap().get(account).cloned())
+        }
+
+        fn delete(&self, account: &str) -> Result<(), String> {
+            self.keys.lock().unwrap().remove(account);
+            Ok(())
+        }
+    }
+
+    fn location(keys: &MemoryKeys, keep_file: bool) -> KeyLocation {
+        KeyLocation {
+            key_path: std::env::temp_dir().join(format!("bloatsql-test-{}.key", Uuid::new_v4())),
+            install_id: "7c9e6679-7425-40de-944b-e07fc1f90ae7".to_string(),
+            backend: Some(Box::new(keys.clone())),
+            keep_file,
+        }
+    }
+
+    const ACCOUNT: &str = "7c9e6679-7425-40de-944b-e07fc1f90ae7#encryption-key";
+
+    #[test]
+    fn test_key_file_moves_to_backend() {
+        let keys = MemoryKeys::default();
+        let location = location(&keys, false);
+        write_key_file(&location.key_path, &[7; KEY_LENGTH]);
+
+        assert_eq!(location.load_or_generate_key(), [7; KEY_LENGTH]);
+        assert_eq!(keys.get(ACCOUNT).unwrap(), Some(vec![7; KEY_LENGTH]));
+        assert!(!location.key_path.exists());
+        assert_eq!(location.load_or_generate_key(), [7; KEY_LENGTH]);
+    }
+
+    #[test]
+    fn test_key_file_stays_when_backend_loses_key() {
+        let keys = MemoryKeys {
+            lossy: true,
+            ..Default::default()
+        };
+        let location = location(&keys, false);
+        write_key_file(&location.key_path, &[7; KEY_LENGTH]);
+
+        assert_eq!(location.load_or_generate_key(), [7; KEY_LENGTH]);
+        assert_eq!(read_key_file(&location.key_path), Some([7; KEY_LENGTH]));
+        assert_eq!(location.load_or_generate_key(), [7; KEY_LENGTH]);
+        let _ = std::fs::remove_file(&location.key_path);
+    }
+
+    #[test]
+    fn test_key_under_path_moves_to_install_id() {
+        let keys = MemoryKeys::default();
+        let location = location(&keys, false);
+        let legacy_account = location.key_path.to_string_lossy().into_owned();
+        keys.set(&legacy_account, &[7; KEY_LENGTH]).unwrap();
+
+        assert_eq!(location.load_or_generate_key(), [7; KEY_LENGTH]);
+        assert_eq!(keys.get(ACCOUNT).unwrap(), Some(vec![7; KEY_LENGTH]));
+        assert_eq!(keys.get(&legacy_account).unwrap(), None);
+        assert!(!location.key_path.exists());
+    }
+
+    #[test]
+    fn test_portable_data_dir_keeps_key_file() {
+        let keys = MemoryKeys::default();
+        let location = location(&keys, true);
+        write_key_file(&location.key_path, &[7; KEY_LENGTH]);
+
+        assert_eq!(location.load_or_generate_key(), [7; KEY_LENGTH]);
+        assert_eq!(read_key_file(&location.key_path), Some([7; KEY_LENGTH]));
+        assert!(keys.keys.lock().unwrap().is_empty());
+
+        // A key an older version moved out of the portable directory comes back to it
+        std::fs::remove_file(&location.key_path).unwrap();
+        let legacy_account = location.key_path.to_string_lossy().into_owned();
+        keys.set(&legacy_account, &[8; KEY_LENGTH]).unwrap();
+        assert_eq!(location.load_or_generate_key(), [8; KEY_LENGTH]);
+        assert_eq!(read_key_file(&location.key_path), Some([8; KEY_LENGTH]));
+        assert!(keys.keys.lock().unwrap().is_empty());
+        let _ = std::fs::remove_file(&location.key_path);
+    }
+}
@@ -0,0 +1,60 @@
+//! Optional master password for the connection store.
+//!
+//! With a master password set, the key that encrypts saved passwords is derived from it with
+//! Argon2 instead of being read from `key_store`, so the store has to be unlocked before
+//! passwords can be read or saved. Only the salt and a verifier (a known value encrypted with
+//! the derived key) are kept, in the `master_password` table of `connections.db`.
+
+use super::key_store::KEY_LENGTH;
+use aes_gcm::aead::OsRng;
+use argon2::Argon2;
+use rand::RngCore;
+use std::fmt;
+
+/// Length of the Argon2 salt in bytes.
+pub const SALT_LENGTH: usize = 16;
+
+/// Encrypted with the derived key to check a master password on unlock.
+pub const VERIFIER_PLAINTEXT: &str = "bloatSQL master password";
+
+/// Returned while the store is locked by its master password.
+#[derive(Debug)]
+pub struct StoreLocked;
+
+impl fmt::Display for StoreLocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The connection store is locked; unlock it with the master password")
+    }
+}
+
+impl std::error::Error for StoreLocked {}
+
+/// Generates a random salt for a new master password.
+pub fn generate_salt() -> [u8; SALT_LENGTH] {
+    let mut salt = [0u8; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// Derives the encryption key from a master password with Argon2id.
+pub fn derive_key(password: &str, salt: &[u8]) -> Result<[u8; KEY_LENGTH], String> {
+    let mut key = [0u8; KEY_LENGTH];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key from master password: {}", e))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_key() {
+        let salt = generate_salt();
+        let key = derive_key("correct horse", &salt).unwrap();
+        assert_eq!(key, derive_key("correct horse", &salt).unwrap());
+        assert_ne!(key, derive_key("battery staple", &salt).unwrap());
+        assert_ne!(key, derive_key("correct horse", &generate_salt()).unwrap());
+    }
+}
@@ -1,3 +1,29 @@
+pub mod audit_log;
+pub mod connection_sync;
 pub mod connections_store;
+pub mod data_dir;
+pub mod export_profiles;
+pub mod key_store;
+pub mod master_password;
+pub mod query_documents;
+pub mod query_library;
+pub mod recovery;
+pub mod schema_cache;
+pub mod secret_store;
+pub mod settings;
+pub mod slow_query_log;
 
-pub use connections_store::{ConnectionsStore, StoredConnection};
+pub use audit_log::{AuditEntry, AuditExportFormat, AuditLog, AuditLogFilter};
+pub use connection_sync::ConnectionSync;
+pub use connections_store::{
+    ConnectionEnvironment, ConnectionGroup, ConnectionPreferences, ConnectionSort,
+    ConnectionsStore, RdsIamConfig, SshAuthMethod, SshConfig, StoredConnection,
+};
+pub use data_dir::DataDir;
+pub use export_profiles::ExportProfileStore;
+pub use query_documents::{DocumentRevision, QueryDocument, QueryDocumentStore};
+pub use query_library::{QueryLibrary, SavedQuery};
+pub use recovery::RecoveryStore;
+pub use schema_cache::{cache_kinds, SchemaCache};
+pub use settings::{AppSettings, SettingsStore};
+pub use slow_query_log::{SlowQuery, SlowQueryLog};
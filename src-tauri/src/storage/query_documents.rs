@@ -0,0 +1,368 @@
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Quiet period after the last edit before an autosaved document is written.
+pub const AUTOSAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// Edits within this many seconds of a document's latest revision update that revision
+/// instead of adding one, so history has a revision per editing burst, not per keystroke.
+const REVISION_INTERVAL_SECS: i64 = 300;
+
+/// Revisions kept per document; older ones are dropped.
+const MAX_REVISIONS: i64 = 50;
+
+/// Closed documents are kept this long so they can be reopened.
+const CLOSED_RETENTION_DAYS: i64 = 30;
+
+/// Caret position in the editor, zero-based.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CursorPosition {
+    pub line: u32,
+    pub column: u32,
+}
+
+/// An editor tab's content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryDocument {
+    pub id: String,
+    pub title: String,
+    pub sql: String,
+    /// Stored connection the tab is used with.
+    #[serde(default)]
+    pub connection_id: Option<String>,
+    #[serde(default)]
+    pub cursor: CursorPosition,
+    /// Order among the open tabs.
+    #[serde(default)]
+    pub position: u32,
+    /// RFC 3339 timestamp of the last save; set by the store.
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+/// A past version of a document's SQL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentRevision {
+    pub revision: i64,
+    pub sql: String,
+    /// RFC 3339 timestamp.
+    pub saved_at: String,
+}
+
+/// Query editor tabs, kept across restarts, with the revision history of their SQL.
+///
+/// Unlike `RecoveryStore`, which only holds work lost to a crash, documents persist until
+/// they are deleted: closing a tab keeps it for `CLOSED_RETENTION_DAYS` so it can be
+/// reopened. Autosaves are staged in memory and written after `AUTOSAVE_DELAY` without
+/// further edits.
+pub struct QueryDocumentStore {
+    db: Mutex<Connection>,
+    /// Autosaved documents not yet written, with the generation of their latest edit.
+    pending: Mutex<HashMap<String, (u64, QueryDocument)>>,
+    generation: Mutex<u64>,
+}
+
+impl QueryDocumentStore {
+    pub fn new(db_path: PathBuf) -> SqlResult<Self> {
+        let db = Connection::open(&db_path)?;
+        // Shares the database file with `ConnectionsStore`
+        db.busy_timeout(Duration::from_secs(5))?;
+        let store = QueryDocumentStore {
+            db: Mutex::new(db),
+            pending: Mutex::new(HashMap::new()),
+            generation: Mutex::new(0),
+        };
+        store.init_tables()?;
+        store.purge_closed()?;
+        Ok(store)
+    }
+
+    fn init_tables(&self) -> SqlResult<()> {
+        let db = self.db.lock().unwrap();
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS query_documents (
+                id TEXT PRIMARY KEY,
+                document TEXT NOT NULL,
+                position INTEGER NOT NULL DEFAULT 0,
+                updated_at TEXT NOT NULL,
+                closed_at TEXT
+            );
+            CREATE TABLE IF NOT EXISTS query_document_revisions (
+                document_id TEXT NOT NULL,
+                revision INTEGER NOT NULL,
+                sql TEXT NOT NULL,
+                saved_at TEXT NOT NULL,
+                PRIMARY KEY (document_id, revision)
+            );",
+        )?;
+        Ok(())
+    }
+
+    fn purge_closed(&self) -> SqlResult<()> {
+        let cutoff =
+            (chrono::Utc::now() - chrono::Duration::days(CLOSED_RETENTION_DAYS)).to_rfc3339();
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "DELETE FROM query_document_revisions WHERE document_id IN
+             (SELECT id FROM query_documents WHERE closed_at < ?)",
+            params![cutoff],
+        )?;
+        db.execute(
+            "DELETE FROM query_documents WHERE closed_at < ?",
+            params![cutoff],
+        )?;
+        Ok(())
+    }
+
+    /// Stages an autosave of `document`, replacing any staged version. Returns the
+    /// generation to pass to `flush_staged` once `AUTOSAVE_DELAY` has passed.
+    pub fn stage(&self, document: QueryDocument) -> u64 {
+        let generation = {
+            let mut generation = self.generation.lock().unwrap();
+            *generation += 1;
+            *generation
+        };
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(document.id.clone(), (generation, document));
+        generation
+    }
+
+    /// Writes the staged document `id` if it wasn't edited again since `generation`.
+    pub fn flush_staged(&self, id: &str, generation: u64) -> SqlResult<()> {
+        let document = {
+            let mut pending = self.pending.lock().unwrap();
+            match pending.get(id) {
+                Some((staged, _)) if *staged == generation => pending.remove(id),
+                _ => None,
+            }
+        };
+        match document {
+            Some((_, document)) => self.save(&document),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes all staged documents now, e.g. before the app exits.
+    pub fn flush_all(&self) -> SqlResult<()> {
+        let documents: Vec<_> = self.pending.lock().unwrap().drain().collect();
+        for (_, (_, document)) in documents {
+            self.save(&document)?;
+        }
+        Ok(())
+    }
+
+    /// Saves a document, reopening it if it was closed, and records its SQL as a revision.
+    pub fn save(&self, document: &QueryDocument) -> SqlResult<()> {
+        // A direct save supersedes a staged autosave
+        self.pending.lock().unwrap().remove(&document.id);
+
+        let now = chrono::Utc::now();
+        let mut document = document.clone();
+        document.updated_at = now.to_rfc3339();
+        let value = serde_json::to_string(&document)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+        let mut db = self.db.lock().unwrap();
+        let transaction = db.transaction()?;
+        transaction.execute(
+            "INSERT INTO query_documents (id, document, position, updated_at, closed_at)
+             VALUES (?, ?, ?, ?, NULL)
+             ON CONFLICT(id) DO UPDATE SET document = excluded.document,
+                position = excluded.position, updated_at = excluded.updated_at,
+                closed_at = NULL",
+            params![document.id, value, document.position, document.updated_at],
+        )?;
+
+        let latest: Option<(i64, String, String)> = transaction
+            .query_row(
+                "SELECT revision, sql, saved_at FROM query_document_revisions
+                 WHERE document_id = ? ORDER BY revision DESC LIMIT 1",
+                params![document.id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let recent = |saved_at: &str| {
+            chrono::DateTime::parse_from_rfc3339(saved_at).is_ok_and(|saved| {
+                (now - saved.with_timezone(&chrono::Utc)).num_seconds() < REVISION_INTERVAL_SECS
+            })
+        };
+        match latest {
+            Some((_, sql, _)) if sql == document.sql => {}
+            Some((revision, _, saved_at)) if recent(&saved_at) => {
+                transaction.execute(
+                    "UPDATE query_document_revisions SET sql = ?
+                     WHERE document_id = ? AND revision = ?",
+                    params![document.sql, document.id, revision],
+                )?;
+            }
+            latest => {
+                let revision = latest.map_or(1, |(revision, _, _)| revision + 1);
+                transaction.execute(
+                    "INSERT INTO query_document_revisions (document_id, revision, sql, saved_at)
+                     VALUES (?, ?, ?, ?)",
+                    params![document.id, revision, document.sql, document.updated_at],
+                )?;
+                transaction.execute(
+                    "DELETE FROM query_document_revisions
+                     WHERE document_id = ? AND revision <= ?",
+                    params![document.id, revision - MAX_REVISIONS],
+                )?;
+            }
+        }
+        transaction.commit()
+    }
+
+    pub fn get(&self, id: &str) -> SqlResult<Option<QueryDocument>> {
+        let db = self.db.lock().unwrap();
+        let value: Option<String> = db
+            .query_row(
+                "SELECT document FROM query_documents WHERE id = ?",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value.and_then(|value| serde_json::from_str(&value).ok()))
+    }
+
+    /// Returns the open documents in tab order, skipping those that no longer deserialize.
+    pub fn get_open(&self) -> SqlResult<Vec<QueryDocument>> {
+        self.query_documents(
+            "SELECT document FROM query_documents WHERE closed_at IS NULL
+             ORDER BY position, updated_at",
+        )
+    }
+
+    /// Returns closed documents that can be reopened, most recently closed first.
+    pub fn get_closed(&self) -> SqlResult<Vec<QueryDocument>> {
+        self.query_documents(
+            "SELECT document FROM query_documents WHERE closed_at IS NOT NULL
+             ORDER BY closed_at DESC",
+        )
+    }
+
+    fn query_documents(&self, query: &str) -> SqlResult<Vec<QueryDocument>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(query)?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut documents = Vec::new();
+        for row in rows {
+            if let Ok(document) = serde_json::from_str(&row?) {
+                documents.push(document);
+            }
+        }
+        Ok(documents)
+    }
+
+    /// Closes a document's tab, keeping it for reopening. A staged autosave is written
+    /// first so the last edits aren't lost.
+    pub fn close(&self, id: &str) -> SqlResult<bool> {
+        let staged = self.pending.lock().unwrap().remove(id);
+        if let Some((_, document)) = staged {
+            self.save(&document)?;
+        }
+        let db = self.db.lock().unwrap();
+        let closed = db.execute(
+            "UPDATE query_documents SET closed_at = ? WHERE id = ? AND closed_at IS NULL",
+            params![chrono::Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(closed > 0)
+    }
+
+    /// Deletes a document and its history.
+    pub fn delete(&self, id: &str) -> SqlResult<bool> {
+        self.pending.lock().unwrap().remove(id);
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "DELETE FROM query_document_revisions WHERE document_id = ?",
+            params![id],
+        )?;
+        let deleted = db.execute("DELETE FROM query_documents WHERE id = ?", params![id])?;
+        Ok(deleted > 0)
+    }
+
+    /// Returns a document's revisions, newest first.
+    pub fn revisions(&self, id: &str) -> SqlResult<Vec<DocumentRevision>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT revision, sql, saved_at FROM query_document_revisions
+             WHERE document_id = ? ORDER BY revision DESC",
+        )?;
+        let rows = stmt.query_map(params![id], |row| {
+            Ok(DocumentRevision {
+                revision: row.get(0)?,
+                sql: row.get(1)?,
+                saved_at: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Puts the SQL of `revision` back into document `id`, recorded as a new revision.
+    /// Returns the updated document, or None if either doesn't exist.
+    pub fn restore_revision(&self, id: &str, revision: i64) -> SqlResult<Option<QueryDocument>> {
+        let sql: Option<String> = {
+            let db = self.db.lock().unwrap();
+            db.query_row(
+                "SELECT sql FROM query_document_revisions WHERE document_id = ? AND revision = ?",
+                params![id, revision],
+                |row| row.get(0),
+            )
+            .optional()?
+        };
+        let (Some(sql), Some(mut document)) = (sql, self.get(id)?) else {
+            return Ok(None);
+        };
+        document.sql = sql;
+        self.save(&document)?;
+        self.get(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_documents_and_revisions() {
+        let path = std::env::temp_dir().join(format!("bloatsql-test-{}.db", uuid::Uuid::new_v4()));
+        let store = QueryDocumentStore::new(path.clone()).unwrap();
+        let mut document = QueryDocument {
+            id: "tab-1".to_string(),
+            title: "Orders".to_string(),
+            sql: "SELECT 1".to_string(),
+            connection_id: None,
+            cursor: CursorPosition::default(),
+            position: 0,
+            updated_at: String::new(),
+        };
+
+        // Only the latest staged edit is written
+        let first = store.stage(document.clone());
+        document.sql = "SELECT 2".to_string();
+        let second = store.stage(document.clone());
+        store.flush_staged("tab-1", first).unwrap();
+        assert!(store.get_open().unwrap().is_empty());
+        store.flush_staged("tab-1", second).unwrap();
+
+        // Edits in quick succession update the same revision
+        document.sql = "SELECT 3".to_string();
+        store.save(&document).unwrap();
+        let revisions = store.revisions("tab-1").unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].sql, "SELECT 3");
+
+        assert!(store.close("tab-1").unwrap());
+        let reopened = QueryDocumentStore::new(path).unwrap();
+        assert!(reopened.get_open().unwrap().is_empty());
+        assert_eq!(reopened.get_closed().unwrap()[0].sql, "SELECT 3");
+        assert!(reopened.delete("tab-1").unwrap());
+        assert!(reopened.revisions("tab-1").unwrap().is_empty());
+    }
+}
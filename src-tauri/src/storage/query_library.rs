@@ -0,0 +1,215 @@
+use rusqlite::{params, Connection, Result as SqlResult};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Identifies a `.bsqlq` file.
+const LIBRARY_FILE_FORMAT: &str = "bloatsql-queries";
+
+/// Version of the `.bsqlq` file format.
+const LIBRARY_FILE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SavedQueryKind {
+    #[default]
+    Query,
+    /// SQL with `variables` to fill in before running.
+    Template,
+}
+
+/// A placeholder in a saved query, e.g. `{{customer_id}}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryVariable {
+    pub name: String,
+    #[serde(default)]
+    pub default_value: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// A saved query or template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub sql: String,
+    #[serde(default)]
+    pub kind: SavedQueryKind,
+    /// Database type the SQL is written for (`postgresql`, `mariadb`), if it is specific
+    /// to one.
+    #[serde(default)]
+    pub dialect: Option<String>,
+    #[serde(default)]
+    pub variables: Vec<QueryVariable>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Contents of a `.bsqlq` file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LibraryFile {
+    format: String,
+    version: u32,
+    /// Version of the app that wrote the file.
+    #[serde(default)]
+    app_version: String,
+    #[serde(default)]
+    exported_at: String,
+    queries: Vec<SavedQuery>,
+}
+
+/// Saved queries and templates, which can be shared as portable `.bsqlq` JSON files
+/// instead of sharing the app database.
+pub struct QueryLibrary {
+    db: Mutex<Connection>,
+}
+
+impl QueryLibrary {
+    pub fn new(db_path: PathBuf) -> SqlResult<Self> {
+        let db = Connection::open(&db_path)?;
+        // Shares the database file with `ConnectionsStore`
+        db.busy_timeout(Duration::from_secs(5))?;
+        let library = QueryLibrary { db: Mutex::new(db) };
+        library.init_tables()?;
+        Ok(library)
+    }
+
+    fn init_tables(&self) -> SqlResult<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS saved_queries (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                query TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Saves a query, replacing the one with the same id.
+    pub fn save(&self, query: &SavedQuery) -> SqlResult<()> {
+        let value = serde_json::to_string(query)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO saved_queries (id, name, query) VALUES (?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET name = excluded.name, query = excluded.query",
+            params![query.id, query.name, value],
+        )?;
+        Ok(())
+    }
+
+    /// Returns all saved queries by name, skipping those that no longer deserialize.
+    pub fn get_all(&self) -> SqlResult<Vec<SavedQuery>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare("SELECT query FROM saved_queries ORDER BY name, id")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut queries = Vec::new();
+        for row in rows {
+            if let Ok(query) = serde_json::from_str(&row?) {
+                queries.push(query);
+            }
+        }
+        Ok(queries)
+    }
+
+    pub fn delete(&self, id: &str) -> SqlResult<bool> {
+        let db = self.db.lock().unwrap();
+        let deleted = db.execute("DELETE FROM saved_queries WHERE id = ?", params![id])?;
+        Ok(deleted > 0)
+    }
+
+    /// Writes the queries with the given ids, or all of them, to a `.bsqlq` file. Returns
+    /// how many were written.
+    pub fn export(&self, path: &Path, ids: Option<&[String]>) -> io::Result<usize> {
+        let queries: Vec<SavedQuery> = self
+            .get_all()
+            .map_err(io::Error::other)?
+            .into_iter()
+            .filter(|query| ids.is_none_or(|ids| ids.contains(&query.id)))
+            .collect();
+        let file = LibraryFile {
+            format: LIBRARY_FILE_FORMAT.to_string(),
+            version: LIBRARY_FILE_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            queries,
+        };
+        let content = serde_json::to_string_pretty(&file).map_err(io::Error::other)?;
+        std::fs::write(path, content)?;
+        Ok(file.queries.len())
+    }
+
+    /// Saves the queries of a `.bsqlq` file, replacing local queries with the same id (a
+    /// newer copy of a shared query). Returns the imported queries.
+    pub fn import(&self, path: &Path) -> io::Result<Vec<SavedQuery>> {
+        let content = std::fs::read_to_string(path)?;
+        let file: LibraryFile = serde_json::from_str(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if file.format != LIBRARY_FILE_FORMAT {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a BloatSQL query library file",
+            ));
+        }
+        if file.version > LIBRARY_FILE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Query library file version {} is newer than supported ({})",
+                    file.version, LIBRARY_FILE_VERSION
+                ),
+            ));
+        }
+
+        for query in &file.queries {
+            self.save(query).map_err(io::Error::other)?;
+        }
+        Ok(file.queries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_export_and_import_round_trip() {
+        let temp = std::env::temp_dir();
+        let library =
+            QueryLibrary::new(temp.join(format!("bloatsql-test-{}.db", Uuid::new_v4()))).unwrap();
+        let template = SavedQuery {
+            id: "orders-by-customer".to_string(),
+            name: "Orders by customer".to_string(),
+            description: None,
+            sql: "SELECT * FROM orders WHERE customer_id = {{customer_id}}".to_string(),
+            kind: SavedQueryKind::Template,
+            dialect: Some("postgresql".to_string()),
+            variables: vec![QueryVariable {
+                name: "customer_id".to_string(),
+                default_value: Some("1".to_string()),
+                description: None,
+            }],
+            tags: vec!["sales".to_string()],
+        };
+        library.save(&template).unwrap();
+
+        let file = temp.join(format!("bloatsql-test-{}.bsqlq", Uuid::new_v4()));
+        assert_eq!(library.export(&file, None).unwrap(), 1);
+
+        let teammate =
+            QueryLibrary::new(temp.join(format!("bloatsql-test-{}.db", Uuid::new_v4()))).unwrap();
+        let imported = teammate.import(&file).unwrap();
+        assert_eq!(imported, teammate.get_all().unwrap());
+        assert_eq!(imported, [template]);
+        std::fs::remove_file(file).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+use rusqlite::{params, Connection, Result as SqlResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Snapshots of unsaved work (query tabs, staged grid edits) per window, so it survives a
+/// crash of the app or the webview.
+///
+/// Snapshots are removed when their window closes, which includes a normal exit. Snapshots
+/// of earlier runs are therefore only left behind by an unclean shutdown, and are handed out
+/// once by `recover`. Like `ExportProfileStore`, snapshots are stored as JSON.
+pub struct RecoveryStore {
+    db: Mutex<Connection>,
+    /// Identifies this run of the app, to tell its snapshots from those of earlier runs.
+    run_id: String,
+}
+
+impl RecoveryStore {
+    pub fn new(db_path: PathBuf) -> SqlResult<Self> {
+        let db = Connection::open(&db_path)?;
+        // Shares the database file with `ConnectionsStore`
+        db.busy_timeout(Duration::from_secs(5))?;
+        let store = RecoveryStore {
+            db: Mutex::new(db),
+            run_id: Uuid::new_v4().to_string(),
+        };
+        store.init_tables()?;
+        Ok(store)
+    }
+
+    fn init_tables(&self) -> SqlResult<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS recovery_snapshots (
+                run_id TEXT NOT NULL,
+                window_label TEXT NOT NULL,
+                snapshot TEXT NOT NULL,
+                PRIMARY KEY (run_id, window_label)
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Saves the snapshot of the window labelled `label`, replacing its previous one.
+    pub fn save<T: Serialize>(&self, label: &str, snapshot: &T) -> SqlResult<()> {
+        let value = serde_json::to_string(snapshot)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO recovery_snapshots (run_id, window_label, snapshot) VALUES (?, ?, ?)
+             ON CONFLICT(run_id, window_label) DO UPDATE SET snapshot = excluded.snapshot",
+            params![self.run_id, label, value],
+        )?;
+        Ok(())
+    }
+
+    /// Drops the snapshot of a window that closed normally.
+    pub fn remove(&self, label: &str) -> SqlResult<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "DELETE FROM recovery_snapshots WHERE run_id = ? AND window_label = ?",
+            params![self.run_id, label],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the work to recover in the window labelled `label`: snapshots left behind by
+    /// an unclean shutdown of an earlier run, which are removed so they are offered once,
+    /// and the window's own snapshot if its webview was reloaded without closing.
+    ///
+    /// Snapshots that no longer deserialize are skipped.
+    pub fn recover<T: DeserializeOwned>(&self, label: &str) -> SqlResult<Vec<T>> {
+        let mut db = self.db.lock().unwrap();
+        let transaction = db.transaction()?;
+        let values = {
+            let mut stmt = transaction.prepare(
+                "SELECT snapshot FROM recovery_snapshots
+                 WHERE run_id != ?1 OR window_label = ?2
+                 ORDER BY rowid",
+            )?;
+            let rows =
+                stmt.query_map(params![self.run_id, label], |row| row.get::<_, String>(0))?;
+            rows.collect::<SqlResult<Vec<_>>>()?
+        };
+        transaction.execute(
+            "DELETE FROM recovery_snapshots WHERE run_id != ?",
+            params![self.run_id],
+        )?;
+        transaction.commit()?;
+
+        Ok(values
+            .iter()
+            .filter_map(|value| serde_json::from_str(value).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshots_left_by_earlier_runs_are_recovered_once() {
+        let path = std::env::temp_dir().join(format!("bloatsql-test-{}.db", Uuid::new_v4()));
+
+        let crashed = RecoveryStore::new(path.clone()).unwrap();
+        crashed.save("main", &"unsaved query").unwrap();
+        crashed.save("window-1", &"closed").unwrap();
+        crashed.remove("window-1").unwrap();
+
+        let store = RecoveryStore::new(path).unwrap();
+        assert_eq!(store.recover::<String>("main").unwrap(), ["unsaved query"]);
+        assert!(store.recover::<String>("main").unwrap().is_empty());
+
+        // A reloaded webview gets its window's snapshot back
+        store.save("main", &"edited").unwrap();
+        assert_eq!(store.recover::<String>("main").unwrap(), ["edited"]);
+        assert!(store.recover::<String>("window-2").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,112 @@
+use rusqlite::{params, Connection, Result as SqlResult};
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Kinds of schema metadata kept in the cache.
+pub mod cache_kinds {
+    pub const TABLES: &str = "tables";
+    pub const COLUMNS: &str = "columns";
+    pub const RELATIONSHIPS: &str = "relationships";
+}
+
+/// A cached value and how old it is.
+#[derive(Debug, Clone)]
+pub struct CachedMetadata<T> {
+    pub value: T,
+    pub age: Duration,
+}
+
+/// Persistent cache of schema metadata (tables, columns, relationships), keyed by
+/// stored connection id, database, kind and object name.
+///
+/// Values are stored as JSON so the cache doesn't need to know the metadata types.
+pub struct SchemaCache {
+    db: Mutex<Connection>,
+}
+
+impl SchemaCache {
+    pub fn new(db_path: PathBuf) -> SqlResult<Self> {
+        let db = Connection::open(&db_path)?;
+        db.busy_timeout(Duration::from_secs(5))?;
+        let cache = SchemaCache { db: Mutex::new(db) };
+        cache.init_tables()?;
+        Ok(cache)
+    }
+
+    fn init_tables(&self) -> SqlResult<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS schema_cache (
+                connection_id TEXT NOT NULL,
+                database TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                object_name TEXT NOT NULL DEFAULT '',
+                payload TEXT NOT NULL,
+                fetched_at INTEGER NOT NULL,
+                PRIMARY KEY (connection_id, database, kind, object_name)
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the cached value, or `None` if missing or no longer deserializable.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        connection_id: &str,
+        database: &str,
+        kind: &str,
+        object_name: &str,
+    ) -> SqlResult<Option<CachedMetadata<T>>> {
+        let db = self.db.lock().unwrap();
+        let result = db.query_row(
+            "SELECT payload, strftime('%s', 'now') - fetched_at FROM schema_cache
+             WHERE connection_id = ? AND database = ? AND kind = ? AND object_name = ?",
+            params![connection_id, database, kind, object_name],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+        );
+
+        match result {
+            Ok((payload, age_secs)) => {
+                Ok(serde_json::from_str(&payload)
+                    .ok()
+                    .map(|value| CachedMetadata {
+                        value,
+                        age: Duration::from_secs(age_secs.max(0) as u64),
+                    }))
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn put<T: Serialize>(
+        &self,
+        connection_id: &str,
+        database: &str,
+        kind: &str,
+        object_name: &str,
+        value: &T,
+    ) -> SqlResult<()> {
+        let payload = serde_json::to_string(value)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT OR REPLACE INTO schema_cache (connection_id, database, kind, object_name, payload, fetched_at)
+             VALUES (?, ?, ?, ?, ?, strftime('%s', 'now'))",
+            params![connection_id, database, kind, object_name, payload],
+        )?;
+        Ok(())
+    }
+
+    /// Drops cached metadata for a connection, optionally limited to one database.
+    pub fn invalidate(&self, connection_id: &str, database: Option<&str>) -> SqlResult<usize> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "DELETE FROM schema_cache WHERE connection_id = ?1 AND (?2 IS NULL OR database = ?2)",
+            params![connection_id, database],
+        )
+    }
+}
@@ -0,0 +1,156 @@
+//! Pluggable storage for saved passwords.
+//!
+//! By default `ConnectionsStore` keeps each password in the OS credential store (the Keychain
+//! on macOS, the Credential Manager on Windows, the Secret Service on Linux), leaving only a
+//! marker in `connections.db`. Where no credential store is available, passwords are
+//! encrypted into the database with the key from `key_store` instead, as older versions did.
+
+use super::key_store::KEYRING_SERVICE;
+use std::fmt;
+use tracing::{debug, warn};
+
+/// Stored in place of a secret that lives in the `SecretBackend`.
+pub const IN_SECRET_BACKEND: &str = "@secret-backend";
+
+/// Returned when a secret the database says is in the `SecretBackend` can't be read from it.
+#[derive(Debug)]
+pub struct SecretUnavailable {
+    pub account: String,
+    /// `None` when the backend has no such secret.
+    pub reason: Option<String>,
+}
+
+impl fmt::Display for SecretUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            Some(reason) => write!(
+                f,
+                "Failed to read {} from the secret backend: {}",
+                self.account, reason
+            ),
+            None => write!(f, "{} is missing from the secret backend", self.account),
+        }
+    }
+}
+
+impl std::error::Error for SecretUnavailable {}
+
+/// Somewhere outside the database to keep secrets, looked up by account name.
+pub trait SecretBackend: Send + Sync {
+    /// Stores `secret` under `account`, replacing any earlier one.
+    fn set(&self, account: &str, secret: &str) -> Result<(), String>;
+
+    /// Returns the secret stored under `account`, if there is one.
+    fn get(&self, account: &str) -> Result<Option<String>, String>;
+
+    /// Removes the secret stored under `account`; removing a missing one is not an error.
+    fn delete(&self, account: &str) -> Result<(), String>;
+}
+
+/// Secrets in the OS credential store, one entry per account.
+pub struct OsKeyring {
+    /// Prefixed to account names so separate data directories keep separate entries.
+    scope: String,
+    /// Scope of entries saved by versions that scoped them by the database path, which
+    /// changes when the data directory moves. They move to `scope` when read.
+    legacy_scope: String,
+}
+
+impl OsKeyring {
+    /// Opens the credential store for the data directory identified by `scope` (its install
+    /// id), or returns `None` when the platform has no usable credential store.
+    pub fn open(scope: &str, legacy_scope: &str) -> Option<Self> {
+        let keyring = OsKeyring {
+            scope: scope.to_string(),
+            legacy_scope: legacy_scope.to_string(),
+        };
+        // A lookup of an entry that doesn't exist shows whether the store answers at all
+        match keyring.get("probe") {
+            Ok(_) => Some(keyring),
+            Err(e) => {
+                warn!("OS credential store unavailable for passwords: {}", e);
+                None
+            }
+        }
+    }
+
+    fn entry(&self, account: &str) -> Result<keyring::Entry, String> {
+        Self::scoped_entry(&self.scope, account)
+    }
+
+    fn scoped_entry(scope: &str, account: &str) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(KEYRING_SERVICE, &format!("{}#{}", scope, account))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Reads a secret saved under the legacy scope, moving it to `scope`. The legacy entry is
+    /// only removed once the secret reads back from its new one.
+    fn take_legacy(&self, account: &str) -> Result<Option<String>, String> {
+        let legacy = Self::scoped_entry(&self.legacy_scope, account)?;
+        let secret = match legacy.get_password() {
+            Ok(secret) => secret,
+            Err(keyring::Error::NoEntry) => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        };
+        let entry = self.entry(account)?;
+        let moved = entry
+            .set_password(&secret)
+            .and_then(|()| entry.get_password())
+            .map(|stored| stored == secret);
+        match moved {
+            Ok(true) => match legacy.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => {
+                    debug!("Moved {} to the install scope", account)
+                }
+                Err(e) => warn!("Failed to remove the old entry of {}: {}", account, e),
+            },
+            Ok(false) => warn!(
+                "{} doesn't read back from its new entry, keeping the old one",
+                account
+            ),
+            Err(e) => warn!("Failed to move {} to the install scope: {}", account, e),
+        }
+        Ok(Some(secret))
+    }
+}
+
+impl SecretBackend for OsKeyring {
+    fn set(&self, account: &str, secret: &str) -> Result<(), String> {
+        self.entry(account)?
+            .set_password(secret)
+            .map_err(|e| e.to_string())
+    }
+
+    fn get(&self, account: &str) -> Result<Option<String>, String> {
+        match self.entry(account)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => self.take_legacy(account),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn delete(&self, account: &str) -> Result<(), String> {
+        for scope in [&self.scope, &self.legacy_scope] {
+            match Self::scoped_entry(scope, account)?.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => {}
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Account name of a connection's database password.
+pub fn password_account(connection_id: &str) -> String {
+    format!("{}/password", connection_id)
+}
+
+/// Account name of a connection's SSH password.
+pub fn ssh_password_account(connection_id: &str) -> String {
+    format!("{}/ssh-password", connection_id)
+}
+
+/// Account name of the passphrase of a connection's SSH private key.
+pub fn ssh_key_passphrase_account(connection_id: &str) -> String {
+    format!("{}/ssh-key-passphrase", connection_id)
+}
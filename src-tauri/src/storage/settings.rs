@@ -0,0 +1,79 @@
+use rusqlite::{params, Connection, Result as SqlResult};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// App-wide settings.
+///
+/// Stored as a single JSON document so new settings don't require schema migrations.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// File that non-secret connection definitions are mirrored to; `None` disables syncing.
+    pub connection_sync_path: Option<String>,
+    /// Memory in MiB that stored query results may use before spilling to disk;
+    /// `None` uses the default.
+    pub result_memory_budget_mb: Option<u64>,
+    /// Leaves data values (edited cells, key values) out of logs.
+    pub hide_data_in_logs: bool,
+    /// Log level ("off", "error", "warn", "info", "debug" or "trace"); `None` uses the
+    /// default for the build.
+    pub log_level: Option<String>,
+    /// Queries running at least this many milliseconds are recorded in the `SlowQueryLog`;
+    /// `None` disables recording.
+    pub slow_query_threshold_ms: Option<u64>,
+    /// Seconds a query, export or import must run to notify when it finishes in the
+    /// background; `None` uses `notify::DEFAULT_THRESHOLD_SECS` and 0 disables notifications.
+    pub notification_threshold_secs: Option<u64>,
+}
+
+/// Persists `AppSettings` in SQLite.
+pub struct SettingsStore {
+    db: Mutex<Connection>,
+}
+
+impl SettingsStore {
+    pub fn new(db_path: PathBuf) -> SqlResult<Self> {
+        let db = Connection::open(&db_path)?;
+        db.busy_timeout(Duration::from_secs(5))?;
+        let store = SettingsStore { db: Mutex::new(db) };
+        store.init_tables()?;
+        Ok(store)
+    }
+
+    fn init_tables(&self) -> SqlResult<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS settings (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the saved settings, or defaults if none were saved.
+    pub fn get(&self) -> SqlResult<AppSettings> {
+        let db = self.db.lock().unwrap();
+        match db.query_row("SELECT value FROM settings WHERE id = 1", [], |row| {
+            row.get::<_, String>(0)
+        }) {
+            Ok(value) => Ok(serde_json::from_str(&value).unwrap_or_default()),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(AppSettings::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, settings: &AppSettings) -> SqlResult<()> {
+        let value = serde_json::to_string(settings)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT OR REPLACE INTO settings (id, value) VALUES (1, ?)",
+            params![value],
+        )?;
+        Ok(())
+    }
+}
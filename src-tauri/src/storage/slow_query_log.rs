@@ -0,0 +1,136 @@
+use rusqlite::{params, Connection, Result as SqlResult};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Number of slow queries kept; older entries are removed as new ones are recorded.
+const MAX_ENTRIES: i64 = 1_000;
+
+/// A query that took longer than the slow query threshold.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowQuery {
+    /// Assigned by the store; ignored when recording.
+    #[serde(default)]
+    pub id: i64,
+    /// UTC timestamp (`YYYY-MM-DD HH:MM:SS`); assigned by the store when recording.
+    #[serde(default)]
+    pub executed_at: String,
+    pub connection_id: Option<String>,
+    pub connection_name: String,
+    pub database: String,
+    pub statement: String,
+    /// Execution time in milliseconds.
+    pub duration_ms: u64,
+    /// Output of EXPLAIN for the statement, if it could be explained.
+    pub plan: Option<String>,
+}
+
+/// Local log of slow queries, stored in SQLite so they can be reviewed without access to
+/// the server's slow query log.
+pub struct SlowQueryLog {
+    db: Mutex<Connection>,
+    /// Threshold in milliseconds; 0 disables recording.
+    threshold_ms: AtomicU64,
+}
+
+impl SlowQueryLog {
+    pub fn new(db_path: PathBuf) -> SqlResult<Self> {
+        let db = Connection::open(&db_path)?;
+        // Shares the database file with `ConnectionsStore`
+        db.busy_timeout(Duration::from_secs(5))?;
+        let log = SlowQueryLog {
+            db: Mutex::new(db),
+            threshold_ms: AtomicU64::new(0),
+        };
+        log.init_tables()?;
+        Ok(log)
+    }
+
+    fn init_tables(&self) -> SqlResult<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "CREATE TABLE IF NOT EXISTS slow_queries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                executed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                connection_id TEXT,
+                connection_name TEXT NOT NULL,
+                database TEXT NOT NULL,
+                statement TEXT NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                plan TEXT
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Sets the duration from which queries are recorded; `None` disables recording.
+    pub fn set_threshold(&self, threshold_ms: Option<u64>) {
+        self.threshold_ms
+            .store(threshold_ms.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Returns true if a query that ran for `duration_ms` should be recorded.
+    pub fn is_slow(&self, duration_ms: u128) -> bool {
+        let threshold = self.threshold_ms.load(Ordering::Relaxed);
+        threshold > 0 && duration_ms >= u128::from(threshold)
+    }
+
+    /// Appends an entry, dropping the oldest beyond `MAX_ENTRIES`. `id` and `executed_at`
+    /// are assigned by the store.
+    pub fn record(&self, entry: &SlowQuery) -> SqlResult<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO slow_queries (connection_id, connection_name, database, statement, duration_ms, plan)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                &entry.connection_id,
+                &entry.connection_name,
+                &entry.database,
+                &entry.statement,
+                entry.duration_ms as i64,
+                &entry.plan
+            ],
+        )?;
+        db.execute(
+            "DELETE FROM slow_queries WHERE id <= (SELECT MAX(id) FROM slow_queries) - ?",
+            params![MAX_ENTRIES],
+        )?;
+        Ok(())
+    }
+
+    /// Returns recorded queries, newest first, optionally for one connection only.
+    pub fn get_entries(
+        &self,
+        connection_id: Option<&str>,
+        limit: Option<u32>,
+    ) -> SqlResult<Vec<SlowQuery>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT id, executed_at, connection_id, connection_name, database, statement,
+                    duration_ms, plan
+             FROM slow_queries
+             WHERE ?1 IS NULL OR connection_id = ?1
+             ORDER BY id DESC
+             LIMIT ?2",
+        )?;
+
+        let limit = limit.map(i64::from).unwrap_or(-1);
+        let entries = stmt.query_map(params![connection_id, limit], |row| {
+            Ok(SlowQuery {
+                id: row.get(0)?,
+                executed_at: row.get(1)?,
+                connection_id: row.get(2)?,
+                connection_name: row.get(3)?,
+                database: row.get(4)?,
+                statement: row.get(5)?,
+                duration_ms: row.get::<_, i64>(6)? as u64,
+                plan: row.get(7)?,
+            })
+        })?;
+
+        entries.collect()
+    }
+}